@@ -35,7 +35,11 @@ fn default_handler(
 
 enum ObjectType<'a> {
     Collection(&'a str),
-    Item { col: &'a str, item: &'a str },
+    Item {
+        col: &'a str,
+        item: &'a str,
+    },
+    #[allow(dead_code)]
     Session(&'a str),
 }
 
@@ -0,0 +1,77 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rustbus::wire::errors::MarshalError;
+use rustbus::wire::marshal::traits::SignatureBuffer;
+use rustbus::wire::marshal::MarshalContext;
+use rustbus::wire::unmarshal::{UnmarshalContext, UnmarshalResult};
+use rustbus::{ByteOrder, Marshal, Signature, Unmarshal};
+
+/// A byte that does not opt into the slice fast path, so arrays of it are handled element by element
+#[derive(Clone, Copy)]
+struct SlowByte(u8);
+
+impl Signature for SlowByte {
+    fn signature() -> rustbus::signature::Type {
+        u8::signature()
+    }
+    fn alignment() -> usize {
+        u8::alignment()
+    }
+    fn sig_str(s_buf: &mut SignatureBuffer) {
+        u8::sig_str(s_buf)
+    }
+    fn has_sig(sig: &str) -> bool {
+        u8::has_sig(sig)
+    }
+}
+impl Marshal for SlowByte {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+        self.0.marshal(ctx)
+    }
+}
+impl<'buf, 'fds> Unmarshal<'buf, 'fds> for SlowByte {
+    fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> UnmarshalResult<Self> {
+        u8::unmarshal(ctx).map(|(bytes, b)| (bytes, SlowByte(b)))
+    }
+}
+
+fn marshal_to_vec<T: Marshal + ?Sized>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut fds = Vec::new();
+    let mut ctx = MarshalContext {
+        buf: &mut buf,
+        fds: &mut fds,
+        byteorder: ByteOrder::LittleEndian,
+    };
+    value.marshal(&mut ctx).unwrap();
+    buf
+}
+
+fn unmarshal_from<'a, T: Unmarshal<'a, 'a>>(buf: &'a [u8]) -> T {
+    let mut ctx = UnmarshalContext::new(&[], buf, ByteOrder::LittleEndian, 0);
+    T::unmarshal(&mut ctx).unwrap().1
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    // 4MiB of bytes
+    let bytes: Vec<u8> = (0..4 * 1024 * 1024).map(|i| i as u8).collect();
+    let slow_bytes: Vec<SlowByte> = bytes.iter().copied().map(SlowByte).collect();
+
+    let marshalled = marshal_to_vec(&bytes);
+    assert_eq!(marshalled, marshal_to_vec(&slow_bytes));
+
+    c.bench_function("marshal_byte_array", |b| {
+        b.iter(|| marshal_to_vec(black_box(&bytes)))
+    });
+    c.bench_function("marshal_byte_array_per_element", |b| {
+        b.iter(|| marshal_to_vec(black_box(&slow_bytes)))
+    });
+    c.bench_function("unmarshal_byte_array", |b| {
+        b.iter(|| unmarshal_from::<Vec<u8>>(black_box(&marshalled)))
+    });
+    c.bench_function("unmarshal_byte_array_per_element", |b| {
+        b.iter(|| unmarshal_from::<Vec<SlowByte>>(black_box(&marshalled)))
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,88 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rustbus::wire::marshal::MarshalContext;
+use rustbus::{ByteOrder, Marshal};
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts the allocations (including reallocations) so the effect of reserving is visible
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn marshal_without_reserve(payload: &[Vec<String>]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut fds = Vec::new();
+    let mut ctx = MarshalContext {
+        buf: &mut buf,
+        fds: &mut fds,
+        byteorder: ByteOrder::LittleEndian,
+    };
+    for p in payload {
+        p.marshal(&mut ctx).unwrap();
+    }
+    buf
+}
+
+fn marshal_with_reserve(payload: &[Vec<String>]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    let mut fds = Vec::new();
+    let mut ctx = MarshalContext {
+        buf: &mut buf,
+        fds: &mut fds,
+        byteorder: ByteOrder::LittleEndian,
+    };
+    ctx.reserve(payload.iter().map(Marshal::estimate_size).sum());
+    for p in payload {
+        p.marshal(&mut ctx).unwrap();
+    }
+    buf
+}
+
+fn count_allocations<F: FnOnce() -> Vec<u8>>(f: F) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let buf = f();
+    let after = ALLOCATIONS.load(Ordering::Relaxed);
+    drop(buf);
+    after - before
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    // roughly 4MiB of strings split into a few arrays
+    let payload: Vec<Vec<String>> = (0..16)
+        .map(|i| (0..8 * 1024).map(|j| format!("{:024}", i * j)).collect())
+        .collect();
+
+    println!(
+        "allocations without reserve: {}, with reserve: {}",
+        count_allocations(|| marshal_without_reserve(&payload)),
+        count_allocations(|| marshal_with_reserve(&payload)),
+    );
+
+    c.bench_function("marshal_without_reserve", |b| {
+        b.iter(|| marshal_without_reserve(black_box(&payload)))
+    });
+    c.bench_function("marshal_with_reserve", |b| {
+        b.iter(|| marshal_with_reserve(black_box(&payload)))
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
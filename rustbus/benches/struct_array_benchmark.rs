@@ -0,0 +1,35 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rustbus::wire::marshal::MarshalContext;
+use rustbus::{ByteOrder, Marshal};
+
+fn marshal_into<T: Marshal + ?Sized>(value: &T, byteorder: ByteOrder, buf: &mut Vec<u8>) {
+    buf.clear();
+    let mut fds = Vec::new();
+    let mut ctx = MarshalContext {
+        buf,
+        fds: &mut fds,
+        byteorder,
+    };
+    value.marshal(&mut ctx).unwrap();
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let pairs: Vec<(u32, u64)> = (0..64 * 1024).map(|i| (i, u64::from(i) << 32)).collect();
+    let mixed: Vec<(u8, u16, u32, bool, f64)> = (0..64 * 1024)
+        .map(|i| (i as u8, i as u16, i, i % 2 == 0, f64::from(i)))
+        .collect();
+
+    let mut buf = Vec::new();
+    c.bench_function("marshal_u32_u64_struct_array", |b| {
+        b.iter(|| marshal_into(black_box(&pairs), ByteOrder::LittleEndian, &mut buf))
+    });
+    c.bench_function("marshal_u32_u64_struct_array_big_endian", |b| {
+        b.iter(|| marshal_into(black_box(&pairs), ByteOrder::BigEndian, &mut buf))
+    });
+    c.bench_function("marshal_mixed_struct_array", |b| {
+        b.iter(|| marshal_into(black_box(&mixed), ByteOrder::LittleEndian, &mut buf))
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -46,12 +46,16 @@ fn main() -> Result<(), rustbus::connection::Error> {
     println!("\n");
     println!("\n");
 
-    if let Ok(ret) = msg.body.parser().get::<u32>() {
+    if let Ok(ret) = msg
+        .body
+        .parser()
+        .get::<standard_messages::RequestNameReply>()
+    {
         match ret {
-            standard_messages::DBUS_REQUEST_NAME_REPLY_PRIMARY_OWNER => {
+            standard_messages::RequestNameReply::PrimaryOwner => {
                 println!("Got name");
             }
-            _ => panic!("Got other return: {}", ret),
+            _ => panic!("Got other return: {:?}", ret),
         }
     } else {
         panic!("Wrong args: {:?}", msg.get_sig());
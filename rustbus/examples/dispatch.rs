@@ -72,7 +72,7 @@ fn main() {
 
     if std::env::args().any(|arg| "server".eq(&arg)) {
         con.send
-            .send_message(&mut rustbus::standard_messages::request_name(
+            .send_message(&rustbus::standard_messages::request_name(
                 "killing.spark.io",
                 rustbus::standard_messages::DBUS_NAME_FLAG_REPLACE_EXISTING,
             ))
@@ -102,49 +102,29 @@ fn main() {
         println!("Sending stuff!");
 
         // default handler
-        let mut msg1 = rustbus::message_builder::MessageBuilder::new()
+        let msg1 = rustbus::message_builder::MessageBuilder::new()
             .call("ABCD")
             .at("killing.spark.io")
             .on("/ABCD")
             .build();
-        con.send
-            .send_message(&mut msg1)
-            .unwrap()
-            .write_all()
-            .unwrap();
+        con.send.send_message(&msg1).unwrap().write_all().unwrap();
 
         // pick up the name
-        let mut msg2 = rustbus::message_builder::MessageBuilder::new()
+        let msg2 = rustbus::message_builder::MessageBuilder::new()
             .call("ABCD")
             .at("killing.spark.io")
             .on("/A/B/moritz")
             .build();
-        con.send
-            .send_message(&mut msg2)
-            .unwrap()
-            .write_all()
-            .unwrap();
+        con.send.send_message(&msg2).unwrap().write_all().unwrap();
 
         // call new handler for that name
-        let mut msg3 = rustbus::message_builder::MessageBuilder::new()
+        let msg3 = rustbus::message_builder::MessageBuilder::new()
             .call("ABCD")
             .at("killing.spark.io")
             .on("/moritz")
             .build();
-        con.send
-            .send_message(&mut msg3)
-            .unwrap()
-            .write_all()
-            .unwrap();
-        con.send
-            .send_message(&mut msg3)
-            .unwrap()
-            .write_all()
-            .unwrap();
-        con.send
-            .send_message(&mut msg3)
-            .unwrap()
-            .write_all()
-            .unwrap();
+        con.send.send_message(&msg3).unwrap().write_all().unwrap();
+        con.send.send_message(&msg3).unwrap().write_all().unwrap();
+        con.send.send_message(&msg3).unwrap().write_all().unwrap();
     }
 }
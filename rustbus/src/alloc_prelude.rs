@@ -0,0 +1,9 @@
+//! The items of the std prelude that live in `alloc`
+//!
+//! Without the `std` feature they are not in scope by default, so the marshalling core imports them from here.
+
+pub(crate) use alloc::borrow::ToOwned;
+pub(crate) use alloc::boxed::Box;
+pub(crate) use alloc::string::{String, ToString};
+pub(crate) use alloc::vec;
+pub(crate) use alloc::vec::Vec;
@@ -1,10 +1,53 @@
 //! Deals with authentication to the other side. You probably do not need this.
 
-use nix::unistd::getuid;
-use std::io::{Read, Write};
-use std::os::unix::net::UnixStream;
+use nix::unistd::{getuid, User};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
 
-fn write_message(msg: &str, stream: &mut UnixStream) -> std::io::Result<()> {
+mod sha1;
+
+/// The SASL mechanisms that can be used to authenticate to the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMechanism {
+    /// Authenticate with the uid of this process. The server checks it against the credentials of the socket.
+    External,
+    /// Do not authenticate at all. Most buses do not allow this.
+    Anonymous,
+    /// Prove access to a secret cookie in `~/.dbus-keyrings`
+    DBusCookieSha1,
+}
+
+impl AuthMechanism {
+    /// The name of the mechanism as it is used in the protocol
+    pub fn name(self) -> &'static str {
+        match self {
+            AuthMechanism::External => "EXTERNAL",
+            AuthMechanism::Anonymous => "ANONYMOUS",
+            AuthMechanism::DBusCookieSha1 => "DBUS_COOKIE_SHA1",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("An io error occured: {0}")]
+    IoError(#[from] io::Error),
+    #[error("All mechanisms were rejected, the server offered: {}", .offered.join(" "))]
+    Rejected { offered: Vec<String> },
+    #[error("Unexpected response from the server: {0}")]
+    UnexpectedResponse(String),
+    #[error("Malformed DBUS_COOKIE_SHA1 challenge from the server: {0}")]
+    MalformedChallenge(String),
+    #[error("Could not determine the home directory to find the dbus keyrings")]
+    NoHomeDir,
+    #[error("Could not read the cookie file {path}: {source}")]
+    CookieFile { path: PathBuf, source: io::Error },
+    #[error("The cookie {id} was not found in {path}")]
+    CookieNotFound { path: PathBuf, id: String },
+}
+
+fn write_message<S: Write>(msg: &str, stream: &mut S) -> std::io::Result<()> {
     let mut buf = Vec::new();
     buf.extend(msg.bytes());
     buf.push(b'\r');
@@ -31,15 +74,100 @@ fn find_line_ending(buf: &[u8]) -> Option<usize> {
     None
 }
 
-fn read_message(stream: &mut UnixStream, buf: &mut Vec<u8>) -> std::io::Result<String> {
+fn read_message<S: Read>(stream: &mut S, buf: &mut Vec<u8>) -> std::io::Result<String> {
     let mut tmpbuf = [0u8; 512];
     while !has_line_ending(buf) {
         let bytes = stream.read(&mut tmpbuf[..])?;
+        if bytes == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
         buf.extend_from_slice(&tmpbuf[..bytes])
     }
     let idx = find_line_ending(buf).unwrap();
     let line = buf.drain(0..idx).collect::<Vec<_>>();
-    Ok(String::from_utf8(line).unwrap())
+    // remove the \r\n too
+    buf.drain(0..2);
+    String::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(hex.get(idx..idx + 2)?, 16).ok())
+        .collect()
+}
+
+fn get_username() -> String {
+    match User::from_uid(getuid()) {
+        Ok(Some(user)) => user.name,
+        _ => getuid().to_string(),
+    }
+}
+
+/// The keyrings live in `~/.dbus-keyrings`
+fn keyring_dir() -> Result<PathBuf, AuthError> {
+    let home = match std::env::var_os("HOME") {
+        Some(home) => PathBuf::from(home),
+        None => match User::from_uid(getuid()) {
+            Ok(Some(user)) => user.dir,
+            _ => return Err(AuthError::NoHomeDir),
+        },
+    };
+    Ok(home.join(".dbus-keyrings"))
+}
+
+fn random_challenge() -> io::Result<String> {
+    let mut bytes = [0u8; 16];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(hex_encode(&bytes))
+}
+
+/// Compute the response to the DBUS_COOKIE_SHA1 challenge `server_data` (the hex decoded payload of the servers DATA line).
+/// Returns the payload of the DATA line that has to be sent back, before hex encoding.
+fn cookie_sha1_response(
+    server_data: &str,
+    keyring_dir: &Path,
+    client_challenge: &str,
+) -> Result<String, AuthError> {
+    let malformed = || AuthError::MalformedChallenge(server_data.to_owned());
+    let mut parts = server_data.split(' ');
+    let (context, id, server_challenge) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(context), Some(id), Some(challenge)) if parts.next().is_none() => {
+            (context, id, challenge)
+        }
+        _ => return Err(malformed()),
+    };
+    // the context is used as a file name, make sure it stays in the keyring dir
+    if context.is_empty() || context.starts_with('.') || context.contains(['/', '\\']) {
+        return Err(malformed());
+    }
+
+    let path = keyring_dir.join(context);
+    let keyring = std::fs::read_to_string(&path).map_err(|source| AuthError::CookieFile {
+        path: path.clone(),
+        source,
+    })?;
+    // each line contains: <id> <creation time> <cookie>
+    let cookie = keyring
+        .lines()
+        .map(|line| line.split(' ').collect::<Vec<_>>())
+        .find(|fields| fields.len() == 3 && fields[0] == id)
+        .map(|fields| fields[2])
+        .ok_or_else(|| AuthError::CookieNotFound {
+            path: path.clone(),
+            id: id.to_owned(),
+        })?;
+
+    let digest =
+        sha1::sha1(format!("{}:{}:{}", server_challenge, client_challenge, cookie).as_bytes());
+    Ok(format!("{} {}", client_challenge, hex_encode(&digest)))
 }
 
 fn get_uid_as_hex() -> String {
@@ -78,21 +206,89 @@ pub enum AuthResult {
     Rejected,
 }
 
-pub fn do_auth(stream: &mut UnixStream) -> std::io::Result<AuthResult> {
+pub fn do_auth<S: Read + Write>(stream: &mut S) -> std::io::Result<AuthResult> {
+    match do_auth_with_mechanisms(stream, &[AuthMechanism::External]) {
+        Ok(_) => Ok(AuthResult::Ok),
+        Err(AuthError::IoError(e)) => Err(e),
+        Err(_) => Ok(AuthResult::Rejected),
+    }
+}
+
+/// Try the mechanisms in order until the server accepts one. Returns the mechanism that was accepted.
+///
+/// When the server rejects a mechanism it tells which mechanisms it supports. Mechanisms further down the
+/// list that the server did not offer are skipped. If no mechanism was accepted `AuthError::Rejected` contains
+/// the mechanisms the server offered.
+///
+/// Problems with the cookie for DBUS_COOKIE_SHA1 (missing keyring, unknown cookie id) are returned right away instead of
+/// trying the next mechanism, so they do not get lost in a generic rejection.
+pub fn do_auth_with_mechanisms<S: Read + Write>(
+    stream: &mut S,
+    mechanisms: &[AuthMechanism],
+) -> Result<AuthMechanism, AuthError> {
     // send a null byte as the first thing
     stream.write_all(&[0])?;
-    write_message(&format!("AUTH EXTERNAL {}", get_uid_as_hex()), stream)?;
 
     let mut read_buf = Vec::new();
-    let msg = read_message(stream, &mut read_buf)?;
-    if msg.starts_with("OK") {
-        Ok(AuthResult::Ok)
-    } else {
-        Ok(AuthResult::Rejected)
+    let mut offered: Option<Vec<String>> = None;
+    for &mechanism in mechanisms {
+        if let Some(offered) = &offered {
+            if !offered.iter().any(|name| name == mechanism.name()) {
+                continue;
+            }
+        }
+        let initial_response = match mechanism {
+            AuthMechanism::External => get_uid_as_hex(),
+            AuthMechanism::Anonymous => hex_encode(b"rustbus"),
+            AuthMechanism::DBusCookieSha1 => hex_encode(get_username().as_bytes()),
+        };
+        write_message(
+            &format!("AUTH {} {}", mechanism.name(), initial_response),
+            stream,
+        )?;
+
+        let mut msg = read_message(stream, &mut read_buf)?;
+        if mechanism == AuthMechanism::DBusCookieSha1 {
+            if let Some(challenge) = msg.strip_prefix("DATA ") {
+                let challenge = hex_decode(challenge)
+                    .and_then(|data| String::from_utf8(data).ok())
+                    .ok_or_else(|| AuthError::MalformedChallenge(challenge.to_owned()))?;
+                let response =
+                    cookie_sha1_response(&challenge, &keyring_dir()?, &random_challenge()?)?;
+                write_message(&format!("DATA {}", hex_encode(response.as_bytes())), stream)?;
+                msg = read_message(stream, &mut read_buf)?;
+            }
+        }
+        if msg.starts_with("ERROR") || msg.starts_with("DATA") {
+            // the server did not understand us, give up on this mechanism
+            write_message("CANCEL", stream)?;
+            msg = read_message(stream, &mut read_buf)?;
+        }
+        if msg.starts_with("OK") {
+            return Ok(mechanism);
+        } else if let Some(mechs) = msg.strip_prefix("REJECTED") {
+            offered = Some(mechs.split_whitespace().map(str::to_owned).collect());
+        } else {
+            return Err(AuthError::UnexpectedResponse(msg));
+        }
     }
+
+    let offered = match offered {
+        Some(offered) => offered,
+        None => {
+            // nothing was tried, ask the server what it supports
+            write_message("AUTH", stream)?;
+            let msg = read_message(stream, &mut read_buf)?;
+            match msg.strip_prefix("REJECTED") {
+                Some(mechs) => mechs.split_whitespace().map(str::to_owned).collect(),
+                None => return Err(AuthError::UnexpectedResponse(msg)),
+            }
+        }
+    };
+    Err(AuthError::Rejected { offered })
 }
 
-pub fn negotiate_unix_fds(stream: &mut UnixStream) -> std::io::Result<AuthResult> {
+pub fn negotiate_unix_fds<S: Read + Write>(stream: &mut S) -> std::io::Result<AuthResult> {
     write_message("NEGOTIATE_UNIX_FD", stream)?;
 
     let mut read_buf = Vec::new();
@@ -104,7 +300,113 @@ pub fn negotiate_unix_fds(stream: &mut UnixStream) -> std::io::Result<AuthResult
     }
 }
 
-pub fn send_begin(stream: &mut UnixStream) -> std::io::Result<()> {
+pub fn send_begin<S: Write>(stream: &mut S) -> std::io::Result<()> {
     write_message("BEGIN", stream)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+
+    /// Plays the server side of the handshake. Answers each line with the next response and returns the lines it received.
+    fn fake_server(
+        responses: &'static [&'static str],
+    ) -> (UnixStream, std::thread::JoinHandle<Vec<String>>) {
+        let (client, mut server) = UnixStream::pair().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut null = [0u8];
+            server.read_exact(&mut null).unwrap();
+            assert_eq!(null[0], 0);
+            let mut buf = Vec::new();
+            let mut received = Vec::new();
+            for response in responses {
+                received.push(read_message(&mut server, &mut buf).unwrap());
+                write_message(response, &mut server).unwrap();
+            }
+            received
+        });
+        (client, handle)
+    }
+
+    #[test]
+    fn test_fallback_to_anonymous() {
+        let (mut client, server) = fake_server(&["REJECTED ANONYMOUS", "OK 1234deadbeef"]);
+        let mech = do_auth_with_mechanisms(
+            &mut client,
+            &[AuthMechanism::External, AuthMechanism::Anonymous],
+        )
+        .unwrap();
+        assert_eq!(mech, AuthMechanism::Anonymous);
+        let received = server.join().unwrap();
+        assert!(received[0].starts_with("AUTH EXTERNAL "));
+        assert_eq!(received[1], "AUTH ANONYMOUS 72757374627573");
+    }
+
+    #[test]
+    fn test_all_rejected() {
+        let (mut client, server) = fake_server(&["ERROR", "REJECTED EXTERNAL DBUS_COOKIE_SHA1"]);
+        let err = do_auth_with_mechanisms(
+            &mut client,
+            &[AuthMechanism::Anonymous, AuthMechanism::Anonymous],
+        )
+        .unwrap_err();
+        match err {
+            AuthError::Rejected { offered } => {
+                assert_eq!(offered, vec!["EXTERNAL", "DBUS_COOKIE_SHA1"])
+            }
+            other => panic!("Expected a rejection, got {:?}", other),
+        }
+        assert_eq!(server.join().unwrap()[1], "CANCEL");
+    }
+
+    #[test]
+    fn test_cookie_sha1_response() {
+        let dir = std::env::temp_dir().join(format!("rustbus-keyrings-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("org_freedesktop_general"),
+            "1 1700000000 ffffffff\n2 1700000001 0123456789abcdef0123456789abcdef\n",
+        )
+        .unwrap();
+
+        let response =
+            cookie_sha1_response("org_freedesktop_general 2 1a2b3c", &dir, "abcdef0123").unwrap();
+        // sha1("1a2b3c:abcdef0123:0123456789abcdef0123456789abcdef")
+        assert_eq!(
+            response,
+            "abcdef0123 960457756742469607927e93bb63503d1cca3802"
+        );
+
+        assert!(matches!(
+            cookie_sha1_response("org_freedesktop_general 3 1a2b3c", &dir, "abcdef0123"),
+            Err(AuthError::CookieNotFound { id, .. }) if id == "3"
+        ));
+        assert!(matches!(
+            cookie_sha1_response("missing_context 1 1a2b3c", &dir, "abcdef0123"),
+            Err(AuthError::CookieFile { source, .. }) if source.kind() == io::ErrorKind::NotFound
+        ));
+        assert!(matches!(
+            cookie_sha1_response("../etc 1 1a2b3c", &dir, "abcdef0123"),
+            Err(AuthError::MalformedChallenge(_))
+        ));
+        assert!(matches!(
+            cookie_sha1_response("org_freedesktop_general 1", &dir, "abcdef0123"),
+            Err(AuthError::MalformedChallenge(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(hex_decode("6f6b"), Some(b"ok".to_vec()));
+        assert_eq!(hex_decode("6f6"), None);
+        assert_eq!(hex_decode("zz"), None);
+    }
+
+    #[test]
+    fn test_unexpected_response() {
+        let (mut client, server) = fake_server(&["AGREE_UNIX_FD"]);
+        let err = do_auth_with_mechanisms(&mut client, &[AuthMechanism::External]).unwrap_err();
+        assert!(matches!(err, AuthError::UnexpectedResponse(msg) if msg == "AGREE_UNIX_FD"));
+        server.join().unwrap();
+    }
+}
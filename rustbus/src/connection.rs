@@ -3,21 +3,29 @@
 //! * ll_conn is the basic send and recive primitives used to build the other connection types
 //! * dispatch_conn is meant for services that need to dispatch calls to different handlers
 //! * rpc_conn is meant for clients that make calls to services on the bus
+//! * monitor receives copies of all messages on the bus for debugging
+//! * transport abstracts over the unix socket and TCP streams the connections run over
 
 pub mod dispatch_conn;
 pub mod ll_conn;
+pub mod monitor;
 pub mod rpc_conn;
+pub mod transport;
 
+use std::os::unix::ffi::OsStrExt;
 use std::path::PathBuf;
 use std::{io, time};
 
 use thiserror::Error;
 
-#[derive(Clone, Copy)]
+/// How long an operation on a connection may block
+#[derive(Clone, Copy, Debug)]
 pub enum Timeout {
     Infinite,
     Nonblock,
     Duration(time::Duration),
+    /// Block until this point in time. Useful to share one timeout between multiple operations.
+    Deadline(time::Instant),
 }
 
 use nix::sys::socket::UnixAddr;
@@ -33,12 +41,22 @@ pub enum Error {
     MarshalError(#[from] crate::wire::errors::MarshalError),
     #[error("Authentication failed")]
     AuthFailed,
+    #[error("Authentication failed: {0}")]
+    AuthRejected(crate::auth::AuthError),
     #[error("Negotiating unix fd usage failed")]
     UnixFdNegotiationFailed,
+    #[error("The bus rejected the Hello call: {0}")]
+    HelloFailed(crate::message_builder::DBusError),
     #[error("The name is already taken")]
     NameTaken,
     #[error("The address type {0} is not yet supportd by this lib")]
     AddressTypeNotSupported(String),
+    #[error("The address {0} is malformed")]
+    InvalidAddress(String),
+    #[error("Abstract unix sockets are not supported on this platform: {0}")]
+    AbstractSocketsNotSupported(String),
+    #[error("Unix fds can not be sent over this transport")]
+    UnixFdsNotSupported,
     #[error("This path does not exist: {0}")]
     PathDoesNotExist(String),
     #[error("Address not found")]
@@ -47,38 +65,217 @@ pub enum Error {
     UnexpectedMessageTypeReceived,
     #[error("Timeout occured")]
     TimedOut,
+    #[error("The operation would block")]
+    WouldBlock,
     #[error("Connection has been closed by the other side")]
     ConnectionClosed,
+    #[error("The call failed: {0}")]
+    ErrorResponse(crate::message_builder::DBusError),
+    #[error("The call has NO_REPLY_EXPECTED set, no response will arrive")]
+    NoReplyExpected,
+    #[error("None of the addresses could be used: {}", join_address_errors(.0))]
+    NoUsableAddress(Vec<AddressError>),
+}
+
+/// Why one of multiple addresses could not be used
+#[derive(Debug)]
+pub struct AddressError {
+    pub address: String,
+    pub error: Error,
+}
+
+impl AddressError {
+    fn new(address: &str, error: Error) -> Self {
+        Self {
+            address: address.to_owned(),
+            error,
+        }
+    }
+}
+
+impl std::fmt::Display for AddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.address, self.error)
+    }
+}
+
+fn join_address_errors(failures: &[AddressError]) -> String {
+    failures
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl From<crate::auth::AuthError> for Error {
+    fn from(e: crate::auth::AuthError) -> Self {
+        match e {
+            crate::auth::AuthError::IoError(e) => Error::IoError(e),
+            e => Error::AuthRejected(e),
+        }
+    }
 }
 
 type Result<T> = std::result::Result<T, Error>;
 
-fn parse_dbus_addr_str(addr: &str) -> Result<UnixAddr> {
+/// Restricts which addresses a TCP host name may resolve to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TcpFamily {
+    Ipv4,
+    Ipv6,
+}
+
+/// The address of a bus, parsed from strings like `unix:path=/run/dbus/system_bus_socket` or `tcp:host=127.0.0.1,port=12345`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BusAddress {
+    Unix(UnixAddr),
+    Tcp {
+        host: String,
+        port: u16,
+        family: Option<TcpFamily>,
+    },
+}
+
+impl From<UnixAddr> for BusAddress {
+    fn from(addr: UnixAddr) -> Self {
+        BusAddress::Unix(addr)
+    }
+}
+
+fn parse_dbus_addr_str(addr: &str) -> Result<BusAddress> {
     // split the address string into <system>:rest
     let (addr_system, addr_pairs) = addr.split_once(':').ok_or(Error::NoAddressFound)?;
-    if addr_system != "unix" {
-        return Err(Error::AddressTypeNotSupported(addr.to_owned()));
+    match addr_system {
+        "unix" => Ok(BusAddress::Unix(parse_unix_addr(addr, addr_pairs)?)),
+        "tcp" => parse_tcp_addr(addr, addr_pairs),
+        _ => Err(Error::AddressTypeNotSupported(addr.to_owned())),
     }
+}
 
-    // split the rest of the address string into each <key>=<value> pair
-    for pair in addr_pairs.split(',') {
+/// Parse all semicolon separated addresses in `addrs`, in the order they should be tried.
+///
+/// Addresses that can not be used (unsupported transports, paths that do not exist, ...) are skipped. If none of them
+/// can be used `Error::NoUsableAddress` lists each address with the reason it was skipped.
+pub fn parse_dbus_addresses(addrs: &str) -> Result<Vec<BusAddress>> {
+    let mut failures = Vec::new();
+    let mut candidates = Vec::new();
+    for addr in split_addresses(addrs) {
+        match parse_dbus_addr_str(addr) {
+            Ok(candidate) => candidates.push(candidate),
+            Err(error) => failures.push(AddressError::new(addr, error)),
+        }
+    }
+    if candidates.is_empty() {
+        Err(no_usable_address(failures))
+    } else {
+        Ok(candidates)
+    }
+}
+
+fn split_addresses(addrs: &str) -> impl Iterator<Item = &str> {
+    addrs.split(';').filter(|addr| !addr.is_empty())
+}
+
+fn no_usable_address(failures: Vec<AddressError>) -> Error {
+    if failures.is_empty() {
+        Error::NoAddressFound
+    } else {
+        Error::NoUsableAddress(failures)
+    }
+}
+
+/// Undo the %-escaping of address values. All other bytes are taken as they are.
+fn unescape_addr_value(addr: &str, value: &str) -> Result<Vec<u8>> {
+    let invalid = || Error::InvalidAddress(addr.to_owned());
+    let mut unescaped = Vec::with_capacity(value.len());
+    let mut bytes = value.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [
+                bytes.next().ok_or_else(invalid)?,
+                bytes.next().ok_or_else(invalid)?,
+            ];
+            let hex = std::str::from_utf8(&hex).map_err(|_| invalid())?;
+            // from_str_radix accepts a leading '+' which is not a hex digit
+            if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+            unescaped.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+        } else {
+            unescaped.push(byte);
+        }
+    }
+    Ok(unescaped)
+}
+
+/// split the rest of the address string into each <key>=<value> pair and unescape the values
+fn split_addr_pairs<'a>(
+    addr: &'a str,
+    addr_pairs: &'a str,
+) -> impl Iterator<Item = Result<(&'a str, Vec<u8>)>> + 'a {
+    addr_pairs.split(',').map(move |pair| {
         let (key, value) = pair
             .split_once('=')
-            .ok_or_else(|| Error::AddressTypeNotSupported(addr.to_owned()))?;
+            .ok_or_else(|| Error::InvalidAddress(addr.to_owned()))?;
+        Ok((key, unescape_addr_value(addr, value)?))
+    })
+}
+
+fn parse_tcp_addr(addr: &str, addr_pairs: &str) -> Result<BusAddress> {
+    let mut host = None;
+    let mut port = None;
+    let mut family = None;
+    for pair in split_addr_pairs(addr, addr_pairs) {
+        let (key, value) = pair?;
+        let value = || String::from_utf8(value).map_err(|_| Error::InvalidAddress(addr.to_owned()));
+        match key {
+            "host" => host = Some(value()?),
+            "port" => {
+                port = Some(
+                    value()?
+                        .parse::<u16>()
+                        .map_err(|_| Error::InvalidAddress(addr.to_owned()))?,
+                )
+            }
+            "family" => match value()?.as_str() {
+                "ipv4" => family = Some(TcpFamily::Ipv4),
+                "ipv6" => family = Some(TcpFamily::Ipv6),
+                _ => return Err(Error::InvalidAddress(addr.to_owned())),
+            },
+            // guid, bind, ... are not needed to connect
+            _ => {}
+        }
+    }
+    match (host, port) {
+        (Some(host), Some(port)) => Ok(BusAddress::Tcp { host, port, family }),
+        _ => Err(Error::InvalidAddress(addr.to_owned())),
+    }
+}
+
+fn parse_unix_addr(addr: &str, addr_pairs: &str) -> Result<UnixAddr> {
+    for pair in split_addr_pairs(addr, addr_pairs) {
+        let (key, value) = pair?;
 
         match key {
             "path" => {
-                let p = PathBuf::from(&value);
+                let p = PathBuf::from(std::ffi::OsStr::from_bytes(&value));
                 if p.exists() {
                     return Ok(UnixAddr::new(&p).map_err(io::Error::from)?);
                 } else {
-                    return Err(Error::PathDoesNotExist(value.to_string()));
+                    return Err(Error::PathDoesNotExist(p.to_string_lossy().into_owned()));
                 }
             }
             "abstract" => {
-                #[cfg(target_os = "linux")]
+                // The kernel marks abstract names by a leading NUL in sun_path. The value in the address
+                // does not contain it, new_abstract prepends it and only counts the name's own bytes
+                // towards the address length, as dbus-daemon does when it binds the socket.
+                #[cfg(any(target_os = "android", target_os = "linux"))]
                 {
-                    return Ok(UnixAddr::new_abstract(value.as_bytes()).map_err(io::Error::from)?);
+                    return Ok(UnixAddr::new_abstract(&value).map_err(io::Error::from)?);
+                }
+                #[cfg(not(any(target_os = "android", target_os = "linux")))]
+                {
+                    return Err(Error::AbstractSocketsNotSupported(addr.to_owned()));
                 }
             }
             _ => {}
@@ -88,14 +285,30 @@ fn parse_dbus_addr_str(addr: &str) -> Result<UnixAddr> {
     Err(Error::AddressTypeNotSupported(addr.to_owned()))
 }
 
-/// Convenience function that returns the UnixAddr of the session bus according to the env
+/// The value of the env var $DBUS_SESSION_BUS_ADDRESS. It may contain multiple addresses, see `parse_dbus_addresses`.
+pub fn get_session_bus_addresses() -> Result<String> {
+    std::env::var("DBUS_SESSION_BUS_ADDRESS").map_err(|_| Error::NoAddressFound)
+}
+
+/// Convenience function that returns the first usable address of the session bus according to the env
 /// var $DBUS_SESSION_BUS_ADDRESS.
+pub fn get_session_bus_address() -> Result<BusAddress> {
+    let mut candidates = parse_dbus_addresses(&get_session_bus_addresses()?)?;
+    Ok(candidates.remove(0))
+}
+
+/// Convenience function that returns the UnixAddr of the session bus according to the env
+/// var $DBUS_SESSION_BUS_ADDRESS. This is the first usable unix address, use `get_session_bus_address` to also
+/// get tcp addresses.
 pub fn get_session_bus_path() -> Result<UnixAddr> {
-    if let Ok(envvar) = std::env::var("DBUS_SESSION_BUS_ADDRESS") {
-        parse_dbus_addr_str(&envvar)
-    } else {
-        Err(Error::NoAddressFound)
-    }
+    let addrs = get_session_bus_addresses()?;
+    parse_dbus_addresses(&addrs)?
+        .into_iter()
+        .find_map(|addr| match addr {
+            BusAddress::Unix(addr) => Some(addr),
+            BusAddress::Tcp { .. } => None,
+        })
+        .ok_or(Error::AddressTypeNotSupported(addrs))
 }
 
 /// Convenience function that returns a path to the system bus at /run/dbus/systemd_bus_socket
@@ -109,8 +322,17 @@ pub fn get_system_bus_path() -> Result<UnixAddr> {
     }
 }
 
+/// Time left until the deadline, errors with `Error::TimedOut` if the deadline has passed
+pub(crate) fn time_until(deadline: time::Instant) -> Result<time::Duration> {
+    deadline
+        .checked_duration_since(time::Instant::now())
+        .filter(|left| !left.is_zero())
+        .ok_or(Error::TimedOut)
+}
+
 pub(crate) fn calc_timeout_left(start_time: &time::Instant, timeout: Timeout) -> Result<Timeout> {
     match timeout {
+        Timeout::Deadline(deadline) => Ok(Timeout::Duration(time_until(deadline)?)),
         Timeout::Duration(timeout) => {
             let elapsed = start_time.elapsed();
             if elapsed >= timeout {
@@ -145,14 +367,20 @@ mod tests {
                 // stripped from the session bus' determined path.
                 assert_eq!("/tmp/dbus-test-not-exist", path);
             }
-            _ => assert!(false, "expected Error::PathDoesNotExist"),
+            _ => panic!("expected Error::PathDoesNotExist"),
         }
 
         let addr = parse_dbus_addr_str(abstract_path).unwrap();
-        assert_eq!(addr, UnixAddr::new_abstract(b"/tmp/dbus-test").unwrap());
+        assert_eq!(
+            addr,
+            BusAddress::Unix(UnixAddr::new_abstract(b"/tmp/dbus-test").unwrap())
+        );
 
         let addr = parse_dbus_addr_str(abstract_path_with_keys).unwrap();
-        assert_eq!(addr, UnixAddr::new_abstract(b"/tmp/dbus-test").unwrap());
+        assert_eq!(
+            addr,
+            BusAddress::Unix(UnixAddr::new_abstract(b"/tmp/dbus-test").unwrap())
+        );
     }
     #[cfg(not(target_os = "linux"))]
     #[test]
@@ -161,5 +389,142 @@ mod tests {
 
         let addr = parse_dbus_addr_str(path);
         assert!(addr.is_err());
+
+        #[cfg(not(target_os = "android"))]
+        assert!(matches!(
+            parse_dbus_addr_str("unix:abstract=/tmp/dbus-test"),
+            Err(Error::AbstractSocketsNotSupported(_))
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_connect_abstract() {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::{SocketAddr, UnixListener};
+
+        let name = format!("/tmp/rustbus-test-{}", std::process::id());
+        let listener =
+            UnixListener::bind_addr(&SocketAddr::from_abstract_name(name.as_bytes()).unwrap())
+                .unwrap();
+
+        let addr = parse_dbus_addr_str(&format!("unix:abstract={},guid=aaaa", name)).unwrap();
+        let unix_addr = match &addr {
+            BusAddress::Unix(unix_addr) => unix_addr,
+            other => panic!("expected a unix address, got {:?}", other),
+        };
+        // the leading NUL is not part of the name
+        assert_eq!(unix_addr.as_abstract(), Some(name.as_bytes()));
+        assert_eq!(unix_addr.path_len(), name.len() + 1);
+
+        let client = std::thread::spawn(move || ll_conn::DuplexConn::connect_to_bus(addr, false));
+        let (server, _) = listener.accept().unwrap();
+        // hangs up during the auth, the client connected to the right socket nonetheless
+        drop(server);
+        assert!(client.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_parse_tcp_addr() {
+        assert_eq!(
+            parse_dbus_addr_str("tcp:host=127.0.0.1,port=12345").unwrap(),
+            BusAddress::Tcp {
+                host: "127.0.0.1".to_owned(),
+                port: 12345,
+                family: None
+            }
+        );
+        assert_eq!(
+            parse_dbus_addr_str("tcp:host=localhost,port=4711,family=ipv6,guid=aaaa").unwrap(),
+            BusAddress::Tcp {
+                host: "localhost".to_owned(),
+                port: 4711,
+                family: Some(TcpFamily::Ipv6)
+            }
+        );
+        for invalid in [
+            "tcp:host=127.0.0.1",
+            "tcp:port=12345",
+            "tcp:host=127.0.0.1,port=123456",
+            "tcp:host=127.0.0.1,port=12345,family=ipx",
+            "tcp:host",
+        ] {
+            match parse_dbus_addr_str(invalid) {
+                Err(Error::InvalidAddress(addr)) => assert_eq!(addr, invalid),
+                other => panic!(
+                    "expected Error::InvalidAddress for {}, got {:?}",
+                    invalid, other
+                ),
+            }
+        }
+        assert!(matches!(
+            parse_dbus_addr_str("nonce-tcp:host=127.0.0.1,port=12345"),
+            Err(Error::AddressTypeNotSupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_unescape_addr_value() {
+        assert_eq!(
+            unescape_addr_value("", "/tmp/with%20space%2c%3d").unwrap(),
+            b"/tmp/with space,="
+        );
+        assert_eq!(unescape_addr_value("", "%ff%00").unwrap(), [0xff, 0]);
+        for invalid in ["%", "%2", "%zz", "%+1"] {
+            assert!(matches!(
+                unescape_addr_value("", invalid),
+                Err(Error::InvalidAddress(_))
+            ));
+        }
+        assert_eq!(
+            parse_dbus_addr_str("tcp:host=%31%32%37.0.0.1,port=%31%32").unwrap(),
+            BusAddress::Tcp {
+                host: "127.0.0.1".to_owned(),
+                port: 12,
+                family: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_dbus_addresses() {
+        let addrs = "unix:path=/tmp/dbus-test-not-exist;;tcp:host=localhost,port=1;\
+                     launchd:env=DBUS_LAUNCHD_SESSION_BUS_SOCKET;tcp:host=localhost,port=2";
+        assert_eq!(
+            parse_dbus_addresses(addrs).unwrap(),
+            [
+                BusAddress::Tcp {
+                    host: "localhost".to_owned(),
+                    port: 1,
+                    family: None
+                },
+                BusAddress::Tcp {
+                    host: "localhost".to_owned(),
+                    port: 2,
+                    family: None
+                }
+            ]
+        );
+
+        let addrs = "unix:path=/tmp/dbus-test-not-exist;tcp:host=localhost";
+        match parse_dbus_addresses(addrs) {
+            Err(Error::NoUsableAddress(failures)) => {
+                assert_eq!(failures.len(), 2);
+                assert_eq!(failures[0].address, "unix:path=/tmp/dbus-test-not-exist");
+                assert!(matches!(failures[0].error, Error::PathDoesNotExist(_)));
+                assert_eq!(failures[1].address, "tcp:host=localhost");
+                assert!(matches!(failures[1].error, Error::InvalidAddress(_)));
+                let msg = Error::NoUsableAddress(failures).to_string();
+                assert!(
+                    msg.contains("unix:path=/tmp/dbus-test-not-exist: This path does not exist")
+                );
+                assert!(msg.contains("; tcp:host=localhost: The address"));
+            }
+            other => panic!("expected Error::NoUsableAddress, got {:?}", other),
+        }
+        assert!(matches!(
+            parse_dbus_addresses(";"),
+            Err(Error::NoAddressFound)
+        ));
     }
 }
@@ -1,42 +1,47 @@
+use super::time_until;
+use super::transport::{Transport, UnixTransport, MAX_FDS_PER_SYSCALL};
+use super::BusAddress;
 use super::Error;
 use super::Result;
 use super::Timeout;
 use crate::auth;
 use crate::message_builder::MarshalledMessage;
-use crate::wire::errors::UnmarshalError;
-use crate::wire::marshal;
-use crate::wire::unmarshal;
+use crate::wire::reassembler::MessageReassembler;
+use crate::wire::writer::MessageWriter;
+use crate::wire::UnixFd;
 
-use std::io::{self, IoSlice, IoSliceMut};
-use std::os::fd::AsFd;
+use std::io::{self, IoSlice};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::os::fd::{AsFd, BorrowedFd};
 use std::time;
 
 use std::os::unix::io::AsRawFd;
 use std::os::unix::io::RawFd;
 use std::os::unix::net::UnixStream;
 
-use nix::cmsg_space;
-use nix::sys::socket::{
-    self, connect, recvmsg, sendmsg, socket, ControlMessage, ControlMessageOwned, MsgFlags,
-    SockaddrStorage, UnixAddr,
-};
+use nix::sys::socket::{self, connect, socket};
 
-/// A lowlevel abstraction over the raw unix socket
+/// A lowlevel abstraction over the raw socket
+///
+/// The serials and headers are handled by a `wire::MessageWriter`, this only adds the writes to the socket.
 #[derive(Debug)]
 pub struct SendConn {
-    stream: UnixStream,
+    stream: Box<dyn Transport>,
     header_buf: Vec<u8>,
 
-    serial_counter: u32,
+    writer: MessageWriter,
+    /// The name the bus assigned in its reply to Hello
+    unique_name: Option<String>,
 }
 
+/// Receives messages from the socket. The bytes are collected in a `wire::MessageReassembler`, this only adds the
+/// reads from the socket.
 pub struct RecvConn {
-    stream: UnixStream,
+    stream: Box<dyn Transport>,
 
-    msg_buf_in: Vec<u8>,
-    msg_buf_filled: usize,
-    cmsgs_in: Vec<ControlMessageOwned>,
-    cmsgspace: Vec<u8>,
+    reassembler: MessageReassembler,
+    /// The fds of one read, they are moved to the reassembler right away
+    fds_in: Vec<RawFd>,
 }
 
 pub struct DuplexConn {
@@ -57,23 +62,19 @@ impl RecvConn {
         Ok(fdset.contains(self.stream.as_fd()))
     }
 
-    /// Reads from the source once but takes care that the internal buffer only reaches at maximum max_buffer_size
-    /// so we can process messages separatly and avoid leaking file descriptors to wrong messages
-    fn refill_buffer(&mut self, max_buffer_size: usize, timeout: Timeout) -> Result<()> {
-        if self.msg_buf_in.len() != max_buffer_size {
-            self.msg_buf_in.resize(max_buffer_size, 0);
-        }
-
-        let iovec = IoSliceMut::new(&mut self.msg_buf_in[self.msg_buf_filled..max_buffer_size]);
-
-        self.cmsgspace.clear();
-        let flags = MsgFlags::empty();
+    /// Reads from the source once but never more than the current message still needs, so we can process messages
+    /// separatly and avoid leaking file descriptors to wrong messages
+    fn refill_buffer(&mut self, timeout: Timeout) -> Result<()> {
+        let max_len = self.reassembler.bytes_missing()?;
 
         let old_timeout = self.stream.read_timeout()?;
         match timeout {
             Timeout::Duration(d) => {
                 self.stream.set_read_timeout(Some(d))?;
             }
+            Timeout::Deadline(deadline) => {
+                self.stream.set_read_timeout(Some(time_until(deadline)?))?;
+            }
             Timeout::Infinite => {
                 self.stream.set_read_timeout(None)?;
             }
@@ -81,71 +82,45 @@ impl RecvConn {
                 self.stream.set_nonblocking(true)?;
             }
         }
-        let iovec_mut = &mut [iovec];
-        let msg = recvmsg::<SockaddrStorage>(
-            self.stream.as_raw_fd(),
-            iovec_mut,
-            Some(&mut self.cmsgspace),
-            flags,
-        )
-        .map_err(|e| match e {
-            nix::errno::Errno::EAGAIN => Error::TimedOut,
-            _ => Error::IoError(e.into()),
-        });
+        let stream = &mut self.stream;
+        let fds_in = &mut self.fds_in;
+        let bytes = self
+            .reassembler
+            .fill_with(max_len, |buf| stream.recv_with_fds(buf, fds_in))
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::WouldBlock => Error::TimedOut,
+                _ => Error::IoError(e),
+            });
+        self.reassembler
+            .push_fds(self.fds_in.drain(..).map(UnixFd::new));
 
         self.stream.set_nonblocking(false)?;
         self.stream.set_read_timeout(old_timeout)?;
 
-        let msg = msg?;
+        let bytes = bytes?;
 
-        if msg.bytes == 0 {
+        if bytes == 0 {
             return Err(Error::ConnectionClosed);
         }
-
-        self.cmsgs_in.extend(msg.cmsgs());
-        let bytes = msg.bytes;
-        self.msg_buf_filled += bytes;
         Ok(())
     }
 
+    /// The length of the current message, or of its fixed header as long as that is not complete
     pub fn bytes_needed_for_current_message(&self) -> Result<usize> {
-        if self.msg_buf_filled < 16 {
-            return Ok(16);
-        }
-        let msg_buf_in = &self.msg_buf_in[..self.msg_buf_filled];
-        let (_, header) = unmarshal::unmarshal_header(msg_buf_in, 0)?;
-        let (_, header_fields_len) =
-            crate::wire::util::parse_u32(&msg_buf_in[unmarshal::HEADER_LEN..], header.byteorder)?;
-        let complete_header_size = unmarshal::HEADER_LEN + header_fields_len as usize + 4; // +4 because the length of the header fields does not count
-
-        let padding_between_header_and_body = 8 - ((complete_header_size) % 8);
-        let padding_between_header_and_body = if padding_between_header_and_body == 8 {
-            0
-        } else {
-            padding_between_header_and_body
-        };
+        Ok(self.reassembler.buffered() + self.reassembler.bytes_missing()?)
+    }
 
-        let bytes_needed =
-            complete_header_size + padding_between_header_and_body + header.body_len as usize;
-        Ok(bytes_needed)
+    /// Messages that announce a length over `max` bytes are rejected with `UnmarshalError::MessageTooLong` before any
+    /// memory is allocated for them. Defaults to `wire::reassembler::MAX_MESSAGE_SIZE`.
+    ///
+    /// After such an error the connection can not find the start of the next message and should be closed.
+    pub fn set_max_message_size(&mut self, max: usize) {
+        self.reassembler.set_max_message_size(max);
     }
 
     // Checks if the internal buffer currently holds a complete message
     pub fn buffer_contains_whole_message(&self) -> Result<bool> {
-        if self.msg_buf_filled < 16 {
-            return Ok(false);
-        }
-        let bytes_needed = self.bytes_needed_for_current_message();
-        match bytes_needed {
-            Err(e) => {
-                if let Error::UnmarshalError(UnmarshalError::NotEnoughBytes) = e {
-                    Ok(false)
-                } else {
-                    Err(e)
-                }
-            }
-            Ok(bytes_needed) => Ok(self.msg_buf_filled >= bytes_needed),
-        }
+        Ok(self.reassembler.bytes_missing()? == 0)
     }
     /// Blocks until a message has been read from the conn or the timeout has been reached
     pub fn read_whole_message(&mut self, timeout: Timeout) -> Result<()> {
@@ -155,83 +130,96 @@ impl RecvConn {
         let start_time = time::Instant::now();
 
         while !self.buffer_contains_whole_message()? {
-            self.refill_buffer(
-                self.bytes_needed_for_current_message()?,
-                super::calc_timeout_left(&start_time, timeout)?,
-            )?;
+            self.refill_buffer(super::calc_timeout_left(&start_time, timeout)?)?;
         }
         Ok(())
     }
 
     /// Blocks until one read towards the message has been performed from the conn or the timeout has been reached
     pub fn read_once(&mut self, timeout: Timeout) -> Result<()> {
-        self.refill_buffer(self.bytes_needed_for_current_message()?, timeout)?;
+        self.refill_buffer(timeout)?;
         Ok(())
     }
 
-    /// Blocks until a message has been read from the conn or the timeout has been reached
-    pub fn get_next_message(&mut self, timeout: Timeout) -> Result<MarshalledMessage> {
-        self.read_whole_message(timeout)?;
-        debug_assert_eq!(self.msg_buf_filled, self.msg_buf_in.len());
-        let (hdrbytes, header) = unmarshal::unmarshal_header(&self.msg_buf_in, 0)?;
-        let (dynhdrbytes, dynheader) =
-            unmarshal::unmarshal_dynamic_header(&header, &self.msg_buf_in, hdrbytes)?;
-
-        let (bytes_used, mut msg) = unmarshal::unmarshal_next_message(
-            &header,
-            dynheader,
-            std::mem::take(&mut self.msg_buf_in),
-            hdrbytes + dynhdrbytes,
-        )?;
-
-        if self.msg_buf_filled != bytes_used + hdrbytes + dynhdrbytes {
-            return Err(Error::UnmarshalError(UnmarshalError::NotAllBytesUsed));
-        }
-        self.msg_buf_filled = 0;
-
-        for cmsg in &self.cmsgs_in {
-            match cmsg {
-                ControlMessageOwned::ScmRights(fds) => {
-                    msg.body
-                        .raw_fds
-                        .extend(fds.iter().map(|fd| crate::wire::UnixFd::new(*fd)));
-                }
-                _ => {
-                    // TODO what to do?
-                    eprintln!("Cmsg other than ScmRights: {:?}", cmsg);
-                }
+    /// Read whatever is available without blocking and return the next message if it is complete.
+    ///
+    /// Returns `Ok(None)` if bytes were read but the message is not complete yet and `Error::WouldBlock` if there was
+    /// nothing to read at all. In both cases wait until the fd (see `AsRawFd`) becomes readable and call this again,
+    /// the partially read message is kept in the internal buffer. This reads until the socket would block, so it also
+    /// works with edge triggered notifications. Only one message is returned per call, keep calling it until it does
+    /// not return a message anymore.
+    pub fn try_get_next_message(&mut self) -> Result<Option<MarshalledMessage>> {
+        let mut bytes_read = false;
+        while !self.buffer_contains_whole_message()? {
+            match self.read_once(Timeout::Nonblock) {
+                Ok(()) => bytes_read = true,
+                Err(Error::TimedOut) if bytes_read => return Ok(None),
+                Err(Error::TimedOut) => return Err(Error::WouldBlock),
+                Err(e) => return Err(e),
             }
         }
-        self.cmsgs_in.clear();
+        self.get_next_message(Timeout::Nonblock).map(Some)
+    }
 
-        Ok(msg)
+    /// Blocks until a message has been read from the conn or the timeout has been reached
+    pub fn get_next_message(&mut self, timeout: Timeout) -> Result<MarshalledMessage> {
+        self.read_whole_message(timeout)?;
+        // a sender may pass the fds of multiple messages at once, the reassembler gives each message only the fds it
+        // announced
+        let msg = self.reassembler.next_message()?;
+        Ok(msg.expect("read_whole_message returns once the message is complete"))
     }
 }
 
 impl SendConn {
-    /// get the next new serial
+    /// get the next new serial. This can also be used to allocate a serial without sending anything, e.g. to record it
+    /// before the message is sent. Put it into `dynheader.serial` of the message so it is used when sending.
+    ///
+    /// Serials count up from 1 and wrap from `u32::MAX` back to 1, 0 is never handed out since it is not a valid serial.
     pub fn alloc_serial(&mut self) -> u32 {
-        let serial = self.serial_counter;
-        self.serial_counter += 1;
-        serial
+        self.writer.next_serial()
+    }
+
+    /// The serial the next message that has no serial yet will get. This does not allocate it.
+    pub fn peek_serial(&self) -> u32 {
+        self.writer.peek_serial()
+    }
+
+    /// Copy `msg` with a freshly allocated serial to retry sending it. See `MarshalledMessage::clone_with_serial`.
+    pub fn clone_with_new_serial(&mut self, msg: &MarshalledMessage) -> MarshalledMessage {
+        let serial = self.alloc_serial();
+        msg.clone_with_serial(serial)
+    }
+
+    /// Whether messages with unix fds can be sent over this conn. This is not the case for TCP connections.
+    pub fn supports_unix_fds(&self) -> bool {
+        self.stream.supports_unix_fds()
+    }
+
+    /// The unique name (like `:1.42`) the bus assigned to this connection, `None` until `DuplexConn::send_hello`
+    /// succeeded. The bus puts it into the sender field of every message sent over this connection.
+    pub fn unique_name(&self) -> Option<&str> {
+        self.unique_name.as_deref()
+    }
+
+    /// Record the unique name from the reply to a Hello call, see `parse_hello_reply`
+    pub(crate) fn set_unique_name(&mut self, name: String) {
+        self.unique_name = Some(name);
     }
 
     /// send a message over the conn
+    ///
+    /// Errors with `Error::UnixFdsNotSupported` if the message carries unix fds but the transport can not pass them.
     pub fn send_message<'a>(
         &'a mut self,
         msg: &'a MarshalledMessage,
     ) -> Result<SendMessageContext<'a>> {
-        let serial = if let Some(serial) = msg.dynheader.serial {
-            serial
-        } else {
-            let serial = self.serial_counter;
-            self.serial_counter += 1;
-            serial
-        };
-
+        if !msg.body.raw_fds.is_empty() && !self.supports_unix_fds() {
+            return Err(Error::UnixFdsNotSupported);
+        }
         // clear the buf before marshalling the new header
         self.header_buf.clear();
-        marshal::marshal(msg, serial, &mut self.header_buf)?;
+        let serial = self.writer.marshal_header(msg, &mut self.header_buf)?;
 
         let ctx = SendMessageContext {
             msg,
@@ -251,6 +239,99 @@ impl SendConn {
         let ctx = self.send_message(msg)?;
         ctx.write_all().map_err(force_finish_on_error)
     }
+
+    /// Send all messages with as few syscalls as possible and block until all bytes have been sent. Returns the serials of the
+    /// messages in the same order. Messages that already have a serial keep it, the others get a new one each.
+    ///
+    /// The headers are marshalled into one buffer and the messages are written in batches with a single `sendmsg`.
+    /// The kernel limits how many fds can be passed in one `sendmsg` (`SCM_MAX_FD`, 253 on linux), so a new batch is
+    /// started when the fds of the next message would exceed `transport::MAX_FDS_PER_SYSCALL`. The fds of a batch are sent in one
+    /// ancillary data block with its first byte. Receivers must distribute them by the number announced in each
+    /// header, as dbus-daemon and `RecvConn` do. A message that carries more fds than that on its own is rejected with
+    /// `MarshalError::TooManyFds` before anything is written.
+    ///
+    /// If an error occurs after some bytes have been written the connection is left in an ill defined state and should be closed.
+    pub fn send_all(&mut self, msgs: &[MarshalledMessage]) -> Result<Vec<u32>> {
+        if !self.supports_unix_fds() && msgs.iter().any(|msg| !msg.body.raw_fds.is_empty()) {
+            return Err(Error::UnixFdsNotSupported);
+        }
+
+        // marshal all headers before anything is written, so a broken message does not leave the conn half written
+        self.header_buf.clear();
+        let mut header_ends = Vec::with_capacity(msgs.len());
+        let mut serials = Vec::with_capacity(msgs.len());
+        let serial_counter = self.writer.serial_counter;
+        for msg in msgs {
+            let serial = match self.writer.marshal_header(msg, &mut self.header_buf) {
+                Ok(serial) => serial,
+                Err(e) => {
+                    self.writer.serial_counter = serial_counter;
+                    return Err(e.into());
+                }
+            };
+            header_ends.push(self.header_buf.len());
+            serials.push(serial);
+        }
+
+        let mut batch_start = 0;
+        while batch_start < msgs.len() {
+            let mut batch_end = batch_start;
+            let mut fds = Vec::new();
+            while batch_end < msgs.len() && batch_end - batch_start < MAX_MSGS_PER_SYSCALL {
+                let msg_fds = &msgs[batch_end].body.raw_fds;
+                if batch_end > batch_start && fds.len() + msg_fds.len() > MAX_FDS_PER_SYSCALL {
+                    break;
+                }
+                fds.extend(msg_fds.iter().filter_map(|fd| fd.get_raw_fd()));
+                batch_end += 1;
+            }
+
+            let mut slices = Vec::with_capacity(2 * (batch_end - batch_start));
+            for idx in batch_start..batch_end {
+                let header_start = if idx == 0 { 0 } else { header_ends[idx - 1] };
+                slices.push(&self.header_buf[header_start..header_ends[idx]]);
+                slices.push(msgs[idx].get_buf());
+            }
+            write_all_with_fds(&mut *self.stream, &slices, &fds)?;
+            batch_start = batch_end;
+        }
+        Ok(serials)
+    }
+}
+
+/// How many messages `SendConn::send_all` writes in one `sendmsg` call at most. Each message needs two of the 1024 iovecs
+/// linux accepts.
+const MAX_MSGS_PER_SYSCALL: usize = 512;
+
+/// Write all bytes of the slices, passing the fds along with the first write
+fn write_all_with_fds(stream: &mut dyn Transport, slices: &[&[u8]], fds: &[RawFd]) -> Result<()> {
+    let total: usize = slices.iter().map(|slice| slice.len()).sum();
+    let mut bytes_sent = 0;
+    while bytes_sent < total {
+        // skip everything that has been sent already
+        let mut skip = bytes_sent;
+        let iov = slices
+            .iter()
+            .filter_map(|slice| {
+                if skip >= slice.len() {
+                    skip -= slice.len();
+                    None
+                } else {
+                    let iov = IoSlice::new(&slice[skip..]);
+                    skip = 0;
+                    Some(iov)
+                }
+            })
+            .collect::<Vec<_>>();
+        let fds = if bytes_sent == 0 { fds } else { &[] };
+        match stream.send_with_fds(&iov, fds) {
+            Ok(0) => return Err(Error::ConnectionClosed),
+            Ok(n) => bytes_sent += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
 }
 
 /// only call if you deem the connection doomed by an error returned from writing.
@@ -400,13 +481,16 @@ impl SendMessageContext<'_> {
             IoSlice::new(header_slice_to_send),
             IoSlice::new(body_slice_to_send),
         ];
-        let flags = MsgFlags::empty();
-
         let old_timeout = self.conn.stream.write_timeout()?;
         match timeout {
             Timeout::Duration(d) => {
                 self.conn.stream.set_write_timeout(Some(d))?;
             }
+            Timeout::Deadline(deadline) => {
+                self.conn
+                    .stream
+                    .set_write_timeout(Some(time_until(deadline)?))?;
+            }
             Timeout::Infinite => {
                 self.conn.stream.set_write_timeout(None)?;
             }
@@ -427,18 +511,12 @@ impl SendMessageContext<'_> {
         } else {
             vec![]
         };
-        let bytes_sent = sendmsg::<SockaddrStorage>(
-            self.conn.stream.as_raw_fd(),
-            &iov,
-            &[ControlMessage::ScmRights(&raw_fds)],
-            flags,
-            None,
-        );
+        let bytes_sent = self.conn.stream.send_with_fds(&iov, &raw_fds);
 
         self.conn.stream.set_write_timeout(old_timeout)?;
         self.conn.stream.set_nonblocking(false)?;
 
-        let bytes_sent = bytes_sent.map_err(io::Error::from)?;
+        let bytes_sent = bytes_sent?;
 
         self.state.bytes_sent += bytes_sent;
 
@@ -447,27 +525,93 @@ impl SendMessageContext<'_> {
 }
 
 impl DuplexConn {
-    /// Connect to a unix socket
+    /// Connect to the bus at `addr`. This can be a `UnixAddr` or a `BusAddress` as returned by `get_session_bus_address`.
+    ///
+    /// Unix sockets authenticate with EXTERNAL. TCP connections carry no credentials and try DBUS_COOKIE_SHA1 and
+    /// then ANONYMOUS instead. Unix fds can not be passed over TCP, so `with_unix_fd` is ignored for TCP connections.
     ///
     /// Remember to send the mandatory hello message before doing anything else with the connection!
     /// You can use the `send_hello` function for this.
-    pub fn connect_to_bus(addr: UnixAddr, with_unix_fd: bool) -> super::Result<DuplexConn> {
-        let sock = socket(
-            socket::AddressFamily::Unix,
-            socket::SockType::Stream,
-            socket::SockFlag::empty(),
-            None,
-        )
-        .map_err(io::Error::from)?;
-
-        connect(sock.as_raw_fd(), &addr).map_err(io::Error::from)?;
-        let mut stream = UnixStream::from(sock);
-        match auth::do_auth(&mut stream)? {
-            auth::AuthResult::Ok => {}
-            auth::AuthResult::Rejected => return Err(Error::AuthFailed),
+    pub fn connect_to_bus<A: Into<BusAddress>>(
+        addr: A,
+        with_unix_fd: bool,
+    ) -> super::Result<DuplexConn> {
+        let addr = addr.into();
+        let mechanisms: &[auth::AuthMechanism] = match addr {
+            BusAddress::Unix(_) => &[auth::AuthMechanism::External],
+            BusAddress::Tcp { .. } => &[
+                auth::AuthMechanism::DBusCookieSha1,
+                auth::AuthMechanism::Anonymous,
+            ],
+        };
+        Self::connect_to_bus_with_auth(addr, with_unix_fd, mechanisms)
+    }
+
+    /// Try each of the semicolon separated addresses in `addrs` in turn, like the value of $DBUS_SESSION_BUS_ADDRESS,
+    /// and return the first connection that could be established.
+    ///
+    /// If none of them works `Error::NoUsableAddress` lists every address with the reason it failed.
+    pub fn connect_to_any_bus(addrs: &str, with_unix_fd: bool) -> super::Result<DuplexConn> {
+        let mut failures = Vec::new();
+        for addr in super::split_addresses(addrs) {
+            match super::parse_dbus_addr_str(addr)
+                .and_then(|parsed| Self::connect_to_bus(parsed, with_unix_fd))
+            {
+                Ok(conn) => return Ok(conn),
+                Err(error) => failures.push(super::AddressError::new(addr, error)),
+            }
         }
+        Err(super::no_usable_address(failures))
+    }
+
+    /// Connect to the bus at `addr` and authenticate with the first of `mechanisms` that the server accepts.
+    ///
+    /// If the server rejects all of them `Error::AuthRejected` tells which mechanisms the server offered.
+    pub fn connect_to_bus_with_auth<A: Into<BusAddress>>(
+        addr: A,
+        with_unix_fd: bool,
+        mechanisms: &[auth::AuthMechanism],
+    ) -> super::Result<DuplexConn> {
+        let stream: Box<dyn Transport> = match addr.into() {
+            BusAddress::Unix(addr) => {
+                let sock = socket(
+                    socket::AddressFamily::Unix,
+                    socket::SockType::Stream,
+                    socket::SockFlag::empty(),
+                    None,
+                )
+                .map_err(io::Error::from)?;
+
+                connect(sock.as_raw_fd(), &addr).map_err(io::Error::from)?;
+                Box::new(UnixTransport::new(UnixStream::from(sock)))
+            }
+            BusAddress::Tcp { host, port, family } => {
+                let addrs = (host.as_str(), port)
+                    .to_socket_addrs()?
+                    .filter(|addr| match family {
+                        Some(super::TcpFamily::Ipv4) => addr.is_ipv4(),
+                        Some(super::TcpFamily::Ipv6) => addr.is_ipv6(),
+                        None => true,
+                    })
+                    .collect::<Vec<SocketAddr>>();
+                let stream = TcpStream::connect(&addrs[..])?;
+                stream.set_nodelay(true)?;
+                Box::new(stream)
+            }
+        };
+        Self::connect_with_transport(stream, with_unix_fd, mechanisms)
+    }
+
+    /// Authenticate over an already connected transport. Unix fds are only negotiated if `with_unix_fd` is set and
+    /// the transport supports them.
+    pub fn connect_with_transport(
+        mut stream: Box<dyn Transport>,
+        with_unix_fd: bool,
+        mechanisms: &[auth::AuthMechanism],
+    ) -> super::Result<DuplexConn> {
+        auth::do_auth_with_mechanisms(&mut stream, mechanisms)?;
 
-        if with_unix_fd {
+        if with_unix_fd && stream.supports_unix_fds() {
             match auth::negotiate_unix_fds(&mut stream)? {
                 auth::AuthResult::Ok => {}
                 auth::AuthResult::Rejected => return Err(Error::UnixFdNegotiationFailed),
@@ -478,21 +622,24 @@ impl DuplexConn {
 
         Ok(DuplexConn {
             send: SendConn {
-                stream: stream.try_clone()?,
+                stream: stream.try_clone_transport()?,
                 header_buf: Vec::new(),
-                serial_counter: 1,
+                writer: MessageWriter::new(),
+                unique_name: None,
             },
             recv: RecvConn {
-                msg_buf_in: Vec::new(),
-                msg_buf_filled: 0,
-                cmsgs_in: Vec::new(),
-                cmsgspace: cmsg_space!([RawFd; 10]),
+                reassembler: MessageReassembler::new(),
+                fds_in: Vec::new(),
                 stream,
             },
         })
     }
 
-    /// Sends the obligatory hello message and returns the unique id the daemon assigned this connection
+    /// Sends the obligatory hello message and returns the unique id the daemon assigned this connection. The name is
+    /// also kept and returned by `unique_name` afterwards.
+    ///
+    /// If the bus answers with an error, e.g. because Hello was already sent on this connection, `Error::HelloFailed`
+    /// is returned.
     pub fn send_hello(&mut self, timeout: crate::connection::Timeout) -> super::Result<String> {
         let start_time = time::Instant::now();
 
@@ -511,29 +658,252 @@ impl DuplexConn {
         if resp.dynheader.response_serial != Some(serial) {
             return Err(super::Error::AuthFailed);
         }
-        let unique_name = resp.body.parser().get::<String>()?;
+        let unique_name = parse_hello_reply(&resp)?;
+        self.send.set_unique_name(unique_name.clone());
         Ok(unique_name)
     }
+
+    /// The unique name the bus assigned to this connection, see `SendConn::unique_name`
+    pub fn unique_name(&self) -> Option<&str> {
+        self.send.unique_name()
+    }
+
+    /// Split the connection into its receiving and sending half, so one thread can receive while another one sends
+    /// without locking the whole connection. `DuplexConn { recv, send }` puts them back together.
+    ///
+    /// Each half owns its own handle to the socket, which was duplicated when the connection was created, and its own
+    /// buffers. The `SendConn` keeps the serial counter, serials continue where the connection left off. The `RecvConn`
+    /// keeps the bytes of partially received messages and the received fds. Both halves are `Send`, so they can be
+    /// moved to other threads, but each of them needs `&mut` access, so one half can not be used from two threads
+    /// at once without a lock of its own.
+    ///
+    /// Nothing is lost by splitting: messages that are on their way are received by the `RecvConn` later. Replies are
+    /// not matched to calls though, that is done by `RpcConn`. Replies to calls sent by the `SendConn` arrive at the
+    /// `RecvConn` in any order between signals and calls and have to be matched by their `reply_serial()`.
+    /// If several threads wait for replies, they need a shared map from the serials of their calls to the waiting thread,
+    /// e.g. an `Arc<Mutex<HashMap<u32, mpsc::Sender<MarshalledMessage>>>>` that is filled before the call is sent.
+    ///
+    /// The send and receive timeouts of the socket are separate, so blocking operations with `Timeout::Duration` or
+    /// `Timeout::Deadline` do not affect each other. `Timeout::Nonblock` however switches the socket itself into
+    /// nonblocking mode for the duration of the operation, which the other half sees as well. Blocking operations of
+    /// the other half may fail with `Error::WouldBlock` meanwhile, so use `Timeout::Nonblock` on at most one half.
+    pub fn into_split(self) -> (RecvConn, SendConn) {
+        (self.recv, self.send)
+    }
+}
+
+/// The unique name from the reply to a Hello call, or `Error::HelloFailed` if the bus rejected the call
+pub(crate) fn parse_hello_reply(resp: &MarshalledMessage) -> super::Result<String> {
+    if let Some(err) = resp.as_error() {
+        return Err(super::Error::HelloFailed(err));
+    }
+    Ok(resp.body.parser().get::<String>()?)
+}
+
+impl AsFd for SendConn {
+    /// Reading or writing to the fd may result in undefined behavior
+    /// and break the `Conn`.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.stream.as_fd()
+    }
+}
+impl AsFd for RecvConn {
+    /// Reading or writing to the fd may result in undefined behavior
+    /// and break the `Conn`.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.stream.as_fd()
+    }
+}
+impl AsFd for DuplexConn {
+    /// Reading or writing to the fd may result in undefined behavior
+    /// and break the `Conn`.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.recv.stream.as_fd()
+    }
 }
 
 impl AsRawFd for SendConn {
     /// Reading or writing to the `RawFd` may result in undefined behavior
     /// and break the `Conn`.
     fn as_raw_fd(&self) -> RawFd {
-        self.stream.as_raw_fd()
+        self.stream.as_fd().as_raw_fd()
     }
 }
 impl AsRawFd for RecvConn {
     /// Reading or writing to the `RawFd` may result in undefined behavior
     /// and break the `Conn`.
     fn as_raw_fd(&self) -> RawFd {
-        self.stream.as_raw_fd()
+        self.stream.as_fd().as_raw_fd()
     }
 }
 impl AsRawFd for DuplexConn {
     /// Reading or writing to the `RawFd` may result in undefined behavior
     /// and break the `Conn`.
     fn as_raw_fd(&self) -> RawFd {
-        self.recv.stream.as_raw_fd()
+        self.recv.stream.as_fd().as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{call, signal, string_signal, to_bytes};
+    use std::io::Write;
+
+    fn recv_conn(stream: UnixStream) -> RecvConn {
+        RecvConn {
+            stream: Box::new(UnixTransport::new(stream)),
+            reassembler: MessageReassembler::new(),
+            fds_in: Vec::new(),
+        }
+    }
+
+    fn send_conn(stream: UnixStream) -> SendConn {
+        SendConn {
+            stream: Box::new(UnixTransport::new(stream)),
+            header_buf: Vec::new(),
+            writer: MessageWriter::new(),
+            unique_name: None,
+        }
+    }
+
+    #[test]
+    fn test_try_get_next_message() {
+        let (client, mut server) = UnixStream::pair().unwrap();
+        let mut recv = recv_conn(client);
+        assert!(matches!(
+            recv.try_get_next_message(),
+            Err(Error::WouldBlock)
+        ));
+
+        let bytes = to_bytes(&string_signal("resumable"), 1);
+
+        // the partial message survives until the rest arrives
+        server.write_all(&bytes[..10]).unwrap();
+        assert!(recv.try_get_next_message().unwrap().is_none());
+        server.write_all(&bytes[10..30]).unwrap();
+        assert!(recv.try_get_next_message().unwrap().is_none());
+        assert!(matches!(
+            recv.try_get_next_message(),
+            Err(Error::WouldBlock)
+        ));
+        server.write_all(&bytes[30..]).unwrap();
+        // two messages at once are returned one by one
+        server.write_all(&bytes).unwrap();
+        for _ in 0..2 {
+            let received = recv.try_get_next_message().unwrap().unwrap();
+            assert_eq!(received.dynheader.serial, Some(1));
+            assert_eq!(received.body.parser().get::<&str>().unwrap(), "resumable");
+        }
+        assert!(matches!(
+            recv.try_get_next_message(),
+            Err(Error::WouldBlock)
+        ));
+
+        drop(server);
+        assert!(matches!(
+            recv.try_get_next_message(),
+            Err(Error::ConnectionClosed)
+        ));
+    }
+
+    #[test]
+    fn test_send_all() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let mut send = send_conn(client);
+        let mut recv = recv_conn(server);
+
+        let fds = |n: usize| {
+            (0..n)
+                .map(|_| crate::wire::UnixFd::new(nix::unistd::dup(0).unwrap()))
+                .collect::<Vec<_>>()
+        };
+        let signal = |arg: &str, fds: Vec<crate::wire::UnixFd>| {
+            let mut msg = string_signal(arg);
+            for fd in fds {
+                msg.body.push_param(fd).unwrap();
+            }
+            msg
+        };
+
+        let mut with_serial = signal("fixed serial", vec![]);
+        with_serial.dynheader.serial = Some(100);
+        let msgs = [
+            signal("two fds", fds(2)),
+            with_serial,
+            signal("no fds", vec![]),
+            // more than fit into one sendmsg together with the next message
+            signal("many fds", fds(200)),
+            signal("more fds", fds(100)),
+        ];
+        let serials = send.send_all(&msgs).unwrap();
+        assert_eq!(serials, [1, 100, 2, 3, 4]);
+        assert_eq!(send.alloc_serial(), 5);
+
+        let expected = [
+            ("two fds", 2),
+            ("fixed serial", 0),
+            ("no fds", 0),
+            ("many fds", 200),
+            ("more fds", 100),
+        ];
+        for (serial, (arg, num_fds)) in serials.iter().zip(expected) {
+            let msg = recv.get_next_message(Timeout::Infinite).unwrap();
+            assert_eq!(msg.dynheader.serial, Some(*serial));
+            assert_eq!(msg.body.parser().get::<&str>().unwrap(), arg);
+            assert_eq!(msg.body.raw_fds.len(), num_fds);
+            assert!(msg.body.raw_fds.iter().all(|fd| fd.get_raw_fd().is_some()));
+        }
+        assert_eq!(recv.reassembler.buffered_fds(), 0);
+    }
+
+    #[test]
+    fn test_clone_with_new_serial() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let mut send = send_conn(client);
+        let mut recv = recv_conn(server);
+
+        let mut msg = call("Retry");
+        msg.body.push_param("again").unwrap();
+        msg.body
+            .push_param(crate::wire::UnixFd::new(nix::unistd::dup(0).unwrap()))
+            .unwrap();
+        msg.dynheader.serial = Some(send.alloc_serial());
+
+        let retry = send.clone_with_new_serial(&msg);
+        assert_eq!(retry.dynheader.serial, Some(2));
+        assert_eq!(msg.dynheader.serial, Some(1));
+        assert_eq!(retry.get_buf(), msg.get_buf());
+        assert_eq!(retry.get_sig(), "sh");
+
+        send.send_message_write_all(&msg).unwrap();
+        send.send_message_write_all(&retry).unwrap();
+        for serial in [1, 2] {
+            let received = recv.get_next_message(Timeout::Infinite).unwrap();
+            assert_eq!(received.dynheader.serial, Some(serial));
+            assert_eq!(received.dynheader.member.as_deref(), Some("Retry"));
+            assert_eq!(received.body.parser().get::<&str>().unwrap(), "again");
+            assert_eq!(received.body.raw_fds.len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_serial_wrapping() {
+        let (client, _server) = UnixStream::pair().unwrap();
+        let mut send = send_conn(client);
+        assert_eq!(send.peek_serial(), 1);
+        assert_eq!(send.alloc_serial(), 1);
+        assert_eq!(send.peek_serial(), 2);
+        assert_eq!(send.alloc_serial(), 2);
+
+        send.writer.serial_counter = u32::MAX - 1;
+        let serials = (0..4).map(|_| send.alloc_serial()).collect::<Vec<_>>();
+        assert_eq!(serials, [u32::MAX - 1, u32::MAX, 1, 2]);
+        assert_eq!(send.peek_serial(), 3);
+
+        send.writer.serial_counter = u32::MAX;
+        let msg = signal();
+        assert_eq!(send.send_message_write_all(&msg).unwrap(), u32::MAX);
+        assert_eq!(send.send_message_write_all(&msg).unwrap(), 1);
     }
 }
@@ -0,0 +1,104 @@
+//! Receive all traffic on the bus, like `dbus-monitor` does
+//!
+//! ```rust,no_run
+//! use rustbus::connection::{get_session_bus_path, Timeout};
+//! use rustbus::{DuplexConn, MatchRule, MessageType};
+//!
+//! let mut conn = DuplexConn::connect_to_bus(get_session_bus_path().unwrap(), true).unwrap();
+//! conn.send_hello(Timeout::Infinite).unwrap();
+//!
+//! let rule = MatchRule::new().msg_type(MessageType::Signal);
+//! let mut monitor = conn.become_monitor(&[rule], Timeout::Infinite).unwrap();
+//! loop {
+//!     let msg = monitor.get_next_message(Timeout::Infinite).unwrap();
+//!     println!("{:?} {:?}.{:?}", msg.typ, msg.interface(), msg.member());
+//! }
+//! ```
+
+use std::os::fd::{AsFd, BorrowedFd};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time;
+
+use super::ll_conn::{DuplexConn, RecvConn};
+use super::{calc_timeout_left, Error, Result, Timeout};
+use crate::message_builder::MarshalledMessage;
+use crate::MatchRule;
+
+impl DuplexConn {
+    /// Turn this connection into a monitor with `org.freedesktop.DBus.Monitoring.BecomeMonitor`. The bus then sends
+    /// a copy of every message matching one of `match_rules` to it, and all messages if there are no rules.
+    ///
+    /// The hello message must have been sent before. Becoming a monitor needs the permission of the bus, usually that
+    /// means running as root or as the same user as the session bus. Otherwise the bus refuses and this returns
+    /// `Error::ErrorResponse` with its error, typically `org.freedesktop.DBus.Error.AccessDenied`.
+    pub fn become_monitor(
+        mut self,
+        match_rules: &[MatchRule],
+        timeout: Timeout,
+    ) -> Result<MonitorConn> {
+        let start_time = time::Instant::now();
+
+        let rules = match_rules
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let call = crate::standard_messages::become_monitor(&rules);
+        let serial = self
+            .send
+            .send_message(&call)?
+            .write(calc_timeout_left(&start_time, timeout)?)
+            .map_err(|(ctx, e)| {
+                ctx.force_finish();
+                e
+            })?;
+
+        loop {
+            let msg = self
+                .recv
+                .get_next_message(calc_timeout_left(&start_time, timeout)?)?;
+            if msg.dynheader.response_serial != Some(serial) {
+                // signals of the bus like NameAcquired may arrive before the reply
+                continue;
+            }
+            return match msg.as_error() {
+                Some(err) => Err(Error::ErrorResponse(err)),
+                None => Ok(MonitorConn { recv: self.recv }),
+            };
+        }
+    }
+}
+
+/// A connection that only receives the messages the bus copies to it. Monitors must not send anything, the
+/// bus closes the connection if they do, so there is no sending half.
+pub struct MonitorConn {
+    recv: RecvConn,
+}
+
+impl MonitorConn {
+    /// Read whatever is available without blocking and return the next message if a whole one has arrived.
+    /// See `RecvConn::try_get_next_message` for what `Ok(None)` and `Error::WouldBlock` mean.
+    pub fn try_get_next_message(&mut self) -> Result<Option<MarshalledMessage>> {
+        self.recv.try_get_next_message()
+    }
+
+    /// Wait for the next message that was copied to this monitor
+    pub fn get_next_message(&mut self, timeout: Timeout) -> Result<MarshalledMessage> {
+        self.recv.get_next_message(timeout)
+    }
+}
+
+impl AsFd for MonitorConn {
+    /// Reading or writing to the fd may result in undefined behavior
+    /// and break the `Conn`.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.recv.as_fd()
+    }
+}
+
+impl AsRawFd for MonitorConn {
+    /// Reading or writing to the `RawFd` may result in undefined behavior
+    /// and break the `Conn`.
+    fn as_raw_fd(&self) -> RawFd {
+        self.recv.as_raw_fd()
+    }
+}
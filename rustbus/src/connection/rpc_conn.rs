@@ -3,8 +3,8 @@
 
 use super::ll_conn::DuplexConn;
 use super::*;
-use crate::message_builder::{MarshalledMessage, MessageType};
-use std::collections::{HashMap, VecDeque};
+use crate::message_builder::{FromReply, MarshalledMessage, MessageType, MethodArgs};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 /// Convenience wrapper around the lowlevel connection
 /// ```rust,no_run
@@ -33,8 +33,18 @@ pub struct RpcConn {
     signals: VecDeque<MarshalledMessage>,
     calls: VecDeque<MarshalledMessage>,
     responses: HashMap<u32, MarshalledMessage>,
+    /// serials of calls sent with send_message that still wait for a response
+    pub(crate) pending: HashSet<u32>,
+    /// responses that did not belong to any pending call
+    unmatched: VecDeque<MarshalledMessage>,
+    max_unmatched: usize,
+    /// serials of calls that timed out in send_and_wait_response, oldest first. Late replies to these are dropped.
+    pub(crate) abandoned: VecDeque<u32>,
     conn: DuplexConn,
     filter: MessageFilter,
+    callbacks: Vec<MessageCallback>,
+    default_timeout: Timeout,
+    interactive_auth_timeout: Timeout,
 }
 
 /// Filter out messages you dont want in your RpcConn.
@@ -73,14 +83,61 @@ pub struct RpcConn {
 /// ```
 pub type MessageFilter = Box<dyn Fn(&MarshalledMessage) -> bool + Sync + Send>;
 
+/// What should happen with a message after a callback registered with `RpcConn::add_filter` has seen it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterAction {
+    /// Pass the message on to the next callback and eventually into the queues of the RpcConn
+    Pass,
+    /// The message has been dealt with and is dropped. No further callbacks are run.
+    Consumed,
+}
+
+/// Callback that is run on received messages, see `RpcConn::add_filter`
+pub type MessageCallback = Box<dyn FnMut(&MarshalledMessage) -> FilterAction + Sync + Send>;
+
+/// How many unmatched responses an `RpcConn` keeps by default, see `RpcConn::set_max_unmatched_responses`
+pub const DEFAULT_MAX_UNMATCHED_RESPONSES: usize = 64;
+
+/// How many calls that timed out an `RpcConn` remembers to drop their late replies. Peers that never answer would
+/// otherwise grow the list forever, the oldest ones are forgotten first.
+pub(crate) const MAX_ABANDONED_CALLS: usize = 1024;
+
+/// The error a service answers with if a call needs interactive authorization, like a polkit password prompt, but was
+/// sent without `MessageFlags::ALLOW_INTERACTIVE_AUTHORIZATION`
+pub const INTERACTIVE_AUTHORIZATION_REQUIRED: &str =
+    "org.freedesktop.DBus.Error.InteractiveAuthorizationRequired";
+
+/// Whether `RpcConn::call_method_with_auth` lets the service prompt the user for authorization
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InteractiveAuth {
+    /// Never allow a prompt, like `call_method`. A service that needs one answers with
+    /// `INTERACTIVE_AUTHORIZATION_REQUIRED`, which is returned as `Error::ErrorResponse`.
+    Deny,
+    /// Send the call with `MessageFlags::ALLOW_INTERACTIVE_AUTHORIZATION`
+    Allow,
+    /// Send the call without the flag first and repeat it with the flag if the service answers with
+    /// `INTERACTIVE_AUTHORIZATION_REQUIRED`. Like polkit clients do, the user is only prompted if the call can not
+    /// succeed without it.
+    Retry,
+}
+
 impl RpcConn {
+    /// Wrap a connection as it is. No Hello is sent, so this is the way to skip it, e.g. for peer to peer connections
+    /// or if `DuplexConn::send_hello` was called already. The other constructors send Hello on their own.
     pub fn new(conn: DuplexConn) -> Self {
         RpcConn {
             signals: VecDeque::new(),
             calls: VecDeque::new(),
             responses: HashMap::new(),
+            pending: HashSet::new(),
+            unmatched: VecDeque::new(),
+            max_unmatched: DEFAULT_MAX_UNMATCHED_RESPONSES,
+            abandoned: VecDeque::new(),
             conn,
             filter: Box::new(|_| true),
+            callbacks: Vec::new(),
+            default_timeout: Timeout::Infinite,
+            interactive_auth_timeout: Timeout::Infinite,
         }
     }
     pub fn conn(&self) -> &DuplexConn {
@@ -90,14 +147,27 @@ impl RpcConn {
         &mut self.conn
     }
 
-    /// get the next new serial
+    /// get the next new serial, see `SendConn::alloc_serial`
     pub fn alloc_serial(&mut self) -> u32 {
         self.conn.send.alloc_serial()
     }
 
+    /// The serial the next message without a serial will get, see `SendConn::peek_serial`
+    pub fn peek_serial(&self) -> u32 {
+        self.conn.send.peek_serial()
+    }
+
+    /// The unique name the bus assigned to this connection, see `SendConn::unique_name`
+    pub fn unique_name(&self) -> Option<&str> {
+        self.conn.unique_name()
+    }
+
+    /// Connect to the session bus, trying each address in $DBUS_SESSION_BUS_ADDRESS in turn. Like for `system_conn`
+    /// and `connect_to_path` the Hello call is made before the connection is returned, if the bus rejects it
+    /// `Error::HelloFailed` is returned.
     pub fn session_conn(timeout: Timeout) -> Result<Self> {
-        let session_path = get_session_bus_path()?;
-        Self::connect_to_path(session_path, timeout)
+        let con = DuplexConn::connect_to_any_bus(&get_session_bus_addresses()?, true)?;
+        Self::with_hello(con, timeout)
     }
 
     pub fn system_conn(timeout: Timeout) -> Result<Self> {
@@ -105,37 +175,476 @@ impl RpcConn {
         Self::connect_to_path(session_path, timeout)
     }
 
-    pub fn connect_to_path(path: UnixAddr, timeout: Timeout) -> Result<Self> {
+    pub fn connect_to_path<A: Into<BusAddress>>(path: A, timeout: Timeout) -> Result<Self> {
         let con = DuplexConn::connect_to_bus(path, true)?;
+        Self::with_hello(con, timeout)
+    }
+
+    fn with_hello(con: DuplexConn, timeout: Timeout) -> Result<Self> {
         let mut con = Self::new(con);
+        con.send_hello(timeout)?;
+        Ok(con)
+    }
 
+    /// Send the obligatory Hello call and return the unique name the bus assigned, like `DuplexConn::send_hello`.
+    /// Only needed for connections made with `RpcConn::new`.
+    pub fn send_hello(&mut self, timeout: Timeout) -> Result<String> {
+        let start_time = time::Instant::now();
         let mut hello = crate::standard_messages::hello();
-        let serial = con
+        let serial = self
             .send_message(&mut hello)?
             .write(timeout)
             .map_err(super::ll_conn::force_finish_on_error)?;
 
-        con.wait_response(serial, timeout)?;
-        Ok(con)
+        let resp = self.wait_response(serial, calc_timeout_left(&start_time, timeout)?)?;
+        let unique_name = super::ll_conn::parse_hello_reply(&resp)?;
+        self.conn.send.set_unique_name(unique_name.clone());
+        Ok(unique_name)
     }
 
     pub fn set_filter(&mut self, filter: MessageFilter) {
         self.filter = filter;
     }
 
+    /// Register a callback that is run on every received message, in the order the callbacks were added.
+    /// If a callback returns `FilterAction::Consumed` the message is dropped and the remaining callbacks are not run.
+    ///
+    /// Responses to calls that were sent with `send_message` are matched to their call first and never run through
+    /// the callbacks, so `wait_response` always gets them. Callbacks run before the filter set with `set_filter`.
+    /// Consumed calls are not answered with an UnknownMethod error, the callback is responsible for replying.
+    /// ```rust,no_run
+    /// use rustbus::{connection::{Timeout, rpc_conn::FilterAction}, RpcConn};
+    ///
+    /// let mut rpc_con = RpcConn::session_conn(Timeout::Infinite).unwrap();
+    /// rpc_con.add_filter(|msg| {
+    ///     if msg.dynheader.member.as_deref() == Some("PropertiesChanged") {
+    ///         println!("Properties changed on {:?}", msg.dynheader.object);
+    ///         FilterAction::Consumed
+    ///     } else {
+    ///         FilterAction::Pass
+    ///     }
+    /// });
+    /// ```
+    pub fn add_filter<F>(&mut self, callback: F)
+    where
+        F: FnMut(&MarshalledMessage) -> FilterAction + Sync + Send + 'static,
+    {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Run the callbacks on the message, returns true if one of them consumed it
+    fn run_callbacks(&mut self, msg: &MarshalledMessage) -> bool {
+        if let Some(serial) = msg.dynheader.response_serial {
+            if self.pending.contains(&serial) || self.abandoned.contains(&serial) {
+                return false;
+            }
+        }
+        self.callbacks
+            .iter_mut()
+            .any(|callback| callback(msg) == FilterAction::Consumed)
+    }
+
+    /// The timeout used by `send_and_wait_response` if no timeout is given for the call. Defaults to `Timeout::Infinite`.
+    pub fn set_default_timeout(&mut self, timeout: Timeout) {
+        self.default_timeout = timeout;
+    }
+
+    /// How many responses that did not match a pending call are kept for `try_get_unmatched_response`. Once the limit is
+    /// reached the oldest one is dropped for each new one, so a peer sending stray responses can not grow the queue
+    /// without bound. Defaults to `DEFAULT_MAX_UNMATCHED_RESPONSES`, a limit of 0 drops all unmatched responses.
+    pub fn set_max_unmatched_responses(&mut self, limit: usize) {
+        self.max_unmatched = limit;
+        while self.unmatched.len() > limit {
+            self.unmatched.pop_front();
+        }
+    }
+
+    pub fn max_unmatched_responses(&self) -> usize {
+        self.max_unmatched
+    }
+
+    pub fn default_timeout(&self) -> Timeout {
+        self.default_timeout
+    }
+
+    /// The timeout used by `send_and_wait_response` instead of the default timeout if no timeout is given for a call with
+    /// `MessageFlags::ALLOW_INTERACTIVE_AUTHORIZATION`. Defaults to `Timeout::Infinite`.
+    ///
+    /// While the service waits for the user to answer an authorization prompt, the reply is delayed for as long as the
+    /// user takes. Default timeouts are usually chosen for calls that answer right away and would fail with
+    /// `Error::TimedOut` under an open prompt, so calls that may prompt get their own timeout. It is used for the whole
+    /// call including the prompt and should be generous. A timeout passed to `send_and_wait_response` explicitly is
+    /// used as it is, even for calls with the flag.
+    pub fn set_interactive_auth_timeout(&mut self, timeout: Timeout) {
+        self.interactive_auth_timeout = timeout;
+    }
+
+    pub fn interactive_auth_timeout(&self) -> Timeout {
+        self.interactive_auth_timeout
+    }
+
+    /// Send a call and wait for its response. If `timeout` is None the default timeout of the connection is used, or
+    /// the interactive auth timeout if the call has `MessageFlags::ALLOW_INTERACTIVE_AUTHORIZATION` set.
+    /// The timeout covers both sending the call and waiting for the response.
+    ///
+    /// If no response arrives in time `Error::TimedOut` is returned and the call is forgotten. A response that
+    /// arrives later is dropped instead of being returned by `try_get_response`/`wait_response`.
+    ///
+    /// Calls with `MessageFlags::NO_REPLY_EXPECTED` are not sent and fail with `Error::NoReplyExpected`, since
+    /// waiting for them would never end. Use `send_no_reply` for them.
+    pub fn send_and_wait_response(
+        &mut self,
+        msg: &mut MarshalledMessage,
+        timeout: Option<Timeout>,
+    ) -> Result<MarshalledMessage> {
+        if msg
+            .message_flags()
+            .contains(crate::message_builder::MessageFlags::NO_REPLY_EXPECTED)
+        {
+            return Err(Error::NoReplyExpected);
+        }
+        let default_timeout = if msg
+            .message_flags()
+            .contains(crate::message_builder::MessageFlags::ALLOW_INTERACTIVE_AUTHORIZATION)
+        {
+            self.interactive_auth_timeout
+        } else {
+            self.default_timeout
+        };
+        let timeout = match timeout.unwrap_or(default_timeout) {
+            Timeout::Duration(d) => Timeout::Deadline(time::Instant::now() + d),
+            other => other,
+        };
+        let ctx = self.send_message(msg)?;
+        let serial = ctx.serial();
+        if let Err(e) = ctx
+            .write(timeout)
+            .map_err(super::ll_conn::force_finish_on_error)
+        {
+            self.pending.remove(&serial);
+            return Err(e);
+        }
+
+        match self.wait_response(serial, timeout) {
+            Err(Error::TimedOut) => {
+                self.abandon(serial);
+                Err(Error::TimedOut)
+            }
+            other => other,
+        }
+    }
+
+    /// Call a method and decode its return value. `args` is `()` or a tuple with one element per argument, the type of
+    /// the return value is picked by the caller:
+    ///
+    /// ```rust,no_run
+    /// # use rustbus::{connection::Timeout, RpcConn};
+    /// let mut rpc_con = RpcConn::session_conn(Timeout::Infinite).unwrap();
+    /// let owner = rpc_con.call_method::<String, _>(
+    ///     "org.freedesktop.DBus",
+    ///     "/org/freedesktop/DBus",
+    ///     "org.freedesktop.DBus",
+    ///     "GetNameOwner",
+    ///     ("org.freedesktop.DBus",),
+    /// ).unwrap();
+    /// ```
+    ///
+    /// The call is sent with `send_and_wait_response` using the default timeout of the connection. An error reply is
+    /// returned as `Error::ErrorResponse`, a reply that does not start with a value of the requested type as
+    /// `Error::UnmarshalError`.
+    pub fn call_method<R: FromReply, A: MethodArgs>(
+        &mut self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        args: A,
+    ) -> Result<R> {
+        self.call_method_with_auth(
+            destination,
+            path,
+            interface,
+            member,
+            args,
+            InteractiveAuth::Deny,
+        )
+    }
+
+    /// Like `call_method` for methods that may ask the user for authorization, e.g. through polkit. `auth` decides
+    /// whether `MessageFlags::ALLOW_INTERACTIVE_AUTHORIZATION` is set on the call.
+    ///
+    /// Calls with the flag wait for the reply with the interactive auth timeout of the connection instead of the
+    /// default timeout, so an open prompt does not make them fail with `Error::TimedOut`. See
+    /// `set_interactive_auth_timeout`. With `InteractiveAuth::Retry` the first attempt uses the default timeout and
+    /// only the repeated call uses the interactive auth timeout.
+    ///
+    /// ```rust,no_run
+    /// # use rustbus::connection::{rpc_conn::InteractiveAuth, Timeout};
+    /// # use rustbus::RpcConn;
+    /// let mut rpc_con = RpcConn::system_conn(Timeout::Infinite).unwrap();
+    /// rpc_con.set_default_timeout(Timeout::Duration(std::time::Duration::from_secs(25)));
+    /// // the user gets five minutes to enter their password
+    /// rpc_con.set_interactive_auth_timeout(Timeout::Duration(std::time::Duration::from_secs(300)));
+    /// rpc_con.call_method_with_auth::<(), _>(
+    ///     "org.freedesktop.hostname1",
+    ///     "/org/freedesktop/hostname1",
+    ///     "org.freedesktop.hostname1",
+    ///     "SetStaticHostname",
+    ///     ("killing-spark", true),
+    ///     InteractiveAuth::Retry,
+    /// ).unwrap();
+    /// ```
+    pub fn call_method_with_auth<R: FromReply, A: MethodArgs>(
+        &mut self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        args: A,
+        auth: InteractiveAuth,
+    ) -> Result<R> {
+        let mut call = crate::MessageBuilder::new()
+            .call(member)
+            .with_interface(interface)
+            .on(path)
+            .at(destination)
+            .build();
+        call.body.push_args(args)?;
+        if auth == InteractiveAuth::Allow {
+            crate::message_builder::HeaderFlags::AllowInteractiveAuthorization.set(&mut call.flags);
+        }
+        let mut reply = self.send_and_wait_response(&mut call, None)?;
+        if auth == InteractiveAuth::Retry
+            && reply.dynheader.error_name.as_deref() == Some(INTERACTIVE_AUTHORIZATION_REQUIRED)
+        {
+            // the call has no serial of its own, sending it again allocates a new one
+            crate::message_builder::HeaderFlags::AllowInteractiveAuthorization.set(&mut call.flags);
+            reply = self.send_and_wait_response(&mut call, None)?;
+        }
+        if let Some(err) = reply.as_error() {
+            return Err(Error::ErrorResponse(err));
+        }
+        Ok(R::from_reply(&reply.body)?)
+    }
+
+    /// Broadcast a signal to every connection with a matching rule. `args` is `()` or a tuple with one element per
+    /// argument, the signature of the signal is derived from it. Returns the serial of the signal once it is sent
+    /// using the default timeout of the connection.
+    ///
+    /// ```rust,no_run
+    /// # use rustbus::{connection::Timeout, RpcConn};
+    /// let mut rpc_con = RpcConn::session_conn(Timeout::Infinite).unwrap();
+    /// rpc_con.emit_signal("/io/killing/spark", "io.killing.spark", "Changed", ("count", 42u32)).unwrap();
+    /// ```
+    pub fn emit_signal<A: MethodArgs>(
+        &mut self,
+        path: &str,
+        interface: &str,
+        member: &str,
+        args: A,
+    ) -> Result<u32> {
+        self.send_signal(None, path, interface, member, args)
+    }
+
+    /// Like `emit_signal` but the signal is only delivered to `destination`
+    pub fn emit_signal_to<A: MethodArgs>(
+        &mut self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        args: A,
+    ) -> Result<u32> {
+        self.send_signal(Some(destination), path, interface, member, args)
+    }
+
+    fn send_signal<A: MethodArgs>(
+        &mut self,
+        destination: Option<&str>,
+        path: &str,
+        interface: &str,
+        member: &str,
+        args: A,
+    ) -> Result<u32> {
+        let mut signal = crate::MessageBuilder::new().signal(interface, member, path);
+        if let Some(destination) = destination {
+            signal = signal.to(destination);
+        }
+        let mut signal = signal.build();
+        signal.body.push_args(args)?;
+        let timeout = self.default_timeout;
+        let serial = self
+            .send_message(&mut signal)?
+            .write(timeout)
+            .map_err(super::ll_conn::force_finish_on_error)?;
+        Ok(serial)
+    }
+
+    /// Send a call without waiting for anything in return. `MessageFlags::NO_REPLY_EXPECTED` is set on the call, so
+    /// the receiver should not answer. No response is expected for it, if one arrives anyway it is returned by
+    /// `try_get_unmatched_response`. Returns the serial of the call once it is sent.
+    /// If `timeout` is None the default timeout of the connection is used.
+    pub fn send_no_reply(
+        &mut self,
+        msg: &mut MarshalledMessage,
+        timeout: Option<Timeout>,
+    ) -> Result<u32> {
+        crate::message_builder::HeaderFlags::NoReplyExpected.set(&mut msg.flags);
+        let timeout = timeout.unwrap_or(self.default_timeout);
+        self.send_message(msg)?
+            .write(timeout)
+            .map_err(super::ll_conn::force_finish_on_error)
+    }
+
+    /// The unique name of the current owner of `name`, or `None` if nobody owns it. Other error replies are returned as
+    /// `Error::ErrorResponse`.
+    pub fn get_name_owner(&mut self, name: &str) -> Result<Option<String>> {
+        let mut call = crate::standard_messages::get_name_owner(name);
+        let reply = self.send_and_wait_response(&mut call, None)?;
+        match reply.as_error() {
+            Some(err) if err.name == crate::standard_messages::NAME_HAS_NO_OWNER => Ok(None),
+            Some(err) => Err(Error::ErrorResponse(err)),
+            None => Ok(Some(reply.body.parser().get()?)),
+        }
+    }
+
+    /// Run `handler` on the `NameOwnerChanged` signals for `name`, or for all names if it is `None`. The match rule is
+    /// added to the bus and a route to `router`, dispatch the received signals with it as shown in `SignalRouter`.
+    /// Signals that can not be decoded are passed on without running the handler.
+    ///
+    /// ```rust,no_run
+    /// use rustbus::{connection::{Timeout, rpc_conn::FilterAction}, RpcConn, SignalRouter};
+    ///
+    /// let mut rpc_con = RpcConn::session_conn(Timeout::Infinite).unwrap();
+    /// let mut router = SignalRouter::new();
+    /// rpc_con
+    ///     .watch_name_owner(&mut router, Some("org.mpris.MediaPlayer2.vlc"), |changed| {
+    ///         match changed.new_owner {
+    ///             Some(owner) => println!("{} is now owned by {}", changed.name, owner),
+    ///             None => println!("{} is gone", changed.name),
+    ///         }
+    ///         FilterAction::Consumed
+    ///     })
+    ///     .unwrap();
+    /// rpc_con.add_filter(move |msg| router.dispatch(msg));
+    /// ```
+    pub fn watch_name_owner<F>(
+        &mut self,
+        router: &mut crate::SignalRouter,
+        name: Option<&str>,
+        mut handler: F,
+    ) -> Result<crate::signal_router::RouteId>
+    where
+        F: FnMut(crate::standard_messages::NameOwnerChanged) -> FilterAction
+            + Sync
+            + Send
+            + 'static,
+    {
+        let rule = crate::standard_messages::name_owner_changed_rule(name);
+        self.add_match(&rule)?;
+        Ok(router.add_route(rule, move |msg| {
+            match crate::standard_messages::NameOwnerChanged::from_signal(msg) {
+                Ok(changed) => handler(changed),
+                Err(_) => FilterAction::Pass,
+            }
+        }))
+    }
+
+    /// Ask the bus to route messages matching `rule` to this connection. Waits for the bus to acknowledge the rule
+    /// using the default timeout. If the bus rejects the rule its error is returned as `Error::ErrorResponse`.
+    pub fn add_match(&mut self, rule: &crate::MatchRule) -> Result<()> {
+        let mut msg = crate::standard_messages::add_match(&rule.to_string());
+        self.call_bus(&mut msg)
+    }
+
+    /// Remove a rule that was previously added with `add_match`. The bus returns an error if the rule is unknown.
+    pub fn remove_match(&mut self, rule: &crate::MatchRule) -> Result<()> {
+        let mut msg = crate::standard_messages::remove_match(&rule.to_string());
+        self.call_bus(&mut msg)
+    }
+
+    fn call_bus(&mut self, msg: &mut MarshalledMessage) -> Result<()> {
+        let resp = self.send_and_wait_response(msg, None)?;
+        match resp.as_error() {
+            Some(err) => Err(Error::ErrorResponse(err)),
+            None => Ok(()),
+        }
+    }
+
+    /// Responses are matched to the pending calls by their response serial, so they can arrive in any order.
+    fn insert_response(&mut self, msg: MarshalledMessage) {
+        let Some(serial) = msg.dynheader.response_serial else {
+            self.push_unmatched(msg);
+            return;
+        };
+        if let Some(idx) = self.abandoned.iter().position(|s| *s == serial) {
+            // the caller gave up waiting for this response
+            self.abandoned.remove(idx);
+        } else if self.pending.remove(&serial) {
+            self.responses.insert(serial, msg);
+        } else {
+            self.push_unmatched(msg);
+        }
+    }
+
+    /// Drop the response to `serial` when it arrives, forgetting the oldest abandoned call if there are too many
+    pub(crate) fn abandon(&mut self, serial: u32) {
+        if self.abandoned.len() >= MAX_ABANDONED_CALLS {
+            self.abandoned.pop_front();
+        }
+        self.abandoned.push_back(serial);
+    }
+
+    /// Queue an unmatched response, dropping the oldest ones if the queue is full
+    fn push_unmatched(&mut self, msg: MarshalledMessage) {
+        if self.max_unmatched == 0 {
+            return;
+        }
+        while self.unmatched.len() >= self.max_unmatched {
+            self.unmatched.pop_front();
+        }
+        self.unmatched.push_back(msg);
+    }
+
     /// Return a response if one is there but dont block
     pub fn try_get_response(&mut self, serial: u32) -> Option<MarshalledMessage> {
-        self.responses.remove(&serial)
+        if let Some(msg) = self.responses.remove(&serial) {
+            return Some(msg);
+        }
+        // the call might have been sent without going through RpcConn::send_message
+        let idx = self
+            .unmatched
+            .iter()
+            .position(|msg| msg.dynheader.response_serial == Some(serial))?;
+        self.unmatched.remove(idx)
+    }
+
+    /// Return a response that did not match any call sent with `send_message`, if one is there. This includes responses
+    /// without a response serial and responses to calls sent over the underlying connection directly. Only the newest
+    /// ones are kept, see `set_max_unmatched_responses`.
+    pub fn try_get_unmatched_response(&mut self) -> Option<MarshalledMessage> {
+        self.unmatched.pop_front()
     }
 
     /// Return a response if one is there or block until it arrives
+    ///
+    /// If this fails, e.g. with `Error::TimedOut`, the call is no longer tracked as pending. A response that arrives
+    /// later is kept like an unmatched one, so `try_get_response` can still find it as long as it is not pushed out of
+    /// that queue. Polling with `Timeout::Nonblock` keeps the call pending.
     pub fn wait_response(&mut self, serial: u32, timeout: Timeout) -> Result<MarshalledMessage> {
         let start_time = time::Instant::now();
         loop {
             if let Some(msg) = self.try_get_response(serial) {
                 return Ok(msg);
             }
-            self.refill_once(calc_timeout_left(&start_time, timeout)?)?;
+            if let Err(e) = calc_timeout_left(&start_time, timeout)
+                .and_then(|timeout| self.refill_once(timeout).map(|_| ()))
+            {
+                if !matches!(timeout, Timeout::Nonblock) {
+                    self.pending.remove(&serial);
+                }
+                return Err(e);
+            }
         }
     }
 
@@ -171,15 +680,27 @@ impl RpcConn {
         }
     }
 
-    /// Send a message to the bus
+    /// Send a message to the bus. If the message is a call that expects a reply, the response will be kept
+    /// for `wait_response`/`try_get_response`, regardless of the order in which responses arrive.
     pub fn send_message<'a>(
         &'a mut self,
         msg: &'a mut crate::message_builder::MarshalledMessage,
     ) -> Result<super::ll_conn::SendMessageContext<'a>> {
-        self.conn.send.send_message(msg)
+        let ctx = self.conn.send.send_message(msg)?;
+        if msg.typ == MessageType::Call
+            && !msg
+                .message_flags()
+                .contains(crate::message_builder::MessageFlags::NO_REPLY_EXPECTED)
+        {
+            self.pending.insert(ctx.serial());
+        }
+        Ok(ctx)
     }
 
     fn insert_message_or_send_error(&mut self, msg: MarshalledMessage) -> Result<()> {
+        if self.run_callbacks(&msg) {
+            return Ok(());
+        }
         if self.filter.as_ref()(&msg) {
             match msg.typ {
                 MessageType::Call => {
@@ -187,12 +708,10 @@ impl RpcConn {
                 }
                 MessageType::Invalid => return Err(Error::UnexpectedMessageTypeReceived),
                 MessageType::Error => {
-                    self.responses
-                        .insert(msg.dynheader.response_serial.unwrap(), msg);
+                    self.insert_response(msg);
                 }
                 MessageType::Reply => {
-                    self.responses
-                        .insert(msg.dynheader.response_serial.unwrap(), msg);
+                    self.insert_response(msg);
                 }
                 MessageType::Signal => {
                     self.signals.push_back(msg);
@@ -270,6 +789,9 @@ impl RpcConn {
                 Err(e) => return Err(e),
                 Ok(m) => m,
             };
+            if self.run_callbacks(&msg) {
+                continue;
+            }
             if self.filter.as_ref()(&msg) {
                 match msg.typ {
                     MessageType::Call => {
@@ -277,12 +799,10 @@ impl RpcConn {
                     }
                     MessageType::Invalid => return Err(Error::UnexpectedMessageTypeReceived),
                     MessageType::Error => {
-                        self.responses
-                            .insert(msg.dynheader.response_serial.unwrap(), msg);
+                        self.insert_response(msg);
                     }
                     MessageType::Reply => {
-                        self.responses
-                            .insert(msg.dynheader.response_serial.unwrap(), msg);
+                        self.insert_response(msg);
                     }
                     MessageType::Signal => {
                         self.signals.push_back(msg);
@@ -0,0 +1,185 @@
+//! The byte streams a connection can run over
+//!
+//! Authentication and message framing only need a `Transport`, so `DuplexConn` works the same over unix sockets
+//! and TCP. Only unix sockets can pass unix fds along with the message bytes.
+
+use std::fmt;
+use std::io::{self, IoSlice, IoSliceMut, Read, Write};
+use std::net::TcpStream;
+use std::os::fd::{AsFd, BorrowedFd};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use nix::cmsg_space;
+use nix::sys::socket::{
+    recvmsg, sendmsg, ControlMessage, ControlMessageOwned, MsgFlags, SockaddrStorage,
+};
+
+/// How many fds can be passed in one `sendmsg` call at most. This is `SCM_MAX_FD` of the linux kernel.
+pub const MAX_FDS_PER_SYSCALL: usize = crate::wire::marshal::MAX_UNIX_FDS;
+
+/// A connected stream socket that messages can be sent and received over
+pub trait Transport: Read + Write + AsFd + fmt::Debug + Send {
+    /// Whether unix fds can be sent and received over this transport
+    fn supports_unix_fds(&self) -> bool;
+    /// Create a second handle to the same socket, used to split the connection into a sending and a receiving half
+    fn try_clone_transport(&self) -> io::Result<Box<dyn Transport>>;
+
+    fn read_timeout(&self) -> io::Result<Option<Duration>>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+    fn write_timeout(&self) -> io::Result<Option<Duration>>;
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+
+    /// Send (parts of) the buffers and pass the fds along with them. Returns how many bytes were sent.
+    ///
+    /// Transports that do not support unix fds return an error with `io::ErrorKind::Unsupported` if `fds` is not empty.
+    fn send_with_fds(&mut self, bufs: &[IoSlice<'_>], fds: &[RawFd]) -> io::Result<usize>;
+    /// Read into `buf` and append the fds that were received to `fds`. Returns how many bytes were read,
+    /// zero means the other side closed the connection.
+    fn recv_with_fds(&mut self, buf: &mut [u8], fds: &mut Vec<RawFd>) -> io::Result<usize>;
+}
+
+/// A unix socket. The buffer for the control messages that carry the fds is kept around, so receiving does not
+/// allocate it on every read.
+#[derive(Debug)]
+pub struct UnixTransport {
+    stream: UnixStream,
+    cmsg_buf: Vec<u8>,
+}
+
+impl UnixTransport {
+    pub fn new(stream: UnixStream) -> Self {
+        Self {
+            stream,
+            cmsg_buf: cmsg_space!([RawFd; MAX_FDS_PER_SYSCALL]),
+        }
+    }
+    pub fn stream(&self) -> &UnixStream {
+        &self.stream
+    }
+}
+
+impl From<UnixStream> for UnixTransport {
+    fn from(stream: UnixStream) -> Self {
+        Self::new(stream)
+    }
+}
+
+impl Read for UnixTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.read(buf)
+    }
+}
+
+impl Write for UnixTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl AsFd for UnixTransport {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.stream.as_fd()
+    }
+}
+
+impl Transport for UnixTransport {
+    fn supports_unix_fds(&self) -> bool {
+        true
+    }
+    fn try_clone_transport(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(Self::new(self.stream.try_clone()?)))
+    }
+
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        self.stream.read_timeout()
+    }
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+    fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        self.stream.write_timeout()
+    }
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_write_timeout(timeout)
+    }
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.stream.set_nonblocking(nonblocking)
+    }
+
+    fn send_with_fds(&mut self, bufs: &[IoSlice<'_>], fds: &[RawFd]) -> io::Result<usize> {
+        let bytes_sent = sendmsg::<SockaddrStorage>(
+            self.stream.as_raw_fd(),
+            bufs,
+            &[ControlMessage::ScmRights(fds)],
+            MsgFlags::empty(),
+            None,
+        )?;
+        Ok(bytes_sent)
+    }
+
+    fn recv_with_fds(&mut self, buf: &mut [u8], fds: &mut Vec<RawFd>) -> io::Result<usize> {
+        let iovec_mut = &mut [IoSliceMut::new(buf)];
+        let msg = recvmsg::<SockaddrStorage>(
+            self.stream.as_raw_fd(),
+            iovec_mut,
+            Some(&mut self.cmsg_buf),
+            MsgFlags::empty(),
+        )?;
+
+        for cmsg in msg.cmsgs() {
+            match cmsg {
+                ControlMessageOwned::ScmRights(received) => fds.extend(received),
+                _ => {
+                    // TODO what to do?
+                    eprintln!("Cmsg other than ScmRights: {:?}", cmsg);
+                }
+            }
+        }
+        Ok(msg.bytes)
+    }
+}
+
+impl Transport for TcpStream {
+    fn supports_unix_fds(&self) -> bool {
+        false
+    }
+    fn try_clone_transport(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(self.try_clone()?))
+    }
+
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        TcpStream::read_timeout(self)
+    }
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+    fn write_timeout(&self) -> io::Result<Option<Duration>> {
+        TcpStream::write_timeout(self)
+    }
+    fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, nonblocking)
+    }
+
+    fn send_with_fds(&mut self, bufs: &[IoSlice<'_>], fds: &[RawFd]) -> io::Result<usize> {
+        if !fds.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "unix fds can not be sent over TCP",
+            ));
+        }
+        self.write_vectored(bufs)
+    }
+
+    fn recv_with_fds(&mut self, buf: &mut [u8], _fds: &mut Vec<RawFd>) -> io::Result<usize> {
+        self.read(buf)
+    }
+}
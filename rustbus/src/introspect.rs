@@ -0,0 +1,930 @@
+//! Describe objects and their interfaces to answer `org.freedesktop.DBus.Introspectable.Introspect` calls
+//!
+//! Build a tree of `Node`s that mirrors the objects your service exports and let it generate the introspection XML
+//! for the object that is being introspected.
+//!
+//! ```rust
+//! use rustbus::introspect::{Access, Interface, Method, Node, Property, Signal};
+//!
+//! let tree = Node::new().with_child(
+//!     Node::named("io").with_child(
+//!         Node::named("killing").with_interface(
+//!             Interface::new("io.killing.spark")
+//!                 .method(Method::new("Echo").in_arg_of::<String>("msg").out_arg_of::<String>("echo"))
+//!                 .signal(Signal::new("Echoed").arg_of::<String>("msg"))
+//!                 .property(Property::of::<u32>("Count", Access::Read)),
+//!         ),
+//!     ),
+//! );
+//! let xml = tree.find("/io/killing").unwrap().to_xml();
+//! assert!(xml.contains(r#"<arg name="msg" type="s" direction="in"/>"#));
+//! ```
+//!
+//! The XML returned by a remote service can be turned back into a `Node` with `parse`. Elements that are not part of
+//! the description (like annotations) are skipped.
+//!
+//! ```rust
+//! use rustbus::introspect;
+//! let node = introspect::parse(r#"
+//!     <node>
+//!       <interface name="io.killing.spark">
+//!         <method name="Echo">
+//!           <arg name="msg" type="s" direction="in"/>
+//!           <arg type="s" direction="out"/>
+//!         </method>
+//!       </interface>
+//!       <node name="child"/>
+//!     </node>"#).unwrap();
+//! let echo = node.interface("io.killing.spark").unwrap().find_method("Echo").unwrap();
+//! assert_eq!(echo.in_signature(), "s");
+//! assert!(node.child("child").is_some());
+//! ```
+//!
+//! Typed client proxies can be generated from such a description in a build script with the `codegen` module.
+
+use crate::message_builder::{MarshalledMessage, MessageType};
+use crate::signature;
+use crate::signature::Type;
+use crate::wire::marshal::traits::Signature;
+use thiserror::Error;
+
+pub mod codegen;
+
+pub const INTROSPECTABLE_INTERFACE: &str = "org.freedesktop.DBus.Introspectable";
+
+/// The doctype that precedes the introspection XML
+pub const DOCTYPE: &str = "<!DOCTYPE node PUBLIC \"-//freedesktop//DTD D-BUS Object Introspection 1.0//EN\"\n \"http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd\">\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::ReadWrite => "readwrite",
+        }
+    }
+}
+
+/// An argument of a method or signal. Signal arguments are always `Direction::Out`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Arg {
+    pub name: Option<String>,
+    pub typ: Type,
+    pub direction: Direction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Method {
+    pub name: String,
+    pub args: Vec<Arg>,
+}
+
+impl Method {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            args: Vec::new(),
+        }
+    }
+    pub fn in_arg<S: Into<String>>(self, name: S, typ: Type) -> Self {
+        self.with_arg(name, typ, Direction::In)
+    }
+    pub fn out_arg<S: Into<String>>(self, name: S, typ: Type) -> Self {
+        self.with_arg(name, typ, Direction::Out)
+    }
+    /// Add an in argument with the signature of `T`
+    pub fn in_arg_of<T: Signature>(self, name: &str) -> Self {
+        self.in_arg(name, T::signature())
+    }
+    /// Add an out argument with the signature of `T`
+    pub fn out_arg_of<T: Signature>(self, name: &str) -> Self {
+        self.out_arg(name, T::signature())
+    }
+    /// The signature of all in arguments, as it is expected in the call
+    pub fn in_signature(&self) -> String {
+        args_signature(&self.args, Direction::In)
+    }
+    /// The signature of all out arguments, as it is expected in the reply
+    pub fn out_signature(&self) -> String {
+        args_signature(&self.args, Direction::Out)
+    }
+    fn with_arg<S: Into<String>>(mut self, name: S, typ: Type, direction: Direction) -> Self {
+        self.args.push(Arg {
+            name: Some(name.into()),
+            typ,
+            direction,
+        });
+        self
+    }
+}
+
+fn args_signature(args: &[Arg], direction: Direction) -> String {
+    let mut sig = String::new();
+    for arg in args.iter().filter(|arg| arg.direction == direction) {
+        arg.typ.to_str(&mut sig);
+    }
+    sig
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signal {
+    pub name: String,
+    pub args: Vec<Arg>,
+}
+
+impl Signal {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            args: Vec::new(),
+        }
+    }
+    pub fn arg<S: Into<String>>(mut self, name: S, typ: Type) -> Self {
+        self.args.push(Arg {
+            name: Some(name.into()),
+            typ,
+            direction: Direction::Out,
+        });
+        self
+    }
+    /// Add an argument with the signature of `T`
+    pub fn arg_of<T: Signature>(self, name: &str) -> Self {
+        self.arg(name, T::signature())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    pub name: String,
+    pub typ: Type,
+    pub access: Access,
+}
+
+impl Property {
+    pub fn new<S: Into<String>>(name: S, typ: Type, access: Access) -> Self {
+        Self {
+            name: name.into(),
+            typ,
+            access,
+        }
+    }
+    /// A property with the signature of `T`
+    pub fn of<T: Signature>(name: &str, access: Access) -> Self {
+        Self::new(name, T::signature(), access)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface {
+    pub name: String,
+    pub methods: Vec<Method>,
+    pub signals: Vec<Signal>,
+    pub properties: Vec<Property>,
+}
+
+impl Interface {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            methods: Vec::new(),
+            signals: Vec::new(),
+            properties: Vec::new(),
+        }
+    }
+    pub fn method(mut self, method: Method) -> Self {
+        self.methods.push(method);
+        self
+    }
+    pub fn signal(mut self, signal: Signal) -> Self {
+        self.signals.push(signal);
+        self
+    }
+    pub fn property(mut self, property: Property) -> Self {
+        self.properties.push(property);
+        self
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<&Method> {
+        self.methods.iter().find(|method| method.name == name)
+    }
+    pub fn find_signal(&self, name: &str) -> Option<&Signal> {
+        self.signals.iter().find(|signal| signal.name == name)
+    }
+    pub fn find_property(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+    }
+}
+
+/// An object in the tree. The root node has no name, all other nodes are named after their last path element.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Node {
+    pub name: Option<String>,
+    pub interfaces: Vec<Interface>,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    /// The root node of a tree
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// A child node, `name` is the last element of its object path
+    pub fn named<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Self::default()
+        }
+    }
+    pub fn with_interface(mut self, interface: Interface) -> Self {
+        self.interfaces.push(interface);
+        self
+    }
+    pub fn with_child(mut self, child: Node) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn interface(&self, name: &str) -> Option<&Interface> {
+        self.interfaces
+            .iter()
+            .find(|interface| interface.name == name)
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Node> {
+        self.children
+            .iter()
+            .find(|child| child.name.as_deref() == Some(name))
+    }
+
+    /// Find the node for an object path, relative to this node
+    pub fn find(&self, path: &str) -> Option<&Node> {
+        path.split('/')
+            .filter(|element| !element.is_empty())
+            .try_fold(self, |node, element| node.child(element))
+    }
+
+    /// Generate the introspection XML for this node. Children are only listed by name, as
+    /// `<node name="..."/>`, they have to be introspected on their own.
+    pub fn to_xml(&self) -> String {
+        let mut xml = DOCTYPE.to_owned();
+        match &self.name {
+            Some(name) => {
+                xml.push_str("<node name=\"");
+                push_escaped(&mut xml, name);
+                xml.push_str("\">\n");
+            }
+            None => xml.push_str("<node>\n"),
+        }
+        for interface in &self.interfaces {
+            write_interface(&mut xml, interface);
+        }
+        for child in &self.children {
+            if let Some(name) = &child.name {
+                xml.push_str("  <node name=\"");
+                push_escaped(&mut xml, name);
+                xml.push_str("\"/>\n");
+            }
+        }
+        xml.push_str("</node>\n");
+        xml
+    }
+
+    /// Answer `call` if it is an `Introspect` call. The object path of the call is looked up relative to this node,
+    /// if it does not exist an `org.freedesktop.DBus.Error.UnknownObject` error is returned.
+    ///
+    /// Returns None for all other calls.
+    pub fn introspect_reply(&self, call: &MarshalledMessage) -> Option<MarshalledMessage> {
+        if call.typ != MessageType::Call
+            || call.dynheader.interface.as_deref() != Some(INTROSPECTABLE_INTERFACE)
+            || call.dynheader.member.as_deref() != Some("Introspect")
+        {
+            return None;
+        }
+        let path = call.dynheader.object.as_deref().unwrap_or("/");
+        match self.find(path) {
+            Some(node) => {
+                let mut reply = call.dynheader.make_response();
+                reply.body.push_param(node.to_xml()).unwrap();
+                Some(reply)
+            }
+            None => Some(call.dynheader.make_error_response(
+                "org.freedesktop.DBus.Error.UnknownObject",
+                Some(format!("No object at path {}", path)),
+            )),
+        }
+    }
+}
+
+fn write_interface(xml: &mut String, interface: &Interface) {
+    xml.push_str("  <interface name=\"");
+    push_escaped(xml, &interface.name);
+    xml.push_str("\">\n");
+    for method in &interface.methods {
+        write_member(xml, "method", &method.name, &method.args, true);
+    }
+    for signal in &interface.signals {
+        write_member(xml, "signal", &signal.name, &signal.args, false);
+    }
+    for property in &interface.properties {
+        xml.push_str("    <property name=\"");
+        push_escaped(xml, &property.name);
+        xml.push_str("\" type=\"");
+        property.typ.to_str(xml);
+        xml.push_str("\" access=\"");
+        xml.push_str(property.access.as_str());
+        xml.push_str("\"/>\n");
+    }
+    xml.push_str("  </interface>\n");
+}
+
+fn write_member(xml: &mut String, tag: &str, name: &str, args: &[Arg], with_direction: bool) {
+    xml.push_str("    <");
+    xml.push_str(tag);
+    xml.push_str(" name=\"");
+    push_escaped(xml, name);
+    if args.is_empty() {
+        xml.push_str("\"/>\n");
+        return;
+    }
+    xml.push_str("\">\n");
+    for arg in args {
+        xml.push_str("      <arg");
+        if let Some(name) = &arg.name {
+            xml.push_str(" name=\"");
+            push_escaped(xml, name);
+            xml.push('"');
+        }
+        xml.push_str(" type=\"");
+        arg.typ.to_str(xml);
+        xml.push('"');
+        if with_direction {
+            xml.push_str(match arg.direction {
+                Direction::In => " direction=\"in\"",
+                Direction::Out => " direction=\"out\"",
+            });
+        }
+        xml.push_str("/>\n");
+    }
+    xml.push_str("    </");
+    xml.push_str(tag);
+    xml.push_str(">\n");
+}
+
+fn push_escaped(xml: &mut String, val: &str) {
+    for c in val.chars() {
+        match c {
+            '&' => xml.push_str("&amp;"),
+            '<' => xml.push_str("&lt;"),
+            '>' => xml.push_str("&gt;"),
+            '"' => xml.push_str("&quot;"),
+            '\'' => xml.push_str("&apos;"),
+            c => xml.push(c),
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Error)]
+pub enum IntrospectError {
+    #[error("Malformed xml at byte offset {0}")]
+    MalformedXml(usize),
+    #[error("Expected the closing tag </{expected}> but found </{found}>")]
+    MismatchedTag { expected: String, found: String },
+    #[error("The xml does not start with a <node> element")]
+    NoRootNode,
+    #[error("The <{element}> element is missing the {attribute} attribute")]
+    MissingAttribute {
+        element: &'static str,
+        attribute: &'static str,
+    },
+    #[error("The signature {signature} is not a single complete type: {source}")]
+    InvalidSignature {
+        signature: String,
+        source: signature::Error,
+    },
+    #[error("Unknown arg direction: {0}")]
+    InvalidDirection(String),
+    #[error("Unknown property access: {0}")]
+    InvalidAccess(String),
+}
+
+type Result<T> = std::result::Result<T, IntrospectError>;
+
+/// Parse introspection XML, as returned by the `Introspect` method, into a `Node`.
+///
+/// Child nodes that are only referenced by name (`<node name="..."/>`) end up as nodes without interfaces.
+/// Introspect them separately to discover the whole subtree.
+pub fn parse(xml: &str) -> Result<Node> {
+    let mut parser = XmlParser { xml, pos: 0 };
+    let node = match parser.next_tag()? {
+        Tag::Start(start) if start.name == "node" => parse_node(&mut parser, start)?,
+        _ => return Err(IntrospectError::NoRootNode),
+    };
+    match parser.next_tag()? {
+        Tag::Eof => Ok(node),
+        _ => Err(IntrospectError::MalformedXml(parser.pos)),
+    }
+}
+
+fn parse_node(parser: &mut XmlParser<'_>, start: StartTag<'_>) -> Result<Node> {
+    let mut node = Node {
+        name: start.attr("name").map(str::to_owned),
+        ..Node::default()
+    };
+    if start.self_closing {
+        return Ok(node);
+    }
+    loop {
+        match parser.next_tag()? {
+            Tag::Start(child) => match child.name {
+                "node" => node.children.push(parse_node(parser, child)?),
+                "interface" => node.interfaces.push(parse_interface(parser, child)?),
+                _ => parser.skip_element(child)?,
+            },
+            Tag::End(name) => return expect_end("node", name).map(|_| node),
+            Tag::Eof => return Err(IntrospectError::MalformedXml(parser.pos)),
+        }
+    }
+}
+
+fn parse_interface(parser: &mut XmlParser<'_>, start: StartTag<'_>) -> Result<Interface> {
+    let mut interface = Interface::new(start.required_attr("interface", "name")?);
+    if start.self_closing {
+        return Ok(interface);
+    }
+    loop {
+        match parser.next_tag()? {
+            Tag::Start(child) => match child.name {
+                "method" => {
+                    let name = child.required_attr("method", "name")?;
+                    let args = parse_args(parser, child, Direction::In)?;
+                    interface.methods.push(Method { name, args });
+                }
+                "signal" => {
+                    let name = child.required_attr("signal", "name")?;
+                    let args = parse_args(parser, child, Direction::Out)?;
+                    interface.signals.push(Signal { name, args });
+                }
+                "property" => {
+                    let name = child.required_attr("property", "name")?;
+                    let typ = parse_type(&child.required_attr("property", "type")?)?;
+                    let access = match child.required_attr("property", "access")?.as_str() {
+                        "read" => Access::Read,
+                        "write" => Access::Write,
+                        "readwrite" => Access::ReadWrite,
+                        other => return Err(IntrospectError::InvalidAccess(other.to_owned())),
+                    };
+                    interface.properties.push(Property { name, typ, access });
+                    parser.skip_element(child)?;
+                }
+                _ => parser.skip_element(child)?,
+            },
+            Tag::End(name) => return expect_end("interface", name).map(|_| interface),
+            Tag::Eof => return Err(IntrospectError::MalformedXml(parser.pos)),
+        }
+    }
+}
+
+/// Parse the args of a method or signal, `default_direction` is used for args that do not specify a direction
+fn parse_args(
+    parser: &mut XmlParser<'_>,
+    start: StartTag<'_>,
+    default_direction: Direction,
+) -> Result<Vec<Arg>> {
+    let mut args = Vec::new();
+    if start.self_closing {
+        return Ok(args);
+    }
+    loop {
+        match parser.next_tag()? {
+            Tag::Start(child) if child.name == "arg" => {
+                let direction = match child.attr("direction") {
+                    None => default_direction,
+                    Some("in") => Direction::In,
+                    Some("out") => Direction::Out,
+                    Some(other) => return Err(IntrospectError::InvalidDirection(other.to_owned())),
+                };
+                args.push(Arg {
+                    name: child.attr("name").map(str::to_owned),
+                    typ: parse_type(&child.required_attr("arg", "type")?)?,
+                    direction,
+                });
+                parser.skip_element(child)?;
+            }
+            Tag::Start(child) => parser.skip_element(child)?,
+            Tag::End(name) => return expect_end(start.name, name).map(|_| args),
+            Tag::Eof => return Err(IntrospectError::MalformedXml(parser.pos)),
+        }
+    }
+}
+
+fn parse_type(sig: &str) -> Result<Type> {
+    sig.parse()
+        .map_err(|source| IntrospectError::InvalidSignature {
+            signature: sig.to_owned(),
+            source,
+        })
+}
+
+fn expect_end(expected: &str, found: &str) -> Result<()> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(IntrospectError::MismatchedTag {
+            expected: expected.to_owned(),
+            found: found.to_owned(),
+        })
+    }
+}
+
+struct StartTag<'a> {
+    name: &'a str,
+    attrs: Vec<(&'a str, String)>,
+    self_closing: bool,
+}
+
+impl StartTag<'_> {
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, val)| val.as_str())
+    }
+    fn required_attr(&self, element: &'static str, attribute: &'static str) -> Result<String> {
+        self.attr(attribute)
+            .map(str::to_owned)
+            .ok_or(IntrospectError::MissingAttribute { element, attribute })
+    }
+}
+
+enum Tag<'a> {
+    Start(StartTag<'a>),
+    End(&'a str),
+    Eof,
+}
+
+/// Just enough of an xml parser to read introspection data. Text, comments, processing instructions and
+/// the doctype are skipped.
+struct XmlParser<'a> {
+    xml: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.xml[self.pos..]
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<()> {
+        match self.rest().find(end) {
+            Some(idx) => {
+                self.pos += idx + end.len();
+                Ok(())
+            }
+            None => Err(IntrospectError::MalformedXml(self.xml.len())),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn name(&mut self) -> Result<&'a str> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '=' | '/' | '>'))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(IntrospectError::MalformedXml(self.pos));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn next_tag(&mut self) -> Result<Tag<'a>> {
+        loop {
+            match self.rest().find('<') {
+                Some(idx) => self.pos += idx,
+                None => return Ok(Tag::Eof),
+            }
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!") {
+                self.skip_past(">")?;
+            } else if rest.starts_with("</") {
+                self.pos += 2;
+                let name = self.name()?;
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') {
+                    return Err(IntrospectError::MalformedXml(self.pos));
+                }
+                self.pos += 1;
+                return Ok(Tag::End(name));
+            } else {
+                self.pos += 1;
+                return self.start_tag().map(Tag::Start);
+            }
+        }
+    }
+
+    fn start_tag(&mut self) -> Result<StartTag<'a>> {
+        let name = self.name()?;
+        let mut attrs = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.pos += 2;
+                return Ok(StartTag {
+                    name,
+                    attrs,
+                    self_closing: true,
+                });
+            }
+            if rest.starts_with('>') {
+                self.pos += 1;
+                return Ok(StartTag {
+                    name,
+                    attrs,
+                    self_closing: false,
+                });
+            }
+
+            let key = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(IntrospectError::MalformedXml(self.pos));
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let quote = match self.rest().chars().next() {
+                Some(quote @ ('"' | '\'')) => quote,
+                _ => return Err(IntrospectError::MalformedXml(self.pos)),
+            };
+            self.pos += 1;
+            let start = self.pos;
+            let len = self
+                .rest()
+                .find(quote)
+                .ok_or(IntrospectError::MalformedXml(self.xml.len()))?;
+            self.pos += len + 1;
+            attrs.push((key, unescape(&self.xml[start..start + len], start)?));
+        }
+    }
+
+    /// Skip over the content of an element that has already been started
+    fn skip_element(&mut self, start: StartTag<'a>) -> Result<()> {
+        if start.self_closing {
+            return Ok(());
+        }
+        let mut open = vec![start.name];
+        while let Some(expected) = open.last() {
+            match self.next_tag()? {
+                Tag::Start(tag) if !tag.self_closing => open.push(tag.name),
+                Tag::Start(_) => {}
+                Tag::End(name) => {
+                    expect_end(expected, name)?;
+                    open.pop();
+                }
+                Tag::Eof => return Err(IntrospectError::MalformedXml(self.pos)),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unescape(val: &str, offset: usize) -> Result<String> {
+    let mut unescaped = String::with_capacity(val.len());
+    let mut rest = val;
+    while let Some(idx) = rest.find('&') {
+        unescaped.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        let end = rest.find(';').ok_or(IntrospectError::MalformedXml(
+            offset + val.len() - rest.len(),
+        ))?;
+        let c = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity => {
+                if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+                } else if let Some(dec) = entity.strip_prefix('#') {
+                    dec.parse().ok().and_then(char::from_u32)
+                } else {
+                    None
+                }
+            }
+        };
+        match c {
+            Some(c) => unescaped.push(c),
+            None => {
+                return Err(IntrospectError::MalformedXml(
+                    offset + val.len() - rest.len(),
+                ))
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message_builder::MessageBuilder;
+    use std::collections::HashMap;
+
+    fn tree() -> Node {
+        Node::new().with_child(
+            Node::named("io").with_child(
+                Node::named("killing")
+                    .with_interface(
+                        Interface::new("io.killing.spark")
+                            .method(
+                                Method::new("Lookup")
+                                    .in_arg_of::<u32>("id")
+                                    .out_arg_of::<HashMap<String, (i32, bool)>>("entries"),
+                            )
+                            .method(Method::new("Reset"))
+                            .signal(Signal::new("Changed").arg_of::<&str>("what"))
+                            .property(Property::of::<Vec<u8>>("Data", Access::ReadWrite)),
+                    )
+                    .with_child(Node::named("child1"))
+                    .with_child(Node::named("child2")),
+            ),
+        )
+    }
+
+    #[test]
+    fn test_to_xml() {
+        let tree = tree();
+        let expected = DOCTYPE.to_owned()
+            + r#"<node name="killing">
+  <interface name="io.killing.spark">
+    <method name="Lookup">
+      <arg name="id" type="u" direction="in"/>
+      <arg name="entries" type="a{s(ib)}" direction="out"/>
+    </method>
+    <method name="Reset"/>
+    <signal name="Changed">
+      <arg name="what" type="s"/>
+    </signal>
+    <property name="Data" type="ay" access="readwrite"/>
+  </interface>
+  <node name="child1"/>
+  <node name="child2"/>
+</node>
+"#;
+        assert_eq!(tree.find("/io/killing").unwrap().to_xml(), expected);
+        assert_eq!(
+            tree.to_xml(),
+            DOCTYPE.to_owned() + "<node>\n  <node name=\"io\"/>\n</node>\n"
+        );
+        assert!(tree.find("/io/killing/child3").is_none());
+        assert_eq!(tree.find("/").unwrap(), &tree);
+
+        let escaped = Node::named("a\"<&>'").to_xml();
+        assert!(escaped.contains(r#"<node name="a&quot;&lt;&amp;&gt;&apos;">"#));
+    }
+
+    #[test]
+    fn test_parse() {
+        let tree = tree();
+        let node = tree.find("/io/killing").unwrap();
+        assert_eq!(&parse(&node.to_xml()).unwrap(), node);
+        assert_eq!(
+            parse(&tree.to_xml()).unwrap().child("io"),
+            Some(&Node::named("io"))
+        );
+
+        let method = node.interfaces[0].find_method("Lookup").unwrap();
+        assert_eq!(method.in_signature(), "u");
+        assert_eq!(method.out_signature(), "a{s(ib)}");
+
+        let parsed = parse(
+            r#"<?xml version="1.0"?>
+            <!-- a comment <node> -->
+            <node name='/a&amp;b'>
+              <interface name="i.f">
+                <annotation name="org.freedesktop.DBus.Deprecated" value="true"/>
+                <method name="M">
+                  <arg type="as"/>
+                  <arg type="v" direction="out"></arg>
+                  <annotation name="x" value="y"><unknown/></annotation>
+                </method>
+                <signal name="S"><arg name="a&#x3c;&#62;" type="(ii)"/></signal>
+                <property name="P" type="a{sv}" access="write">
+                  <annotation name="org.freedesktop.DBus.Property.EmitsChangedSignal" value="false"/>
+                </property>
+              </interface>
+              <node name="c"><interface name="i.g"/></node>
+            </node>
+            "#,
+        )
+        .unwrap();
+        assert_eq!(parsed.name.as_deref(), Some("/a&b"));
+        let interface = parsed.interface("i.f").unwrap();
+        let m = interface.find_method("M").unwrap();
+        assert_eq!(m.args[0].name, None);
+        assert_eq!(m.args[0].direction, Direction::In);
+        assert_eq!(m.in_signature(), "as");
+        assert_eq!(m.out_signature(), "v");
+        let s = interface.find_signal("S").unwrap();
+        assert_eq!(s.args[0].name.as_deref(), Some("a<>"));
+        assert_eq!(s.args[0].direction, Direction::Out);
+        let p = interface.find_property("P").unwrap();
+        assert_eq!(p.access, Access::Write);
+        assert_eq!(p.typ, "a{sv}".parse().unwrap());
+        assert!(parsed.find("/c").unwrap().interface("i.g").is_some());
+
+        assert_eq!(parse(""), Err(IntrospectError::NoRootNode));
+        assert_eq!(parse("<interface/>"), Err(IntrospectError::NoRootNode));
+        assert!(matches!(
+            parse("<node><interface name=\"a\"></node>"),
+            Err(IntrospectError::MismatchedTag { .. })
+        ));
+        assert!(matches!(
+            parse("<node><interface/></node>"),
+            Err(IntrospectError::MissingAttribute {
+                element: "interface",
+                attribute: "name"
+            })
+        ));
+        assert!(matches!(
+            parse(
+                r#"<node><interface name="a"><method name="m"><arg type="ii"/></method></interface></node>"#
+            ),
+            Err(IntrospectError::InvalidSignature {
+                source: signature::Error::TooManyTypes,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse(
+                r#"<node><interface name="a"><property name="p" type="i" access="none"/></interface></node>"#
+            ),
+            Err(IntrospectError::InvalidAccess(_))
+        ));
+        assert!(matches!(
+            parse("<node"),
+            Err(IntrospectError::MalformedXml(_))
+        ));
+        assert!(matches!(
+            parse("<node/><node/>"),
+            Err(IntrospectError::MalformedXml(_))
+        ));
+    }
+
+    #[test]
+    fn test_introspect_reply() {
+        let tree = tree();
+        let call = MessageBuilder::new()
+            .call("Introspect")
+            .with_interface(INTROSPECTABLE_INTERFACE)
+            .on("/io/killing")
+            .build();
+        let reply = tree.introspect_reply(&call).unwrap();
+        let xml: String = reply.body.parser().get().unwrap();
+        assert_eq!(xml, tree.find("/io/killing").unwrap().to_xml());
+
+        let call = MessageBuilder::new()
+            .call("Introspect")
+            .with_interface(INTROSPECTABLE_INTERFACE)
+            .on("/nope")
+            .build();
+        let reply = tree.introspect_reply(&call).unwrap();
+        assert_eq!(
+            reply.dynheader.error_name.as_deref(),
+            Some("org.freedesktop.DBus.Error.UnknownObject")
+        );
+
+        let call = MessageBuilder::new()
+            .call("Lookup")
+            .with_interface("io.killing.spark")
+            .on("/io/killing")
+            .build();
+        assert!(tree.introspect_reply(&call).is_none());
+    }
+}
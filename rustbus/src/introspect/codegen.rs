@@ -0,0 +1,436 @@
+//! Generate typed client proxies from introspection XML
+//!
+//! This is meant to be run from a build script, with rustbus as a build dependency. The generated code is then
+//! included into the crate:
+//!
+//! ```rust,no_run
+//! // build.rs
+//! let xml = std::fs::read_to_string("org.freedesktop.DBus.xml").unwrap();
+//! let code = rustbus::introspect::codegen::generate_proxies(&xml, Some("org.freedesktop.DBus")).unwrap();
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! std::fs::write(std::path::Path::new(&out_dir).join("dbus_proxy.rs"), code).unwrap();
+//! println!("cargo:rerun-if-changed=org.freedesktop.DBus.xml");
+//! ```
+//!
+//! ```rust,ignore
+//! // src/lib.rs
+//! include!(concat!(env!("OUT_DIR"), "/dbus_proxy.rs"));
+//!
+//! let mut proxy = DBusProxy::new(&mut rpc_con, "org.freedesktop.DBus", "/org/freedesktop/DBus");
+//! let owner: String = proxy.get_name_owner("org.freedesktop.DBus")?;
+//! ```
+//!
+//! Each interface becomes a struct named after the last element of the interface name with a `Proxy` suffix. It
+//! borrows an `RpcConn` and has one method per D-Bus method, named in snake case. The arguments are typed from their
+//! signatures and the methods are called with `RpcConn::call_method`, so error replies are returned as
+//! `connection::Error::ErrorResponse`. Signals and properties are not generated, neither are methods with more than
+//! twelve arguments.
+
+use std::fmt::Write;
+
+use thiserror::Error;
+
+use super::{Direction, Interface, IntrospectError, Method};
+use crate::signature::{Base, Container, Type};
+
+#[derive(Debug, Error)]
+pub enum CodegenError {
+    #[error("Could not parse the introspection xml: {0}")]
+    Introspect(#[from] IntrospectError),
+    #[error("The xml does not describe the interface {0}")]
+    UnknownInterface(String),
+    #[error("The signature {signature} of {member} can not be used: {reason}")]
+    UnsupportedType {
+        member: String,
+        signature: String,
+        reason: &'static str,
+    },
+    #[error("{member} has {count} arguments, at most 12 are supported")]
+    TooManyArguments { member: String, count: usize },
+}
+
+type Result<T> = std::result::Result<T, CodegenError>;
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Names used by the generated methods themselves, arguments and methods with these names get a `_` appended
+const RESERVED: &[&str] = &["new", "reply", "parser"];
+
+/// Parse `xml` and generate proxies for the interfaces of its root node. If `interface` is given only that one is
+/// generated, otherwise all of them.
+pub fn generate_proxies(xml: &str, interface: Option<&str>) -> Result<String> {
+    let node = super::parse(xml)?;
+    let interfaces = match interface {
+        Some(name) => vec![node
+            .interface(name)
+            .ok_or_else(|| CodegenError::UnknownInterface(name.to_owned()))?],
+        None => node.interfaces.iter().collect(),
+    };
+
+    let mut code = String::from("// Generated by rustbus::introspect::codegen, do not edit\n");
+    for interface in interfaces {
+        code.push('\n');
+        code.push_str(&generate_proxy(interface)?);
+    }
+    Ok(code)
+}
+
+/// Generate the proxy for one interface
+pub fn generate_proxy(interface: &Interface) -> Result<String> {
+    let name = proxy_name(&interface.name);
+    let mut code = String::new();
+    let _ = writeln!(
+        code,
+        "/// Calls the methods of the `{}` interface",
+        interface.name
+    );
+    let _ = writeln!(code, "pub struct {}<'a> {{", name);
+    code.push_str("    conn: &'a mut ::rustbus::RpcConn,\n");
+    code.push_str("    destination: ::std::string::String,\n");
+    code.push_str("    path: ::std::string::String,\n");
+    code.push_str("}\n\n");
+    let _ = writeln!(code, "impl<'a> {}<'a> {{", name);
+    let _ = writeln!(
+        code,
+        "    pub const INTERFACE: &'static str = {:?};\n",
+        interface.name
+    );
+    code.push_str("    /// Call the methods of the object at `path` of `destination`\n");
+    code.push_str("    pub fn new<D: ::std::convert::Into<::std::string::String>, P: ::std::convert::Into<::std::string::String>>(\n");
+    code.push_str("        conn: &'a mut ::rustbus::RpcConn,\n");
+    code.push_str("        destination: D,\n");
+    code.push_str("        path: P,\n");
+    code.push_str("    ) -> Self {\n");
+    code.push_str("        Self {\n");
+    code.push_str("            conn,\n");
+    code.push_str("            destination: destination.into(),\n");
+    code.push_str("            path: path.into(),\n");
+    code.push_str("        }\n");
+    code.push_str("    }\n");
+
+    for method in &interface.methods {
+        code.push('\n');
+        generate_method(method, &mut code)?;
+    }
+    code.push_str("}\n");
+    Ok(code)
+}
+
+fn generate_method(method: &Method, code: &mut String) -> Result<()> {
+    let unsupported = |typ: &Type, reason| CodegenError::UnsupportedType {
+        member: method.name.clone(),
+        signature: typ.to_string(),
+        reason,
+    };
+
+    let mut params = Vec::new();
+    let mut outputs = Vec::new();
+    for (idx, arg) in method.args.iter().enumerate() {
+        match arg.direction {
+            Direction::In => {
+                let name = match &arg.name {
+                    Some(name) => identifier(&snake_case(name)),
+                    None => format!("arg{}", idx),
+                };
+                let name = if params.iter().any(|(other, _)| *other == name) {
+                    format!("{}{}", name, idx)
+                } else {
+                    name
+                };
+                let typ = input_type(&arg.typ).map_err(|reason| unsupported(&arg.typ, reason))?;
+                params.push((name, typ));
+            }
+            Direction::Out => {
+                outputs.push(owned_type(&arg.typ).map_err(|reason| unsupported(&arg.typ, reason))?)
+            }
+        }
+    }
+    // the arguments are passed to `call_method` as one tuple
+    if params.len() > 12 {
+        return Err(CodegenError::TooManyArguments {
+            member: method.name.clone(),
+            count: params.len(),
+        });
+    }
+
+    let _ = writeln!(
+        code,
+        "    /// `{}({}) -> ({})`",
+        method.name,
+        method.in_signature(),
+        method.out_signature()
+    );
+    if params.len() > 6 {
+        code.push_str("    #[allow(clippy::too_many_arguments)]\n");
+    }
+    let _ = write!(
+        code,
+        "    pub fn {}(&mut self",
+        identifier(&snake_case(&method.name))
+    );
+    for (name, typ) in &params {
+        let _ = write!(code, ", {}: {}", name, typ);
+    }
+    let ret = match outputs.len() {
+        0 => "()".to_owned(),
+        1 => outputs[0].clone(),
+        _ => format!("({})", outputs.join(", ")),
+    };
+    let _ = writeln!(
+        code,
+        ") -> ::std::result::Result<{}, ::rustbus::connection::Error> {{",
+        ret
+    );
+
+    // `FromReply` only decodes the first value, several out arguments are read from the whole body
+    let count = outputs.len();
+    if count > 1 {
+        code.push_str("        let reply: ::rustbus::message_builder::MarshalledMessageBody = ");
+    } else {
+        code.push_str("        ");
+    }
+    code.push_str("self.conn.call_method(\n");
+    code.push_str("            self.destination.as_str(),\n");
+    code.push_str("            self.path.as_str(),\n");
+    code.push_str("            Self::INTERFACE,\n");
+    let _ = writeln!(code, "            {:?},", method.name);
+    let args = params
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+    match args.len() {
+        1 => {
+            let _ = writeln!(code, "            ({},),", args[0]);
+        }
+        _ => {
+            let _ = writeln!(code, "            ({}),", args.join(", "));
+        }
+    }
+    if count > 1 {
+        code.push_str("        )?;\n");
+        code.push_str("        let mut parser = reply.parser();\n");
+        code.push_str("        ::std::result::Result::Ok((");
+        code.push_str(&vec!["parser.get()?"; count].join(", "));
+        code.push_str("))\n");
+    } else {
+        code.push_str("        )\n");
+    }
+    code.push_str("    }\n");
+    Ok(())
+}
+
+/// Top level arguments are borrowed where that is possible without knowing more about the caller
+fn input_type(typ: &Type) -> std::result::Result<String, &'static str> {
+    Ok(match typ {
+        Type::Base(Base::String) => "&str".to_owned(),
+        Type::Base(Base::ObjectPath) => "::rustbus::wire::ObjectPath<&str>".to_owned(),
+        Type::Base(Base::Signature) => "::rustbus::wire::SignatureWrapper<&str>".to_owned(),
+        Type::Base(Base::UnixFd) => "&::rustbus::wire::UnixFd".to_owned(),
+        Type::Base(_) => owned_type(typ)?,
+        Type::Container(Container::Array(elem)) => format!("&[{}]", owned_type(elem)?),
+        Type::Container(Container::Variant) => "&::rustbus::params::Variant<'_, '_>".to_owned(),
+        Type::Container(_) => format!("&{}", owned_type(typ)?),
+    })
+}
+
+fn owned_type(typ: &Type) -> std::result::Result<String, &'static str> {
+    Ok(match typ {
+        Type::Base(base) => base_type(*base).to_owned(),
+        Type::Container(Container::Array(elem)) => {
+            format!("::std::vec::Vec<{}>", owned_type(elem)?)
+        }
+        Type::Container(Container::Dict(key, value)) => {
+            if matches!(key, Base::Double | Base::UnixFd) {
+                return Err("dict keys of type d or h can not be hashed");
+            }
+            format!(
+                "::std::collections::HashMap<{}, {}>",
+                base_type(*key),
+                owned_type(value)?
+            )
+        }
+        Type::Container(Container::Struct(fields)) => {
+            let fields = fields.as_ref();
+            if fields.len() > 12 {
+                return Err("structs with more than 12 fields are not supported");
+            }
+            let fields = fields
+                .iter()
+                .map(owned_type)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            if fields.len() == 1 {
+                format!("({},)", fields[0])
+            } else {
+                format!("({})", fields.join(", "))
+            }
+        }
+        Type::Container(Container::Variant) => {
+            "::rustbus::params::Variant<'static, 'static>".to_owned()
+        }
+    })
+}
+
+fn base_type(base: Base) -> &'static str {
+    match base {
+        Base::Byte => "u8",
+        Base::Int16 => "i16",
+        Base::Uint16 => "u16",
+        Base::Int32 => "i32",
+        Base::Uint32 => "u32",
+        Base::UnixFd => "::rustbus::wire::UnixFd",
+        Base::Int64 => "i64",
+        Base::Uint64 => "u64",
+        Base::Double => "f64",
+        Base::String => "::std::string::String",
+        Base::Signature => "::rustbus::wire::SignatureWrapper<::std::string::String>",
+        Base::ObjectPath => "::rustbus::wire::ObjectPath<::std::string::String>",
+        Base::Boolean => "bool",
+    }
+}
+
+/// `org.freedesktop.DBus` becomes `DBusProxy`
+fn proxy_name(interface: &str) -> String {
+    let last = interface.rsplit('.').next().unwrap_or(interface);
+    let mut name = String::new();
+    for part in last.split('_').filter(|part| !part.is_empty()) {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            name.extend(first.to_uppercase());
+            name.extend(chars);
+        }
+    }
+    name.push_str("Proxy");
+    name
+}
+
+/// `GetConnectionUnixProcessID` becomes `get_connection_unix_process_id`
+fn snake_case(name: &str) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    let mut snake = String::new();
+    for (idx, c) in chars.iter().copied().enumerate() {
+        if c.is_ascii_uppercase() {
+            let prev = idx.checked_sub(1).map(|prev| chars[prev]);
+            let next = chars.get(idx + 1).copied();
+            let starts_word = match prev {
+                None | Some('_') => false,
+                Some(prev) if prev.is_ascii_lowercase() || prev.is_ascii_digit() => true,
+                // the last letter of an acronym that is followed by a word
+                Some(_) => next.is_some_and(|next| next.is_ascii_lowercase()),
+            };
+            if starts_word {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else if c.is_ascii_alphanumeric() {
+            snake.push(c);
+        } else {
+            snake.push('_');
+        }
+    }
+    snake
+}
+
+fn identifier(name: &str) -> String {
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else if KEYWORDS.contains(&name) || RESERVED.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"
+        <node>
+          <interface name="io.killing.spark">
+            <method name="GetConnectionUnixProcessID">
+              <arg name="bus_name" type="s" direction="in"/>
+              <arg type="u" direction="out"/>
+            </method>
+            <method name="Move">
+              <arg name="type" type="a{sv}" direction="in"/>
+              <arg name="call" type="(ob)" direction="in"/>
+              <arg type="ao" direction="out"/>
+              <arg type="a{sa{sv}}" direction="out"/>
+            </method>
+            <method name="Ping"/>
+          </interface>
+          <interface name="org.freedesktop.DBus.Peer">
+            <method name="Ping"/>
+          </interface>
+        </node>"#;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(proxy_name("org.freedesktop.DBus"), "DBusProxy");
+        assert_eq!(
+            proxy_name("org.gnome.shell_extensions"),
+            "ShellExtensionsProxy"
+        );
+        assert_eq!(
+            snake_case("GetConnectionUnixProcessID"),
+            "get_connection_unix_process_id"
+        );
+        assert_eq!(
+            snake_case("GetAdtAuditSessionData"),
+            "get_adt_audit_session_data"
+        );
+        assert_eq!(snake_case("UUIDFor2Names"), "uuid_for2_names");
+        assert_eq!(snake_case("already_snake"), "already_snake");
+        assert_eq!(identifier("type"), "type_");
+        assert_eq!(identifier("new"), "new_");
+        assert_eq!(identifier("2fa"), "_2fa");
+    }
+
+    #[test]
+    fn test_generate() {
+        let code = generate_proxies(XML, Some("io.killing.spark")).unwrap();
+        assert!(code.contains("pub struct SparkProxy<'a> {"));
+        assert!(!code.contains("PeerProxy"));
+        assert!(code.contains(
+            "pub fn get_connection_unix_process_id(&mut self, bus_name: &str) -> ::std::result::Result<u32, ::rustbus::connection::Error> {"
+        ));
+        assert!(code.contains("pub fn move_(&mut self, type_: &::std::collections::HashMap<::std::string::String, ::rustbus::params::Variant<'static, 'static>>, call: &(::rustbus::wire::ObjectPath<::std::string::String>, bool)) -> ::std::result::Result<(::std::vec::Vec<::rustbus::wire::ObjectPath<::std::string::String>>, ::std::collections::HashMap<::std::string::String, ::std::collections::HashMap<::std::string::String, ::rustbus::params::Variant<'static, 'static>>>), ::rustbus::connection::Error> {"));
+        assert!(code.contains("        let mut parser = reply.parser();\n        ::std::result::Result::Ok((parser.get()?, parser.get()?))\n"));
+        assert!(code.contains(
+            "            \"GetConnectionUnixProcessID\",\n            (bus_name,),\n        )\n"
+        ));
+        assert!(code.contains(
+            "pub fn ping(&mut self) -> ::std::result::Result<(), ::rustbus::connection::Error> {"
+        ));
+
+        let all = generate_proxies(XML, None).unwrap();
+        assert!(all.contains("pub struct SparkProxy<'a> {"));
+        assert!(all.contains("pub struct PeerProxy<'a> {"));
+
+        assert!(matches!(
+            generate_proxies(XML, Some("io.killing.nope")),
+            Err(CodegenError::UnknownInterface(name)) if name == "io.killing.nope"
+        ));
+        let unhashable = XML.replace("a{sv}", "a{dv}");
+        assert!(matches!(
+            generate_proxies(&unhashable, Some("io.killing.spark")),
+            Err(CodegenError::UnsupportedType { member, signature, .. })
+                if member == "Move" && signature == "a{dv}"
+        ));
+        let args = "<arg type=\"y\" direction=\"in\"/>".repeat(13);
+        let long = XML.replace(
+            "<method name=\"Ping\"/>",
+            &format!("<method name=\"Ping\">{}</method>", args),
+        );
+        assert!(matches!(
+            generate_proxies(&long, Some("io.killing.spark")),
+            Err(CodegenError::TooManyArguments { member, count: 13 }) if member == "Ping"
+        ));
+    }
+}
@@ -60,10 +60,16 @@
 //! * RpcConn is meant for clients calling methods on services on the bus
 //! * DispatchConn is meant for services that need to dispatch calls to many handlers.
 //!
+//! To handle signals from different sources, a `SignalRouter` runs handlers on the received signals that match their `MatchRule`s.
+//!
 //! Since different usecases have different constraints you might need to write your own wrapper around the low level conn. This should not be too hard
 //! if you copy the existing ones and modify them to your needs. If you have an issue that would be helpful for others I would of course consider adding
 //! it to this libary.
 //!
+//! The low level conn itself only does the socket io. Reassembling messages from the received bytes is done by `wire::MessageReassembler`
+//! and assigning serials and marshalling the headers by `wire::MessageWriter`. Neither does any io, so they can also drive a connection over the
+//! `AsyncRead`/`AsyncWrite` of an async runtime without rustbus depending on one.
+//!
 //! ## Params and Marshal and Unmarshal
 //! This lib started out as an attempt to understand how dbus worked. Thus I modeled the types a closely as possible with enums, which is still in the params module.
 //! This is kept around for weird weird edge-cases where that might be necessary but they should not generally be used.
@@ -78,6 +84,14 @@
 //! For Variants there is a macro dbus_variant_sig! and dbus_variant_var! which will generate an enum and the Marshal and Unmarshal impls for you. These might get
 //! replaced with a proc-macro derive like it exists already for structs.
 //!
+//! Enums can also be derived. By default each enum variant is marshalled as a Variant and identified by its signature when unmarshalling.
+//! With `#[rustbus(discriminant)]` on the enum, the Variant instead contains a u32 discriminant followed by the fields of the enum variant
+//! (signature `(u...)`, or just `u` for unit variants). Unknown discriminants are rejected with `UnmarshalError::UnknownDiscriminant`.
+//! With `#[rustbus(string_enum)]` enums without fields are marshalled as a string holding the name of the variant. Names can be changed with
+//! `#[rustbus(rename = "...")]` on the variant. Unknown strings are rejected with `UnmarshalError::UnknownVariant` unless one variant with a
+//! single field is marked with `#[rustbus(other)]`, which then holds the string. If that variant holds the name of another variant it is
+//! unmarshalled as the other variant, e.g. `Profile::Other("Performance")` comes back as `Profile::Performance`.
+//!
 //! The doc for the traits gives more specifics on how to implement them for your own types if necessary.
 //!
 //! There is an exmaple for all of this in `examples/user_defined_types.rs`.
@@ -88,18 +102,67 @@
 //! the pitfalls of sending and receiving filedescriptors in a sensible way. If you see any issues with the API or have wishes for extensions to the API please
 //! open an issue.
 //!
+//! ## Without std
+//! Connections, authentication and unix fds are behind the `std` feature, which is enabled by default. With `default-features = false` only the
+//! params, signature and wire modules and the `MessageBuilder` are built, using `core` and `alloc`. This is enough to marshal and unmarshal messages
+//! on targets without an operating system.
+//!
+//! ## Testing
+//! The `testing` feature adds the testing module with `testing::roundtrip`, an invariant that marshals a param and
+//! unmarshals it again. It is meant for property tests and fuzzers of code that builds params.
+//!
+//! ## GVariant
+//! The `gvariant` feature adds `wire::gvariant` and `wire::WireFormat`, which marshal params in the GVariant serialization format
+//! used by GLib instead of the dbus wire format.
+//!
+//! ## Network addresses
+//! The `net` feature implements `Marshal` and `Unmarshal` for `IpAddr`, `Ipv4Addr`, `Ipv6Addr` and `SocketAddr` from `std::net`. They are
+//! sent as strings in their `Display` format, e.g. `fe80::1` or `[fe80::1%2]:8080` for an IPv6 socket address with a scope id.
+//!
+//! ## JSON
+//! The `serde_json` feature adds `params::json` to convert message bodies and params into `serde_json::Value`s, e.g. for logging, and to
+//! build params of a given signature from `serde_json::Value`s.
+//!
+//! ## Conformance
+//! Unmarshalling is forgiving by default, e.g. padding bytes are skipped without looking at them. Bus daemons and fuzzers that must reject
+//! everything the spec forbids can use `wire::unmarshal::unmarshal_message_conformant` for whole messages and `UnmarshalContext::conformance`
+//! for single values. Their docs list the checks and the errors they report.
+//!
+//! ## Arena
+//! The `arena` feature adds `MarshalledMessage::unmarshal_all_in` and `wire::unmarshal::container::unmarshal_with_sig_in`. They unmarshal
+//! the strings, object paths and signatures of params into a `bumpalo::Bump` instead of allocating a `String` for each of them, which helps
+//! with bodies that contain many small strings. The arena is passed next to the `UnmarshalContext` rather than stored in it, because the params
+//! borrow from the arena and not from the message buffer.
+//!
 //! ## Byteorders
 //! Dbus supports both big and little endian and so does rustbus. You can specify how a message should be marshalled when you create the MessageBuilder. Messages
 //! can be received in any byteorder and will be transparently unmarshalled into the byteorder you CPU uses. Note that unmarshalling from/to the native byteorder will
 //! be faster. The default byteorder is little endian.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+mod alloc_prelude;
+#[cfg(feature = "std")]
 pub mod auth;
+#[cfg(feature = "std")]
 pub mod connection;
+#[cfg(feature = "std")]
+pub mod introspect;
+#[cfg(feature = "std")]
+pub mod match_rule;
 pub mod message_builder;
 pub mod params;
+#[cfg(feature = "std")]
 pub mod peer;
+#[cfg(feature = "std")]
+pub mod signal_router;
 pub mod signature;
+#[cfg(feature = "std")]
 pub mod standard_messages;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod wire;
 
 // reexport derive macros
@@ -108,15 +171,33 @@ pub use rustbus_derive::*;
 // TODO create a rustbus::prelude
 
 // needed to make own filters in RpcConn
+#[cfg(feature = "std")]
+pub use match_rule::MatchRule;
 pub use message_builder::MessageType;
+#[cfg(feature = "std")]
+pub use signal_router::SignalRouter;
+
+// needed to handle error replies
+pub use message_builder::DBusError;
 
 // needed to create a connection
+#[cfg(feature = "std")]
 pub use connection::dispatch_conn::DispatchConn;
+#[cfg(feature = "std")]
 pub use connection::ll_conn::DuplexConn;
+#[cfg(feature = "std")]
 pub use connection::ll_conn::RecvConn;
+#[cfg(feature = "std")]
 pub use connection::ll_conn::SendConn;
+#[cfg(feature = "std")]
+pub use connection::monitor::MonitorConn;
+#[cfg(feature = "std")]
 pub use connection::rpc_conn::RpcConn;
-pub use connection::{get_session_bus_path, get_system_bus_path};
+#[cfg(feature = "std")]
+pub use connection::{
+    get_session_bus_address, get_session_bus_addresses, get_session_bus_path, get_system_bus_path,
+    BusAddress,
+};
 
 // needed to make new messages
 pub use message_builder::{CallBuilder, MessageBuilder, SignalBuilder};
@@ -125,6 +206,8 @@ pub use wire::marshal::traits::Signature;
 pub use wire::unmarshal::traits::Unmarshal;
 
 #[cfg(test)]
+mod test_fixtures;
+#[cfg(all(test, feature = "std"))]
 mod tests;
 
 /// The supported byte orders
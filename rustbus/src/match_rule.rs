@@ -0,0 +1,496 @@
+//! Build match rules to subscribe to signals with AddMatch/RemoveMatch
+//!
+//! ```rust,no_run
+//! use rustbus::{connection::Timeout, MatchRule, MessageType, RpcConn};
+//!
+//! let mut rpc_con = RpcConn::session_conn(Timeout::Infinite).unwrap();
+//! let rule = MatchRule::new()
+//!     .msg_type(MessageType::Signal)
+//!     .interface("org.freedesktop.DBus.Properties")
+//!     .member("PropertiesChanged")
+//!     .path_namespace("/org/mpris");
+//! rpc_con.add_match(&rule).unwrap();
+//! // ...
+//! rpc_con.remove_match(&rule).unwrap();
+//! ```
+
+use crate::message_builder::{MarshalledMessage, MessageType};
+use crate::wire::ObjectPath;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A match rule as described in the [dbus spec](https://dbus.freedesktop.org/doc/dbus-specification.html#message-bus-routing-match-rules).
+///
+/// Use `to_string()` to get the canonical string that is sent to the bus. All values are quoted, so commas and
+/// backslashes in values are sent as they are. Apostrophes are escaped as `'\''`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MatchRule {
+    typ: Option<MessageType>,
+    sender: Option<String>,
+    interface: Option<String>,
+    member: Option<String>,
+    path: Option<String>,
+    path_namespace: Option<String>,
+    destination: Option<String>,
+    args: BTreeMap<u8, String>,
+    arg_paths: BTreeMap<u8, String>,
+    arg0namespace: Option<String>,
+    eavesdrop: bool,
+}
+
+/// The highest argument index a match rule may refer to
+pub const MAX_ARG_INDEX: u8 = 63;
+
+impl MatchRule {
+    /// A rule without any restrictions, matching all messages
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match messages of this type. `MessageType::Invalid` removes the restriction.
+    pub fn msg_type(mut self, typ: MessageType) -> Self {
+        self.typ = match typ {
+            MessageType::Invalid => None,
+            typ => Some(typ),
+        };
+        self
+    }
+    pub fn sender<S: Into<String>>(mut self, sender: S) -> Self {
+        self.sender = Some(sender.into());
+        self
+    }
+    pub fn interface<S: Into<String>>(mut self, interface: S) -> Self {
+        self.interface = Some(interface.into());
+        self
+    }
+    pub fn member<S: Into<String>>(mut self, member: S) -> Self {
+        self.member = Some(member.into());
+        self
+    }
+    pub fn path<S: Into<String>>(mut self, path: S) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+    /// Match messages with this object path or any object path below it
+    pub fn path_namespace<S: Into<String>>(mut self, path_namespace: S) -> Self {
+        self.path_namespace = Some(path_namespace.into());
+        self
+    }
+    pub fn destination<S: Into<String>>(mut self, destination: S) -> Self {
+        self.destination = Some(destination.into());
+        self
+    }
+    /// Match messages whose argument `idx` is a string equal to `value`.
+    ///
+    /// Panics if `idx` is bigger than `MAX_ARG_INDEX`
+    pub fn arg<S: Into<String>>(mut self, idx: u8, value: S) -> Self {
+        assert!(idx <= MAX_ARG_INDEX, "match rule arg index out of range");
+        self.args.insert(idx, value.into());
+        self
+    }
+    /// Match messages whose argument `idx` is a string or object path that is a path prefix of `value` or the other way around.
+    ///
+    /// Panics if `idx` is bigger than `MAX_ARG_INDEX`
+    pub fn arg_path<S: Into<String>>(mut self, idx: u8, value: S) -> Self {
+        assert!(idx <= MAX_ARG_INDEX, "match rule arg index out of range");
+        self.arg_paths.insert(idx, value.into());
+        self
+    }
+    /// Match messages whose first argument is a bus name in the namespace `namespace`
+    pub fn arg0namespace<S: Into<String>>(mut self, namespace: S) -> Self {
+        self.arg0namespace = Some(namespace.into());
+        self
+    }
+    /// Also match messages that are sent to other connections, not only broadcast signals and messages to this one.
+    ///
+    /// This is a legacy of old versions of dbus-daemon. Newer versions only allow it for privileged connections, usually
+    /// root or the user of the session bus, and reject the rule with `org.freedesktop.DBus.Error.AccessDenied` otherwise.
+    /// Use `DuplexConn::become_monitor` instead, monitors get copies of all messages matching their rules.
+    pub fn eavesdrop(mut self, eavesdrop: bool) -> Self {
+        self.eavesdrop = eavesdrop;
+        self
+    }
+}
+
+/// The args of a message that match rules can refer to
+pub(crate) struct MatchArgs {
+    /// String and object path args up to `MAX_ARG_INDEX`, `None` for args of other types
+    args: Vec<Option<MatchArg>>,
+}
+
+enum MatchArg {
+    String(String),
+    ObjectPath(String),
+}
+
+impl MatchArgs {
+    /// Parse the args of the body. Parsing stops at the first arg that can not be unmarshalled, the args after it can
+    /// not match anything.
+    pub(crate) fn parse(msg: &MarshalledMessage) -> Self {
+        let mut parser = msg.body.parser();
+        let mut args = Vec::new();
+        while args.len() <= MAX_ARG_INDEX as usize {
+            let arg = match parser.get_next_sig() {
+                None => break,
+                Some("s") => parser
+                    .get::<&str>()
+                    .map(|s| Some(MatchArg::String(s.to_owned()))),
+                Some("o") => parser
+                    .get::<ObjectPath<&str>>()
+                    .map(|path| Some(MatchArg::ObjectPath(path.as_ref().to_owned()))),
+                Some(_) => parser.get_param().map(|_| None),
+            };
+            match arg {
+                Ok(arg) => args.push(arg),
+                Err(_) => break,
+            }
+        }
+        MatchArgs { args }
+    }
+
+    fn string(&self, idx: u8) -> Option<&str> {
+        match self.args.get(idx as usize) {
+            Some(Some(MatchArg::String(s))) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn string_or_path(&self, idx: u8) -> Option<&str> {
+        match self.args.get(idx as usize) {
+            Some(Some(MatchArg::String(s) | MatchArg::ObjectPath(s))) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// `path` is `namespace` or below it
+fn in_path_namespace(path: &str, namespace: &str) -> bool {
+    // the root namespace is the only one that ends with a slash
+    namespace == "/"
+        || path
+            .strip_prefix(namespace)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// The argNpath rule: equal, or one of them ends with a slash and is a prefix of the other
+fn path_args_match(arg: &str, value: &str) -> bool {
+    arg == value
+        || (value.ends_with('/') && arg.starts_with(value))
+        || (arg.ends_with('/') && value.starts_with(arg))
+}
+
+/// `name` is `namespace` or a name below it, e.g. `org.mpris.MediaPlayer2` is in `org.mpris`
+fn in_name_namespace(name: &str, namespace: &str) -> bool {
+    name.strip_prefix(namespace)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+impl MatchRule {
+    /// Check if the bus would route `msg` to a connection that added this rule, following the rules of the
+    /// [dbus spec](https://dbus.freedesktop.org/doc/dbus-specification.html#message-bus-routing-match-rules):
+    ///
+    /// * `path_namespace='/a/b'` matches `/a/b` and `/a/b/c` but not `/a/bc`. `path_namespace='/'` matches all paths.
+    /// * `argN` only matches string args, `argNpath` matches string and object path args that are equal to the value or of
+    ///   which one ends with `/` and is a prefix of the other.
+    /// * `arg0namespace='org.mpris'` matches a string arg0 of `org.mpris` and `org.mpris.MediaPlayer2` but not `org.mprisfoo`.
+    ///
+    /// The bus also matches a well known name in `sender` against the unique name of its owner. The `sender` header of
+    /// messages from the bus is always a unique name, so here a well known sender only matches messages that carry the
+    /// name itself. Use the unique name of the owner to match its signals.
+    ///
+    /// `eavesdrop` is ignored, it only decides which messages the bus considers and not whether they match.
+    pub fn matches(&self, msg: &MarshalledMessage) -> bool {
+        self.matches_header(msg)
+            && (!self.needs_args() || self.matches_args(&MatchArgs::parse(msg)))
+    }
+
+    /// Whether any of the conditions refer to the args of the message, which then need to be parsed
+    pub(crate) fn needs_args(&self) -> bool {
+        !self.args.is_empty() || !self.arg_paths.is_empty() || self.arg0namespace.is_some()
+    }
+
+    /// Check all conditions besides the args
+    pub(crate) fn matches_header(&self, msg: &MarshalledMessage) -> bool {
+        let hdr = &msg.dynheader;
+        let field_matches = |rule: &Option<String>, field: &Option<String>| match rule {
+            Some(rule) => field.as_deref() == Some(rule.as_str()),
+            None => true,
+        };
+        self.typ.is_none_or(|typ| typ == msg.typ)
+            && field_matches(&self.sender, &hdr.sender)
+            && field_matches(&self.interface, &hdr.interface)
+            && field_matches(&self.member, &hdr.member)
+            && field_matches(&self.path, &hdr.object)
+            && field_matches(&self.destination, &hdr.destination)
+            && self.path_namespace.as_ref().is_none_or(|namespace| {
+                hdr.object
+                    .as_deref()
+                    .is_some_and(|path| in_path_namespace(path, namespace))
+            })
+    }
+
+    pub(crate) fn matches_args(&self, args: &MatchArgs) -> bool {
+        self.args
+            .iter()
+            .all(|(idx, value)| args.string(*idx) == Some(value.as_str()))
+            && self.arg_paths.iter().all(|(idx, value)| {
+                args.string_or_path(*idx)
+                    .is_some_and(|arg| path_args_match(arg, value))
+            })
+            && self.arg0namespace.as_ref().is_none_or(|namespace| {
+                args.string(0)
+                    .is_some_and(|arg| in_name_namespace(arg, namespace))
+            })
+    }
+}
+
+fn write_quoted(f: &mut fmt::Formatter<'_>, val: &str) -> fmt::Result {
+    f.write_str("'")?;
+    // apostrophes can not appear within quotes, close the quotes, add an escaped apostrophe and reopen them
+    let mut parts = val.split('\'');
+    if let Some(first) = parts.next() {
+        f.write_str(first)?;
+    }
+    for part in parts {
+        f.write_str("'\\''")?;
+        f.write_str(part)?;
+    }
+    f.write_str("'")
+}
+
+impl fmt::Display for MatchRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        let mut write_pair = |f: &mut fmt::Formatter<'_>, key: &dyn fmt::Display, val: &str| {
+            if !first {
+                f.write_str(",")?;
+            }
+            first = false;
+            write!(f, "{}=", key)?;
+            write_quoted(f, val)
+        };
+
+        if let Some(typ) = self.typ {
+            let typ = match typ {
+                MessageType::Signal => "signal",
+                MessageType::Call => "method_call",
+                MessageType::Reply => "method_return",
+                MessageType::Error => "error",
+                MessageType::Invalid => unreachable!("Invalid is never stored"),
+            };
+            write_pair(f, &"type", typ)?;
+        }
+        let fields = [
+            ("sender", &self.sender),
+            ("interface", &self.interface),
+            ("member", &self.member),
+            ("path", &self.path),
+            ("path_namespace", &self.path_namespace),
+            ("destination", &self.destination),
+        ];
+        for (key, val) in fields {
+            if let Some(val) = val {
+                write_pair(f, &key, val)?;
+            }
+        }
+        for (idx, val) in &self.args {
+            write_pair(f, &format_args!("arg{}", idx), val)?;
+        }
+        for (idx, val) in &self.arg_paths {
+            write_pair(f, &format_args!("arg{}path", idx), val)?;
+        }
+        if let Some(val) = &self.arg0namespace {
+            write_pair(f, &"arg0namespace", val)?;
+        }
+        // false is the default, so only true is written
+        if self.eavesdrop {
+            write_pair(f, &"eavesdrop", "true")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_rule_string() {
+        assert_eq!(MatchRule::new().to_string(), "");
+        assert_eq!(
+            MatchRule::new().msg_type(MessageType::Signal).to_string(),
+            "type='signal'"
+        );
+
+        let rule = MatchRule::new()
+            .arg(3, "three")
+            .member("NameOwnerChanged")
+            .sender("org.freedesktop.DBus")
+            .interface("org.freedesktop.DBus")
+            .msg_type(MessageType::Signal)
+            .path("/org/freedesktop/DBus")
+            .arg(0, "zero")
+            .arg_path(1, "/a/b/")
+            .destination(":1.42")
+            .arg0namespace("org.mpris");
+        assert_eq!(
+            rule.to_string(),
+            "type='signal',sender='org.freedesktop.DBus',interface='org.freedesktop.DBus',\
+             member='NameOwnerChanged',path='/org/freedesktop/DBus',destination=':1.42',\
+             arg0='zero',arg3='three',arg1path='/a/b/',arg0namespace='org.mpris'"
+        );
+
+        assert_eq!(
+            MatchRule::new()
+                .msg_type(MessageType::Call)
+                .path_namespace("/a")
+                .to_string(),
+            "type='method_call',path_namespace='/a'"
+        );
+        assert_eq!(
+            MatchRule::new()
+                .msg_type(MessageType::Signal)
+                .msg_type(MessageType::Invalid)
+                .to_string(),
+            ""
+        );
+
+        // commas and backslashes are protected by the quotes, apostrophes need escaping
+        assert_eq!(
+            MatchRule::new().arg(0, "a,b\\c").to_string(),
+            "arg0='a,b\\c'"
+        );
+        assert_eq!(
+            MatchRule::new().arg(0, "it's").to_string(),
+            "arg0='it'\\''s'"
+        );
+        assert_eq!(MatchRule::new().arg(0, "'").to_string(), "arg0=''\\'''");
+    }
+
+    fn signal_at(path: &str, args: &[&str]) -> MarshalledMessage {
+        let mut msg = crate::MessageBuilder::new()
+            .signal("io.killing.spark", "Test", path)
+            .build();
+        for arg in args {
+            msg.body.push_param(arg).unwrap();
+        }
+        msg
+    }
+
+    #[test]
+    fn test_eavesdrop_string() {
+        assert_eq!(
+            MatchRule::new().eavesdrop(true).to_string(),
+            "eavesdrop='true'"
+        );
+        assert_eq!(MatchRule::new().eavesdrop(false).to_string(), "");
+        // the spec lists eavesdrop after all other keys
+        let rule = MatchRule::new()
+            .eavesdrop(true)
+            .arg0namespace("org.mpris")
+            .msg_type(MessageType::Call)
+            .arg(0, "it's")
+            .destination("org.freedesktop.Notifications");
+        assert_eq!(
+            rule.to_string(),
+            "type='method_call',destination='org.freedesktop.Notifications',arg0='it'\\''s',\
+             arg0namespace='org.mpris',eavesdrop='true'"
+        );
+        assert_ne!(rule, rule.clone().eavesdrop(false));
+    }
+
+    #[test]
+    fn test_path_namespace_matches() {
+        let rule = MatchRule::new().path_namespace("/io/killing");
+        assert!(rule.matches(&signal_at("/io/killing", &[])));
+        assert!(rule.matches(&signal_at("/io/killing/spark", &[])));
+        assert!(rule.matches(&signal_at("/io/killing/spark/deeper", &[])));
+        assert!(!rule.matches(&signal_at("/io/killingspark", &[])));
+        assert!(!rule.matches(&signal_at("/io", &[])));
+        assert!(!rule.matches(&signal_at("/", &[])));
+
+        let root = MatchRule::new().path_namespace("/");
+        assert!(root.matches(&signal_at("/", &[])));
+        assert!(root.matches(&signal_at("/io/killing", &[])));
+
+        // a call without a path can not be in any namespace
+        let mut msg = signal_at("/io/killing", &[]);
+        msg.dynheader.object = None;
+        assert!(!rule.matches(&msg));
+        assert!(!root.matches(&msg));
+    }
+
+    #[test]
+    fn test_header_matches() {
+        let mut msg = signal_at("/io/killing/spark", &[]);
+        msg.dynheader.sender = Some(":1.42".to_owned());
+        assert!(MatchRule::new().matches(&msg));
+        assert!(MatchRule::new()
+            .msg_type(MessageType::Signal)
+            .sender(":1.42")
+            .interface("io.killing.spark")
+            .member("Test")
+            .path("/io/killing/spark")
+            .matches(&msg));
+        assert!(!MatchRule::new().msg_type(MessageType::Call).matches(&msg));
+        assert!(!MatchRule::new().sender(":1.43").matches(&msg));
+        assert!(!MatchRule::new().member("Other").matches(&msg));
+        assert!(!MatchRule::new().path("/io/killing").matches(&msg));
+        // broadcast signals have no destination
+        assert!(!MatchRule::new().destination(":1.42").matches(&msg));
+    }
+
+    #[test]
+    fn test_arg_matches() {
+        let msg = signal_at("/", &["org.mpris.MediaPlayer2.vlc", "/a/b/c"]);
+        assert!(MatchRule::new()
+            .arg(0, "org.mpris.MediaPlayer2.vlc")
+            .matches(&msg));
+        assert!(!MatchRule::new().arg(0, "org.mpris").matches(&msg));
+        assert!(!MatchRule::new().arg(2, "").matches(&msg));
+
+        assert!(MatchRule::new().arg0namespace("org.mpris").matches(&msg));
+        assert!(MatchRule::new()
+            .arg0namespace("org.mpris.MediaPlayer2.vlc")
+            .matches(&msg));
+        assert!(!MatchRule::new().arg0namespace("org.mpr").matches(&msg));
+        assert!(!MatchRule::new()
+            .arg0namespace("org.mpris.Media")
+            .matches(&msg));
+
+        for value in ["/a/b/c", "/a/b/", "/"] {
+            assert!(
+                MatchRule::new().arg_path(1, value).matches(&msg),
+                "{}",
+                value
+            );
+        }
+        for value in ["/a/b", "/a/b/c/d", "/a/b/c/d/", "/a/b/cd/"] {
+            assert!(
+                !MatchRule::new().arg_path(1, value).matches(&msg),
+                "{}",
+                value
+            );
+        }
+        let dir = signal_at("/", &["", "/a/b/"]);
+        assert!(MatchRule::new().arg_path(1, "/a/b/c/d").matches(&dir));
+
+        // argN only matches strings but argNpath also matches object paths
+        let mut msg = signal_at("/", &[]);
+        msg.body
+            .push_param2(ObjectPath::new("/a/b").unwrap(), 5u32)
+            .unwrap();
+        msg.body.push_param("after").unwrap();
+        assert!(!MatchRule::new().arg(0, "/a/b").matches(&msg));
+        assert!(MatchRule::new().arg_path(0, "/a/").matches(&msg));
+        assert!(!MatchRule::new().arg0namespace("/a/b").matches(&msg));
+        assert!(!MatchRule::new().arg(1, "5").matches(&msg));
+        assert!(MatchRule::new().arg(2, "after").matches(&msg));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_match_rule_arg_out_of_range() {
+        let _ = MatchRule::new().arg(64, "nope");
+    }
+}
@@ -1,4 +1,5 @@
 //! Build new messages that you want to send over a connection
+use crate::alloc_prelude::*;
 use crate::params::message;
 use crate::signature::SignatureIter;
 use crate::wire::errors::MarshalError;
@@ -38,7 +39,7 @@ impl HeaderFlags {
     }
 
     pub fn is_set(self, flags: u8) -> bool {
-        flags & self.into_raw() == 1
+        flags & self.into_raw() != 0
     }
 
     pub fn set(self, flags: &mut u8) {
@@ -58,7 +59,7 @@ impl HeaderFlags {
 }
 
 /// The dynamic part of a dbus message header
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct DynamicHeader {
     pub interface: Option<String>,
     pub member: Option<String>,
@@ -80,7 +81,7 @@ impl DynamicHeader {
         error_msg: Option<String>,
     ) -> crate::message_builder::MarshalledMessage {
         let mut err_resp = crate::message_builder::MarshalledMessage {
-            typ: MessageType::Reply,
+            typ: MessageType::Error,
             dynheader: DynamicHeader {
                 interface: None,
                 member: None,
@@ -123,6 +124,83 @@ impl DynamicHeader {
     }
 }
 
+/// The complete header of a message, the fixed part and the header fields
+///
+/// A `MarshalledMessage` keeps the header fields in `dynheader` and the rest in `typ`, `flags` and the body. This puts
+/// all of them together, e.g. for logging or assertions. `wire::unmarshal::unmarshal_message_header` reads it from the
+/// raw bytes of a message, `MarshalledMessage::header` makes it for a message and `MarshalledMessage::from_header`
+/// puts a header and its body back together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageHeader {
+    pub byteorder: ByteOrder,
+    pub typ: MessageType,
+    pub flags: u8,
+    /// The protocol version, rustbus always writes 1
+    pub version: u8,
+    pub body_len: u32,
+    pub serial: u32,
+    /// The header fields. `fields.signature` and `fields.num_fds` are the fields as they are on the wire.
+    pub fields: DynamicHeader,
+}
+
+/// The flags byte of the message header as a set. Combine the flags with `|`.
+///
+/// ```rust
+/// use rustbus::message_builder::{MessageBuilder, MessageFlags};
+/// let call = MessageBuilder::new()
+///     .call("Notify")
+///     .on("/io/killing/spark")
+///     .with_flags(MessageFlags::NO_REPLY_EXPECTED | MessageFlags::NO_AUTO_START)
+///     .build();
+/// assert_eq!(call.flags, 0x3);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MessageFlags(u8);
+
+impl MessageFlags {
+    /// The caller does not want a reply, the receiver should not send one
+    pub const NO_REPLY_EXPECTED: Self = Self(1);
+    /// The bus should not start a service to receive this message
+    pub const NO_AUTO_START: Self = Self(2);
+    /// The caller is prepared to wait for an interactive authorization prompt
+    pub const ALLOW_INTERACTIVE_AUTHORIZATION: Self = Self(4);
+
+    pub fn empty() -> Self {
+        Self(0)
+    }
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl From<u8> for MessageFlags {
+    fn from(bits: u8) -> Self {
+        Self(bits)
+    }
+}
+
+impl From<HeaderFlags> for MessageFlags {
+    fn from(flag: HeaderFlags) -> Self {
+        Self(flag.into_raw())
+    }
+}
+
+impl core::ops::BitOr for MessageFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl core::ops::BitOrAssign for MessageFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
 /// Starting point for new messages. Create either a call or a signal
 #[derive(Default)]
 pub struct MessageBuilder {
@@ -189,6 +267,23 @@ impl CallBuilder {
         self
     }
 
+    /// Replace the header flags of the call
+    pub fn with_flags<F: Into<MessageFlags>>(mut self, flags: F) -> Self {
+        self.msg.flags = flags.into().bits();
+        self
+    }
+
+    /// Write an empty signature header field if the call has no arguments. By default the field is omitted then, as
+    /// the spec allows and dbus-daemon does. This is only useful to reproduce messages of implementations that always
+    /// write the field, receivers treat both the same.
+    ///
+    /// This sets `dynheader.signature` to an empty string, which is also what messages that were received with an
+    /// empty signature field have. They keep it when they are marshalled again.
+    pub fn with_empty_signature_field(mut self) -> Self {
+        self.msg.dynheader.signature = Some(String::new());
+        self
+    }
+
     pub fn build(self) -> MarshalledMessage {
         self.msg
     }
@@ -200,6 +295,13 @@ impl SignalBuilder {
         self
     }
 
+    /// Write an empty signature header field if the signal has no arguments, see
+    /// `CallBuilder::with_empty_signature_field`
+    pub fn with_empty_signature_field(mut self) -> Self {
+        self.msg.dynheader.signature = Some(String::new());
+        self
+    }
+
     pub fn build(self) -> MarshalledMessage {
         self.msg
     }
@@ -235,6 +337,185 @@ impl MarshalledMessage {
         &self.body.sig
     }
 
+    /// The header this message is marshalled with. The serial is 0 if `dynheader.serial` is not set yet, the
+    /// signature and unix fds fields are set like the marshaller writes them.
+    pub fn header(&self) -> MessageHeader {
+        let mut fields = self.dynheader.clone();
+        // see `wire::marshal::marshal` for when the signature field is written
+        fields.signature = if !self.body.buf.is_empty() || fields.signature.as_deref() == Some("") {
+            Some(self.get_sig().to_owned())
+        } else {
+            None
+        };
+        fields.num_fds = if self.body.raw_fds.is_empty() {
+            None
+        } else {
+            Some(self.body.raw_fds.len() as u32)
+        };
+        MessageHeader {
+            byteorder: self.body.byteorder,
+            typ: self.typ,
+            flags: self.flags,
+            version: 1,
+            body_len: self.body.buf.len() as u32,
+            serial: self.dynheader.serial.unwrap_or(0),
+            fields,
+        }
+    }
+
+    /// Put a message back together from its header and the bytes of its body, e.g. as returned by
+    /// `wire::unmarshal::unmarshal_message_header`. The body must be exactly `header.body_len` bytes long and there must
+    /// be as many fds as the unix fds header field announces, otherwise `UnmarshalError::FdCountMismatch` is returned.
+    /// Marshalling the message again with `header.serial` gives back the same bytes if the message was written by
+    /// rustbus.
+    ///
+    /// The body is not checked against the signature of the header, that happens when it is read.
+    pub fn from_header(
+        header: MessageHeader,
+        body: Vec<u8>,
+        fds: Vec<UnixFd>,
+    ) -> Result<MarshalledMessage, UnmarshalError> {
+        let body_len = header.body_len as usize;
+        if body.len() < body_len {
+            return Err(UnmarshalError::NotEnoughBytes);
+        }
+        if body.len() > body_len {
+            return Err(UnmarshalError::NotAllBytesUsed);
+        }
+        let declared = header.fields.num_fds.unwrap_or(0) as usize;
+        if fds.len() != declared {
+            return Err(UnmarshalError::FdCountMismatch {
+                declared,
+                received: fds.len(),
+            });
+        }
+        let mut dynheader = header.fields;
+        dynheader.serial = Some(header.serial);
+        let sig = dynheader.signature.clone().unwrap_or_default();
+        Ok(MarshalledMessage {
+            body: MarshalledMessageBody::from_parts(body, fds, sig, header.byteorder),
+            dynheader,
+            typ: header.typ,
+            flags: header.flags,
+        })
+    }
+
+    /// Copy the message but give it a new serial, e.g. to send it again after sending it failed. Sending a message
+    /// twice with the same serial would confuse the matching of replies.
+    ///
+    /// The body is copied as is without marshalling it again. The serial only ends up in the fixed part of the header,
+    /// which is written when the message is sent. The copy refers to the same unix fds as the original, they are closed
+    /// once both are dropped.
+    pub fn clone_with_serial(&self, serial: u32) -> MarshalledMessage {
+        let mut dynheader = self.dynheader.clone();
+        dynheader.serial = Some(serial);
+        MarshalledMessage {
+            body: MarshalledMessageBody {
+                buf: self.body.buf.clone(),
+                raw_fds: self.body.raw_fds.clone(),
+                sig: self.body.sig.clone(),
+                byteorder: self.body.byteorder,
+            },
+            dynheader,
+            typ: self.typ,
+            flags: self.flags,
+        }
+    }
+
+    /// The object path the message is sent to or emitted from
+    #[inline]
+    pub fn path(&self) -> Option<&str> {
+        self.dynheader.object.as_deref()
+    }
+    #[inline]
+    pub fn interface(&self) -> Option<&str> {
+        self.dynheader.interface.as_deref()
+    }
+    /// The name of the called method or the emitted signal
+    #[inline]
+    pub fn member(&self) -> Option<&str> {
+        self.dynheader.member.as_deref()
+    }
+    /// The unique name of the sending connection. This is filled in by the bus.
+    #[inline]
+    pub fn sender(&self) -> Option<&str> {
+        self.dynheader.sender.as_deref()
+    }
+    #[inline]
+    pub fn destination(&self) -> Option<&str> {
+        self.dynheader.destination.as_deref()
+    }
+    /// The signature of the body, `None` if the body is empty. Unlike the other accessors this is read from the body,
+    /// so it is also correct for messages that are still being built.
+    #[inline]
+    pub fn signature(&self) -> Option<&str> {
+        Some(self.get_sig()).filter(|sig| !sig.is_empty())
+    }
+    /// The serial of the call this message is a reply or error to
+    #[inline]
+    pub fn reply_serial(&self) -> Option<u32> {
+        self.dynheader.response_serial
+    }
+
+    // The setters validate the new value right away. The header is marshalled from these fields every time the
+    // message is sent, so changing them does not touch the already marshalled body.
+
+    /// Replace or remove (`None`) the destination, e.g. to forward the message to another peer
+    pub fn set_destination(
+        &mut self,
+        destination: Option<String>,
+    ) -> Result<(), crate::params::validation::Error> {
+        if let Some(destination) = &destination {
+            crate::params::validate_busname(destination)?;
+        }
+        self.dynheader.destination = destination;
+        Ok(())
+    }
+    /// Replace or remove (`None`) the sender. Buses overwrite it, but peer to peer connections pass it on as is.
+    pub fn set_sender(
+        &mut self,
+        sender: Option<String>,
+    ) -> Result<(), crate::params::validation::Error> {
+        if let Some(sender) = &sender {
+            crate::params::validate_busname(sender)?;
+        }
+        self.dynheader.sender = sender;
+        Ok(())
+    }
+    /// Replace or remove (`None`) the object path
+    pub fn set_path(
+        &mut self,
+        path: Option<String>,
+    ) -> Result<(), crate::params::validation::Error> {
+        if let Some(path) = &path {
+            crate::params::validate_object_path(path)?;
+        }
+        self.dynheader.object = path;
+        Ok(())
+    }
+    /// Replace or remove (`None`) the interface
+    pub fn set_interface(
+        &mut self,
+        interface: Option<String>,
+    ) -> Result<(), crate::params::validation::Error> {
+        if let Some(interface) = &interface {
+            crate::params::validate_interface(interface)?;
+        }
+        self.dynheader.interface = interface;
+        Ok(())
+    }
+    /// Replace or remove (`None`) the member
+    pub fn set_member(
+        &mut self,
+        member: Option<String>,
+    ) -> Result<(), crate::params::validation::Error> {
+        if let Some(member) = &member {
+            crate::params::validate_membername(member)?;
+        }
+        self.dynheader.member = member;
+        Ok(())
+    }
+
     /// New message with the default native byteorder
     pub fn new() -> Self {
         MarshalledMessage {
@@ -263,21 +544,51 @@ impl MarshalledMessage {
         self.body.reserve(additional)
     }
 
-    pub fn unmarshall_all<'a, 'e>(self) -> Result<message::Message<'a, 'e>, UnmarshalError> {
-        let params = if self.body.sig.is_empty() {
-            vec![]
-        } else {
-            let sigs: Vec<_> = crate::signature::Type::parse_description(&self.body.sig)?;
+    /// Unmarshal the whole body into `Param`s according to the signature in the header, for messages whose signature
+    /// is only known at runtime. A body without arguments results in an empty vec.
+    ///
+    /// Errors with `UnmarshalError::NotAllBytesUsed` if there are bytes left after the last argument.
+    pub fn unmarshal_all<'a, 'e>(
+        &self,
+    ) -> Result<Vec<crate::params::Param<'a, 'e>>, UnmarshalError> {
+        self.unmarshal_all_into(Default::default())
+    }
 
-            let (_, params) = crate::wire::unmarshal::unmarshal_body(
-                self.body.byteorder,
-                &sigs,
-                &self.body.buf,
-                &self.body.raw_fds,
-                0,
-            )?;
-            params
-        };
+    /// Like `unmarshal_all` but the strings, object paths and signatures are allocated in `arena`, see
+    /// `unmarshal_with_sig_in`.
+    #[cfg(feature = "arena")]
+    pub fn unmarshal_all_in<'a>(
+        &self,
+        arena: &'a bumpalo::Bump,
+    ) -> Result<Vec<crate::params::Param<'a, 'a>>, UnmarshalError> {
+        self.unmarshal_all_into(crate::wire::unmarshal::base::StringStore::arena(arena))
+    }
+
+    fn unmarshal_all_into<'a, 'e>(
+        &self,
+        strings: crate::wire::unmarshal::base::StringStore<'a>,
+    ) -> Result<Vec<crate::params::Param<'a, 'e>>, UnmarshalError> {
+        if self.body.sig.is_empty() {
+            return Ok(vec![]);
+        }
+        let sigs: Vec<_> = crate::signature::Type::parse_description(&self.body.sig)?;
+
+        let (bytes_used, params) = crate::wire::unmarshal::unmarshal_body_into(
+            self.body.byteorder,
+            &sigs,
+            &self.body.buf,
+            &self.body.raw_fds,
+            0,
+            strings,
+        )?;
+        if bytes_used != self.body.buf.len() {
+            return Err(UnmarshalError::NotAllBytesUsed);
+        }
+        Ok(params)
+    }
+
+    pub fn unmarshall_all<'a, 'e>(self) -> Result<message::Message<'a, 'e>, UnmarshalError> {
+        let params = self.unmarshal_all()?;
         Ok(message::Message {
             dynheader: self.dynheader,
             params,
@@ -286,7 +597,156 @@ impl MarshalledMessage {
             raw_fds: self.body.raw_fds,
         })
     }
+
+    /// Make a method return for this call. The type, the destination (the sender of the call) and the reply serial
+    /// (the serial of the call) are filled in.
+    ///
+    /// Only the return values still need to be pushed to the body. The serial of the reply itself is chosen when it
+    /// is sent. Calls that have `HeaderFlags::NoReplyExpected` set should not be answered at all.
+    ///
+    /// ```rust
+    /// use rustbus::{MessageBuilder, MessageType};
+    /// let mut call = MessageBuilder::new()
+    ///     .call("Add")
+    ///     .with_interface("io.killing.spark")
+    ///     .on("/io/killing/spark")
+    ///     .build();
+    /// call.dynheader.serial = Some(7);
+    /// call.dynheader.sender = Some(":1.42".to_owned());
+    ///
+    /// let mut reply = call.make_reply();
+    /// reply.body.push_param(3u32).unwrap();
+    /// assert_eq!(reply.typ, MessageType::Reply);
+    /// assert_eq!(reply.dynheader.response_serial, Some(7));
+    /// assert_eq!(reply.dynheader.destination.as_deref(), Some(":1.42"));
+    /// ```
+    pub fn make_reply(&self) -> MarshalledMessage {
+        self.dynheader.make_response()
+    }
+
+    /// Like `make_reply` but makes an error with the error name `name`. The description of the error should be pushed
+    /// to the body as the only argument, see `standard_messages::error`.
+    pub fn make_error_reply<S: Into<String>>(&self, name: S) -> MarshalledMessage {
+        self.dynheader.make_error_response(name, None)
+    }
+
+    /// The header flags of the message
+    #[inline]
+    pub fn message_flags(&self) -> MessageFlags {
+        MessageFlags(self.flags)
+    }
+
+    /// Get the name and description out of an error message. Returns `None` for all other message types.
+    ///
+    /// The description is the first argument if it is a string, like the spec recommends. Errors without one have
+    /// `message: None`.
+    pub fn as_error(&self) -> Option<DBusError> {
+        if self.typ != MessageType::Error {
+            return None;
+        }
+        let name = self.dynheader.error_name.clone()?;
+        let message = if self.get_sig().starts_with('s') {
+            self.body.get_first::<String>().ok()
+        } else {
+            None
+        };
+        Some(DBusError { name, message })
+    }
+}
+
+/// An error reply as sent by the peer of a failed call, see `MarshalledMessage::as_error`
+/// and `standard_messages::error`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DBusError {
+    /// The error name from the header, like `org.freedesktop.DBus.Error.UnknownMethod`
+    pub name: String,
+    /// The human readable description, if the error had one
+    pub message: Option<String>,
+}
+
+impl core::fmt::Display for DBusError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{}: {}", self.name, message),
+            None => f.write_str(&self.name),
+        }
+    }
+}
+
+impl core::error::Error for DBusError {}
+
+/// The arguments of a method call, see `MarshalledMessageBody::push_args`.
+///
+/// Implemented for `()` and tuples of up to twelve `Marshal` values, like `Marshal` itself. Each element of the tuple becomes its own argument,
+/// so a single argument is passed as `(arg,)` and a struct argument as `((a, b),)`.
+pub trait MethodArgs {
+    fn push_args(self, body: &mut MarshalledMessageBody) -> Result<(), MarshalError>;
+}
+
+impl MethodArgs for () {
+    fn push_args(self, _body: &mut MarshalledMessageBody) -> Result<(), MarshalError> {
+        Ok(())
+    }
+}
+
+macro_rules! method_args_impls {
+    ($($name:ident $idx:tt),+) => {
+        impl<$($name: Marshal),+> MethodArgs for ($($name,)+) {
+            fn push_args(self, body: &mut MarshalledMessageBody) -> Result<(), MarshalError> {
+                $(body.push_param(self.$idx)?;)+
+                Ok(())
+            }
+        }
+    };
+}
+
+method_args_impls!(A1 0);
+method_args_impls!(A1 0, A2 1);
+method_args_impls!(A1 0, A2 1, A3 2);
+method_args_impls!(A1 0, A2 1, A3 2, A4 3);
+method_args_impls!(A1 0, A2 1, A3 2, A4 3, A5 4);
+method_args_impls!(A1 0, A2 1, A3 2, A4 3, A5 4, A6 5);
+method_args_impls!(A1 0, A2 1, A3 2, A4 3, A5 4, A6 5, A7 6);
+method_args_impls!(A1 0, A2 1, A3 2, A4 3, A5 4, A6 5, A7 6, A8 7);
+method_args_impls!(A1 0, A2 1, A3 2, A4 3, A5 4, A6 5, A7 6, A8 7, A9 8);
+method_args_impls!(A1 0, A2 1, A3 2, A4 3, A5 4, A6 5, A7 6, A8 7, A9 8, A10 9);
+method_args_impls!(A1 0, A2 1, A3 2, A4 3, A5 4, A6 5, A7 6, A8 7, A9 8, A10 9, A11 10);
+method_args_impls!(A1 0, A2 1, A3 2, A4 3, A5 4, A6 5, A7 6, A8 7, A9 8, A10 9, A11 10, A12 11);
+
+/// The return value of a method, decoded from the body of the reply.
+///
+/// `()` accepts any reply and ignores its body, every owned `Unmarshal` type is decoded from the first value of the body
+/// like `MarshalledMessageBody::get_first` does. Only that first value is decoded: a tuple stands for a single struct
+/// argument, not for several out arguments. For methods with more out arguments ask for the `MarshalledMessageBody`
+/// itself and decode it with `MessageBodyParser::get2` and friends.
+pub trait FromReply: Sized {
+    fn from_reply(body: &MarshalledMessageBody) -> Result<Self, UnmarshalError>;
+}
+
+impl FromReply for () {
+    fn from_reply(_body: &MarshalledMessageBody) -> Result<Self, UnmarshalError> {
+        Ok(())
+    }
+}
+
+impl<T: for<'a> Unmarshal<'a, 'a>> FromReply for T {
+    fn from_reply(body: &MarshalledMessageBody) -> Result<Self, UnmarshalError> {
+        body.parser().get()
+    }
+}
+
+/// A copy of the whole body, referring to the same unix fds
+impl FromReply for MarshalledMessageBody {
+    fn from_reply(body: &MarshalledMessageBody) -> Result<Self, UnmarshalError> {
+        Ok(MarshalledMessageBody {
+            buf: body.buf.clone(),
+            raw_fds: body.raw_fds.clone(),
+            sig: body.sig.clone(),
+            byteorder: body.byteorder,
+        })
+    }
 }
+
 /// The body accepts everything that implements the Marshal trait (e.g. all basic types, strings, slices, Hashmaps,.....)
 /// And you can of course write an Marshal impl for your own datastrcutures
 #[derive(Debug)]
@@ -353,6 +813,16 @@ impl MarshalledMessageBody {
         }
     }
 
+    /// New messagebody with the default native byteorder and space reserved for `capacity` bytes
+    pub fn with_capacity(capacity: usize) -> Self {
+        MarshalledMessageBody {
+            buf: Vec::with_capacity(capacity),
+            raw_fds: Vec::new(),
+            sig: SignatureBuffer::new(),
+            byteorder: ByteOrder::NATIVE,
+        }
+    }
+
     pub fn from_parts(
         buf: Vec<u8>,
         raw_fds: Vec<crate::wire::UnixFd>,
@@ -408,7 +878,7 @@ impl MarshalledMessageBody {
         }
         Ok(())
     }
-    fn create_ctx(&mut self) -> MarshalContext {
+    fn create_ctx(&mut self) -> MarshalContext<'_, '_> {
         MarshalContext {
             buf: &mut self.buf,
             fds: &mut self.raw_fds,
@@ -416,9 +886,12 @@ impl MarshalledMessageBody {
         }
     }
 
-    /// Append something that is Marshal to the message body
+    /// Append something that is Marshal to the message body.
+    ///
+    /// Space in the buffer is reserved according to `Marshal::estimate_size` before marshalling.
     pub fn push_param<P: Marshal>(&mut self, p: P) -> Result<(), MarshalError> {
         let mut ctx = self.create_ctx();
+        ctx.reserve(p.estimate_size());
         p.marshal(&mut ctx)?;
         P::sig_str(&mut self.sig);
         Ok(())
@@ -506,6 +979,12 @@ impl MarshalledMessageBody {
         })
     }
 
+    /// Append the arguments of a method call, each element of the tuple as its own param. If one of them fails the body
+    /// is reset to the state before the call.
+    pub fn push_args<A: MethodArgs>(&mut self, args: A) -> Result<(), MarshalError> {
+        self.push_mult_helper(move |msg: &mut Self| args.push_args(msg))
+    }
+
     /// Append any number of things that have the same type that is Marshal to the message body
     pub fn push_params<P: Marshal>(&mut self, params: &[P]) -> Result<(), MarshalError> {
         for p in params {
@@ -537,13 +1016,57 @@ impl MarshalledMessageBody {
             Err(UnmarshalError::NotAllBytesUsed)
         }
     }
+    /// The range of bytes in the buffer that each param of the body takes up, following the signature of the body.
+    /// See `validate_raw::arg_ranges`.
+    ///
+    /// ```rust
+    /// let mut msg = rustbus::MessageBuilder::new()
+    ///     .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+    ///     .build();
+    /// msg.body.push_param2(1u8, "forward me").unwrap();
+    /// let ranges = msg.body.arg_ranges().unwrap();
+    /// assert_eq!(ranges, [0..1, 4..19]);
+    /// assert_eq!(&msg.get_buf()[ranges[1].start + 4..ranges[1].end - 1], b"forward me");
+    /// ```
+    pub fn arg_ranges(&self) -> Result<Vec<core::ops::Range<usize>>, UnmarshalError> {
+        let types = crate::signature::Type::parse_description(&self.sig)?;
+        validate_raw::arg_ranges(self.byteorder, &self.buf, &types).map_err(|(_, e)| e)
+    }
+    /// Render the content of the body as a human readable tree, one value per line with its type. Useful for debugging
+    /// signature mismatches. If the content does not match the signature the rest is shown as `<unparseable>`.
+    ///
+    /// ```rust
+    /// let mut body = rustbus::message_builder::MarshalledMessageBody::new();
+    /// body.push_param2("key", &[1u32, 2][..]).unwrap();
+    /// assert_eq!(body.dump(), "s \"key\"\nau [\n  u 1\n  u 2\n]\n");
+    /// ```
+    pub fn dump(&self) -> String {
+        crate::params::dump::dump_body(self, &self.sig)
+    }
+
     /// Create a parser to retrieve parameters from the body.
     #[inline]
-    pub fn parser(&self) -> MessageBodyParser {
+    pub fn parser(&self) -> MessageBodyParser<'_> {
         MessageBodyParser::new(self)
     }
+
+    /// Decode only the first param of the body. The rest of the body is not looked at, which makes this cheap for
+    /// handlers that dispatch on the first argument like `arg0` match rules do.
+    ///
+    /// To decode more than one param use the `get2()`, `get3()`... methods of the `parser()`, which also leave
+    /// everything after the requested params unread.
+    ///
+    /// ```rust
+    /// let mut body = rustbus::message_builder::MarshalledMessageBody::new();
+    /// body.push_param3("org.mpris.MediaPlayer2.Player", 7u8, 42u64).unwrap();
+    /// assert_eq!(body.get_first::<&str>().unwrap(), "org.mpris.MediaPlayer2.Player");
+    /// ```
+    pub fn get_first<'a, T: Unmarshal<'a, 'a>>(&'a self) -> Result<T, UnmarshalError> {
+        self.parser().get()
+    }
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn test_marshal_trait() {
     let mut body = MarshalledMessageBody::new();
@@ -649,12 +1172,18 @@ fn test_marshal_trait() {
         std::collections::HashMap<String, std::collections::HashMap<String, u64>>;
     assert_eq!(
         body_iter.get::<WrongNestedDict>().err().unwrap(),
-        UnmarshalError::WrongSignature
+        UnmarshalError::WrongSignature {
+            expected: "a{sa{st}}".to_owned(),
+            actual: "a{sa{su}}".to_owned(),
+        }
     );
     type WrongStruct = (u64, i32, String);
     assert_eq!(
         body_iter.get::<WrongStruct>().err().unwrap(),
-        UnmarshalError::WrongSignature
+        UnmarshalError::WrongSignature {
+            expected: "(tis)".to_owned(),
+            actual: "a{sa{su}}".to_owned(),
+        }
     );
 
     // the get the correct type and make sure the content is correct
@@ -667,11 +1196,17 @@ fn test_marshal_trait() {
     // again try some stuff that has the wrong signature
     assert_eq!(
         body_iter.get::<WrongNestedDict>().err().unwrap(),
-        UnmarshalError::WrongSignature
+        UnmarshalError::WrongSignature {
+            expected: "a{sa{st}}".to_owned(),
+            actual: "a{su}".to_owned(),
+        }
     );
     assert_eq!(
         body_iter.get::<WrongStruct>().err().unwrap(),
-        UnmarshalError::WrongSignature
+        UnmarshalError::WrongSignature {
+            expected: "(tis)".to_owned(),
+            actual: "a{su}".to_owned(),
+        }
     );
 
     // get the empty map next
@@ -682,7 +1217,10 @@ fn test_marshal_trait() {
     let mut body_iter = body.parser();
     assert_eq!(
         body_iter.get2::<NestedDict, u16>().unwrap_err(),
-        UnmarshalError::WrongSignature
+        UnmarshalError::WrongSignature {
+            expected: "q".to_owned(),
+            actual: "a{su}".to_owned(),
+        }
     );
     assert_eq!(
         body_iter
@@ -744,20 +1282,64 @@ use crate::wire::unmarshal::traits::Unmarshal;
 /// that you can use to get the params one by one, calling `get::<T>` until you have obtained all the parameters.
 /// If you try to get more parameters than the signature has types, it will return None, if you try to get a parameter that doesn not
 /// fit the current one, it will return an Error::WrongSignature, but you can safely try other types, the iterator stays valid.
+///
+/// The signatures are compared before anything is decoded, so a mismatch is reported as
+/// `UnmarshalError::WrongSignature { expected, actual }` instead of an error from somewhere inside the value.
 #[derive(Debug)]
 pub struct MessageBodyParser<'body> {
     buf_idx: usize,
     sig_idx: usize,
     body: &'body MarshalledMessageBody,
+    strict: bool,
+    check_signatures: bool,
 }
 
+/// The parts of `Signature` needed to check a requested type against the message signature
+type SigCheck = (fn(&str) -> bool, fn(&mut SignatureBuffer));
+
 impl<'fds, 'body: 'fds> MessageBodyParser<'body> {
     pub fn new(body: &'body MarshalledMessageBody) -> Self {
         Self {
             buf_idx: 0,
             sig_idx: 0,
             body,
+            strict: false,
+            check_signatures: true,
+        }
+    }
+
+    /// Reject padding bytes that are not zero with `UnmarshalError::NonZeroPadding`. By default they are skipped.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Compare the requested types against the message signature before decoding. Enabled by default.
+    ///
+    /// Disabling this allows decoding only a prefix of a param, e.g. the first fields of a struct. The parser still
+    /// moves on by one whole param of the signature, so its position is only meaningful for the following params if
+    /// the decoded type consumed the whole param.
+    pub fn check_signatures(mut self, check: bool) -> Self {
+        self.check_signatures = check;
+        self
+    }
+
+    /// Compare the types against the next params of the signature, without decoding anything
+    fn check_sigs(&self, checks: &[SigCheck]) -> Result<(), UnmarshalError> {
+        if !self.check_signatures {
+            return Ok(());
         }
+        for (actual, (has_sig, sig_str)) in self.sig_iter().zip(checks) {
+            if !has_sig(actual) {
+                let mut expected = SignatureBuffer::new();
+                sig_str(&mut expected);
+                return Err(UnmarshalError::WrongSignature {
+                    expected: expected.as_ref().to_owned(),
+                    actual: actual.to_owned(),
+                });
+            }
+        }
+        Ok(())
     }
 
     #[inline(always)]
@@ -780,16 +1362,15 @@ impl<'fds, 'body: 'fds> MessageBodyParser<'body> {
     /// This checks if there are params left in the message and if the type you requested fits the signature of the message.
     pub fn get<T: Unmarshal<'body, 'fds>>(&mut self) -> Result<T, UnmarshalError> {
         if let Some(expected_sig) = self.get_next_sig() {
-            if !T::has_sig(expected_sig) {
-                return Err(UnmarshalError::WrongSignature);
-            }
+            self.check_sigs(&[(T::has_sig, T::sig_str)])?;
 
-            let mut ctx = UnmarshalContext {
-                byteorder: self.body.byteorder,
-                buf: &self.body.buf,
-                offset: self.buf_idx,
-                fds: &self.body.raw_fds,
-            };
+            let mut ctx = UnmarshalContext::new(
+                &self.body.raw_fds,
+                &self.body.buf,
+                self.body.byteorder,
+                self.buf_idx,
+            );
+            ctx.set_strict(self.strict);
             match T::unmarshal(&mut ctx) {
                 Ok((bytes, res)) => {
                     self.buf_idx += bytes;
@@ -803,13 +1384,18 @@ impl<'fds, 'body: 'fds> MessageBodyParser<'body> {
         }
     }
     /// Perform error handling for `get2(), get3()...` if `get_calls` fails.
-    fn get_mult_helper<T, F>(&mut self, count: usize, get_calls: F) -> Result<T, UnmarshalError>
+    fn get_mult_helper<T, F>(
+        &mut self,
+        checks: &[SigCheck],
+        get_calls: F,
+    ) -> Result<T, UnmarshalError>
     where
         F: FnOnce(&mut Self) -> Result<T, UnmarshalError>,
     {
-        if count > self.sigs_left() {
+        if checks.len() > self.sigs_left() {
             return Err(UnmarshalError::EndOfMessage);
         }
+        self.check_sigs(checks)?;
         let start_sig_idx = self.sig_idx;
         let start_buf_idx = self.buf_idx;
         match get_calls(self) {
@@ -834,7 +1420,10 @@ impl<'fds, 'body: 'fds> MessageBodyParser<'body> {
             let ret2 = parser.get()?;
             Ok((ret1, ret2))
         };
-        self.get_mult_helper(2, get_calls)
+        self.get_mult_helper(
+            &[(T1::has_sig, T1::sig_str), (T2::has_sig, T2::sig_str)],
+            get_calls,
+        )
     }
 
     /// Get the next three params, use get3::<TYPE, TYPE, TYPE> to specify what type you expect. For example `let s = parser.get3::<String, i32, u64>()?;`
@@ -851,7 +1440,14 @@ impl<'fds, 'body: 'fds> MessageBodyParser<'body> {
             let ret3 = parser.get()?;
             Ok((ret1, ret2, ret3))
         };
-        self.get_mult_helper(3, get_calls)
+        self.get_mult_helper(
+            &[
+                (T1::has_sig, T1::sig_str),
+                (T2::has_sig, T2::sig_str),
+                (T3::has_sig, T3::sig_str),
+            ],
+            get_calls,
+        )
     }
 
     /// Get the next four params, use get4::<TYPE, TYPE, TYPE, TYPE> to specify what type you expect. For example `let s = parser.get4::<String, i32, u64, u8>()?;`
@@ -870,7 +1466,15 @@ impl<'fds, 'body: 'fds> MessageBodyParser<'body> {
             let ret4 = parser.get()?;
             Ok((ret1, ret2, ret3, ret4))
         };
-        self.get_mult_helper(4, get_calls)
+        self.get_mult_helper(
+            &[
+                (T1::has_sig, T1::sig_str),
+                (T2::has_sig, T2::sig_str),
+                (T3::has_sig, T3::sig_str),
+                (T4::has_sig, T4::sig_str),
+            ],
+            get_calls,
+        )
     }
 
     /// Get the next five params, use get5::<TYPE, TYPE, TYPE, TYPE, TYPE> to specify what type you expect. For example `let s = parser.get4::<String, i32, u64, u8, bool>()?;`
@@ -891,19 +1495,29 @@ impl<'fds, 'body: 'fds> MessageBodyParser<'body> {
             let ret5 = parser.get()?;
             Ok((ret1, ret2, ret3, ret4, ret5))
         };
-        self.get_mult_helper(5, get_calls)
+        self.get_mult_helper(
+            &[
+                (T1::has_sig, T1::sig_str),
+                (T2::has_sig, T2::sig_str),
+                (T3::has_sig, T3::sig_str),
+                (T4::has_sig, T4::sig_str),
+                (T5::has_sig, T5::sig_str),
+            ],
+            get_calls,
+        )
     }
 
     /// Get the next (old_style) param.
     /// This checks if there are params left in the message and if the type you requested fits the signature of the message.
-    pub fn get_param(&mut self) -> Result<crate::params::Param, UnmarshalError> {
+    pub fn get_param(&mut self) -> Result<crate::params::Param<'_, '_>, UnmarshalError> {
         if let Some(sig_str) = self.get_next_sig() {
-            let mut ctx = UnmarshalContext {
-                byteorder: self.body.byteorder,
-                buf: &self.body.buf,
-                offset: self.buf_idx,
-                fds: &self.body.raw_fds,
-            };
+            let mut ctx = UnmarshalContext::new(
+                &self.body.raw_fds,
+                &self.body.buf,
+                self.body.byteorder,
+                self.buf_idx,
+            );
+            ctx.set_strict(self.strict);
 
             let sig = &crate::signature::Type::parse_description(sig_str).unwrap()[0];
 
@@ -923,6 +1537,196 @@ impl<'fds, 'body: 'fds> MessageBodyParser<'body> {
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_unmarshal_all() {
+        use crate::params::{Base, Container, Param};
+        use crate::wire::errors::UnmarshalError;
+        use crate::wire::marshal::traits::Variant;
+        use std::collections::HashMap;
+
+        let mut msg = super::MessageBuilder::new()
+            .signal("io.killingspark", "Signal", "/io/killingspark/Signaler")
+            .build();
+        assert_eq!(msg.unmarshal_all(), Ok(vec![]));
+
+        let mut dict = HashMap::new();
+        dict.insert("nested", Variant((7i32, vec!["a", "b"])));
+        msg.body.push_param2("first", &dict).unwrap();
+        assert_eq!(msg.get_sig(), "sa{sv}");
+
+        let params = msg.unmarshal_all().unwrap();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0], Param::Base(Base::String("first".to_owned())));
+        let dict = match &params[1] {
+            Param::Container(Container::Dict(dict)) => dict,
+            other => panic!("expected a dict, got {:?}", other),
+        };
+        assert_eq!(dict.value_sig.to_string(), "v");
+        let variant = match &dict.map[&Base::String("nested".to_owned())] {
+            Param::Container(Container::Variant(variant)) => variant,
+            other => panic!("expected a variant, got {:?}", other),
+        };
+        assert_eq!(variant.sig.to_string(), "(ias)");
+        let fields = match &variant.value {
+            Param::Container(Container::Struct(fields)) => fields,
+            other => panic!("expected a struct, got {:?}", other),
+        };
+        assert_eq!(fields[0], Param::Base(Base::Int32(7)));
+        match &fields[1] {
+            Param::Container(Container::Array(array)) => assert_eq!(
+                array.values,
+                vec![
+                    Param::Base(Base::String("a".to_owned())),
+                    Param::Base(Base::String("b".to_owned()))
+                ]
+            ),
+            other => panic!("expected an array, got {:?}", other),
+        }
+
+        msg.body.buf.extend_from_slice(&[0, 0, 0, 0]);
+        assert_eq!(msg.unmarshal_all(), Err(UnmarshalError::NotAllBytesUsed));
+    }
+
+    #[cfg(feature = "arena")]
+    #[test]
+    fn test_unmarshal_all_in() {
+        use crate::params::{Base, Container, Param};
+        use crate::wire::{ObjectPath, SignatureWrapper};
+
+        let mut msg = super::MessageBuilder::new()
+            .signal("io.killingspark", "Signal", "/io/killingspark/Signaler")
+            .build();
+        msg.body
+            .push_param3(
+                "first",
+                ObjectPath::new("/io/killingspark").unwrap(),
+                (SignatureWrapper::new("a{sv}").unwrap(), vec!["a", "b"]),
+            )
+            .unwrap();
+
+        let mut arena = bumpalo::Bump::new();
+        for _ in 0..2 {
+            let params = msg.unmarshal_all_in(&arena).unwrap();
+            assert_eq!(params, msg.unmarshal_all().unwrap());
+            assert!(matches!(params[0], Param::Base(Base::StringRef("first"))));
+            assert!(matches!(
+                params[1],
+                Param::Base(Base::ObjectPathRef("/io/killingspark"))
+            ));
+            let fields = match &params[2] {
+                Param::Container(Container::Struct(fields)) => fields,
+                other => panic!("expected a struct, got {:?}", other),
+            };
+            assert!(matches!(
+                fields[0],
+                Param::Base(Base::SignatureRef("a{sv}"))
+            ));
+            match &fields[1] {
+                Param::Container(Container::Array(array)) => {
+                    assert!(matches!(array.values[1], Param::Base(Base::StringRef("b"))))
+                }
+                other => panic!("expected an array, got {:?}", other),
+            }
+            assert!(arena.allocated_bytes() > 0);
+            drop(params);
+            arena.reset();
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_message_header() {
+        use super::{MarshalledMessage, MessageBuilder, MessageType, UnixFd};
+        use crate::wire::errors::UnmarshalError;
+        use crate::wire::marshal::marshal;
+        use crate::wire::unmarshal::unmarshal_message_header;
+        use crate::ByteOrder;
+
+        for byteorder in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let mut msg = MessageBuilder::with_byteorder(byteorder)
+                .call("Frobnicate")
+                .with_interface("io.killing.spark")
+                .on("/io/killing/spark")
+                .at("io.killing.spark.service")
+                .build();
+            msg.body.push_param2("arg", 42u64).unwrap();
+            msg.dynheader.serial = Some(7);
+            msg.flags = 1;
+
+            let mut bytes = Vec::new();
+            marshal(&msg, 7, &mut bytes).unwrap();
+            let header_len = bytes.len();
+            bytes.extend_from_slice(msg.get_buf());
+
+            let (used, header) = unmarshal_message_header(&bytes, 0).unwrap();
+            assert_eq!(used, header_len);
+            assert_eq!(header, msg.header());
+            assert_eq!(header.byteorder, byteorder);
+            assert_eq!(header.typ, MessageType::Call);
+            assert_eq!(header.flags, 1);
+            assert_eq!(header.version, 1);
+            assert_eq!(header.body_len as usize, msg.get_buf().len());
+            assert_eq!(header.serial, 7);
+            assert_eq!(header.fields.member.as_deref(), Some("Frobnicate"));
+            assert_eq!(header.fields.signature.as_deref(), Some("st"));
+            assert_eq!(header.fields.num_fds, None);
+            assert!(format!("{:?}", header).contains("Frobnicate"));
+
+            let body = bytes[used..].to_vec();
+            let rebuilt =
+                MarshalledMessage::from_header(header.clone(), body.clone(), vec![]).unwrap();
+            assert_eq!(rebuilt.header(), header);
+            let mut remarshalled = Vec::new();
+            marshal(&rebuilt, header.serial, &mut remarshalled).unwrap();
+            remarshalled.extend_from_slice(rebuilt.get_buf());
+            assert_eq!(remarshalled, bytes);
+            assert_eq!(
+                rebuilt.body.parser().get2::<&str, u64>().unwrap(),
+                ("arg", 42)
+            );
+
+            assert_eq!(
+                MarshalledMessage::from_header(header.clone(), body[1..].to_vec(), vec![])
+                    .unwrap_err(),
+                UnmarshalError::NotEnoughBytes
+            );
+            let mut longer = body;
+            longer.push(0);
+            assert_eq!(
+                MarshalledMessage::from_header(header.clone(), longer, vec![]).unwrap_err(),
+                UnmarshalError::NotAllBytesUsed
+            );
+            let mut with_fds = header;
+            with_fds.fields.num_fds = Some(1);
+            assert_eq!(
+                MarshalledMessage::from_header(with_fds.clone(), bytes[used..].to_vec(), vec![])
+                    .unwrap_err(),
+                UnmarshalError::FdCountMismatch {
+                    declared: 1,
+                    received: 0
+                }
+            );
+            let fds = vec![UnixFd::new(nix::unistd::dup(0).unwrap()); 2];
+            assert_eq!(
+                MarshalledMessage::from_header(with_fds, bytes[used..].to_vec(), fds).unwrap_err(),
+                UnmarshalError::FdCountMismatch {
+                    declared: 1,
+                    received: 2
+                }
+            );
+        }
+
+        // a message without a body has no signature field
+        let signal = MessageBuilder::new()
+            .signal("io.killing.spark", "Empty", "/io/killing/spark")
+            .build();
+        let header = signal.header();
+        assert_eq!(header.serial, 0);
+        assert_eq!(header.body_len, 0);
+        assert_eq!(header.fields.signature, None);
+    }
+
     #[test]
     fn parser_get() {
         use crate::wire::errors::UnmarshalError;
@@ -960,4 +1764,289 @@ mod tests {
         assert!(parser.get::<(u32, i32, &str)>().is_ok());
         assert!(parser.get2::<(u32, i32, &str), (u32, i32, &str)>().is_ok());
     }
+
+    #[test]
+    fn parser_check_signatures() {
+        use crate::wire::errors::UnmarshalError;
+
+        let mut sig = super::MessageBuilder::new()
+            .signal("io.killingspark", "Signal", "/io/killingspark/Signaler")
+            .build();
+        sig.body
+            .push_param2(7u8, (100u32, "ABCDEFGH", 300u64))
+            .unwrap();
+
+        // the second type does not match, so nothing is decoded
+        let mut parser = sig.body.parser();
+        assert_eq!(
+            parser.get2::<u8, (u32, &str)>(),
+            Err(UnmarshalError::WrongSignature {
+                expected: "(us)".to_owned(),
+                actual: "(ust)".to_owned(),
+            })
+        );
+        assert_eq!(parser.get(), Ok(7u8));
+        assert_eq!(
+            parser.get::<(u32, &str)>(),
+            Err(UnmarshalError::WrongSignature {
+                expected: "(us)".to_owned(),
+                actual: "(ust)".to_owned(),
+            })
+        );
+
+        // decoding a prefix of the struct works if the check is skipped
+        let mut parser = sig.body.parser().check_signatures(false);
+        assert_eq!(parser.get(), Ok(7u8));
+        assert_eq!(parser.get(), Ok((100u32, "ABCDEFGH")));
+        assert_eq!(parser.get::<u8>(), Err(UnmarshalError::EndOfMessage));
+    }
+
+    #[test]
+    fn parser_prefix() {
+        use crate::wire::errors::UnmarshalError;
+
+        let mut sig = super::MessageBuilder::new()
+            .signal("io.killingspark", "Signal", "/io/killingspark/Signaler")
+            .build();
+        sig.body.push_param3(7u8, 300u64, "ABCDEFGH").unwrap();
+
+        // the u64 is aligned to 8 after the byte
+        let mut parser = sig.body.parser();
+        assert_eq!(parser.get2(), Ok((7u8, 300u64)));
+        assert_eq!(parser.get_next_sig(), Some("s"));
+        assert_eq!(parser.get(), Ok("ABCDEFGH"));
+
+        // the params after the prefix are never read, so a cut off body is fine
+        sig.body.buf.truncate(16);
+        assert_eq!(sig.body.get_first(), Ok(7u8));
+        assert_eq!(sig.body.parser().get2(), Ok((7u8, 300u64)));
+        assert_eq!(
+            sig.body.parser().get3::<u8, u64, &str>(),
+            Err(UnmarshalError::NotEnoughBytes)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_as_error() {
+        use super::DBusError;
+        use crate::wire::marshal::marshal;
+
+        let roundtrip = |msg: &super::MarshalledMessage| {
+            let mut bytes = Vec::new();
+            marshal(msg, 1, &mut bytes).unwrap();
+            bytes.extend_from_slice(msg.get_buf());
+            crate::wire::reassembler::unmarshal_message(bytes).unwrap()
+        };
+
+        let err = crate::standard_messages::error(
+            7,
+            "io.killing.spark.Error.Busy",
+            Some("try again later".to_owned()),
+        );
+        let received = roundtrip(&err);
+        assert_eq!(received.typ, super::MessageType::Error);
+        assert_eq!(received.dynheader.response_serial, Some(7));
+        let parsed = received.as_error().unwrap();
+        assert_eq!(
+            parsed,
+            DBusError {
+                name: "io.killing.spark.Error.Busy".to_owned(),
+                message: Some("try again later".to_owned()),
+            }
+        );
+        assert_eq!(
+            parsed.to_string(),
+            "io.killing.spark.Error.Busy: try again later"
+        );
+
+        let err = crate::standard_messages::error(7, "io.killing.spark.Error.Busy", None);
+        let parsed = roundtrip(&err).as_error().unwrap();
+        assert_eq!(parsed.message, None);
+        assert_eq!(parsed.to_string(), "io.killing.spark.Error.Busy");
+
+        // a first argument that is not a string is not a description
+        let mut err = crate::standard_messages::error(7, "io.killing.spark.Error.Busy", None);
+        err.body.push_param(42u32).unwrap();
+        assert_eq!(roundtrip(&err).as_error().unwrap().message, None);
+
+        let mut call = super::MessageBuilder::new()
+            .call("Frobnicate")
+            .with_interface("io.killing.spark")
+            .on("/io/killing/spark")
+            .build();
+        call.dynheader.serial = Some(3);
+        assert_eq!(call.as_error(), None);
+        assert_eq!(call.dynheader.make_response().as_error(), None);
+        let unknown = crate::standard_messages::unknown_method(&call.dynheader);
+        assert_eq!(
+            roundtrip(&unknown).as_error().unwrap().name,
+            "org.freedesktop.DBus.Error.UnknownMethod"
+        );
+    }
+
+    #[test]
+    fn test_make_reply() {
+        use super::MessageType;
+
+        let mut call = super::MessageBuilder::new()
+            .call("Frobnicate")
+            .with_interface("io.killing.spark")
+            .on("/io/killing/spark")
+            .at("io.killing.spark")
+            .build();
+        call.dynheader.serial = Some(3);
+        call.dynheader.sender = Some(":1.42".to_owned());
+
+        let reply = call.make_reply();
+        assert_eq!(reply.typ, MessageType::Reply);
+        assert_eq!(reply.dynheader.response_serial, Some(3));
+        assert_eq!(reply.dynheader.destination.as_deref(), Some(":1.42"));
+        assert_eq!(reply.dynheader.member, None);
+        assert_eq!(reply.dynheader.error_name, None);
+
+        let mut err = call.make_error_reply("io.killing.spark.Error.Busy");
+        err.body.push_param("try again later").unwrap();
+        assert_eq!(err.typ, MessageType::Error);
+        assert_eq!(err.dynheader.response_serial, Some(3));
+        assert_eq!(err.dynheader.destination.as_deref(), Some(":1.42"));
+        let parsed = err.as_error().unwrap();
+        assert_eq!(parsed.name, "io.killing.spark.Error.Busy");
+        assert_eq!(parsed.message.as_deref(), Some("try again later"));
+    }
+
+    #[test]
+    fn test_header_accessors() {
+        use crate::wire::marshal::marshal;
+
+        let mut signal = super::MessageBuilder::new()
+            .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+            .to("io.killing.listener")
+            .build();
+        assert_eq!(signal.signature(), None);
+        signal.body.push_param2("value", 7u32).unwrap();
+        signal.dynheader.sender = Some(":1.42".to_owned());
+
+        let mut bytes = Vec::new();
+        marshal(&signal, 1, &mut bytes).unwrap();
+        bytes.extend_from_slice(signal.get_buf());
+        let received = crate::wire::reassembler::unmarshal_message(bytes).unwrap();
+
+        assert_eq!(received.path(), Some("/io/killing/spark"));
+        assert_eq!(received.interface(), Some("io.killing.spark"));
+        assert_eq!(received.member(), Some("TestSignal"));
+        assert_eq!(received.sender(), Some(":1.42"));
+        assert_eq!(received.destination(), Some("io.killing.listener"));
+        assert_eq!(received.signature(), Some("su"));
+        assert_eq!(received.reply_serial(), None);
+
+        let mut call = super::MessageBuilder::new()
+            .call("Frobnicate")
+            .on("/io/killing/spark")
+            .build();
+        call.dynheader.serial = Some(3);
+        assert_eq!(call.interface(), None);
+        assert_eq!(call.make_reply().reply_serial(), Some(3));
+    }
+
+    #[test]
+    fn test_forward_with_new_destination() {
+        use crate::params::validation::{Error, NameError};
+        use crate::wire::marshal::marshal;
+
+        let mut signal = super::MessageBuilder::new()
+            .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+            .to("io.killing.first.listener")
+            .build();
+        signal.body.push_param3(7u8, 300u64, "payload").unwrap();
+        signal.set_sender(Some(":1.42".to_owned())).unwrap();
+
+        let mut bytes = Vec::new();
+        marshal(&signal, 1, &mut bytes).unwrap();
+        bytes.extend_from_slice(signal.get_buf());
+        let mut received = crate::wire::reassembler::unmarshal_message(bytes.clone()).unwrap();
+        let body_before = received.get_buf().to_vec();
+
+        assert_eq!(
+            received.set_destination(Some("io..nope".to_owned())),
+            Err(Error::InvalidBusname(NameError::EmptyElement(3)))
+        );
+        received
+            .set_destination(Some("io.killing.second".to_owned()))
+            .unwrap();
+        received.set_sender(None).unwrap();
+
+        let mut forwarded = Vec::new();
+        marshal(&received, 2, &mut forwarded).unwrap();
+        assert_eq!(forwarded.len() % 8, 0);
+        assert!(forwarded.len() < bytes.len() - body_before.len());
+        forwarded.extend_from_slice(received.get_buf());
+        assert_eq!(received.get_buf(), &body_before[..]);
+
+        let forwarded = crate::wire::reassembler::unmarshal_message(forwarded).unwrap();
+        assert_eq!(forwarded.destination(), Some("io.killing.second"));
+        assert_eq!(forwarded.sender(), None);
+        assert_eq!(forwarded.member(), Some("TestSignal"));
+        forwarded.body.validate().unwrap();
+        assert_eq!(forwarded.body.parser().get3(), Ok((7u8, 300u64, "payload")));
+    }
+
+    #[test]
+    fn test_message_flags() {
+        use super::{HeaderFlags, MessageFlags};
+        use crate::wire::marshal::marshal;
+
+        let call = super::MessageBuilder::new()
+            .call("Frobnicate")
+            .on("/io/killing/spark")
+            .with_flags(MessageFlags::NO_AUTO_START | MessageFlags::ALLOW_INTERACTIVE_AUTHORIZATION)
+            .build();
+        let flags = call.message_flags();
+        assert!(flags.contains(MessageFlags::NO_AUTO_START));
+        assert!(!flags.contains(MessageFlags::NO_REPLY_EXPECTED));
+        assert!(HeaderFlags::AllowInteractiveAuthorization.is_set(call.flags));
+        assert!(!HeaderFlags::NoReplyExpected.is_set(call.flags));
+
+        // the flags are the third byte of the header, after the byte order and the message type
+        let mut bytes = Vec::new();
+        marshal(&call, 1, &mut bytes).unwrap();
+        assert_eq!(bytes[2], 0x6);
+        let (_, header) = crate::wire::unmarshal::unmarshal_header(&bytes, 0).unwrap();
+        assert_eq!(header.flags, 0x6);
+
+        let mut raw = 0;
+        HeaderFlags::NoAutoStart.toggle(&mut raw);
+        assert_eq!(MessageFlags::from(raw), MessageFlags::NO_AUTO_START);
+        HeaderFlags::NoAutoStart.toggle(&mut raw);
+        assert_eq!(MessageFlags::from(raw), MessageFlags::empty());
+    }
+
+    #[test]
+    fn test_push_args() {
+        use super::{FromReply, MarshalledMessageBody};
+
+        let mut body = MarshalledMessageBody::new();
+        body.push_args(()).unwrap();
+        body.push_args(("spark",)).unwrap();
+        body.push_args((1u32, (true, 2u8), vec!["a", "b"])).unwrap();
+        assert_eq!(body.sig.as_str(), "su(by)as");
+        let mut parser = body.parser();
+        assert_eq!(parser.get::<&str>().unwrap(), "spark");
+        assert_eq!(parser.get::<u32>().unwrap(), 1);
+        assert_eq!(parser.get::<(bool, u8)>().unwrap(), (true, 2));
+
+        assert_eq!(<() as FromReply>::from_reply(&body), Ok(()));
+        assert_eq!(String::from_reply(&body).unwrap(), "spark");
+        assert!(u32::from_reply(&body).is_err());
+        let copy = MarshalledMessageBody::from_reply(&body).unwrap();
+        assert_eq!(copy.buf, body.buf);
+        assert_eq!(copy.sig.as_str(), body.sig.as_str());
+
+        let mut body = MarshalledMessageBody::new();
+        body.push_args((
+            1u8, 2u8, 3u8, 4u8, 5u8, 6u8, 7u8, 8u8, 9u8, 10u8, 11u8, "twelve",
+        ))
+        .unwrap();
+        assert_eq!(body.sig.as_str(), "yyyyyyyyyyys");
+    }
 }
@@ -6,10 +6,14 @@
 
 mod container_constructors;
 mod conversion;
+pub(crate) mod dump;
+#[cfg(feature = "serde_json")]
+pub mod json;
 pub mod message;
 mod types;
 pub mod validation;
 
+pub use container_constructors::{ArrayBuilder, DictBuilder, StructBuilder};
 pub use conversion::*;
 pub use types::*;
 pub use validation::*;
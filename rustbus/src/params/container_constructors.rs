@@ -2,6 +2,7 @@
 //!
 //! These allow for easier construction of containers. Note that empty containers require you to specify the
 //! signature.
+use crate::alloc_prelude::*;
 use crate::params::*;
 use crate::signature;
 use crate::wire::errors::MarshalError;
@@ -54,9 +55,21 @@ impl<'e, 'a: 'e> Container<'a, 'e> {
     }
 }
 
+impl<'a, 'e> Param<'a, 'e> {
+    /// Make an `ay` param from bytes, same as `Param::from(bytes)`
+    pub fn byte_array(bytes: &[u8]) -> Param<'a, 'e> {
+        Param::Container(Container::make_byte_array(bytes))
+    }
+}
+
 impl<'e, 'a: 'e> Container<'a, 'e> {
     pub fn make_struct<P: Into<Param<'a, 'e>>>(elements: Vec<P>) -> Container<'a, 'e> {
-        Container::Struct(elements.into_iter().map(std::convert::Into::into).collect())
+        Container::Struct(
+            elements
+                .into_iter()
+                .map(core::convert::Into::into)
+                .collect(),
+        )
     }
     pub fn make_struct_ref(elements: &'a [Param<'a, 'e>]) -> Container<'a, 'e> {
         Container::StructRef(elements)
@@ -85,10 +98,7 @@ impl<'e, 'a: 'e> Container<'a, 'e> {
     pub fn make_variant<P: Into<Param<'a, 'e>>>(element: P) -> Container<'a, 'e> {
         let param: Param = element.into();
 
-        Container::Variant(Box::new(Variant {
-            sig: param.sig(),
-            value: param,
-        }))
+        Container::Variant(Box::new(Variant::new(param)))
     }
 
     pub fn make_array_ref(
@@ -139,7 +149,7 @@ impl<'e, 'a: 'e> Container<'a, 'e> {
     ) -> Result<Container<'a, 'e>, MarshalError> {
         let arr: Array<'a, 'e> = Array {
             element_sig,
-            values: elements.map(std::convert::Into::into).collect(),
+            values: elements.map(core::convert::Into::into).collect(),
         };
 
         validate_array(&arr.values, &arr.element_sig)?;
@@ -147,6 +157,14 @@ impl<'e, 'a: 'e> Container<'a, 'e> {
         Ok(Container::Array(arr))
     }
 
+    /// Make an `ay` array. This can not fail since every element has the right type.
+    pub fn make_byte_array(bytes: &[u8]) -> Container<'a, 'e> {
+        Container::Array(Array {
+            element_sig: signature::Type::Base(signature::Base::Byte),
+            values: bytes.iter().map(|b| Param::Base(Base::Byte(*b))).collect(),
+        })
+    }
+
     pub fn make_dict<K: Into<Base<'e>>, V: Into<Param<'a, 'e>>, I: Iterator<Item = (K, V)>>(
         key_sig: &str,
         val_sig: &str,
@@ -236,3 +254,207 @@ impl<'e, 'a: 'e> Container<'a, 'e> {
         Ok(Container::DictRef(dict))
     }
 }
+
+fn parse_single_type(sig: &str) -> Result<signature::Type, MarshalError> {
+    let mut sigs = signature::Type::parse_description(sig)?;
+    if sigs.len() != 1 {
+        return Err(crate::signature::Error::TooManyTypes.into());
+    }
+    Ok(sigs.remove(0))
+}
+
+/// Build a `Container::Struct` one field at a time
+///
+/// ```rust
+/// use rustbus::params::{ArrayBuilder, StructBuilder};
+/// let arr = ArrayBuilder::new("s").unwrap().add("A").unwrap().add("B").unwrap().build();
+/// let strct = StructBuilder::new().add(10u32).add_container(arr).build().unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct StructBuilder<'a, 'e> {
+    elements: Vec<Param<'a, 'e>>,
+}
+
+impl<'e, 'a: 'e> StructBuilder<'a, 'e> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<P: Into<Param<'a, 'e>>>(mut self, element: P) -> Self {
+        self.elements.push(element.into());
+        self
+    }
+    pub fn add_container(mut self, element: Container<'a, 'e>) -> Self {
+        self.elements.push(Param::Container(element));
+        self
+    }
+    /// Fails if no fields have been added, empty structs are not allowed
+    pub fn build(self) -> Result<Container<'a, 'e>, MarshalError> {
+        if self.elements.is_empty() {
+            return Err(crate::signature::Error::EmptyStruct.into());
+        }
+        Ok(Container::Struct(self.elements))
+    }
+}
+
+/// Build a `Container::Array` one element at a time. Each element is checked against the element signature when it is added.
+#[derive(Debug)]
+pub struct ArrayBuilder<'a, 'e> {
+    element_sig: signature::Type,
+    values: Vec<Param<'a, 'e>>,
+}
+
+impl<'e, 'a: 'e> ArrayBuilder<'a, 'e> {
+    pub fn new(element_sig: &str) -> Result<Self, MarshalError> {
+        Ok(Self::with_sig(parse_single_type(element_sig)?))
+    }
+    pub fn with_sig(element_sig: signature::Type) -> Self {
+        ArrayBuilder {
+            element_sig,
+            values: Vec::new(),
+        }
+    }
+    /// Fails with `ArrayElementTypesDiffer` if the signature of `element` is not the element signature of the array
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<P: Into<Param<'a, 'e>>>(mut self, element: P) -> Result<Self, MarshalError> {
+        let element = element.into();
+        if !self.element_sig.eq(&element.sig()) {
+            return Err(crate::params::validation::Error::ArrayElementTypesDiffer.into());
+        }
+        self.values.push(element);
+        Ok(self)
+    }
+    pub fn add_container(self, element: Container<'a, 'e>) -> Result<Self, MarshalError> {
+        self.add(Param::Container(element))
+    }
+    pub fn build(self) -> Container<'a, 'e> {
+        Container::Array(Array {
+            element_sig: self.element_sig,
+            values: self.values,
+        })
+    }
+}
+
+/// Build a `Container::Dict` one entry at a time. Each key and value is checked against the signatures when it is inserted.
+#[derive(Debug)]
+pub struct DictBuilder<'a, 'e> {
+    key_sig: signature::Base,
+    value_sig: signature::Type,
+    map: DictMap<'a, 'e>,
+}
+
+impl<'e, 'a: 'e> DictBuilder<'a, 'e> {
+    pub fn new(key_sig: &str, value_sig: &str) -> Result<Self, MarshalError> {
+        let key_sig = if let signature::Type::Base(sig) = parse_single_type(key_sig)? {
+            sig
+        } else {
+            return Err(crate::signature::Error::ShouldBeBaseType.into());
+        };
+        Ok(Self::with_sig(key_sig, parse_single_type(value_sig)?))
+    }
+    pub fn with_sig(key_sig: signature::Base, value_sig: signature::Type) -> Self {
+        DictBuilder {
+            key_sig,
+            value_sig,
+            map: DictMap::new(),
+        }
+    }
+    /// Fails with `DictKeyTypesDiffer` or `DictValueTypesDiffer` if the signatures of `key` or `value` do not match the dict
+    pub fn insert<K: Into<Base<'a>>, V: Into<Param<'a, 'e>>>(
+        mut self,
+        key: K,
+        value: V,
+    ) -> Result<Self, MarshalError> {
+        let key = key.into();
+        let value = value.into();
+        if !signature::Type::Base(self.key_sig).eq(&key.sig()) {
+            return Err(crate::params::validation::Error::DictKeyTypesDiffer.into());
+        }
+        if !self.value_sig.eq(&value.sig()) {
+            return Err(crate::params::validation::Error::DictValueTypesDiffer.into());
+        }
+        self.map.insert(key, value);
+        Ok(self)
+    }
+    pub fn build(self) -> Container<'a, 'e> {
+        Container::Dict(Dict {
+            key_sig: self.key_sig,
+            value_sig: self.value_sig,
+            map: self.map,
+        })
+    }
+}
+
+#[test]
+fn test_builders() {
+    use crate::params::validation::Error;
+
+    let arr = ArrayBuilder::new("u")
+        .unwrap()
+        .add(1u32)
+        .unwrap()
+        .add(2u32)
+        .unwrap()
+        .build();
+    assert_eq!(arr.len(), 2);
+    assert_eq!(
+        ArrayBuilder::new("u")
+            .unwrap()
+            .add(1u32)
+            .unwrap()
+            .add(1u64)
+            .unwrap_err(),
+        MarshalError::Validation(Error::ArrayElementTypesDiffer)
+    );
+
+    let dict = DictBuilder::new("s", "au")
+        .unwrap()
+        .insert("A", arr.clone())
+        .unwrap()
+        .build();
+    assert_eq!(
+        DictBuilder::new("s", "u")
+            .unwrap()
+            .insert(1u8, 1u32)
+            .unwrap_err(),
+        MarshalError::Validation(Error::DictKeyTypesDiffer)
+    );
+    assert_eq!(
+        DictBuilder::new("s", "u")
+            .unwrap()
+            .insert("A", 1u8)
+            .unwrap_err(),
+        MarshalError::Validation(Error::DictValueTypesDiffer)
+    );
+    assert!(DictBuilder::new("(s)", "u").is_err());
+
+    let strct = StructBuilder::new()
+        .add("A")
+        .add_container(arr)
+        .add_container(dict)
+        .build()
+        .unwrap();
+    let mut sig = String::new();
+    strct.make_signature(&mut sig);
+    assert_eq!(sig, "(saua{sau})");
+    assert!(StructBuilder::new().build().is_err());
+}
+
+#[test]
+fn test_byte_array() {
+    use crate::message_builder::MarshalledMessageBody;
+
+    let bytes = [0xde, 0xad, 0xbe, 0xef];
+    let param = Param::byte_array(&bytes);
+    assert_eq!(param.sig().to_string(), "ay");
+    assert_eq!(Param::from(&bytes[..]), param);
+    assert_eq!(Param::from(bytes.to_vec()), param);
+    assert_eq!(Param::byte_array(&[]).sig().to_string(), "ay");
+
+    let mut body = MarshalledMessageBody::new();
+    body.push_old_param(&param).unwrap();
+    body.push_param(&bytes[..]).unwrap();
+    let mut parser = body.parser();
+    assert_eq!(parser.get::<&[u8]>().unwrap(), &bytes);
+    assert_eq!(parser.get::<&[u8]>().unwrap(), &bytes);
+}
@@ -1,7 +1,10 @@
 //! Provide a few Into<...> implementations to make working with Param a bit easier
 
 use super::*;
+use crate::alloc_prelude::*;
 use crate::signature;
+use alloc::borrow::Cow;
+use core::convert::TryFrom;
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum ConversionError {
@@ -13,6 +16,18 @@ pub enum ConversionError {
     Validation(crate::params::validation::Error),
     /// Tried to convert a Param to the wron type
     InvalidType,
+    /// Tried to convert a Base to a type that does not match its variant
+    WrongBaseType {
+        expected: signature::Base,
+        found: signature::Base,
+    },
+    /// Tried to get a value out of a Variant with a type that does not match the Variant's signature, or to create a
+    /// Variant with a signature that does not describe its value
+    WrongVariantSignature { expected: String, actual: String },
+    /// Errors occuring while marshalling a value to convert it
+    Marshal(crate::wire::errors::MarshalError),
+    /// Errors occuring while unmarshalling a value to convert it
+    Unmarshal(crate::wire::errors::UnmarshalError),
 }
 
 impl From<crate::params::validation::Error> for ConversionError {
@@ -46,7 +61,7 @@ impl<'a, 'e> Param<'a, 'e> {
             Param::Container(_) => None,
         }
     }
-    pub fn as_slice(&'a self) -> Option<&'a [Param]> {
+    pub fn as_slice(&'a self) -> Option<&'a [Param<'a, 'e>]> {
         match self {
             Param::Container(Container::Array(arr)) => Some(arr.values.as_slice()),
             Param::Container(Container::ArrayRef(arr)) => Some(arr.values),
@@ -56,10 +71,10 @@ impl<'a, 'e> Param<'a, 'e> {
         }
     }
 
+    /// See `Base::as_str`
     pub fn as_str(&'a self) -> Option<&'a str> {
         match self {
-            Param::Base(Base::String(b)) => Some(b),
-            Param::Base(Base::StringRef(b)) => Some(b),
+            Param::Base(b) => b.as_str(),
             _ => None,
         }
     }
@@ -72,6 +87,13 @@ impl<'a, 'e> Param<'a, 'e> {
             _ => None,
         }
     }
+    /// See `Base::to_u64`
+    pub fn to_u64(&self) -> Option<u64> {
+        match self {
+            Param::Base(b) => b.to_u64(),
+            _ => None,
+        }
+    }
     pub fn as_u32(&'a self) -> Option<&'a u32> {
         match self {
             Param::Base(Base::Uint32(b)) => Some(b),
@@ -93,6 +115,13 @@ impl<'a, 'e> Param<'a, 'e> {
             _ => None,
         }
     }
+    /// See `Base::to_i64`
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            Param::Base(b) => b.to_i64(),
+            _ => None,
+        }
+    }
     pub fn as_i32(&'a self) -> Option<&'a i32> {
         match self {
             Param::Base(Base::Int32(b)) => Some(b),
@@ -120,6 +149,13 @@ impl<'a, 'e> Param<'a, 'e> {
             _ => None,
         }
     }
+    /// See `Base::as_f64`
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Param::Base(b) => b.as_f64(),
+            _ => None,
+        }
+    }
     pub fn as_unix_fd(&'a self) -> Option<&'a crate::wire::UnixFd> {
         match self {
             Param::Base(Base::UnixFd(b)) => Some(b),
@@ -204,10 +240,12 @@ impl<'a, 'e> Param<'a, 'e> {
 //
 
 impl<'a> Base<'a> {
+    /// The text of strings, signatures and object paths, owned or borrowed
     pub fn as_str(&'a self) -> Option<&'a str> {
         match self {
-            Base::String(b) => Some(b),
-            Base::StringRef(b) => Some(b),
+            Base::String(b) | Base::Signature(b) | Base::ObjectPath(b) => Some(b),
+            Base::StringRef(b) | Base::SignatureRef(b) | Base::ObjectPathRef(b) => Some(b),
+            Base::StringCow(b) => Some(b),
             _ => None,
         }
     }
@@ -220,6 +258,20 @@ impl<'a> Base<'a> {
             _ => None,
         }
     }
+    /// The value of any integer type widened to u64. Signed integers are only returned if they are not negative.
+    /// Booleans, doubles and unix fds return `None`.
+    pub fn to_u64(&self) -> Option<u64> {
+        match *self {
+            Base::Byte(b) => Some(u64::from(b)),
+            Base::Uint16(b) => Some(u64::from(b)),
+            Base::Uint32(b) => Some(u64::from(b)),
+            Base::Uint64(b) => Some(b),
+            Base::Int16(b) => u64::try_from(b).ok(),
+            Base::Int32(b) => u64::try_from(b).ok(),
+            Base::Int64(b) => u64::try_from(b).ok(),
+            _ => None,
+        }
+    }
     pub fn as_u32(&'a self) -> Option<&'a u32> {
         match self {
             Base::Uint32(b) => Some(b),
@@ -241,6 +293,20 @@ impl<'a> Base<'a> {
             _ => None,
         }
     }
+    /// The value of any integer type widened to i64. A u64 is only returned if it is at most `i64::MAX`.
+    /// Booleans, doubles and unix fds return `None`.
+    pub fn to_i64(&self) -> Option<i64> {
+        match *self {
+            Base::Byte(b) => Some(i64::from(b)),
+            Base::Uint16(b) => Some(i64::from(b)),
+            Base::Uint32(b) => Some(i64::from(b)),
+            Base::Uint64(b) => i64::try_from(b).ok(),
+            Base::Int16(b) => Some(i64::from(b)),
+            Base::Int32(b) => Some(i64::from(b)),
+            Base::Int64(b) => Some(b),
+            _ => None,
+        }
+    }
     pub fn as_i32(&'a self) -> Option<&'a i32> {
         match self {
             Base::Int32(b) => Some(b),
@@ -268,6 +334,13 @@ impl<'a> Base<'a> {
             _ => None,
         }
     }
+    /// The value of a `Double`
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Base::Double(b) => Some(f64::from_bits(*b)),
+            _ => None,
+        }
+    }
     pub fn as_unix_fd(&'a self) -> Option<&'a crate::wire::UnixFd> {
         match self {
             Base::UnixFd(b) => Some(b),
@@ -289,6 +362,16 @@ impl<'a> Base<'a> {
         }
     }
 
+    /// The text of any string, without copying it whether it is owned or borrowed
+    pub fn into_str_cow(self) -> Result<Cow<'a, str>, Self> {
+        match self {
+            Base::String(s) => Ok(Cow::Owned(s)),
+            Base::StringRef(s) => Ok(Cow::Borrowed(s)),
+            Base::StringCow(s) => Ok(s),
+            _ => Err(self),
+        }
+    }
+
     pub fn into_u64(self) -> Result<u64, Self> {
         match self {
             Base::Uint64(s) => Ok(s),
@@ -345,7 +428,7 @@ impl<'a> Base<'a> {
     }
 }
 
-impl<'a> std::convert::From<&Base<'a>> for signature::Base {
+impl<'a> core::convert::From<&Base<'a>> for signature::Base {
     fn from(b: &Base) -> crate::signature::Base {
         match b {
             Base::Boolean(_) => signature::Base::Boolean,
@@ -364,126 +447,82 @@ impl<'a> std::convert::From<&Base<'a>> for signature::Base {
             Base::ObjectPathRef(_) => signature::Base::ObjectPath,
             Base::SignatureRef(_) => signature::Base::Signature,
             Base::StringRef(_) => signature::Base::String,
+            Base::StringCow(_) => signature::Base::String,
         }
     }
 }
 
-impl<'a> std::convert::TryFrom<&Base<'a>> for bool {
-    type Error = ConversionError;
-    fn try_from(b: &Base) -> std::result::Result<bool, ConversionError> {
-        if let Base::Boolean(value) = b {
-            Ok(*value)
-        } else {
-            Err(ConversionError::InvalidType)
-        }
+fn wrong_base_type(expected: signature::Base, found: &Base) -> ConversionError {
+    ConversionError::WrongBaseType {
+        expected,
+        found: found.into(),
     }
 }
 
-impl<'a> std::convert::TryFrom<&Base<'a>> for String {
-    type Error = ConversionError;
-    fn try_from(b: &Base) -> std::result::Result<String, ConversionError> {
-        if let Base::String(value) = b {
-            Ok(value.clone())
-        } else {
-            Err(ConversionError::InvalidType)
-        }
-    }
-}
-impl<'a> std::convert::TryFrom<&Base<'a>> for &'a str {
-    type Error = ConversionError;
-    fn try_from(b: &Base<'a>) -> std::result::Result<&'a str, ConversionError> {
-        if let Base::StringRef(value) = b {
-            Ok(value)
-        } else {
-            Err(ConversionError::InvalidType)
-        }
-    }
-}
-
-impl<'a> std::convert::TryFrom<&Base<'a>> for u8 {
-    type Error = ConversionError;
-    fn try_from(b: &Base) -> std::result::Result<u8, ConversionError> {
-        if let Base::Byte(value) = b {
-            Ok(*value)
-        } else {
-            Err(ConversionError::InvalidType)
-        }
-    }
-}
-
-impl<'a> std::convert::TryFrom<&Base<'a>> for u16 {
-    type Error = ConversionError;
-    fn try_from(b: &Base) -> std::result::Result<u16, ConversionError> {
-        if let Base::Uint16(value) = b {
-            Ok(*value)
-        } else {
-            Err(ConversionError::InvalidType)
-        }
-    }
-}
-
-impl<'a> std::convert::TryFrom<&Base<'a>> for u32 {
-    type Error = ConversionError;
-    fn try_from(b: &Base) -> std::result::Result<u32, ConversionError> {
-        if let Base::Uint32(value) = b {
-            Ok(*value)
-        } else {
-            Err(ConversionError::InvalidType)
+macro_rules! impl_try_from_base {
+    ($typ:ty, $variant:ident, $value:ident => $convert:expr) => {
+        impl<'a> core::convert::TryFrom<&Base<'a>> for $typ {
+            type Error = ConversionError;
+            fn try_from(b: &Base) -> core::result::Result<$typ, ConversionError> {
+                if let Base::$variant($value) = b {
+                    Ok($convert)
+                } else {
+                    Err(wrong_base_type(signature::Base::$variant, b))
+                }
+            }
         }
-    }
-}
-
-impl<'a> std::convert::TryFrom<&Base<'a>> for u64 {
-    type Error = ConversionError;
-    fn try_from(b: &Base) -> std::result::Result<u64, ConversionError> {
-        if let Base::Uint64(value) = b {
-            Ok(*value)
-        } else {
-            Err(ConversionError::InvalidType)
+        impl<'a> core::convert::TryFrom<Base<'a>> for $typ {
+            type Error = ConversionError;
+            fn try_from(b: Base) -> core::result::Result<$typ, ConversionError> {
+                <$typ>::try_from(&b)
+            }
         }
-    }
+    };
 }
 
-impl<'a> std::convert::TryFrom<&Base<'a>> for i16 {
-    type Error = ConversionError;
-    fn try_from(b: &Base) -> std::result::Result<i16, ConversionError> {
-        if let Base::Int16(value) = b {
-            Ok(*value)
-        } else {
-            Err(ConversionError::InvalidType)
-        }
-    }
-}
+impl_try_from_base!(bool, Boolean, value => *value);
+impl_try_from_base!(u8, Byte, value => *value);
+impl_try_from_base!(u16, Uint16, value => *value);
+impl_try_from_base!(u32, Uint32, value => *value);
+impl_try_from_base!(u64, Uint64, value => *value);
+impl_try_from_base!(i16, Int16, value => *value);
+impl_try_from_base!(i32, Int32, value => *value);
+impl_try_from_base!(i64, Int64, value => *value);
+impl_try_from_base!(f64, Double, value => f64::from_bits(*value));
 
-impl<'a> std::convert::TryFrom<&Base<'a>> for i32 {
+impl<'a> core::convert::TryFrom<&Base<'a>> for String {
     type Error = ConversionError;
-    fn try_from(b: &Base) -> std::result::Result<i32, ConversionError> {
-        if let Base::Int32(value) = b {
-            Ok(*value)
-        } else {
-            Err(ConversionError::InvalidType)
+    fn try_from(b: &Base) -> core::result::Result<String, ConversionError> {
+        match b {
+            Base::String(value) => Ok(value.clone()),
+            Base::StringRef(value) => Ok(value.to_string()),
+            Base::StringCow(value) => Ok(value.to_string()),
+            _ => Err(wrong_base_type(signature::Base::String, b)),
         }
     }
 }
-
-impl<'a> std::convert::TryFrom<&Base<'a>> for i64 {
+impl<'a> core::convert::TryFrom<Base<'a>> for String {
     type Error = ConversionError;
-    fn try_from(b: &Base) -> std::result::Result<i64, ConversionError> {
-        if let Base::Int64(value) = b {
-            Ok(*value)
-        } else {
-            Err(ConversionError::InvalidType)
+    fn try_from(b: Base) -> core::result::Result<String, ConversionError> {
+        match b {
+            Base::String(value) => Ok(value),
+            Base::StringRef(value) => Ok(value.to_string()),
+            Base::StringCow(value) => Ok(value.into_owned()),
+            _ => Err(wrong_base_type(signature::Base::String, &b)),
         }
     }
 }
-
-impl<'a> std::convert::TryFrom<&Base<'a>> for f64 {
+/// Works for all string-like types (strings, signatures and object paths) that borrow their text.
+/// Use `Base::as_str` for the owned ones.
+impl<'a> core::convert::TryFrom<&Base<'a>> for &'a str {
     type Error = ConversionError;
-    fn try_from(b: &Base) -> std::result::Result<f64, ConversionError> {
-        if let Base::Double(value) = b {
-            Ok(f64::from_bits(*value))
-        } else {
-            Err(ConversionError::InvalidType)
+    fn try_from(b: &Base<'a>) -> core::result::Result<&'a str, ConversionError> {
+        match b {
+            Base::StringRef(value) | Base::SignatureRef(value) | Base::ObjectPathRef(value) => {
+                Ok(value)
+            }
+            Base::StringCow(alloc::borrow::Cow::Borrowed(value)) => Ok(value),
+            _ => Err(wrong_base_type(signature::Base::String, b)),
         }
     }
 }
@@ -494,7 +533,7 @@ impl<'a> std::convert::TryFrom<&Base<'a>> for f64 {
 //
 //
 
-impl<'a, 'e> std::convert::From<&Param<'a, 'e>> for signature::Type {
+impl<'a, 'e> core::convert::From<&Param<'a, 'e>> for signature::Type {
     fn from(p: &Param<'a, 'e>) -> crate::signature::Type {
         match p {
             Param::Base(b) => signature::Type::Base(b.into()),
@@ -509,29 +548,48 @@ impl<'a, 'e> std::convert::From<&Param<'a, 'e>> for signature::Type {
 //
 //
 
-impl<'a, 'e, B: Into<Base<'a>>> std::convert::From<B> for Param<'a, 'e> {
+impl<'a, 'e, B: Into<Base<'a>>> core::convert::From<B> for Param<'a, 'e> {
     fn from(s: B) -> Self {
         Param::Base(s.into())
     }
 }
 
-impl<'a, 'e> std::convert::From<Container<'a, 'e>> for Param<'a, 'e> {
+impl<'a, 'e> core::convert::From<Container<'a, 'e>> for Param<'a, 'e> {
     fn from(s: Container<'a, 'e>) -> Self {
         Param::Container(s)
     }
 }
 
+/// Byte slices become `ay` arrays
+impl<'a, 'e> core::convert::From<&[u8]> for Param<'a, 'e> {
+    fn from(bytes: &[u8]) -> Self {
+        Param::byte_array(bytes)
+    }
+}
+
+impl<'a, 'e> core::convert::From<Vec<u8>> for Param<'a, 'e> {
+    fn from(bytes: Vec<u8>) -> Self {
+        Param::Container(Container::Array(Array {
+            element_sig: signature::Type::Base(signature::Base::Byte),
+            values: bytes
+                .into_iter()
+                .map(|b| Param::Base(Base::Byte(b)))
+                .collect(),
+        }))
+    }
+}
+
 //
 //
 // Container FROM
 //
 //
 
-impl<'a, 'e> std::convert::TryFrom<(signature::Type, Vec<Param<'a, 'e>>)> for Container<'a, 'e> {
+impl<'a, 'e> core::convert::TryFrom<(signature::Type, Vec<Param<'a, 'e>>)> for Container<'a, 'e> {
     type Error = ConversionError;
     fn try_from(
         parts: (signature::Type, Vec<Param<'a, 'e>>),
-    ) -> std::result::Result<Container<'a, 'e>, ConversionError> {
+    ) -> core::result::Result<Container<'a, 'e>, ConversionError> {
         let arr = Array {
             element_sig: parts.0,
             values: parts.1,
@@ -540,11 +598,11 @@ impl<'a, 'e> std::convert::TryFrom<(signature::Type, Vec<Param<'a, 'e>>)> for Co
         Ok(Container::Array(arr))
     }
 }
-impl<'a, 'e> std::convert::TryFrom<Vec<Param<'a, 'e>>> for Container<'a, 'e> {
+impl<'a, 'e> core::convert::TryFrom<Vec<Param<'a, 'e>>> for Container<'a, 'e> {
     type Error = ConversionError;
     fn try_from(
         elems: Vec<Param<'a, 'e>>,
-    ) -> std::result::Result<Container<'a, 'e>, ConversionError> {
+    ) -> core::result::Result<Container<'a, 'e>, ConversionError> {
         if elems.is_empty() {
             return Err(ConversionError::EmptyArray);
         }
@@ -552,13 +610,54 @@ impl<'a, 'e> std::convert::TryFrom<Vec<Param<'a, 'e>>> for Container<'a, 'e> {
     }
 }
 
-impl<'a, 'e> std::convert::TryFrom<(signature::Base, signature::Type, DictMap<'a, 'e>)>
+/// Collect params into an array, the element signature is the signature of the first param. Like for
+/// `TryFrom<Vec<Param>>` this fails with `EmptyArray` if there are no params, because the signature can not be known
+/// then; use `Array::from_iter_with_sig` for arrays that may be empty. Params with a different signature than the
+/// first one fail with `ArrayElementTypesDiffer`.
+///
+/// ```rust
+/// use rustbus::params::{Array, ConversionError, Param};
+///
+/// let arr: Result<Array, ConversionError> = (1..4u32).map(Param::from).collect();
+/// assert_eq!(arr.unwrap().values.len(), 3);
+/// ```
+impl<'a, 'e> core::iter::FromIterator<Param<'a, 'e>>
+    for core::result::Result<Array<'a, 'e>, ConversionError>
+{
+    fn from_iter<I: IntoIterator<Item = Param<'a, 'e>>>(iter: I) -> Self {
+        let values: Vec<Param<'a, 'e>> = iter.into_iter().collect();
+        let element_sig = values.first().ok_or(ConversionError::EmptyArray)?.sig();
+        validate_array(&values, &element_sig)?;
+        Ok(Array {
+            element_sig,
+            values,
+        })
+    }
+}
+
+impl<'a, 'e> Array<'a, 'e> {
+    /// Collect params into an array with the given element signature. An empty iterator makes an empty array, params
+    /// with a different signature fail with `ArrayElementTypesDiffer`.
+    pub fn from_iter_with_sig<P: Into<Param<'a, 'e>>, I: IntoIterator<Item = P>>(
+        element_sig: signature::Type,
+        iter: I,
+    ) -> core::result::Result<Self, ConversionError> {
+        let values: Vec<Param<'a, 'e>> = iter.into_iter().map(Into::into).collect();
+        validate_array(&values, &element_sig)?;
+        Ok(Array {
+            element_sig,
+            values,
+        })
+    }
+}
+
+impl<'a, 'e> core::convert::TryFrom<(signature::Base, signature::Type, DictMap<'a, 'e>)>
     for Container<'a, 'e>
 {
     type Error = ConversionError;
     fn try_from(
         parts: (signature::Base, signature::Type, DictMap<'a, 'e>),
-    ) -> std::result::Result<Container<'a, 'e>, ConversionError> {
+    ) -> core::result::Result<Container<'a, 'e>, ConversionError> {
         let dict = Dict {
             key_sig: parts.0,
             value_sig: parts.1,
@@ -568,9 +667,11 @@ impl<'a, 'e> std::convert::TryFrom<(signature::Base, signature::Type, DictMap<'a
         Ok(Container::Dict(dict))
     }
 }
-impl<'a, 'e> std::convert::TryFrom<DictMap<'a, 'e>> for Container<'a, 'e> {
+impl<'a, 'e> core::convert::TryFrom<DictMap<'a, 'e>> for Container<'a, 'e> {
     type Error = ConversionError;
-    fn try_from(elems: DictMap<'a, 'e>) -> std::result::Result<Container<'a, 'e>, ConversionError> {
+    fn try_from(
+        elems: DictMap<'a, 'e>,
+    ) -> core::result::Result<Container<'a, 'e>, ConversionError> {
         if elems.is_empty() {
             return Err(ConversionError::EmptyDict);
         }
@@ -591,102 +692,107 @@ impl<'a, 'e> std::convert::TryFrom<DictMap<'a, 'e>> for Container<'a, 'e> {
 //
 //
 
-impl<'a> std::convert::From<String> for Base<'a> {
+impl<'a> core::convert::From<String> for Base<'a> {
     fn from(s: String) -> Self {
         Base::String(s)
     }
 }
-impl<'a> std::convert::From<&'a str> for Base<'a> {
+impl<'a> core::convert::From<&'a str> for Base<'a> {
     fn from(s: &'a str) -> Self {
         Base::StringRef(s)
     }
 }
-impl<'a> std::convert::From<bool> for Base<'a> {
+impl<'a> core::convert::From<Cow<'a, str>> for Base<'a> {
+    fn from(s: Cow<'a, str>) -> Self {
+        Base::StringCow(s)
+    }
+}
+impl<'a> core::convert::From<bool> for Base<'a> {
     fn from(s: bool) -> Self {
         Base::Boolean(s)
     }
 }
-impl<'a> std::convert::From<u8> for Base<'a> {
+impl<'a> core::convert::From<u8> for Base<'a> {
     fn from(s: u8) -> Self {
         Base::Byte(s)
     }
 }
-impl<'a> std::convert::From<u16> for Base<'a> {
+impl<'a> core::convert::From<u16> for Base<'a> {
     fn from(s: u16) -> Self {
         Base::Uint16(s)
     }
 }
-impl<'a> std::convert::From<u32> for Base<'a> {
+impl<'a> core::convert::From<u32> for Base<'a> {
     fn from(s: u32) -> Self {
         Base::Uint32(s)
     }
 }
-impl<'a> std::convert::From<u64> for Base<'a> {
+impl<'a> core::convert::From<u64> for Base<'a> {
     fn from(s: u64) -> Self {
         Base::Uint64(s)
     }
 }
-impl<'a> std::convert::From<i16> for Base<'a> {
+impl<'a> core::convert::From<i16> for Base<'a> {
     fn from(s: i16) -> Self {
         Base::Int16(s)
     }
 }
-impl<'a> std::convert::From<i32> for Base<'a> {
+impl<'a> core::convert::From<i32> for Base<'a> {
     fn from(s: i32) -> Self {
         Base::Int32(s)
     }
 }
-impl<'a> std::convert::From<i64> for Base<'a> {
+impl<'a> core::convert::From<i64> for Base<'a> {
     fn from(s: i64) -> Self {
         Base::Int64(s)
     }
 }
-impl<'a> std::convert::From<f64> for Base<'a> {
+impl<'a> core::convert::From<f64> for Base<'a> {
     fn from(s: f64) -> Self {
         Base::Double(s.to_bits())
     }
 }
-impl<'a> std::convert::From<&'a bool> for Base<'a> {
+impl<'a> core::convert::From<&'a bool> for Base<'a> {
     fn from(s: &'a bool) -> Self {
         Base::Boolean(*s)
     }
 }
-impl<'a> std::convert::From<&'a u8> for Base<'a> {
+impl<'a> core::convert::From<&'a u8> for Base<'a> {
     fn from(s: &'a u8) -> Self {
         Base::Byte(*s)
     }
 }
-impl<'a> std::convert::From<&'a u16> for Base<'a> {
+impl<'a> core::convert::From<&'a u16> for Base<'a> {
     fn from(s: &'a u16) -> Self {
         Base::Uint16(*s)
     }
 }
-impl<'a> std::convert::From<&'a u32> for Base<'a> {
+impl<'a> core::convert::From<&'a u32> for Base<'a> {
     fn from(s: &'a u32) -> Self {
         Base::Uint32(*s)
     }
 }
-impl<'a> std::convert::From<&'a u64> for Base<'a> {
+impl<'a> core::convert::From<&'a u64> for Base<'a> {
     fn from(s: &'a u64) -> Self {
         Base::Uint64(*s)
     }
 }
-impl<'a> std::convert::From<&'a f64> for Base<'a> {
+impl<'a> core::convert::From<&'a f64> for Base<'a> {
     fn from(s: &'a f64) -> Self {
         Base::Double(s.to_bits())
     }
 }
-impl<'a> std::convert::From<&'a i16> for Base<'a> {
+impl<'a> core::convert::From<&'a i16> for Base<'a> {
     fn from(s: &'a i16) -> Self {
         Base::Int16(*s)
     }
 }
-impl<'a> std::convert::From<&'a i32> for Base<'a> {
+impl<'a> core::convert::From<&'a i32> for Base<'a> {
     fn from(s: &'a i32) -> Self {
         Base::Int32(*s)
     }
 }
-impl<'a> std::convert::From<&'a i64> for Base<'a> {
+impl<'a> core::convert::From<&'a i64> for Base<'a> {
     fn from(s: &'a i64) -> Self {
         Base::Int64(*s)
     }
@@ -698,7 +804,7 @@ impl<'a> std::convert::From<&'a i64> for Base<'a> {
 //
 //
 
-impl<'a, 'e> std::convert::From<&Container<'a, 'e>> for signature::Container {
+impl<'a, 'e> core::convert::From<&Container<'a, 'e>> for signature::Container {
     fn from(c: &Container<'a, 'e>) -> crate::signature::Container {
         match c {
             Container::Array(arr) => signature::Container::Array(Box::new(arr.element_sig.clone())),
@@ -713,6 +819,9 @@ impl<'a, 'e> std::convert::From<&Container<'a, 'e>> for signature::Container {
             Container::ArrayRef(arr) => {
                 signature::Container::Array(Box::new(arr.element_sig.clone()))
             }
+            Container::OrderedDict(dict) => {
+                signature::Container::Dict(dict.key_sig, Box::new(dict.value_sig.clone()))
+            }
             Container::DictRef(dict) => {
                 signature::Container::Dict(dict.key_sig, Box::new(dict.value_sig.clone()))
             }
@@ -723,3 +832,119 @@ impl<'a, 'e> std::convert::From<&Container<'a, 'e>> for signature::Container {
         }
     }
 }
+
+#[test]
+fn test_array_from_iter() {
+    let arr: core::result::Result<Array, ConversionError> =
+        ["a", "b"].iter().copied().map(Param::from).collect();
+    let arr = arr.unwrap();
+    assert_eq!(
+        arr.element_sig,
+        signature::Type::Base(signature::Base::String)
+    );
+    assert_eq!(arr.values, [Param::from("a"), Param::from("b")]);
+
+    let empty: core::result::Result<Array, ConversionError> = core::iter::empty().collect();
+    assert_eq!(empty, Err(ConversionError::EmptyArray));
+    let mixed: core::result::Result<Array, ConversionError> =
+        vec![Param::from(1u32), Param::from(1u64)]
+            .into_iter()
+            .collect();
+    assert_eq!(
+        mixed,
+        Err(ConversionError::Validation(
+            crate::params::validation::Error::ArrayElementTypesDiffer
+        ))
+    );
+
+    let sig = signature::Type::Base(signature::Base::Uint32);
+    let empty = Array::from_iter_with_sig(sig.clone(), core::iter::empty::<u32>()).unwrap();
+    assert!(empty.values.is_empty());
+    assert_eq!(
+        Param::Container(Container::Array(empty)).sig().to_string(),
+        "au"
+    );
+    let arr = Array::from_iter_with_sig(sig.clone(), 1..4u32).unwrap();
+    assert_eq!(arr.values.len(), 3);
+    assert_eq!(
+        Array::from_iter_with_sig(sig, vec![Param::from(1u32), Param::from("a")]),
+        Err(ConversionError::Validation(
+            crate::params::validation::Error::ArrayElementTypesDiffer
+        ))
+    );
+}
+
+#[test]
+fn test_base_try_from() {
+    use core::convert::TryFrom;
+
+    assert_eq!(u32::try_from(Base::Uint32(10)), Ok(10));
+    assert_eq!(i16::try_from(&Base::Int16(-10)), Ok(-10));
+    assert_eq!(f64::try_from(Base::Double(1.5f64.to_bits())), Ok(1.5));
+    assert_eq!(bool::try_from(Base::Boolean(true)), Ok(true));
+    assert_eq!(
+        u64::try_from(Base::Uint32(10)),
+        Err(ConversionError::WrongBaseType {
+            expected: signature::Base::Uint64,
+            found: signature::Base::Uint32,
+        })
+    );
+
+    assert_eq!(
+        String::try_from(Base::String("ABCD".to_owned())),
+        Ok("ABCD".to_owned())
+    );
+    assert_eq!(
+        String::try_from(&Base::StringRef("ABCD")),
+        Ok("ABCD".to_owned())
+    );
+    assert_eq!(
+        String::try_from(Base::ObjectPathRef("/a/b")),
+        Err(ConversionError::WrongBaseType {
+            expected: signature::Base::String,
+            found: signature::Base::ObjectPath,
+        })
+    );
+
+    let owned = Base::String("ABCD".to_owned());
+    assert!(<&str>::try_from(&owned).is_err());
+    assert_eq!(<&str>::try_from(&Base::StringRef("ABCD")), Ok("ABCD"));
+    assert_eq!(<&str>::try_from(&Base::SignatureRef("a{sv}")), Ok("a{sv}"));
+    assert_eq!(<&str>::try_from(&Base::ObjectPathRef("/a/b")), Ok("/a/b"));
+    assert!(<&str>::try_from(&Base::Byte(0)).is_err());
+}
+
+#[test]
+fn test_base_accessors() {
+    assert_eq!(Base::String("a".to_owned()).as_str(), Some("a"));
+    assert_eq!(Base::SignatureRef("a{sv}").as_str(), Some("a{sv}"));
+    assert_eq!(Base::ObjectPath("/a/b".to_owned()).as_str(), Some("/a/b"));
+    assert_eq!(Base::Uint32(1).as_str(), None);
+
+    assert_eq!(Base::Uint64(5).as_u64(), Some(&5));
+    assert_eq!(Base::Uint32(5).as_u64(), None);
+    assert_eq!(Base::Int64(-5).as_i64(), Some(&-5));
+    assert_eq!(Base::Int32(-5).as_i64(), None);
+
+    assert_eq!(Base::Byte(255).to_u64(), Some(255));
+    assert_eq!(Base::Uint64(u64::MAX).to_u64(), Some(u64::MAX));
+    assert_eq!(Base::Int16(7).to_u64(), Some(7));
+    assert_eq!(Base::Int64(-1).to_u64(), None);
+    assert_eq!(Base::Boolean(true).to_u64(), None);
+    assert_eq!(Base::Double(1.0f64.to_bits()).to_u64(), None);
+
+    assert_eq!(Base::Int32(-5).to_i64(), Some(-5));
+    assert_eq!(Base::Uint32(u32::MAX).to_i64(), Some(i64::from(u32::MAX)));
+    assert_eq!(Base::Uint64(i64::MAX as u64).to_i64(), Some(i64::MAX));
+    assert_eq!(Base::Uint64(u64::MAX).to_i64(), None);
+
+    assert_eq!(Base::double(1.5).as_f64(), Some(1.5));
+    assert_eq!(Base::Uint64(1).as_f64(), None);
+    assert_eq!(Param::Base(Base::double(-2.0)).as_f64(), Some(-2.0));
+
+    let param = Param::Base(Base::Uint16(3));
+    assert_eq!(param.to_u64(), Some(3));
+    assert_eq!(param.to_i64(), Some(3));
+    assert_eq!(param.as_u64(), None);
+    assert_eq!(Param::Base(Base::ObjectPathRef("/a")).as_str(), Some("/a"));
+}
@@ -0,0 +1,251 @@
+//! Render message bodies as a human readable tree for debugging
+
+use crate::alloc_prelude::*;
+use core::fmt::Write;
+
+use crate::message_builder::MarshalledMessageBody;
+use crate::params::{Base, Container, Param};
+use crate::signature;
+use crate::wire::unmarshal::container::unmarshal_with_sig;
+use crate::wire::unmarshal::UnmarshalContext;
+
+const INDENT: &str = "  ";
+
+/// Walk the body according to its signature and render each value on its own line with its type. Containers indent
+/// their content. Everything from the first value that can not be unmarshalled on is replaced by an `<unparseable>` line.
+pub(crate) fn dump_body(body: &MarshalledMessageBody, sig_str: &str) -> String {
+    let mut out = String::new();
+    let sigs = match signature::Type::parse_description(sig_str) {
+        Ok(sigs) => sigs,
+        Err(e) => {
+            let _ = writeln!(out, "<unparseable> invalid signature {:?}: {}", sig_str, e);
+            return out;
+        }
+    };
+    let mut ctx = UnmarshalContext::new(&body.raw_fds, &body.buf, body.byteorder, 0);
+    for sig in &sigs {
+        match unmarshal_with_sig(sig, &mut ctx) {
+            Ok((_, param)) => {
+                dump_param(&param, body, 0, &mut out);
+                out.push('\n');
+            }
+            Err(e) => {
+                let _ = writeln!(out, "<unparseable> {}: {}", sig, e);
+                return out;
+            }
+        }
+    }
+    if ctx.offset < body.buf.len() {
+        let _ = writeln!(
+            out,
+            "<unparseable> {} bytes after the last value",
+            body.buf.len() - ctx.offset
+        );
+    }
+    out
+}
+
+fn indent(depth: usize, out: &mut String) {
+    for _ in 0..depth {
+        out.push_str(INDENT);
+    }
+}
+
+/// Write the param starting at the current position. Multi line containers end on the line of their closing bracket.
+fn dump_param(param: &Param, body: &MarshalledMessageBody, depth: usize, out: &mut String) {
+    match param {
+        Param::Base(base) => dump_base(base, body, out),
+        Param::Container(container) => dump_container(container, body, depth, out),
+    }
+}
+
+fn dump_base(base: &Base, body: &MarshalledMessageBody, out: &mut String) {
+    let sig = base.sig();
+    let _ = match base {
+        Base::Boolean(b) => write!(out, "{} {}", sig, b),
+        Base::Double(bits) => write!(out, "{} {}", sig, f64::from_bits(*bits)),
+        Base::Byte(b) => write!(out, "{} 0x{:02x}", sig, b),
+        Base::Int16(i) => write!(out, "{} {}", sig, i),
+        Base::Uint16(i) => write!(out, "{} {}", sig, i),
+        Base::Int32(i) => write!(out, "{} {}", sig, i),
+        Base::Uint32(i) => write!(out, "{} {}", sig, i),
+        Base::Int64(i) => write!(out, "{} {}", sig, i),
+        Base::Uint64(i) => write!(out, "{} {}", sig, i),
+        Base::UnixFd(fd) => match body.raw_fds.iter().position(|other| other == fd) {
+            Some(idx) => write!(out, "{} fd #{}", sig, idx),
+            None => write!(out, "{} fd", sig),
+        },
+        Base::String(s) | Base::Signature(s) | Base::ObjectPath(s) => {
+            write!(out, "{} {:?}", sig, s)
+        }
+        Base::StringRef(s) | Base::SignatureRef(s) | Base::ObjectPathRef(s) => {
+            write!(out, "{} {:?}", sig, s)
+        }
+        Base::StringCow(s) => write!(out, "{} {:?}", sig, s),
+    };
+}
+
+fn dump_children(
+    open: char,
+    close: char,
+    children: &[Param],
+    body: &MarshalledMessageBody,
+    depth: usize,
+    out: &mut String,
+) {
+    out.push(open);
+    if children.is_empty() {
+        out.push(close);
+        return;
+    }
+    out.push('\n');
+    for child in children {
+        indent(depth + 1, out);
+        dump_param(child, body, depth + 1, out);
+        out.push('\n');
+    }
+    indent(depth, out);
+    out.push(close);
+}
+
+fn dump_dict(
+    entries: &[(&Base, &Param)],
+    body: &MarshalledMessageBody,
+    depth: usize,
+    out: &mut String,
+) {
+    out.push('{');
+    if entries.is_empty() {
+        out.push('}');
+        return;
+    }
+    out.push('\n');
+    for (key, value) in entries.iter().copied() {
+        indent(depth + 1, out);
+        dump_base(key, body, out);
+        out.push_str(" => ");
+        dump_param(value, body, depth + 1, out);
+        out.push('\n');
+    }
+    indent(depth, out);
+    out.push('}');
+}
+
+/// Hashmaps are sorted by key so the output does not change between runs
+fn sorted_entries<'m, 'a, 'e>(
+    map: &'m crate::params::DictMap<'a, 'e>,
+) -> Vec<(&'m Base<'a>, &'m Param<'a, 'e>)> {
+    let mut entries = map.iter().collect::<Vec<_>>();
+    entries.sort_by_key(|(key, _)| *key);
+    entries
+}
+
+fn dump_container(
+    container: &Container,
+    body: &MarshalledMessageBody,
+    depth: usize,
+    out: &mut String,
+) {
+    let _ = write!(out, "{} ", container.sig());
+    match container {
+        Container::Array(array) => dump_array(&array.element_sig, &array.values, body, depth, out),
+        Container::ArrayRef(array) => {
+            dump_array(&array.element_sig, array.values, body, depth, out)
+        }
+        Container::Struct(fields) => dump_children('(', ')', fields, body, depth, out),
+        Container::StructRef(fields) => dump_children('(', ')', fields, body, depth, out),
+        Container::Dict(dict) => dump_dict(&sorted_entries(&dict.map), body, depth, out),
+        Container::DictRef(dict) => dump_dict(&sorted_entries(dict.map), body, depth, out),
+        Container::OrderedDict(dict) => {
+            dump_dict(&dict.map.iter().collect::<Vec<_>>(), body, depth, out)
+        }
+        Container::Variant(variant) => dump_param(&variant.value, body, depth, out),
+    }
+}
+
+fn dump_array(
+    element_sig: &signature::Type,
+    values: &[Param],
+    body: &MarshalledMessageBody,
+    depth: usize,
+    out: &mut String,
+) {
+    if *element_sig == signature::Type::Base(signature::Base::Byte) {
+        // byte arrays are shown as hex on one line
+        out.push('[');
+        for (idx, value) in values.iter().enumerate() {
+            if let Param::Base(Base::Byte(b)) = value {
+                if idx > 0 {
+                    out.push(' ');
+                }
+                let _ = write!(out, "{:02x}", b);
+            }
+        }
+        out.push(']');
+    } else {
+        dump_children('[', ']', values, body, depth, out);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use alloc::collections::BTreeMap;
+
+    use crate::message_builder::MarshalledMessageBody;
+    use crate::wire::marshal::traits::Variant;
+    use crate::wire::{ObjectPath, UnixFd};
+
+    #[test]
+    fn test_dump() {
+        let mut body = MarshalledMessageBody::new();
+        body.push_param("hello \"world\"").unwrap();
+        body.push_param(42u32).unwrap();
+        body.push_param(&[0xdeu8, 0xad, 0xbe, 0xef][..]).unwrap();
+        let mut dict = BTreeMap::new();
+        dict.insert("count", Variant(7i16));
+        dict.insert("delta", Variant(-3i16));
+        body.push_param(&dict).unwrap();
+        body.push_param((true, 1.5f64, ObjectPath::new("/a/b").unwrap()))
+            .unwrap();
+        body.push_param(UnixFd::new(nix::unistd::dup(1).unwrap()))
+            .unwrap();
+        body.push_param(Vec::<u32>::new()).unwrap();
+
+        assert_eq!(
+            body.dump(),
+            r#"s "hello \"world\""
+u 42
+ay [de ad be ef]
+a{sv} {
+  s "count" => v n 7
+  s "delta" => v n -3
+}
+(bdo) (
+  b true
+  d 1.5
+  o "/a/b"
+)
+h fd #0
+au []
+"#
+        );
+    }
+
+    #[test]
+    fn test_dump_unparseable() {
+        let mut body = MarshalledMessageBody::new();
+        body.push_param(42u32).unwrap();
+        body.push_param("cut off").unwrap();
+        body.buf.truncate(10);
+        let dump = body.dump();
+        assert!(dump.starts_with("u 42\n<unparseable> s: "), "{}", dump);
+
+        let mut body = MarshalledMessageBody::new();
+        body.push_param(42u32).unwrap();
+        body.buf.extend_from_slice(&[1, 2]);
+        assert_eq!(
+            body.dump(),
+            "u 42\n<unparseable> 2 bytes after the last value\n"
+        );
+    }
+}
@@ -0,0 +1,697 @@
+//! Convert params to `serde_json::Value`s for logging or bridging to JSON based systems, enabled with the `serde_json` feature
+//!
+//! Values are mapped like this:
+//!
+//! * booleans, numbers and strings (including object paths and signatures) to their JSON counterparts
+//! * arrays and structs to arrays, so byte arrays become arrays of numbers
+//! * dicts to objects. Keys that are not strings are turned into strings: numbers and booleans as they are written in
+//!   JSON, unix fds like their values below
+//! * variants to the value they contain
+//! * unix fds to the index of the fd in the message, or `null` if it is not known
+//!
+//! JSON numbers can only be relied on to represent integers in the range of an `f64` without loss, which ends at
+//! 2^53 - 1 (`MAX_SAFE_INTEGER`). `u64` and `i64` values outside of `-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER` are
+//! emitted as strings of their decimal representation instead. Doubles that are not finite are emitted as the strings
+//! `"NaN"`, `"Infinity"` and `"-Infinity"`.
+//!
+//! `from_json` goes the other way and builds a param of a given signature from a JSON value, see its docs for the rules.
+//!
+//! ```rust
+//! use rustbus::message_builder::MarshalledMessageBody;
+//! use rustbus::params::json::to_json;
+//! use serde_json::json;
+//!
+//! let mut body = MarshalledMessageBody::new();
+//! body.push_param2("name", (u64::MAX, &[1u8, 2][..])).unwrap();
+//! assert_eq!(
+//!     to_json(&body, "s(tay)").unwrap(),
+//!     json!(["name", ["18446744073709551615", [1, 2]]])
+//! );
+//! ```
+
+use std::convert::TryFrom;
+
+use serde_json::{Map, Number, Value};
+use thiserror::Error;
+
+use crate::message_builder::MarshalledMessageBody;
+use crate::params::{self, Base, Container, Param};
+use crate::signature;
+use crate::wire::errors::UnmarshalError;
+use crate::wire::unmarshal::container::unmarshal_with_sig;
+use crate::wire::unmarshal::UnmarshalContext;
+use crate::wire::UnixFd;
+
+/// The largest integer that JSON numbers are guaranteed to represent exactly, 2^53 - 1
+pub const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Unmarshal the values described by `sig` from `body` and convert them into a JSON array with one element per value
+pub fn to_json(body: &MarshalledMessageBody, sig: &str) -> Result<Value, UnmarshalError> {
+    let sigs = signature::Type::parse_description(sig)?;
+    let mut ctx = UnmarshalContext::new(&body.raw_fds, &body.buf, body.byteorder, 0);
+    let mut values = Vec::with_capacity(sigs.len());
+    for sig in &sigs {
+        let (_, param) = unmarshal_with_sig(sig, &mut ctx)?;
+        values.push(convert_param(&param, &body.raw_fds));
+    }
+    Ok(Value::Array(values))
+}
+
+/// Convert a single param. Unix fds become `null` since it is not known which message they belong to.
+pub fn param_to_json(param: &Param) -> Value {
+    convert_param(param, &[])
+}
+
+fn convert_param(param: &Param, fds: &[UnixFd]) -> Value {
+    match param {
+        Param::Base(base) => convert_base(base, fds),
+        Param::Container(container) => convert_container(container, fds),
+    }
+}
+
+fn u64_to_json(val: u64) -> Value {
+    if val <= MAX_SAFE_INTEGER {
+        Value::Number(val.into())
+    } else {
+        Value::String(val.to_string())
+    }
+}
+
+fn i64_to_json(val: i64) -> Value {
+    if val.unsigned_abs() <= MAX_SAFE_INTEGER {
+        Value::Number(val.into())
+    } else {
+        Value::String(val.to_string())
+    }
+}
+
+fn f64_to_json(val: f64) -> Value {
+    match Number::from_f64(val) {
+        Some(number) => Value::Number(number),
+        None if val.is_nan() => Value::String("NaN".to_owned()),
+        None if val > 0.0 => Value::String("Infinity".to_owned()),
+        None => Value::String("-Infinity".to_owned()),
+    }
+}
+
+fn fd_to_json(fd: &UnixFd, fds: &[UnixFd]) -> Value {
+    match fds.iter().position(|other| other == fd) {
+        Some(idx) => Value::Number(idx.into()),
+        None => Value::Null,
+    }
+}
+
+fn convert_base(base: &Base, fds: &[UnixFd]) -> Value {
+    match base {
+        Base::Boolean(b) => Value::Bool(*b),
+        Base::Byte(i) => Value::Number((*i).into()),
+        Base::Int16(i) => Value::Number((*i).into()),
+        Base::Uint16(i) => Value::Number((*i).into()),
+        Base::Int32(i) => Value::Number((*i).into()),
+        Base::Uint32(i) => Value::Number((*i).into()),
+        Base::Int64(i) => i64_to_json(*i),
+        Base::Uint64(i) => u64_to_json(*i),
+        Base::Double(bits) => f64_to_json(f64::from_bits(*bits)),
+        Base::UnixFd(fd) => fd_to_json(fd, fds),
+        Base::String(s) | Base::Signature(s) | Base::ObjectPath(s) => Value::String(s.clone()),
+        Base::StringRef(s) | Base::SignatureRef(s) | Base::ObjectPathRef(s) => {
+            Value::String((*s).to_owned())
+        }
+        Base::StringCow(s) => Value::String(s.clone().into_owned()),
+    }
+}
+
+/// Dict keys are base types, which are turned into the string of their JSON value
+fn convert_key(key: &Base, fds: &[UnixFd]) -> String {
+    match convert_base(key, fds) {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+fn convert_dict<'x>(
+    dict: impl IntoIterator<Item = (&'x Base<'x>, &'x Param<'x, 'x>)>,
+    fds: &[UnixFd],
+) -> Value {
+    let map = dict
+        .into_iter()
+        .map(|(key, value)| (convert_key(key, fds), convert_param(value, fds)))
+        .collect::<Map<_, _>>();
+    Value::Object(map)
+}
+
+fn convert_params(params: &[Param], fds: &[UnixFd]) -> Value {
+    Value::Array(params.iter().map(|p| convert_param(p, fds)).collect())
+}
+
+fn convert_container(container: &Container, fds: &[UnixFd]) -> Value {
+    match container {
+        Container::Array(array) => convert_params(&array.values, fds),
+        Container::ArrayRef(array) => convert_params(array.values, fds),
+        Container::Struct(fields) => convert_params(fields, fds),
+        Container::StructRef(fields) => convert_params(fields, fds),
+        Container::Dict(dict) => convert_dict(&dict.map, fds),
+        Container::DictRef(dict) => convert_dict(dict.map, fds),
+        Container::OrderedDict(dict) => convert_dict(&dict.map, fds),
+        Container::Variant(variant) => convert_param(&variant.value, fds),
+    }
+}
+
+/// Why a JSON value could not be turned into a param
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum FromJsonErrorKind {
+    /// The JSON value has the wrong type for the signature, e.g. a string where a number is needed
+    #[error("expected {expected} but found {found}")]
+    WrongType {
+        /// The signature of the value that was expected
+        expected: String,
+        found: &'static str,
+    },
+    /// A number or numeric string does not fit into the type of the signature
+    #[error("{value} is not a valid {expected}")]
+    InvalidNumber { value: String, expected: String },
+    /// A JSON array for a struct does not have one element per field
+    #[error("expected {expected} struct fields but found {found}")]
+    WrongStructLength { expected: usize, found: usize },
+    /// Two keys of a JSON object are the same value of the dict key type, e.g. `"1"` and `"1.0"` for `d`
+    #[error("the key occurs more than once")]
+    DuplicateKey,
+    /// JSON has no representation for unix fds
+    #[error("unix fds can not be created from JSON")]
+    UnixFd,
+    /// No type can be inferred for a `null` in a variant
+    #[error("no type can be inferred for null")]
+    Null,
+    /// A string is not a valid object path or signature or contains a null byte
+    #[error("{0}")]
+    Validation(#[from] params::validation::Error),
+}
+
+/// A JSON value could not be turned into a param. `path` points to the offending value in JSONPath notation like
+/// `$.devices[3].name`.
+#[derive(Debug, PartialEq, Eq, Error)]
+#[error("{path}: {kind}")]
+pub struct FromJsonError {
+    pub path: String,
+    pub kind: FromJsonErrorKind,
+}
+
+/// Build a param with the signature `sig` from a JSON value.
+///
+/// * booleans need JSON booleans, strings, object paths and signatures need JSON strings
+/// * integers need JSON numbers without a fractional part that fit into the type. Like `to_json` emits them, `x` and
+///   `t` also accept strings of decimal integers
+/// * doubles accept any JSON number and the strings `"NaN"`, `"Infinity"` and `"-Infinity"`
+/// * arrays need JSON arrays and structs JSON arrays with one element per field
+/// * dicts need JSON objects. The keys are parsed as the key type, e.g. `"42"` for a `u` key
+/// * variants take the type of the JSON value: booleans are `b`, integers are `x` (or `t` if they are too big for an
+///   `i64`), other numbers are `d` and strings are `s`. Arrays whose elements all have the same type are arrays of
+///   that type, other arrays are `av`. Objects are `a{sv}`. `null` has no type and is an error.
+/// * unix fds can not be created from JSON
+///
+/// ```rust
+/// use rustbus::params::json::from_json;
+/// use rustbus::params::{Base, Container, Param};
+/// use rustbus::signature::Type;
+/// use serde_json::json;
+///
+/// let sig = &Type::parse_description("(ua{sv})").unwrap()[0];
+/// let param = from_json(&json!([7, {"name": "seven"}]), sig).unwrap();
+/// assert_eq!(param.sig(), *sig);
+///
+/// let err = from_json(&json!(["7", {}]), sig).unwrap_err();
+/// assert_eq!(err.to_string(), "$[0]: expected u but found a string");
+/// ```
+pub fn from_json(
+    value: &Value,
+    sig: &signature::Type,
+) -> Result<Param<'static, 'static>, FromJsonError> {
+    let mut path = String::from("$");
+    json_to_param(value, sig, &mut path).map_err(|kind| FromJsonError { path, kind })
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+fn sig_string(sig: &signature::Type) -> String {
+    let mut s = String::new();
+    sig.to_str(&mut s);
+    s
+}
+
+/// Append the path of an object member. Keys that are not identifiers are quoted.
+fn push_key(path: &mut String, key: &str) {
+    let is_ident = key.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_ident {
+        path.push('.');
+        path.push_str(key);
+    } else {
+        path.push_str(&format!("[{:?}]", key));
+    }
+}
+
+/// On error `path` is left pointing to the offending value
+fn json_to_param(
+    value: &Value,
+    sig: &signature::Type,
+    path: &mut String,
+) -> Result<Param<'static, 'static>, FromJsonErrorKind> {
+    let wrong_type = || FromJsonErrorKind::WrongType {
+        expected: sig_string(sig),
+        found: json_type_name(value),
+    };
+    let container = match sig {
+        signature::Type::Base(base) => return Ok(Param::Base(json_to_base(value, *base)?)),
+        signature::Type::Container(container) => container,
+    };
+    let container = match (container, value) {
+        (signature::Container::Array(element_sig), Value::Array(elements)) => {
+            let values = json_to_params(elements.iter().map(|e| (e, &**element_sig)), path)?;
+            Container::Array(params::Array {
+                element_sig: (**element_sig).clone(),
+                values,
+            })
+        }
+        (signature::Container::Struct(field_sigs), Value::Array(fields)) => {
+            let field_sigs = field_sigs.as_ref();
+            if fields.len() != field_sigs.len() {
+                return Err(FromJsonErrorKind::WrongStructLength {
+                    expected: field_sigs.len(),
+                    found: fields.len(),
+                });
+            }
+            Container::Struct(json_to_params(fields.iter().zip(field_sigs), path)?)
+        }
+        (signature::Container::Dict(key_sig, value_sig), Value::Object(members)) => {
+            let mut map = params::DictMap::new();
+            for (key, member) in members {
+                let len = path.len();
+                push_key(path, key);
+                let key = key_to_base(key, *key_sig)?;
+                let member = json_to_param(member, value_sig, path)?;
+                if map.insert(key, member).is_some() {
+                    return Err(FromJsonErrorKind::DuplicateKey);
+                }
+                path.truncate(len);
+            }
+            Container::Dict(params::Dict {
+                key_sig: *key_sig,
+                value_sig: (**value_sig).clone(),
+                map,
+            })
+        }
+        (signature::Container::Variant, _) => {
+            Container::Variant(Box::new(params::Variant::new(infer_param(value, path)?)))
+        }
+        _ => return Err(wrong_type()),
+    };
+    Ok(Param::Container(container))
+}
+
+/// Convert the elements of a JSON array, each with its own signature
+fn json_to_params<'v>(
+    elements: impl Iterator<Item = (&'v Value, &'v signature::Type)>,
+    path: &mut String,
+) -> Result<Vec<Param<'static, 'static>>, FromJsonErrorKind> {
+    let mut params = Vec::new();
+    for (idx, (element, sig)) in elements.enumerate() {
+        let len = path.len();
+        path.push_str(&format!("[{}]", idx));
+        params.push(json_to_param(element, sig, path)?);
+        path.truncate(len);
+    }
+    Ok(params)
+}
+
+fn invalid_number(value: impl ToString, sig: signature::Base) -> FromJsonErrorKind {
+    FromJsonErrorKind::InvalidNumber {
+        value: value.to_string(),
+        expected: sig.as_char().to_string(),
+    }
+}
+
+/// Integers up to 32 bits must be JSON integers, 64 bit integers may also be strings
+fn json_to_integer<T: TryFrom<i64> + TryFrom<u64> + core::str::FromStr>(
+    value: &Value,
+    sig: signature::Base,
+) -> Result<T, FromJsonErrorKind> {
+    let wide = matches!(sig, signature::Base::Int64 | signature::Base::Uint64);
+    match value {
+        Value::Number(number) => {
+            let converted = if let Some(i) = number.as_i64() {
+                T::try_from(i).ok()
+            } else {
+                number.as_u64().and_then(|u| T::try_from(u).ok())
+            };
+            converted.ok_or_else(|| invalid_number(number, sig))
+        }
+        Value::String(s) if wide => s.parse().map_err(|_| invalid_number(s, sig)),
+        _ => Err(FromJsonErrorKind::WrongType {
+            expected: sig.as_char().to_string(),
+            found: json_type_name(value),
+        }),
+    }
+}
+
+fn parse_double(s: &str) -> Option<f64> {
+    match s {
+        "NaN" => Some(f64::NAN),
+        "Infinity" => Some(f64::INFINITY),
+        "-Infinity" => Some(f64::NEG_INFINITY),
+        _ => None,
+    }
+}
+
+fn json_to_base(value: &Value, sig: signature::Base) -> Result<Base<'static>, FromJsonErrorKind> {
+    let wrong_type = || FromJsonErrorKind::WrongType {
+        expected: sig.as_char().to_string(),
+        found: json_type_name(value),
+    };
+    let base = match sig {
+        signature::Base::Boolean => Base::Boolean(value.as_bool().ok_or_else(wrong_type)?),
+        signature::Base::Byte => Base::Byte(json_to_integer(value, sig)?),
+        signature::Base::Int16 => Base::Int16(json_to_integer(value, sig)?),
+        signature::Base::Uint16 => Base::Uint16(json_to_integer(value, sig)?),
+        signature::Base::Int32 => Base::Int32(json_to_integer(value, sig)?),
+        signature::Base::Uint32 => Base::Uint32(json_to_integer(value, sig)?),
+        signature::Base::Int64 => Base::Int64(json_to_integer(value, sig)?),
+        signature::Base::Uint64 => Base::Uint64(json_to_integer(value, sig)?),
+        signature::Base::Double => match value {
+            Value::Number(number) => Base::double(number.as_f64().ok_or_else(wrong_type)?),
+            Value::String(s) => {
+                Base::double(parse_double(s).ok_or_else(|| invalid_number(s, sig))?)
+            }
+            _ => return Err(wrong_type()),
+        },
+        signature::Base::UnixFd => return Err(FromJsonErrorKind::UnixFd),
+        signature::Base::String | signature::Base::ObjectPath | signature::Base::Signature => {
+            let s = value.as_str().ok_or_else(wrong_type)?;
+            string_to_base(s, sig)?
+        }
+    };
+    Ok(base)
+}
+
+fn string_to_base(s: &str, sig: signature::Base) -> Result<Base<'static>, FromJsonErrorKind> {
+    if s.contains('\0') {
+        return Err(params::validation::Error::StringContainsNullByte.into());
+    }
+    match sig {
+        signature::Base::ObjectPath => {
+            params::validate_object_path(s)?;
+            Ok(Base::ObjectPath(s.to_owned()))
+        }
+        signature::Base::Signature => {
+            params::validate_signature(s)?;
+            Ok(Base::Signature(s.to_owned()))
+        }
+        _ => Ok(Base::String(s.to_owned())),
+    }
+}
+
+/// Object keys are always strings, parse them like the JSON value they would be for the key type
+fn key_to_base(key: &str, sig: signature::Base) -> Result<Base<'static>, FromJsonErrorKind> {
+    match sig {
+        signature::Base::String | signature::Base::ObjectPath | signature::Base::Signature => {
+            string_to_base(key, sig)
+        }
+        signature::Base::Boolean => match key {
+            "true" => Ok(Base::Boolean(true)),
+            "false" => Ok(Base::Boolean(false)),
+            _ => Err(FromJsonErrorKind::WrongType {
+                expected: sig.as_char().to_string(),
+                found: "a string",
+            }),
+        },
+        signature::Base::Double if parse_double(key).is_some() => {
+            json_to_base(&Value::String(key.to_owned()), sig)
+        }
+        _ => match serde_json::from_str::<Number>(key) {
+            Ok(number) => json_to_base(&Value::Number(number), sig),
+            Err(_) => Err(invalid_number(key, sig)),
+        },
+    }
+}
+
+/// Pick a type for a JSON value in a variant
+fn infer_param(
+    value: &Value,
+    path: &mut String,
+) -> Result<Param<'static, 'static>, FromJsonErrorKind> {
+    let base = match value {
+        Value::Null => return Err(FromJsonErrorKind::Null),
+        Value::Bool(b) => Base::Boolean(*b),
+        Value::Number(number) => match (number.as_i64(), number.as_u64(), number.as_f64()) {
+            (Some(i), _, _) => Base::Int64(i),
+            (None, Some(u), _) => Base::Uint64(u),
+            (None, None, Some(f)) => Base::double(f),
+            (None, None, None) => return Err(invalid_number(number, signature::Base::Double)),
+        },
+        Value::String(s) => string_to_base(s, signature::Base::String)?,
+        Value::Array(elements) => {
+            let mut values = Vec::with_capacity(elements.len());
+            for (idx, element) in elements.iter().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", idx));
+                values.push(infer_param(element, path)?);
+                path.truncate(len);
+            }
+            let element_sig = match values.split_first() {
+                Some((first, rest)) => {
+                    let sig = first.sig();
+                    if rest.iter().all(|v| v.sig() == sig) {
+                        Some(sig)
+                    } else {
+                        None
+                    }
+                }
+                None => None,
+            };
+            let (element_sig, values) = match element_sig {
+                Some(sig) => (sig, values),
+                None => (
+                    signature::Type::Container(signature::Container::Variant),
+                    values
+                        .into_iter()
+                        .map(|v| {
+                            Param::Container(Container::Variant(Box::new(params::Variant::new(v))))
+                        })
+                        .collect(),
+                ),
+            };
+            return Ok(Param::Container(Container::Array(params::Array {
+                element_sig,
+                values,
+            })));
+        }
+        Value::Object(_) => {
+            let sig = signature::Type::Container(signature::Container::Dict(
+                signature::Base::String,
+                Box::new(signature::Type::Container(signature::Container::Variant)),
+            ));
+            return json_to_param(value, &sig, path);
+        }
+    };
+    Ok(Param::Base(base))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::wire::marshal::traits::Variant;
+    use crate::wire::ObjectPath;
+
+    #[test]
+    fn test_to_json() {
+        let mut body = MarshalledMessageBody::new();
+        body.push_param(true).unwrap();
+        body.push_param(-5i16).unwrap();
+        body.push_param(1.5f64).unwrap();
+        body.push_param(ObjectPath::new("/a/b").unwrap()).unwrap();
+        body.push_param(&[0xdeu8, 0xad][..]).unwrap();
+        let mut dict = HashMap::new();
+        dict.insert("names", Variant(vec!["a", "b"]));
+        dict.insert("empty", Variant(vec![]));
+        body.push_param(&dict).unwrap();
+        let mut by_number = HashMap::new();
+        by_number.insert(3u8, "three");
+        by_number.insert(4u8, "four");
+        body.push_param(&by_number).unwrap();
+        body.push_param(UnixFd::new(nix::unistd::dup(1).unwrap()))
+            .unwrap();
+
+        assert_eq!(
+            to_json(&body, "bndoaya{sv}a{ys}h").unwrap(),
+            json!([
+                true,
+                -5,
+                1.5,
+                "/a/b",
+                [0xde, 0xad],
+                {"empty": [], "names": ["a", "b"]},
+                {"3": "three", "4": "four"},
+                0
+            ])
+        );
+        assert!(to_json(&body, "s").is_err());
+    }
+
+    #[test]
+    fn test_unsafe_numbers() {
+        let max = MAX_SAFE_INTEGER;
+        let int = |i: i64| param_to_json(&Param::Base(Base::Int64(i)));
+        let uint = |i: u64| param_to_json(&Param::Base(Base::Uint64(i)));
+        let double = |d: f64| param_to_json(&Param::Base(Base::double(d)));
+
+        assert_eq!(uint(max), json!(max));
+        assert_eq!(uint(max + 1), json!("9007199254740992"));
+        assert_eq!(int(-(max as i64)), json!(-(max as i64)));
+        assert_eq!(int(-(max as i64) - 1), json!("-9007199254740992"));
+        assert_eq!(int(i64::MIN), json!("-9223372036854775808"));
+        assert_eq!(double(f64::NAN), json!("NaN"));
+        assert_eq!(double(f64::NEG_INFINITY), json!("-Infinity"));
+
+        // keys that are not strings are stringified with the same rules
+        let mut dict = crate::params::Dict {
+            key_sig: signature::Base::Uint64,
+            value_sig: signature::Type::Base(signature::Base::Boolean),
+            map: crate::params::DictMap::new(),
+        };
+        dict.map
+            .insert(Base::Uint64(u64::MAX), Param::Base(Base::Boolean(true)));
+        dict.map
+            .insert(Base::Uint64(1), Param::Base(Base::Boolean(false)));
+        assert_eq!(
+            param_to_json(&Param::Container(Container::Dict(dict))),
+            json!({"1": false, "18446744073709551615": true})
+        );
+        assert_eq!(
+            param_to_json(&Param::Base(Base::UnixFd(UnixFd::new(-1)))),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn test_from_json() {
+        let sig = |s: &str| signature::Type::parse_description(s).unwrap().remove(0);
+        let roundtrip = |value: Value, sig_str: &str| {
+            let param = from_json(&value, &sig(sig_str)).unwrap();
+            assert_eq!(param.sig(), sig(sig_str));
+            let mut body = MarshalledMessageBody::new();
+            body.push_old_param(&param).unwrap();
+            assert_eq!(to_json(&body, sig_str).unwrap(), json!([value]));
+        };
+
+        roundtrip(json!(true), "b");
+        roundtrip(json!(255), "y");
+        roundtrip(json!(-32768), "n");
+        roundtrip(json!(4_000_000_000u32), "u");
+        roundtrip(json!("18446744073709551615"), "t");
+        roundtrip(json!("-9223372036854775808"), "x");
+        roundtrip(json!(-2.5), "d");
+        roundtrip(json!("Infinity"), "d");
+        roundtrip(json!("/org/freedesktop"), "o");
+        roundtrip(json!("a{sv}"), "g");
+        roundtrip(json!([[1, "one"], [2, "two"]]), "a(us)");
+        roundtrip(json!({"1": [true], "2": []}), "a{qab}");
+        roundtrip(json!({"true": "yes"}), "a{bs}");
+
+        assert_eq!(
+            from_json(&json!(1), &sig("d")).unwrap(),
+            Param::Base(Base::double(1.0))
+        );
+        assert_eq!(
+            from_json(&json!(7), &sig("x")).unwrap(),
+            Param::Base(Base::Int64(7))
+        );
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        let sig = |s: &str| signature::Type::parse_description(s).unwrap().remove(0);
+        let err = |value: Value, sig_str: &str| from_json(&value, &sig(sig_str)).unwrap_err();
+
+        assert_eq!(
+            err(
+                json!({"devices": [{"name": "a"}, {"name": 1}]}),
+                "a{saa{ss}}"
+            ),
+            FromJsonError {
+                path: "$.devices[1].name".to_owned(),
+                kind: FromJsonErrorKind::WrongType {
+                    expected: "s".to_owned(),
+                    found: "a number",
+                },
+            }
+        );
+        assert_eq!(
+            err(json!({"not ident": 256}), "a{sy}").to_string(),
+            "$[\"not ident\"]: 256 is not a valid y"
+        );
+        assert_eq!(
+            err(json!([1.5]), "au").kind,
+            FromJsonErrorKind::InvalidNumber {
+                value: "1.5".to_owned(),
+                expected: "u".to_owned(),
+            }
+        );
+        assert_eq!(
+            err(json!(["1", 2]), "(uuu)").kind,
+            FromJsonErrorKind::WrongStructLength {
+                expected: 3,
+                found: 2
+            }
+        );
+        assert_eq!(err(json!({"1": 1, "1.0": 2}), "a{dx}").path, "$[\"1.0\"]");
+        assert_eq!(
+            err(json!({"1": 1, "1.0": 2}), "a{dx}").kind,
+            FromJsonErrorKind::DuplicateKey
+        );
+        assert_eq!(
+            err(json!("no/path"), "o").kind,
+            FromJsonErrorKind::Validation(params::validation::Error::InvalidObjectPath(
+                params::validation::ObjectPathError::MissingLeadingSlash
+            ))
+        );
+        assert_eq!(err(json!(0), "h").kind, FromJsonErrorKind::UnixFd);
+        assert_eq!(err(json!([1, null]), "v").path, "$[1]");
+        assert_eq!(err(json!([1, null]), "v").kind, FromJsonErrorKind::Null);
+    }
+
+    #[test]
+    fn test_from_json_variant() {
+        let sig = signature::Type::Container(signature::Container::Variant);
+        let inferred = |value: Value| match from_json(&value, &sig).unwrap() {
+            Param::Container(Container::Variant(variant)) => {
+                let mut s = String::new();
+                variant.sig.to_str(&mut s);
+                s
+            }
+            other => panic!("not a variant: {:?}", other),
+        };
+        assert_eq!(inferred(json!(false)), "b");
+        assert_eq!(inferred(json!(-1)), "x");
+        assert_eq!(inferred(json!(u64::MAX)), "t");
+        assert_eq!(inferred(json!(0.5)), "d");
+        assert_eq!(inferred(json!("text")), "s");
+        assert_eq!(inferred(json!([1, 2, 3])), "ax");
+        assert_eq!(inferred(json!([[1], [2, 3]])), "aax");
+        assert_eq!(inferred(json!([[1], ["a"]])), "av");
+        assert_eq!(inferred(json!([1, "a"])), "av");
+        assert_eq!(inferred(json!([])), "av");
+        assert_eq!(inferred(json!({"a": {"b": 1}})), "a{sv}");
+    }
+}
@@ -1,5 +1,6 @@
 //! Messages that have been completetly unmarshalled
 
+use crate::alloc_prelude::*;
 use crate::message_builder::{DynamicHeader, HeaderFlags, MessageType};
 use crate::params::*;
 use crate::signature;
@@ -53,7 +54,7 @@ impl<'a, 'e> Message<'a, 'e> {
     }
     pub fn push_params<P: Into<Param<'a, 'e>>>(&mut self, params: Vec<P>) {
         self.params
-            .extend(params.into_iter().map(std::convert::Into::into));
+            .extend(params.into_iter().map(core::convert::Into::into));
     }
     pub fn push_param<P: Into<Param<'a, 'e>>>(&mut self, param: P) {
         self.params.push(param.into());
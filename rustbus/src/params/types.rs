@@ -1,4 +1,6 @@
+use crate::alloc_prelude::*;
 use crate::{signature, wire::marshal::traits::SignatureBuffer, Marshal, Signature, Unmarshal};
+use alloc::borrow::Cow;
 
 /// The Types a message can have as parameters
 /// There are From<T> impls for most of the Base ones
@@ -12,9 +14,17 @@ pub enum Param<'a, 'e> {
 
 /// The base types a message can have as parameters
 /// There are From<T> impls for most of them
-#[derive(Debug, Hash, PartialEq, Eq, Clone)]
+///
+/// The owned and by-ref variants of the string-like types are equal if their contents are, so a decoded value can be
+/// compared against an expected one regardless of which variant either uses.
+///
+/// Doubles are stored as their raw bits and `Eq`, `Hash` and `Ord` work on these bits, not on the `f64` value. This
+/// keeps `Base` usable as a map key and makes the comparison exact, but it means that `0.0` and `-0.0` are not equal,
+/// while two NaNs with the same bits are. Use `Base::as_f64` and `Base::approx_eq` to compare by value.
+#[derive(Debug, Clone)]
 pub enum Base<'a> {
     // Owned
+    /// The bits of an `f64`, see `Base::double` and `Base::as_f64`
     Double(u64),
     Byte(u8),
     Int16(i16),
@@ -33,9 +43,14 @@ pub enum Base<'a> {
     StringRef(&'a str),
     SignatureRef(&'a str),
     ObjectPathRef(&'a str),
+
+    /// A string that is either owned or borrowed, for code that only decides at runtime. It marshals and compares
+    /// like `String` and `StringRef`.
+    StringCow(Cow<'a, str>),
 }
 
-pub type DictMap<'a, 'e> = std::collections::HashMap<Base<'a>, Param<'a, 'e>>;
+/// The entries of a dict, sorted by their keys
+pub type DictMap<'a, 'e> = alloc::collections::BTreeMap<Base<'a>, Param<'a, 'e>>;
 
 /// The container types a message can have as parameters
 ///
@@ -46,6 +61,7 @@ pub enum Container<'e, 'a: 'e> {
     Array(Array<'e, 'a>),
     Struct(Vec<Param<'a, 'e>>),
     Dict(Dict<'a, 'e>),
+    OrderedDict(OrderedDict<'a, 'e>),
     Variant(Box<Variant<'a, 'e>>),
     // By ref
     ArrayRef(ArrayRef<'a, 'e>),
@@ -53,12 +69,27 @@ pub enum Container<'e, 'a: 'e> {
     DictRef(DictRef<'a, 'e>),
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+/// A value together with its signature.
+///
+/// Prefer `Variant::new` or `Variant::with_sig` over filling in the fields directly, they make sure `sig` describes
+/// `value`. A variant whose `sig` does not match its `value` marshals into an invalid message.
+#[derive(Debug, Clone)]
 pub struct Variant<'a, 'e: 'a> {
     pub sig: signature::Type,
     pub value: Param<'a, 'e>,
+    /// The string form of `sig`, set by `Variant::with_cached_sig` so marshalling does not need to build and validate
+    /// it every time
+    pub(crate) cached_sig: Option<String>,
 }
 
+/// The cached signature is derived from `sig` so it is not compared
+impl PartialEq for Variant<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.sig == other.sig && self.value == other.value
+    }
+}
+impl Eq for Variant<'_, '_> {}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Array<'a, 'e: 'a> {
     pub element_sig: signature::Type,
@@ -78,6 +109,15 @@ pub struct Dict<'a, 'e: 'a> {
     pub map: DictMap<'a, 'e>,
 }
 
+/// A dict that keeps its entries sorted by key. Marshalling it always produces the same bytes
+/// for the same content.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OrderedDict<'a, 'e: 'a> {
+    pub key_sig: signature::Base,
+    pub value_sig: signature::Type,
+    pub map: DictMap<'a, 'e>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct DictRef<'a, 'e: 'a> {
     pub key_sig: signature::Base,
@@ -98,6 +138,15 @@ impl<'a, 'e> Param<'a, 'e> {
             Param::Container(c) => c.sig(),
         }
     }
+
+    /// Copy everything that is borrowed, so the param can outlive the buffer it was unmarshalled from. Owned strings,
+    /// vecs and maps are moved into the result instead of being copied.
+    pub fn into_owned(self) -> Param<'static, 'static> {
+        match self {
+            Param::Base(b) => Param::Base(b.into_owned()),
+            Param::Container(c) => Param::Container(c.into_owned()),
+        }
+    }
 }
 
 impl<'a> Base<'a> {
@@ -119,6 +168,7 @@ impl<'a> Base<'a> {
             Base::ObjectPathRef(_) => buf.push('o'),
             Base::StringRef(_) => buf.push('s'),
             Base::SignatureRef(_) => buf.push('g'),
+            Base::StringCow(_) => buf.push('s'),
         }
     }
 
@@ -126,8 +176,185 @@ impl<'a> Base<'a> {
         let sig: signature::Base = self.into();
         signature::Type::Base(sig)
     }
+
+    /// Turn the by-ref variants into their owned counterparts, see `Param::into_owned`
+    pub fn into_owned(self) -> Base<'static> {
+        match self {
+            Base::Double(v) => Base::Double(v),
+            Base::Byte(v) => Base::Byte(v),
+            Base::Int16(v) => Base::Int16(v),
+            Base::Uint16(v) => Base::Uint16(v),
+            Base::Int32(v) => Base::Int32(v),
+            Base::Uint32(v) => Base::Uint32(v),
+            Base::UnixFd(v) => Base::UnixFd(v),
+            Base::Int64(v) => Base::Int64(v),
+            Base::Uint64(v) => Base::Uint64(v),
+            Base::String(v) => Base::String(v),
+            Base::Signature(v) => Base::Signature(v),
+            Base::ObjectPath(v) => Base::ObjectPath(v),
+            Base::Boolean(v) => Base::Boolean(v),
+            Base::StringRef(v) => Base::String(v.to_owned()),
+            Base::SignatureRef(v) => Base::Signature(v.to_owned()),
+            Base::ObjectPathRef(v) => Base::ObjectPath(v.to_owned()),
+            Base::StringCow(v) => Base::String(v.into_owned()),
+        }
+    }
+
+    /// A `Double` holding the bits of `value`
+    pub fn double(value: f64) -> Self {
+        Base::Double(value.to_bits())
+    }
+
+    /// Compare doubles by their value, allowing a difference of up to `epsilon`. Like for `f64` NaN is never equal
+    /// to anything and `0.0` equals `-0.0`. Other values are compared with `==`.
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        match (self, other) {
+            (Base::Double(a), Base::Double(b)) => {
+                (f64::from_bits(*a) - f64::from_bits(*b)).abs() <= epsilon
+            }
+            _ => self == other,
+        }
+    }
+
+    fn str_value(&self) -> Option<&str> {
+        match self {
+            Base::String(s) | Base::Signature(s) | Base::ObjectPath(s) => Some(s),
+            Base::StringRef(s) | Base::SignatureRef(s) | Base::ObjectPathRef(s) => Some(s),
+            Base::StringCow(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl PartialEq for Base<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Base::Double(a), Base::Double(b)) => a == b,
+            (Base::Byte(a), Base::Byte(b)) => a == b,
+            (Base::Int16(a), Base::Int16(b)) => a == b,
+            (Base::Uint16(a), Base::Uint16(b)) => a == b,
+            (Base::Int32(a), Base::Int32(b)) => a == b,
+            (Base::Uint32(a), Base::Uint32(b)) => a == b,
+            (Base::UnixFd(a), Base::UnixFd(b)) => a == b,
+            (Base::Int64(a), Base::Int64(b)) => a == b,
+            (Base::Uint64(a), Base::Uint64(b)) => a == b,
+            (Base::Boolean(a), Base::Boolean(b)) => a == b,
+            _ => match (self.str_value(), other.str_value()) {
+                (Some(a), Some(b)) => {
+                    signature::Base::from(self) == signature::Base::from(other) && a == b
+                }
+                _ => false,
+            },
+        }
+    }
+}
+impl Eq for Base<'_> {}
+
+/// Consistent with `PartialEq`, the owned and by-ref variants hash the same
+impl core::hash::Hash for Base<'_> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        (signature::Base::from(self) as u8).hash(state);
+        match self {
+            Base::Double(b) => b.hash(state),
+            Base::Byte(b) => b.hash(state),
+            Base::Int16(b) => b.hash(state),
+            Base::Uint16(b) => b.hash(state),
+            Base::Int32(b) => b.hash(state),
+            Base::Uint32(b) => b.hash(state),
+            Base::UnixFd(b) => b.hash(state),
+            Base::Int64(b) => b.hash(state),
+            Base::Uint64(b) => b.hash(state),
+            Base::Boolean(b) => b.hash(state),
+            Base::String(s) | Base::Signature(s) | Base::ObjectPath(s) => s.hash(state),
+            Base::StringRef(s) | Base::SignatureRef(s) | Base::ObjectPathRef(s) => s.hash(state),
+            Base::StringCow(s) => s.hash(state),
+        }
+    }
+}
+
+/// Values of different types are ordered like their types in `signature::Base`, values of the same type
+/// are ordered by their value. Doubles use the IEEE 754 total order so this stays consistent with `Eq`,
+/// which compares the bits. The owned and by-ref variants of the string-like types compare by content.
+impl Ord for Base<'_> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        let self_type = signature::Base::from(self) as u8;
+        let other_type = signature::Base::from(other) as u8;
+        if self_type != other_type {
+            return self_type.cmp(&other_type);
+        }
+        match (self, other) {
+            (Base::Double(a), Base::Double(b)) => f64::from_bits(*a).total_cmp(&f64::from_bits(*b)),
+            (Base::Byte(a), Base::Byte(b)) => a.cmp(b),
+            (Base::Int16(a), Base::Int16(b)) => a.cmp(b),
+            (Base::Uint16(a), Base::Uint16(b)) => a.cmp(b),
+            (Base::Int32(a), Base::Int32(b)) => a.cmp(b),
+            (Base::Uint32(a), Base::Uint32(b)) => a.cmp(b),
+            (Base::UnixFd(a), Base::UnixFd(b)) => a.cmp(b),
+            (Base::Int64(a), Base::Int64(b)) => a.cmp(b),
+            (Base::Uint64(a), Base::Uint64(b)) => a.cmp(b),
+            (Base::Boolean(a), Base::Boolean(b)) => a.cmp(b),
+            _ => match (self.str_value(), other.str_value()) {
+                (Some(a), Some(b)) => a.cmp(b),
+                _ => unreachable!("Values have the same type"),
+            },
+        }
+    }
+}
+impl PartialOrd for Base<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
 }
 impl<'a, 'e> Container<'a, 'e> {
+    /// Turn the by-ref variants into their owned counterparts and copy the borrowed values inside them, see
+    /// `Param::into_owned`
+    pub fn into_owned(self) -> Container<'static, 'static> {
+        match self {
+            Container::Array(arr) => Container::Array(Array {
+                element_sig: arr.element_sig,
+                values: arr.values.into_iter().map(Param::into_owned).collect(),
+            }),
+            Container::Struct(fields) => {
+                Container::Struct(fields.into_iter().map(Param::into_owned).collect())
+            }
+            Container::Dict(dict) => Container::Dict(Dict {
+                key_sig: dict.key_sig,
+                value_sig: dict.value_sig,
+                map: dict
+                    .map
+                    .into_iter()
+                    .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                    .collect(),
+            }),
+            Container::OrderedDict(dict) => Container::OrderedDict(OrderedDict {
+                key_sig: dict.key_sig,
+                value_sig: dict.value_sig,
+                map: dict
+                    .map
+                    .into_iter()
+                    .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                    .collect(),
+            }),
+            Container::Variant(var) => Container::Variant(Box::new(var.into_owned())),
+            Container::ArrayRef(arr) => Container::Array(Array {
+                element_sig: arr.element_sig,
+                values: arr.values.iter().cloned().map(Param::into_owned).collect(),
+            }),
+            Container::StructRef(fields) => {
+                Container::Struct(fields.iter().cloned().map(Param::into_owned).collect())
+            }
+            Container::DictRef(dict) => Container::Dict(Dict {
+                key_sig: dict.key_sig,
+                value_sig: dict.value_sig,
+                map: dict
+                    .map
+                    .iter()
+                    .map(|(key, value)| (key.clone().into_owned(), value.clone().into_owned()))
+                    .collect(),
+            }),
+        }
+    }
+
     pub fn make_signature(&self, buf: &mut String) {
         match self {
             Container::Array(elements) => {
@@ -141,6 +368,13 @@ impl<'a, 'e> Container<'a, 'e> {
                 map.value_sig.to_str(buf);
                 buf.push('}');
             }
+            Container::OrderedDict(map) => {
+                buf.push('a');
+                buf.push('{');
+                map.key_sig.to_str(buf);
+                map.value_sig.to_str(buf);
+                buf.push('}');
+            }
             Container::Struct(elements) => {
                 buf.push('(');
                 for el in elements {
@@ -177,6 +411,7 @@ impl<'a, 'e> Container<'a, 'e> {
             Container::Array(elements) => elements.values.len(),
             Container::ArrayRef(elements) => elements.values.len(),
             Container::Dict(map) => map.map.len(),
+            Container::OrderedDict(map) => map.map.len(),
             Container::DictRef(map) => map.map.len(),
             Container::Struct(elements) => elements.len(),
             Container::StructRef(elements) => elements.len(),
@@ -192,6 +427,27 @@ impl<'a, 'e> Container<'a, 'e> {
         let sig: signature::Container = self.into();
         signature::Type::Container(sig)
     }
+
+    /// Turn a `Dict` or `DictRef` into an `OrderedDict`. Other containers are returned unchanged.
+    pub fn into_ordered(self) -> Container<'a, 'e> {
+        match self {
+            Container::Dict(dict) => Container::OrderedDict(OrderedDict {
+                key_sig: dict.key_sig,
+                value_sig: dict.value_sig,
+                map: dict.map.into_iter().collect(),
+            }),
+            Container::DictRef(dict) => Container::OrderedDict(OrderedDict {
+                key_sig: dict.key_sig,
+                value_sig: dict.value_sig.clone(),
+                map: dict
+                    .map
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+            }),
+            other => other,
+        }
+    }
 }
 
 impl Signature for Variant<'_, '_> {
@@ -214,21 +470,461 @@ impl Marshal for Variant<'_, '_> {
         &self,
         ctx: &mut crate::wire::marshal::MarshalContext,
     ) -> Result<(), crate::wire::errors::MarshalError> {
-        let mut sig = String::new();
-        self.sig.to_str(&mut sig);
-        if sig.len() > 255 {
-            let sig_err = crate::signature::Error::SignatureTooLong;
-            return Err(sig_err.into());
-        }
-        debug_assert!(crate::params::validation::validate_signature(&sig).is_ok());
-        crate::wire::util::write_signature(&sig, ctx.buf);
+        if let Some(sig) = &self.cached_sig {
+            // validated when it was cached
+            crate::wire::util::write_signature(sig, ctx.buf)?;
+        } else {
+            let mut sig = String::new();
+            self.sig.to_str(&mut sig);
+            crate::wire::util::write_signature(&sig, ctx.buf)?;
+        }
         crate::wire::marshal::container::marshal_param(&self.value, ctx)
     }
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        let sig_len = match &self.cached_sig {
+            Some(sig) => sig.len(),
+            None => self.sig.str_len(),
+        };
+        let sig_len = 1 + sig_len + 1;
+        sig_len
+            + crate::wire::marshal::container::param_marshalled_len(&self.value, offset + sig_len)
+    }
 }
-impl<'buf, 'fds> Unmarshal<'buf, 'fds> for Variant<'buf, 'fds> {
+/// The variant only holds owned params, so it can outlive the message it was unmarshalled from
+impl<'buf, 'fds, 'a, 'e> Unmarshal<'buf, 'fds> for Variant<'a, 'e> {
     fn unmarshal(
         ctx: &mut crate::wire::unmarshal::UnmarshalContext<'fds, 'buf>,
     ) -> crate::wire::unmarshal::UnmarshalResult<Self> {
         crate::wire::unmarshal::container::unmarshal_variant(ctx)
     }
 }
+
+impl<'a, 'e> Variant<'a, 'e> {
+    /// Copy the borrowed parts of the value, see `Param::into_owned`
+    pub fn into_owned(self) -> Variant<'static, 'static> {
+        Variant {
+            sig: self.sig,
+            value: self.value.into_owned(),
+            cached_sig: self.cached_sig,
+        }
+    }
+
+    /// Create a variant with the signature of `value`
+    pub fn new(value: Param<'a, 'e>) -> Self {
+        Self {
+            sig: value.sig(),
+            value,
+            cached_sig: None,
+        }
+    }
+
+    /// Create a variant with the signature `sig`. Returns `ConversionError::WrongVariantSignature` if it is not the
+    /// signature of `value`.
+    pub fn with_sig(
+        sig: signature::Type,
+        value: Param<'a, 'e>,
+    ) -> Result<Self, crate::params::ConversionError> {
+        let actual = value.sig();
+        if actual != sig {
+            return Err(crate::params::ConversionError::WrongVariantSignature {
+                expected: sig.to_string(),
+                actual: actual.to_string(),
+            });
+        }
+        Ok(Self {
+            sig,
+            value,
+            cached_sig: None,
+        })
+    }
+
+    /// Like `Variant::new` but also stores the string form of the signature, so it does not have to be built again
+    /// every time the variant is marshalled. The signature is validated here instead of while marshalling.
+    ///
+    /// The stored string is not updated if `sig` or `value` are changed afterwards, create a new variant instead.
+    pub fn with_cached_sig(value: Param<'a, 'e>) -> Result<Self, crate::params::validation::Error> {
+        let sig = value.sig();
+        let mut sig_str = String::new();
+        sig.to_str(&mut sig_str);
+        crate::params::validation::validate_signature(&sig_str)?;
+        Ok(Self {
+            sig,
+            value,
+            cached_sig: Some(sig_str),
+        })
+    }
+
+    /// Get the value of this variant as a `T`. This checks that `T` matches the signature of the variant
+    /// and returns `ConversionError::WrongVariantSignature` otherwise.
+    ///
+    /// Base values are converted directly. Containers are marshalled into a scratch buffer and unmarshalled as `T`.
+    pub fn get<'v, T>(&'v self) -> Result<T, crate::params::ConversionError>
+    where
+        T: FromVariant<'v>,
+    {
+        let mut base_sig = [0; 4];
+        let mut container_sig = String::new();
+        let actual: &str = match (&self.cached_sig, &self.sig) {
+            (Some(sig), _) => sig,
+            (None, signature::Type::Base(b)) => b.as_char().encode_utf8(&mut base_sig),
+            (None, sig) => {
+                sig.to_str(&mut container_sig);
+                &container_sig
+            }
+        };
+        if !T::has_sig(actual) {
+            let mut expected = SignatureBuffer::new();
+            T::sig_str(&mut expected);
+            return Err(crate::params::ConversionError::WrongVariantSignature {
+                expected: expected.as_str().to_owned(),
+                actual: actual.to_owned(),
+            });
+        }
+        T::from_variant(&self.value)
+    }
+}
+
+/// Types that can be taken out of a `Variant` with `Variant::get`. This is implemented for everything that can be
+/// unmarshalled independently of the buffer's lifetime, and for `&str`, which borrows from the variant.
+pub trait FromVariant<'v>: Signature + Sized {
+    /// Convert the value of a variant, its signature has already been checked.
+    fn from_variant(value: &'v Param<'_, '_>) -> Result<Self, crate::params::ConversionError>;
+}
+
+impl<'v, T> FromVariant<'v> for T
+where
+    T: for<'buf, 'fds> Unmarshal<'buf, 'fds>,
+{
+    fn from_variant(value: &'v Param<'_, '_>) -> Result<Self, crate::params::ConversionError> {
+        if let Param::Base(b) = value {
+            if let Some(val) = T::from_base(b) {
+                return val;
+            }
+        }
+
+        let mut buf = Vec::new();
+        let mut fds = Vec::new();
+        let mut ctx = crate::wire::marshal::MarshalContext {
+            buf: &mut buf,
+            fds: &mut fds,
+            byteorder: crate::ByteOrder::NATIVE,
+        };
+        match value {
+            Param::Base(b) => crate::wire::marshal::base::marshal_base_param(b, &mut ctx),
+            Param::Container(c) => {
+                crate::wire::marshal::container::marshal_container_param(c, &mut ctx)
+            }
+        }
+        .map_err(crate::params::ConversionError::Marshal)?;
+
+        let mut ctx =
+            crate::wire::unmarshal::UnmarshalContext::new(&fds, &buf, crate::ByteOrder::NATIVE, 0);
+        T::unmarshal(&mut ctx)
+            .map(|(_, val)| val)
+            .map_err(crate::params::ConversionError::Unmarshal)
+    }
+}
+
+impl<'v> FromVariant<'v> for &'v str {
+    fn from_variant(value: &'v Param<'_, '_>) -> Result<Self, crate::params::ConversionError> {
+        match value {
+            Param::Base(b) => b
+                .as_str()
+                .ok_or(crate::params::ConversionError::InvalidType),
+            Param::Container(_) => Err(crate::params::ConversionError::InvalidType),
+        }
+    }
+}
+
+#[test]
+fn test_variant_get() {
+    let var = Variant {
+        sig: signature::Type::Base(signature::Base::Uint32),
+        value: Param::Base(Base::Uint32(42)),
+        cached_sig: None,
+    };
+    assert_eq!(var.get::<u32>(), Ok(42));
+    assert_eq!(
+        var.get::<String>(),
+        Err(crate::params::ConversionError::WrongVariantSignature {
+            expected: "s".to_owned(),
+            actual: "u".to_owned(),
+        })
+    );
+
+    let var = Container::make_variant(Container::make_struct2(
+        Base::String("ABCD".to_owned()),
+        Base::Uint64(1234),
+    ));
+    if let Container::Variant(var) = var {
+        assert_eq!(var.get::<(String, u64)>(), Ok(("ABCD".to_owned(), 1234)));
+        assert_eq!(
+            var.get::<(u64, String)>(),
+            Err(crate::params::ConversionError::WrongVariantSignature {
+                expected: "(ts)".to_owned(),
+                actual: "(st)".to_owned(),
+            })
+        );
+    } else {
+        unreachable!();
+    }
+}
+
+#[test]
+fn test_variant_get_base() {
+    // a &str borrows the string stored in the variant
+    let var = Variant::new(Param::Base(Base::String("ABCD".to_owned())));
+    let s = var.get::<&str>().unwrap();
+    assert_eq!(s, "ABCD");
+    if let Param::Base(Base::String(stored)) = &var.value {
+        assert_eq!(s.as_ptr(), stored.as_ptr());
+    } else {
+        unreachable!();
+    }
+    assert_eq!(var.get::<String>(), Ok("ABCD".to_owned()));
+    assert_eq!(
+        var.get::<u32>(),
+        Err(crate::params::ConversionError::WrongVariantSignature {
+            expected: "u".to_owned(),
+            actual: "s".to_owned(),
+        })
+    );
+}
+
+#[test]
+fn test_variant_cached_sig() {
+    use crate::wire::marshal::container::marshal_container_param;
+    use crate::wire::marshal::MarshalContext;
+
+    let sig = signature::Type::parse_description("(us)")
+        .unwrap()
+        .remove(0);
+    let value = Param::Container(Container::make_struct2(
+        Base::Uint32(7),
+        Base::String("seven".to_owned()),
+    ));
+    let cached = Variant::with_cached_sig(value.clone()).unwrap();
+    assert_eq!(cached.cached_sig.as_deref(), Some("(us)"));
+    let uncached = Variant {
+        sig,
+        value,
+        cached_sig: None,
+    };
+    assert_eq!(cached, uncached);
+
+    let marshal_both = |var: &Variant| {
+        let mut buf = Vec::new();
+        let mut fds = Vec::new();
+        let mut ctx = MarshalContext {
+            buf: &mut buf,
+            fds: &mut fds,
+            byteorder: crate::ByteOrder::LittleEndian,
+        };
+        var.marshal(&mut ctx).unwrap();
+        marshal_container_param(&Container::Variant(Box::new(var.clone())), &mut ctx).unwrap();
+        buf
+    };
+    assert_eq!(marshal_both(&cached), marshal_both(&uncached));
+
+    // signatures that are too long are rejected when the variant is created
+    let long = Param::Container(Container::Struct(vec![Param::Base(Base::Byte(0)); 300]));
+    assert!(Variant::with_cached_sig(long).is_err());
+}
+
+#[test]
+fn test_variant_constructors() {
+    let value = Param::Container(Container::make_struct2(
+        Base::Uint32(7),
+        Base::String("seven".to_owned()),
+    ));
+    let var = Variant::new(value.clone());
+    assert_eq!(var.sig.to_string(), "(us)");
+    assert_eq!(var.get::<(u32, String)>(), Ok((7, "seven".to_owned())));
+
+    let sig = signature::Type::parse_description("(us)")
+        .unwrap()
+        .remove(0);
+    assert_eq!(Variant::with_sig(sig, value.clone()), Ok(var));
+    let sig = signature::Type::parse_description("(ut)")
+        .unwrap()
+        .remove(0);
+    assert_eq!(
+        Variant::with_sig(sig, value),
+        Err(crate::params::ConversionError::WrongVariantSignature {
+            expected: "(ut)".to_owned(),
+            actual: "(us)".to_owned(),
+        })
+    );
+}
+
+#[test]
+fn test_base_owned_ref_eq() {
+    use core::hash::{Hash, Hasher};
+    use std::collections::hash_map::DefaultHasher;
+
+    fn hash(base: &Base) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        base.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let pairs = [
+        (Base::String("x".to_owned()), Base::StringRef("x")),
+        (
+            Base::Signature("a{sv}".to_owned()),
+            Base::SignatureRef("a{sv}"),
+        ),
+        (
+            Base::ObjectPath("/a/b".to_owned()),
+            Base::ObjectPathRef("/a/b"),
+        ),
+    ];
+    for (owned, borrowed) in &pairs {
+        assert_eq!(owned, borrowed);
+        assert_eq!(borrowed, owned);
+        assert_eq!(hash(owned), hash(borrowed));
+        assert_eq!(owned.cmp(borrowed), core::cmp::Ordering::Equal);
+    }
+
+    // same content but a different type
+    assert_ne!(Base::String("/a".to_owned()), Base::ObjectPathRef("/a"));
+    assert_ne!(Base::StringRef("a"), Base::SignatureRef("a"));
+    assert_ne!(Base::StringRef("a"), Base::StringRef("b"));
+    assert_ne!(Base::Uint32(1), Base::Int32(1));
+
+    // doubles still compare by their bits
+    assert_eq!(
+        Base::Double(f64::NAN.to_bits()),
+        Base::Double(f64::NAN.to_bits())
+    );
+    assert_ne!(
+        Base::Double(0.0f64.to_bits()),
+        Base::Double((-0.0f64).to_bits())
+    );
+
+    let mut map = std::collections::HashMap::new();
+    map.insert(Base::String("key".to_owned()), 1);
+    assert_eq!(map.get(&Base::StringRef("key")), Some(&1));
+}
+
+#[test]
+fn test_string_cow() {
+    use crate::message_builder::MarshalledMessageBody;
+
+    let borrowed = Base::from(Cow::Borrowed("cow"));
+    let owned = Base::from(Cow::<str>::Owned("cow".to_owned()));
+    assert_eq!(borrowed, Base::StringRef("cow"));
+    assert_eq!(owned, Base::String("cow".to_owned()));
+    assert_ne!(borrowed, Base::ObjectPathRef("cow"));
+    assert_eq!(
+        borrowed.sig(),
+        signature::Type::Base(signature::Base::String)
+    );
+
+    let mut map = std::collections::HashMap::new();
+    map.insert(Base::StringRef("cow"), 1);
+    assert_eq!(map.get(&owned), Some(&1));
+
+    let mut cow_body = MarshalledMessageBody::new();
+    cow_body
+        .push_old_param(&Param::Base(borrowed.clone()))
+        .unwrap();
+    cow_body
+        .push_old_param(&Param::Base(owned.clone()))
+        .unwrap();
+    let mut body = MarshalledMessageBody::new();
+    body.push_param2("cow", "cow").unwrap();
+    assert_eq!(cow_body.buf, body.buf);
+    assert_eq!(cow_body.parser().get2::<&str, &str>(), Ok(("cow", "cow")));
+
+    assert!(matches!(borrowed.clone().into_owned(), Base::String(s) if s == "cow"));
+    assert_eq!(
+        Base::StringRef("cow").into_str_cow(),
+        Ok(Cow::Borrowed("cow"))
+    );
+    assert!(matches!(
+        Base::String("cow".to_owned()).into_str_cow(),
+        Ok(Cow::Owned(_))
+    ));
+    assert_eq!(Base::Uint32(1).into_str_cow(), Err(Base::Uint32(1)));
+}
+
+#[test]
+fn test_into_owned() {
+    fn keep(param: Param<'_, '_>) -> Param<'static, 'static> {
+        param.into_owned()
+    }
+
+    let owned_string = "moved, not copied".to_owned();
+    let string_ptr = owned_string.as_ptr();
+    let (owned, expected) = {
+        let path = "/borrowed/path".to_owned();
+        let elements = vec![Param::Base(Base::StringRef(&path))];
+        let mut map = DictMap::new();
+        map.insert(
+            Base::StringRef("key"),
+            Param::Base(Base::SignatureRef("a{sv}")),
+        );
+        let fields = [
+            Param::Base(Base::ObjectPathRef(&path)),
+            Param::Base(Base::Uint32(5)),
+        ];
+        let variant = Variant::new(Param::Container(Container::ArrayRef(ArrayRef {
+            element_sig: signature::Type::Base(signature::Base::String),
+            values: &elements,
+        })));
+        let param = Param::Container(Container::Struct(vec![
+            Param::Container(Container::StructRef(&fields)),
+            Param::Container(Container::DictRef(DictRef {
+                key_sig: signature::Base::String,
+                value_sig: signature::Type::Base(signature::Base::Signature),
+                map: &map,
+            })),
+            Param::Container(Container::Variant(Box::new(variant))),
+            Param::Base(Base::String(owned_string)),
+        ]));
+        let expected = param.clone();
+        let expected = format!("{:?}", expected).replace("Ref", "");
+        (keep(param), expected)
+    };
+    // the debug output of the owned value has no by-ref variants left
+    assert_eq!(format!("{:?}", owned), expected);
+
+    let fields = match &owned {
+        Param::Container(Container::Struct(fields)) => fields,
+        other => panic!("Expected a struct, got {:?}", other),
+    };
+    match &fields[0] {
+        Param::Container(Container::Struct(inner)) => {
+            assert!(matches!(inner[0], Param::Base(Base::ObjectPath(_))));
+        }
+        other => panic!("Expected a struct, got {:?}", other),
+    }
+    assert!(matches!(fields[1], Param::Container(Container::Dict(_))));
+    match &fields[2] {
+        Param::Container(Container::Variant(var)) => {
+            assert!(matches!(var.value, Param::Container(Container::Array(_))));
+        }
+        other => panic!("Expected a variant, got {:?}", other),
+    }
+    // owned values are moved, not copied
+    match &fields[3] {
+        Param::Base(Base::String(s)) => assert_eq!(s.as_ptr(), string_ptr),
+        other => panic!("Expected a string, got {:?}", other),
+    }
+
+    let base = Base::StringRef("x").into_owned();
+    assert!(matches!(base, Base::String(ref s) if s == "x"));
+    assert!(matches!(Base::Uint32(5).into_owned(), Base::Uint32(5)));
+}
+
+#[test]
+fn test_base_double() {
+    assert_eq!(Base::double(1.5), Base::Double(1.5f64.to_bits()));
+    assert!(Base::double(0.0).approx_eq(&Base::double(-0.0), 0.0));
+    assert!(!Base::double(f64::NAN).approx_eq(&Base::double(f64::NAN), 1.0));
+    assert!(Base::double(0.1 + 0.2).approx_eq(&Base::double(0.3), 1e-12));
+    assert!(!Base::double(0.1).approx_eq(&Base::double(0.3), 1e-12));
+    assert!(!Base::double(1.0).approx_eq(&Base::Uint64(1), 1.0));
+    assert!(Base::StringRef("a").approx_eq(&Base::String("a".to_owned()), 0.0));
+}
@@ -12,18 +12,21 @@ use thiserror::Error;
 pub enum Error {
     #[error("Invalid signature: {0}")]
     InvalidSignature(signature::Error),
-    #[error("Invalid object path")]
-    InvalidObjectPath,
-    #[error("Invalid bus name")]
-    InvalidBusname,
-    #[error("Invalid error name")]
-    InvalidErrorname,
-    #[error("Invalid member name")]
-    InvalidMembername,
-    #[error("Invalid Interface name")]
-    InvalidInterface,
+    #[error("Invalid object path: {0}")]
+    InvalidObjectPath(ObjectPathError),
+    #[error("Invalid bus name: {0}")]
+    InvalidBusname(NameError),
+    #[error("Invalid error name: {0}")]
+    InvalidErrorname(NameError),
+    #[error("Invalid member name: {0}")]
+    InvalidMembername(NameError),
+    #[error("Invalid Interface name: {0}")]
+    InvalidInterface(NameError),
     #[error("Invalid header fields")]
     InvalidHeaderFields,
+    /// The message type requires a header field the message does not have, named like in the spec, e.g. `PATH`
+    #[error("Missing the required header field {0}")]
+    MissingHeaderField(&'static str),
     #[error("String contained a null byte")]
     StringContainsNullByte,
     #[error("String did contain invalid utf-8")]
@@ -38,31 +41,61 @@ pub enum Error {
     DictValueTypesDiffer,
 }
 
-type Result<T> = std::result::Result<T, Error>;
+/// The rule of the object path grammar that was violated
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Error)]
+pub enum ObjectPathError {
+    #[error("the path is empty")]
+    Empty,
+    #[error("the path does not start with '/'")]
+    MissingLeadingSlash,
+    #[error("the path ends with '/'")]
+    TrailingSlash,
+    #[error("empty path element at byte offset {0}")]
+    EmptyElement(usize),
+    #[error(
+        "invalid character {character:?} at byte offset {offset}, only [A-Za-z0-9_] are allowed"
+    )]
+    InvalidChar { character: char, offset: usize },
+}
 
-pub fn validate_object_path(op: &str) -> Result<()> {
-    // should starts with '/'
-    let op = op
-        .split_once('/')
-        .and_then(|(r, op)| if r.is_empty() { Some(op) } else { None })
-        .ok_or(Error::InvalidObjectPath)?;
+type Result<T> = core::result::Result<T, Error>;
 
+pub fn validate_object_path(op: &str) -> Result<()> {
     if op.is_empty() {
-        // may be '/'
-        Ok(())
-    } else {
-        // check path components
-        op.split('/')
-            .find_map(|elem| {
-                if elem.is_empty() || !elem.chars().all(|c| c.is_alphanumeric() || c == '_') {
-                    Some(Error::InvalidObjectPath)
-                } else {
-                    None
-                }
-            })
-            .map(Err)
-            .unwrap_or(Ok(()))
+        return Err(Error::InvalidObjectPath(ObjectPathError::Empty));
     }
+    if !op.starts_with('/') {
+        return Err(Error::InvalidObjectPath(
+            ObjectPathError::MissingLeadingSlash,
+        ));
+    }
+    if op == "/" {
+        return Ok(());
+    }
+
+    // check path components
+    let mut after_slash = true;
+    for (offset, character) in op.char_indices().skip(1) {
+        if character == '/' {
+            if after_slash {
+                return Err(Error::InvalidObjectPath(ObjectPathError::EmptyElement(
+                    offset,
+                )));
+            }
+            after_slash = true;
+        } else if character.is_ascii_alphanumeric() || character == '_' {
+            after_slash = false;
+        } else {
+            return Err(Error::InvalidObjectPath(ObjectPathError::InvalidChar {
+                character,
+                offset,
+            }));
+        }
+    }
+    if after_slash {
+        return Err(Error::InvalidObjectPath(ObjectPathError::TrailingSlash));
+    }
+    Ok(())
 }
 
 #[test]
@@ -79,82 +112,112 @@ fn test_validate_object_path() {
     assert!(validate_object_path("//").is_err());
     assert!(validate_object_path("//foo_baz").is_err());
     assert!(validate_object_path("///baz_bar").is_err());
+    assert!(validate_object_path("/f\u{f6}\u{f6}").is_err());
 }
 
-pub fn validate_interface(int: &str) -> Result<()> {
-    let split = int.split('.');
-    let mut cnt = 0;
-    for (i, element) in split.enumerate() {
-        if element
-            .chars()
-            .next()
-            .ok_or(Error::InvalidInterface)?
-            .is_numeric()
+/// The rule of the grammar for bus, interface, error and member names that was violated
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Error)]
+pub enum NameError {
+    #[error("the name is empty")]
+    Empty,
+    #[error("the name exceeds 255 bytes")]
+    TooLong,
+    #[error("the name needs at least two elements separated by '.'")]
+    TooFewElements,
+    #[error("empty element at byte offset {0}")]
+    EmptyElement(usize),
+    #[error("element starts with a digit at byte offset {0}")]
+    ElementStartsWithDigit(usize),
+    #[error("invalid character {character:?} at byte offset {offset}")]
+    InvalidChar { character: char, offset: usize },
+}
+
+/// The maximum length of bus, interface, error and member names in bytes
+pub const MAX_NAME_LEN: usize = 255;
+
+/// Checks names made of at least two elements separated by '.'. `name` starts at `offset` in the whole name.
+fn validate_dotted_name(
+    name: &str,
+    offset: usize,
+    allow_dash: bool,
+    allow_leading_digit: bool,
+) -> core::result::Result<(), NameError> {
+    let mut element_start = true;
+    let mut elements = 1;
+    for (idx, character) in name.char_indices() {
+        let offset = offset + idx;
+        if character == '.' {
+            if element_start {
+                return Err(NameError::EmptyElement(offset));
+            }
+            elements += 1;
+            element_start = true;
+            continue;
+        }
+        if !(character.is_ascii_alphanumeric()
+            || character == '_'
+            || (allow_dash && character == '-'))
         {
-            return Err(Error::InvalidInterface);
+            return Err(NameError::InvalidChar { character, offset });
         }
-        let alphanum_or_underscore = element.chars().all(|c| c.is_alphanumeric() || c == '_');
-        if !alphanum_or_underscore {
-            return Err(Error::InvalidInterface);
+        if element_start && character.is_ascii_digit() && !allow_leading_digit {
+            return Err(NameError::ElementStartsWithDigit(offset));
         }
-        cnt = i + 1;
+        element_start = false;
     }
-    if cnt >= 2 {
-        Ok(())
-    } else {
-        Err(Error::InvalidInterface)
+    if element_start {
+        return Err(NameError::EmptyElement(offset + name.len()));
     }
+    if elements < 2 {
+        return Err(NameError::TooFewElements);
+    }
+    Ok(())
 }
 
-#[inline]
-pub fn validate_errorname(en: &str) -> Result<()> {
-    validate_interface(en).map_err(|_| Error::InvalidErrorname)
-}
-
-pub fn validate_busname(bn: &str) -> Result<()> {
-    let (unique, bus_name) = if let Some(unique_name) = bn.strip_prefix(':') {
-        (true, unique_name)
+fn validate_name_len(name: &str) -> core::result::Result<(), NameError> {
+    if name.is_empty() {
+        Err(NameError::Empty)
+    } else if name.len() > MAX_NAME_LEN {
+        Err(NameError::TooLong)
     } else {
-        (false, bn)
-    };
-
-    let split = bus_name.split('.');
-    let mut cnt = 0;
-    for (i, element) in split.enumerate() {
-        if element
-            .chars()
-            .next()
-            .ok_or(Error::InvalidBusname)?
-            .is_numeric()
-            && !unique
-        {
-            return Err(Error::InvalidBusname);
-        }
-        let alphanum_or_underscore_or_dash = element
-            .chars()
-            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
-        if !alphanum_or_underscore_or_dash {
-            return Err(Error::InvalidBusname);
-        }
-        cnt = i + 1;
-    }
-    if cnt >= 2 {
         Ok(())
-    } else {
-        Err(Error::InvalidBusname)
     }
 }
 
+pub fn validate_interface(int: &str) -> Result<()> {
+    validate_name_len(int)
+        .and_then(|_| validate_dotted_name(int, 0, false, false))
+        .map_err(Error::InvalidInterface)
+}
+pub fn validate_errorname(en: &str) -> Result<()> {
+    validate_name_len(en)
+        .and_then(|_| validate_dotted_name(en, 0, false, false))
+        .map_err(Error::InvalidErrorname)
+}
+/// Unique names start with ':' and their elements may start with digits, well-known names may not.
+pub fn validate_busname(bn: &str) -> Result<()> {
+    validate_name_len(bn)
+        .and_then(|_| match bn.strip_prefix(':') {
+            Some(unique_name) => validate_dotted_name(unique_name, 1, true, true),
+            None => validate_dotted_name(bn, 0, true, false),
+        })
+        .map_err(Error::InvalidBusname)
+}
 pub fn validate_membername(mem: &str) -> Result<()> {
-    if mem.is_empty() {
-        return Err(Error::InvalidMembername);
+    validate_name_len(mem).map_err(Error::InvalidMembername)?;
+    for (offset, character) in mem.char_indices() {
+        if !(character.is_ascii_alphanumeric() || character == '_') {
+            return Err(Error::InvalidMembername(NameError::InvalidChar {
+                character,
+                offset,
+            }));
+        }
     }
-
-    let alphanum_or_underscore = mem.chars().all(|c| c.is_alphanumeric() || c == '_');
-    if !alphanum_or_underscore {
-        return Err(Error::InvalidMembername);
+    if mem.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(Error::InvalidMembername(NameError::ElementStartsWithDigit(
+            0,
+        )));
     }
-
     Ok(())
 }
 
@@ -350,17 +413,23 @@ pub fn validate_header_fields(msg_type: MessageType, header_fields: &[HeaderFiel
         }
     }
 
-    let valid = match msg_type {
-        MessageType::Invalid => false,
-        MessageType::Call => have_path && have_member,
-        MessageType::Signal => have_path && have_member && have_interface,
-        MessageType::Reply => have_replyserial,
-        MessageType::Error => have_errorname && have_replyserial,
+    let required: &[(bool, &'static str)] = match msg_type {
+        MessageType::Invalid => return Err(Error::InvalidHeaderFields),
+        MessageType::Call => &[(have_path, "PATH"), (have_member, "MEMBER")],
+        MessageType::Signal => &[
+            (have_path, "PATH"),
+            (have_interface, "INTERFACE"),
+            (have_member, "MEMBER"),
+        ],
+        MessageType::Reply => &[(have_replyserial, "REPLY_SERIAL")],
+        MessageType::Error => &[
+            (have_errorname, "ERROR_NAME"),
+            (have_replyserial, "REPLY_SERIAL"),
+        ],
     };
-    if valid {
-        Ok(())
-    } else {
-        Err(Error::InvalidHeaderFields)
+    match required.iter().find(|(have, _)| !have) {
+        Some((_, field)) => Err(Error::MissingHeaderField(field)),
+        None => Ok(()),
     }
 }
 
@@ -369,24 +438,33 @@ pub fn validate_header_fields(msg_type: MessageType, header_fields: &[HeaderFiel
 fn test_objectpath_constraints() {
     let no_beginning_slash = "da/di/du";
     assert_eq!(
-        Err(Error::InvalidObjectPath),
+        Err(Error::InvalidObjectPath(
+            ObjectPathError::MissingLeadingSlash
+        )),
         crate::params::validate_object_path(no_beginning_slash)
     );
     let empty_element = "/da//du";
     assert_eq!(
-        Err(Error::InvalidObjectPath),
+        Err(Error::InvalidObjectPath(ObjectPathError::EmptyElement(4))),
         crate::params::validate_object_path(empty_element)
     );
     let trailing_slash = "/da/di/du/";
     assert_eq!(
-        Err(Error::InvalidObjectPath),
+        Err(Error::InvalidObjectPath(ObjectPathError::TrailingSlash)),
         crate::params::validate_object_path(trailing_slash)
     );
     let invalid_chars = "/da$$/di!!/du~~";
     assert_eq!(
-        Err(Error::InvalidObjectPath),
+        Err(Error::InvalidObjectPath(ObjectPathError::InvalidChar {
+            character: '$',
+            offset: 3
+        })),
         crate::params::validate_object_path(invalid_chars)
     );
+    assert_eq!(
+        Err(Error::InvalidObjectPath(ObjectPathError::Empty)),
+        crate::params::validate_object_path("")
+    );
     let trailing_slash_on_root = "/";
     assert_eq!(
         Ok(()),
@@ -397,17 +475,22 @@ fn test_objectpath_constraints() {
 fn test_interface_constraints() {
     let invalid_chars = "/da$$/di!!/du~~";
     assert_eq!(
-        Err(Error::InvalidInterface),
+        Err(Error::InvalidInterface(NameError::InvalidChar {
+            character: '/',
+            offset: 0
+        })),
         crate::params::validate_interface(invalid_chars)
     );
     let leading_digits = "1leading.digits";
     assert_eq!(
-        Err(Error::InvalidInterface),
+        Err(Error::InvalidInterface(NameError::ElementStartsWithDigit(
+            0
+        ))),
         crate::params::validate_interface(leading_digits)
     );
     let too_short = "have_more_than_one_element";
     assert_eq!(
-        Err(Error::InvalidInterface),
+        Err(Error::InvalidInterface(NameError::TooFewElements)),
         crate::params::validate_interface(too_short)
     );
     let too_long = (0..256).fold(String::new(), |mut s, _| {
@@ -416,7 +499,7 @@ fn test_interface_constraints() {
         s
     });
     assert_eq!(
-        Err(Error::InvalidInterface),
+        Err(Error::InvalidInterface(NameError::TooLong)),
         crate::params::validate_interface(&too_long)
     );
 }
@@ -424,17 +507,20 @@ fn test_interface_constraints() {
 fn test_busname_constraints() {
     let invalid_chars = "/da$$/di!!/du~~";
     assert_eq!(
-        Err(Error::InvalidBusname),
+        Err(Error::InvalidBusname(NameError::InvalidChar {
+            character: '/',
+            offset: 0
+        })),
         crate::params::validate_busname(invalid_chars)
     );
     let empty = "";
     assert_eq!(
-        Err(Error::InvalidBusname),
+        Err(Error::InvalidBusname(NameError::Empty)),
         crate::params::validate_busname(empty)
     );
     let too_short = "have_more_than_one_element";
     assert_eq!(
-        Err(Error::InvalidBusname),
+        Err(Error::InvalidBusname(NameError::TooFewElements)),
         crate::params::validate_busname(too_short)
     );
 
@@ -444,7 +530,7 @@ fn test_busname_constraints() {
         s
     });
     assert_eq!(
-        Err(Error::InvalidBusname),
+        Err(Error::InvalidBusname(NameError::TooLong)),
         crate::params::validate_busname(&too_long)
     );
 }
@@ -452,17 +538,23 @@ fn test_busname_constraints() {
 fn test_membername_constraints() {
     let invalid_chars = "/da$$/di!!/du~~";
     assert_eq!(
-        Err(Error::InvalidMembername),
+        Err(Error::InvalidMembername(NameError::InvalidChar {
+            character: '/',
+            offset: 0
+        })),
         crate::params::validate_membername(invalid_chars)
     );
     let dots = "Shouldnt.have.dots";
     assert_eq!(
-        Err(Error::InvalidMembername),
+        Err(Error::InvalidMembername(NameError::InvalidChar {
+            character: '.',
+            offset: 8
+        })),
         crate::params::validate_membername(dots)
     );
     let empty = "";
     assert_eq!(
-        Err(Error::InvalidMembername),
+        Err(Error::InvalidMembername(NameError::Empty)),
         crate::params::validate_membername(empty)
     );
 
@@ -472,7 +564,7 @@ fn test_membername_constraints() {
         s
     });
     assert_eq!(
-        Err(Error::InvalidMembername),
+        Err(Error::InvalidMembername(NameError::TooLong)),
         crate::params::validate_membername(&too_long)
     );
 }
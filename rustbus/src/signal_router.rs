@@ -0,0 +1,179 @@
+//! Route received signals to handlers by match rules
+//!
+//! The bus only delivers signals that match one of the rules added with AddMatch, but all of them arrive on the same
+//! connection. A `SignalRouter` sorts them out again: each handler is registered with a `MatchRule` and runs for every
+//! signal that matches it. Normally the same rules are also added to the bus.
+//!
+//! ```rust,no_run
+//! use rustbus::{connection::{Timeout, rpc_conn::FilterAction}, MatchRule, MessageType, RpcConn, SignalRouter};
+//!
+//! let mut rpc_con = RpcConn::session_conn(Timeout::Infinite).unwrap();
+//! let mut router = SignalRouter::new();
+//! router.add_route(
+//!     MatchRule::new()
+//!         .msg_type(MessageType::Signal)
+//!         .interface("org.freedesktop.DBus.Properties")
+//!         .path_namespace("/org/mpris"),
+//!     |msg| {
+//!         println!("Properties changed on {:?}", msg.dynheader.object);
+//!         FilterAction::Consumed
+//!     },
+//! );
+//! for rule in router.rules() {
+//!     rpc_con.add_match(rule).unwrap();
+//! }
+//! rpc_con.add_filter(move |msg| router.dispatch(msg));
+//! ```
+
+use crate::connection::rpc_conn::FilterAction;
+use crate::match_rule::MatchArgs;
+use crate::message_builder::{MarshalledMessage, MessageType};
+use crate::MatchRule;
+
+/// Handler that is run on the signals matching its rule, see `SignalRouter::add_route`
+pub type SignalHandler = Box<dyn FnMut(&MarshalledMessage) -> FilterAction + Sync + Send>;
+
+/// Identifies a route to remove it again with `SignalRouter::remove_route`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RouteId(u64);
+
+/// Dispatches signals to every handler whose `MatchRule` matches them, see `MatchRule::matches` for how the rules are
+/// evaluated.
+#[derive(Default)]
+pub struct SignalRouter {
+    routes: Vec<(RouteId, MatchRule, SignalHandler)>,
+    next_id: u64,
+}
+
+impl SignalRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `handler` on every signal that matches `rule`. Handlers run in the order they were added.
+    pub fn add_route<F>(&mut self, rule: MatchRule, handler: F) -> RouteId
+    where
+        F: FnMut(&MarshalledMessage) -> FilterAction + Sync + Send + 'static,
+    {
+        let id = RouteId(self.next_id);
+        self.next_id += 1;
+        self.routes.push((id, rule, Box::new(handler)));
+        id
+    }
+
+    /// Remove a route and return its rule, e.g. to remove it from the bus with `RpcConn::remove_match`
+    pub fn remove_route(&mut self, id: RouteId) -> Option<MatchRule> {
+        let idx = self.routes.iter().position(|(route, _, _)| *route == id)?;
+        let (_, rule, _) = self.routes.remove(idx);
+        Some(rule)
+    }
+
+    /// The rules of all routes, in the order they were added
+    pub fn rules(&self) -> impl Iterator<Item = &MatchRule> {
+        self.routes.iter().map(|(_, rule, _)| rule)
+    }
+
+    /// Run the handlers of all routes matching the signal. If a handler returns `FilterAction::Consumed` the remaining
+    /// handlers are not run and `Consumed` is returned. Messages that are not signals are passed on without running
+    /// any handler.
+    pub fn dispatch(&mut self, msg: &MarshalledMessage) -> FilterAction {
+        if msg.typ != MessageType::Signal {
+            return FilterAction::Pass;
+        }
+        // the args are only parsed once, and only if a rule needs them
+        let mut args = None;
+        for (_, rule, handler) in &mut self.routes {
+            if !rule.matches_header(msg) {
+                continue;
+            }
+            if rule.needs_args()
+                && !rule.matches_args(args.get_or_insert_with(|| MatchArgs::parse(msg)))
+            {
+                continue;
+            }
+            if handler(msg) == FilterAction::Consumed {
+                return FilterAction::Consumed;
+            }
+        }
+        FilterAction::Pass
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{call, signal_on};
+    use std::sync::{Arc, Mutex};
+
+    fn signal(path: &str, arg: &str) -> MarshalledMessage {
+        let mut msg = signal_on(path);
+        msg.body.push_param(arg).unwrap();
+        msg
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut router = SignalRouter::new();
+        let route = |router: &mut SignalRouter, name: &'static str, rule, action| {
+            let seen = seen.clone();
+            router.add_route(rule, move |_| {
+                seen.lock().unwrap().push(name);
+                action
+            })
+        };
+        route(
+            &mut router,
+            "killing",
+            MatchRule::new().path_namespace("/io/killing"),
+            FilterAction::Pass,
+        );
+        let spark = route(
+            &mut router,
+            "spark",
+            MatchRule::new().path_namespace("/io/killing/spark"),
+            FilterAction::Pass,
+        );
+        route(
+            &mut router,
+            "arg",
+            MatchRule::new()
+                .path_namespace("/")
+                .arg0namespace("org.mpris"),
+            FilterAction::Consumed,
+        );
+        route(&mut router, "all", MatchRule::new(), FilterAction::Pass);
+
+        let dispatch = |router: &mut SignalRouter, msg: &MarshalledMessage| {
+            let action = router.dispatch(msg);
+            (action, std::mem::take(&mut *seen.lock().unwrap()))
+        };
+        assert_eq!(
+            dispatch(&mut router, &signal("/io/killing/spark/deep", "x")),
+            (FilterAction::Pass, vec!["killing", "spark", "all"])
+        );
+        assert_eq!(
+            dispatch(&mut router, &signal("/io/killingspark", "x")),
+            (FilterAction::Pass, vec!["all"])
+        );
+        // consumed by the third route, the last one does not run anymore
+        assert_eq!(
+            dispatch(&mut router, &signal("/io/killing", "org.mpris.vlc")),
+            (FilterAction::Consumed, vec!["killing", "arg"])
+        );
+
+        assert_eq!(
+            dispatch(&mut router, &call("Test")),
+            (FilterAction::Pass, vec![])
+        );
+
+        let rule = router.remove_route(spark).unwrap();
+        assert_eq!(rule, MatchRule::new().path_namespace("/io/killing/spark"));
+        assert_eq!(router.remove_route(spark), None);
+        assert_eq!(router.rules().count(), 3);
+        assert_eq!(
+            dispatch(&mut router, &signal("/io/killing/spark", "x")),
+            (FilterAction::Pass, vec!["killing", "all"])
+        );
+    }
+}
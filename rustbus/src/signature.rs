@@ -1,13 +1,17 @@
 //! Everything needed to deal with dbus signatures
 
 mod signature_iter;
-use std::iter::Peekable;
+use crate::alloc_prelude::*;
+use core::cell::Cell;
+use core::iter::Peekable;
 
 pub use signature_iter::*;
 
 use thiserror::Error;
 
 /// Base types that might occur in a signature
+///
+/// Base types are ordered by their signature character, so `Boolean` ('b') comes first and `Byte` ('y') last.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Base {
     Byte,
@@ -26,7 +30,9 @@ pub enum Base {
 }
 
 /// Wraps the types a struct contains. Must contain at least one type, empty structs are not allowed in the spec
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// Ordered lexicographically by the field types.
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 pub struct StructTypes(Vec<Type>);
 
 impl StructTypes {
@@ -47,7 +53,11 @@ impl AsRef<[Type]> for StructTypes {
 }
 
 /// Containers for other types
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// Containers are ordered by their kind first: arrays, structs, dicts and then variants. Containers of the same kind
+/// are ordered by their content: arrays by their element type, structs by their field types and dicts by their key
+/// type and then their value type.
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 pub enum Container {
     Array(Box<Type>),
     Struct(StructTypes),
@@ -56,7 +66,10 @@ pub enum Container {
 }
 
 /// Either a Base or a Container
-#[derive(Clone, Debug, Eq, PartialEq)]
+///
+/// All base types come before all containers, see `Base` and `Container` for the order among them.
+/// This order is part of the API and will not change, so it can be relied on for sorting and as keys in a `BTreeMap`.
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
 pub enum Type {
     Base(Base),
     Container(Container),
@@ -70,6 +83,8 @@ pub enum Error {
     ShouldBeBaseType,
     #[error("Signature was invalid")]
     InvalidSignature,
+    #[error("Signature was invalid at byte offset {offset}")]
+    InvalidSignatureAt { offset: usize },
     #[error("signature was too long")]
     SignatureTooLong,
     #[error("Nesting of structs/arrays/variants was too deep")]
@@ -80,7 +95,7 @@ pub enum Error {
     EmptyStruct,
 }
 
-type Result<T> = std::result::Result<T, Error>;
+type Result<T> = core::result::Result<T, Error>;
 
 #[derive(PartialEq, Eq, Debug)]
 enum Token {
@@ -131,20 +146,125 @@ fn char_to_token(c: char) -> Result<Token> {
     Ok(t)
 }
 
-struct TokenIter<I: Iterator<Item = char>> {
+struct TokenIter<'a, I: Iterator<Item = (usize, char)>> {
     chars: I,
+    len: usize,
+    /// Byte offset of the last char that was read, or the length of the signature once all chars were read
+    offset: &'a Cell<usize>,
 }
 
-impl<I: Iterator<Item = char>> Iterator for TokenIter<I> {
+impl<I: Iterator<Item = (usize, char)>> Iterator for TokenIter<'_, I> {
     type Item = Result<Token>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.chars.next().map(char_to_token)
+        match self.chars.next() {
+            Some((offset, c)) => {
+                self.offset.set(offset);
+                Some(char_to_token(c))
+            }
+            None => {
+                self.offset.set(self.len);
+                None
+            }
+        }
+    }
+}
+
+fn make_tokens<'a>(
+    sig: &'a str,
+    offset: &'a Cell<usize>,
+) -> TokenIter<'a, core::str::CharIndices<'a>> {
+    TokenIter {
+        chars: sig.char_indices(),
+        len: sig.len(),
+        offset,
+    }
+}
+
+/// Parse a signature string into the types it contains. This is the inverse of `Type::to_str`.
+///
+/// This works like `Type::parse_description` but if the signature is malformed it reports the byte offset of the
+/// first character at which the signature stopped making sense as `Error::InvalidSignatureAt`.
+/// ```rust
+/// use rustbus::signature::{self, Base, Container, Error, Type};
+/// let types = signature::parse("a{sv}i").unwrap();
+/// assert_eq!(types, vec![
+///     Type::Container(Container::Dict(Base::String, Box::new(Type::Container(Container::Variant)))),
+///     Type::Base(Base::Int32),
+/// ]);
+/// assert_eq!(signature::parse("a{vs}"), Err(Error::InvalidSignatureAt { offset: 2 }));
+/// ```
+pub fn parse(sig: &str) -> Result<Vec<Type>> {
+    let offset = Cell::new(0);
+    Type::parse_with_offset(sig, &offset).map_err(|e| match e {
+        Error::InvalidSignature => Error::InvalidSignatureAt {
+            offset: offset.get(),
+        },
+        e => e,
+    })
+}
+
+impl core::fmt::Display for Base {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut buf = String::with_capacity(1);
+        self.to_str(&mut buf);
+        f.write_str(&buf)
     }
 }
 
-fn make_tokens<I: Iterator<Item = char>>(sig: I) -> TokenIter<I> {
-    TokenIter { chars: sig }
+impl core::fmt::Display for Container {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut buf = String::new();
+        self.to_str(&mut buf);
+        f.write_str(&buf)
+    }
+}
+
+impl core::fmt::Display for Type {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut buf = String::new();
+        self.to_str(&mut buf);
+        f.write_str(&buf)
+    }
+}
+
+/// Parses exactly one single complete type. Signatures with multiple types
+/// return `Error::TooManyTypes`, use `signature::parse` for those.
+impl core::str::FromStr for Type {
+    type Err = Error;
+
+    fn from_str(sig: &str) -> Result<Self> {
+        // parse rejects empty signatures, so there is at least one type
+        let mut types = parse(sig)?;
+        if types.len() > 1 {
+            return Err(Error::TooManyTypes);
+        }
+        Ok(types.remove(0))
+    }
+}
+
+/// Parses exactly one base type. Returns `Error::ShouldBeBaseType` if the signature describes a container.
+impl core::str::FromStr for Base {
+    type Err = Error;
+
+    fn from_str(sig: &str) -> Result<Self> {
+        match sig.parse()? {
+            Type::Base(b) => Ok(b),
+            Type::Container(_) => Err(Error::ShouldBeBaseType),
+        }
+    }
+}
+
+/// Parses exactly one container type. Returns `Error::InvalidSignature` if the signature describes a base type.
+impl core::str::FromStr for Container {
+    type Err = Error;
+
+    fn from_str(sig: &str) -> Result<Self> {
+        match sig.parse()? {
+            Type::Container(c) => Ok(c),
+            Type::Base(_) => Err(Error::InvalidSignature),
+        }
+    }
 }
 
 impl Container {
@@ -174,6 +294,16 @@ impl Container {
         }
     }
 
+    /// The length of the string `to_str` writes
+    pub fn str_len(&self) -> usize {
+        match self {
+            Container::Array(el) => 1 + el.str_len(),
+            Container::Dict(_, val) => 4 + val.str_len(),
+            Container::Struct(types) => 2 + types.as_ref().iter().map(Type::str_len).sum::<usize>(),
+            Container::Variant => 1,
+        }
+    }
+
     pub fn get_alignment(&self) -> usize {
         match self {
             Container::Variant => 1,
@@ -182,26 +312,52 @@ impl Container {
             Container::Struct(_) => 8,
         }
     }
+
+    /// The alignment of the elements of arrays and dicts. The elements start at this alignment after the length,
+    /// even if there are none. Dict entries are aligned like structs. Returns `None` for structs and variants.
+    pub fn element_alignment(&self) -> Option<usize> {
+        match self {
+            Container::Array(el) => Some(el.get_alignment()),
+            Container::Dict(_, _) => Some(8),
+            Container::Struct(_) | Container::Variant => None,
+        }
+    }
+}
+
+impl Ord for Base {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.as_char().cmp(&other.as_char())
+    }
+}
+
+impl PartialOrd for Base {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl Base {
-    pub fn to_str(self, buf: &mut String) {
+    /// The character that represents this type in a signature
+    pub fn as_char(self) -> char {
         match self {
-            Base::Boolean => buf.push('b'),
-            Base::Byte => buf.push('y'),
-            Base::Int16 => buf.push('n'),
-            Base::Uint16 => buf.push('q'),
-            Base::Int32 => buf.push('i'),
-            Base::Uint32 => buf.push('u'),
-            Base::UnixFd => buf.push('h'),
-            Base::Int64 => buf.push('x'),
-            Base::Uint64 => buf.push('t'),
-            Base::Double => buf.push('d'),
-            Base::String => buf.push('s'),
-            Base::ObjectPath => buf.push('o'),
-            Base::Signature => buf.push('g'),
+            Base::Boolean => 'b',
+            Base::Byte => 'y',
+            Base::Int16 => 'n',
+            Base::Uint16 => 'q',
+            Base::Int32 => 'i',
+            Base::Uint32 => 'u',
+            Base::UnixFd => 'h',
+            Base::Int64 => 'x',
+            Base::Uint64 => 't',
+            Base::Double => 'd',
+            Base::String => 's',
+            Base::ObjectPath => 'o',
+            Base::Signature => 'g',
         }
     }
+    pub fn to_str(self, buf: &mut String) {
+        buf.push(self.as_char());
+    }
     pub fn get_alignment(self) -> usize {
         match self {
             Base::Boolean => 4,
@@ -239,6 +395,10 @@ impl Base {
 
 impl Type {
     pub fn parse_description(sig: &str) -> Result<Vec<Type>> {
+        Self::parse_with_offset(sig, &Cell::new(0))
+    }
+
+    fn parse_with_offset(sig: &str, offset: &Cell<usize>) -> Result<Vec<Type>> {
         if sig.len() > 255 {
             return Err(Error::SignatureTooLong);
         }
@@ -246,7 +406,7 @@ impl Type {
             return Err(Error::EmptySignature);
         }
 
-        let mut tokens = make_tokens(sig.chars()).peekable();
+        let mut tokens = make_tokens(sig, offset).peekable();
         let mut types = Vec::new();
         while let Some(t) = Self::parse_next_type(&mut tokens, None)? {
             types.push(t);
@@ -287,6 +447,16 @@ impl Type {
         }
     }
 
+    /// The length of the string `to_str` writes
+    pub fn str_len(&self) -> usize {
+        match self {
+            Type::Container(c) => c.str_len(),
+            Type::Base(_) => 1,
+        }
+    }
+
+    /// The alignment a value of this type starts at in the marshalled message, which is 1, 2, 4 or 8 as defined in
+    /// the spec. Arrays and dicts are aligned for their `u32` length, structs to 8 and variants to 1 for their signature.
     pub fn get_alignment(&self) -> usize {
         match self {
             Type::Base(b) => b.get_alignment(),
@@ -384,6 +554,7 @@ impl Type {
             let token = token?;
             match token {
                 Token::Byte => Ok(Base::Byte),
+                Token::Boolean => Ok(Base::Boolean),
                 Token::Int16 => Ok(Base::Int16),
                 Token::Uint16 => Ok(Base::Uint16),
                 Token::Int32 => Ok(Base::Int32),
@@ -411,12 +582,101 @@ impl Type {
         }
         Ok(types)
     }
+
+    /// The zero or empty value of this type, useful to build valid messages for arbitrary signatures. Numbers are 0,
+    /// booleans false, strings and signatures empty and object paths `/`. Arrays and dicts are empty, structs hold the
+    /// defaults of their fields and variants hold the byte 0. The `sig()` of the returned param is this type.
+    ///
+    /// A unix fd can not be made up, so `h` becomes an empty `UnixFd` that fails to marshal with
+    /// `MarshalError::EmptyUnixFd`. This needs the `std` feature because there are no unix fds without it.
+    #[cfg(feature = "std")]
+    pub fn default_param(&self) -> crate::params::Param<'static, 'static> {
+        use crate::params;
+        match self {
+            Type::Base(base) => params::Param::Base(match base {
+                Base::Byte => params::Base::Byte(0),
+                Base::Int16 => params::Base::Int16(0),
+                Base::Uint16 => params::Base::Uint16(0),
+                Base::Int32 => params::Base::Int32(0),
+                Base::Uint32 => params::Base::Uint32(0),
+                Base::UnixFd => params::Base::UnixFd(crate::wire::UnixFd::new(-1)),
+                Base::Int64 => params::Base::Int64(0),
+                Base::Uint64 => params::Base::Uint64(0),
+                Base::Double => params::Base::double(0.0),
+                Base::String => params::Base::String(String::new()),
+                Base::Signature => params::Base::Signature(String::new()),
+                Base::ObjectPath => params::Base::ObjectPath("/".to_owned()),
+                Base::Boolean => params::Base::Boolean(false),
+            }),
+            Type::Container(container) => params::Param::Container(match container {
+                Container::Array(elem) => params::Container::Array(params::Array {
+                    element_sig: elem.as_ref().clone(),
+                    values: Vec::new(),
+                }),
+                Container::Dict(key, value) => params::Container::Dict(params::Dict {
+                    key_sig: *key,
+                    value_sig: value.as_ref().clone(),
+                    map: Default::default(),
+                }),
+                Container::Struct(fields) => params::Container::Struct(
+                    fields.as_ref().iter().map(Type::default_param).collect(),
+                ),
+                Container::Variant => params::Container::Variant(Box::new(params::Variant::new(
+                    params::Param::Base(params::Base::Byte(0)),
+                ))),
+            }),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_dict_keys() {
+        for key in "ybnqiuxtdsogh".chars() {
+            let sig = format!("a{{{}s}}", key);
+            let types = Type::parse_description(&sig).unwrap();
+            assert_eq!(types[0].to_string(), sig);
+        }
+        assert_eq!(
+            Type::parse_description("a{vs}"),
+            Err(Error::InvalidSignature)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_default_param() {
+        let sigs = "ybnqiuxtdsogva{sv}a(oa{ya(gd)})(i(s(av)))aah";
+        for typ in Type::parse_description(sigs).unwrap() {
+            assert_eq!(typ.default_param().sig(), typ, "{}", typ);
+        }
+
+        let mut body = crate::message_builder::MarshalledMessageBody::new();
+        let types = Type::parse_description("sogva{sv}a(oa{ya(gd)})(i(s(av)))").unwrap();
+        for typ in &types {
+            body.push_old_param(&typ.default_param()).unwrap();
+        }
+        body.validate().unwrap();
+        let mut parser = body.parser();
+        assert_eq!(parser.get::<&str>().unwrap(), "");
+        assert_eq!(
+            parser.get::<crate::wire::ObjectPath<&str>>().unwrap(),
+            crate::wire::ObjectPath::new("/").unwrap()
+        );
+
+        assert_eq!(
+            Type::Base(Base::UnixFd)
+                .default_param()
+                .as_unix_fd()
+                .unwrap()
+                .get_raw_fd(),
+            None
+        );
+    }
+
     #[test]
     fn test_base_to_str() {
         {
@@ -528,4 +788,142 @@ mod tests {
         assert_parse_and_back!("aa{si}");
         assert_parse_and_back!("aaaa{si}");
     }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("(ii)as").unwrap(),
+            vec![
+                Type::Container(Container::Struct(
+                    StructTypes::new(vec![Type::Base(Base::Int32), Type::Base(Base::Int32)])
+                        .unwrap()
+                )),
+                Type::Container(Container::Array(Box::new(Type::Base(Base::String)))),
+            ]
+        );
+
+        let nested = "a(ia{s(a{sv}aay)}v)";
+        let mut sig = String::new();
+        for t in parse(nested).unwrap() {
+            t.to_str(&mut sig);
+        }
+        assert_eq!(sig, nested);
+
+        let deep = format!("{}i{}", "(".repeat(31), ")".repeat(31));
+        assert_eq!(parse(&deep).unwrap().len(), 1);
+        let too_deep = format!("{}i{}", "(".repeat(33), ")".repeat(33));
+        assert_eq!(parse(&too_deep), Err(Error::NestingTooDeep));
+
+        assert_eq!(parse(""), Err(Error::EmptySignature));
+        assert_eq!(parse("()"), Err(Error::EmptyStruct));
+        assert_eq!(parse("iiz"), Err(Error::InvalidSignatureAt { offset: 2 }));
+        assert_eq!(parse("a{vs}"), Err(Error::InvalidSignatureAt { offset: 2 }));
+        assert_eq!(parse("a{ss"), Err(Error::InvalidSignatureAt { offset: 4 }));
+        assert_eq!(
+            parse("a{sss}"),
+            Err(Error::InvalidSignatureAt { offset: 4 })
+        );
+        assert_eq!(parse("(ii"), Err(Error::InvalidSignatureAt { offset: 3 }));
+        assert_eq!(parse("(ii))"), Err(Error::InvalidSignatureAt { offset: 4 }));
+        assert_eq!(parse("sa"), Err(Error::InvalidSignatureAt { offset: 2 }));
+        assert_eq!(parse("a)"), Err(Error::InvalidSignatureAt { offset: 1 }));
+    }
+
+    #[test]
+    fn test_display_from_str() {
+        for sig in &["i", "v", "as", "a{sv}", "(ia{s(yv)}ah)", "aa{oa{sa{sv}}}"] {
+            let t: Type = sig.parse().unwrap();
+            assert_eq!(t.to_string(), *sig);
+            if let Type::Container(c) = &t {
+                assert_eq!(c.to_string(), *sig);
+                assert_eq!(sig.parse::<Container>().as_ref(), Ok(c));
+            }
+        }
+        assert_eq!(Base::ObjectPath.to_string(), "o");
+        assert_eq!("g".parse::<Base>(), Ok(Base::Signature));
+        assert_eq!(
+            format!(
+                "{}",
+                Type::Container(Container::Dict(
+                    Base::String,
+                    Box::new(Type::Base(Base::Byte))
+                ))
+            ),
+            "a{sy}"
+        );
+
+        assert_eq!("ii".parse::<Type>(), Err(Error::TooManyTypes));
+        assert_eq!("".parse::<Type>(), Err(Error::EmptySignature));
+        assert_eq!("as".parse::<Base>(), Err(Error::ShouldBeBaseType));
+        assert_eq!("s".parse::<Container>(), Err(Error::InvalidSignature));
+        assert_eq!(
+            "a{s".parse::<Type>(),
+            Err(Error::InvalidSignatureAt { offset: 3 })
+        );
+    }
+
+    #[test]
+    fn test_ord() {
+        let mut types = [
+            "v", "(ay)", "a{sv}", "as", "a{ss}", "ay", "(i)", "s", "y", "b", "(is)",
+        ]
+        .iter()
+        .map(|sig| sig.parse::<Type>().unwrap())
+        .collect::<Vec<_>>();
+        types.sort();
+        let sorted = types.iter().map(Type::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            sorted,
+            ["b", "s", "y", "as", "ay", "(i)", "(is)", "(ay)", "a{ss}", "a{sv}", "v"]
+        );
+
+        assert!(Base::Boolean < Base::Byte);
+        assert!(Type::Base(Base::Byte) < Type::Container(Container::Variant));
+
+        let mut registry = alloc::collections::BTreeMap::new();
+        registry.insert("a{sv}".parse::<Type>().unwrap(), "properties");
+        registry.insert("s".parse::<Type>().unwrap(), "name");
+        assert_eq!(
+            registry.values().copied().collect::<Vec<_>>(),
+            ["name", "properties"]
+        );
+    }
+
+    #[test]
+    fn test_alignment() {
+        // the alignment table from the spec
+        let table = [
+            ("y", 1),
+            ("b", 4),
+            ("n", 2),
+            ("q", 2),
+            ("i", 4),
+            ("u", 4),
+            ("x", 8),
+            ("t", 8),
+            ("d", 8),
+            ("s", 4),
+            ("o", 4),
+            ("g", 1),
+            ("h", 4),
+            ("ay", 4),
+            ("at", 4),
+            ("a{yy}", 4),
+            ("(y)", 8),
+            ("(yy)", 8),
+            ("v", 1),
+        ];
+        for (sig, alignment) in table {
+            let typ = sig.parse::<Type>().unwrap();
+            assert_eq!(typ.get_alignment(), alignment, "{}", sig);
+        }
+
+        let element_alignment = |sig: &str| sig.parse::<Container>().unwrap().element_alignment();
+        assert_eq!(element_alignment("ay"), Some(1));
+        assert_eq!(element_alignment("at"), Some(8));
+        assert_eq!(element_alignment("a(y)"), Some(8));
+        assert_eq!(element_alignment("a{yy}"), Some(8));
+        assert_eq!(element_alignment("(t)"), None);
+        assert_eq!(element_alignment("v"), None);
+    }
 }
@@ -3,6 +3,15 @@
 use crate::message_builder::DynamicHeader;
 use crate::message_builder::MarshalledMessage;
 use crate::message_builder::MessageBuilder;
+use crate::wire::errors::UnmarshalError;
+use crate::wire::marshal::traits::SignatureBuffer;
+use crate::wire::unmarshal::UnmarshalContext;
+use crate::{MatchRule, MessageType, Signature, Unmarshal};
+use std::convert::TryFrom;
+use std::ops::{BitOr, BitOrAssign};
+
+pub mod object_manager;
+pub mod properties;
 
 pub fn hello() -> MarshalledMessage {
     make_standard_msg("Hello")
@@ -38,6 +47,139 @@ pub const DBUS_REQUEST_NAME_REPLY_IN_QUEUE: u32 = 2;
 pub const DBUS_REQUEST_NAME_REPLY_EXISTS: u32 = 3;
 pub const DBUS_REQUEST_NAME_REPLY_ALREADY_OWNER: u32 = 4;
 
+pub const DBUS_RELEASE_NAME_REPLY_RELEASED: u32 = 1;
+pub const DBUS_RELEASE_NAME_REPLY_NON_EXISTENT: u32 = 2;
+pub const DBUS_RELEASE_NAME_REPLY_NOT_OWNER: u32 = 3;
+
+/// Flags for `request_name`. Combine them with `|`.
+///
+/// ```rust
+/// use rustbus::standard_messages::{request_name, RequestNameFlags};
+/// let msg = request_name(
+///     "io.killing.spark",
+///     RequestNameFlags::ALLOW_REPLACEMENT | RequestNameFlags::DO_NOT_QUEUE,
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RequestNameFlags(u32);
+
+impl RequestNameFlags {
+    /// Allow other connections to take the name with `REPLACE_EXISTING`
+    pub const ALLOW_REPLACEMENT: Self = Self(DBUS_NAME_FLAG_ALLOW_REPLACEMENT);
+    /// Take the name from the current owner if it allowed replacement
+    pub const REPLACE_EXISTING: Self = Self(DBUS_NAME_FLAG_REPLACE_EXISTING);
+    /// Fail instead of waiting in the queue if the name is already owned
+    pub const DO_NOT_QUEUE: Self = Self(DBUS_NAME_FLAG_DO_NOT_QUEUE);
+
+    pub fn empty() -> Self {
+        Self(0)
+    }
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl From<u32> for RequestNameFlags {
+    fn from(bits: u32) -> Self {
+        Self(bits)
+    }
+}
+
+impl BitOr for RequestNameFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for RequestNameFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Implements `TryFrom<u32>`, `Signature` and `Unmarshal` for the reply codes, so they can be read directly
+/// from the reply with `msg.body.parser().get::<...>()`
+macro_rules! reply_code {
+    ($t:ident { $($variant:ident = $code:path,)* }) => {
+        impl TryFrom<u32> for $t {
+            type Error = UnmarshalError;
+            fn try_from(code: u32) -> Result<Self, UnmarshalError> {
+                match code {
+                    $($code => Ok(Self::$variant),)*
+                    other => Err(UnmarshalError::UnknownDiscriminant(other)),
+                }
+            }
+        }
+        impl Signature for $t {
+            #[inline]
+            fn signature() -> crate::signature::Type {
+                u32::signature()
+            }
+            #[inline]
+            fn alignment() -> usize {
+                u32::alignment()
+            }
+            #[inline]
+            fn sig_str(s_buf: &mut SignatureBuffer) {
+                u32::sig_str(s_buf)
+            }
+            #[inline]
+            fn has_sig(sig: &str) -> bool {
+                u32::has_sig(sig)
+            }
+        }
+        impl<'buf, 'fds> Unmarshal<'buf, 'fds> for $t {
+            fn unmarshal(
+                ctx: &mut UnmarshalContext<'fds, 'buf>,
+            ) -> crate::wire::unmarshal::UnmarshalResult<Self> {
+                let (bytes, code) = u32::unmarshal(ctx)?;
+                Ok((bytes, Self::try_from(code)?))
+            }
+        }
+    };
+}
+
+/// The reply to `request_name`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestNameReply {
+    /// The name was not owned before or it was taken from the previous owner
+    PrimaryOwner,
+    /// The name is owned by someone else, this connection was put in the queue
+    InQueue,
+    /// The name is owned by someone else and `DO_NOT_QUEUE` was set or replacing was not allowed
+    Exists,
+    /// This connection already owns the name
+    AlreadyOwner,
+}
+
+reply_code!(RequestNameReply {
+    PrimaryOwner = DBUS_REQUEST_NAME_REPLY_PRIMARY_OWNER,
+    InQueue = DBUS_REQUEST_NAME_REPLY_IN_QUEUE,
+    Exists = DBUS_REQUEST_NAME_REPLY_EXISTS,
+    AlreadyOwner = DBUS_REQUEST_NAME_REPLY_ALREADY_OWNER,
+});
+
+/// The reply to `release_name`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseNameReply {
+    /// The name was owned by this connection and has been released
+    Released,
+    /// Nobody owned the name
+    NonExistent,
+    /// The name is owned by someone else and this connection was not in its queue
+    NotOwner,
+}
+
+reply_code!(ReleaseNameReply {
+    Released = DBUS_RELEASE_NAME_REPLY_RELEASED,
+    NonExistent = DBUS_RELEASE_NAME_REPLY_NON_EXISTENT,
+    NotOwner = DBUS_RELEASE_NAME_REPLY_NOT_OWNER,
+});
+
 fn make_standard_msg(name: &str) -> MarshalledMessage {
     MessageBuilder::new()
         .call(name)
@@ -46,21 +188,73 @@ fn make_standard_msg(name: &str) -> MarshalledMessage {
         .at("org.freedesktop.DBus")
         .build()
 }
-/// Request a name on the bus
-pub fn request_name(name: &str, flags: u32) -> MarshalledMessage {
+/// Request a name on the bus. The reply can be read as a `RequestNameReply`.
+pub fn request_name<F: Into<RequestNameFlags>>(name: &str, flags: F) -> MarshalledMessage {
     let mut msg = make_standard_msg("RequestName");
     msg.body.push_param(name).unwrap();
-    msg.body.push_param(flags).unwrap();
+    msg.body.push_param(flags.into().bits()).unwrap();
     msg
 }
 
-/// Release a name on the bus
+/// Release a name on the bus. The reply can be read as a `ReleaseNameReply`.
 pub fn release_name(name: &str) -> MarshalledMessage {
     let mut msg = make_standard_msg("ReleaseName");
     msg.body.push_param(name).unwrap();
     msg
 }
 
+/// The error the bus answers `get_name_owner` with if nobody owns the name
+pub const NAME_HAS_NO_OWNER: &str = "org.freedesktop.DBus.Error.NameHasNoOwner";
+
+/// Ask the bus for the unique name of the owner of `name`. The reply is a string, or a `NAME_HAS_NO_OWNER` error.
+/// `RpcConn::get_name_owner` makes the call and handles both.
+pub fn get_name_owner(name: &str) -> MarshalledMessage {
+    let mut msg = make_standard_msg("GetNameOwner");
+    msg.body.push_param(name).unwrap();
+    msg
+}
+
+/// The rule for the `NameOwnerChanged` signals of the bus, either for all names or only for `name`. Add it with
+/// `RpcConn::add_match` and read the signals with `NameOwnerChanged::from_signal`, or use
+/// `RpcConn::watch_name_owner` which does both.
+pub fn name_owner_changed_rule(name: Option<&str>) -> MatchRule {
+    let rule = MatchRule::new()
+        .msg_type(MessageType::Signal)
+        .sender("org.freedesktop.DBus")
+        .interface("org.freedesktop.DBus")
+        .member("NameOwnerChanged")
+        .path("/org/freedesktop/DBus");
+    match name {
+        Some(name) => rule.arg(0, name),
+        None => rule,
+    }
+}
+
+/// A `NameOwnerChanged` signal, sent by the bus whenever a name gets a new owner, loses its owner or both. The bus
+/// sends empty strings for a missing owner, these are `None` here.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameOwnerChanged {
+    pub name: String,
+    /// The unique name of the previous owner, `None` if the name was not owned before
+    pub old_owner: Option<String>,
+    /// The unique name of the new owner, `None` if the name is not owned anymore
+    pub new_owner: Option<String>,
+}
+
+impl NameOwnerChanged {
+    /// Read the arguments of a `NameOwnerChanged` signal. Only the body is looked at, use
+    /// `name_owner_changed_rule` to check the header.
+    pub fn from_signal(msg: &MarshalledMessage) -> Result<Self, UnmarshalError> {
+        let (name, old_owner, new_owner) = msg.body.parser().get3::<String, String, String>()?;
+        let non_empty = |owner: String| Some(owner).filter(|owner| !owner.is_empty());
+        Ok(NameOwnerChanged {
+            name,
+            old_owner: non_empty(old_owner),
+            new_owner: non_empty(new_owner),
+        })
+    }
+}
+
 /// Add a match rule to receive signals. e.g. match_rule = "type='signal'" to get all signals
 pub fn add_match(match_rule: &str) -> MarshalledMessage {
     let mut msg = make_standard_msg("AddMatch");
@@ -73,6 +267,45 @@ pub fn remove_match(match_rule: &str) -> MarshalledMessage {
     msg.body.push_param(match_rule).unwrap();
     msg
 }
+/// Turn the connection into a monitor that receives all messages matching one of the `match_rules`, or all messages
+/// if there are none. See `DuplexConn::become_monitor`.
+pub fn become_monitor(match_rules: &[String]) -> MarshalledMessage {
+    let mut msg = MessageBuilder::new()
+        .call("BecomeMonitor")
+        .on("/org/freedesktop/DBus")
+        .with_interface("org.freedesktop.DBus.Monitoring")
+        .at("org.freedesktop.DBus")
+        .build();
+    // the flags are unused and must be 0
+    msg.body.push_param2(match_rules, 0u32).unwrap();
+    msg
+}
+
+/// An error reply to the call with the serial `reply_serial`. The description is optional but recommended, it is sent
+/// as the only argument. Set `dynheader.destination` to the sender of the call, or use
+/// `DynamicHeader::make_error_response` which does that.
+///
+/// The receiver can get the name and description back with `MarshalledMessage::as_error`.
+///
+/// ```rust
+/// use rustbus::standard_messages::error;
+/// let err = error(7, "io.killing.spark.Error.Busy", Some("try again later".to_owned()));
+/// let parsed = err.as_error().unwrap();
+/// assert_eq!(parsed.name, "io.killing.spark.Error.Busy");
+/// assert_eq!(parsed.message.as_deref(), Some("try again later"));
+/// ```
+pub fn error<S: Into<String>>(
+    reply_serial: u32,
+    name: S,
+    text: Option<String>,
+) -> MarshalledMessage {
+    let call = DynamicHeader {
+        serial: Some(reply_serial),
+        ..Default::default()
+    };
+    call.make_error_response(name, text)
+}
+
 /// Error message to tell the caller that this method is not known by your server
 pub fn unknown_method(call: &DynamicHeader) -> MarshalledMessage {
     let text = format!(
@@ -0,0 +1,150 @@
+//! Calls and signals of the `org.freedesktop.DBus.ObjectManager` interface
+//!
+//! ```rust,no_run
+//! use rustbus::{connection::Timeout, standard_messages::object_manager, RpcConn};
+//!
+//! let mut rpc_con = RpcConn::system_conn(Timeout::Infinite).unwrap();
+//! let mut call = object_manager::get_managed_objects("org.bluez", "/");
+//! let reply = rpc_con.send_and_wait_response(&mut call, None).unwrap();
+//! for (path, interfaces) in object_manager::parse_get_managed_objects_reply(&reply).unwrap() {
+//!     println!("{}: {:?}", path.as_ref(), interfaces.keys().collect::<Vec<_>>());
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use crate::message_builder::{MarshalledMessage, MessageBuilder};
+use crate::wire::errors::UnmarshalError;
+use crate::wire::unmarshal::traits::Variant;
+use crate::wire::{InterfaceName, ObjectPath};
+
+pub const OBJECT_MANAGER_INTERFACE: &str = "org.freedesktop.DBus.ObjectManager";
+
+/// The properties of one interface, keyed by the property names
+pub type Properties<'a> = HashMap<String, Variant<'a, 'a>>;
+/// The interfaces of one object with their properties, keyed by the interface names
+pub type Interfaces<'a> = HashMap<InterfaceName<String>, Properties<'a>>;
+/// All objects managed by an object manager, keyed by their object paths
+pub type ManagedObjects<'a> = HashMap<ObjectPath<String>, Interfaces<'a>>;
+
+/// Get all objects below the object manager at `path` of `dest`
+pub fn get_managed_objects(dest: &str, path: &str) -> MarshalledMessage {
+    MessageBuilder::new()
+        .call("GetManagedObjects")
+        .with_interface(OBJECT_MANAGER_INTERFACE)
+        .on(path)
+        .at(dest)
+        .build()
+}
+
+/// Get the objects out of the reply to a `get_managed_objects` call
+pub fn parse_get_managed_objects_reply(
+    reply: &MarshalledMessage,
+) -> Result<ManagedObjects<'_>, UnmarshalError> {
+    reply.body.parser().get()
+}
+
+/// The content of an `InterfacesAdded` signal
+#[derive(Debug)]
+pub struct InterfacesAdded<'a> {
+    pub path: ObjectPath<String>,
+    pub interfaces: Interfaces<'a>,
+}
+
+/// Get the content out of an `InterfacesAdded` signal
+pub fn parse_interfaces_added(
+    signal: &MarshalledMessage,
+) -> Result<InterfacesAdded<'_>, UnmarshalError> {
+    let (path, interfaces) = signal.body.parser().get2()?;
+    Ok(InterfacesAdded { path, interfaces })
+}
+
+/// The content of an `InterfacesRemoved` signal
+#[derive(Debug)]
+pub struct InterfacesRemoved {
+    pub path: ObjectPath<String>,
+    pub interfaces: Vec<InterfaceName<String>>,
+}
+
+/// Get the content out of an `InterfacesRemoved` signal
+pub fn parse_interfaces_removed(
+    signal: &MarshalledMessage,
+) -> Result<InterfacesRemoved, UnmarshalError> {
+    let (path, interfaces) = signal.body.parser().get2()?;
+    Ok(InterfacesRemoved { path, interfaces })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::marshal::traits::Variant as MarshalVariant;
+
+    fn device_interfaces() -> HashMap<&'static str, HashMap<&'static str, MarshalVariant<u32>>> {
+        let mut props = HashMap::new();
+        props.insert("Class", MarshalVariant(7u32));
+        let mut interfaces = HashMap::new();
+        interfaces.insert("org.bluez.Device1", props);
+        interfaces.insert("org.freedesktop.DBus.Properties", HashMap::new());
+        interfaces
+    }
+
+    #[test]
+    fn test_get_managed_objects() {
+        let call = get_managed_objects("org.bluez", "/");
+        assert_eq!(call.dynheader.member.as_deref(), Some("GetManagedObjects"));
+
+        let mut objects = HashMap::new();
+        objects.insert(
+            ObjectPath::new("/org/bluez/hci0/dev_1").unwrap(),
+            device_interfaces(),
+        );
+        objects.insert(ObjectPath::new("/org/bluez/hci0").unwrap(), HashMap::new());
+        let mut reply = call.dynheader.make_response();
+        reply.body.push_param(&objects).unwrap();
+        assert_eq!(reply.get_sig(), "a{oa{sa{sv}}}");
+
+        let parsed = parse_get_managed_objects_reply(&reply).unwrap();
+        assert_eq!(parsed.len(), 2);
+        let device = &parsed[&ObjectPath::new("/org/bluez/hci0/dev_1".to_owned()).unwrap()];
+        assert_eq!(
+            device["org.bluez.Device1"]["Class"].get::<u32>().unwrap(),
+            7
+        );
+        assert!(device["org.freedesktop.DBus.Properties"].is_empty());
+        assert!(parsed[&ObjectPath::new("/org/bluez/hci0".to_owned()).unwrap()].is_empty());
+    }
+
+    #[test]
+    fn test_interfaces_signals() {
+        let path = ObjectPath::new("/org/bluez/hci0/dev_1").unwrap();
+
+        let mut added = MessageBuilder::new()
+            .signal(OBJECT_MANAGER_INTERFACE, "InterfacesAdded", "/")
+            .build();
+        added.body.push_param2(path, device_interfaces()).unwrap();
+        let parsed = parse_interfaces_added(&added).unwrap();
+        assert_eq!(parsed.path.as_ref(), "/org/bluez/hci0/dev_1");
+        assert_eq!(
+            parsed.interfaces["org.bluez.Device1"]["Class"]
+                .get::<u32>()
+                .unwrap(),
+            7
+        );
+
+        let mut removed = MessageBuilder::new()
+            .signal(OBJECT_MANAGER_INTERFACE, "InterfacesRemoved", "/")
+            .build();
+        removed
+            .body
+            .push_param2(path, &["org.bluez.Device1"][..])
+            .unwrap();
+        let parsed = parse_interfaces_removed(&removed).unwrap();
+        assert_eq!(parsed.path.as_ref(), "/org/bluez/hci0/dev_1");
+        assert_eq!(
+            parsed.interfaces,
+            vec![InterfaceName::new("org.bluez.Device1".to_owned()).unwrap()]
+        );
+
+        assert!(parse_interfaces_removed(&added).is_err());
+    }
+}
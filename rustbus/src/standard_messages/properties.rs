@@ -0,0 +1,165 @@
+//! Calls and signals of the `org.freedesktop.DBus.Properties` interface
+//!
+//! ```rust,no_run
+//! use rustbus::{connection::Timeout, standard_messages::properties, RpcConn};
+//!
+//! let mut rpc_con = RpcConn::session_conn(Timeout::Infinite).unwrap();
+//! let mut call = properties::get_all("org.freedesktop.DBus", "/org/freedesktop/DBus", "org.freedesktop.DBus");
+//! let reply = rpc_con.send_and_wait_response(&mut call, None).unwrap();
+//! for (name, value) in properties::parse_get_all_reply(&reply).unwrap() {
+//!     println!("{}: {:?}", name, value.get_value_sig());
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use crate::message_builder::{MarshalledMessage, MessageBuilder};
+use crate::signature;
+use crate::wire::errors::{MarshalError, UnmarshalError};
+use crate::wire::marshal::traits::Variant;
+use crate::wire::unmarshal::traits::Variant as UnmarshalVariant;
+use crate::Marshal;
+
+pub const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+fn make_properties_call(member: &str, dest: &str, path: &str) -> MarshalledMessage {
+    MessageBuilder::new()
+        .call(member)
+        .with_interface(PROPERTIES_INTERFACE)
+        .on(path)
+        .at(dest)
+        .build()
+}
+
+/// Get the value of the property `property` of `interface` at the object `path` of `dest`
+pub fn get(dest: &str, path: &str, interface: &str, property: &str) -> MarshalledMessage {
+    let mut msg = make_properties_call("Get", dest, path);
+    msg.body.push_param2(interface, property).unwrap();
+    msg
+}
+
+/// Get the values of all properties of `interface` at the object `path` of `dest`
+pub fn get_all(dest: &str, path: &str, interface: &str) -> MarshalledMessage {
+    let mut msg = make_properties_call("GetAll", dest, path);
+    msg.body.push_param(interface).unwrap();
+    msg
+}
+
+/// Set the property `property` of `interface` at the object `path` of `dest`. The value is wrapped into a variant.
+pub fn set<T: Marshal>(
+    dest: &str,
+    path: &str,
+    interface: &str,
+    property: &str,
+    value: T,
+) -> Result<MarshalledMessage, MarshalError> {
+    let mut msg = make_properties_call("Set", dest, path);
+    msg.body.push_param3(interface, property, Variant(value))?;
+    Ok(msg)
+}
+
+/// Get the property value out of the reply to a `get` call
+pub fn parse_get_reply(
+    reply: &MarshalledMessage,
+) -> Result<UnmarshalVariant<'_, '_>, UnmarshalError> {
+    reply.body.parser().get()
+}
+
+/// Get the property values, keyed by the property names, out of the reply to a `get_all` call
+pub fn parse_get_all_reply(
+    reply: &MarshalledMessage,
+) -> Result<HashMap<String, UnmarshalVariant<'_, '_>>, UnmarshalError> {
+    reply.body.parser().get()
+}
+
+/// Build the `PropertiesChanged` signal for the object at `path`.
+///
+/// The values in `changed` must marshal as variants. That is the case for `Variant<T>` and for enums generated with `dbus_variant_sig!`
+/// or derived with `#[derive(Marshal)]`. Otherwise `MarshalError::SignatureMismatch` is returned. `invalidated` lists properties
+/// that changed but whose new value is not part of the signal.
+pub fn properties_changed<V: Marshal>(
+    path: &str,
+    interface: &str,
+    changed: &HashMap<&str, V>,
+    invalidated: &[&str],
+) -> Result<MarshalledMessage, MarshalError> {
+    let variant_sig = signature::Type::Container(signature::Container::Variant);
+    if V::signature() != variant_sig {
+        return Err(MarshalError::SignatureMismatch {
+            expected: variant_sig.to_string(),
+            actual: V::signature().to_string(),
+        });
+    }
+    let mut msg = MessageBuilder::new()
+        .signal(PROPERTIES_INTERFACE, "PropertiesChanged", path)
+        .build();
+    msg.body.push_param3(interface, changed, invalidated)?;
+    Ok(msg)
+}
+
+/// The content of a `PropertiesChanged` signal
+#[derive(Debug)]
+pub struct PropertiesChanged<'a> {
+    pub interface: &'a str,
+    pub changed: HashMap<String, UnmarshalVariant<'a, 'a>>,
+    pub invalidated: Vec<&'a str>,
+}
+
+/// Get the content out of a `PropertiesChanged` signal
+pub fn parse_properties_changed(
+    signal: &MarshalledMessage,
+) -> Result<PropertiesChanged<'_>, UnmarshalError> {
+    let (interface, changed, invalidated) = signal.body.parser().get3()?;
+    Ok(PropertiesChanged {
+        interface,
+        changed,
+        invalidated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_properties_changed() {
+        crate::dbus_variant_sig!(Value, Count => u32; Name => String);
+
+        let mut changed = HashMap::new();
+        changed.insert("Count", Value::Count(42));
+        changed.insert("Name", Value::Name("spark".to_owned()));
+        let signal =
+            properties_changed("/io/killing/spark", "io.killing.spark", &changed, &["Data"])
+                .unwrap();
+        assert_eq!(signal.get_sig(), "sa{sv}as");
+
+        let parsed = parse_properties_changed(&signal).unwrap();
+        assert_eq!(parsed.interface, "io.killing.spark");
+        assert_eq!(parsed.changed["Count"].get::<u32>().unwrap(), 42);
+        assert_eq!(parsed.changed["Name"].get::<&str>().unwrap(), "spark");
+        assert_eq!(parsed.invalidated, vec!["Data"]);
+
+        let mut not_variants = HashMap::new();
+        not_variants.insert("Count", 42u32);
+        assert!(matches!(
+            properties_changed("/", "io.killing.spark", &not_variants, &[]),
+            Err(MarshalError::SignatureMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_calls() {
+        let msg = get("io.killing.spark", "/", "io.killing.spark", "Count");
+        assert_eq!(
+            msg.dynheader.interface.as_deref(),
+            Some(PROPERTIES_INTERFACE)
+        );
+        assert_eq!(msg.dynheader.member.as_deref(), Some("Get"));
+        assert_eq!(msg.get_sig(), "ss");
+
+        let msg = set("io.killing.spark", "/", "io.killing.spark", "Count", 42u32).unwrap();
+        assert_eq!(msg.get_sig(), "ssv");
+        let (_, _, value): (&str, &str, UnmarshalVariant) = msg.body.parser().get3().unwrap();
+        assert_eq!(value.get::<u32>().unwrap(), 42);
+    }
+}
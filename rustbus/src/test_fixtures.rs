@@ -0,0 +1,70 @@
+//! Messages and helpers shared by the unit tests
+//!
+//! Apart from `bus_call` the messages all talk to the same made up service. They use the default (little endian)
+//! byte order.
+
+#[cfg(feature = "std")]
+use crate::connection::{rpc_conn::RpcConn, Timeout};
+use crate::message_builder::{MarshalledMessage, MessageBuilder};
+use crate::wire::marshal::marshal;
+
+/// How long the tests against the session bus wait before they give up
+#[cfg(feature = "std")]
+pub(crate) const TIMEOUT: Timeout = Timeout::Duration(std::time::Duration::from_secs(10));
+
+#[cfg(feature = "std")]
+pub(crate) fn call(member: &str) -> MarshalledMessage {
+    MessageBuilder::new()
+        .call(member)
+        .with_interface("io.killing.spark")
+        .on("/io/killing/spark")
+        .at("io.killing.spark")
+        .build()
+}
+
+/// A call to the bus itself
+#[cfg(feature = "std")]
+pub(crate) fn bus_call(member: &str) -> MarshalledMessage {
+    MessageBuilder::new()
+        .call(member)
+        .with_interface("org.freedesktop.DBus")
+        .on("/org/freedesktop/DBus")
+        .at("org.freedesktop.DBus")
+        .build()
+}
+
+pub(crate) fn signal() -> MarshalledMessage {
+    signal_on("/io/killing/spark")
+}
+
+pub(crate) fn signal_on(path: &str) -> MarshalledMessage {
+    MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", path)
+        .build()
+}
+
+/// A signal with a single string argument
+pub(crate) fn string_signal(arg: &str) -> MarshalledMessage {
+    let mut msg = signal();
+    msg.body.push_param(arg).unwrap();
+    msg
+}
+
+/// Wait for the next signal on `interface`, skipping signals from the bus itself, like NameAcquired
+#[cfg(feature = "std")]
+pub(crate) fn wait_signal_on(rpc_con: &mut RpcConn, interface: &str) -> MarshalledMessage {
+    loop {
+        let signal = rpc_con.wait_signal(TIMEOUT).unwrap();
+        if signal.dynheader.interface.as_deref() == Some(interface) {
+            return signal;
+        }
+    }
+}
+
+/// The complete message as it goes over the wire
+pub(crate) fn to_bytes(msg: &MarshalledMessage, serial: u32) -> Vec<u8> {
+    let mut buf = Vec::new();
+    marshal(msg, serial, &mut buf).unwrap();
+    buf.extend_from_slice(msg.get_buf());
+    buf
+}
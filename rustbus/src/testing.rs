@@ -0,0 +1,60 @@
+//! Invariants for property tests and fuzzers, enabled with the `testing` feature
+//!
+//! ```rust
+//! use rustbus::params::{Base, Container, Param};
+//! use rustbus::testing::roundtrip;
+//!
+//! let param = Param::Container(Container::make_struct2(Base::Uint32(7), Base::StringRef("seven")));
+//! assert_eq!(roundtrip(&param).unwrap(), param);
+//! ```
+
+use crate::alloc_prelude::*;
+use thiserror::Error;
+
+use crate::params::Param;
+use crate::wire::errors::{MarshalError, UnmarshalError};
+use crate::wire::marshal::container::marshal_param;
+use crate::wire::marshal::MarshalContext;
+use crate::wire::unmarshal::container::unmarshal_with_sig;
+use crate::wire::unmarshal::UnmarshalContext;
+use crate::ByteOrder;
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum RoundtripError {
+    #[error("Marshalling failed: {0}")]
+    Marshal(#[from] MarshalError),
+    #[error("Unmarshalling failed: {0}")]
+    Unmarshal(#[from] UnmarshalError),
+}
+
+/// Marshal `param` into a fresh little endian buffer and unmarshal it again with its own signature. For every param
+/// that marshals the result should equal the input, with any by-ref params turned into owned ones.
+///
+/// Unmarshalling is strict, so padding that is not zero is reported. If the unmarshalled param does not use all of
+/// the bytes `UnmarshalError::NotAllBytesUsed` is returned. Unix fds are dup()ed while marshalling, so a param that
+/// contains one does not compare equal to the result.
+pub fn roundtrip(param: &Param) -> Result<Param<'static, 'static>, RoundtripError> {
+    roundtrip_with_byteorder(param, ByteOrder::LittleEndian)
+}
+
+/// Like `roundtrip` but in the given byteorder
+pub fn roundtrip_with_byteorder(
+    param: &Param,
+    byteorder: ByteOrder,
+) -> Result<Param<'static, 'static>, RoundtripError> {
+    let mut buf = Vec::new();
+    let mut fds = Vec::new();
+    let mut ctx = MarshalContext {
+        buf: &mut buf,
+        fds: &mut fds,
+        byteorder,
+    };
+    marshal_param(param, &mut ctx)?;
+
+    let mut ctx = UnmarshalContext::conformance(&fds, &buf, byteorder, 0);
+    let (bytes_used, unmarshalled) = unmarshal_with_sig(&param.sig(), &mut ctx)?;
+    if bytes_used != buf.len() {
+        return Err(UnmarshalError::NotAllBytesUsed.into());
+    }
+    Ok(unmarshalled)
+}
@@ -5,25 +5,40 @@ use crate::wire::unmarshal::unmarshal_dynamic_header;
 use crate::wire::unmarshal::unmarshal_header;
 use crate::wire::unmarshal::unmarshal_next_message;
 
+mod addresses;
+mod auth;
+mod call_timeout;
+mod conformance;
 mod dbus_send;
 mod fdpassing;
+mod filters;
+mod header_fields;
+mod introspect;
+mod match_rules;
+mod monitor;
+mod names;
+mod nesting;
+mod properties;
+mod reply_matching;
+mod split;
+mod tcp;
 mod verify_marshalling;
 mod verify_padding;
 
 // this tests the happy path
 #[test]
 fn test_marshal_unmarshal() {
-    let mut params: Vec<Param> = Vec::new();
-
-    params.push(128u8.into());
-    params.push(128u16.into());
-    params.push((-128i16).into());
-    params.push(1212128u32.into());
-    params.push((-1212128i32).into());
-    params.push(1212121212128u64.into());
-    params.push((-1212121212128i64).into());
-    params.push("TesttestTesttest".to_owned().into());
-    params.push(Base::ObjectPath("/this/object/path".into()).into());
+    let mut params: Vec<Param> = vec![
+        128u8.into(),
+        128u16.into(),
+        (-128i16).into(),
+        1212128u32.into(),
+        (-1212128i32).into(),
+        1212121212128u64.into(),
+        (-1212121212128i64).into(),
+        "TesttestTesttest".to_owned().into(),
+        Base::ObjectPath("/this/object/path".into()).into(),
+    ];
 
     let mut msg = crate::message_builder::MessageBuilder::new()
         .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
@@ -56,6 +71,46 @@ fn test_marshal_unmarshal() {
     assert_eq!(params, msg.params);
 }
 
+// this tests that messages can be marshalled in big endian and read back
+#[test]
+fn test_marshal_unmarshal_big_endian() {
+    let mut msg =
+        crate::message_builder::MessageBuilder::with_byteorder(crate::ByteOrder::BigEndian)
+            .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+            .build();
+    msg.body.push_param(0x0102_0304u32).unwrap();
+    msg.body.push_param("ABCD").unwrap();
+    msg.body.push_param(1.5f64).unwrap();
+    msg.body.push_param(vec![0x0506u16, 0x0708u16]).unwrap();
+
+    assert_eq!(
+        msg.get_buf(),
+        &[
+            1, 2, 3, 4, 0, 0, 0, 4, b'A', b'B', b'C', b'D', 0, 0, 0, 0, 0x3f, 0xf8, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 4, 5, 6, 7, 8
+        ]
+    );
+
+    msg.dynheader.serial = Some(1);
+    let mut buf = Vec::new();
+    marshal(&msg, 0, &mut buf).unwrap();
+    assert_eq!(buf[0], b'B');
+
+    let (hdrbytes, header) = unmarshal_header(&buf, 0).unwrap();
+    assert_eq!(header.byteorder, crate::ByteOrder::BigEndian);
+    assert_eq!(header.body_len as usize, msg.get_buf().len());
+    let (_, dynheader) = unmarshal_dynamic_header(&header, &buf, hdrbytes).unwrap();
+    assert_eq!(dynheader.signature.as_deref(), Some("usdaq"));
+
+    let (_, unmarshalled) =
+        unmarshal_next_message(&header, dynheader, msg.get_buf().to_vec(), 0).unwrap();
+    let mut parser = unmarshalled.body.parser();
+    assert_eq!(parser.get(), Ok(0x0102_0304u32));
+    assert_eq!(parser.get(), Ok("ABCD"));
+    assert_eq!(parser.get(), Ok(1.5f64));
+    assert_eq!(parser.get(), Ok(vec![0x0506u16, 0x0708u16]));
+}
+
 // this tests that invalid inputs return appropriate errors
 #[test]
 fn test_invalid_stuff() {
@@ -85,7 +140,9 @@ fn test_invalid_stuff() {
         .push_old_param(&Param::Base(Base::ObjectPath("invalid/object/path".into())));
     assert_eq!(
         Err(crate::wire::errors::MarshalError::Validation(
-            crate::params::validation::Error::InvalidObjectPath
+            crate::params::validation::Error::InvalidObjectPath(
+                crate::params::validation::ObjectPathError::MissingLeadingSlash
+            )
         )),
         err
     );
@@ -98,7 +155,9 @@ fn test_invalid_stuff() {
     let mut buf = Vec::new();
     assert_eq!(
         Err(crate::wire::errors::MarshalError::Validation(
-            crate::params::validation::Error::InvalidInterface
+            crate::params::validation::Error::InvalidInterface(
+                crate::params::validation::NameError::EmptyElement(0)
+            )
         )),
         marshal(&msg, 0, &mut buf)
     );
@@ -115,7 +174,12 @@ fn test_invalid_stuff() {
     let mut buf = Vec::new();
     assert_eq!(
         Err(crate::wire::errors::MarshalError::Validation(
-            crate::params::validation::Error::InvalidMembername
+            crate::params::validation::Error::InvalidMembername(
+                crate::params::validation::NameError::InvalidChar {
+                    character: '.',
+                    offset: 7
+                }
+            )
         )),
         marshal(&msg, 0, &mut buf)
     );
@@ -0,0 +1,35 @@
+use crate::connection::ll_conn::DuplexConn;
+use crate::connection::{get_session_bus_addresses, AddressError, Error, Timeout};
+
+#[test]
+fn test_connect_to_any_bus() {
+    // the candidates before the real session bus address can not be used and are skipped
+    let addrs = format!(
+        "unix:path=/tmp/dbus-test-not-exist;autolaunch:;{}",
+        get_session_bus_addresses().unwrap()
+    );
+    let mut conn = DuplexConn::connect_to_any_bus(&addrs, true).unwrap();
+    conn.send_hello(Timeout::Infinite).unwrap();
+
+    // nothing listens on port 1
+    let addrs = "unix:path=/tmp/dbus-test-not-exist;tcp:host=127.0.0.1,port=1";
+    match DuplexConn::connect_to_any_bus(addrs, true) {
+        Err(Error::NoUsableAddress(failures)) => {
+            let addresses = failures
+                .iter()
+                .map(|AddressError { address, .. }| address.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                addresses,
+                [
+                    "unix:path=/tmp/dbus-test-not-exist",
+                    "tcp:host=127.0.0.1,port=1"
+                ]
+            );
+            assert!(matches!(failures[0].error, Error::PathDoesNotExist(_)));
+            assert!(matches!(failures[1].error, Error::IoError(_)));
+        }
+        Err(other) => panic!("expected Error::NoUsableAddress, got {:?}", other),
+        Ok(_) => panic!("expected Error::NoUsableAddress"),
+    }
+}
@@ -0,0 +1,30 @@
+use crate::auth::{AuthError, AuthMechanism};
+use crate::connection::ll_conn::DuplexConn;
+use crate::connection::{get_session_bus_path, Error, Timeout};
+
+#[test]
+fn test_auth_fallback() {
+    // the session bus does not allow anonymous connections but offers EXTERNAL after rejecting it
+    let mut conn = DuplexConn::connect_to_bus_with_auth(
+        get_session_bus_path().unwrap(),
+        true,
+        &[AuthMechanism::Anonymous, AuthMechanism::External],
+    )
+    .unwrap();
+    conn.send_hello(Timeout::Infinite).unwrap();
+
+    match DuplexConn::connect_to_bus_with_auth(
+        get_session_bus_path().unwrap(),
+        true,
+        &[AuthMechanism::Anonymous],
+    ) {
+        Err(Error::AuthRejected(AuthError::Rejected { offered })) => {
+            assert!(offered.iter().any(|mech| mech == "EXTERNAL"))
+        }
+        Err(other) => panic!(
+            "Expected the bus to reject the connection, got: {:?}",
+            other
+        ),
+        Ok(_) => panic!("Expected the bus to reject the connection"),
+    }
+}
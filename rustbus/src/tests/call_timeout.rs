@@ -0,0 +1,136 @@
+use crate::connection::ll_conn::{force_finish_on_error, DuplexConn};
+use crate::connection::rpc_conn::{
+    InteractiveAuth, RpcConn, INTERACTIVE_AUTHORIZATION_REQUIRED, MAX_ABANDONED_CALLS,
+};
+use crate::connection::{get_session_bus_path, Error, Timeout};
+use crate::message_builder::{HeaderFlags, MessageBuilder};
+use std::time::Duration;
+
+#[test]
+fn test_call_timeout() {
+    let conn = DuplexConn::connect_to_bus(get_session_bus_path().unwrap(), true).unwrap();
+    let mut rpc_con = RpcConn::new(conn);
+    rpc_con.set_default_timeout(Timeout::Duration(Duration::from_secs(10)));
+
+    let reply = rpc_con
+        .send_and_wait_response(&mut crate::standard_messages::hello(), None)
+        .unwrap();
+    let unique_name: String = reply.body.parser().get().unwrap();
+
+    // call ourselves, nobody is going to answer this in time
+    let mut call = MessageBuilder::new()
+        .call("Ping")
+        .with_interface("io.killing.spark")
+        .on("/io/killing/spark")
+        .at(unique_name.clone())
+        .build();
+    let res = rpc_con.send_and_wait_response(
+        &mut call,
+        Some(Timeout::Duration(Duration::from_millis(100))),
+    );
+    assert!(matches!(res, Err(Error::TimedOut)));
+    assert!(rpc_con.pending.is_empty());
+
+    // answer the call late, the reply must not be delivered as a response anymore
+    let call = rpc_con
+        .wait_call(Timeout::Duration(Duration::from_secs(10)))
+        .unwrap();
+    let serial = call.dynheader.serial.unwrap();
+    rpc_con
+        .send_message(&mut call.dynheader.make_response())
+        .unwrap()
+        .write_all()
+        .map_err(force_finish_on_error)
+        .unwrap();
+    rpc_con
+        .refill_once(Timeout::Duration(Duration::from_secs(10)))
+        .unwrap();
+    assert!(rpc_con.try_get_response(serial).is_none());
+    assert!(rpc_con.abandoned.is_empty());
+
+    // deadlines in the past time out immediately
+    let res = rpc_con.wait_response(serial, Timeout::Deadline(std::time::Instant::now()));
+    assert!(matches!(res, Err(Error::TimedOut)));
+
+    // giving up on a call sent with send_message forgets it as well, polling does not
+    let mut call = MessageBuilder::new()
+        .call("Ping")
+        .with_interface("io.killing.spark")
+        .on("/io/killing/spark")
+        .at(unique_name)
+        .build();
+    let serial = rpc_con
+        .send_message(&mut call)
+        .unwrap()
+        .write_all()
+        .map_err(force_finish_on_error)
+        .unwrap();
+    assert!(rpc_con.pending.contains(&serial));
+    assert!(rpc_con.wait_response(serial, Timeout::Nonblock).is_err());
+    assert!(rpc_con.pending.contains(&serial));
+    let res = rpc_con.wait_response(serial, Timeout::Duration(Duration::from_millis(100)));
+    assert!(matches!(res, Err(Error::TimedOut)));
+    assert!(rpc_con.pending.is_empty());
+
+    // calls that are never answered are eventually forgotten
+    for serial in 0..MAX_ABANDONED_CALLS as u32 + 10 {
+        rpc_con.abandon(serial);
+    }
+    assert_eq!(rpc_con.abandoned.len(), MAX_ABANDONED_CALLS);
+    assert_eq!(rpc_con.abandoned.front(), Some(&10));
+}
+
+#[test]
+fn test_interactive_auth_timeout() {
+    let timeout = Timeout::Duration(Duration::from_secs(10));
+    let conn = DuplexConn::connect_to_bus(get_session_bus_path().unwrap(), true).unwrap();
+    let mut service = RpcConn::new(conn);
+    let reply = service
+        .send_and_wait_response(&mut crate::standard_messages::hello(), Some(timeout))
+        .unwrap();
+    let service_name: String = reply.body.parser().get().unwrap();
+
+    let handle = std::thread::spawn(move || {
+        // the first attempt comes without the flag and is rejected
+        let call = service.wait_call(timeout).unwrap();
+        assert!(!HeaderFlags::AllowInteractiveAuthorization.is_set(call.flags));
+        let mut err = call
+            .dynheader
+            .make_error_response(INTERACTIVE_AUTHORIZATION_REQUIRED, None);
+        service
+            .send_message(&mut err)
+            .unwrap()
+            .write_all()
+            .map_err(force_finish_on_error)
+            .unwrap();
+
+        // the repeated call waits for a prompt that takes longer than the default timeout of the caller
+        let call = service.wait_call(timeout).unwrap();
+        assert!(HeaderFlags::AllowInteractiveAuthorization.is_set(call.flags));
+        std::thread::sleep(Duration::from_millis(300));
+        let mut reply = call.dynheader.make_response();
+        reply.body.push_param("granted").unwrap();
+        service
+            .send_message(&mut reply)
+            .unwrap()
+            .write_all()
+            .map_err(force_finish_on_error)
+            .unwrap();
+    });
+
+    let mut rpc_con = RpcConn::session_conn(timeout).unwrap();
+    rpc_con.set_default_timeout(Timeout::Duration(Duration::from_millis(100)));
+    rpc_con.set_interactive_auth_timeout(timeout);
+    let answer: String = rpc_con
+        .call_method_with_auth(
+            &service_name,
+            "/io/killing/spark",
+            "io.killing.spark",
+            "Unlock",
+            (),
+            InteractiveAuth::Retry,
+        )
+        .unwrap();
+    assert_eq!(answer, "granted");
+    handle.join().unwrap();
+}
@@ -0,0 +1,122 @@
+use crate::message_builder::MessageBuilder;
+use crate::test_fixtures::{signal, to_bytes};
+use crate::wire::errors::{StringError, UnmarshalError};
+use crate::wire::reassembler::MAX_MESSAGE_SIZE;
+use crate::wire::unmarshal::unmarshal_message_conformant;
+use crate::wire::util::parse_u32;
+use crate::wire::UnixFd;
+use crate::ByteOrder;
+
+#[test]
+fn test_conformant_message() {
+    let mut msg = signal();
+    msg.body.push_param3(7u8, "text", 42u32).unwrap();
+    let mut buf = to_bytes(&msg, 1);
+    let len = buf.len();
+    // bytes after the message are not part of it
+    buf.extend_from_slice(&[0xFF; 8]);
+
+    let (used, parsed) = unmarshal_message_conformant(&buf, vec![]).unwrap();
+    assert_eq!(used, len);
+    assert_eq!(parsed.dynheader.member.as_deref(), Some("TestSignal"));
+    assert_eq!(parsed.get_buf(), msg.get_buf());
+    assert_eq!(
+        parsed.body.parser().get3::<u8, &str, u32>().unwrap(),
+        (7, "text", 42)
+    );
+
+    assert_eq!(
+        unmarshal_message_conformant(&buf[..len - 1], vec![]).unwrap_err(),
+        UnmarshalError::NotEnoughBytes
+    );
+}
+
+#[test]
+fn test_conformance_violations() {
+    let mut msg = signal();
+    msg.body.push_param3(7u8, 42u32, 1u8).unwrap();
+    let good = to_bytes(&msg, 1);
+    let body_start = good.len() - msg.get_buf().len();
+    let check = |buf: &[u8]| unmarshal_message_conformant(buf, vec![]).unwrap_err();
+
+    // padding between the u8 and the u32 in the body
+    let mut buf = good.clone();
+    buf[body_start + 2] = 1;
+    assert_eq!(check(&buf), UnmarshalError::NonZeroPadding { offset: 2 });
+
+    // padding between the header fields and the body
+    let fields_end = 16 + parse_u32(&good[12..], ByteOrder::LittleEndian).unwrap().1 as usize;
+    assert!(fields_end < body_start);
+    let mut buf = good.clone();
+    buf[fields_end] = 1;
+    assert_eq!(check(&buf), UnmarshalError::PaddingContainedData);
+
+    // padding between the path field, which ends at 42, and the next one
+    let mut buf = good.clone();
+    buf[42] = 1;
+    assert_eq!(check(&buf), UnmarshalError::PaddingContainedData);
+
+    // a signal needs an interface, the call does not have one
+    let call = MessageBuilder::new()
+        .call("Conformance")
+        .on("/io/killing/spark")
+        .build();
+    let mut buf = to_bytes(&call, 1);
+    assert!(unmarshal_message_conformant(&buf, vec![]).is_ok());
+    buf[1] = 4;
+    assert_eq!(
+        check(&buf),
+        UnmarshalError::MissingHeaderField { field: "INTERFACE" }
+    );
+
+    let mut buf = good.clone();
+    buf[4..8].copy_from_slice(&(MAX_MESSAGE_SIZE as u32).to_le_bytes());
+    assert!(matches!(check(&buf), UnmarshalError::MessageTooLong { .. }));
+
+    let mut text = signal();
+    text.body.push_param("text").unwrap();
+    let mut buf = to_bytes(&text, 1);
+    let idx = buf.len() - 2;
+    buf[idx] = 0xFF;
+    assert!(matches!(
+        check(&buf),
+        UnmarshalError::InvalidString(StringError::InvalidUtf8 { valid_up_to: 3 })
+    ));
+
+    // a boolean that is neither 0 nor 1
+    let mut flag = signal();
+    flag.body.push_param(true).unwrap();
+    let mut buf = to_bytes(&flag, 1);
+    let idx = buf.len() - 4;
+    buf[idx] = 2;
+    assert_eq!(check(&buf), UnmarshalError::InvalidBoolean);
+}
+
+#[test]
+fn test_conformance_fds() {
+    let mut msg = signal();
+    msg.body
+        .push_param(UnixFd::new(nix::unistd::dup(0).unwrap()))
+        .unwrap();
+    let buf = to_bytes(&msg, 1);
+    let fds = msg.body.raw_fds.clone();
+
+    assert_eq!(
+        unmarshal_message_conformant(&buf, vec![]).unwrap_err(),
+        UnmarshalError::FdCountMismatch {
+            declared: 1,
+            received: 0
+        }
+    );
+    let (_, parsed) = unmarshal_message_conformant(&buf, fds).unwrap();
+    assert_eq!(parsed.dynheader.num_fds, Some(1));
+
+    // the index in the body points past the only fd
+    let mut buf = buf;
+    let idx = buf.len() - 4;
+    buf[idx..].copy_from_slice(&1u32.to_le_bytes());
+    assert_eq!(
+        unmarshal_message_conformant(&buf, msg.body.raw_fds.clone()).unwrap_err(),
+        UnmarshalError::BadFdIndex(1)
+    );
+}
@@ -116,12 +116,10 @@ fn test_fd_marshalling() {
     let mut parser = sig.body.parser();
     let _fd1: crate::wire::UnixFd = parser.get().unwrap();
     // get _fd2
-    assert!(match parser.get_param().unwrap() {
-        crate::params::Param::Base(crate::params::Base::UnixFd(_fd)) => {
-            true
-        }
-        _ => false,
-    });
+    assert!(matches!(
+        parser.get_param().unwrap(),
+        crate::params::Param::Base(crate::params::Base::UnixFd(_fd))
+    ));
     let _fd3: crate::wire::UnixFd = parser.get().unwrap();
 
     // Take all fds back to prevent accidental closing of actual FDs
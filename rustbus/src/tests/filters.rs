@@ -0,0 +1,85 @@
+use crate::connection::ll_conn::force_finish_on_error;
+use crate::connection::rpc_conn::{FilterAction, RpcConn};
+use crate::message_builder::MessageBuilder;
+use crate::test_fixtures::{wait_signal_on, TIMEOUT};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_filter_callbacks() {
+    let mut rpc_con = RpcConn::session_conn(TIMEOUT).unwrap();
+    rpc_con
+        .send_and_wait_response(
+            &mut crate::standard_messages::add_match(
+                "type='signal',interface='io.killing.spark.filtertest'",
+            ),
+            None,
+        )
+        .unwrap();
+
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::new(AtomicUsize::new(0));
+
+    let (order1, seen1) = (order.clone(), seen.clone());
+    rpc_con.add_filter(move |msg| {
+        if msg.dynheader.interface.as_deref() == Some("io.killing.spark.filtertest") {
+            order1.lock().unwrap().push(1);
+            seen1.fetch_add(1, Ordering::SeqCst);
+        }
+        FilterAction::Pass
+    });
+    let order2 = order.clone();
+    rpc_con.add_filter(move |msg| {
+        if msg.dynheader.interface.as_deref() != Some("io.killing.spark.filtertest") {
+            return FilterAction::Pass;
+        }
+        order2.lock().unwrap().push(2);
+        if msg.dynheader.member.as_deref() == Some("Consumed") {
+            FilterAction::Consumed
+        } else {
+            FilterAction::Pass
+        }
+    });
+    let order3 = order.clone();
+    rpc_con.add_filter(move |msg| {
+        if msg.dynheader.interface.as_deref() == Some("io.killing.spark.filtertest") {
+            order3.lock().unwrap().push(3);
+        }
+        FilterAction::Pass
+    });
+
+    for member in ["Consumed", "Passed"] {
+        let mut signal = MessageBuilder::new()
+            .signal("io.killing.spark.filtertest", member, "/io/killing/spark")
+            .build();
+        rpc_con
+            .send_message(&mut signal)
+            .unwrap()
+            .write_all()
+            .map_err(force_finish_on_error)
+            .unwrap();
+    }
+
+    // only the signal that was not consumed ends up in the queue
+    let signal = wait_signal_on(&mut rpc_con, "io.killing.spark.filtertest");
+    assert_eq!(signal.dynheader.member.as_deref(), Some("Passed"));
+    assert_eq!(seen.load(Ordering::SeqCst), 2);
+    assert_eq!(*order.lock().unwrap(), vec![1, 2, 1, 2, 3]);
+
+    // responses to pending calls never reach the callbacks
+    rpc_con.add_filter(|msg| {
+        assert!(msg.dynheader.response_serial.is_none());
+        FilterAction::Consumed
+    });
+    rpc_con
+        .send_and_wait_response(
+            &mut MessageBuilder::new()
+                .call("GetId")
+                .with_interface("org.freedesktop.DBus")
+                .on("/org/freedesktop/DBus")
+                .at("org.freedesktop.DBus")
+                .build(),
+            None,
+        )
+        .unwrap();
+}
@@ -0,0 +1,88 @@
+use crate::message_builder::MarshalledMessage;
+use crate::standard_messages;
+use crate::test_fixtures::{call, signal};
+use crate::wire::errors::UnmarshalError;
+use crate::wire::marshal::marshal;
+use crate::wire::unmarshal::unmarshal_message_header;
+
+fn unmarshal_without(
+    mut msg: MarshalledMessage,
+    remove: fn(&mut MarshalledMessage),
+) -> UnmarshalError {
+    let mut buf = Vec::new();
+    marshal(&msg, 1, &mut buf).unwrap();
+    assert!(unmarshal_message_header(&buf, 0).is_ok());
+
+    remove(&mut msg);
+    buf.clear();
+    marshal(&msg, 1, &mut buf).unwrap();
+    unmarshal_message_header(&buf, 0).unwrap_err()
+}
+
+fn missing(field: &'static str) -> UnmarshalError {
+    UnmarshalError::MissingHeaderField { field }
+}
+
+fn answered_call() -> MarshalledMessage {
+    let mut call = call("Method");
+    call.dynheader.serial = Some(1);
+    call
+}
+
+#[test]
+fn test_call_header_fields() {
+    assert_eq!(
+        unmarshal_without(call("Method"), |msg| msg.dynheader.object = None),
+        missing("PATH")
+    );
+    assert_eq!(
+        unmarshal_without(call("Method"), |msg| msg.dynheader.member = None),
+        missing("MEMBER")
+    );
+    // the interface is optional for calls
+    let mut buf = Vec::new();
+    let mut msg = call("Method");
+    msg.dynheader.interface = None;
+    marshal(&msg, 1, &mut buf).unwrap();
+    assert!(unmarshal_message_header(&buf, 0).is_ok());
+}
+
+#[test]
+fn test_signal_header_fields() {
+    assert_eq!(
+        unmarshal_without(signal(), |msg| msg.dynheader.object = None),
+        missing("PATH")
+    );
+    assert_eq!(
+        unmarshal_without(signal(), |msg| msg.dynheader.interface = None),
+        missing("INTERFACE")
+    );
+    assert_eq!(
+        unmarshal_without(signal(), |msg| msg.dynheader.member = None),
+        missing("MEMBER")
+    );
+}
+
+#[test]
+fn test_reply_header_fields() {
+    assert_eq!(
+        unmarshal_without(answered_call().dynheader.make_response(), |msg| msg
+            .dynheader
+            .response_serial =
+            None),
+        missing("REPLY_SERIAL")
+    );
+}
+
+#[test]
+fn test_error_header_fields() {
+    let error = || standard_messages::unknown_method(&answered_call().dynheader);
+    assert_eq!(
+        unmarshal_without(error(), |msg| msg.dynheader.error_name = None),
+        missing("ERROR_NAME")
+    );
+    assert_eq!(
+        unmarshal_without(error(), |msg| msg.dynheader.response_serial = None),
+        missing("REPLY_SERIAL")
+    );
+}
@@ -0,0 +1,31 @@
+use crate::connection::rpc_conn::RpcConn;
+use crate::connection::Timeout;
+use crate::introspect::{self, INTROSPECTABLE_INTERFACE};
+use crate::message_builder::MessageBuilder;
+use std::time::Duration;
+
+#[test]
+fn test_parse_bus_introspection() {
+    let mut rpc_con = RpcConn::session_conn(Timeout::Duration(Duration::from_secs(10))).unwrap();
+    let reply = rpc_con
+        .send_and_wait_response(
+            &mut MessageBuilder::new()
+                .call("Introspect")
+                .with_interface(INTROSPECTABLE_INTERFACE)
+                .on("/org/freedesktop/DBus")
+                .at("org.freedesktop.DBus")
+                .build(),
+            None,
+        )
+        .unwrap();
+    let xml: &str = reply.body.parser().get().unwrap();
+    let node = introspect::parse(xml).unwrap();
+
+    let bus = node.interface("org.freedesktop.DBus").unwrap();
+    let request_name = bus.find_method("RequestName").unwrap();
+    assert_eq!(request_name.in_signature(), "su");
+    assert_eq!(request_name.out_signature(), "u");
+    let name_owner_changed = bus.find_signal("NameOwnerChanged").unwrap();
+    assert_eq!(name_owner_changed.args.len(), 3);
+    assert!(node.interface(INTROSPECTABLE_INTERFACE).is_some());
+}
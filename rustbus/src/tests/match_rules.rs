@@ -0,0 +1,115 @@
+use crate::connection::ll_conn::force_finish_on_error;
+use crate::connection::rpc_conn::RpcConn;
+use crate::connection::Error;
+use crate::message_builder::{MessageBuilder, MessageType};
+use crate::test_fixtures::{wait_signal_on, TIMEOUT};
+use crate::MatchRule;
+
+#[test]
+fn test_add_remove_match() {
+    let mut rpc_con = RpcConn::session_conn(TIMEOUT).unwrap();
+    let tricky_arg = "a,b\\c'd";
+    let rule = MatchRule::new()
+        .msg_type(MessageType::Signal)
+        .interface("io.killing.spark.matchtest")
+        .path_namespace("/io/killing")
+        .arg(0, tricky_arg);
+    rpc_con.add_match(&rule).unwrap();
+
+    for arg in ["a,b\\c", tricky_arg] {
+        let mut signal = MessageBuilder::new()
+            .signal("io.killing.spark.matchtest", "Test", "/io/killing/spark")
+            .build();
+        signal.body.push_param(arg).unwrap();
+        rpc_con
+            .send_message(&mut signal)
+            .unwrap()
+            .write_all()
+            .map_err(force_finish_on_error)
+            .unwrap();
+    }
+
+    // only the signal with the exact arg0 is routed back to us
+    let signal = wait_signal_on(&mut rpc_con, "io.killing.spark.matchtest");
+    assert_eq!(signal.body.parser().get::<&str>().unwrap(), tricky_arg);
+
+    rpc_con.remove_match(&rule).unwrap();
+    match rpc_con.remove_match(&rule) {
+        Err(Error::ErrorResponse(err)) => {
+            assert_eq!(err.name, "org.freedesktop.DBus.Error.MatchRuleNotFound")
+        }
+        other => panic!("Removing an unknown rule should fail, got: {:?}", other),
+    }
+}
+
+#[test]
+fn test_emit_signal() {
+    let mut sender = RpcConn::session_conn(TIMEOUT).unwrap();
+    sender.set_default_timeout(TIMEOUT);
+    let mut receiver = RpcConn::session_conn(TIMEOUT).unwrap();
+    receiver.set_default_timeout(TIMEOUT);
+    let rule = MatchRule::new()
+        .msg_type(MessageType::Signal)
+        .interface("io.killing.spark.emittest");
+    receiver.add_match(&rule).unwrap();
+    let name = "io.killing.spark.emittest";
+    receiver
+        .call_method::<u32, _>(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "RequestName",
+            (name, 0u32),
+        )
+        .unwrap();
+
+    let serial = sender
+        .emit_signal(
+            "/io/killing/spark",
+            "io.killing.spark.emittest",
+            "Broadcast",
+            ("count", 42u32, vec![(1u8, true)]),
+        )
+        .unwrap();
+    sender
+        .emit_signal_to(
+            name,
+            "/io/killing/spark",
+            "io.killing.spark.emittest",
+            "Direct",
+            (),
+        )
+        .unwrap();
+
+    let mut received = Vec::new();
+    while received.len() < 2 {
+        let signal = receiver.wait_signal(TIMEOUT).unwrap();
+        if signal.dynheader.interface.as_deref() == Some("io.killing.spark.emittest") {
+            received.push(signal);
+        }
+    }
+
+    let broadcast = &received[0];
+    assert_eq!(broadcast.typ, MessageType::Signal);
+    assert_eq!(broadcast.dynheader.serial, Some(serial));
+    assert_eq!(broadcast.dynheader.member.as_deref(), Some("Broadcast"));
+    assert_eq!(
+        broadcast.dynheader.object.as_deref(),
+        Some("/io/killing/spark")
+    );
+    assert_eq!(broadcast.dynheader.destination, None);
+    assert_eq!(broadcast.get_sig(), "sua(yb)");
+    assert_eq!(
+        broadcast
+            .body
+            .parser()
+            .get3::<&str, u32, Vec<(u8, bool)>>()
+            .unwrap(),
+        ("count", 42, vec![(1, true)])
+    );
+
+    let direct = &received[1];
+    assert_eq!(direct.dynheader.member.as_deref(), Some("Direct"));
+    assert_eq!(direct.dynheader.destination.as_deref(), Some(name));
+    assert_eq!(direct.get_sig(), "");
+}
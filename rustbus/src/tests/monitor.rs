@@ -0,0 +1,39 @@
+use crate::connection::get_session_bus_path;
+use crate::connection::ll_conn::{force_finish_on_error, DuplexConn};
+use crate::connection::rpc_conn::RpcConn;
+use crate::message_builder::{MessageBuilder, MessageType};
+use crate::test_fixtures::TIMEOUT;
+use crate::MatchRule;
+
+#[test]
+fn test_monitor() {
+    let mut conn = DuplexConn::connect_to_bus(get_session_bus_path().unwrap(), false).unwrap();
+    conn.send_hello(TIMEOUT).unwrap();
+    let rule = MatchRule::new()
+        .msg_type(MessageType::Signal)
+        .interface("io.killing.spark.monitortest");
+    let mut monitor = conn.become_monitor(&[rule], TIMEOUT).unwrap();
+
+    // the signal is not addressed to the monitor and nobody subscribed to it, it is still copied to the monitor
+    let mut sender = RpcConn::session_conn(TIMEOUT).unwrap();
+    let mut signal = MessageBuilder::new()
+        .signal("io.killing.spark.monitortest", "Test", "/io/killing/spark")
+        .build();
+    signal.body.push_param("observed").unwrap();
+    sender
+        .send_message(&mut signal)
+        .unwrap()
+        .write_all()
+        .map_err(force_finish_on_error)
+        .unwrap();
+
+    let msg = loop {
+        let msg = monitor.get_next_message(TIMEOUT).unwrap();
+        if msg.interface() == Some("io.killing.spark.monitortest") {
+            break msg;
+        }
+    };
+    assert_eq!(msg.member(), Some("Test"));
+    assert!(msg.sender().unwrap().starts_with(':'));
+    assert_eq!(msg.body.get_first::<&str>().unwrap(), "observed");
+}
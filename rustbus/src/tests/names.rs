@@ -0,0 +1,151 @@
+use crate::connection::rpc_conn::RpcConn;
+use crate::standard_messages::{
+    release_name, request_name, ReleaseNameReply, RequestNameFlags, RequestNameReply,
+};
+use crate::test_fixtures::TIMEOUT;
+use crate::wire::errors::UnmarshalError;
+use std::convert::TryFrom;
+
+const NAME: &str = "io.killing.spark.nametest";
+
+fn request(rpc_con: &mut RpcConn, flags: RequestNameFlags) -> RequestNameReply {
+    let reply = rpc_con
+        .send_and_wait_response(&mut request_name(NAME, flags), None)
+        .unwrap();
+    reply.body.parser().get().unwrap()
+}
+
+fn release(rpc_con: &mut RpcConn) -> ReleaseNameReply {
+    let reply = rpc_con
+        .send_and_wait_response(&mut release_name(NAME), None)
+        .unwrap();
+    reply.body.parser().get().unwrap()
+}
+
+#[test]
+fn test_name_owner() {
+    use crate::connection::rpc_conn::FilterAction;
+    use crate::standard_messages::NameOwnerChanged;
+    use crate::SignalRouter;
+    use std::sync::{Arc, Mutex};
+
+    const OWNER_NAME: &str = "io.killing.spark.ownertest";
+    let mut watcher = RpcConn::session_conn(TIMEOUT).unwrap();
+    let mut owner = RpcConn::session_conn(TIMEOUT).unwrap();
+    let owner_name = owner.unique_name().unwrap().to_owned();
+    assert_eq!(watcher.get_name_owner(OWNER_NAME).unwrap(), None);
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut router = SignalRouter::new();
+    let handler_seen = seen.clone();
+    watcher
+        .watch_name_owner(&mut router, Some(OWNER_NAME), move |changed| {
+            handler_seen.lock().unwrap().push(changed);
+            FilterAction::Consumed
+        })
+        .unwrap();
+    watcher.add_filter(move |msg| router.dispatch(msg));
+
+    let wait_for_change = |watcher: &mut RpcConn| loop {
+        if let Some(changed) = seen.lock().unwrap().pop() {
+            return changed;
+        }
+        watcher.refill_once(TIMEOUT).unwrap();
+    };
+
+    let reply = owner
+        .send_and_wait_response(
+            &mut request_name(OWNER_NAME, RequestNameFlags::DO_NOT_QUEUE),
+            None,
+        )
+        .unwrap();
+    assert_eq!(
+        reply.body.parser().get::<RequestNameReply>().unwrap(),
+        RequestNameReply::PrimaryOwner
+    );
+    assert_eq!(
+        wait_for_change(&mut watcher),
+        NameOwnerChanged {
+            name: OWNER_NAME.to_owned(),
+            old_owner: None,
+            new_owner: Some(owner_name.clone()),
+        }
+    );
+    assert_eq!(
+        watcher.get_name_owner(OWNER_NAME).unwrap(),
+        Some(owner_name.clone())
+    );
+
+    drop(owner);
+    assert_eq!(
+        wait_for_change(&mut watcher),
+        NameOwnerChanged {
+            name: OWNER_NAME.to_owned(),
+            old_owner: Some(owner_name),
+            new_owner: None,
+        }
+    );
+    assert_eq!(watcher.get_name_owner(OWNER_NAME).unwrap(), None);
+    // the signals were consumed by the handler, only the NameAcquired for the unique name of the watcher is left
+    while let Some(signal) = watcher.try_get_signal() {
+        assert_eq!(signal.dynheader.member.as_deref(), Some("NameAcquired"));
+    }
+}
+
+#[test]
+fn test_hello_unique_name() {
+    use crate::connection::Error;
+    use crate::DuplexConn;
+
+    let mut rpc_con = RpcConn::session_conn(TIMEOUT).unwrap();
+    let unique_name = rpc_con.unique_name().unwrap().to_owned();
+    assert!(unique_name.starts_with(':'));
+
+    // the bus only accepts one Hello per connection
+    match rpc_con.send_hello(TIMEOUT) {
+        Err(Error::HelloFailed(err)) => assert_eq!(err.name, "org.freedesktop.DBus.Error.Failed"),
+        other => panic!("Expected the second Hello to fail, got {:?}", other),
+    }
+    assert_eq!(rpc_con.unique_name(), Some(unique_name.as_str()));
+
+    // RpcConn::new skips the Hello
+    let addr = crate::get_session_bus_path().unwrap();
+    let mut conn = DuplexConn::connect_to_bus(addr, true).unwrap();
+    assert_eq!(conn.unique_name(), None);
+    let name = conn.send_hello(TIMEOUT).unwrap();
+    assert_ne!(name, unique_name);
+    let rpc_con = RpcConn::new(conn);
+    assert_eq!(rpc_con.unique_name(), Some(name.as_str()));
+    assert_eq!(rpc_con.conn().send.unique_name(), Some(name.as_str()));
+}
+
+#[test]
+fn test_request_release_name() {
+    let mut first = RpcConn::session_conn(TIMEOUT).unwrap();
+    let mut second = RpcConn::session_conn(TIMEOUT).unwrap();
+
+    let flags = RequestNameFlags::ALLOW_REPLACEMENT | RequestNameFlags::DO_NOT_QUEUE;
+    assert!(flags.contains(RequestNameFlags::DO_NOT_QUEUE));
+    assert!(!flags.contains(RequestNameFlags::REPLACE_EXISTING));
+    assert_eq!(flags.bits(), 0b101);
+
+    assert_eq!(request(&mut first, flags), RequestNameReply::PrimaryOwner);
+    assert_eq!(request(&mut first, flags), RequestNameReply::AlreadyOwner);
+    assert_eq!(
+        request(&mut second, RequestNameFlags::DO_NOT_QUEUE),
+        RequestNameReply::Exists
+    );
+    assert_eq!(
+        request(&mut second, RequestNameFlags::REPLACE_EXISTING),
+        RequestNameReply::PrimaryOwner
+    );
+
+    assert_eq!(release(&mut first), ReleaseNameReply::NotOwner);
+    assert_eq!(release(&mut second), ReleaseNameReply::Released);
+    assert_eq!(release(&mut second), ReleaseNameReply::NonExistent);
+
+    assert!(matches!(
+        RequestNameReply::try_from(5),
+        Err(UnmarshalError::UnknownDiscriminant(5))
+    ));
+}
@@ -0,0 +1,87 @@
+use crate::message_builder::MarshalledMessageBody;
+use crate::signature;
+use crate::wire::errors::UnmarshalError;
+use crate::wire::unmarshal::traits::{ArrayIter, Variant};
+use crate::wire::validate_raw::validate_marshalled;
+use crate::ByteOrder;
+
+/// A body with `depth` variants nested into each other around a single byte
+fn nested_variants(depth: usize) -> MarshalledMessageBody {
+    let mut buf = Vec::new();
+    for _ in 1..depth {
+        buf.extend_from_slice(&[1, b'v', 0]);
+    }
+    buf.extend_from_slice(&[1, b'y', 0, 42]);
+    MarshalledMessageBody::from_parts(buf, vec![], "v".to_owned(), ByteOrder::NATIVE)
+}
+
+/// `nested_variants` as the only element of an array
+fn variants_in_array(depth: usize) -> MarshalledMessageBody {
+    let inner = nested_variants(depth).buf;
+    let mut buf = (inner.len() as u32).to_ne_bytes().to_vec();
+    buf.extend_from_slice(&inner);
+    MarshalledMessageBody::from_parts(buf, vec![], "av".to_owned(), ByteOrder::NATIVE)
+}
+
+#[test]
+fn test_nested_variants() {
+    let body = nested_variants(64);
+    assert!(body.parser().get_param().is_ok());
+    assert!(body.parser().get::<Variant>().is_ok());
+    assert!(validate_marshalled(
+        ByteOrder::NATIVE,
+        0,
+        &body.buf,
+        &signature::Type::Container(signature::Container::Variant)
+    )
+    .is_ok());
+
+    // deep enough to overflow the stack without a limit
+    for depth in [65, 100_000] {
+        let body = nested_variants(depth);
+        assert_eq!(
+            body.parser().get_param().unwrap_err(),
+            UnmarshalError::NestingTooDeep
+        );
+        assert_eq!(
+            body.parser().get::<Variant>().unwrap_err(),
+            UnmarshalError::NestingTooDeep
+        );
+        assert_eq!(
+            validate_marshalled(
+                ByteOrder::NATIVE,
+                0,
+                &body.buf,
+                &signature::Type::Container(signature::Container::Variant)
+            )
+            .unwrap_err()
+            .1,
+            UnmarshalError::NestingTooDeep
+        );
+    }
+}
+
+#[test]
+fn test_variants_in_arrays() {
+    // the array counts towards the limit, however the elements are unmarshalled
+    let body = variants_in_array(63);
+    assert!(body.parser().get_param().is_ok());
+    assert!(body.parser().get::<Vec<Variant>>().is_ok());
+    let mut iter = body.parser().get::<ArrayIter<Variant>>().unwrap();
+    assert!(iter.next().unwrap().is_ok());
+
+    let body = variants_in_array(64);
+    assert_eq!(
+        body.parser().get_param().unwrap_err(),
+        UnmarshalError::NestingTooDeep
+    );
+    assert_eq!(
+        body.parser().get::<Vec<Variant>>().unwrap_err(),
+        UnmarshalError::NestingTooDeep
+    );
+    let mut iter = body.parser().get::<ArrayIter<Variant>>().unwrap();
+    assert_eq!(
+        iter.next().unwrap().unwrap_err(),
+        UnmarshalError::NestingTooDeep
+    );
+}
@@ -0,0 +1,30 @@
+use crate::connection::rpc_conn::RpcConn;
+use crate::connection::Timeout;
+use crate::message_builder::MessageType;
+use crate::standard_messages::properties;
+use std::time::Duration;
+
+const BUS: &str = "org.freedesktop.DBus";
+const BUS_PATH: &str = "/org/freedesktop/DBus";
+
+#[test]
+fn test_bus_properties() {
+    let mut rpc_con = RpcConn::session_conn(Timeout::Duration(Duration::from_secs(10))).unwrap();
+
+    let reply = rpc_con
+        .send_and_wait_response(&mut properties::get(BUS, BUS_PATH, BUS, "Interfaces"), None)
+        .unwrap();
+    let interfaces: Vec<String> = properties::parse_get_reply(&reply).unwrap().get().unwrap();
+
+    let reply = rpc_con
+        .send_and_wait_response(&mut properties::get_all(BUS, BUS_PATH, BUS), None)
+        .unwrap();
+    let all = properties::parse_get_all_reply(&reply).unwrap();
+    assert_eq!(all["Interfaces"].get::<Vec<String>>().unwrap(), interfaces);
+    assert!(all.contains_key("Features"));
+
+    // the properties of the bus are read only
+    let mut set = properties::set(BUS, BUS_PATH, BUS, "Features", vec!["nope"]).unwrap();
+    let reply = rpc_con.send_and_wait_response(&mut set, None).unwrap();
+    assert_eq!(reply.typ, MessageType::Error);
+}
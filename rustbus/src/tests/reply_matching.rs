@@ -0,0 +1,175 @@
+use crate::connection::ll_conn::force_finish_on_error;
+use crate::connection::rpc_conn::RpcConn;
+use crate::connection::Error;
+use crate::message_builder::{DynamicHeader, MessageFlags};
+use crate::test_fixtures::{bus_call, TIMEOUT};
+
+#[test]
+fn test_out_of_order_responses() {
+    let mut rpc_con = RpcConn::session_conn(TIMEOUT).unwrap();
+
+    let mut get_id = bus_call("GetId");
+    let id_serial = rpc_con
+        .send_message(&mut get_id)
+        .unwrap()
+        .write_all()
+        .map_err(force_finish_on_error)
+        .unwrap();
+    let mut list_names = bus_call("ListNames");
+    let names_serial = rpc_con
+        .send_message(&mut list_names)
+        .unwrap()
+        .write_all()
+        .map_err(force_finish_on_error)
+        .unwrap();
+
+    // wait for the second call first, the first response needs to be kept around
+    let names = rpc_con.wait_response(names_serial, TIMEOUT).unwrap();
+    assert_eq!(names.dynheader.response_serial, Some(names_serial));
+    let names: Vec<String> = names.body.parser().get().unwrap();
+    assert!(names.iter().any(|name| name == "org.freedesktop.DBus"));
+
+    let id = rpc_con.wait_response(id_serial, TIMEOUT).unwrap();
+    assert_eq!(id.dynheader.response_serial, Some(id_serial));
+    let _id: &str = id.body.parser().get().unwrap();
+
+    // a response for a call we never sent is surfaced separately. Send one to ourselves.
+    let hdr = DynamicHeader {
+        sender: Some(unique_name(&mut rpc_con)),
+        serial: Some(u32::MAX),
+        ..Default::default()
+    };
+    rpc_con
+        .send_message(&mut hdr.make_response())
+        .unwrap()
+        .write_all()
+        .map_err(force_finish_on_error)
+        .unwrap();
+    rpc_con.refill_once(TIMEOUT).unwrap();
+    let unmatched = rpc_con.try_get_unmatched_response().unwrap();
+    assert_eq!(unmatched.dynheader.response_serial, Some(u32::MAX));
+    assert!(rpc_con.try_get_unmatched_response().is_none());
+
+    // only the newest unmatched responses are kept
+    rpc_con.set_max_unmatched_responses(2);
+    for serial in [u32::MAX - 2, u32::MAX - 1, u32::MAX] {
+        let hdr = DynamicHeader {
+            serial: Some(serial),
+            ..hdr.clone()
+        };
+        rpc_con
+            .send_message(&mut hdr.make_response())
+            .unwrap()
+            .write_all()
+            .map_err(force_finish_on_error)
+            .unwrap();
+    }
+    while rpc_con.try_get_response(u32::MAX).is_none() {
+        rpc_con.refill_once(TIMEOUT).unwrap();
+    }
+    let unmatched = rpc_con.try_get_unmatched_response().unwrap();
+    assert_eq!(unmatched.dynheader.response_serial, Some(u32::MAX - 1));
+    assert!(rpc_con.try_get_unmatched_response().is_none());
+}
+
+fn unique_name(rpc_con: &mut RpcConn) -> String {
+    let mut call = bus_call("GetNameOwner");
+    call.body.push_param("org.freedesktop.DBus").unwrap();
+    let bus_name: String = rpc_con
+        .send_and_wait_response(&mut call, None)
+        .unwrap()
+        .body
+        .parser()
+        .get()
+        .unwrap();
+    assert_eq!(bus_name, "org.freedesktop.DBus");
+
+    // the bus sets the sender of all messages we send, our unique name is the destination of its replies
+    let reply = rpc_con
+        .send_and_wait_response(&mut bus_call("GetId"), None)
+        .unwrap();
+    reply.dynheader.destination.unwrap()
+}
+
+#[test]
+fn test_no_reply_expected() {
+    let mut rpc_con = RpcConn::session_conn(TIMEOUT).unwrap();
+
+    let mut fire_and_forget = bus_call("GetId");
+    let serial = rpc_con.send_no_reply(&mut fire_and_forget, None).unwrap();
+    assert!(fire_and_forget
+        .message_flags()
+        .contains(MessageFlags::NO_REPLY_EXPECTED));
+
+    let reply = rpc_con
+        .send_and_wait_response(&mut bus_call("GetId"), None)
+        .unwrap();
+    assert!(reply.body.get_first::<&str>().is_ok());
+    // dbus-daemon answers its own methods even if no reply is expected. Nothing waits for that answer, so it is
+    // treated like a response to an unknown call, which the bus sends in order before the second reply.
+    if let Some(unmatched) = rpc_con.try_get_unmatched_response() {
+        assert_eq!(unmatched.reply_serial(), Some(serial));
+    }
+    assert!(rpc_con.try_get_unmatched_response().is_none());
+
+    match rpc_con.send_and_wait_response(&mut fire_and_forget, None) {
+        Err(Error::NoReplyExpected) => {}
+        other => panic!(
+            "Waiting for a call without reply should fail, got: {:?}",
+            other
+        ),
+    }
+}
+
+#[test]
+fn test_call_method() {
+    let mut rpc_con = RpcConn::session_conn(TIMEOUT).unwrap();
+    rpc_con.set_default_timeout(TIMEOUT);
+    let bus = (
+        "org.freedesktop.DBus",
+        "/org/freedesktop/DBus",
+        "org.freedesktop.DBus",
+    );
+
+    let names = rpc_con
+        .call_method::<Vec<String>, _>(bus.0, bus.1, bus.2, "ListNames", ())
+        .unwrap();
+    assert!(names.iter().any(|name| name == "org.freedesktop.DBus"));
+    let owner: String = rpc_con
+        .call_method(
+            bus.0,
+            bus.1,
+            bus.2,
+            "GetNameOwner",
+            ("org.freedesktop.DBus",),
+        )
+        .unwrap();
+    assert_eq!(owner, "org.freedesktop.DBus");
+
+    let name = "io.killing.spark.callmethod";
+    let reply = rpc_con
+        .call_method::<u32, _>(bus.0, bus.1, bus.2, "RequestName", (name, 0u32))
+        .unwrap();
+    assert_eq!(
+        reply,
+        crate::standard_messages::DBUS_REQUEST_NAME_REPLY_PRIMARY_OWNER
+    );
+    rpc_con
+        .call_method::<(), _>(bus.0, bus.1, bus.2, "ReleaseName", (name,))
+        .unwrap();
+
+    match rpc_con.call_method::<String, _>(bus.0, bus.1, bus.2, "GetNameOwner", (name,)) {
+        Err(Error::ErrorResponse(err)) => {
+            assert_eq!(err.name, "org.freedesktop.DBus.Error.NameHasNoOwner");
+            assert!(err.message.is_some());
+        }
+        other => panic!("Expected the call to fail, got {:?}", other),
+    }
+    match rpc_con.call_method::<u32, _>(bus.0, bus.1, bus.2, "GetId", ()) {
+        Err(Error::UnmarshalError(crate::wire::errors::UnmarshalError::WrongSignature {
+            expected,
+            actual,
+        })) => assert_eq!((expected.as_str(), actual.as_str()), ("u", "s")),
+        other => panic!("Expected a signature mismatch, got {:?}", other),
+    }
+}
@@ -0,0 +1,41 @@
+use crate::connection::get_session_bus_path;
+use crate::connection::ll_conn::{force_finish_on_error, DuplexConn};
+use crate::message_builder::MessageType;
+use crate::test_fixtures::{bus_call, TIMEOUT};
+use std::collections::HashSet;
+
+#[test]
+fn test_into_split() {
+    let mut conn = DuplexConn::connect_to_bus(get_session_bus_path().unwrap(), true).unwrap();
+    conn.send_hello(TIMEOUT).unwrap();
+    let serial_after_hello = conn.send.peek_serial();
+
+    let (mut recv, mut send) = conn.into_split();
+    assert_eq!(send.peek_serial(), serial_after_hello);
+
+    let sender = std::thread::spawn(move || {
+        (0..10)
+            .map(|_| {
+                send.send_message(&bus_call("GetId"))
+                    .unwrap()
+                    .write(TIMEOUT)
+                    .map_err(force_finish_on_error)
+                    .unwrap()
+            })
+            .collect::<HashSet<u32>>()
+    });
+
+    // the bus also sends the NameAcquired signal for the unique name, replies are told apart by their reply serial
+    let mut replied = HashSet::new();
+    while replied.len() < 10 {
+        let msg = recv.get_next_message(TIMEOUT).unwrap();
+        if msg.typ == MessageType::Reply {
+            assert!(msg.body.get_first::<&str>().is_ok());
+            replied.insert(msg.reply_serial().unwrap());
+        }
+    }
+    let sent = sender.join().unwrap();
+    assert_eq!(sent, replied);
+    assert_eq!(sent.len(), 10);
+    assert!(!sent.contains(&0));
+}
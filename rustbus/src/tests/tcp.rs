@@ -0,0 +1,77 @@
+use crate::auth::AuthMechanism;
+use crate::connection::ll_conn::DuplexConn;
+use crate::connection::{BusAddress, Error, Timeout};
+use crate::message_builder::MessageBuilder;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+
+/// Accepts one connection, plays the server side of the handshake and then echoes all bytes back.
+/// Returns the handshake lines it received.
+fn echo_server(listener: TcpListener) -> std::thread::JoinHandle<Vec<String>> {
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+
+        let mut null = [0u8];
+        reader.read_exact(&mut null).unwrap();
+        assert_eq!(null[0], 0);
+
+        let mut received = Vec::new();
+        for response in ["OK 1234deadbeef\r\n", ""] {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            received.push(line.trim_end().to_owned());
+            writer.write_all(response.as_bytes()).unwrap();
+        }
+        std::io::copy(&mut reader, &mut writer).unwrap();
+        received
+    })
+}
+
+#[test]
+fn test_tcp_transport() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = echo_server(listener);
+
+    let addr = BusAddress::Tcp {
+        host: "127.0.0.1".to_owned(),
+        port,
+        family: None,
+    };
+    // unix fds are not negotiated over TCP even if they are requested
+    let mut conn =
+        DuplexConn::connect_to_bus_with_auth(addr, true, &[AuthMechanism::Anonymous]).unwrap();
+    assert!(!conn.send.supports_unix_fds());
+
+    let mut msg = MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    msg.body.push_param2("over tcp", 42u32).unwrap();
+    let serial = conn.send.send_message_write_all(&msg).unwrap();
+
+    let echoed = conn.recv.get_next_message(Timeout::Infinite).unwrap();
+    assert_eq!(echoed.dynheader.serial, Some(serial));
+    assert_eq!(echoed.dynheader.member.as_deref(), Some("TestSignal"));
+    assert_eq!(
+        echoed.body.parser().get2::<&str, u32>().unwrap(),
+        ("over tcp", 42)
+    );
+
+    let mut with_fd = MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    with_fd
+        .body
+        .push_param(crate::wire::UnixFd::new(nix::unistd::dup(0).unwrap()))
+        .unwrap();
+    assert!(matches!(
+        conn.send.send_message(&with_fd),
+        Err(Error::UnixFdsNotSupported)
+    ));
+
+    drop(conn);
+    let received = server.join().unwrap();
+    assert_eq!(received, ["AUTH ANONYMOUS 72757374627573", "BEGIN"]);
+}
@@ -195,6 +195,36 @@ fn verify_array_marshalling() {
     ctx.buf.clear();
 }
 
+#[test]
+fn verify_mixed_array_rejected() {
+    use crate::wire::marshal::MarshalContext;
+
+    let mut fds = Vec::new();
+    let mut valid_buf = Vec::new();
+    let mut ctx = MarshalContext {
+        buf: &mut valid_buf,
+        fds: &mut fds,
+        byteorder: ByteOrder::LittleEndian,
+    };
+    let ctx = &mut ctx;
+
+    // the fields are public so nothing stops anyone from mixing element types
+    let arr = crate::params::Container::Array(crate::params::Array {
+        element_sig: crate::signature::Type::Base(crate::signature::Base::Uint32),
+        values: vec![
+            crate::params::Base::Uint32(1).into(),
+            crate::params::Base::Uint32(2).into(),
+            crate::params::Base::String("3".to_owned()).into(),
+        ],
+    });
+    assert_eq!(
+        marshal_container_param(&arr, ctx),
+        Err(crate::wire::errors::MarshalError::Validation(
+            crate::params::validation::Error::ArrayElementTypesDiffer
+        ))
+    );
+}
+
 #[test]
 fn verify_dict_marshalling() {
     let mut fds = Vec::new();
@@ -254,6 +284,70 @@ fn verify_dict_marshalling() {
     ctx.buf.clear();
 }
 
+#[test]
+fn verify_ordered_dict_marshalling() {
+    use crate::wire::marshal::MarshalContext;
+
+    let mut fds = Vec::new();
+    let mut valid_buf = Vec::new();
+    let mut ctx = MarshalContext {
+        buf: &mut valid_buf,
+        fds: &mut fds,
+        byteorder: ByteOrder::LittleEndian,
+    };
+    let ctx = &mut ctx;
+
+    let mut map: std::collections::HashMap<u32, u8> = std::collections::HashMap::new();
+    for i in 0..16 {
+        map.insert(16 - i, i as u8);
+    }
+    let dict = crate::params::Container::make_dict("u", "y", map.into_iter())
+        .unwrap()
+        .into_ordered();
+    assert_eq!(dict.len(), 16);
+    let mut sig = String::new();
+    dict.make_signature(&mut sig);
+    assert_eq!(sig, "a{uy}");
+
+    marshal_container_param(&dict, ctx).unwrap();
+
+    // The entries must always be sorted by key, no matter what order the HashMap had
+    let mut expected = vec![0u8; 8];
+    for i in 1..=16u32 {
+        if i > 1 {
+            expected.extend_from_slice(&[0, 0, 0]);
+        }
+        expected.extend_from_slice(&i.to_le_bytes());
+        expected.push(16 - i as u8);
+    }
+    let len = (expected.len() - 8) as u32;
+    expected[..4].copy_from_slice(&len.to_le_bytes());
+    assert_eq!(ctx.buf, &expected);
+    ctx.buf.clear();
+
+    // keys of different types are ordered by their type, keys of the same type by their value
+    let mut keys = vec![
+        crate::params::Base::StringRef("b"),
+        crate::params::Base::Uint32(2),
+        crate::params::Base::String("a".to_owned()),
+        crate::params::Base::Byte(200),
+        crate::params::Base::Uint32(1),
+        crate::params::Base::String("b".to_owned()),
+    ];
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            crate::params::Base::Byte(200),
+            crate::params::Base::Uint32(1),
+            crate::params::Base::Uint32(2),
+            crate::params::Base::String("a".to_owned()),
+            crate::params::Base::String("b".to_owned()),
+            crate::params::Base::StringRef("b"),
+        ]
+    );
+}
+
 #[test]
 fn verify_variant_marshalling() {
     let mut fds = Vec::new();
@@ -301,6 +395,7 @@ fn verify_variant_marshalling() {
     let v = crate::params::Variant {
         sig: crate::signature::Type::Base(crate::signature::Base::Byte),
         value: param,
+        cached_sig: None,
     };
 
     v.marshal(ctx).unwrap();
@@ -1,16 +1,31 @@
 //! Everything that deals with converting from/to raw bytes. You probably only need the various wrapper types.
 
+use crate::alloc_prelude::*;
+
 pub mod errors;
+#[cfg(feature = "gvariant")]
+pub mod gvariant;
 pub mod marshal;
+pub mod reassembler;
 pub mod unmarshal;
 pub mod util;
 pub mod validate_raw;
 pub mod variant_macros;
+pub mod writer;
 
 mod wrapper_types;
+#[cfg(feature = "gvariant")]
+pub use gvariant::WireFormat;
+pub use reassembler::MessageReassembler;
+pub use wrapper_types::dict_entry::DictEntry;
+pub use wrapper_types::names::{BusName, InterfaceName, MemberName};
+pub use wrapper_types::time::{Micros, Millis};
+#[cfg(feature = "std")]
+pub use wrapper_types::time::{UnixMicros, UnixMillis};
 pub use wrapper_types::unixfd::UnixFd;
 pub use wrapper_types::ObjectPath;
 pub use wrapper_types::SignatureWrapper;
+pub use writer::MessageWriter;
 
 /// The different header fields a message may or maynot have
 #[derive(Debug)]
@@ -1,3 +1,4 @@
+use crate::alloc_prelude::*;
 use thiserror::Error;
 
 /// Errors that can occur while marshalling a value into a dbus message
@@ -10,11 +11,26 @@ pub enum MarshalError {
     #[error("Tried to marshal an empty UnixFd")]
     EmptyUnixFd,
     /// Error while trying to dup a UnixFd
+    #[cfg(feature = "std")]
     #[error("Error while trying to dup a UnixFd: {0}")]
     DupUnixFd(std::io::ErrorKind),
     /// Errors occuring while validating the input
     #[error("Errors occured while validating: {0}")]
     Validation(#[from] crate::params::validation::Error),
+    /// A value did not have the signature it is required to have
+    #[error("Expected a value with signature {expected} but got {actual}")]
+    SignatureMismatch { expected: String, actual: String },
+    /// A message carries more unix fds than can be passed with one `sendmsg`
+    #[error(
+        "The message carries {count} unix fds but at most {limit} can be sent with one message"
+    )]
+    TooManyFds { count: usize, limit: usize },
+    /// A time value did not fit into the u64 it is marshalled as
+    #[error("A time value did not fit into the u64 it is marshalled as")]
+    TimeOutOfRange,
+    /// The slice a message was marshalled into is too small, the message needs `needed` bytes
+    #[error("The buffer is too small, {needed} bytes are needed")]
+    BufferTooSmall { needed: usize },
 }
 
 //--------
@@ -27,6 +43,20 @@ impl From<crate::signature::Error> for MarshalError {
     }
 }
 
+/// Why the bytes of a string could not be unmarshalled, see `UnmarshalError::InvalidString`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum StringError {
+    /// The string is not valid UTF-8, the bytes up to `valid_up_to` are
+    #[error("it is not valid UTF-8 after byte {valid_up_to}")]
+    InvalidUtf8 { valid_up_to: usize },
+    /// The string contains a NUL byte at this index before its end
+    #[error("it contains a NUL byte at index {0}")]
+    ContainsNul(usize),
+    /// The byte after the string is not the NUL byte that has to terminate it
+    #[error("it is not terminated by a NUL byte")]
+    NotNulTerminated,
+}
+
 /// Errors that can  occur while unmarshaling a value from a dbus message
 #[derive(Debug, PartialEq, Eq, Error)]
 pub enum UnmarshalError {
@@ -49,9 +79,9 @@ pub enum UnmarshalError {
     #[error("A message indicated an invalid message type")]
     InvalidMessageType,
     /// There was a mismatch between expected an encountered signatures
-    /// (e.g. trying to unmarshal a string when there is a u64 in the message)
-    #[error("There was a mismatch between expected an encountered signatures")]
-    WrongSignature,
+    /// (e.g. trying to unmarshal a string when there is a u64 in the message).
+    #[error("Expected a value with signature {expected:?} but found {actual:?}")]
+    WrongSignature { expected: String, actual: String },
     /// Any error encountered while validating input
     #[error("Error encountered while validating input: {0}")]
     Validation(#[from] crate::params::validation::Error),
@@ -61,12 +91,25 @@ pub enum UnmarshalError {
     /// A message contained an invalid header fields
     #[error("A message contained an invalid header fields")]
     InvalidHeaderFields,
+
+    /// The message type requires a header field the message does not have, named like in the spec, e.g. `PATH`
+    #[error("A message is missing the required header field {field}")]
+    MissingHeaderField { field: &'static str },
     /// A message contained unknown header fields
     #[error("A message contained unknown header fields")]
     UnknownHeaderField,
     /// Returned when data is encountered in padding between values. This is a sign of a corrupted message (or a bug in this library)
     #[error("Returned when data is encountered in padding between values. This is a sign of a corrupted message (or a bug in this library)")]
     PaddingContainedData,
+    /// Containers were nested deeper than the spec allows, see `wire::unmarshal::NestingDepth`
+    #[error("Containers were nested deeper than the spec allows")]
+    NestingTooDeep,
+    /// The header announced a message that is longer than the allowed maximum
+    #[error("The message is {len} bytes long which is more than the maximum of {max}")]
+    MessageTooLong { len: usize, max: usize },
+    /// Unmarshalling in strict mode found a padding byte that is not zero
+    #[error("Found a non-zero padding byte at offset {offset}")]
+    NonZeroPadding { offset: usize },
     /// A boolean did contain something other than 0 or 1
     #[error("A boolean did contain something other than 0 or 1")]
     InvalidBoolean,
@@ -79,7 +122,54 @@ pub enum UnmarshalError {
     /// A unix fd member had an index that is bigger than the size of the list of unix fds passed along with the message
     #[error("A unix fd member had an index that is bigger than the size of the list of unix fds passed along with the message")]
     BadFdIndex(usize),
+    /// The unix fds header field announced a different number of fds than were received with the message
+    #[error(
+        "The header announced {declared} unix fds but {received} were received with the message"
+    )]
+    FdCountMismatch { declared: usize, received: usize },
+    /// A unix fd was unmarshalled into an owning type but it has already been taken out of the message
+    #[error("The unix fd with index {0} has already been taken out of the message")]
+    FdAlreadyTaken(usize),
     /// When unmarshalling a Variant and there is not matching variant in the enum that had the unmarshal impl derived
     #[error("When unmarshalling a Variant and there is not matching variant in the enum that had the unmarshal impl derived")]
     NoMatchingVariantFound,
+    /// An array was unmarshalled into a fixed size array but had a different number of elements
+    #[error("Expected an array with {expected} elements but it had {actual} elements")]
+    WrongArrayLength { expected: usize, actual: usize },
+    /// An array was unmarshalled into an `Option` but had more than one element
+    #[error("Expected an array with at most one element for an Option but it had {0} elements")]
+    TooManyElementsForOption(usize),
+    /// An enum with derived discriminants was unmarshalled but the discriminant did not match any of its variants
+    #[error("The discriminant {0} did not match any variant of the enum")]
+    UnknownDiscriminant(u32),
+    /// An enum with derived discriminants was unmarshalled but the variant holds neither a `u` nor a `(u...)` struct
+    #[error("A variant with signature {signature:?} does not hold the discriminant of an enum")]
+    NoDiscriminant { signature: String },
+    /// The signature of a variant does not contain exactly one complete type
+    #[error("The variant signature {signature:?} does not contain exactly one complete type")]
+    VariantSignatureNotSingleType { signature: String },
+    /// A string enum was unmarshalled but the string did not match any of its variants
+    #[error("The string {0:?} did not match any variant of the enum")]
+    UnknownVariant(String),
+    /// A string was unmarshalled into a `char` but did not contain exactly one character
+    #[error("Expected a string with exactly one character but it had {0} characters")]
+    NotOneChar(usize),
+    /// A dict contained the same key more than once
+    #[error("A dict contained the same key more than once")]
+    DuplicateDictKey,
+    /// A time value could not be represented on this platform
+    #[error("A time value could not be represented on this platform")]
+    TimeOutOfRange,
+    /// A value in the GVariant format did not match the size or the framing offsets of its container
+    #[error("A value did not match the size or the framing offsets of its container")]
+    InvalidFraming,
+    /// A string could not be parsed as the network address it was unmarshalled into
+    #[error("The string {0:?} is not a valid network address")]
+    InvalidAddress(String),
+    /// A string, object path or signature did not have the framing the spec requires
+    #[error("Found an invalid string: {0}")]
+    InvalidString(StringError),
+    /// A 0 was received for one of the `NonZero` integer types, the type is named
+    #[error("Received 0 for a {0}, which can not be zero")]
+    ZeroValue(&'static str),
 }
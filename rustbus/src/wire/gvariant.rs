@@ -0,0 +1,854 @@
+//! The GVariant serialization format as an alternative to the dbus wire format, enabled with the `gvariant` feature
+//!
+//! GVariant uses the same type system as dbus, so values are described by the same `signature::Type` and `params::Param`.
+//! It differs in how values are laid out: it is always little endian, has no length prefixes and instead appends framing
+//! offsets to containers that hold values of variable size, see the
+//! [GVariant specification](https://people.gnome.org/~desrt/gvariant-serialisation.pdf).
+//!
+//! ```rust
+//! use rustbus::params::{Base, Container, Param};
+//! use rustbus::wire::WireFormat;
+//!
+//! let param = Param::Container(Container::make_struct2(Base::StringRef("foo"), Base::Int32(-1)));
+//! let mut fds = Vec::new();
+//! let bytes = WireFormat::GVariant.marshal(&param, &mut fds).unwrap();
+//! assert_eq!(bytes, [b'f', b'o', b'o', 0, 0xff, 0xff, 0xff, 0xff, 4]);
+//!
+//! let unmarshalled = WireFormat::GVariant.unmarshal(&param.sig(), &bytes, &fds).unwrap();
+//! assert_eq!(unmarshalled, param);
+//! ```
+
+use crate::alloc_prelude::*;
+use core::convert::TryFrom;
+
+use crate::params::{self, Base, Container, Param};
+use crate::signature;
+use crate::wire::errors::{MarshalError, StringError, UnmarshalError};
+use crate::wire::marshal::MarshalContext;
+use crate::wire::unmarshal::container::unmarshal_with_sig;
+use crate::wire::unmarshal::{NestingDepth, UnmarshalContext};
+use crate::wire::util::{marshal_unixfd, write_u16, write_u32, write_u64};
+use crate::wire::UnixFd;
+use crate::ByteOrder;
+
+/// The encodings a value can be marshalled in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireFormat {
+    /// The dbus wire format, always marshalled in little endian
+    DBus,
+    /// The GVariant serialization format, which is always little endian
+    GVariant,
+}
+
+impl WireFormat {
+    /// Marshal `param` into a new buffer. Unix fds are dup()ed into `fds` and referenced by their index, like they are
+    /// in dbus messages.
+    pub fn marshal(self, param: &Param, fds: &mut Vec<UnixFd>) -> Result<Vec<u8>, MarshalError> {
+        match self {
+            WireFormat::DBus => {
+                let mut buf = Vec::new();
+                let mut ctx = MarshalContext {
+                    buf: &mut buf,
+                    fds,
+                    byteorder: ByteOrder::LittleEndian,
+                };
+                crate::wire::marshal::container::marshal_param(param, &mut ctx)?;
+                Ok(buf)
+            }
+            WireFormat::GVariant => marshal_param(param, fds),
+        }
+    }
+
+    /// Unmarshal a value with the signature `sig` that takes up all of `buf`. Padding must be zero in both formats.
+    pub fn unmarshal(
+        self,
+        sig: &signature::Type,
+        buf: &[u8],
+        fds: &[UnixFd],
+    ) -> Result<Param<'static, 'static>, UnmarshalError> {
+        match self {
+            WireFormat::DBus => {
+                let mut ctx = UnmarshalContext::conformance(fds, buf, ByteOrder::LittleEndian, 0);
+                let (bytes_used, param) = unmarshal_with_sig(sig, &mut ctx)?;
+                if bytes_used != buf.len() {
+                    return Err(UnmarshalError::NotAllBytesUsed);
+                }
+                Ok(param)
+            }
+            WireFormat::GVariant => unmarshal_param(sig, buf, fds),
+        }
+    }
+}
+
+/// Marshal `param` in the GVariant format into a new buffer
+pub fn marshal_param(param: &Param, fds: &mut Vec<UnixFd>) -> Result<Vec<u8>, MarshalError> {
+    let mut buf = Vec::new();
+    let mut ctx = MarshalContext {
+        buf: &mut buf,
+        fds,
+        byteorder: ByteOrder::LittleEndian,
+    };
+    marshal_value(param, &mut ctx)?;
+    Ok(buf)
+}
+
+/// Unmarshal a GVariant serialized value with the signature `sig` that takes up all of `buf`.
+///
+/// Only values in normal form are accepted: padding must be zero and framing offsets must describe the values exactly.
+pub fn unmarshal_param(
+    sig: &signature::Type,
+    buf: &[u8],
+    fds: &[UnixFd],
+) -> Result<Param<'static, 'static>, UnmarshalError> {
+    let mut de = Deserializer {
+        fds,
+        depth: NestingDepth::default(),
+    };
+    de.value(sig, buf)
+}
+
+/// Where a value has to start and how many bytes it needs, if that does not depend on the value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    alignment: usize,
+    fixed_size: Option<usize>,
+}
+
+impl Layout {
+    fn variable(alignment: usize) -> Self {
+        Layout {
+            alignment,
+            fixed_size: None,
+        }
+    }
+}
+
+fn align_up(pos: usize, alignment: usize) -> usize {
+    pos + (alignment - pos % alignment) % alignment
+}
+
+fn base_layout(base: signature::Base) -> Layout {
+    let (alignment, fixed_size) = match base {
+        signature::Base::Byte | signature::Base::Boolean => (1, Some(1)),
+        signature::Base::Int16 | signature::Base::Uint16 => (2, Some(2)),
+        signature::Base::Int32 | signature::Base::Uint32 | signature::Base::UnixFd => (4, Some(4)),
+        signature::Base::Int64 | signature::Base::Uint64 | signature::Base::Double => (8, Some(8)),
+        signature::Base::String | signature::Base::ObjectPath | signature::Base::Signature => {
+            (1, None)
+        }
+    };
+    Layout {
+        alignment,
+        fixed_size,
+    }
+}
+
+/// Structs are aligned to their most aligned field. They only have a fixed size if all fields have one, the size is
+/// then padded to the alignment so they can be put into arrays back to back.
+fn struct_layout(fields: impl IntoIterator<Item = Layout>) -> Layout {
+    let mut alignment = 1;
+    let mut size = Some(0);
+    for field in fields {
+        alignment = usize::max(alignment, field.alignment);
+        size = match (size, field.fixed_size) {
+            (Some(size), Some(field_size)) => Some(align_up(size, field.alignment) + field_size),
+            _ => None,
+        };
+    }
+    Layout {
+        alignment,
+        fixed_size: size.map(|size| align_up(size, alignment)),
+    }
+}
+
+fn dict_entry_layout(key: signature::Base, value: &signature::Type) -> Layout {
+    struct_layout([base_layout(key), type_layout(value)])
+}
+
+fn type_layout(sig: &signature::Type) -> Layout {
+    match sig {
+        signature::Type::Base(base) => base_layout(*base),
+        signature::Type::Container(signature::Container::Array(elem)) => {
+            Layout::variable(type_layout(elem).alignment)
+        }
+        signature::Type::Container(signature::Container::Struct(fields)) => {
+            struct_layout(fields.as_ref().iter().map(type_layout))
+        }
+        signature::Type::Container(signature::Container::Dict(key, value)) => {
+            Layout::variable(dict_entry_layout(*key, value).alignment)
+        }
+        signature::Type::Container(signature::Container::Variant) => Layout::variable(8),
+    }
+}
+
+fn param_layout(param: &Param) -> Layout {
+    match param {
+        Param::Base(base) => type_layout(&base.sig()),
+        Param::Container(container) => match container {
+            Container::Array(array) => Layout::variable(type_layout(&array.element_sig).alignment),
+            Container::ArrayRef(array) => {
+                Layout::variable(type_layout(&array.element_sig).alignment)
+            }
+            Container::Struct(fields) => struct_layout(fields.iter().map(param_layout)),
+            Container::StructRef(fields) => struct_layout(fields.iter().map(param_layout)),
+            Container::Dict(dict) => {
+                Layout::variable(dict_entry_layout(dict.key_sig, &dict.value_sig).alignment)
+            }
+            Container::OrderedDict(dict) => {
+                Layout::variable(dict_entry_layout(dict.key_sig, &dict.value_sig).alignment)
+            }
+            Container::DictRef(dict) => {
+                Layout::variable(dict_entry_layout(dict.key_sig, &dict.value_sig).alignment)
+            }
+            Container::Variant(_) => Layout::variable(8),
+        },
+    }
+}
+
+/// How many bytes each framing offset takes in a container of `container_len` bytes
+fn offset_size(container_len: usize) -> usize {
+    match container_len as u64 {
+        0 => 0,
+        1..=0xff => 1,
+        0x100..=0xffff => 2,
+        0x1_0000..=0xffff_ffff => 4,
+        _ => 8,
+    }
+}
+
+/// Append the framing offsets of a container that started at `start`. The offsets need to be big enough to address the
+/// whole container including themselves.
+fn write_framing_offsets(ends: &[usize], start: usize, buf: &mut Vec<u8>) {
+    if ends.is_empty() {
+        return;
+    }
+    let body_len = buf.len() - start;
+    let size = [1, 2, 4]
+        .iter()
+        .copied()
+        .find(|size| offset_size(body_len + ends.len() * size) == *size)
+        .unwrap_or(8);
+    for end in ends {
+        buf.extend_from_slice(&(*end as u64).to_le_bytes()[..size]);
+    }
+}
+
+fn marshal_value(param: &Param, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+    match param {
+        Param::Base(base) => marshal_base(base, ctx),
+        Param::Container(container) => marshal_container(container, ctx),
+    }
+}
+
+fn marshal_str(s: &str, buf: &mut Vec<u8>) -> Result<(), MarshalError> {
+    if s.contains('\0') {
+        return Err(params::validation::Error::StringContainsNullByte.into());
+    }
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+    Ok(())
+}
+
+fn marshal_object_path(s: &str, buf: &mut Vec<u8>) -> Result<(), MarshalError> {
+    params::validate_object_path(s)?;
+    marshal_str(s, buf)
+}
+
+fn marshal_signature(s: &str, buf: &mut Vec<u8>) -> Result<(), MarshalError> {
+    params::validate_signature(s)?;
+    marshal_str(s, buf)
+}
+
+fn marshal_base(base: &Base, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+    ctx.align_to(type_layout(&base.sig()).alignment);
+    let le = ByteOrder::LittleEndian;
+    match base {
+        Base::Boolean(b) => ctx.buf.push(u8::from(*b)),
+        Base::Byte(b) => ctx.buf.push(*b),
+        Base::Int16(i) => write_u16(*i as u16, le, ctx.buf),
+        Base::Uint16(i) => write_u16(*i, le, ctx.buf),
+        Base::Int32(i) => write_u32(*i as u32, le, ctx.buf),
+        Base::Uint32(i) => write_u32(*i, le, ctx.buf),
+        Base::Int64(i) => write_u64(*i as u64, le, ctx.buf),
+        Base::Uint64(i) => write_u64(*i, le, ctx.buf),
+        Base::Double(bits) => write_u64(*bits, le, ctx.buf),
+        Base::String(s) => marshal_str(s, ctx.buf)?,
+        Base::StringRef(s) => marshal_str(s, ctx.buf)?,
+        Base::StringCow(s) => marshal_str(s, ctx.buf)?,
+        Base::ObjectPath(s) => marshal_object_path(s, ctx.buf)?,
+        Base::ObjectPathRef(s) => marshal_object_path(s, ctx.buf)?,
+        Base::Signature(s) => marshal_signature(s, ctx.buf)?,
+        Base::SignatureRef(s) => marshal_signature(s, ctx.buf)?,
+        Base::UnixFd(fd) => marshal_unixfd(fd, ctx)?,
+    }
+    Ok(())
+}
+
+fn marshal_container(container: &Container, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+    match container {
+        Container::Array(array) => marshal_array(&array.element_sig, &array.values, ctx),
+        Container::ArrayRef(array) => marshal_array(&array.element_sig, array.values, ctx),
+        Container::Struct(fields) => marshal_struct(fields, ctx),
+        Container::StructRef(fields) => marshal_struct(fields, ctx),
+        Container::Dict(dict) => {
+            params::validate_dict(&dict.map, dict.key_sig, &dict.value_sig)?;
+            marshal_dict(dict.key_sig, &dict.value_sig, &dict.map, ctx)
+        }
+        Container::OrderedDict(dict) => {
+            params::validate_dict(&dict.map, dict.key_sig, &dict.value_sig)?;
+            marshal_dict(dict.key_sig, &dict.value_sig, &dict.map, ctx)
+        }
+        Container::DictRef(dict) => {
+            params::validate_dict(dict.map, dict.key_sig, &dict.value_sig)?;
+            marshal_dict(dict.key_sig, &dict.value_sig, dict.map, ctx)
+        }
+        Container::Variant(variant) => marshal_variant(variant, ctx),
+    }
+}
+
+/// Elements of a fixed size are written back to back, otherwise the end of each element is appended as framing offset
+fn marshal_array(
+    element_sig: &signature::Type,
+    values: &[Param],
+    ctx: &mut MarshalContext,
+) -> Result<(), MarshalError> {
+    params::validate_array(values, element_sig)?;
+    let layout = type_layout(element_sig);
+    ctx.align_to(layout.alignment);
+    let start = ctx.buf.len();
+    let mut ends = Vec::new();
+    for value in values {
+        marshal_value(value, ctx)?;
+        if layout.fixed_size.is_none() {
+            ends.push(ctx.buf.len() - start);
+        }
+    }
+    write_framing_offsets(&ends, start, ctx.buf);
+    Ok(())
+}
+
+/// The ends of all fields of variable size but the last one are appended in reverse order, fixed size structs are
+/// padded to their alignment instead
+fn marshal_struct(fields: &[Param], ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+    let layouts = fields.iter().map(param_layout).collect::<Vec<_>>();
+    let layout = struct_layout(layouts.iter().copied());
+    ctx.align_to(layout.alignment);
+    let start = ctx.buf.len();
+    let mut ends = Vec::new();
+    for (idx, (field, field_layout)) in fields.iter().zip(&layouts).enumerate() {
+        marshal_value(field, ctx)?;
+        if idx + 1 < fields.len() && field_layout.fixed_size.is_none() {
+            ends.push(ctx.buf.len() - start);
+        }
+    }
+    if layout.fixed_size.is_some() {
+        ctx.align_to(layout.alignment);
+    } else {
+        ends.reverse();
+        write_framing_offsets(&ends, start, ctx.buf);
+    }
+    Ok(())
+}
+
+/// Dicts are arrays of dict entries, which are laid out like a struct of the key and the value
+fn marshal_dict<'x>(
+    key_sig: signature::Base,
+    value_sig: &signature::Type,
+    dict: impl IntoIterator<Item = (&'x Base<'x>, &'x Param<'x, 'x>)>,
+    ctx: &mut MarshalContext,
+) -> Result<(), MarshalError> {
+    let entry_layout = dict_entry_layout(key_sig, value_sig);
+    let key_is_fixed = base_layout(key_sig).fixed_size.is_some();
+    ctx.align_to(entry_layout.alignment);
+    let start = ctx.buf.len();
+    let mut ends = Vec::new();
+    for (key, value) in dict {
+        ctx.align_to(entry_layout.alignment);
+        let entry_start = ctx.buf.len();
+        marshal_base(key, ctx)?;
+        let key_end = ctx.buf.len() - entry_start;
+        marshal_value(value, ctx)?;
+        if entry_layout.fixed_size.is_some() {
+            ctx.align_to(entry_layout.alignment);
+        } else {
+            if !key_is_fixed {
+                write_framing_offsets(&[key_end], entry_start, ctx.buf);
+            }
+            ends.push(ctx.buf.len() - start);
+        }
+    }
+    write_framing_offsets(&ends, start, ctx.buf);
+    Ok(())
+}
+
+/// The value followed by a zero byte and the signature without a terminating zero
+fn marshal_variant(
+    variant: &params::Variant,
+    ctx: &mut MarshalContext,
+) -> Result<(), MarshalError> {
+    ctx.align_to(8);
+    marshal_value(&variant.value, ctx)?;
+    ctx.buf.push(0);
+    if let Some(sig_str) = &variant.cached_sig {
+        // built from the signature of the value and validated by Variant::with_cached_sig
+        ctx.buf.extend_from_slice(sig_str.as_bytes());
+    } else {
+        let mut sig_str = String::new();
+        variant.sig.to_str(&mut sig_str);
+        params::validate_signature(&sig_str)?;
+        ctx.buf.extend_from_slice(sig_str.as_bytes());
+    }
+    Ok(())
+}
+
+/// Read a little endian framing offset of `size` bytes at `pos`
+fn read_offset(data: &[u8], pos: usize, size: usize) -> Result<usize, UnmarshalError> {
+    let mut bytes = [0u8; 8];
+    bytes[..size].copy_from_slice(&data[pos..pos + size]);
+    usize::try_from(u64::from_le_bytes(bytes)).map_err(|_| UnmarshalError::InvalidFraming)
+}
+
+/// Skip the padding from `pos` to the next multiple of `alignment`, which must not go beyond `limit`
+fn skip_padding(
+    data: &[u8],
+    pos: usize,
+    alignment: usize,
+    limit: usize,
+) -> Result<usize, UnmarshalError> {
+    let padded = align_up(pos, alignment);
+    if padded > limit {
+        return Err(UnmarshalError::InvalidFraming);
+    }
+    check_padding(data, pos, padded)?;
+    Ok(padded)
+}
+
+fn check_padding(data: &[u8], start: usize, end: usize) -> Result<(), UnmarshalError> {
+    match data[start..end].iter().position(|b| *b != 0) {
+        Some(idx) => Err(UnmarshalError::NonZeroPadding {
+            offset: start + idx,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Split the content of an array into its elements
+fn array_elements(element: Layout, data: &[u8]) -> Result<Vec<&[u8]>, UnmarshalError> {
+    if let Some(size) = element.fixed_size {
+        if !data.len().is_multiple_of(size) {
+            return Err(UnmarshalError::InvalidFraming);
+        }
+        return Ok(data.chunks_exact(size).collect());
+    }
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+    let size = offset_size(data.len());
+    // the last offset is the end of the last element and therefore where the offsets start
+    let offsets_start = read_offset(data, data.len() - size, size)?;
+    if offsets_start > data.len() - size || !(data.len() - offsets_start).is_multiple_of(size) {
+        return Err(UnmarshalError::InvalidFraming);
+    }
+    let count = (data.len() - offsets_start) / size;
+    let mut elements = Vec::with_capacity(count);
+    let mut pos = 0;
+    for idx in 0..count {
+        let end = read_offset(data, offsets_start + idx * size, size)?;
+        pos = skip_padding(data, pos, element.alignment, offsets_start)?;
+        if end < pos || end > offsets_start {
+            return Err(UnmarshalError::InvalidFraming);
+        }
+        elements.push(&data[pos..end]);
+        pos = end;
+    }
+    Ok(elements)
+}
+
+/// Split the content of a struct or dict entry into its fields
+fn struct_fields<'d>(fields: &[Layout], data: &'d [u8]) -> Result<Vec<&'d [u8]>, UnmarshalError> {
+    let layout = struct_layout(fields.iter().copied());
+    if let Some(size) = layout.fixed_size {
+        if data.len() < size {
+            return Err(UnmarshalError::NotEnoughBytes);
+        }
+        if data.len() > size {
+            return Err(UnmarshalError::InvalidFraming);
+        }
+    }
+    let size = offset_size(data.len());
+    // framing offsets are read from the back
+    let mut offsets_start = data.len();
+    let mut pos = 0;
+    let mut slices = Vec::with_capacity(fields.len());
+    for (idx, field) in fields.iter().enumerate() {
+        pos = skip_padding(data, pos, field.alignment, offsets_start)?;
+        let end = match field.fixed_size {
+            Some(field_size) => pos + field_size,
+            None if idx + 1 == fields.len() => offsets_start,
+            None => {
+                if offsets_start < pos + size {
+                    return Err(UnmarshalError::InvalidFraming);
+                }
+                offsets_start -= size;
+                read_offset(data, offsets_start, size)?
+            }
+        };
+        if end < pos || end > offsets_start {
+            return Err(UnmarshalError::InvalidFraming);
+        }
+        slices.push(&data[pos..end]);
+        pos = end;
+    }
+    if layout.fixed_size.is_some() {
+        check_padding(data, pos, data.len())?;
+    } else if pos != offsets_start {
+        return Err(UnmarshalError::InvalidFraming);
+    }
+    Ok(slices)
+}
+
+fn fixed<const N: usize>(data: &[u8]) -> Result<[u8; N], UnmarshalError> {
+    if data.len() < N {
+        return Err(UnmarshalError::NotEnoughBytes);
+    }
+    <[u8; N]>::try_from(data).map_err(|_| UnmarshalError::InvalidFraming)
+}
+
+fn unmarshal_str(data: &[u8]) -> Result<&str, UnmarshalError> {
+    // the terminator is part of the framing in GVariant
+    match data.last() {
+        Some(0) => crate::wire::util::str_with_nul(data),
+        _ => Err(UnmarshalError::InvalidFraming),
+    }
+}
+
+struct Deserializer<'fds> {
+    fds: &'fds [UnixFd],
+    depth: NestingDepth,
+}
+
+impl Deserializer<'_> {
+    /// Unmarshal a value that takes up all of `data`
+    fn value(
+        &mut self,
+        sig: &signature::Type,
+        data: &[u8],
+    ) -> Result<Param<'static, 'static>, UnmarshalError> {
+        match sig {
+            signature::Type::Base(base) => Ok(Param::Base(self.base(*base, data)?)),
+            signature::Type::Container(container) => {
+                let outer_depth = self.depth;
+                self.depth = outer_depth.enter(container)?;
+                let res = self.container(container, data);
+                self.depth = outer_depth;
+                Ok(Param::Container(res?))
+            }
+        }
+    }
+
+    // without std `UnixFd` is uninhabited so the clone never returns
+    #[cfg_attr(not(feature = "std"), allow(unreachable_code))]
+    fn base(&self, sig: signature::Base, data: &[u8]) -> Result<Base<'static>, UnmarshalError> {
+        let base = match sig {
+            signature::Base::Byte => Base::Byte(fixed::<1>(data)?[0]),
+            signature::Base::Boolean => match fixed::<1>(data)? {
+                [0] => Base::Boolean(false),
+                [1] => Base::Boolean(true),
+                _ => return Err(UnmarshalError::InvalidBoolean),
+            },
+            signature::Base::Int16 => Base::Int16(i16::from_le_bytes(fixed(data)?)),
+            signature::Base::Uint16 => Base::Uint16(u16::from_le_bytes(fixed(data)?)),
+            signature::Base::Int32 => Base::Int32(i32::from_le_bytes(fixed(data)?)),
+            signature::Base::Uint32 => Base::Uint32(u32::from_le_bytes(fixed(data)?)),
+            signature::Base::Int64 => Base::Int64(i64::from_le_bytes(fixed(data)?)),
+            signature::Base::Uint64 => Base::Uint64(u64::from_le_bytes(fixed(data)?)),
+            signature::Base::Double => Base::Double(u64::from_le_bytes(fixed(data)?)),
+            signature::Base::UnixFd => {
+                let idx = u32::from_le_bytes(fixed(data)?) as usize;
+                match self.fds.get(idx) {
+                    Some(fd) => Base::UnixFd(fd.clone()),
+                    None => return Err(UnmarshalError::BadFdIndex(idx)),
+                }
+            }
+            signature::Base::String => Base::String(unmarshal_str(data)?.to_owned()),
+            signature::Base::ObjectPath => {
+                let string = unmarshal_str(data)?;
+                params::validate_object_path(string)?;
+                Base::ObjectPath(string.to_owned())
+            }
+            signature::Base::Signature => {
+                let string = unmarshal_str(data)?;
+                params::validate_signature(string)?;
+                Base::Signature(string.to_owned())
+            }
+        };
+        Ok(base)
+    }
+
+    fn container(
+        &mut self,
+        sig: &signature::Container,
+        data: &[u8],
+    ) -> Result<Container<'static, 'static>, UnmarshalError> {
+        let container = match sig {
+            signature::Container::Array(element_sig) => {
+                let elements = array_elements(type_layout(element_sig), data)?;
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.value(element_sig, element)?);
+                }
+                Container::Array(params::Array {
+                    element_sig: element_sig.as_ref().clone(),
+                    values,
+                })
+            }
+            signature::Container::Struct(field_sigs) => {
+                let field_sigs = field_sigs.as_ref();
+                let layouts = field_sigs.iter().map(type_layout).collect::<Vec<_>>();
+                let slices = struct_fields(&layouts, data)?;
+                let mut fields = Vec::with_capacity(field_sigs.len());
+                for (field_sig, slice) in field_sigs.iter().zip(slices) {
+                    fields.push(self.value(field_sig, slice)?);
+                }
+                Container::Struct(fields)
+            }
+            signature::Container::Dict(key_sig, value_sig) => {
+                let entry_layout = dict_entry_layout(*key_sig, value_sig);
+                let layouts = [base_layout(*key_sig), type_layout(value_sig)];
+                let mut map = params::DictMap::new();
+                for entry in array_elements(entry_layout, data)? {
+                    let slices = struct_fields(&layouts, entry)?;
+                    let key = self.base(*key_sig, slices[0])?;
+                    let value = self.value(value_sig, slices[1])?;
+                    if map.insert(key, value).is_some() {
+                        return Err(UnmarshalError::DuplicateDictKey);
+                    }
+                }
+                Container::Dict(params::Dict {
+                    key_sig: *key_sig,
+                    value_sig: value_sig.as_ref().clone(),
+                    map,
+                })
+            }
+            signature::Container::Variant => {
+                let separator = data
+                    .iter()
+                    .rposition(|b| *b == 0)
+                    .ok_or(UnmarshalError::InvalidFraming)?;
+                let sig_str = core::str::from_utf8(&data[separator + 1..]).map_err(|e| {
+                    UnmarshalError::InvalidString(StringError::InvalidUtf8 {
+                        valid_up_to: e.valid_up_to(),
+                    })
+                })?;
+                let mut sigs = signature::Type::parse_description(sig_str)?;
+                if sigs.len() != 1 {
+                    return Err(UnmarshalError::VariantSignatureNotSingleType {
+                        signature: sig_str.to_owned(),
+                    });
+                }
+                let sig = sigs.remove(0);
+                let value = self.value(&sig, &data[..separator])?;
+                Container::Variant(Box::new(params::Variant {
+                    sig,
+                    value,
+                    cached_sig: None,
+                }))
+            }
+        };
+        Ok(container)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gvariant_bytes(param: &Param) -> Vec<u8> {
+        marshal_param(param, &mut Vec::new()).unwrap()
+    }
+
+    fn roundtrip(param: &Param) {
+        for format in [WireFormat::DBus, WireFormat::GVariant] {
+            let mut fds = Vec::new();
+            let bytes = format.marshal(param, &mut fds).unwrap();
+            let unmarshalled = format.unmarshal(&param.sig(), &bytes, &fds).unwrap();
+            assert_eq!(&unmarshalled, param, "{:?}", format);
+        }
+    }
+
+    fn string_array(strings: &[&str]) -> Param<'static, 'static> {
+        Param::Container(Container::Array(params::Array {
+            element_sig: signature::Type::Base(signature::Base::String),
+            values: strings
+                .iter()
+                .map(|s| Param::Base(Base::String(s.to_string())))
+                .collect(),
+        }))
+    }
+
+    #[test]
+    fn test_spec_examples() {
+        assert_eq!(
+            gvariant_bytes(&Param::Base(Base::StringRef("hello world"))),
+            b"hello world\0"
+        );
+        assert_eq!(gvariant_bytes(&Param::Base(Base::Boolean(true))), [1]);
+
+        let strings = string_array(&["i", "can", "has", "strings?"]);
+        assert_eq!(
+            gvariant_bytes(&strings),
+            b"i\0can\0has\0strings?\0\x02\x06\x0a\x13"
+        );
+
+        let structs = Container::make_array(
+            "(si)",
+            vec![
+                Param::Container(Container::make_struct2(
+                    Base::StringRef("hi"),
+                    Base::Int32(-2),
+                )),
+                Param::Container(Container::make_struct2(
+                    Base::StringRef("bye"),
+                    Base::Int32(-1),
+                )),
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+        assert_eq!(
+            gvariant_bytes(&Param::Container(structs)),
+            b"hi\0\0\xfe\xff\xff\xff\x03\0\0\0bye\0\xff\xff\xff\xff\x04\x09\x15"
+        );
+
+        // fixed size structs are padded to their alignment and need no framing offsets
+        let fixed = Container::make_array(
+            "(iy)",
+            vec![
+                Param::Container(Container::make_struct2(Base::Int32(1), Base::Byte(2))),
+                Param::Container(Container::make_struct2(Base::Int32(3), Base::Byte(4))),
+            ]
+            .into_iter(),
+        )
+        .unwrap();
+        assert_eq!(
+            gvariant_bytes(&Param::Container(fixed)),
+            [1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0]
+        );
+
+        let variant = Param::Container(Container::make_variant(0x1234u16));
+        assert_eq!(gvariant_bytes(&variant), [0x34, 0x12, 0, b'q']);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        roundtrip(&Param::Base(Base::Double(1.5f64.to_bits())));
+        roundtrip(&string_array(&[]));
+        roundtrip(&string_array(&["", "", ""]));
+        // more than 255 bytes need framing offsets of two bytes
+        let long = "x".repeat(100);
+        roundtrip(&string_array(&[&long, &long, &long]));
+        let longer = "x".repeat(70_000);
+        roundtrip(&string_array(&[&longer, "short"]));
+
+        let mut dict = params::Dict {
+            key_sig: signature::Base::String,
+            value_sig: signature::Type::Container(signature::Container::Variant),
+            map: params::DictMap::new(),
+        };
+        dict.map.insert(
+            Base::String("path".into()),
+            Param::Container(Container::make_variant(Base::ObjectPath("/a/b".into()))),
+        );
+        dict.map.insert(
+            Base::String("nested".into()),
+            Param::Container(Container::make_variant(string_array(&["a", "bc"]))),
+        );
+        dict.map.insert(
+            Base::String("fixed".into()),
+            Param::Container(Container::make_variant(Container::make_struct2(
+                Base::Uint64(u64::MAX),
+                Base::Boolean(false),
+            ))),
+        );
+        let dict = Param::Container(Container::Dict(dict));
+        roundtrip(&dict);
+
+        let mut fixed_entries = params::Dict {
+            key_sig: signature::Base::Byte,
+            value_sig: signature::Type::Base(signature::Base::Int64),
+            map: params::DictMap::new(),
+        };
+        fixed_entries
+            .map
+            .insert(Base::Byte(1), Param::Base(Base::Int64(-1)));
+        fixed_entries
+            .map
+            .insert(Base::Byte(2), Param::Base(Base::Int64(2)));
+        let fixed_entries = Param::Container(Container::Dict(fixed_entries));
+        assert_eq!(gvariant_bytes(&fixed_entries).len(), 32);
+        roundtrip(&fixed_entries);
+
+        roundtrip(&Param::Container(Container::Struct(vec![
+            Param::Base(Base::Signature("a{sv}".into())),
+            dict,
+            Param::Base(Base::Int16(-7)),
+            string_array(&["last"]),
+        ])));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let sig = |s: &str| signature::Type::parse_description(s).unwrap().remove(0);
+
+        assert_eq!(
+            unmarshal_param(&sig("b"), &[2], &[]),
+            Err(UnmarshalError::InvalidBoolean)
+        );
+        assert_eq!(
+            unmarshal_param(&sig("u"), &[1, 2], &[]),
+            Err(UnmarshalError::NotEnoughBytes)
+        );
+        assert_eq!(
+            unmarshal_param(&sig("s"), b"no nul", &[]),
+            Err(UnmarshalError::InvalidFraming)
+        );
+        assert_eq!(
+            unmarshal_param(&sig("h"), &[0, 0, 0, 0], &[]),
+            Err(UnmarshalError::BadFdIndex(0))
+        );
+        // the framing offset of the string points beyond the struct
+        assert_eq!(
+            unmarshal_param(&sig("(si)"), b"foo\0\xff\xff\xff\xff\x0a", &[]),
+            Err(UnmarshalError::InvalidFraming)
+        );
+        assert_eq!(
+            unmarshal_param(&sig("as"), b"a\0b\0\x02\x09", &[]),
+            Err(UnmarshalError::InvalidFraming)
+        );
+        assert_eq!(
+            unmarshal_param(&sig("ai"), &[1, 0, 0, 0, 2], &[]),
+            Err(UnmarshalError::InvalidFraming)
+        );
+        assert_eq!(
+            unmarshal_param(&sig("(iy)"), &[1, 0, 0, 0, 2, 0, 1, 0], &[]),
+            Err(UnmarshalError::NonZeroPadding { offset: 6 })
+        );
+        assert_eq!(
+            unmarshal_param(&sig("v"), &[1, 0, b'u', b'u'], &[]),
+            Err(UnmarshalError::VariantSignatureNotSingleType {
+                signature: "uu".to_owned(),
+            })
+        );
+
+        // variants in variants are limited like in dbus messages
+        let mut nested = Param::Base(Base::Byte(0));
+        for _ in 0..100 {
+            nested = Param::Container(Container::make_variant(nested));
+        }
+        let bytes = marshal_param(&nested, &mut Vec::new()).unwrap();
+        assert_eq!(
+            unmarshal_param(&sig("v"), &bytes, &[]),
+            Err(UnmarshalError::NestingTooDeep)
+        );
+    }
+}
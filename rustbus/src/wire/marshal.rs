@@ -3,20 +3,28 @@
 //! * `base` and `container` are for the Param approach that map dbus concepts to enums/structs
 //! * `traits` is for the trait based approach
 
+use crate::alloc_prelude::*;
 use crate::message_builder;
 use crate::params;
-use crate::wire::HeaderField;
 use crate::ByteOrder;
 
 use crate::wire::util::*;
 
+mod buffer;
 mod param;
+pub use buffer::{MarshalBuffer, SliceCursor};
 pub use param::base;
 pub use param::container;
 pub mod traits;
 
 type MarshalResult<T> = Result<T, crate::wire::errors::MarshalError>;
 
+/// How many unix fds one message can carry at most. The fds are sent in one control message along with the message
+/// bytes and the linux kernel accepts at most `SCM_MAX_FD` (253) fds per control message.
+pub const MAX_UNIX_FDS: usize = 253;
+
+/// Values are always marshalled into a `Vec`. To write a whole message into a fixed size buffer without allocating,
+/// build it as usual and use `marshal_into_slice`.
 pub struct MarshalContext<'fds, 'buf> {
     pub fds: &'fds mut Vec<crate::wire::UnixFd>,
     pub buf: &'buf mut Vec<u8>,
@@ -28,31 +36,92 @@ impl MarshalContext<'_, '_> {
     pub fn align_to(&mut self, alignment: usize) {
         pad_to_align(alignment, self.buf);
     }
+
+    /// Reserve space for at least `additional` more bytes in the buffer
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.buf.reserve(additional);
+    }
 }
 
 /// This only prepares the header and dynheader fields. To send a message you still need the original message
 /// and use get_buf() to get to the contents
+///
+/// The header is appended to `buf`. Since the header is padded to 8 bytes, headers of multiple messages can be marshalled
+/// into the same buffer one after the other. Headers have to start 8-aligned, if `buf` is not it is padded with zeros
+/// first.
 pub fn marshal(
     msg: &crate::message_builder::MarshalledMessage,
     chosen_serial: u32,
     buf: &mut Vec<u8>,
 ) -> MarshalResult<()> {
+    pad_to_align(8, buf);
+    marshal_padded_header(msg, chosen_serial, buf)
+}
+
+/// Offset of the serial in the fixed part of the header
+const SERIAL_OFFSET: usize = 8;
+
+/// Replace the serial of a message that was already marshalled into `buf`, in the byteorder the header announces.
+///
+/// The serial is a `u32` at a fixed offset, so the length of the body and of the header fields do not change and the
+/// rest of the message stays valid. Dbus messages carry no checksum that would need to be updated.
+pub fn rewrite_serial(
+    buf: &mut [u8],
+    serial: u32,
+) -> Result<(), crate::wire::errors::UnmarshalError> {
+    let (_, header) = crate::wire::unmarshal::unmarshal_header(buf, 0)?;
+    let bytes = match header.byteorder {
+        ByteOrder::LittleEndian => serial.to_le_bytes(),
+        ByteOrder::BigEndian => serial.to_be_bytes(),
+    };
+    buf[SERIAL_OFFSET..SERIAL_OFFSET + 4].copy_from_slice(&bytes);
+    Ok(())
+}
+
+/// Marshal the header and the body of `msg` into `out` without allocating. Returns how many bytes were written.
+///
+/// If `out` is too small `MarshalError::BufferTooSmall` tells how long the whole message is. The content of `out` is
+/// unspecified in that case.
+pub fn marshal_into_slice(
+    msg: &crate::message_builder::MarshalledMessage,
+    chosen_serial: u32,
+    out: &mut [u8],
+) -> MarshalResult<usize> {
+    let mut cursor = SliceCursor::new(out);
+    marshal_padded_header(msg, chosen_serial, &mut cursor)?;
+    cursor.extend_from_slice(msg.get_buf());
+    cursor.finish()
+}
+
+fn marshal_padded_header<B: MarshalBuffer + ?Sized>(
+    msg: &crate::message_builder::MarshalledMessage,
+    chosen_serial: u32,
+    buf: &mut B,
+) -> MarshalResult<()> {
+    if msg.body.raw_fds.len() > MAX_UNIX_FDS {
+        return Err(crate::wire::errors::MarshalError::TooManyFds {
+            count: msg.body.raw_fds.len(),
+            limit: MAX_UNIX_FDS,
+        });
+    }
+
+    let start = buf.len();
+    debug_assert!(start.is_multiple_of(8), "headers must start 8-aligned");
     marshal_header(msg, chosen_serial, buf)?;
     pad_to_align(8, buf);
 
     // set the correct message length
-    insert_u32(
-        msg.body.byteorder,
-        msg.get_buf().len() as u32,
-        &mut buf[4..8],
-    );
+    if let Some(len_bytes) = buf.written_mut(start + 4..start + 8) {
+        insert_u32(msg.body.byteorder, msg.get_buf().len() as u32, len_bytes);
+    }
     Ok(())
 }
 
-fn marshal_header(
+fn marshal_header<B: MarshalBuffer + ?Sized>(
     msg: &crate::message_builder::MarshalledMessage,
     chosen_serial: u32,
-    buf: &mut Vec<u8>,
+    buf: &mut B,
 ) -> MarshalResult<()> {
     let byteorder = msg.body.byteorder;
 
@@ -90,47 +159,69 @@ fn marshal_header(
     let pos = buf.len();
     buf.extend_from_slice(&[0, 0, 0, 0]);
 
-    if let Some(serial) = &msg.dynheader.response_serial {
-        marshal_header_field(byteorder, &HeaderField::ReplySerial(*serial), buf)?;
+    if let Some(serial) = msg.dynheader.response_serial {
+        marshal_header_field(byteorder, HeaderFieldRef::ReplySerial(serial), buf)?;
     }
     if let Some(int) = &msg.dynheader.interface {
-        marshal_header_field(byteorder, &HeaderField::Interface(int.clone()), buf)?;
+        marshal_header_field(byteorder, HeaderFieldRef::Interface(int), buf)?;
     }
     if let Some(dest) = &msg.dynheader.destination {
-        marshal_header_field(byteorder, &HeaderField::Destination(dest.clone()), buf)?;
+        marshal_header_field(byteorder, HeaderFieldRef::Destination(dest), buf)?;
     }
     if let Some(mem) = &msg.dynheader.member {
-        marshal_header_field(byteorder, &HeaderField::Member(mem.clone()), buf)?;
+        marshal_header_field(byteorder, HeaderFieldRef::Member(mem), buf)?;
     }
     if let Some(obj) = &msg.dynheader.object {
-        marshal_header_field(byteorder, &HeaderField::Path(obj.clone()), buf)?;
+        marshal_header_field(byteorder, HeaderFieldRef::Path(obj), buf)?;
+    }
+    if let Some(name) = &msg.dynheader.error_name {
+        marshal_header_field(byteorder, HeaderFieldRef::ErrorName(name), buf)?;
+    }
+    if let Some(sender) = &msg.dynheader.sender {
+        marshal_header_field(byteorder, HeaderFieldRef::Sender(sender), buf)?;
     }
     if !msg.body.raw_fds.is_empty() {
         marshal_header_field(
             byteorder,
-            &HeaderField::UnixFds(msg.body.raw_fds.len() as u32),
+            HeaderFieldRef::UnixFds(msg.body.raw_fds.len() as u32),
             buf,
         )?;
     }
 
-    if !msg.get_buf().is_empty() {
-        let sig_str = msg.get_sig().to_owned();
-        marshal_header_field(byteorder, &HeaderField::Signature(sig_str), buf)?;
+    // the spec allows omitting the signature of a message without arguments, as dbus-daemon and libdbus do. An empty
+    // signature field is only written if the header asks for it, see `CallBuilder::with_empty_signature_field`.
+    if !msg.get_buf().is_empty() || msg.dynheader.signature.as_deref() == Some("") {
+        marshal_header_field(byteorder, HeaderFieldRef::Signature(msg.get_sig()), buf)?;
     }
     let len = buf.len() - pos - 4; // -4 the bytes for the length indicator do not count
-    insert_u32(byteorder, len as u32, &mut buf[pos..pos + 4]);
+    if let Some(len_bytes) = buf.written_mut(pos..pos + 4) {
+        insert_u32(byteorder, len as u32, len_bytes);
+    }
 
     Ok(())
 }
 
-fn marshal_header_field(
+/// The header fields that are marshalled, borrowing the values from the message so they do not need to be copied
+enum HeaderFieldRef<'a> {
+    Path(&'a str),
+    Interface(&'a str),
+    Member(&'a str),
+    ErrorName(&'a str),
+    ReplySerial(u32),
+    Destination(&'a str),
+    Sender(&'a str),
+    Signature(&'a str),
+    UnixFds(u32),
+}
+
+fn marshal_header_field<B: MarshalBuffer + ?Sized>(
     byteorder: ByteOrder,
-    field: &HeaderField,
-    buf: &mut Vec<u8>,
+    field: HeaderFieldRef,
+    buf: &mut B,
 ) -> MarshalResult<()> {
     pad_to_align(8, buf);
     match field {
-        HeaderField::Path(path) => {
+        HeaderFieldRef::Path(path) => {
             params::validate_object_path(path)?;
             buf.push(1);
             buf.push(1);
@@ -139,7 +230,7 @@ fn marshal_header_field(
             pad_to_align(4, buf);
             write_string(path, byteorder, buf);
         }
-        HeaderField::Interface(int) => {
+        HeaderFieldRef::Interface(int) => {
             params::validate_interface(int)?;
             buf.push(2);
             buf.push(1);
@@ -148,7 +239,7 @@ fn marshal_header_field(
             pad_to_align(4, buf);
             write_string(int, byteorder, buf);
         }
-        HeaderField::Member(mem) => {
+        HeaderFieldRef::Member(mem) => {
             params::validate_membername(mem)?;
             buf.push(3);
             buf.push(1);
@@ -157,7 +248,7 @@ fn marshal_header_field(
             pad_to_align(4, buf);
             write_string(mem, byteorder, buf);
         }
-        HeaderField::ErrorName(name) => {
+        HeaderFieldRef::ErrorName(name) => {
             params::validate_errorname(name)?;
             buf.push(4);
             buf.push(1);
@@ -166,15 +257,15 @@ fn marshal_header_field(
             pad_to_align(4, buf);
             write_string(name, byteorder, buf);
         }
-        HeaderField::ReplySerial(rs) => {
+        HeaderFieldRef::ReplySerial(rs) => {
             buf.push(5);
             buf.push(1);
             buf.push(b'u');
             buf.push(0);
             pad_to_align(4, buf);
-            write_u32(*rs, byteorder, buf);
+            write_u32(rs, byteorder, buf);
         }
-        HeaderField::Destination(dest) => {
+        HeaderFieldRef::Destination(dest) => {
             params::validate_busname(dest)?;
             buf.push(6);
             buf.push(1);
@@ -183,32 +274,220 @@ fn marshal_header_field(
             pad_to_align(4, buf);
             write_string(dest, byteorder, buf);
         }
-        HeaderField::Sender(snd) => {
-            params::validate_busname(snd)?;
+        HeaderFieldRef::Sender(sender) => {
+            params::validate_busname(sender)?;
             buf.push(7);
             buf.push(1);
             buf.push(b's');
             buf.push(0);
             pad_to_align(4, buf);
-            write_string(snd, byteorder, buf);
+            write_string(sender, byteorder, buf);
         }
-        HeaderField::Signature(sig) => {
+        HeaderFieldRef::Signature(sig) => {
             params::validate_signature(sig)?;
             buf.push(8);
             buf.push(1);
             buf.push(b'g');
             buf.push(0);
             pad_to_align(4, buf);
-            write_signature(sig, buf);
+            write_signature(sig, buf)?;
         }
-        HeaderField::UnixFds(fds) => {
+        HeaderFieldRef::UnixFds(fds) => {
             buf.push(9);
             buf.push(1);
             buf.push(b'u');
             buf.push(0);
             pad_to_align(4, buf);
-            write_u32(*fds, byteorder, buf);
+            write_u32(fds, byteorder, buf);
         }
     }
     Ok(())
 }
+
+#[test]
+fn test_marshal_into_slice() {
+    let mut msg = crate::message_builder::MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    msg.body.push_param("into a slice").unwrap();
+
+    let mut expected = Vec::new();
+    marshal(&msg, 7, &mut expected).unwrap();
+    expected.extend_from_slice(msg.get_buf());
+
+    let mut out = [0xffu8; 256];
+    let len = marshal_into_slice(&msg, 7, &mut out).unwrap();
+    assert_eq!(out[..len], expected[..]);
+    let mut exact = vec![0xffu8; expected.len()];
+    assert_eq!(marshal_into_slice(&msg, 7, &mut exact), Ok(expected.len()));
+    assert_eq!(exact, expected);
+
+    for short in [0, 10, 16, expected.len() - 1] {
+        let mut out = vec![0xffu8; short];
+        assert_eq!(
+            marshal_into_slice(&msg, 7, &mut out),
+            Err(crate::wire::errors::MarshalError::BufferTooSmall {
+                needed: expected.len()
+            })
+        );
+    }
+}
+
+#[test]
+fn test_marshal_misaligned_buffer() {
+    let msg = crate::message_builder::MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    let mut aligned = Vec::new();
+    marshal(&msg, 7, &mut aligned).unwrap();
+
+    let mut buf = vec![0xff; 3];
+    marshal(&msg, 7, &mut buf).unwrap();
+    assert_eq!(buf[..8], [0xff, 0xff, 0xff, 0, 0, 0, 0, 0]);
+    assert_eq!(buf[8..], aligned[..]);
+    let (_, header) = crate::wire::unmarshal::unmarshal_header(&buf, 8).unwrap();
+    assert_eq!(header.serial, 7);
+}
+
+#[test]
+fn test_rewrite_serial() {
+    for byteorder in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+        let mut msg = crate::message_builder::MessageBuilder::with_byteorder(byteorder)
+            .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+            .build();
+        msg.body.push_param("retransmitted").unwrap();
+
+        let mut bytes = Vec::new();
+        marshal(&msg, 7, &mut bytes).unwrap();
+        bytes.extend_from_slice(msg.get_buf());
+        let original = bytes.clone();
+        rewrite_serial(&mut bytes, 0x0102_0304).unwrap();
+
+        // only the serial changed, so the lengths and header fields of the original are still valid
+        let mut expected = Vec::new();
+        marshal(&msg, 0x0102_0304, &mut expected).unwrap();
+        expected.extend_from_slice(msg.get_buf());
+        assert_eq!(bytes, expected);
+        assert_eq!(bytes[..SERIAL_OFFSET], original[..SERIAL_OFFSET]);
+        assert_eq!(bytes[SERIAL_OFFSET + 4..], original[SERIAL_OFFSET + 4..]);
+        let serial_bytes = match byteorder {
+            ByteOrder::LittleEndian => [4, 3, 2, 1],
+            ByteOrder::BigEndian => [1, 2, 3, 4],
+        };
+        assert_eq!(bytes[SERIAL_OFFSET..SERIAL_OFFSET + 4], serial_bytes);
+        let (_, header) = crate::wire::unmarshal::unmarshal_header(&bytes, 0).unwrap();
+        assert_eq!(header.serial, 0x0102_0304);
+    }
+
+    assert_eq!(
+        rewrite_serial(&mut [b'l', 4, 0, 1], 1),
+        Err(crate::wire::errors::UnmarshalError::NotEnoughBytes)
+    );
+}
+
+#[test]
+fn test_empty_signature_field() {
+    use crate::wire::reassembler::unmarshal_message;
+
+    // the Hello call as dbus-daemon expects it, in the order rustbus writes the header fields
+    let capture = [
+        &b"l\x01\x00\x01\x00\x00\x00\x00\x01\x00\x00\x00\x6e\x00\x00\x00"[..],
+        b"\x02\x01s\x00\x14\x00\x00\x00org.freedesktop.DBus\x00\x00\x00\x00",
+        b"\x06\x01s\x00\x14\x00\x00\x00org.freedesktop.DBus\x00\x00\x00\x00",
+        b"\x03\x01s\x00\x05\x00\x00\x00Hello\x00\x00\x00",
+        b"\x01\x01o\x00\x15\x00\x00\x00/org/freedesktop/DBus\x00\x00\x00",
+    ]
+    .concat();
+    let hello = crate::message_builder::MessageBuilder::with_byteorder(ByteOrder::LittleEndian)
+        .call("Hello")
+        .on("/org/freedesktop/DBus")
+        .with_interface("org.freedesktop.DBus")
+        .at("org.freedesktop.DBus")
+        .build();
+    let mut bytes = Vec::new();
+    marshal(&hello, 1, &mut bytes).unwrap();
+    assert_eq!(bytes, capture);
+
+    // a missing signature is an empty one and stays missing
+    let received = unmarshal_message(capture.clone()).unwrap();
+    assert_eq!(received.dynheader.signature, None);
+    assert_eq!(received.get_sig(), "");
+    assert_eq!(received.unmarshal_all(), Ok(vec![]));
+    let mut bytes = Vec::new();
+    marshal(&received, 1, &mut bytes).unwrap();
+    assert_eq!(bytes, capture);
+
+    // as written by implementations that always include the field
+    let mut with_field = capture.clone();
+    with_field[12] = 0x76;
+    with_field.extend_from_slice(b"\x08\x01g\x00\x00\x00\x00\x00");
+    let hello = crate::message_builder::MessageBuilder::with_byteorder(ByteOrder::LittleEndian)
+        .call("Hello")
+        .on("/org/freedesktop/DBus")
+        .with_interface("org.freedesktop.DBus")
+        .at("org.freedesktop.DBus")
+        .with_empty_signature_field()
+        .build();
+    let mut bytes = Vec::new();
+    marshal(&hello, 1, &mut bytes).unwrap();
+    assert_eq!(bytes, with_field);
+    let received = unmarshal_message(with_field.clone()).unwrap();
+    assert_eq!(received.dynheader.signature.as_deref(), Some(""));
+    let mut bytes = Vec::new();
+    marshal(&received, 1, &mut bytes).unwrap();
+    assert_eq!(bytes, with_field);
+}
+
+#[test]
+fn test_signature_limits() {
+    use crate::signature::Error as SigError;
+    use crate::wire::errors::MarshalError;
+    use crate::wire::util::write_signature;
+
+    let sig_error = |e| {
+        Err(MarshalError::Validation(
+            params::validation::Error::InvalidSignature(e),
+        ))
+    };
+
+    let mut msg = crate::message_builder::MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    for _ in 0..255 {
+        msg.body.push_param(0u8).unwrap();
+    }
+    assert_eq!(msg.get_sig().len(), 255);
+    assert!(marshal(&msg, 1, &mut Vec::new()).is_ok());
+    msg.body.push_param(0u8).unwrap();
+    assert_eq!(
+        marshal(&msg, 1, &mut Vec::new()),
+        sig_error(SigError::SignatureTooLong)
+    );
+
+    let mut buf = Vec::new();
+    assert_eq!(
+        write_signature(&"y".repeat(256), &mut buf),
+        sig_error(SigError::SignatureTooLong)
+    );
+    let nested = |open: &str, close: &str, depth: usize| {
+        format!("{}y{}", open.repeat(depth), close.repeat(depth))
+    };
+    for (open, close) in [("a", ""), ("(", ")"), ("a{y", "}"), ("(a", ")")] {
+        assert_eq!(write_signature(&nested(open, close, 32), &mut buf), Ok(()));
+        assert_eq!(
+            write_signature(&nested(open, close, 33), &mut buf),
+            sig_error(SigError::NestingTooDeep)
+        );
+    }
+    // closed containers do not count towards the depth of the following ones
+    assert_eq!(write_signature(&"aaaay".repeat(50), &mut buf), Ok(()));
+    assert_eq!(write_signature(&"((y))".repeat(50), &mut buf), Ok(()));
+    assert_eq!(write_signature(&"a{sv}".repeat(51), &mut buf), Ok(()));
+
+    // nothing was written for the rejected signatures
+    buf.clear();
+    assert!(write_signature(&nested("a", "", 33), &mut buf).is_err());
+    assert!(buf.is_empty());
+    write_signature("a{sv}", &mut buf).unwrap();
+    assert_eq!(buf, b"\x05a{sv}\0");
+}
@@ -0,0 +1,166 @@
+//! The buffers marshalled bytes can be written to
+//!
+//! Values are marshalled into a `Vec<u8>`. Message headers can also be marshalled into a caller supplied slice with
+//! a `SliceCursor`, so whole messages can be written into fixed size buffers without allocating.
+
+use core::ops::Range;
+
+use crate::alloc_prelude::*;
+use crate::wire::errors::MarshalError;
+
+/// Something marshalled bytes can be appended to
+pub trait MarshalBuffer {
+    /// How many bytes have been written so far
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn push(&mut self, byte: u8);
+    fn extend_from_slice(&mut self, bytes: &[u8]);
+    /// Append `count` zero bytes
+    fn extend_zeros(&mut self, count: usize);
+    /// Get previously written bytes to fill in values that were not known when they were written, like lengths.
+    /// Returns `None` if the bytes were not actually stored.
+    fn written_mut(&mut self, range: Range<usize>) -> Option<&mut [u8]>;
+}
+
+impl MarshalBuffer for Vec<u8> {
+    #[inline]
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+    #[inline]
+    fn push(&mut self, byte: u8) {
+        Vec::push(self, byte)
+    }
+    #[inline]
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        Vec::extend_from_slice(self, bytes)
+    }
+    #[inline]
+    fn extend_zeros(&mut self, count: usize) {
+        self.resize(Vec::len(self) + count, 0);
+    }
+    #[inline]
+    fn written_mut(&mut self, range: Range<usize>) -> Option<&mut [u8]> {
+        self.get_mut(range)
+    }
+}
+
+/// Allows passing `&mut ctx.buf` where a buffer is expected
+impl<B: MarshalBuffer + ?Sized> MarshalBuffer for &mut B {
+    #[inline]
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+    #[inline]
+    fn push(&mut self, byte: u8) {
+        (**self).push(byte)
+    }
+    #[inline]
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        (**self).extend_from_slice(bytes)
+    }
+    #[inline]
+    fn extend_zeros(&mut self, count: usize) {
+        (**self).extend_zeros(count)
+    }
+    #[inline]
+    fn written_mut(&mut self, range: Range<usize>) -> Option<&mut [u8]> {
+        (**self).written_mut(range)
+    }
+}
+
+/// Writes into a caller supplied slice instead of a `Vec`.
+///
+/// Writes that do not fit into the slice are dropped but still counted, so `finish` can tell how big the slice would
+/// have needed to be.
+#[derive(Debug)]
+pub struct SliceCursor<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceCursor<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// Returns how many bytes were written or `MarshalError::BufferTooSmall` if they did not all fit into the slice
+    pub fn finish(self) -> Result<usize, MarshalError> {
+        if self.len > self.buf.len() {
+            Err(MarshalError::BufferTooSmall { needed: self.len })
+        } else {
+            Ok(self.len)
+        }
+    }
+}
+
+impl MarshalBuffer for SliceCursor<'_> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+    #[inline]
+    fn push(&mut self, byte: u8) {
+        if let Some(dst) = self.buf.get_mut(self.len) {
+            *dst = byte;
+        }
+        self.len += 1;
+    }
+    #[inline]
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        let end = self.len + bytes.len();
+        if let Some(dst) = self.buf.get_mut(self.len..end) {
+            dst.copy_from_slice(bytes);
+        }
+        self.len = end;
+    }
+    #[inline]
+    fn extend_zeros(&mut self, count: usize) {
+        // the slice may contain anything, padding has to be zeroed explicitly
+        let end = self.len + count;
+        if let Some(dst) = self.buf.get_mut(self.len..end) {
+            dst.fill(0);
+        }
+        self.len = end;
+    }
+    #[inline]
+    fn written_mut(&mut self, range: Range<usize>) -> Option<&mut [u8]> {
+        self.buf.get_mut(range)
+    }
+}
+
+#[test]
+fn test_slice_cursor() {
+    use crate::wire::util::{pad_to_align, write_string};
+    use crate::ByteOrder;
+
+    let mut vec = Vec::new();
+    vec.push(1);
+    pad_to_align(4, &mut vec);
+    write_string("abc", ByteOrder::LittleEndian, &mut vec);
+    assert_eq!(vec, [1, 0, 0, 0, 3, 0, 0, 0, b'a', b'b', b'c', 0]);
+
+    let mut out = [0xffu8; 12];
+    let mut cursor = SliceCursor::new(&mut out);
+    cursor.push(1);
+    pad_to_align(4, &mut cursor);
+    write_string("abc", ByteOrder::LittleEndian, &mut cursor);
+    assert_eq!(cursor.written_mut(4..5), Some(&mut [3][..]));
+    assert_eq!(cursor.finish(), Ok(12));
+    assert_eq!(out[..], vec[..]);
+
+    // the padding and the string do not fit anymore
+    let mut out = [0xffu8; 6];
+    let mut cursor = SliceCursor::new(&mut out);
+    cursor.push(1);
+    pad_to_align(4, &mut cursor);
+    write_string("abc", ByteOrder::LittleEndian, &mut cursor);
+    assert_eq!(cursor.written_mut(4..8), None);
+    assert_eq!(
+        cursor.finish(),
+        Err(MarshalError::BufferTooSmall { needed: 12 })
+    );
+    assert_eq!(out, [1, 0, 0, 0, 0xff, 0xff]);
+}
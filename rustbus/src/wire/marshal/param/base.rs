@@ -1,5 +1,6 @@
 //! Marshal base params into raw bytes
 
+use crate::alloc_prelude::*;
 use crate::params;
 use crate::wire::errors::MarshalError;
 use crate::wire::marshal::MarshalContext;
@@ -59,8 +60,26 @@ fn marshal_objectpath(
 }
 pub(super) fn marshal_signature(s: &str, buf: &mut Vec<u8>) -> Result<(), MarshalError> {
     params::validate_signature(s)?;
-    write_signature(s, buf);
-    Ok(())
+    write_signature(s, buf)
+}
+
+/// The exact number of bytes `marshal_base_param` writes into a buffer of length `offset`
+pub fn base_param_marshalled_len(p: &params::Base, offset: usize) -> usize {
+    let len = match p {
+        params::Base::Byte(_) => 1,
+        params::Base::Int16(_) | params::Base::Uint16(_) => 2,
+        params::Base::Boolean(_)
+        | params::Base::Int32(_)
+        | params::Base::Uint32(_)
+        | params::Base::UnixFd(_) => 4,
+        params::Base::Int64(_) | params::Base::Uint64(_) | params::Base::Double(_) => 8,
+        params::Base::StringRef(s) | params::Base::ObjectPathRef(s) => 4 + s.len() + 1,
+        params::Base::StringCow(s) => 4 + s.len() + 1,
+        params::Base::String(s) | params::Base::ObjectPath(s) => 4 + s.len() + 1,
+        params::Base::Signature(s) => 1 + s.len() + 1,
+        params::Base::SignatureRef(s) => 1 + s.len() + 1,
+    };
+    padding_for(p.sig().get_alignment(), offset) + len
 }
 
 pub fn marshal_base_param(p: &params::Base, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
@@ -77,6 +96,7 @@ pub fn marshal_base_param(p: &params::Base, ctx: &mut MarshalContext) -> Result<
         params::Base::Uint64(i) => marshal_u64(*i, ctx.byteorder, ctx.buf),
         params::Base::Double(i) => marshal_u64(*i, ctx.byteorder, ctx.buf),
         params::Base::StringRef(s) => marshal_string(s, ctx.byteorder, ctx.buf)?,
+        params::Base::StringCow(s) => marshal_string(s, ctx.byteorder, ctx.buf)?,
         params::Base::String(s) => marshal_string(s, ctx.byteorder, ctx.buf)?,
         params::Base::Signature(s) => marshal_signature(s, ctx.buf)?,
         params::Base::SignatureRef(s) => marshal_signature(s, ctx.buf)?,
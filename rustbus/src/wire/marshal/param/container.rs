@@ -1,5 +1,6 @@
 //! Marshal container params into raw bytes
 
+use crate::alloc_prelude::*;
 use crate::params;
 use crate::signature;
 use crate::wire::errors::MarshalError;
@@ -14,6 +15,14 @@ pub fn marshal_param(p: &params::Param, ctx: &mut MarshalContext) -> Result<(),
     }
 }
 
+/// The exact number of bytes `marshal_param` writes into a buffer of length `offset`
+pub fn param_marshalled_len(p: &params::Param, offset: usize) -> usize {
+    match p {
+        params::Param::Base(b) => base_param_marshalled_len(b, offset),
+        params::Param::Container(c) => container_param_marshalled_len(c, offset),
+    }
+}
+
 fn marshal_array(
     array: &[params::Param],
     sig: &signature::Type,
@@ -28,8 +37,19 @@ fn marshal_array(
     // into the length
     ctx.align_to(sig.get_alignment());
     let content_pos = ctx.buf.len();
-    for p in array {
-        marshal_param(p, ctx)?;
+    if *sig == signature::Type::Base(signature::Base::Byte) {
+        // bytes need no alignment and no dispatch on the type, so write them directly
+        ctx.buf.reserve(array.len());
+        for p in array {
+            match p {
+                params::Param::Base(params::Base::Byte(b)) => ctx.buf.push(*b),
+                _ => marshal_param(p, ctx)?,
+            }
+        }
+    } else {
+        for p in array {
+            marshal_param(p, ctx)?;
+        }
     }
     let len = ctx.buf.len() - content_pos;
     insert_u32(
@@ -49,14 +69,22 @@ fn marshal_struct(params: &[params::Param], ctx: &mut MarshalContext) -> Result<
 }
 
 fn marshal_variant(var: &params::Variant, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
-    let mut sig_str = String::new();
-    var.sig.to_str(&mut sig_str);
-    marshal_signature(&sig_str, ctx.buf)?;
+    if let Some(sig_str) = &var.cached_sig {
+        // built from the signature of the value and validated by Variant::with_cached_sig
+        crate::wire::util::write_signature(sig_str, ctx.buf)?;
+    } else {
+        let mut sig_str = String::new();
+        var.sig.to_str(&mut sig_str);
+        marshal_signature(&sig_str, ctx.buf)?;
+    }
     marshal_param(&var.value, ctx)?;
     Ok(())
 }
 
-fn marshal_dict(dict: &params::DictMap, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+fn marshal_dict<'x>(
+    dict: impl IntoIterator<Item = (&'x params::Base<'x>, &'x params::Param<'x, 'x>)>,
+    ctx: &mut MarshalContext,
+) -> Result<(), MarshalError> {
     ctx.align_to(4);
     let len_pos = ctx.buf.len();
     // placeholder. The lenght will be written here later
@@ -81,6 +109,57 @@ fn marshal_dict(dict: &params::DictMap, ctx: &mut MarshalContext) -> Result<(),
     Ok(())
 }
 
+fn array_marshalled_len(array: &[params::Param], sig: &signature::Type, offset: usize) -> usize {
+    let len_end = offset + padding_for(4, offset) + 4;
+    let content_start = len_end + padding_for(sig.get_alignment(), len_end);
+    let content_end = array
+        .iter()
+        .fold(content_start, |pos, p| pos + param_marshalled_len(p, pos));
+    content_end - offset
+}
+
+fn struct_marshalled_len(params: &[params::Param], offset: usize) -> usize {
+    let start = offset + padding_for(8, offset);
+    let end = params
+        .iter()
+        .fold(start, |pos, p| pos + param_marshalled_len(p, pos));
+    end - offset
+}
+
+fn dict_marshalled_len<'x>(
+    dict: impl IntoIterator<Item = (&'x params::Base<'x>, &'x params::Param<'x, 'x>)>,
+    offset: usize,
+) -> usize {
+    let len_end = offset + padding_for(4, offset) + 4;
+    let content_start = len_end + padding_for(8, len_end);
+    let content_end = dict.into_iter().fold(content_start, |pos, (key, value)| {
+        let pos = pos + padding_for(8, pos);
+        let pos = pos + base_param_marshalled_len(key, pos);
+        pos + param_marshalled_len(value, pos)
+    });
+    content_end - offset
+}
+
+/// The exact number of bytes `marshal_container_param` writes into a buffer of length `offset`
+pub fn container_param_marshalled_len(p: &params::Container, offset: usize) -> usize {
+    match p {
+        params::Container::Array(params) => {
+            array_marshalled_len(&params.values, &params.element_sig, offset)
+        }
+        params::Container::ArrayRef(params) => {
+            array_marshalled_len(params.values, &params.element_sig, offset)
+        }
+        params::Container::Struct(params) => struct_marshalled_len(params, offset),
+        params::Container::StructRef(params) => struct_marshalled_len(params, offset),
+        params::Container::Dict(params) => dict_marshalled_len(&params.map, offset),
+        params::Container::OrderedDict(params) => dict_marshalled_len(&params.map, offset),
+        params::Container::DictRef(params) => dict_marshalled_len(params.map, offset),
+        params::Container::Variant(variant) => {
+            crate::Marshal::marshalled_len_at(&**variant, offset)
+        }
+    }
+}
+
 pub fn marshal_container_param(
     p: &params::Container,
     ctx: &mut MarshalContext,
@@ -104,6 +183,10 @@ pub fn marshal_container_param(
             params::validate_dict(&params.map, params.key_sig, &params.value_sig)?;
             marshal_dict(&params.map, ctx)?;
         }
+        params::Container::OrderedDict(params) => {
+            params::validate_dict(&params.map, params.key_sig, &params.value_sig)?;
+            marshal_dict(&params.map, ctx)?;
+        }
         params::Container::DictRef(params) => {
             params::validate_dict(params.map, params.key_sig, &params.value_sig)?;
             marshal_dict(params.map, ctx)?;
@@ -1,4 +1,5 @@
 //! Marshal trait and implementations for the basic types
+use crate::alloc_prelude::*;
 use crate::wire::marshal::MarshalContext;
 
 mod base;
@@ -62,32 +63,104 @@ pub use container::*;
 /// # Implementing for your own structs
 /// There are some rules you need to follow, or the messages will be malformed:
 /// 1. Structs need to be aligned to 8 bytes. Use `ctx.align_to(8);` to do that. If your type is marshalled as a primitive type
-///     you still need to align to that types alignment.
+///    you still need to align to that types alignment.
 /// 1. If you write your own dict type, you need to align every key-value pair at 8 bytes like a struct
 /// 1. The signature needs to be correct, or the message will be malformed
 /// 1. The alignment must report the correct number. This does not need to be a constant like in the example, but it needs to be consistent with the type
-///     the signature() function returns. If you are not sure, just use Self::signature().get_alignment().
+///    the signature() function returns. If you are not sure, just use Self::signature().get_alignment().
+/// 1. If you override `marshalled_len_at` it needs to return exactly the number of bytes `marshal` writes, padding included.
+///    If you are not sure, keep the default implementation.
 pub trait Marshal: Signature {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), crate::wire::errors::MarshalError>;
+    /// A hint how many bytes marshalling this value will produce. This is used to reserve space in the
+    /// buffer before marshalling. It does not need to be exact and the default implementation returns 0.
+    /// Containers should return the sum of their elements.
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        0
+    }
+    /// The exact number of bytes `marshal` writes into a buffer that already holds `offset` bytes, including the
+    /// padding in front of the value. Unlike `estimate_size` this is not a hint, it must match what `marshal` does.
+    ///
+    /// The default implementation marshals the value into a scratch buffer. The implementations in this crate and the
+    /// ones for derived structs override it and compute the length without writing or allocating anything.
+    /// If marshalling the value fails, the result is meaningless.
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        // padding only depends on the offset modulo the largest alignment
+        let start = offset % 8;
+        let mut buf = vec![0; start];
+        let mut fds = Vec::new();
+        let mut ctx = MarshalContext {
+            buf: &mut buf,
+            fds: &mut fds,
+            byteorder: crate::ByteOrder::LittleEndian,
+        };
+        let _ = self.marshal(&mut ctx);
+        buf.len() - start
+    }
+    /// The exact number of bytes `marshal` writes into an empty buffer, see `marshalled_len_at`
+    ///
+    /// ```rust
+    /// use rustbus::{Marshal, MessageBuilder, Signature};
+    ///
+    /// #[derive(Marshal, Signature)]
+    /// struct Reading {
+    ///     sensor: String,
+    ///     value: f64,
+    /// }
+    ///
+    /// let readings = vec![
+    ///     Reading { sensor: "cpu".to_owned(), value: 42.5 },
+    ///     Reading { sensor: "gpu".to_owned(), value: 61.0 },
+    /// ];
+    /// let mut msg = MessageBuilder::new()
+    ///     .signal("io.killing.spark", "Readings", "/io/killing/spark")
+    ///     .build();
+    /// let len = readings.marshalled_len();
+    /// msg.body.push_param(&readings).unwrap();
+    /// assert_eq!(msg.get_buf().len(), len);
+    /// ```
+    #[inline]
+    fn marshalled_len(&self) -> usize {
+        self.marshalled_len_at(0)
+    }
+    /// Write the value into the zeroed `out`, in the layout it would have at an offset that is a multiple of 8, and
+    /// return the number of bytes written. This is only possible if every value of this type marshals to the same
+    /// number of bytes, marshalling them can not fail and does not need any padding after the value is aligned. This
+    /// is the case for the integer and float types and `bool`, and for tuples of them.
+    ///
+    /// Returns `None` without a meaningful write if the type has no fixed size or `out` is too short for it, the
+    /// caller then marshals the value with `marshal`. The default implementation always returns `None`.
+    ///
+    /// Tuples and arrays of such values compute the padding between their elements once and write the elements with
+    /// `marshal_fixed` into one buffer, instead of aligning and growing the buffer for every single field.
+    #[inline]
+    fn marshal_fixed(&self, byteorder: crate::ByteOrder, out: &mut [u8]) -> Option<usize> {
+        let _ = (byteorder, out);
+        None
+    }
+    /// The exact number of bytes `marshal_as_variant` writes into a buffer that already holds `offset` bytes
+    fn marshalled_len_as_variant(&self, offset: usize) -> usize {
+        let mut sig = SignatureBuffer::new();
+        Self::sig_str(&mut sig);
+        let sig_len = 1 + sig.len() + 1;
+        sig_len + self.marshalled_len_at(offset + sig_len)
+    }
     fn marshal_as_variant(
         &self,
         ctx: &mut MarshalContext,
     ) -> Result<(), crate::wire::errors::MarshalError> {
         let mut sig = SignatureBuffer::new();
         Self::sig_str(&mut sig);
-        if sig.len() > 255 {
-            let sig_err = crate::signature::Error::SignatureTooLong;
-            return Err(sig_err.into());
-        }
+        crate::wire::util::write_signature(&sig, ctx.buf)?;
         debug_assert!(crate::params::validation::validate_signature(&sig).is_ok());
-        crate::wire::util::write_signature(&sig, ctx.buf);
         self.marshal(ctx)
     }
 }
 
 /// `SignatureBuffer` is used to store static or dynamic signatures and avoid allocations if possible.
 /// It is a wrapper around Cow.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SignatureBuffer(Cow<'static, str>);
 
 impl SignatureBuffer {
@@ -153,7 +226,7 @@ impl SignatureBuffer {
         Ok(())
     }
 }
-impl std::ops::Deref for SignatureBuffer {
+impl core::ops::Deref for SignatureBuffer {
     type Target = str;
     #[inline]
     fn deref(&self) -> &Self::Target {
@@ -173,7 +246,7 @@ impl Default for SignatureBuffer {
     }
 }
 
-use std::borrow::Cow;
+use alloc::borrow::Cow;
 pub trait Signature {
     fn signature() -> crate::signature::Type;
     fn alignment() -> usize;
@@ -255,14 +328,27 @@ impl<P: Marshal> Marshal for &P {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), crate::wire::errors::MarshalError> {
         (*self).marshal(ctx)
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        (*self).estimate_size()
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        (*self).marshalled_len_at(offset)
+    }
+    #[inline]
+    fn marshal_fixed(&self, byteorder: crate::ByteOrder, out: &mut [u8]) -> Option<usize> {
+        (*self).marshal_fixed(byteorder, out)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::wire::marshal::MarshalContext;
-    use crate::wire::ObjectPath;
-    use crate::wire::SignatureWrapper;
+    #[cfg(feature = "std")]
+    use crate::wire::{ObjectPath, SignatureWrapper};
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_trait_signature_creation() {
         let mut msg = crate::message_builder::MarshalledMessage::new();
@@ -339,4 +425,201 @@ mod test {
             ]
         )
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_estimate_size() {
+        use crate::wire::marshal::traits::Variant;
+        use crate::Marshal;
+
+        assert_eq!(0u8.estimate_size(), 1);
+        assert_eq!(0u64.estimate_size(), 8);
+        assert_eq!("ABCD".estimate_size(), 9);
+        assert_eq!(ObjectPath::new("/a/b").unwrap().estimate_size(), 9);
+        assert_eq!(SignatureWrapper::new("a{sv}").unwrap().estimate_size(), 7);
+        assert_eq!((1u32, "AB").estimate_size(), 11);
+        assert_eq!(Variant(1u32).estimate_size(), 7);
+        assert_eq!(vec![0u8; 100].estimate_size(), 108);
+        assert_eq!(["A", "B"].estimate_size(), 4 + 8 + 8);
+
+        let mut map = std::collections::HashMap::new();
+        map.insert(1u32, "AB");
+        assert_eq!(map.estimate_size(), 8 + 16);
+
+        // pushing a param reserves the estimated space up front
+        let mut msg = crate::message_builder::MarshalledMessage::new();
+        let strings: Vec<String> = (0..1000).map(|i| format!("{:08}", i)).collect();
+        let estimate = strings.estimate_size();
+        assert_eq!(estimate, 4 + 1000 * 16);
+        msg.body.push_param(&strings).unwrap();
+        assert_eq!(msg.get_buf().len(), estimate - 3);
+        assert!(msg.body.buf.capacity() >= estimate);
+
+        let body = crate::message_builder::MarshalledMessageBody::with_capacity(128);
+        assert!(body.buf.capacity() >= 128);
+    }
+
+    #[cfg(feature = "std")]
+    /// Marshal `value` after `offset` bytes and compare with the computed length
+    fn assert_marshalled_len<T: crate::Marshal>(value: T) {
+        for offset in 0..16 {
+            let mut fds = Vec::new();
+            let mut buf = vec![0; offset];
+            let mut ctx = MarshalContext {
+                fds: &mut fds,
+                buf: &mut buf,
+                byteorder: crate::ByteOrder::BigEndian,
+            };
+            value.marshal(&mut ctx).unwrap();
+            assert_eq!(
+                value.marshalled_len_at(offset),
+                buf.len() - offset,
+                "{} at offset {}",
+                T::signature(),
+                offset
+            );
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_marshalled_len() {
+        use crate::params::{Base, Container, Param};
+        use crate::wire::marshal::traits::Variant;
+        use crate::wire::{DictEntry, InterfaceName, Micros};
+        use std::collections::{BTreeMap, HashMap};
+
+        assert_marshalled_len(true);
+        assert_marshalled_len(1u8);
+        assert_marshalled_len(-1i16);
+        assert_marshalled_len(1u16);
+        assert_marshalled_len(-1i32);
+        assert_marshalled_len(1u32);
+        assert_marshalled_len(-1i64);
+        assert_marshalled_len(1u64);
+        assert_marshalled_len(1.5f64);
+        assert_marshalled_len("");
+        assert_marshalled_len("ABCDE".to_owned());
+        assert_marshalled_len('ä');
+        assert_marshalled_len(ObjectPath::new("/a/b").unwrap());
+        assert_marshalled_len(SignatureWrapper::new("a{sv}").unwrap());
+        assert_marshalled_len(InterfaceName::new("io.killing.spark").unwrap());
+        assert_marshalled_len(Micros(core::time::Duration::from_micros(5)));
+
+        assert_marshalled_len((1u8,));
+        assert_marshalled_len((1u8, 2u64, "A", 3u16));
+        assert_marshalled_len(vec![0u8; 3]);
+        assert_marshalled_len(vec![0u64; 3]);
+        assert_marshalled_len(Vec::<u64>::new());
+        assert_marshalled_len(vec![(1u8, 2u32), (3u8, 4u32)]);
+        assert_marshalled_len(vec![vec![1u64], vec![], vec![2u64, 3]]);
+        assert_marshalled_len(["A", "BC", "DEF"]);
+        assert_marshalled_len(Some((1u8, true)));
+        assert_marshalled_len(None::<u64>);
+        assert_marshalled_len(Variant(1u8));
+        assert_marshalled_len(Variant((1u8, vec![2u64])));
+
+        let mut map = HashMap::new();
+        map.insert("A", Variant(1u8));
+        map.insert("BC", Variant(2u8));
+        assert_marshalled_len(&map);
+        assert_marshalled_len(HashMap::<u8, u8>::new());
+        let mut map = BTreeMap::new();
+        map.insert(1u8, (2u16, "3"));
+        map.insert(4u8, (5u16, "6"));
+        assert_marshalled_len(map);
+        assert_marshalled_len(vec![DictEntry::new(1u8, 2u64), DictEntry::new(3u8, 4u64)]);
+
+        let param = Param::Container(Container::make_struct2(
+            Base::Byte(1),
+            Container::make_variant(
+                Container::make_array("t", vec![Base::Uint64(2)].into_iter()).unwrap(),
+            ),
+        ));
+        assert_marshalled_len(crate::params::Variant::new(param));
+        let mut cached = crate::params::Variant::new(Param::Base(Base::StringRef("A")));
+        cached.cached_sig = Some("s".into());
+        assert_marshalled_len(cached);
+    }
+
+    #[test]
+    fn test_fixed_size_structs() {
+        use crate::wire::errors::MarshalError;
+        use crate::{ByteOrder, Marshal};
+
+        fn fixed_size(value: impl Marshal) -> Option<usize> {
+            value.marshal_fixed(ByteOrder::LittleEndian, &mut [0; 64])
+        }
+        assert_eq!(fixed_size((1u32, 2u64)), Some(16));
+        assert_eq!(fixed_size((1u8, (2u16, 3u64), true)), Some(28));
+        assert_eq!(fixed_size((1u32, "text")), None);
+        assert_eq!(
+            fixed_size((1u64, 2u64, 3u64, 4u64, (5u64, 6u64, 7u64, 8u64, 9u64))),
+            None
+        );
+        // the caller's buffer is too short
+        assert_eq!(
+            (1u32, 2u64).marshal_fixed(ByteOrder::LittleEndian, &mut [0; 15]),
+            None
+        );
+
+        // marshal bit by bit like before fixed sizes existed, the bytes must not change
+        fn field_by_field(
+            values: &[(u8, (u16, u64), bool, f64)],
+            ctx: &mut MarshalContext,
+        ) -> Result<(), MarshalError> {
+            ctx.align_to(4);
+            let size_pos = ctx.buf.len();
+            ctx.buf.extend_from_slice(&[0; 4]);
+            ctx.align_to(8);
+            let start = ctx.buf.len();
+            for (a, (b, c), d, e) in values {
+                ctx.align_to(8);
+                a.marshal(ctx)?;
+                ctx.align_to(8);
+                b.marshal(ctx)?;
+                c.marshal(ctx)?;
+                d.marshal(ctx)?;
+                e.marshal(ctx)?;
+            }
+            let len = (ctx.buf.len() - start) as u32;
+            crate::wire::util::insert_u32(ctx.byteorder, len, &mut ctx.buf[size_pos..]);
+            Ok(())
+        }
+
+        let values: Vec<_> = (0..5u8)
+            .map(|i| {
+                (
+                    i,
+                    (0x0102 * u16::from(i), u64::MAX - u64::from(i)),
+                    i % 2 == 0,
+                    -1.5,
+                )
+            })
+            .collect();
+        for byteorder in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let marshal = |f: &dyn Fn(&mut MarshalContext)| {
+                // start unaligned to check the padding in front of the array
+                let mut buf = vec![0xFF];
+                let mut fds = Vec::new();
+                let mut ctx = MarshalContext {
+                    buf: &mut buf,
+                    fds: &mut fds,
+                    byteorder,
+                };
+                f(&mut ctx);
+                buf
+            };
+            let fixed = marshal(&|ctx| values.marshal(ctx).unwrap());
+            let expected = marshal(&|ctx| field_by_field(&values, ctx).unwrap());
+            assert_eq!(fixed, expected);
+            assert_eq!(fixed.len() - 1, values.marshalled_len_at(1));
+
+            let single = marshal(&|ctx| values[1].marshal(ctx).unwrap());
+            let expected = marshal(&|ctx| field_by_field(&values[1..2], ctx).unwrap());
+            // the struct starts at 8 in both, behind the padding or the array length
+            assert_eq!(single.len(), 8 + 40);
+            assert_eq!(single[8..], expected[8..]);
+        }
+    }
 }
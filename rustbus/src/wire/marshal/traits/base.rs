@@ -1,5 +1,6 @@
 //! This contains the implementations for the `Marshal` trait for base types like integers and strings
 
+use crate::alloc_prelude::*;
 use crate::wire::errors::MarshalError;
 use crate::wire::marshal::traits::SignatureBuffer;
 use crate::wire::marshal::MarshalContext;
@@ -35,6 +36,19 @@ impl Marshal for u64 {
         util::write_u64(*self, ctx.byteorder, ctx.buf);
         Ok(())
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        8
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        util::padding_for(Self::alignment(), offset) + 8
+    }
+    #[inline]
+    fn marshal_fixed(&self, byteorder: crate::ByteOrder, out: &mut [u8]) -> Option<usize> {
+        util::insert_u64(byteorder, *self, out.get_mut(..8)?);
+        Some(8)
+    }
 }
 
 impl Signature for i64 {
@@ -64,6 +78,19 @@ impl Marshal for i64 {
         util::write_u64(*self as u64, ctx.byteorder, ctx.buf);
         Ok(())
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        8
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        util::padding_for(Self::alignment(), offset) + 8
+    }
+    #[inline]
+    fn marshal_fixed(&self, byteorder: crate::ByteOrder, out: &mut [u8]) -> Option<usize> {
+        util::insert_u64(byteorder, *self as u64, out.get_mut(..8)?);
+        Some(8)
+    }
 }
 
 impl Signature for u32 {
@@ -92,6 +119,19 @@ impl Marshal for u32 {
         crate::wire::util::write_u32(*self, ctx.byteorder, ctx.buf);
         Ok(())
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        4
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        util::padding_for(Self::alignment(), offset) + 4
+    }
+    #[inline]
+    fn marshal_fixed(&self, byteorder: crate::ByteOrder, out: &mut [u8]) -> Option<usize> {
+        util::insert_u32(byteorder, *self, out.get_mut(..4)?);
+        Some(4)
+    }
 }
 
 impl Signature for i32 {
@@ -121,6 +161,19 @@ impl Marshal for i32 {
         crate::wire::util::write_u32(*self as u32, ctx.byteorder, ctx.buf);
         Ok(())
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        4
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        util::padding_for(Self::alignment(), offset) + 4
+    }
+    #[inline]
+    fn marshal_fixed(&self, byteorder: crate::ByteOrder, out: &mut [u8]) -> Option<usize> {
+        util::insert_u32(byteorder, *self as u32, out.get_mut(..4)?);
+        Some(4)
+    }
 }
 
 impl Signature for u16 {
@@ -147,6 +200,19 @@ impl Marshal for u16 {
         util::write_u16(*self, ctx.byteorder, ctx.buf);
         Ok(())
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        2
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        util::padding_for(Self::alignment(), offset) + 2
+    }
+    #[inline]
+    fn marshal_fixed(&self, byteorder: crate::ByteOrder, out: &mut [u8]) -> Option<usize> {
+        util::insert_u16(byteorder, *self, out.get_mut(..2)?);
+        Some(2)
+    }
 }
 
 impl Signature for i16 {
@@ -174,6 +240,19 @@ impl Marshal for i16 {
         util::write_u16(*self as u16, ctx.byteorder, ctx.buf);
         Ok(())
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        2
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        util::padding_for(Self::alignment(), offset) + 2
+    }
+    #[inline]
+    fn marshal_fixed(&self, byteorder: crate::ByteOrder, out: &mut [u8]) -> Option<usize> {
+        util::insert_u16(byteorder, *self as u16, out.get_mut(..2)?);
+        Some(2)
+    }
 }
 
 impl Signature for u8 {
@@ -203,6 +282,19 @@ impl Marshal for u8 {
         ctx.buf.push(*self);
         Ok(())
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        1
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        util::padding_for(Self::alignment(), offset) + 1
+    }
+    #[inline]
+    fn marshal_fixed(&self, _: crate::ByteOrder, out: &mut [u8]) -> Option<usize> {
+        *out.first_mut()? = *self;
+        Some(1)
+    }
 }
 
 impl Signature for bool {
@@ -228,6 +320,19 @@ impl Marshal for bool {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
         (*self as u32).marshal(ctx)
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        4
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        util::padding_for(Self::alignment(), offset) + 4
+    }
+    #[inline]
+    fn marshal_fixed(&self, byteorder: crate::ByteOrder, out: &mut [u8]) -> Option<usize> {
+        util::insert_u32(byteorder, *self as u32, out.get_mut(..4)?);
+        Some(4)
+    }
 }
 
 impl Signature for f64 {
@@ -256,6 +361,19 @@ impl Marshal for f64 {
         util::write_u64(self.to_bits(), ctx.byteorder, ctx.buf);
         Ok(())
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        8
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        util::padding_for(Self::alignment(), offset) + 8
+    }
+    #[inline]
+    fn marshal_fixed(&self, byteorder: crate::ByteOrder, out: &mut [u8]) -> Option<usize> {
+        util::insert_u64(byteorder, self.to_bits(), out.get_mut(..8)?);
+        Some(8)
+    }
 }
 
 impl Signature for String {
@@ -280,6 +398,14 @@ impl Marshal for String {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
         self.as_str().marshal(ctx)
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        4 + self.len() + 1
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        self.as_str().marshalled_len_at(offset)
+    }
 }
 
 impl Signature for &str {
@@ -300,12 +426,55 @@ impl Signature for &str {
         String::has_sig(sig)
     }
 }
+/// Strings are written straight from the borrow, pushing a `&str` does not allocate a `String`
 impl Marshal for &str {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
         ctx.align_to(Self::alignment());
         crate::wire::util::write_string(self, ctx.byteorder, ctx.buf);
         Ok(())
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        4 + self.len() + 1
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        util::padding_for(Self::alignment(), offset) + 4 + self.len() + 1
+    }
+}
+
+/// A `char` is marshalled as a string that contains only this character
+impl Signature for char {
+    #[inline]
+    fn signature() -> crate::signature::Type {
+        String::signature()
+    }
+    #[inline]
+    fn alignment() -> usize {
+        String::alignment()
+    }
+    #[inline]
+    fn sig_str(sig: &mut SignatureBuffer) {
+        String::sig_str(sig);
+    }
+    #[inline]
+    fn has_sig(sig: &str) -> bool {
+        String::has_sig(sig)
+    }
+}
+impl Marshal for char {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+        let mut utf8 = [0; 4];
+        (&*self.encode_utf8(&mut utf8)).marshal(ctx)
+    }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        4 + self.len_utf8() + 1
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        util::padding_for(Self::alignment(), offset) + 4 + self.len_utf8() + 1
+    }
 }
 
 impl<S: AsRef<str>> Signature for ObjectPath<S> {
@@ -331,6 +500,14 @@ impl<S: AsRef<str>> Marshal for ObjectPath<S> {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
         self.as_ref().marshal(ctx)
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        self.as_ref().estimate_size()
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        self.as_ref().marshalled_len_at(offset)
+    }
 }
 
 impl<S: AsRef<str>> Signature for SignatureWrapper<S> {
@@ -354,7 +531,14 @@ impl<S: AsRef<str>> Signature for SignatureWrapper<S> {
 impl<S: AsRef<str>> Marshal for SignatureWrapper<S> {
     #[inline]
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
-        crate::wire::util::write_signature(self.as_ref(), ctx.buf);
-        Ok(())
+        crate::wire::util::write_signature(self.as_ref(), ctx.buf)
+    }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        1 + self.as_ref().len() + 1
+    }
+    #[inline]
+    fn marshalled_len_at(&self, _offset: usize) -> usize {
+        1 + self.as_ref().len() + 1
     }
 }
@@ -1,241 +1,161 @@
 //! This contains the implementations for the `Marshal` trait for container types like lists and dicts
 
+use crate::alloc_prelude::*;
 use crate::signature::SignatureIter;
 use crate::wire::errors::MarshalError;
 use crate::wire::marshal::traits::SignatureBuffer;
 use crate::wire::marshal::MarshalContext;
+use crate::wire::util::padding_for;
 use crate::Marshal;
 use crate::Signature;
 
-impl<E: Signature> Signature for (E,) {
-    fn signature() -> crate::signature::Type {
-        crate::signature::Type::Container(crate::signature::Container::Struct(
-            crate::signature::StructTypes::new(vec![E::signature()]).unwrap(),
-        ))
-    }
-    fn alignment() -> usize {
-        8
-    }
-    fn has_sig(sig: &str) -> bool {
-        let Some(sig) = sig.strip_prefix('(') else {
-            return false;
-        };
-        let Some(sig) = sig.strip_suffix(')') else {
-            return false;
-        };
-        let mut iter = SignatureIter::new(sig);
-        let Some(s) = iter.next() else { return false };
-        iter.next().is_none() && E::has_sig(s)
-    }
-}
-impl<E: Marshal> Marshal for (E,) {
-    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
-        // always align to 8
-        ctx.align_to(8);
-        self.0.marshal(ctx)?;
-        Ok(())
-    }
+/// Tuples of fixed size values up to this size are written into a buffer on the stack and appended in one go.
+/// Bigger ones are marshalled field by field.
+const MAX_FIXED_STRUCT_SIZE: usize = 64;
+
+/// Tuples are marshalled as structs, so `(String, u32)` has the signature `(su)`. Structs are always aligned to 8.
+macro_rules! tuple_impls {
+    ($($name:ident $idx:tt),+) => {
+        impl<$($name: Signature),+> Signature for ($($name,)+) {
+            fn signature() -> crate::signature::Type {
+                crate::signature::Type::Container(crate::signature::Container::Struct(
+                    crate::signature::StructTypes::new(vec![$($name::signature()),+]).unwrap(),
+                ))
+            }
+            fn alignment() -> usize {
+                8
+            }
+            fn sig_str(s_buf: &mut SignatureBuffer) {
+                s_buf.push_str("(");
+                $($name::sig_str(s_buf);)+
+                s_buf.push_str(")");
+            }
+            fn has_sig(sig: &str) -> bool {
+                let Some(sig) = sig.strip_prefix('(') else {
+                    return false;
+                };
+                let Some(sig) = sig.strip_suffix(')') else {
+                    return false;
+                };
+                let mut iter = SignatureIter::new(sig);
+                $(
+                    match iter.next() {
+                        Some(s) if $name::has_sig(s) => {}
+                        _ => return false,
+                    }
+                )+
+                iter.next().is_none()
+            }
+        }
+        impl<$($name: Marshal),+> Marshal for ($($name,)+) {
+            fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+                // always align to 8
+                ctx.align_to(8);
+                let mut bytes = [0; MAX_FIXED_STRUCT_SIZE];
+                if let Some(len) = self.marshal_fixed(ctx.byteorder, &mut bytes) {
+                    ctx.buf.extend_from_slice(&bytes[..len]);
+                    return Ok(());
+                }
+                $(self.$idx.marshal(ctx)?;)+
+                Ok(())
+            }
+            /// The padding between the fields only depends on their alignments, since the struct starts 8-aligned
+            #[inline]
+            fn marshal_fixed(&self, byteorder: crate::ByteOrder, out: &mut [u8]) -> Option<usize> {
+                let mut pos = 0;
+                $(
+                    pos += padding_for($name::alignment(), pos);
+                    pos += self.$idx.marshal_fixed(byteorder, out.get_mut(pos..)?)?;
+                )+
+                Some(pos)
+            }
+            #[inline]
+            fn estimate_size(&self) -> usize {
+                0 $(+ self.$idx.estimate_size())+
+            }
+            fn marshalled_len_at(&self, offset: usize) -> usize {
+                let mut pos = offset + padding_for(8, offset);
+                $(pos += self.$idx.marshalled_len_at(pos);)+
+                pos - offset
+            }
+        }
+    };
 }
 
-impl<E1: Signature, E2: Signature> Signature for (E1, E2) {
+tuple_impls!(E1 0);
+tuple_impls!(E1 0, E2 1);
+tuple_impls!(E1 0, E2 1, E3 2);
+tuple_impls!(E1 0, E2 1, E3 2, E4 3);
+tuple_impls!(E1 0, E2 1, E3 2, E4 3, E5 4);
+tuple_impls!(E1 0, E2 1, E3 2, E4 3, E5 4, E6 5);
+tuple_impls!(E1 0, E2 1, E3 2, E4 3, E5 4, E6 5, E7 6);
+tuple_impls!(E1 0, E2 1, E3 2, E4 3, E5 4, E6 5, E7 6, E8 7);
+tuple_impls!(E1 0, E2 1, E3 2, E4 3, E5 4, E6 5, E7 6, E8 7, E9 8);
+tuple_impls!(E1 0, E2 1, E3 2, E4 3, E5 4, E6 5, E7 6, E8 7, E9 8, E10 9);
+tuple_impls!(E1 0, E2 1, E3 2, E4 3, E5 4, E6 5, E7 6, E8 7, E9 8, E10 9, E11 10);
+tuple_impls!(E1 0, E2 1, E3 2, E4 3, E5 4, E6 5, E7 6, E8 7, E9 8, E10 9, E11 10, E12 11);
+
+impl<E: Signature, const N: usize> Signature for [E; N] {
+    #[inline]
     fn signature() -> crate::signature::Type {
-        crate::signature::Type::Container(crate::signature::Container::Struct(
-            crate::signature::StructTypes::new(vec![E1::signature(), E2::signature()]).unwrap(),
-        ))
+        <[E]>::signature()
     }
+    #[inline]
     fn alignment() -> usize {
-        8
+        <[E]>::alignment()
     }
+    #[inline]
     fn sig_str(s_buf: &mut SignatureBuffer) {
-        s_buf.push_str("(");
-        E1::sig_str(s_buf);
-        E2::sig_str(s_buf);
-        s_buf.push_str(")");
+        <[E]>::sig_str(s_buf)
     }
     fn has_sig(sig: &str) -> bool {
-        let Some(sig) = sig.strip_prefix('(') else {
-            return false;
-        };
-        let Some(sig) = sig.strip_suffix(')') else {
-            return false;
-        };
-        let mut iter = SignatureIter::new(sig);
-        let Some(s1) = iter.next() else { return false };
-        let Some(s2) = iter.next() else { return false };
-        iter.next().is_none() && E1::has_sig(s1) && E2::has_sig(s2)
+        <[E]>::has_sig(sig)
     }
 }
-impl<E1: Marshal, E2: Marshal> Marshal for (E1, E2) {
+impl<E: Marshal, const N: usize> Marshal for [E; N] {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
-        // always align to 8
-        ctx.align_to(8);
-        self.0.marshal(ctx)?;
-        self.1.marshal(ctx)?;
-        Ok(())
-    }
-}
-
-impl<E1: Signature, E2: Signature, E3: Signature> Signature for (E1, E2, E3) {
-    fn signature() -> crate::signature::Type {
-        crate::signature::Type::Container(crate::signature::Container::Struct(
-            crate::signature::StructTypes::new(vec![
-                E1::signature(),
-                E2::signature(),
-                E3::signature(),
-            ])
-            .unwrap(),
-        ))
-    }
-    fn alignment() -> usize {
-        8
-    }
-
-    fn sig_str(s_buf: &mut SignatureBuffer) {
-        s_buf.push_str("(");
-        E1::sig_str(s_buf);
-        E2::sig_str(s_buf);
-        E3::sig_str(s_buf);
-        s_buf.push_str(")");
+        self.as_slice().marshal(ctx)
     }
-    fn has_sig(sig: &str) -> bool {
-        let Some(sig) = sig.strip_prefix('(') else {
-            return false;
-        };
-        let Some(sig) = sig.strip_suffix(')') else {
-            return false;
-        };
-        let mut iter = SignatureIter::new(sig);
-        let Some(s1) = iter.next() else { return false };
-        let Some(s2) = iter.next() else { return false };
-        let Some(s3) = iter.next() else { return false };
-        iter.next().is_none() && E1::has_sig(s1) && E2::has_sig(s2) && E3::has_sig(s3)
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        self.as_slice().estimate_size()
     }
-}
-impl<E1: Marshal, E2: Marshal, E3: Marshal> Marshal for (E1, E2, E3) {
-    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
-        // always align to 8
-        ctx.align_to(8);
-        self.0.marshal(ctx)?;
-        self.1.marshal(ctx)?;
-        self.2.marshal(ctx)?;
-        Ok(())
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        self.as_slice().marshalled_len_at(offset)
     }
 }
 
-impl<E1: Signature, E2: Signature, E3: Signature, E4: Signature> Signature for (E1, E2, E3, E4) {
+/// `Option<E>` is marshalled as an array with zero or one element, which is a common idiom for optional values.
+/// The signature is the same as for `[E]`.
+impl<E: Signature> Signature for Option<E> {
+    #[inline]
     fn signature() -> crate::signature::Type {
-        crate::signature::Type::Container(crate::signature::Container::Struct(
-            crate::signature::StructTypes::new(vec![
-                E1::signature(),
-                E2::signature(),
-                E3::signature(),
-                E4::signature(),
-            ])
-            .unwrap(),
-        ))
+        <[E]>::signature()
     }
+    #[inline]
     fn alignment() -> usize {
-        8
+        <[E]>::alignment()
     }
+    #[inline]
     fn sig_str(s_buf: &mut SignatureBuffer) {
-        s_buf.push_str("(");
-        E1::sig_str(s_buf);
-        E2::sig_str(s_buf);
-        E3::sig_str(s_buf);
-        E4::sig_str(s_buf);
-        s_buf.push_str(")");
+        <[E]>::sig_str(s_buf)
     }
     fn has_sig(sig: &str) -> bool {
-        let Some(sig) = sig.strip_prefix('(') else {
-            return false;
-        };
-        let Some(sig) = sig.strip_suffix(')') else {
-            return false;
-        };
-        let mut iter = SignatureIter::new(sig);
-        let Some(s1) = iter.next() else { return false };
-        let Some(s2) = iter.next() else { return false };
-        let Some(s3) = iter.next() else { return false };
-        let Some(s4) = iter.next() else { return false };
-        iter.next().is_none()
-            && E1::has_sig(s1)
-            && E2::has_sig(s2)
-            && E3::has_sig(s3)
-            && E4::has_sig(s4)
+        <[E]>::has_sig(sig)
     }
 }
-impl<E1: Marshal, E2: Marshal, E3: Marshal, E4: Marshal> Marshal for (E1, E2, E3, E4) {
+impl<E: Marshal> Marshal for Option<E> {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
-        // always align to 8
-        ctx.align_to(8);
-        self.0.marshal(ctx)?;
-        self.1.marshal(ctx)?;
-        self.2.marshal(ctx)?;
-        self.3.marshal(ctx)?;
-        Ok(())
-    }
-}
-
-impl<E1: Signature, E2: Signature, E3: Signature, E4: Signature, E5: Signature> Signature
-    for (E1, E2, E3, E4, E5)
-{
-    fn signature() -> crate::signature::Type {
-        crate::signature::Type::Container(crate::signature::Container::Struct(
-            crate::signature::StructTypes::new(vec![
-                E1::signature(),
-                E2::signature(),
-                E3::signature(),
-                E4::signature(),
-                E5::signature(),
-            ])
-            .unwrap(),
-        ))
-    }
-    fn alignment() -> usize {
-        8
-    }
-    fn sig_str(s_buf: &mut SignatureBuffer) {
-        s_buf.push_str("(");
-        E1::sig_str(s_buf);
-        E2::sig_str(s_buf);
-        E3::sig_str(s_buf);
-        E4::sig_str(s_buf);
-        E5::sig_str(s_buf);
-        s_buf.push_str(")");
+        self.as_slice().marshal(ctx)
     }
-    fn has_sig(sig: &str) -> bool {
-        let Some(sig) = sig.strip_prefix('(') else {
-            return false;
-        };
-        let Some(sig) = sig.strip_suffix(')') else {
-            return false;
-        };
-        let mut iter = SignatureIter::new(sig);
-        let Some(s1) = iter.next() else { return false };
-        let Some(s2) = iter.next() else { return false };
-        let Some(s3) = iter.next() else { return false };
-        let Some(s4) = iter.next() else { return false };
-        let Some(s5) = iter.next() else { return false };
-        iter.next().is_none()
-            && E1::has_sig(s1)
-            && E2::has_sig(s2)
-            && E3::has_sig(s3)
-            && E4::has_sig(s4)
-            && E5::has_sig(s5)
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        self.as_slice().estimate_size()
     }
-}
-impl<E1: Marshal, E2: Marshal, E3: Marshal, E4: Marshal, E5: Marshal> Marshal
-    for (E1, E2, E3, E4, E5)
-{
-    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
-        // always align to 8
-        ctx.align_to(8);
-        self.0.marshal(ctx)?;
-        self.1.marshal(ctx)?;
-        self.2.marshal(ctx)?;
-        self.3.marshal(ctx)?;
-        self.4.marshal(ctx)?;
-        Ok(())
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        self.as_slice().marshalled_len_at(offset)
     }
 }
 
@@ -243,6 +163,14 @@ impl<E: Marshal> Marshal for Vec<E> {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
         self.as_slice().marshal(ctx)
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        self.as_slice().estimate_size()
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        self.as_slice().marshalled_len_at(offset)
+    }
 }
 
 impl<E: Signature> Signature for [E] {
@@ -272,6 +200,14 @@ impl<E: Marshal> Marshal for [E] {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
         (&self).marshal(ctx)
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        (&self).estimate_size()
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        (&self).marshalled_len_at(offset)
+    }
 }
 
 impl<E: Signature> Signature for &[E] {
@@ -299,13 +235,13 @@ impl<E: Marshal> Marshal for &[E] {
         let alignment = E::alignment();
         unsafe {
             if E::valid_slice(ctx.byteorder) {
-                debug_assert_eq!(alignment, std::mem::size_of::<E>());
+                debug_assert_eq!(alignment, core::mem::size_of::<E>());
                 let len = alignment * self.len();
                 assert!(len <= u32::MAX as usize);
                 write_u32(len as u32, ctx.byteorder, ctx.buf);
                 ctx.align_to(alignment);
                 let ptr = self.as_ptr().cast::<u8>();
-                let slice = std::slice::from_raw_parts(ptr, len);
+                let slice = core::slice::from_raw_parts(ptr, len);
                 ctx.buf.extend_from_slice(slice);
                 return Ok(());
             }
@@ -320,12 +256,26 @@ impl<E: Marshal> Marshal for &[E] {
             return Ok(());
         }
 
-        // In an array each entry, except the last  will take up at least its alignment in space.
-        // The last may take less (like type '(yy)') but this is small and worth it.
-        ctx.buf.reserve(self.len() * alignment);
         let size_before = ctx.buf.len();
-        for p in self.iter() {
-            p.marshal(ctx)?;
+        let mut first = [0; MAX_FIXED_STRUCT_SIZE];
+        if let Some(size) = self[0].marshal_fixed(ctx.byteorder, &mut first) {
+            // every element but the last is followed by the padding up to the next one
+            let stride = round_up(size, alignment);
+            ctx.buf
+                .resize(size_before + stride * (self.len() - 1) + size, 0);
+            ctx.buf[size_before..size_before + size].copy_from_slice(&first[..size]);
+            let rest = &mut ctx.buf[size_before + stride..];
+            for (p, out) in self[1..].iter().zip(rest.chunks_mut(stride)) {
+                let written = p.marshal_fixed(ctx.byteorder, out);
+                debug_assert_eq!(written, Some(size));
+            }
+        } else {
+            // In an array each entry, except the last  will take up at least its alignment in space.
+            // The last may take less (like type '(yy)') but this is small and worth it.
+            ctx.buf.reserve(self.len() * alignment);
+            for p in self.iter() {
+                p.marshal(ctx)?;
+            }
         }
         let size_of_content = ctx.buf.len() - size_before;
         crate::wire::util::insert_u32(
@@ -336,6 +286,37 @@ impl<E: Marshal> Marshal for &[E] {
 
         Ok(())
     }
+    fn estimate_size(&self) -> usize {
+        unsafe {
+            if E::valid_slice(crate::ByteOrder::NATIVE) {
+                return 8 + core::mem::size_of_val(*self);
+            }
+        }
+        // every element but the last is padded to the alignment of the next one
+        let alignment = E::alignment();
+        4 + self
+            .iter()
+            .map(|e| round_up(e.estimate_size(), alignment))
+            .sum::<usize>()
+    }
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        let len_end = offset + padding_for(4, offset) + 4;
+        let content_start = len_end + padding_for(E::alignment(), len_end);
+        let content_end = unsafe {
+            if E::valid_slice(crate::ByteOrder::NATIVE) {
+                content_start + core::mem::size_of_val(*self)
+            } else {
+                self.iter()
+                    .fold(content_start, |pos, e| pos + e.marshalled_len_at(pos))
+            }
+        };
+        content_end - offset
+    }
+}
+
+#[inline]
+fn round_up(size: usize, alignment: usize) -> usize {
+    size.div_ceil(alignment) * alignment
 }
 
 pub struct Variant<T: Marshal + Signature>(pub T);
@@ -362,9 +343,18 @@ impl<T: Marshal + Signature> Marshal for Variant<T> {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
         self.0.marshal_as_variant(ctx)
     }
+    fn estimate_size(&self) -> usize {
+        let mut sig = SignatureBuffer::new();
+        T::sig_str(&mut sig);
+        1 + sig.len() + 1 + self.0.estimate_size()
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        self.0.marshalled_len_as_variant(offset)
+    }
 }
 
-impl<K: Signature, V: Signature> Signature for std::collections::HashMap<K, V> {
+impl<K: Signature, V: Signature> Signature for alloc::collections::BTreeMap<K, V> {
     fn signature() -> crate::signature::Type {
         let ks = K::signature();
         let vs = V::signature();
@@ -394,38 +384,113 @@ impl<K: Signature, V: Signature> Signature for std::collections::HashMap<K, V> {
     }
 }
 
-impl<K: Marshal, V: Marshal> Marshal for std::collections::HashMap<K, V> {
-    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
-        // always align to 4
-        ctx.align_to(4);
+pub(crate) fn marshal_dict_entries<'a, K, V, I>(
+    entries: I,
+    ctx: &mut MarshalContext,
+) -> Result<(), MarshalError>
+where
+    K: Marshal + 'a,
+    V: Marshal + 'a,
+    I: IntoIterator<Item = (&'a K, &'a V)>,
+{
+    // always align to 4
+    ctx.align_to(4);
 
-        let size_pos = ctx.buf.len();
-        ctx.buf.push(0);
-        ctx.buf.push(0);
-        ctx.buf.push(0);
-        ctx.buf.push(0);
+    let size_pos = ctx.buf.len();
+    ctx.buf.push(0);
+    ctx.buf.push(0);
+    ctx.buf.push(0);
+    ctx.buf.push(0);
 
+    // always align to 8
+    ctx.align_to(8);
+
+    let size_before = ctx.buf.len();
+    for (key, val) in entries {
         // always align to 8
         ctx.align_to(8);
+        key.marshal(ctx)?;
+        val.marshal(ctx)?;
+    }
+    let size_of_content = ctx.buf.len() - size_before;
+    crate::wire::util::insert_u32(
+        ctx.byteorder,
+        size_of_content as u32,
+        &mut ctx.buf[size_pos..size_pos + 4],
+    );
 
-        if self.is_empty() {
-            return Ok(());
-        }
+    Ok(())
+}
 
-        let size_before = ctx.buf.len();
-        for p in self.iter() {
-            // always align to 8
-            ctx.align_to(8);
-            p.0.marshal(ctx)?;
-            p.1.marshal(ctx)?;
-        }
-        let size_of_content = ctx.buf.len() - size_before;
-        crate::wire::util::insert_u32(
-            ctx.byteorder,
-            size_of_content as u32,
-            &mut ctx.buf[size_pos..size_pos + 4],
-        );
+pub(crate) fn dict_entries_marshalled_len<'a, K, V, I>(entries: I, offset: usize) -> usize
+where
+    K: Marshal + 'a,
+    V: Marshal + 'a,
+    I: IntoIterator<Item = (&'a K, &'a V)>,
+{
+    let len_end = offset + padding_for(4, offset) + 4;
+    let content_start = len_end + padding_for(8, len_end);
+    let content_end = entries.into_iter().fold(content_start, |pos, (key, val)| {
+        let pos = pos + padding_for(8, pos);
+        let pos = pos + key.marshalled_len_at(pos);
+        pos + val.marshalled_len_at(pos)
+    });
+    content_end - offset
+}
 
-        Ok(())
+pub(crate) fn estimate_dict_size<'a, K, V, I>(entries: I) -> usize
+where
+    K: Marshal + 'a,
+    V: Marshal + 'a,
+    I: IntoIterator<Item = (&'a K, &'a V)>,
+{
+    8 + entries
+        .into_iter()
+        .map(|(k, v)| round_up(k.estimate_size() + v.estimate_size(), 8))
+        .sum::<usize>()
+}
+
+#[cfg(feature = "std")]
+impl<K: Marshal, V: Marshal> Marshal for std::collections::HashMap<K, V> {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+        marshal_dict_entries(self, ctx)
+    }
+    fn estimate_size(&self) -> usize {
+        estimate_dict_size(self)
+    }
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        dict_entries_marshalled_len(self, offset)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Signature, V: Signature> Signature for std::collections::HashMap<K, V> {
+    #[inline]
+    fn signature() -> crate::signature::Type {
+        <alloc::collections::BTreeMap<K, V>>::signature()
+    }
+    #[inline]
+    fn alignment() -> usize {
+        <alloc::collections::BTreeMap<K, V>>::alignment()
+    }
+    #[inline]
+    fn sig_str(s_buf: &mut SignatureBuffer) {
+        <alloc::collections::BTreeMap<K, V>>::sig_str(s_buf)
+    }
+    fn has_sig(sig: &str) -> bool {
+        <alloc::collections::BTreeMap<K, V>>::has_sig(sig)
+    }
+}
+
+/// Entries are marshalled in the order of their keys, so the output is deterministic
+impl<K: Marshal, V: Marshal> Marshal for alloc::collections::BTreeMap<K, V> {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+        marshal_dict_entries(self, ctx)
+    }
+    fn estimate_size(&self) -> usize {
+        estimate_dict_size(self)
+    }
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        dict_entries_marshalled_len(self, offset)
     }
 }
@@ -0,0 +1,346 @@
+//! Reassemble messages from chunks of bytes as they arrive on a stream
+//!
+//! This does no io by itself, so it can be driven by any kind of stream. Feed the bytes with `push` or `fill_with` and
+//! take the messages with `next_message`. `wire::writer` has the counterpart for sending.
+//!
+//! ```rust
+//! use rustbus::message_builder::MessageBuilder;
+//! use rustbus::wire::{marshal::marshal, MessageReassembler};
+//!
+//! let mut msg = MessageBuilder::new()
+//!     .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+//!     .build();
+//! msg.body.push_param("chunked").unwrap();
+//! let mut bytes = Vec::new();
+//! marshal(&msg, 1, &mut bytes).unwrap();
+//! bytes.extend_from_slice(msg.get_buf());
+//!
+//! let mut reassembler = MessageReassembler::new();
+//! for chunk in bytes.chunks(7) {
+//!     reassembler.push(chunk);
+//! }
+//! let received = reassembler.next_message().unwrap().unwrap();
+//! assert_eq!(received.body.parser().get::<&str>().unwrap(), "chunked");
+//! assert!(reassembler.next_message().unwrap().is_none());
+//! ```
+
+use crate::alloc_prelude::*;
+use alloc::collections::VecDeque;
+use core::convert::TryFrom;
+
+use crate::message_builder::MarshalledMessage;
+use crate::wire::errors::UnmarshalError;
+use crate::wire::unmarshal;
+use crate::wire::UnixFd;
+
+/// Length of the fixed part of the header including the length of the header fields array
+pub const FIXED_HEADER_LEN: usize = unmarshal::HEADER_LEN + 4;
+
+/// The maximum length of a message the spec allows, 128 MiB. This is also what dbus-daemon enforces by default.
+pub const MAX_MESSAGE_SIZE: usize = 1 << 27;
+
+/// Calculate the length of the whole message that starts at the beginning of `buf` from its fixed header.
+///
+/// Returns `Ok(None)` if `buf` does not contain the first `FIXED_HEADER_LEN` bytes yet and
+/// `UnmarshalError::MessageTooLong` if the message would be longer than `MAX_MESSAGE_SIZE`.
+pub fn message_len(buf: &[u8]) -> Result<Option<usize>, UnmarshalError> {
+    message_len_with_limit(buf, MAX_MESSAGE_SIZE)
+}
+
+/// Like `message_len` but with a custom maximum length
+pub fn message_len_with_limit(buf: &[u8], max: usize) -> Result<Option<usize>, UnmarshalError> {
+    if buf.len() < FIXED_HEADER_LEN {
+        return Ok(None);
+    }
+    let (_, header) = unmarshal::unmarshal_header(buf, 0)?;
+    let (_, header_fields_len) =
+        crate::wire::util::parse_u32(&buf[unmarshal::HEADER_LEN..], header.byteorder)?;
+    // calculate with u64 so lengths near u32::MAX can not overflow on 32 bit targets
+    let complete_header_size = FIXED_HEADER_LEN as u64 + u64::from(header_fields_len);
+    // the body starts 8-aligned
+    let padding_between_header_and_body = (8 - complete_header_size % 8) % 8;
+    let len = complete_header_size + padding_between_header_and_body + u64::from(header.body_len);
+
+    if len > max as u64 {
+        return Err(UnmarshalError::MessageTooLong {
+            len: usize::try_from(len).unwrap_or(usize::MAX),
+            max,
+        });
+    }
+    Ok(Some(len as usize))
+}
+
+/// Unmarshal a buffer that contains exactly one complete message
+pub(crate) fn unmarshal_message(buf: Vec<u8>) -> Result<MarshalledMessage, UnmarshalError> {
+    let (hdrbytes, header) = unmarshal::unmarshal_header(&buf, 0)?;
+    let (dynhdrbytes, dynheader) = unmarshal::unmarshal_dynamic_header(&header, &buf, hdrbytes)?;
+
+    let len = buf.len();
+    let (bytes_used, msg) =
+        unmarshal::unmarshal_next_message(&header, dynheader, buf, hdrbytes + dynhdrbytes)?;
+
+    if len != bytes_used + hdrbytes + dynhdrbytes {
+        return Err(UnmarshalError::NotAllBytesUsed);
+    }
+    Ok(msg)
+}
+
+/// Collects bytes read from a stream and yields the messages once they are complete.
+///
+/// Bytes beyond the end of a message are kept for the next one, so chunks do not need to line up with message boundaries.
+/// Unix fds that were received alongside the bytes can be pushed with `push_fds`. They are handed to the messages in
+/// order, each message takes as many as its header announces. The fds of a message are sent with its first byte or
+/// earlier, together with those of other messages, so if fewer fds were pushed than a complete message announces
+/// `next_message` fails with `UnmarshalError::FdCountMismatch` and the fds are closed. Additional fds are kept for
+/// the following messages.
+///
+/// After an error the buffered bytes can not be trusted to start at a message boundary anymore and the stream should
+/// be closed.
+#[derive(Debug)]
+pub struct MessageReassembler {
+    buf: Vec<u8>,
+    fds: VecDeque<UnixFd>,
+    max_message_size: usize,
+}
+
+impl Default for MessageReassembler {
+    fn default() -> Self {
+        Self {
+            buf: Vec::new(),
+            fds: VecDeque::new(),
+            max_message_size: MAX_MESSAGE_SIZE,
+        }
+    }
+}
+
+impl MessageReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject messages that are longer than `max` bytes with `UnmarshalError::MessageTooLong`.
+    /// Defaults to `MAX_MESSAGE_SIZE`.
+    pub fn set_max_message_size(&mut self, max: usize) {
+        self.max_message_size = max;
+    }
+
+    /// Append a chunk of bytes that was read from the stream
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Append unix fds that were received from the stream
+    pub fn push_fds<I: IntoIterator<Item = UnixFd>>(&mut self, fds: I) {
+        self.fds.extend(fds);
+    }
+
+    /// Let `read` write up to `max_len` bytes directly into the buffer, instead of reading into a separate buffer that is
+    /// then pushed. `read` returns how many bytes it wrote, errors are passed through and keep nothing.
+    ///
+    /// Reading at most `bytes_missing` bytes at a time never reads beyond the end of the current message. Streams that
+    /// pass unix fds need this, so the fds received by one read all belong to the same message.
+    pub fn fill_with<E>(
+        &mut self,
+        max_len: usize,
+        read: impl FnOnce(&mut [u8]) -> Result<usize, E>,
+    ) -> Result<usize, E> {
+        let start = self.buf.len();
+        self.buf.resize(start + max_len, 0);
+        let res = read(&mut self.buf[start..]);
+        let filled = match res {
+            Ok(filled) => usize::min(filled, max_len),
+            Err(_) => 0,
+        };
+        self.buf.truncate(start + filled);
+        res
+    }
+
+    /// How many bytes are buffered but not yet returned as part of a message
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// How many unix fds are buffered but not yet handed to a message
+    pub fn buffered_fds(&self) -> usize {
+        self.fds.len()
+    }
+
+    /// How many more bytes are needed to complete the current message. If the fixed header is not complete yet this
+    /// only counts the bytes missing from it, since the length of the message is not known.
+    pub fn bytes_missing(&self) -> Result<usize, UnmarshalError> {
+        let needed =
+            message_len_with_limit(&self.buf, self.max_message_size)?.unwrap_or(FIXED_HEADER_LEN);
+        Ok(needed.saturating_sub(self.buf.len()))
+    }
+
+    /// Return the next message if all of its bytes have been pushed
+    pub fn next_message(&mut self) -> Result<Option<MarshalledMessage>, UnmarshalError> {
+        let len = match message_len_with_limit(&self.buf, self.max_message_size)? {
+            Some(len) if len <= self.buf.len() => len,
+            _ => return Ok(None),
+        };
+        let rest = self.buf.split_off(len);
+        let msg_buf = core::mem::replace(&mut self.buf, rest);
+        let mut msg = unmarshal_message(msg_buf)?;
+
+        let declared = msg.dynheader.num_fds.unwrap_or(0) as usize;
+        if declared > self.fds.len() {
+            let received = self.fds.len();
+            self.fds.clear();
+            return Err(UnmarshalError::FdCountMismatch { declared, received });
+        }
+        msg.body.raw_fds.extend(self.fds.drain(..declared));
+        Ok(Some(msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::{string_signal, to_bytes};
+
+    #[test]
+    fn test_reassemble_chunks() {
+        let mut stream = to_bytes(&string_signal("first"), 1);
+        stream.extend(to_bytes(&string_signal("second with a longer body"), 2));
+        let first_len = message_len(&stream).unwrap().unwrap();
+        assert_eq!(first_len, to_bytes(&string_signal("first"), 1).len());
+
+        for chunk_size in [1, 3, 16, first_len, stream.len()] {
+            let mut reassembler = MessageReassembler::new();
+            let mut received = Vec::new();
+            for chunk in stream.chunks(chunk_size) {
+                reassembler.push(chunk);
+                while let Some(msg) = reassembler.next_message().unwrap() {
+                    received.push(msg);
+                }
+            }
+            assert_eq!(reassembler.buffered(), 0);
+            assert_eq!(received.len(), 2);
+            assert_eq!(received[0].dynheader.serial, Some(1));
+            assert_eq!(received[0].body.parser().get::<&str>().unwrap(), "first");
+            assert_eq!(received[1].dynheader.serial, Some(2));
+            assert_eq!(
+                received[1].body.parser().get::<&str>().unwrap(),
+                "second with a longer body"
+            );
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_fd_count_mismatch() {
+        let fds = |n: usize| {
+            (0..n)
+                .map(|_| UnixFd::new(nix::unistd::dup(0).unwrap()))
+                .collect::<Vec<_>>()
+        };
+        let mut msg = crate::test_fixtures::signal();
+        for fd in fds(2) {
+            msg.body.push_param(fd).unwrap();
+        }
+        let bytes = to_bytes(&msg, 1);
+
+        let mut reassembler = MessageReassembler::new();
+        reassembler.push(&bytes);
+        reassembler.push_fds(fds(2));
+        let received = reassembler.next_message().unwrap().unwrap();
+        assert_eq!(received.body.raw_fds.len(), 2);
+
+        // too few fds
+        for pushed in [0, 1] {
+            let mut reassembler = MessageReassembler::new();
+            reassembler.push(&bytes);
+            reassembler.push_fds(fds(pushed));
+            assert_eq!(
+                reassembler.next_message().unwrap_err(),
+                UnmarshalError::FdCountMismatch {
+                    declared: 2,
+                    received: pushed
+                }
+            );
+            assert_eq!(reassembler.buffered_fds(), 0);
+        }
+
+        // too many fds, the rest is kept for the next message
+        let mut reassembler = MessageReassembler::new();
+        reassembler.push(&bytes);
+        reassembler.push_fds(fds(3));
+        assert_eq!(
+            reassembler
+                .next_message()
+                .unwrap()
+                .unwrap()
+                .body
+                .raw_fds
+                .len(),
+            2
+        );
+        assert_eq!(reassembler.buffered_fds(), 1);
+        reassembler.push(&bytes);
+        assert_eq!(
+            reassembler.next_message().unwrap_err(),
+            UnmarshalError::FdCountMismatch {
+                declared: 2,
+                received: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_bytes_missing() {
+        let bytes = to_bytes(&string_signal("first"), 1);
+        let mut reassembler = MessageReassembler::new();
+        assert_eq!(reassembler.bytes_missing().unwrap(), FIXED_HEADER_LEN);
+        reassembler.push(&bytes[..10]);
+        assert_eq!(reassembler.bytes_missing().unwrap(), FIXED_HEADER_LEN - 10);
+        reassembler.push(&bytes[10..20]);
+        assert_eq!(reassembler.bytes_missing().unwrap(), bytes.len() - 20);
+        assert!(reassembler.next_message().unwrap().is_none());
+        reassembler.push(&bytes[20..]);
+        assert_eq!(reassembler.bytes_missing().unwrap(), 0);
+        assert!(reassembler.next_message().unwrap().is_some());
+
+        reassembler.push(b"xyzxyzxyzxyzxyzxyz");
+        assert_eq!(
+            reassembler.next_message().unwrap_err(),
+            UnmarshalError::InvalidByteOrder
+        );
+    }
+
+    #[test]
+    fn test_max_message_size() {
+        let mut bytes = to_bytes(&string_signal("first"), 1);
+        let len = bytes.len();
+        assert_eq!(message_len_with_limit(&bytes, len).unwrap(), Some(len));
+        assert_eq!(
+            message_len_with_limit(&bytes, len - 1),
+            Err(UnmarshalError::MessageTooLong { len, max: len - 1 })
+        );
+
+        let mut reassembler = MessageReassembler::new();
+        reassembler.set_max_message_size(len - 1);
+        reassembler.push(&bytes[..FIXED_HEADER_LEN]);
+        assert_eq!(
+            reassembler.bytes_missing(),
+            Err(UnmarshalError::MessageTooLong { len, max: len - 1 })
+        );
+
+        // a header that claims the longest possible body and header fields
+        // the test messages are little endian
+        assert_eq!(bytes[0], b'l');
+        bytes[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        bytes[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        let claimed = FIXED_HEADER_LEN + 2 * u32::MAX as usize + 1;
+        assert_eq!(
+            message_len(&bytes),
+            Err(UnmarshalError::MessageTooLong {
+                len: claimed,
+                max: MAX_MESSAGE_SIZE
+            })
+        );
+        let mut reassembler = MessageReassembler::new();
+        reassembler.push(&bytes);
+        assert!(reassembler.next_message().is_err());
+    }
+}
@@ -4,9 +4,11 @@
 //! * `traits` is for the trait based approach
 //! * `iter` is an experimental approach to an libdbus-like iterator
 
+use crate::alloc_prelude::*;
 use crate::message_builder::DynamicHeader;
 use crate::message_builder::MarshalledMessage;
 use crate::message_builder::MarshalledMessageBody;
+use crate::message_builder::MessageHeader;
 use crate::message_builder::MessageType;
 use crate::params;
 use crate::signature;
@@ -38,18 +40,164 @@ pub struct UnmarshalContext<'fds, 'buf> {
     pub buf: &'buf [u8],
     pub byteorder: ByteOrder,
     pub offset: usize,
+    /// Check that padding bytes are zero, as the spec requires. Non-zero padding is skipped over if this is not set,
+    /// which is more forgiving towards other implementations.
+    pub(crate) strict: bool,
+    /// How deep the value at the current offset is nested into containers
+    pub(crate) depth: NestingDepth,
 }
 
-impl UnmarshalContext<'_, '_> {
+/// Maximum nesting of arrays (and dicts) the spec allows
+pub const MAX_ARRAY_DEPTH: u8 = 32;
+/// Maximum nesting of structs the spec allows
+pub const MAX_STRUCT_DEPTH: u8 = 32;
+/// Maximum nesting of all containers together, including variants
+pub const MAX_TOTAL_DEPTH: u8 = 64;
+
+/// Counts the containers a value is nested in.
+///
+/// Signatures can not nest deeper than the spec allows, but variants bring their own signature which can contain
+/// another variant. Without this a message could make the unmarshalling recurse until the stack overflows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NestingDepth {
+    pub arrays: u8,
+    pub structs: u8,
+    pub variants: u8,
+}
+
+impl NestingDepth {
+    pub fn enter_array(self) -> Result<Self, UnmarshalError> {
+        Self {
+            arrays: self.arrays.saturating_add(1),
+            ..self
+        }
+        .check()
+    }
+    pub fn enter_struct(self) -> Result<Self, UnmarshalError> {
+        Self {
+            structs: self.structs.saturating_add(1),
+            ..self
+        }
+        .check()
+    }
+    pub fn enter_variant(self) -> Result<Self, UnmarshalError> {
+        Self {
+            variants: self.variants.saturating_add(1),
+            ..self
+        }
+        .check()
+    }
+    /// Enter the container described by `sig`
+    pub fn enter(self, sig: &signature::Container) -> Result<Self, UnmarshalError> {
+        match sig {
+            signature::Container::Array(_) | signature::Container::Dict(_, _) => self.enter_array(),
+            signature::Container::Struct(_) => self.enter_struct(),
+            signature::Container::Variant => self.enter_variant(),
+        }
+    }
+
+    fn check(self) -> Result<Self, UnmarshalError> {
+        let total = self.arrays as u16 + self.structs as u16 + self.variants as u16;
+        if self.arrays > MAX_ARRAY_DEPTH
+            || self.structs > MAX_STRUCT_DEPTH
+            || total > MAX_TOTAL_DEPTH as u16
+        {
+            Err(UnmarshalError::NestingTooDeep)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl<'fds, 'buf> UnmarshalContext<'fds, 'buf> {
+    /// A context that starts at `offset` of `buf`, outside of any container and without the strict padding checks
+    pub fn new(
+        fds: &'fds [crate::wire::UnixFd],
+        buf: &'buf [u8],
+        byteorder: ByteOrder,
+        offset: usize,
+    ) -> Self {
+        UnmarshalContext {
+            fds,
+            buf,
+            byteorder,
+            offset,
+            strict: false,
+            depth: Default::default(),
+        }
+    }
+
+    /// A context for conformance mode, which turns on every check this crate has. Padding bytes must be zero, in
+    /// addition to the checks that always happen: strings are valid UTF-8 without NUL bytes, object paths, signatures
+    /// and booleans are valid, unix fd indices are in bounds and containers do not nest deeper than the spec allows.
+    ///
+    /// To check a whole message, including its header and unix fds, use `unmarshal_message_conformant`.
+    pub fn conformance(
+        fds: &'fds [crate::wire::UnixFd],
+        buf: &'buf [u8],
+        byteorder: ByteOrder,
+        offset: usize,
+    ) -> Self {
+        UnmarshalContext {
+            fds,
+            buf,
+            byteorder,
+            offset,
+            strict: true,
+            depth: Default::default(),
+        }
+    }
+
+    /// Whether padding bytes must be zero, see `set_strict`
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Check that padding bytes are zero, as the spec requires. Non-zero padding is skipped over if this is not set,
+    /// which is more forgiving towards other implementations. Contexts made with `new` are not strict, the ones made
+    /// with `conformance` are.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// How deep the value at the current offset is nested into containers
+    pub fn depth(&self) -> NestingDepth {
+        self.depth
+    }
+
+    /// Run `f` on the elements of an array, which are nested one array deeper than the array itself
+    pub(crate) fn in_array<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, UnmarshalError>,
+    ) -> Result<T, UnmarshalError> {
+        let outer_depth = self.depth;
+        self.depth = outer_depth.enter_array()?;
+        let res = f(self);
+        self.depth = outer_depth;
+        res
+    }
+
+    /// How many bytes are left in the buffer after the current offset
+    pub fn remaining(&self) -> usize {
+        self.buf.len().saturating_sub(self.offset)
+    }
+
     pub fn align_to(&mut self, alignment: usize) -> Result<usize, UnmarshalError> {
-        let padding = crate::wire::util::align_offset(alignment, self.buf, self.offset)?;
+        let padding = (alignment - self.offset % alignment) % alignment;
 
         if self.offset + padding > self.buf.len() {
-            Err(UnmarshalError::NotEnoughBytes)
-        } else {
-            self.offset += padding;
-            Ok(padding)
+            return Err(UnmarshalError::NotEnoughBytes);
+        }
+        if self.strict {
+            let padding_bytes = &self.buf[self.offset..self.offset + padding];
+            if let Some(idx) = padding_bytes.iter().position(|b| *b != 0) {
+                return Err(UnmarshalError::NonZeroPadding {
+                    offset: self.offset + idx,
+                });
+            }
         }
+        self.offset += padding;
+        Ok(padding)
     }
 }
 
@@ -69,7 +217,7 @@ impl UnmarshalError {
 
 pub const HEADER_LEN: usize = 12;
 
-pub type UnmarshalResult<T> = std::result::Result<(usize, T), UnmarshalError>;
+pub type UnmarshalResult<T> = core::result::Result<(usize, T), UnmarshalError>;
 
 pub fn unmarshal_header(buf: &[u8], offset: usize) -> UnmarshalResult<Header> {
     if buf.len() < offset + HEADER_LEN {
@@ -122,29 +270,98 @@ pub fn unmarshal_dynamic_header(
     Ok((fields_bytes_used, hdr))
 }
 
+/// Unmarshal the fixed part of the header and the header fields. The bytes used include the padding before the body,
+/// so the body starts at `offset` plus the bytes used.
+pub fn unmarshal_message_header(buf: &[u8], offset: usize) -> UnmarshalResult<MessageHeader> {
+    let (header_bytes, header) = unmarshal_header(buf, offset)?;
+    let (fields_bytes, fields) = unmarshal_dynamic_header(&header, buf, offset + header_bytes)?;
+    let padding = align_offset(8, buf, offset + header_bytes + fields_bytes)?;
+    Ok((
+        header_bytes + fields_bytes + padding,
+        MessageHeader {
+            byteorder: header.byteorder,
+            typ: header.typ,
+            flags: header.flags,
+            version: header.version,
+            body_len: header.body_len,
+            serial: header.serial,
+            fields,
+        },
+    ))
+}
+
 pub fn unmarshal_body<'a, 'e>(
     byteorder: ByteOrder,
     sigs: &[crate::signature::Type],
     buf: &[u8],
     fds: &[crate::wire::UnixFd],
     offset: usize,
+) -> UnmarshalResult<Vec<params::Param<'a, 'e>>> {
+    unmarshal_body_into(byteorder, sigs, buf, fds, offset, Default::default())
+}
+
+pub(crate) fn unmarshal_body_into<'a, 'e>(
+    byteorder: ByteOrder,
+    sigs: &[crate::signature::Type],
+    buf: &[u8],
+    fds: &[crate::wire::UnixFd],
+    offset: usize,
+    strings: base::StringStore<'a>,
 ) -> UnmarshalResult<Vec<params::Param<'a, 'e>>> {
     let mut params = Vec::new();
     let mut body_bytes_used = 0;
-    let mut ctx = UnmarshalContext {
-        fds,
-        buf,
-        byteorder,
-        offset,
-    };
+    let mut ctx = UnmarshalContext::new(fds, buf, byteorder, offset);
     for param_sig in sigs {
-        let (bytes, new_param) = unmarshal_with_sig(param_sig, &mut ctx)?;
+        let (bytes, new_param) = unmarshal_with_sig_into(param_sig, &mut ctx, strings)?;
         params.push(new_param);
         body_bytes_used += bytes;
     }
     Ok((body_bytes_used, params))
 }
 
+/// Unmarshal the complete message at the start of `buf` in conformance mode, for bus daemons, proxies and fuzzers
+/// that must not accept anything the spec forbids. The message is checked for, in this order:
+///
+/// 1. its length, which must not be more than `MAX_MESSAGE_SIZE` (`MessageTooLong`) and must fit into `buf`
+///    (`NotEnoughBytes`)
+/// 1. a valid byteorder (`InvalidByteOrder`) and message type (`InvalidMessageType`)
+/// 1. header fields with the signature the spec assigns to them (`WrongSignature`), valid values
+///    (`Validation`, `InvalidString`) and the fields their message type requires, e.g. `PATH` and `MEMBER` for method
+///    calls (`MissingHeaderField`), each of them only once (`InvalidHeaderFields`)
+/// 1. zero padding between the header fields and before the body (`PaddingContainedData`)
+/// 1. the number of unix fds, which must be exactly what the `UNIX_FDS` header field says (`FdCountMismatch`)
+/// 1. a body that matches the signature of the header, with all the checks of `UnmarshalContext::conformance`, and
+///    that ends exactly at the body length from the header (`NotAllBytesUsed`)
+///
+/// The returned bytes used is the length of the message, bytes after it in `buf` are not looked at.
+pub fn unmarshal_message_conformant(
+    buf: &[u8],
+    fds: Vec<crate::wire::UnixFd>,
+) -> UnmarshalResult<MarshalledMessage> {
+    let len = crate::wire::reassembler::message_len(buf)?.ok_or(UnmarshalError::NotEnoughBytes)?;
+    if buf.len() < len {
+        return Err(UnmarshalError::NotEnoughBytes);
+    }
+    let buf = &buf[..len];
+    let (header_bytes, header) = unmarshal_message_header(buf, 0)?;
+
+    let msg = MarshalledMessage::from_header(header, buf[header_bytes..].to_vec(), fds)?;
+    let sigs = if msg.get_sig().is_empty() {
+        vec![]
+    } else {
+        signature::Type::parse_description(msg.get_sig())?
+    };
+    let mut ctx =
+        UnmarshalContext::conformance(&msg.body.raw_fds, &msg.body.buf, msg.body.byteorder, 0);
+    for sig in &sigs {
+        unmarshal_with_sig(sig, &mut ctx)?;
+    }
+    if ctx.offset != msg.body.buf.len() {
+        return Err(UnmarshalError::NotAllBytesUsed);
+    }
+    Ok((len, msg))
+}
+
 pub fn unmarshal_next_message(
     header: &Header,
     dynheader: DynamicHeader,
@@ -226,12 +443,23 @@ fn unmarshal_header_fields(
             Err(e) => return Err(e),
         }
     }
-    params::validate_header_fields(header.typ, &fields)
-        .map_err(|_| UnmarshalError::InvalidHeaderFields)?;
+    params::validate_header_fields(header.typ, &fields).map_err(|err| match err {
+        params::validation::Error::MissingHeaderField(field) => {
+            UnmarshalError::MissingHeaderField { field }
+        }
+        _ => UnmarshalError::InvalidHeaderFields,
+    })?;
 
     Ok((header_fields_bytes as usize + 4, fields))
 }
 
+fn wrong_field_signature(expected: &str, actual: &str) -> UnmarshalError {
+    UnmarshalError::WrongSignature {
+        expected: expected.to_owned(),
+        actual: actual.to_owned(),
+    }
+}
+
 fn unmarshal_header_field(
     header: &Header,
     buf: &[u8],
@@ -266,49 +494,49 @@ fn unmarshal_header_field(
                 crate::params::validate_object_path(&objpath)?;
                 (b, Ok(HeaderField::Path(objpath)))
             }
-            _ => (0, Err(UnmarshalError::WrongSignature)),
+            _ => (0, Err(wrong_field_signature("o", sig_str))),
         },
         2 => match sig {
             signature::Type::Base(signature::Base::String) => {
                 let (b, int) = unmarshal_string(header.byteorder, &buf[offset..])?;
                 (b, Ok(HeaderField::Interface(int)))
             }
-            _ => (0, Err(UnmarshalError::WrongSignature)),
+            _ => (0, Err(wrong_field_signature("s", sig_str))),
         },
         3 => match sig {
             signature::Type::Base(signature::Base::String) => {
                 let (b, mem) = unmarshal_string(header.byteorder, &buf[offset..])?;
                 (b, Ok(HeaderField::Member(mem)))
             }
-            _ => (0, Err(UnmarshalError::WrongSignature)),
+            _ => (0, Err(wrong_field_signature("s", sig_str))),
         },
         4 => match sig {
             signature::Type::Base(signature::Base::String) => {
                 let (b, name) = unmarshal_string(header.byteorder, &buf[offset..])?;
                 (b, Ok(HeaderField::ErrorName(name)))
             }
-            _ => (0, Err(UnmarshalError::WrongSignature)),
+            _ => (0, Err(wrong_field_signature("s", sig_str))),
         },
         5 => match sig {
             signature::Type::Base(signature::Base::Uint32) => {
                 let (b, serial) = parse_u32(&buf[offset..], header.byteorder)?;
                 (b, Ok(HeaderField::ReplySerial(serial)))
             }
-            _ => (0, Err(UnmarshalError::WrongSignature)),
+            _ => (0, Err(wrong_field_signature("u", sig_str))),
         },
         6 => match sig {
             signature::Type::Base(signature::Base::String) => {
                 let (b, dest) = unmarshal_string(header.byteorder, &buf[offset..])?;
                 (b, Ok(HeaderField::Destination(dest)))
             }
-            _ => (0, Err(UnmarshalError::WrongSignature)),
+            _ => (0, Err(wrong_field_signature("s", sig_str))),
         },
         7 => match sig {
             signature::Type::Base(signature::Base::String) => {
                 let (b, snd) = unmarshal_string(header.byteorder, &buf[offset..])?;
                 (b, Ok(HeaderField::Sender(snd)))
             }
-            _ => (0, Err(UnmarshalError::WrongSignature)),
+            _ => (0, Err(wrong_field_signature("s", sig_str))),
         },
         8 => match sig {
             signature::Type::Base(signature::Base::Signature) => {
@@ -319,14 +547,14 @@ fn unmarshal_header_field(
                 }
                 (b, Ok(HeaderField::Signature(sig.to_owned())))
             }
-            _ => (0, Err(UnmarshalError::WrongSignature)),
+            _ => (0, Err(wrong_field_signature("g", sig_str))),
         },
         9 => match sig {
             signature::Type::Base(signature::Base::Uint32) => {
                 let (b, fds) = parse_u32(&buf[offset..], header.byteorder)?;
                 (b, Ok(HeaderField::UnixFds(fds)))
             }
-            _ => (0, Err(UnmarshalError::WrongSignature)),
+            _ => (0, Err(wrong_field_signature("u", sig_str))),
         },
         0 => (0, Err(UnmarshalError::InvalidHeaderField)),
         _ => (0, Err(UnmarshalError::UnknownHeaderField)),
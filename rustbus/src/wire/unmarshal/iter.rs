@@ -55,12 +55,12 @@ impl<'a> MessageIter<'a> {
         if self.counter >= self.sig.len() {
             None
         } else {
-            let ctx = &mut crate::wire::unmarshal::UnmarshalContext {
-                buf: self.source,
-                fds: &[],
-                byteorder: self.byteorder,
-                offset: *self.current_offset,
-            };
+            let ctx = &mut crate::wire::unmarshal::UnmarshalContext::new(
+                &[],
+                self.source,
+                self.byteorder,
+                *self.current_offset,
+            );
             let (bytes, val) = match T::unmarshal(ctx) {
                 Err(e) => return Some(Err(e)),
                 Ok(t) => t,
@@ -143,12 +143,8 @@ impl<'a, 'parent> DictEntryIter<'a> {
         let iter = if self.counter == 0 {
             // read the key value
 
-            let mut ctx = UnmarshalContext {
-                byteorder: self.byteorder,
-                buf: self.source,
-                offset: *self.current_offset,
-                fds: &Vec::new(),
-            };
+            let mut ctx =
+                UnmarshalContext::new(&[], self.source, self.byteorder, *self.current_offset);
 
             match unmarshal_base(self.key_sig, &mut ctx) {
                 Ok((bytes, param)) => {
@@ -241,12 +237,7 @@ impl<'a, 'parent> ParamIter<'a> {
 
         match new_sig {
             signature::Type::Base(b) => {
-                let mut ctx = UnmarshalContext {
-                    byteorder,
-                    buf: source,
-                    offset: *offset,
-                    fds: &Vec::new(),
-                };
+                let mut ctx = UnmarshalContext::new(&[], source, byteorder, *offset);
                 match unmarshal_base(*b, &mut ctx) {
                     Ok((bytes, param)) => {
                         *offset += bytes;
@@ -409,7 +400,7 @@ fn make_new_dict_iter<'a>(
 
 #[test]
 fn test_array_iter() {
-    use std::convert::TryFrom;
+    use core::convert::TryFrom;
     let arr = params::Container::try_from(vec![0i32.into(), 1i32.into(), 2i32.into()]).unwrap();
 
     let mut fds = Vec::new();
@@ -1,5 +1,6 @@
 //! Unmarshal base params from raw bytes
 
+use crate::alloc_prelude::*;
 use crate::params;
 use crate::signature;
 use crate::wire::errors::UnmarshalError;
@@ -7,9 +8,60 @@ use crate::wire::unmarshal::UnmarshalContext;
 use crate::wire::unmarshal::UnmarshalResult;
 use crate::wire::util::*;
 
+/// Where the strings, object paths and signatures of unmarshalled params are put. By default each of them is copied
+/// into its own `String`, with the `arena` feature they can be allocated in a `bumpalo::Bump` instead.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct StringStore<'a> {
+    #[cfg(feature = "arena")]
+    arena: Option<&'a bumpalo::Bump>,
+    _strings: core::marker::PhantomData<&'a str>,
+}
+
+impl<'a> StringStore<'a> {
+    #[cfg(feature = "arena")]
+    pub(crate) fn arena(arena: &'a bumpalo::Bump) -> Self {
+        StringStore {
+            arena: Some(arena),
+            _strings: core::marker::PhantomData,
+        }
+    }
+
+    fn store(
+        self,
+        s: &str,
+        owned: fn(String) -> params::Base<'a>,
+        by_ref: fn(&'a str) -> params::Base<'a>,
+    ) -> params::Base<'a> {
+        #[cfg(feature = "arena")]
+        if let Some(arena) = self.arena {
+            return by_ref(arena.alloc_str(s));
+        }
+        let _ = by_ref;
+        owned(s.to_owned())
+    }
+}
+
 pub fn unmarshal_base<'a>(
     typ: signature::Base,
     ctx: &mut UnmarshalContext,
+) -> UnmarshalResult<params::Base<'a>> {
+    unmarshal_base_into(typ, ctx, StringStore::default())
+}
+
+/// Like `unmarshal_base` but the strings are allocated in `arena`
+#[cfg(feature = "arena")]
+pub fn unmarshal_base_in<'a>(
+    typ: signature::Base,
+    ctx: &mut UnmarshalContext,
+    arena: &'a bumpalo::Bump,
+) -> UnmarshalResult<params::Base<'a>> {
+    unmarshal_base_into(typ, ctx, StringStore::arena(arena))
+}
+
+pub(crate) fn unmarshal_base_into<'a>(
+    typ: signature::Base,
+    ctx: &mut UnmarshalContext,
+    strings: StringStore<'a>,
 ) -> UnmarshalResult<params::Base<'a>> {
     let padding = ctx.align_to(typ.get_alignment())?;
 
@@ -35,6 +87,8 @@ pub fn unmarshal_base<'a>(
             let (bytes, val) = parse_u32(slice, ctx.byteorder)?;
             Ok((bytes, params::Base::Uint32(val)))
         }
+        // without std `UnixFd` is uninhabited so the clone never returns
+        #[cfg_attr(not(feature = "std"), allow(unreachable_code))]
         signature::Base::UnixFd => {
             let slice = &ctx.buf[ctx.offset..];
             let (bytes, idx) = parse_u32(slice, ctx.byteorder)?;
@@ -75,18 +129,25 @@ pub fn unmarshal_base<'a>(
             }
         }
         signature::Base::String => {
-            let (bytes, string) = unmarshal_string(ctx.byteorder, &ctx.buf[ctx.offset..])?;
-            Ok((bytes, params::Base::String(string)))
+            let (bytes, string) = unmarshal_str(ctx.byteorder, &ctx.buf[ctx.offset..])?;
+            let base = strings.store(string, params::Base::String, params::Base::StringRef);
+            Ok((bytes, base))
         }
         signature::Base::ObjectPath => {
-            let (bytes, string) = unmarshal_string(ctx.byteorder, &ctx.buf[ctx.offset..])?;
-            crate::params::validate_object_path(&string)?;
-            Ok((bytes, params::Base::ObjectPath(string)))
+            let (bytes, string) = unmarshal_str(ctx.byteorder, &ctx.buf[ctx.offset..])?;
+            crate::params::validate_object_path(string)?;
+            let base = strings.store(
+                string,
+                params::Base::ObjectPath,
+                params::Base::ObjectPathRef,
+            );
+            Ok((bytes, base))
         }
         signature::Base::Signature => {
             let (bytes, string) = unmarshal_signature(&ctx.buf[ctx.offset..])?;
             crate::params::validate_signature(string)?;
-            Ok((bytes, params::Base::Signature(string.to_owned())))
+            let base = strings.store(string, params::Base::Signature, params::Base::SignatureRef);
+            Ok((bytes, base))
         }
     }?;
     ctx.offset += bytes;
@@ -1,9 +1,10 @@
 //! Unmarshal container params from raw bytes
 
+use crate::alloc_prelude::*;
 use crate::params;
 use crate::signature;
 use crate::wire::errors::UnmarshalError;
-use crate::wire::unmarshal::base::unmarshal_base;
+use crate::wire::unmarshal::base::{unmarshal_base_into, StringStore};
 use crate::wire::unmarshal::UnmarshalContext;
 use crate::wire::unmarshal::UnmarshalResult;
 use crate::wire::util::*;
@@ -11,14 +12,50 @@ use crate::wire::util::*;
 pub fn unmarshal_with_sig<'a, 'e>(
     sig: &signature::Type,
     ctx: &mut UnmarshalContext,
+) -> UnmarshalResult<params::Param<'a, 'e>> {
+    unmarshal_with_sig_into(sig, ctx, StringStore::default())
+}
+
+/// Like `unmarshal_with_sig` but the strings, object paths and signatures are allocated in `arena` and returned as the
+/// by-ref variants of `Base`. All of them share the allocations of the arena, which can be reset once the params are
+/// not needed anymore.
+///
+/// ```rust
+/// use rustbus::params::{Base, Param};
+/// use rustbus::MessageBuilder;
+///
+/// let mut msg = MessageBuilder::new()
+///     .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+///     .build();
+/// msg.body.push_param2("first", vec!["second", "third"]).unwrap();
+///
+/// let mut arena = bumpalo::Bump::new();
+/// let params = msg.unmarshal_all_in(&arena).unwrap();
+/// assert_eq!(params[0], Param::Base(Base::StringRef("first")));
+/// drop(params);
+/// arena.reset();
+/// ```
+#[cfg(feature = "arena")]
+pub fn unmarshal_with_sig_in<'a>(
+    sig: &signature::Type,
+    ctx: &mut UnmarshalContext,
+    arena: &'a bumpalo::Bump,
+) -> UnmarshalResult<params::Param<'a, 'a>> {
+    unmarshal_with_sig_into(sig, ctx, StringStore::arena(arena))
+}
+
+pub(crate) fn unmarshal_with_sig_into<'a, 'e>(
+    sig: &signature::Type,
+    ctx: &mut UnmarshalContext,
+    strings: StringStore<'a>,
 ) -> UnmarshalResult<params::Param<'a, 'e>> {
     let (bytes, param) = match &sig {
         signature::Type::Base(base) => {
-            let (bytes, base) = unmarshal_base(*base, ctx)?;
+            let (bytes, base) = unmarshal_base_into(*base, ctx, strings)?;
             (bytes, params::Param::Base(base))
         }
         signature::Type::Container(cont) => {
-            let (bytes, cont) = unmarshal_container(cont, ctx)?;
+            let (bytes, cont) = unmarshal_container_into(cont, ctx, strings)?;
             (bytes, params::Param::Container(cont))
         }
     };
@@ -27,27 +64,60 @@ pub fn unmarshal_with_sig<'a, 'e>(
 
 pub fn unmarshal_variant<'a, 'e>(
     ctx: &mut UnmarshalContext,
+) -> UnmarshalResult<params::Variant<'a, 'e>> {
+    unmarshal_variant_into(ctx, StringStore::default())
+}
+
+fn unmarshal_variant_into<'a, 'e>(
+    ctx: &mut UnmarshalContext,
+    strings: StringStore<'a>,
 ) -> UnmarshalResult<params::Variant<'a, 'e>> {
     let (sig_bytes_used, sig_str) = unmarshal_signature(&ctx.buf[ctx.offset..])?;
 
     let mut sig = signature::Type::parse_description(sig_str)?;
     if sig.len() != 1 {
         // There must be exactly one type in the signature!
-        return Err(UnmarshalError::WrongSignature);
+        return Err(UnmarshalError::VariantSignatureNotSingleType {
+            signature: sig_str.to_owned(),
+        });
     }
     let sig = sig.remove(0);
     ctx.offset += sig_bytes_used;
 
-    let (param_bytes_used, param) = unmarshal_with_sig(&sig, ctx)?;
+    let (param_bytes_used, param) = unmarshal_with_sig_into(&sig, ctx, strings)?;
     Ok((
         sig_bytes_used + param_bytes_used,
-        params::Variant { sig, value: param },
+        params::Variant {
+            sig,
+            value: param,
+            cached_sig: None,
+        },
     ))
 }
 
 pub fn unmarshal_container<'a, 'e>(
     typ: &signature::Container,
     ctx: &mut UnmarshalContext,
+) -> UnmarshalResult<params::Container<'a, 'e>> {
+    unmarshal_container_into(typ, ctx, StringStore::default())
+}
+
+fn unmarshal_container_into<'a, 'e>(
+    typ: &signature::Container,
+    ctx: &mut UnmarshalContext,
+    strings: StringStore<'a>,
+) -> UnmarshalResult<params::Container<'a, 'e>> {
+    let outer_depth = ctx.depth;
+    ctx.depth = outer_depth.enter(typ)?;
+    let res = unmarshal_container_content(typ, ctx, strings);
+    ctx.depth = outer_depth;
+    res
+}
+
+fn unmarshal_container_content<'a, 'e>(
+    typ: &signature::Container,
+    ctx: &mut UnmarshalContext,
+    strings: StringStore<'a>,
 ) -> UnmarshalResult<params::Container<'a, 'e>> {
     let param = match typ {
         signature::Container::Array(elem_sig) => {
@@ -59,6 +129,11 @@ pub fn unmarshal_container<'a, 'e>(
 
             ctx.align_to(elem_sig.get_alignment())?;
 
+            // reject bogus lengths before looping over elements that can not be there
+            if bytes_in_array as usize > ctx.remaining() {
+                return Err(UnmarshalError::NotEnoughBytes);
+            }
+
             let mut elements = Vec::new();
             let mut bytes_used_counter = 0;
             while bytes_used_counter < bytes_in_array as usize {
@@ -66,7 +141,7 @@ pub fn unmarshal_container<'a, 'e>(
                     return Err(UnmarshalError::NotEnoughBytes);
                 }
 
-                let (bytes_used, element) = unmarshal_with_sig(elem_sig, ctx)?;
+                let (bytes_used, element) = unmarshal_with_sig_into(elem_sig, ctx, strings)?;
                 elements.push(element);
                 bytes_used_counter += bytes_used;
             }
@@ -89,21 +164,24 @@ pub fn unmarshal_container<'a, 'e>(
 
             ctx.align_to(8)?;
 
-            let mut elements = std::collections::HashMap::new();
+            // reject bogus lengths before looping over elements that can not be there
+            if bytes_in_dict as usize > ctx.remaining() {
+                return Err(UnmarshalError::NotEnoughBytes);
+            }
+
+            let mut elements = params::DictMap::new();
             let mut bytes_used_counter = 0;
             while bytes_used_counter < bytes_in_dict as usize {
                 if ctx.offset >= ctx.buf.len() {
                     return Err(UnmarshalError::NotEnoughBytes);
                 }
 
-                let element_padding = align_offset(8, ctx.buf, ctx.offset)?;
-                bytes_used_counter += element_padding;
-                ctx.offset += element_padding;
+                bytes_used_counter += ctx.align_to(8)?;
 
-                let (key_bytes, key) = unmarshal_base(*key_sig, ctx)?;
+                let (key_bytes, key) = unmarshal_base_into(*key_sig, ctx, strings)?;
                 bytes_used_counter += key_bytes;
 
-                let (val_bytes, val) = unmarshal_with_sig(val_sig, ctx)?;
+                let (val_bytes, val) = unmarshal_with_sig_into(val_sig, ctx, strings)?;
                 bytes_used_counter += val_bytes;
 
                 elements.insert(key, val);
@@ -130,14 +208,14 @@ pub fn unmarshal_container<'a, 'e>(
             }
 
             for field_sig in sigs.as_ref() {
-                let (_, field) = unmarshal_with_sig(field_sig, ctx)?;
+                let (_, field) = unmarshal_with_sig_into(field_sig, ctx, strings)?;
                 fields.push(field);
             }
             let total_bytes_used = ctx.offset - start_offset;
             (total_bytes_used, params::Container::Struct(fields))
         }
         signature::Container::Variant => {
-            let (bytes_used, variant) = unmarshal_variant(ctx)?;
+            let (bytes_used, variant) = unmarshal_variant_into(ctx, strings)?;
             (bytes_used, params::Container::Variant(Box::new(variant)))
         }
     };
@@ -133,9 +133,17 @@ pub use container::*;
 ///     }
 /// }
 /// ```
-
 pub trait Unmarshal<'buf, 'fds>: Sized + Signature {
     fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self>;
+
+    /// Convert a `params::Base` without going through the wire format. This is used by `params::Variant::get`,
+    /// types that return `None` here are marshalled into a scratch buffer and unmarshalled again.
+    #[doc(hidden)]
+    fn from_base(
+        _base: &crate::params::Base<'_>,
+    ) -> Option<Result<Self, crate::params::ConversionError>> {
+        None
+    }
 }
 
 pub fn unmarshal<'buf, 'fds, T: Unmarshal<'buf, 'fds>>(
@@ -149,6 +157,7 @@ mod test {
     use super::unmarshal;
     use super::Unmarshal;
     use super::UnmarshalContext;
+    #[cfg(feature = "std")]
     use super::Variant;
     use crate::wire::marshal::MarshalContext;
     use crate::ByteOrder;
@@ -168,36 +177,36 @@ mod test {
 
         // annotate the receiver with a type &str to unmarshal a &str
         "ABCD".marshal(ctx).unwrap();
-        let _s: &str = unmarshal(&mut UnmarshalContext {
-            buf: ctx.buf,
-            byteorder: ctx.byteorder,
-            fds: ctx.fds,
-            offset: 0,
-        })
+        let _s: &str = unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ))
         .unwrap()
         .1;
 
         // annotate the receiver with a type bool to unmarshal a bool
         ctx.buf.clear();
         true.marshal(ctx).unwrap();
-        let _b: bool = unmarshal(&mut UnmarshalContext {
-            buf: ctx.buf,
-            byteorder: ctx.byteorder,
-            fds: ctx.fds,
-            offset: 0,
-        })
+        let _b: bool = unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ))
         .unwrap()
         .1;
 
         // can also use turbofish syntax
         ctx.buf.clear();
         0i32.marshal(ctx).unwrap();
-        let _i = unmarshal::<i32>(&mut UnmarshalContext {
-            buf: ctx.buf,
-            byteorder: ctx.byteorder,
-            fds: ctx.fds,
-            offset: 0,
-        })
+        let _i = unmarshal::<i32>(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ))
         .unwrap()
         .1;
 
@@ -205,12 +214,12 @@ mod test {
         ctx.buf.clear();
         fn x(_arg: (i32, i32, &str)) {}
         (0, 0, "ABCD").marshal(ctx).unwrap();
-        let arg = unmarshal(&mut UnmarshalContext {
-            buf: ctx.buf,
-            byteorder: ctx.byteorder,
-            fds: ctx.fds,
-            offset: 0,
-        })
+        let arg = unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ))
         .unwrap()
         .1;
         x(arg);
@@ -238,12 +247,12 @@ mod test {
         orig.marshal(ctx).unwrap();
         assert_eq!(&ctx.buf[..4], &[0, 4, 0, 0]);
         assert_eq!(ctx.buf.len(), 1028);
-        let (bytes, unorig) = <&[u8] as Unmarshal>::unmarshal(&mut UnmarshalContext {
-            buf: ctx.buf,
-            fds: ctx.fds,
-            byteorder: ctx.byteorder,
-            offset: 0,
-        })
+        let (bytes, unorig) = <&[u8] as Unmarshal>::unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ))
         .unwrap();
         assert_eq!(bytes, orig.len() + 4);
         assert_eq!(orig, unorig);
@@ -264,16 +273,272 @@ mod test {
         orig.marshal(ctx).unwrap();
 
         // unorig[x] points into the appropriate region in buf, and unorigs lifetime is bound to buf
-        let (_bytes, unorig) = <Vec<&[u8]> as Unmarshal>::unmarshal(&mut UnmarshalContext {
-            buf: ctx.buf,
-            fds: ctx.fds,
-            byteorder: ctx.byteorder,
-            offset: 0,
-        })
+        let (_bytes, unorig) = <Vec<&[u8]> as Unmarshal>::unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ))
         .unwrap();
         assert_eq!(orig, unorig);
+
+        // the slice must not reach beyond the end of the buffer
+        ctx.buf.clear();
+        orig1.marshal(ctx).unwrap();
+        let res = <&[u8] as Unmarshal>::unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            &ctx.buf[..ctx.buf.len() - 1],
+            ctx.byteorder,
+            0,
+        ));
+        assert_eq!(
+            res,
+            Err(crate::wire::errors::UnmarshalError::NotEnoughBytes)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dicts() {
+        use std::collections::{BTreeMap, HashMap};
+
+        let mut fds = Vec::new();
+        let mut buf = Vec::new();
+        let mut ctx = MarshalContext {
+            buf: &mut buf,
+            fds: &mut fds,
+            byteorder: ByteOrder::LittleEndian,
+        };
+        let ctx = &mut ctx;
+
+        // a{us}
+        let mut map = BTreeMap::new();
+        map.insert(2u32, "B".to_owned());
+        map.insert(1u32, "A".to_owned());
+        let mut sig = crate::wire::marshal::traits::SignatureBuffer::new();
+        BTreeMap::<u32, String>::sig_str(&mut sig);
+        assert_eq!(sig.as_ref(), "a{us}");
+        map.marshal(ctx).unwrap();
+        // entries are sorted by key
+        assert_eq!(
+            ctx.buf,
+            &[
+                26, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, b'A', 0, 0, 0, 0, 0, 0, 0, 2, 0,
+                0, 0, 1, 0, 0, 0, b'B', 0
+            ]
+        );
+
+        let (bytes, unmarshalled) = <BTreeMap<u32, String> as Unmarshal>::unmarshal(
+            &mut UnmarshalContext::new(ctx.fds, ctx.buf, ctx.byteorder, 0),
+        )
+        .unwrap();
+        assert_eq!(bytes, ctx.buf.len());
+        assert_eq!(unmarshalled, map);
+        let (_, unmarshalled) = <HashMap<u32, &str> as Unmarshal>::unmarshal(
+            &mut UnmarshalContext::new(ctx.fds, ctx.buf, ctx.byteorder, 0),
+        )
+        .unwrap();
+        assert_eq!(unmarshalled.len(), 2);
+        assert_eq!(unmarshalled[&1], "A");
+        assert_eq!(unmarshalled[&2], "B");
+
+        // a{sv}
+        ctx.buf.clear();
+        let mut map = HashMap::new();
+        map.insert("A", crate::wire::marshal::traits::Variant(10u32));
+        map.insert("B", crate::wire::marshal::traits::Variant(20u32));
+        assert_eq!(
+            HashMap::<&str, crate::wire::marshal::traits::Variant<u32>>::signature(),
+            BTreeMap::<String, Variant>::signature()
+        );
+        map.marshal(ctx).unwrap();
+        let (_, unmarshalled) = <BTreeMap<String, Variant> as Unmarshal>::unmarshal(
+            &mut UnmarshalContext::new(ctx.fds, ctx.buf, ctx.byteorder, 0),
+        )
+        .unwrap();
+        assert_eq!(unmarshalled.len(), 2);
+        assert_eq!(unmarshalled["A"].get::<u32>(), Ok(10));
+        assert_eq!(unmarshalled["B"].get::<u32>(), Ok(20));
+
+        // duplicated keys are an error
+        ctx.buf.clear();
+        vec![(1u32, "A"), (1u32, "B")].marshal(ctx).unwrap();
+        let res = <HashMap<u32, &str> as Unmarshal>::unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ));
+        assert_eq!(
+            res,
+            Err(crate::wire::errors::UnmarshalError::DuplicateDictKey)
+        );
+        let res = <BTreeMap<u32, &str> as Unmarshal>::unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ));
+        assert_eq!(
+            res,
+            Err(crate::wire::errors::UnmarshalError::DuplicateDictKey)
+        );
     }
 
+    #[test]
+    fn test_option() {
+        let mut fds = Vec::new();
+        let mut buf = Vec::new();
+        let mut ctx = MarshalContext {
+            buf: &mut buf,
+            fds: &mut fds,
+            byteorder: ByteOrder::LittleEndian,
+        };
+        let ctx = &mut ctx;
+
+        assert_eq!(Option::<u32>::signature(), Vec::<u32>::signature());
+        let mut sig = crate::wire::marshal::traits::SignatureBuffer::new();
+        Option::<(u8, String)>::sig_str(&mut sig);
+        assert_eq!(sig.as_ref(), "a(ys)");
+
+        Option::<u32>::None.marshal(ctx).unwrap();
+        assert_eq!(ctx.buf, &[0, 0, 0, 0]);
+        ctx.buf.clear();
+        Some(0xAABBCCDDu32).marshal(ctx).unwrap();
+        assert_eq!(ctx.buf, &[4, 0, 0, 0, 0xDD, 0xCC, 0xBB, 0xAA]);
+
+        let (bytes, unmarshalled) = <Option<u32> as Unmarshal>::unmarshal(
+            &mut UnmarshalContext::new(ctx.fds, ctx.buf, ctx.byteorder, 0),
+        )
+        .unwrap();
+        assert_eq!(bytes, 8);
+        assert_eq!(unmarshalled, Some(0xAABBCCDD));
+
+        let (_, unmarshalled) = <Option<u32> as Unmarshal>::unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            &[0, 0, 0, 0],
+            ctx.byteorder,
+            0,
+        ))
+        .unwrap();
+        assert_eq!(unmarshalled, None);
+
+        ctx.buf.clear();
+        vec![1u32, 2u32].marshal(ctx).unwrap();
+        let res = <Option<u32> as Unmarshal>::unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ));
+        assert_eq!(
+            res,
+            Err(crate::wire::errors::UnmarshalError::TooManyElementsForOption(2))
+        );
+    }
+
+    #[test]
+    fn test_fixed_size_array() {
+        let mut fds = Vec::new();
+        let mut buf = Vec::new();
+        let mut ctx = MarshalContext {
+            buf: &mut buf,
+            fds: &mut fds,
+            byteorder: ByteOrder::LittleEndian,
+        };
+        let ctx = &mut ctx;
+
+        let uuid = [0xAAu8; 16];
+        assert_eq!(<[u8; 16]>::signature(), Vec::<u8>::signature());
+        uuid.marshal(ctx).unwrap();
+        let mut vec_buf = Vec::new();
+        vec![0xAAu8; 16]
+            .marshal(&mut MarshalContext {
+                buf: &mut vec_buf,
+                fds: &mut Vec::new(),
+                byteorder: ByteOrder::LittleEndian,
+            })
+            .unwrap();
+        assert_eq!(ctx.buf, &vec_buf);
+
+        let (bytes, unmarshalled) = <[u8; 16] as Unmarshal>::unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ))
+        .unwrap();
+        assert_eq!(bytes, 20);
+        assert_eq!(unmarshalled, uuid);
+
+        let res = <[u8; 8] as Unmarshal>::unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ));
+        assert_eq!(
+            res,
+            Err(crate::wire::errors::UnmarshalError::WrongArrayLength {
+                expected: 8,
+                actual: 16
+            })
+        );
+
+        ctx.buf.clear();
+        [(1u32, "A"), (2u32, "B")].marshal(ctx).unwrap();
+        let (_, unmarshalled) = <[(u32, &str); 2] as Unmarshal>::unmarshal(
+            &mut UnmarshalContext::new(ctx.fds, ctx.buf, ctx.byteorder, 0),
+        )
+        .unwrap();
+        assert_eq!(unmarshalled, [(1u32, "A"), (2u32, "B")]);
+    }
+
+    #[test]
+    fn test_array_iter() {
+        use super::ArrayIter;
+
+        let mut fds = Vec::new();
+        let mut buf = Vec::new();
+        let mut ctx = MarshalContext {
+            buf: &mut buf,
+            fds: &mut fds,
+            byteorder: ByteOrder::LittleEndian,
+        };
+        let ctx = &mut ctx;
+
+        let orig = vec![(1u64, "ABCD"), (2u64, "EFGH"), (3u64, "IJKL")];
+        orig.marshal(ctx).unwrap();
+        0xFFu8.marshal(ctx).unwrap();
+
+        let uctx = &mut UnmarshalContext::new(ctx.fds, ctx.buf, ctx.byteorder, 0);
+        let (bytes, iter) = <ArrayIter<(u64, &str)> as Unmarshal>::unmarshal(uctx).unwrap();
+        assert_eq!(bytes, ctx.buf.len() - 1);
+        // the parent context is already positioned after the array
+        assert_eq!(u8::unmarshal(uctx).unwrap().1, 0xFF);
+
+        let elements: Result<Vec<_>, _> = iter.collect();
+        assert_eq!(elements.unwrap(), orig);
+
+        // the declared length ends in the middle of the second element
+        let buf = [6, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0];
+        let mut iter = <ArrayIter<u32> as Unmarshal>::unmarshal(&mut UnmarshalContext::new(
+            &[],
+            &buf,
+            ByteOrder::LittleEndian,
+            0,
+        ))
+        .unwrap()
+        .1;
+        assert_eq!(iter.next(), Some(Ok(1)));
+        assert_eq!(
+            iter.next(),
+            Some(Err(crate::wire::errors::UnmarshalError::NotEnoughBytes))
+        );
+        assert_eq!(iter.next(), None);
+    }
+
+    #[cfg(feature = "std")]
     #[test]
     fn test_unmarshal_traits() {
         use crate::wire::marshal::MarshalContext;
@@ -291,12 +556,12 @@ mod test {
         let original = &["a", "b"];
         original.marshal(ctx).unwrap();
 
-        let (_, v) = Vec::<&str>::unmarshal(&mut UnmarshalContext {
-            buf: ctx.buf,
-            fds: ctx.fds,
-            byteorder: ctx.byteorder,
-            offset: 0,
-        })
+        let (_, v) = Vec::<&str>::unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ))
         .unwrap();
 
         assert_eq!(original, v.as_slice());
@@ -310,12 +575,9 @@ mod test {
 
         original.marshal(ctx).unwrap();
 
-        let (_, map) = std::collections::HashMap::<u64, &str>::unmarshal(&mut UnmarshalContext {
-            buf: ctx.buf,
-            fds: ctx.fds,
-            byteorder: ctx.byteorder,
-            offset: 0,
-        })
+        let (_, map) = std::collections::HashMap::<u64, &str>::unmarshal(
+            &mut UnmarshalContext::new(ctx.fds, ctx.buf, ctx.byteorder, 0),
+        )
         .unwrap();
         assert_eq!(original, map);
 
@@ -324,12 +586,12 @@ mod test {
         let orig = (30u8, true, 100u8, -123i32);
         orig.marshal(ctx).unwrap();
         type ST = (u8, bool, u8, i32);
-        let s = ST::unmarshal(&mut UnmarshalContext {
-            buf: ctx.buf,
-            fds: ctx.fds,
-            byteorder: ctx.byteorder,
-            offset: 0,
-        })
+        let s = ST::unmarshal(&mut UnmarshalContext::new(
+            ctx.fds,
+            ctx.buf,
+            ctx.byteorder,
+            0,
+        ))
         .unwrap()
         .1;
         assert_eq!(orig, s);
@@ -354,12 +616,7 @@ mod test {
         );
         let (_, (p, s, _fd)) =
             <(ObjectPath<String>, SignatureWrapper<&str>, UnixFd) as Unmarshal>::unmarshal(
-                &mut UnmarshalContext {
-                    buf: ctx.buf,
-                    fds: ctx.fds,
-                    byteorder: ctx.byteorder,
-                    offset: 0,
-                },
+                &mut UnmarshalContext::new(ctx.fds, ctx.buf, ctx.byteorder, 0),
             )
             .unwrap();
 
@@ -367,6 +624,7 @@ mod test {
         assert_eq!(s.as_ref(), "ss(aiau)");
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_variant() {
         use crate::message_builder::MarshalledMessageBody;
@@ -401,6 +659,7 @@ mod test {
             let cont = Container::Variant(Box::new(ParamVariant {
                 sig: param.1.clone(),
                 value: param.0.clone(),
+                cached_sig: None,
             }));
             body.push_old_param(&Param::Container(cont)).unwrap();
         }
@@ -412,6 +671,7 @@ mod test {
                 Param::Container(Container::Variant(Box::new(ParamVariant {
                     sig: typ.clone(),
                     value: param.clone(),
+                    cached_sig: None,
                 })))
             })
             .collect();
@@ -431,6 +691,7 @@ mod test {
                     Param::Container(Container::Variant(Box::new(ParamVariant {
                         sig: typ.clone(),
                         value: param.clone(),
+                        cached_sig: None,
                     }))),
                 )
             })
@@ -514,4 +775,243 @@ mod test {
         );
         assert!(var_map["9"].get::<bool>().unwrap());
     }
+
+    #[test]
+    fn test_strict_padding() {
+        use crate::message_builder::MarshalledMessageBody;
+        use crate::wire::errors::UnmarshalError;
+
+        let mut body = MarshalledMessageBody::new();
+        body.push_param((7u8, 0xAABBCCDDu32, 1u64)).unwrap();
+        // the u32 is padded to offset 4, the u64 to offset 8
+        body.buf[2] = 0xFF;
+        body.buf[4..8].copy_from_slice(&0xAABBCCDDu32.to_ne_bytes());
+
+        let mut ctx = UnmarshalContext::new(&[], &body.buf, crate::ByteOrder::NATIVE, 0);
+        assert_eq!(
+            <(u8, u32, u64)>::unmarshal(&mut ctx).unwrap(),
+            (16, (7, 0xAABBCCDD, 1))
+        );
+        assert_eq!(
+            body.parser().get::<(u8, u32, u64)>().unwrap(),
+            (7, 0xAABBCCDD, 1)
+        );
+
+        let mut ctx = UnmarshalContext::conformance(&[], &body.buf, crate::ByteOrder::NATIVE, 0);
+        assert_eq!(
+            <(u8, u32, u64)>::unmarshal(&mut ctx),
+            Err(UnmarshalError::NonZeroPadding { offset: 2 })
+        );
+        assert_eq!(
+            body.parser().strict(true).get::<(u8, u32, u64)>(),
+            Err(UnmarshalError::NonZeroPadding { offset: 2 })
+        );
+
+        body.buf[2] = 0;
+        assert_eq!(
+            body.parser().strict(true).get::<(u8, u32, u64)>().unwrap(),
+            (7, 0xAABBCCDD, 1)
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_array_len_bounded_by_buffer() {
+        use crate::message_builder::MarshalledMessageBody;
+        use crate::wire::errors::UnmarshalError;
+        use std::collections::HashMap;
+
+        // claim a length of u32::MAX for the array that starts the body
+        let mut body = MarshalledMessageBody::new();
+        body.push_param(vec!["a", "b"]).unwrap();
+        body.buf[0..4].copy_from_slice(&u32::MAX.to_ne_bytes());
+        assert_eq!(
+            body.parser().get::<Vec<&str>>(),
+            Err(UnmarshalError::NotEnoughBytes)
+        );
+        assert_eq!(
+            body.parser().get_param().unwrap_err(),
+            UnmarshalError::NotEnoughBytes
+        );
+
+        let mut body = MarshalledMessageBody::new();
+        let mut map = HashMap::new();
+        map.insert(1u8, 2u8);
+        body.push_param(map).unwrap();
+        body.buf[0..4].copy_from_slice(&u32::MAX.to_ne_bytes());
+        assert_eq!(
+            body.parser().get::<HashMap<u8, u8>>(),
+            Err(UnmarshalError::NotEnoughBytes)
+        );
+        assert_eq!(
+            body.parser().get_param().unwrap_err(),
+            UnmarshalError::NotEnoughBytes
+        );
+    }
+
+    #[test]
+    fn test_tuples() {
+        use crate::message_builder::MarshalledMessageBody;
+
+        type Twelve = (
+            u8,
+            u16,
+            u32,
+            u64,
+            i16,
+            i32,
+            i64,
+            bool,
+            f64,
+            String,
+            u8,
+            Vec<u32>,
+        );
+        let twelve: Twelve = (
+            1,
+            2,
+            3,
+            4,
+            -5,
+            -6,
+            -7,
+            true,
+            9.5,
+            "ten".to_owned(),
+            11,
+            vec![12],
+        );
+        let nested = ((1u8,), ("two".to_owned(), (3u32, (true,))), 4u64);
+
+        let mut body = MarshalledMessageBody::new();
+        body.push_param((7u8,)).unwrap();
+        body.push_param(("a", 1u32, false)).unwrap();
+        body.push_param(&twelve).unwrap();
+        body.push_param(&nested).unwrap();
+
+        let mut sig = crate::wire::marshal::traits::SignatureBuffer::new();
+        Twelve::sig_str(&mut sig);
+        assert_eq!(sig.as_ref(), "(yqutnixbdsyau)");
+        assert_eq!(Twelve::signature().to_string(), sig.as_ref());
+
+        let mut parser = body.parser();
+        assert_eq!(parser.get::<(u8,)>().unwrap(), (7,));
+        assert_eq!(parser.get::<(&str, u32, bool)>().unwrap(), ("a", 1, false));
+        assert_eq!(parser.get::<Twelve>().unwrap(), twelve);
+        assert_eq!(
+            parser
+                .get::<((u8,), (String, (u32, (bool,))), u64)>()
+                .unwrap(),
+            nested
+        );
+        assert!(!<(u8, u16)>::has_sig("(yqy)"));
+        assert!(!<(u8, u16, u32)>::has_sig("(yq)"));
+    }
+
+    #[test]
+    fn test_char() {
+        use crate::message_builder::MarshalledMessageBody;
+        use crate::wire::errors::UnmarshalError;
+
+        let mut body = MarshalledMessageBody::new();
+        body.push_param('a').unwrap();
+        body.push_param('ß').unwrap();
+        body.push_param("ab").unwrap();
+        body.push_param("").unwrap();
+
+        let mut parser = body.parser();
+        assert_eq!(parser.get::<char>().unwrap(), 'a');
+        assert_eq!(parser.get::<&str>().unwrap(), "ß");
+        assert_eq!(body.parser().get2::<char, char>().unwrap(), ('a', 'ß'));
+
+        parser = body.parser();
+        parser.get2::<&str, &str>().unwrap();
+        assert_eq!(parser.get::<char>(), Err(UnmarshalError::NotOneChar(2)));
+        parser.get::<&str>().unwrap();
+        assert_eq!(parser.get::<char>(), Err(UnmarshalError::NotOneChar(0)));
+        assert_eq!(char::signature().to_string(), "s");
+    }
+
+    #[test]
+    fn test_invalid_strings() {
+        use crate::signature;
+        use crate::wire::errors::{StringError, UnmarshalError};
+        use crate::wire::unmarshal::container::unmarshal_with_sig;
+        use crate::wire::{ObjectPath, SignatureWrapper};
+
+        fn string(content: &[u8], terminator: u8) -> Vec<u8> {
+            let mut buf = (content.len() as u32).to_le_bytes().to_vec();
+            buf.extend_from_slice(content);
+            buf.push(terminator);
+            buf
+        }
+        fn ctx(buf: &[u8]) -> UnmarshalContext<'_, '_> {
+            UnmarshalContext::new(&[], buf, ByteOrder::LittleEndian, 0)
+        }
+        let invalid = UnmarshalError::InvalidString;
+
+        let nul = string(b"ab\0c", 0);
+        let utf8 = string(b"ab\xffc", 0);
+        let unterminated = string(b"abc", b'd');
+        let sig = |name| signature::Type::Base(name);
+        for (buf, reason) in [
+            (&nul, StringError::ContainsNul(2)),
+            (&utf8, StringError::InvalidUtf8 { valid_up_to: 2 }),
+            (&unterminated, StringError::NotNulTerminated),
+        ] {
+            assert_eq!(
+                <&str>::unmarshal(&mut ctx(buf)).unwrap_err(),
+                invalid(reason)
+            );
+            assert_eq!(
+                String::unmarshal(&mut ctx(buf)).unwrap_err(),
+                invalid(reason)
+            );
+            assert_eq!(char::unmarshal(&mut ctx(buf)).unwrap_err(), invalid(reason));
+            assert_eq!(
+                ObjectPath::<&str>::unmarshal(&mut ctx(buf)).unwrap_err(),
+                invalid(reason)
+            );
+            for base in [signature::Base::String, signature::Base::ObjectPath] {
+                assert_eq!(
+                    unmarshal_with_sig(&sig(base), &mut ctx(buf)).unwrap_err(),
+                    invalid(reason)
+                );
+            }
+        }
+        let mut short = string(b"abc", 0);
+        short.pop();
+        assert_eq!(
+            <&str>::unmarshal(&mut ctx(&short)),
+            Err(UnmarshalError::NotEnoughBytes)
+        );
+
+        // signatures have a one byte length prefix
+        for (buf, reason) in [
+            (&b"\x02a\0\0"[..], StringError::ContainsNul(1)),
+            (b"\x02\xffa\0", StringError::InvalidUtf8 { valid_up_to: 0 }),
+            (b"\x02aaa", StringError::NotNulTerminated),
+        ] {
+            assert_eq!(
+                SignatureWrapper::<&str>::unmarshal(&mut ctx(buf)).unwrap_err(),
+                invalid(reason)
+            );
+            assert_eq!(
+                unmarshal_with_sig(&sig(signature::Base::Signature), &mut ctx(buf)).unwrap_err(),
+                invalid(reason)
+            );
+        }
+
+        // valid strings that do not follow the grammar of object paths and signatures
+        let path = string(b"/a//b", 0);
+        assert!(matches!(
+            ObjectPath::<&str>::unmarshal(&mut ctx(&path)),
+            Err(UnmarshalError::Validation(_))
+        ));
+        assert_eq!(<&str>::unmarshal(&mut ctx(&path)), Ok((10, "/a//b")));
+        assert!(matches!(
+            SignatureWrapper::<&str>::unmarshal(&mut ctx(b"\x02a{\0")),
+            Err(UnmarshalError::Validation(_))
+        ));
+    }
 }
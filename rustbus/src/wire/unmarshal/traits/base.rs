@@ -1,5 +1,6 @@
 //! This contains the implementations for the `Unmarshal` trait for base types like integers and strings
 
+use crate::alloc_prelude::*;
 use crate::wire::errors::UnmarshalError;
 use crate::wire::unmarshal;
 use crate::wire::unmarshal::UnmarshalContext;
@@ -8,6 +9,7 @@ use crate::wire::ObjectPath;
 use crate::wire::SignatureWrapper;
 use crate::Signature;
 use crate::Unmarshal;
+use core::convert::TryFrom;
 
 impl<'buf, 'fds> Unmarshal<'buf, 'fds> for u64 {
     fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
@@ -16,6 +18,12 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for u64 {
         ctx.offset += bytes;
         Ok((bytes + padding, val))
     }
+
+    fn from_base(
+        base: &crate::params::Base<'_>,
+    ) -> Option<Result<Self, crate::params::ConversionError>> {
+        Some(Self::try_from(base))
+    }
 }
 impl<'buf, 'fds> Unmarshal<'buf, 'fds> for u32 {
     fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
@@ -24,6 +32,12 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for u32 {
         ctx.offset += bytes;
         Ok((bytes + padding, val))
     }
+
+    fn from_base(
+        base: &crate::params::Base<'_>,
+    ) -> Option<Result<Self, crate::params::ConversionError>> {
+        Some(Self::try_from(base))
+    }
 }
 impl<'buf, 'fds> Unmarshal<'buf, 'fds> for u16 {
     fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
@@ -32,6 +46,12 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for u16 {
         ctx.offset += bytes;
         Ok((bytes + padding, val))
     }
+
+    fn from_base(
+        base: &crate::params::Base<'_>,
+    ) -> Option<Result<Self, crate::params::ConversionError>> {
+        Some(Self::try_from(base))
+    }
 }
 impl<'buf, 'fds> Unmarshal<'buf, 'fds> for i64 {
     fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
@@ -41,6 +61,12 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for i64 {
         ctx.offset += bytes;
         Ok((bytes + padding, val))
     }
+
+    fn from_base(
+        base: &crate::params::Base<'_>,
+    ) -> Option<Result<Self, crate::params::ConversionError>> {
+        Some(Self::try_from(base))
+    }
 }
 impl<'buf, 'fds> Unmarshal<'buf, 'fds> for i32 {
     fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
@@ -50,6 +76,12 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for i32 {
         ctx.offset += bytes;
         Ok((bytes + padding, val))
     }
+
+    fn from_base(
+        base: &crate::params::Base<'_>,
+    ) -> Option<Result<Self, crate::params::ConversionError>> {
+        Some(Self::try_from(base))
+    }
 }
 impl<'buf, 'fds> Unmarshal<'buf, 'fds> for i16 {
     fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
@@ -59,6 +91,12 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for i16 {
         ctx.offset += bytes;
         Ok((bytes + padding, val))
     }
+
+    fn from_base(
+        base: &crate::params::Base<'_>,
+    ) -> Option<Result<Self, crate::params::ConversionError>> {
+        Some(Self::try_from(base))
+    }
 }
 
 impl<'buf, 'fds> Unmarshal<'buf, 'fds> for u8 {
@@ -70,6 +108,12 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for u8 {
         ctx.offset += 1;
         Ok((1, val))
     }
+
+    fn from_base(
+        base: &crate::params::Base<'_>,
+    ) -> Option<Result<Self, crate::params::ConversionError>> {
+        Some(Self::try_from(base))
+    }
 }
 
 impl<'buf, 'fds> Unmarshal<'buf, 'fds> for bool {
@@ -83,6 +127,12 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for bool {
             _ => Err(UnmarshalError::InvalidBoolean),
         }
     }
+
+    fn from_base(
+        base: &crate::params::Base<'_>,
+    ) -> Option<Result<Self, crate::params::ConversionError>> {
+        Some(Self::try_from(base))
+    }
 }
 
 impl<'buf, 'fds> Unmarshal<'buf, 'fds> for f64 {
@@ -92,6 +142,12 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for f64 {
         ctx.offset += bytes;
         Ok((bytes + padding, f64::from_bits(val)))
     }
+
+    fn from_base(
+        base: &crate::params::Base<'_>,
+    ) -> Option<Result<Self, crate::params::ConversionError>> {
+        Some(Self::try_from(base))
+    }
 }
 
 impl<'buf, 'fds> Unmarshal<'buf, 'fds> for &'buf str {
@@ -103,6 +159,17 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for &'buf str {
     }
 }
 
+impl<'buf, 'fds> Unmarshal<'buf, 'fds> for char {
+    fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
+        let (bytes, val) = <&str>::unmarshal(ctx)?;
+        let mut chars = val.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok((bytes, c)),
+            _ => Err(UnmarshalError::NotOneChar(val.chars().count())),
+        }
+    }
+}
+
 impl<'buf, 'fds> Unmarshal<'buf, 'fds> for String {
     fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
         let padding = ctx.align_to(Self::alignment())?;
@@ -110,6 +177,12 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for String {
         ctx.offset += bytes;
         Ok((bytes + padding, val))
     }
+
+    fn from_base(
+        base: &crate::params::Base<'_>,
+    ) -> Option<Result<Self, crate::params::ConversionError>> {
+        Some(Self::try_from(base))
+    }
 }
 
 impl<'buf, 'fds, S: AsRef<str> + From<&'buf str> + Unmarshal<'buf, 'fds>> Unmarshal<'buf, 'fds>
@@ -1,5 +1,6 @@
 //! This contains the implementations for the `Unmarshal` trait for container types like lists and dicts
 
+use crate::alloc_prelude::*;
 use crate::signature;
 use crate::wire::errors::UnmarshalError;
 use crate::wire::marshal::traits::SignatureBuffer;
@@ -9,86 +10,42 @@ use crate::wire::util;
 use crate::ByteOrder;
 use crate::Signature;
 use crate::Unmarshal;
-use std::borrow::Cow;
-
-impl<'buf, 'fds, E1> Unmarshal<'buf, 'fds> for (E1,)
-where
-    E1: Unmarshal<'buf, 'fds> + Sized,
-{
-    fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
-        let padding = ctx.align_to(8)?;
-        let (bytes, val1) = E1::unmarshal(ctx)?;
-        Ok((bytes + padding, (val1,)))
-    }
-}
-
-impl<'buf, 'fds, E1, E2> Unmarshal<'buf, 'fds> for (E1, E2)
-where
-    E1: Unmarshal<'buf, 'fds> + Sized,
-    E2: Unmarshal<'buf, 'fds> + Sized,
-{
-    fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
-        let start_offset = ctx.offset;
-        ctx.align_to(8)?;
-        let (_bytes, val1) = E1::unmarshal(ctx)?;
-
-        ctx.align_to(E2::alignment())?;
-        let (_bytes, val2) = E2::unmarshal(ctx)?;
-
-        let total_bytes = ctx.offset - start_offset;
-        Ok((total_bytes, (val1, val2)))
-    }
-}
-
-impl<'buf, 'fds, E1, E2, E3> Unmarshal<'buf, 'fds> for (E1, E2, E3)
-where
-    E1: Unmarshal<'buf, 'fds> + Sized,
-    E2: Unmarshal<'buf, 'fds> + Sized,
-    E3: Unmarshal<'buf, 'fds> + Sized,
-{
-    fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
-        let start_offset = ctx.offset;
-
-        ctx.align_to(8)?;
-        let (_bytes, val1) = E1::unmarshal(ctx)?;
-
-        ctx.align_to(E2::alignment())?;
-        let (_bytes, val2) = E2::unmarshal(ctx)?;
-
-        ctx.align_to(E3::alignment())?;
-        let (_bytes, val3) = E3::unmarshal(ctx)?;
-
-        let total_bytes = ctx.offset - start_offset;
-        Ok((total_bytes, (val1, val2, val3)))
-    }
+use alloc::borrow::Cow;
+
+/// Tuples are unmarshalled from structs, see the `Marshal` impls
+macro_rules! tuple_impls {
+    ($($name:ident $val:ident),+) => {
+        impl<'buf, 'fds, $($name),+> Unmarshal<'buf, 'fds> for ($($name,)+)
+        where
+            $($name: Unmarshal<'buf, 'fds> + Sized),+
+        {
+            fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
+                let start_offset = ctx.offset;
+                ctx.align_to(8)?;
+                $(
+                    ctx.align_to($name::alignment())?;
+                    let (_bytes, $val) = $name::unmarshal(ctx)?;
+                )+
+
+                let total_bytes = ctx.offset - start_offset;
+                Ok((total_bytes, ($($val,)+)))
+            }
+        }
+    };
 }
 
-impl<'buf, 'fds, E1, E2, E3, E4> Unmarshal<'buf, 'fds> for (E1, E2, E3, E4)
-where
-    E1: Unmarshal<'buf, 'fds> + Sized,
-    E2: Unmarshal<'buf, 'fds> + Sized,
-    E3: Unmarshal<'buf, 'fds> + Sized,
-    E4: Unmarshal<'buf, 'fds> + Sized,
-{
-    fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
-        let start_offset = ctx.offset;
-
-        ctx.align_to(8)?;
-        let (_bytes, val1) = E1::unmarshal(ctx)?;
-
-        ctx.align_to(E2::alignment())?;
-        let (_bytes, val2) = E2::unmarshal(ctx)?;
-
-        ctx.align_to(E3::alignment())?;
-        let (_bytes, val3) = E3::unmarshal(ctx)?;
-
-        ctx.align_to(E4::alignment())?;
-        let (_bytes, val4) = E4::unmarshal(ctx)?;
-
-        let total_bytes = ctx.offset - start_offset;
-        Ok((total_bytes, (val1, val2, val3, val4)))
-    }
-}
+tuple_impls!(E1 val1);
+tuple_impls!(E1 val1, E2 val2);
+tuple_impls!(E1 val1, E2 val2, E3 val3);
+tuple_impls!(E1 val1, E2 val2, E3 val3, E4 val4);
+tuple_impls!(E1 val1, E2 val2, E3 val3, E4 val4, E5 val5);
+tuple_impls!(E1 val1, E2 val2, E3 val3, E4 val4, E5 val5, E6 val6);
+tuple_impls!(E1 val1, E2 val2, E3 val3, E4 val4, E5 val5, E6 val6, E7 val7);
+tuple_impls!(E1 val1, E2 val2, E3 val3, E4 val4, E5 val5, E6 val6, E7 val7, E8 val8);
+tuple_impls!(E1 val1, E2 val2, E3 val3, E4 val4, E5 val5, E6 val6, E7 val7, E8 val8, E9 val9);
+tuple_impls!(E1 val1, E2 val2, E3 val3, E4 val4, E5 val5, E6 val6, E7 val7, E8 val8, E9 val9, E10 val10);
+tuple_impls!(E1 val1, E2 val2, E3 val3, E4 val4, E5 val5, E6 val6, E7 val7, E8 val8, E9 val9, E10 val10, E11 val11);
+tuple_impls!(E1 val1, E2 val2, E3 val3, E4 val4, E5 val5, E6 val6, E7 val7, E8 val8, E9 val9, E10 val10, E11 val11, E12 val12);
 
 impl<E: Signature> Signature for Vec<E> {
     fn signature() -> crate::signature::Type {
@@ -127,12 +84,27 @@ impl<E: Signature + Clone> Signature for Cow<'_, [E]> {
     }
 }
 /// for byte arrays we can give an efficient method of decoding. This will bind the returned slice to the lifetime of the buffer.
+///
+/// The slice points directly into the buffer the message was read into (e.g. the body of a `MarshalledMessage`), no bytes are copied.
+/// This means it can not outlive that buffer, if you need to keep the data around longer, unmarshal into a `Vec<u8>` instead.
+/// ```rust
+/// # use rustbus::message_builder::MessageBuilder;
+/// let mut msg = MessageBuilder::new().signal("io.killing.spark", "Blob", "/io/killing/spark").build();
+/// msg.body.push_param(&[1u8, 2, 3, 4][..]).unwrap();
+///
+/// // data borrows from msg.body
+/// let data: &[u8] = msg.body.parser().get().unwrap();
+/// assert_eq!(data, &[1, 2, 3, 4]);
+/// ```
 impl<'buf, 'fds> Unmarshal<'buf, 'fds> for &'buf [u8] {
     fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
         let padding = ctx.align_to(Self::alignment())?;
         let (_, bytes_in_array) = u32::unmarshal(ctx)?;
 
-        let elements = &ctx.buf[ctx.offset..ctx.offset + bytes_in_array as usize];
+        let elements = ctx
+            .buf
+            .get(ctx.offset..ctx.offset + bytes_in_array as usize)
+            .ok_or(UnmarshalError::NotEnoughBytes)?;
         ctx.offset += bytes_in_array as usize;
 
         let total_bytes_used = padding + 4 + bytes_in_array as usize;
@@ -155,7 +127,7 @@ where
     ctx.align_to(alignment)?;
 
     // Check that we will have a range of complete elements
-    if bytes_in_array % alignment != 0 {
+    if !bytes_in_array.is_multiple_of(alignment) {
         return Err(UnmarshalError::NotAllBytesUsed);
     }
     // Start at offset
@@ -170,7 +142,7 @@ where
     // cast the slice from u8 to the target type
     let elem_cnt = bytes_in_array / alignment;
     let ptr = content_slice.as_ptr().cast::<E>();
-    let slice = std::slice::from_raw_parts(ptr, elem_cnt);
+    let slice = core::slice::from_raw_parts(ptr, elem_cnt);
 
     ctx.offset += bytes_in_array;
     Ok((ctx.offset - start_offset, slice))
@@ -182,7 +154,7 @@ impl<'buf, 'fds, E: Unmarshal<'buf, 'fds> + Clone> Unmarshal<'buf, 'fds> for Cow
                 let (used, src): (_, &[E]) = unmarshal_slice(ctx)?;
                 // SAFETY: One of requirements is for valid_slice it is only valid for 'buf
                 // Thus this lifetime cast is always valid
-                let l_expand: &'buf [E] = std::mem::transmute(src);
+                let l_expand: &'buf [E] = core::mem::transmute(src);
                 return Ok((used, Cow::Borrowed(l_expand)));
             }
         }
@@ -196,7 +168,7 @@ impl<'buf, 'fds, E: Unmarshal<'buf, 'fds>> Unmarshal<'buf, 'fds> for Vec<E> {
                 let (used, src) = unmarshal_slice::<E>(ctx)?;
                 let mut ret = Vec::with_capacity(src.len());
                 let dst = ret.as_mut_ptr();
-                std::ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len());
+                core::ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len());
                 ret.set_len(src.len());
                 return Ok((used, ret));
             }
@@ -207,22 +179,27 @@ impl<'buf, 'fds, E: Unmarshal<'buf, 'fds>> Unmarshal<'buf, 'fds> for Vec<E> {
 
         ctx.align_to(E::alignment())?;
 
-        let mut elements = Vec::new();
-        let mut bytes_used_counter = 0;
-        while bytes_used_counter < bytes_in_array as usize {
-            if ctx.offset >= ctx.buf.len() {
-                return Err(UnmarshalError::NotEnoughBytes);
-            }
+        // reject bogus lengths before looping over elements that can not be there
+        if bytes_in_array as usize > ctx.remaining() {
+            return Err(UnmarshalError::NotEnoughBytes);
+        }
 
-            let elem_padding = util::align_offset(E::alignment(), ctx.buf, ctx.offset)?;
+        let elements = ctx.in_array(|ctx| {
+            let mut elements = Vec::new();
+            let mut bytes_used_counter = 0;
+            while bytes_used_counter < bytes_in_array as usize {
+                if ctx.offset >= ctx.buf.len() {
+                    return Err(UnmarshalError::NotEnoughBytes);
+                }
 
-            bytes_used_counter += elem_padding;
-            ctx.offset += elem_padding;
+                bytes_used_counter += ctx.align_to(E::alignment())?;
 
-            let (bytes_used, element) = E::unmarshal(ctx)?;
-            elements.push(element);
-            bytes_used_counter += bytes_used;
-        }
+                let (bytes_used, element) = E::unmarshal(ctx)?;
+                elements.push(element);
+                bytes_used_counter += bytes_used;
+            }
+            Ok(elements)
+        })?;
 
         let total_bytes_used = ctx.offset - start_offset;
 
@@ -230,44 +207,191 @@ impl<'buf, 'fds, E: Unmarshal<'buf, 'fds>> Unmarshal<'buf, 'fds> for Vec<E> {
     }
 }
 
-impl<'buf, 'fds, K: Unmarshal<'buf, 'fds> + std::hash::Hash + Eq, V: Unmarshal<'buf, 'fds>>
-    Unmarshal<'buf, 'fds> for std::collections::HashMap<K, V>
-{
+/// Fixed size arrays are marshalled like any other array. Unmarshalling fails with `WrongArrayLength` if
+/// the array in the message does not contain exactly `N` elements.
+impl<'buf, 'fds, E: Unmarshal<'buf, 'fds>, const N: usize> Unmarshal<'buf, 'fds> for [E; N] {
+    fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
+        let (bytes, elements) = Vec::<E>::unmarshal(ctx)?;
+        let actual = elements.len();
+        let array = core::convert::TryInto::<[E; N]>::try_into(elements).map_err(|_| {
+            UnmarshalError::WrongArrayLength {
+                expected: N,
+                actual,
+            }
+        })?;
+        Ok((bytes, array))
+    }
+}
+
+/// Unmarshals an array with zero or one element. Arrays with more elements result in
+/// `UnmarshalError::TooManyElementsForOption`.
+impl<'buf, 'fds, E: Unmarshal<'buf, 'fds>> Unmarshal<'buf, 'fds> for Option<E> {
+    fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
+        let (bytes, mut elements) = Vec::<E>::unmarshal(ctx)?;
+        if elements.len() > 1 {
+            return Err(UnmarshalError::TooManyElementsForOption(elements.len()));
+        }
+        Ok((bytes, elements.pop()))
+    }
+}
+
+/// Lazily unmarshals the elements of an array one at a time, instead of collecting all of them into a `Vec` up front.
+///
+/// Unmarshalling an `ArrayIter` only reads the length of the array and skips over its content. The elements are
+/// unmarshalled when calling `next()`. The iterator never reads beyond the length the array declared, an element
+/// reaching over the end results in an error. After the first error the iterator stops.
+/// ```rust
+/// # use rustbus::message_builder::MessageBuilder;
+/// # use rustbus::wire::unmarshal::traits::ArrayIter;
+/// let mut msg = MessageBuilder::new().signal("io.killing.spark", "Blob", "/io/killing/spark").build();
+/// msg.body.push_param(vec![(1u32, "A"), (2u32, "B")]).unwrap();
+///
+/// let mut parser = msg.body.parser();
+/// let iter: ArrayIter<(u32, &str)> = parser.get().unwrap();
+/// for element in iter {
+///     let (num, text) = element.unwrap();
+///     println!("{}: {}", num, text);
+/// }
+/// ```
+pub struct ArrayIter<'buf, 'fds, E> {
+    ctx: UnmarshalContext<'fds, 'buf>,
+    end: usize,
+    _phantom: core::marker::PhantomData<E>,
+}
+
+impl<'buf, 'fds, E: Unmarshal<'buf, 'fds>> Iterator for ArrayIter<'buf, 'fds, E> {
+    type Item = Result<E, UnmarshalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ctx.offset >= self.end {
+            return None;
+        }
+        match E::unmarshal(&mut self.ctx) {
+            Ok((_, element)) => Some(Ok(element)),
+            Err(e) => {
+                self.ctx.offset = self.end;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<'buf, 'fds, E: Signature> Signature for ArrayIter<'buf, 'fds, E> {
+    fn signature() -> crate::signature::Type {
+        <[E]>::signature()
+    }
+    #[inline]
+    fn alignment() -> usize {
+        <[E]>::alignment()
+    }
+    #[inline]
+    fn sig_str(s_buf: &mut SignatureBuffer) {
+        <[E]>::sig_str(s_buf)
+    }
+    fn has_sig(sig: &str) -> bool {
+        <[E]>::has_sig(sig)
+    }
+}
+
+impl<'buf, 'fds, E: Unmarshal<'buf, 'fds>> Unmarshal<'buf, 'fds> for ArrayIter<'buf, 'fds, E> {
     fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
         let start_offset = ctx.offset;
         ctx.align_to(4)?;
         let (_, bytes_in_array) = u32::unmarshal(ctx)?;
 
-        // align even if no elements are present
-        ctx.align_to(8)?;
+        ctx.align_to(E::alignment())?;
 
-        let mut map = std::collections::HashMap::new();
+        let end = ctx.offset + bytes_in_array as usize;
+        if end > ctx.buf.len() {
+            return Err(UnmarshalError::NotEnoughBytes);
+        }
+        let iter = ArrayIter {
+            ctx: UnmarshalContext {
+                fds: ctx.fds,
+                buf: &ctx.buf[..end],
+                byteorder: ctx.byteorder,
+                offset: ctx.offset,
+                strict: ctx.strict,
+                depth: ctx.depth.enter_array()?,
+            },
+            end,
+            _phantom: core::marker::PhantomData,
+        };
+        ctx.offset = end;
+
+        Ok((ctx.offset - start_offset, iter))
+    }
+}
+
+/// Unmarshal the entries of a dict and pass them to `insert`, which returns false if the key was already present.
+pub(crate) fn unmarshal_dict_entries<'buf, 'fds, K, V, F>(
+    ctx: &mut UnmarshalContext<'fds, 'buf>,
+    mut insert: F,
+) -> Result<usize, UnmarshalError>
+where
+    K: Unmarshal<'buf, 'fds>,
+    V: Unmarshal<'buf, 'fds>,
+    F: FnMut(K, V) -> bool,
+{
+    let start_offset = ctx.offset;
+    ctx.align_to(4)?;
+    let (_, bytes_in_array) = u32::unmarshal(ctx)?;
+
+    // align even if no elements are present
+    ctx.align_to(8)?;
+
+    // reject bogus lengths before looping over elements that can not be there
+    if bytes_in_array as usize > ctx.remaining() {
+        return Err(UnmarshalError::NotEnoughBytes);
+    }
+
+    ctx.in_array(|ctx| {
         let mut bytes_used_counter = 0;
         while bytes_used_counter < bytes_in_array as usize {
             if ctx.offset >= ctx.buf.len() {
                 return Err(UnmarshalError::NotEnoughBytes);
             }
 
-            let elem_padding = util::align_offset(8, ctx.buf, ctx.offset)?;
-            bytes_used_counter += elem_padding;
-            ctx.offset += elem_padding;
+            bytes_used_counter += ctx.align_to(8)?;
 
             let (key_bytes_used, key) = K::unmarshal(ctx)?;
             bytes_used_counter += key_bytes_used;
 
-            let val_padding = util::align_offset(V::alignment(), ctx.buf, ctx.offset)?;
-            bytes_used_counter += val_padding;
-            ctx.offset += val_padding;
+            bytes_used_counter += ctx.align_to(V::alignment())?;
 
             let (val_bytes_used, val) = V::unmarshal(ctx)?;
             bytes_used_counter += val_bytes_used;
 
-            map.insert(key, val);
+            if !insert(key, val) {
+                return Err(UnmarshalError::DuplicateDictKey);
+            }
         }
+        Ok(())
+    })?;
 
-        let total_bytes_used = ctx.offset - start_offset;
+    Ok(ctx.offset - start_offset)
+}
 
-        Ok((total_bytes_used, map))
+/// Duplicate keys are rejected with `UnmarshalError::DuplicateDictKey`
+#[cfg(feature = "std")]
+impl<'buf, 'fds, K: Unmarshal<'buf, 'fds> + core::hash::Hash + Eq, V: Unmarshal<'buf, 'fds>>
+    Unmarshal<'buf, 'fds> for std::collections::HashMap<K, V>
+{
+    fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
+        let mut map = std::collections::HashMap::new();
+        let bytes = unmarshal_dict_entries(ctx, |k, v| map.insert(k, v).is_none())?;
+        Ok((bytes, map))
+    }
+}
+
+/// Duplicate keys are rejected with `UnmarshalError::DuplicateDictKey`
+impl<'buf, 'fds, K: Unmarshal<'buf, 'fds> + Ord, V: Unmarshal<'buf, 'fds>> Unmarshal<'buf, 'fds>
+    for alloc::collections::BTreeMap<K, V>
+{
+    fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> unmarshal::UnmarshalResult<Self> {
+        let mut map = alloc::collections::BTreeMap::new();
+        let bytes = unmarshal_dict_entries(ctx, |k, v| map.insert(k, v).is_none())?;
+        Ok((bytes, map))
     }
 }
 
@@ -291,15 +415,14 @@ impl<'buf, 'fds> Variant<'fds, 'buf> {
     ///
     /// [`MessageBodyParser::get()`]: /rustbus/message_builder/struct.MessageBodyParser.html#method.get
     pub fn get<T: Unmarshal<'buf, 'fds>>(&self) -> Result<T, UnmarshalError> {
-        if self.sig != T::signature() {
-            return Err(UnmarshalError::WrongSignature);
+        let expected = T::signature();
+        if self.sig != expected {
+            return Err(UnmarshalError::WrongSignature {
+                expected: expected.to_string(),
+                actual: self.sig.to_string(),
+            });
         }
-        let mut ctx = UnmarshalContext {
-            byteorder: self.byteorder,
-            offset: self.offset,
-            buf: self.buf,
-            fds: self.fds,
-        };
+        let mut ctx = UnmarshalContext::new(self.fds, self.buf, self.byteorder, self.offset);
         T::unmarshal(&mut ctx).map(|r| r.1)
     }
 }
@@ -324,12 +447,15 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for Variant<'fds, 'buf> {
         let (sig_bytes, desc) = util::unmarshal_signature(&ctx.buf[ctx.offset..])?;
         ctx.offset += sig_bytes;
 
+        let not_single_type = || UnmarshalError::VariantSignatureNotSingleType {
+            signature: desc.to_owned(),
+        };
         let mut sigs = match signature::Type::parse_description(desc) {
             Ok(sigs) => sigs,
-            Err(_) => return Err(UnmarshalError::WrongSignature),
+            Err(_) => return Err(not_single_type()),
         };
         if sigs.len() != 1 {
-            return Err(UnmarshalError::WrongSignature);
+            return Err(not_single_type());
         }
         let sig = sigs.remove(0);
 
@@ -337,11 +463,12 @@ impl<'buf, 'fds> Unmarshal<'buf, 'fds> for Variant<'fds, 'buf> {
 
         let start_loc = ctx.offset;
 
-        let val_bytes = crate::wire::validate_raw::validate_marshalled(
+        let val_bytes = crate::wire::validate_raw::validate_marshalled_at_depth(
             ctx.byteorder,
             ctx.offset,
             ctx.buf,
             &sig,
+            ctx.depth.enter_variant()?,
         )
         .map_err(|e| e.1)?;
         ctx.offset += val_bytes;
@@ -1,124 +1,173 @@
 //! Utility functions used often in many places
 
+use crate::alloc_prelude::*;
+#[cfg(feature = "std")]
 use std::io;
 
 use crate::wire::errors::MarshalError;
-use crate::wire::errors::UnmarshalError;
+use crate::wire::errors::{StringError, UnmarshalError};
+use crate::wire::marshal::MarshalBuffer;
 use crate::wire::unmarshal::UnmarshalResult;
 use crate::ByteOrder;
 
+/// The number of bytes `pad_to_align` adds to a buffer of length `offset`
 #[inline(always)]
-pub fn pad_to_align(align_to: usize, buf: &mut Vec<u8>) {
+pub fn padding_for(align_to: usize, offset: usize) -> usize {
+    (align_to - offset % align_to) % align_to
+}
+
+#[inline(always)]
+pub fn pad_to_align<B: MarshalBuffer + ?Sized>(align_to: usize, buf: &mut B) {
     let padding_needed = align_to - (buf.len() % align_to);
     if padding_needed != align_to {
-        buf.resize(buf.len() + padding_needed, 0);
-        debug_assert!(buf.len() % align_to == 0);
+        buf.extend_zeros(padding_needed);
+        debug_assert!(buf.len().is_multiple_of(align_to));
     }
 }
 
-pub fn write_u16(val: u16, byteorder: ByteOrder, buf: &mut Vec<u8>) {
+pub fn write_u16<B: MarshalBuffer + ?Sized>(val: u16, byteorder: ByteOrder, buf: &mut B) {
     match byteorder {
         ByteOrder::LittleEndian => buf.extend_from_slice(&val.to_le_bytes()),
         ByteOrder::BigEndian => buf.extend_from_slice(&val.to_be_bytes()),
     }
 }
 #[inline]
-pub fn write_u32(val: u32, byteorder: ByteOrder, buf: &mut Vec<u8>) {
+pub fn write_u32<B: MarshalBuffer + ?Sized>(val: u32, byteorder: ByteOrder, buf: &mut B) {
     match byteorder {
         ByteOrder::LittleEndian => buf.extend_from_slice(&val.to_le_bytes()),
         ByteOrder::BigEndian => buf.extend_from_slice(&val.to_be_bytes()),
     }
 }
-pub fn write_u64(val: u64, byteorder: ByteOrder, buf: &mut Vec<u8>) {
+pub fn write_u64<B: MarshalBuffer + ?Sized>(val: u64, byteorder: ByteOrder, buf: &mut B) {
     match byteorder {
         ByteOrder::LittleEndian => buf.extend_from_slice(&val.to_le_bytes()),
         ByteOrder::BigEndian => buf.extend_from_slice(&val.to_be_bytes()),
     }
 }
 
+#[cfg(feature = "std")]
 pub fn marshal_unixfd(
     i: &crate::wire::UnixFd,
     ctx: &mut crate::wire::marshal::MarshalContext,
 ) -> Result<(), MarshalError> {
     if let Some(fd) = i.get_raw_fd() {
-        let new_fd = nix::unistd::dup(fd)
-            .map_err(|err| MarshalError::DupUnixFd(io::Error::from(err).kind()))?;
-        ctx.fds.push(crate::wire::UnixFd::new(new_fd));
-
-        let idx = ctx.fds.len() - 1;
-        ctx.align_to(<crate::wire::UnixFd as crate::Signature>::alignment());
-        crate::wire::util::write_u32(idx as u32, ctx.byteorder, ctx.buf);
-        Ok(())
+        marshal_raw_fd(fd, ctx)
     } else {
         Err(MarshalError::EmptyUnixFd)
     }
 }
 
+/// Without `std` there are no unix fds that could be marshalled
+#[cfg(not(feature = "std"))]
+pub fn marshal_unixfd(
+    i: &crate::wire::UnixFd,
+    _ctx: &mut crate::wire::marshal::MarshalContext,
+) -> Result<(), MarshalError> {
+    match *i {}
+}
+
+/// Dup the fd into the fds of the message and write its index
+#[cfg(feature = "std")]
+pub fn marshal_raw_fd(
+    fd: std::os::unix::io::RawFd,
+    ctx: &mut crate::wire::marshal::MarshalContext,
+) -> Result<(), MarshalError> {
+    let new_fd =
+        nix::unistd::dup(fd).map_err(|err| MarshalError::DupUnixFd(io::Error::from(err).kind()))?;
+    ctx.fds.push(crate::wire::UnixFd::new(new_fd));
+
+    let idx = ctx.fds.len() - 1;
+    ctx.align_to(<crate::wire::UnixFd as crate::Signature>::alignment());
+    crate::wire::util::write_u32(idx as u32, ctx.byteorder, ctx.buf);
+    Ok(())
+}
+
 pub fn insert_u16(byteorder: ByteOrder, val: u16, buf: &mut [u8]) {
-    match byteorder {
-        ByteOrder::LittleEndian => {
-            buf[0] = (val) as u8;
-            buf[1] = (val >> 8) as u8;
-        }
-        ByteOrder::BigEndian => {
-            buf[0] = (val >> 8) as u8;
-            buf[1] = (val) as u8;
-        }
-    }
+    let bytes = match byteorder {
+        ByteOrder::LittleEndian => val.to_le_bytes(),
+        ByteOrder::BigEndian => val.to_be_bytes(),
+    };
+    buf[..2].copy_from_slice(&bytes);
 }
 pub fn insert_u32(byteorder: ByteOrder, val: u32, buf: &mut [u8]) {
-    match byteorder {
-        ByteOrder::LittleEndian => {
-            buf[0] = (val) as u8;
-            buf[1] = (val >> 8) as u8;
-            buf[2] = (val >> 16) as u8;
-            buf[3] = (val >> 24) as u8;
-        }
-        ByteOrder::BigEndian => {
-            buf[0] = (val >> 24) as u8;
-            buf[1] = (val >> 16) as u8;
-            buf[2] = (val >> 8) as u8;
-            buf[3] = (val) as u8;
-        }
-    }
+    let bytes = match byteorder {
+        ByteOrder::LittleEndian => val.to_le_bytes(),
+        ByteOrder::BigEndian => val.to_be_bytes(),
+    };
+    buf[..4].copy_from_slice(&bytes);
 }
 pub fn insert_u64(byteorder: ByteOrder, val: u64, buf: &mut [u8]) {
-    match byteorder {
-        ByteOrder::LittleEndian => {
-            buf[0] = (val) as u8;
-            buf[1] = (val >> 8) as u8;
-            buf[2] = (val >> 16) as u8;
-            buf[3] = (val >> 24) as u8;
-            buf[4] = (val >> 32) as u8;
-            buf[5] = (val >> 40) as u8;
-            buf[6] = (val >> 48) as u8;
-            buf[7] = (val >> 56) as u8;
-        }
-        ByteOrder::BigEndian => {
-            buf[7] = (val) as u8;
-            buf[6] = (val >> 8) as u8;
-            buf[5] = (val >> 16) as u8;
-            buf[4] = (val >> 24) as u8;
-            buf[3] = (val >> 32) as u8;
-            buf[2] = (val >> 40) as u8;
-            buf[1] = (val >> 48) as u8;
-            buf[0] = (val >> 56) as u8;
-        }
-    }
+    let bytes = match byteorder {
+        ByteOrder::LittleEndian => val.to_le_bytes(),
+        ByteOrder::BigEndian => val.to_be_bytes(),
+    };
+    buf[..8].copy_from_slice(&bytes);
 }
 
-pub fn write_string(val: &str, byteorder: ByteOrder, buf: &mut Vec<u8>) {
+pub fn write_string<B: MarshalBuffer + ?Sized>(val: &str, byteorder: ByteOrder, buf: &mut B) {
     let len = val.len() as u32;
     write_u32(len, byteorder, buf);
     buf.extend_from_slice(val.as_bytes());
     buf.push(0);
 }
 
-pub fn write_signature(val: &str, buf: &mut Vec<u8>) {
-    let len = val.len() as u8;
-    buf.push(len);
+/// Write a signature with its length byte and terminator. Nothing is written if it is longer than 255 bytes or nests
+/// arrays or structs deeper than 32 levels, which is checked here so no caller can write a signature the length byte
+/// can not describe. The rest of the grammar is not checked, signatures are validated when they are created.
+pub fn write_signature<B: MarshalBuffer + ?Sized>(
+    val: &str,
+    buf: &mut B,
+) -> Result<(), MarshalError> {
+    if val.len() > 255 {
+        return Err(crate::signature::Error::SignatureTooLong.into());
+    }
+    check_signature_nesting(val)?;
+    buf.push(val.len() as u8);
     buf.extend_from_slice(val.as_bytes());
     buf.push(0);
+    Ok(())
+}
+
+/// The deepest nesting of arrays and of structs (including dict entries) the spec allows in a signature
+pub const MAX_SIGNATURE_NESTING: usize = 32;
+
+fn check_signature_nesting(sig: &str) -> Result<(), MarshalError> {
+    // the containers that are still open, true for arrays. A signature has at most 255 bytes.
+    let mut open = [false; 255];
+    let mut depth = 0;
+    let (mut arrays, mut structs) = (0, 0);
+    for c in sig.bytes() {
+        match c {
+            b'a' => {
+                open[depth] = true;
+                depth += 1;
+                arrays += 1;
+            }
+            b'(' | b'{' => {
+                open[depth] = false;
+                depth += 1;
+                structs += 1;
+            }
+            _ => {
+                if c == b')' || c == b'}' {
+                    // malformed signatures are found by the validation, only keep the counts right here
+                    if depth > 0 && !open[depth - 1] {
+                        depth -= 1;
+                        structs -= 1;
+                    }
+                }
+                // a complete type also completes the arrays it is the element of
+                while depth > 0 && open[depth - 1] {
+                    depth -= 1;
+                    arrays -= 1;
+                }
+            }
+        }
+        if arrays > MAX_SIGNATURE_NESTING || structs > MAX_SIGNATURE_NESTING {
+            return Err(crate::signature::Error::NestingTooDeep.into());
+        }
+    }
+    Ok(())
 }
 
 pub fn parse_u64(number: &[u8], byteorder: ByteOrder) -> UnmarshalResult<u64> {
@@ -209,9 +258,7 @@ pub fn unmarshal_signature(buf: &[u8]) -> UnmarshalResult<&str> {
     if buf.len() < len + 2 {
         return Err(UnmarshalError::NotEnoughBytes);
     }
-    let sig_buf = &buf[1..];
-    let string = std::str::from_utf8(&sig_buf[..len])
-        .map_err(|_| crate::params::validation::Error::InvalidUtf8)?;
+    let string = str_with_nul(&buf[1..len + 2])?;
     Ok((len + 2, string))
 }
 
@@ -225,11 +272,23 @@ pub fn unmarshal_str<'r, 'a: 'r>(byteorder: ByteOrder, buf: &'a [u8]) -> Unmarsh
     if buf.len() < len + 5 {
         return Err(UnmarshalError::NotEnoughBytes);
     }
-    let str_buf = &buf[4..];
-    let string = std::str::from_utf8(&str_buf[..len])
-        .map_err(|_| crate::params::validation::Error::InvalidUtf8)?;
-    if string.contains('\0') {
-        return Err(crate::params::validation::Error::StringContainsNullByte.into());
-    }
+    let string = str_with_nul(&buf[4..len + 5])?;
     Ok((len + 5, string))
 }
+
+/// Check that `bytes` are valid UTF-8 followed by the terminating NUL byte, which must be the only one, and return
+/// the string without the terminator
+pub fn str_with_nul(bytes: &[u8]) -> Result<&str, UnmarshalError> {
+    let (terminator, content) = bytes.split_last().ok_or(UnmarshalError::NotEnoughBytes)?;
+    if *terminator != 0 {
+        return Err(UnmarshalError::InvalidString(StringError::NotNulTerminated));
+    }
+    if let Some(idx) = content.iter().position(|b| *b == 0) {
+        return Err(UnmarshalError::InvalidString(StringError::ContainsNul(idx)));
+    }
+    core::str::from_utf8(content).map_err(|e| {
+        UnmarshalError::InvalidString(StringError::InvalidUtf8 {
+            valid_up_to: e.valid_up_to(),
+        })
+    })
+}
@@ -3,8 +3,12 @@
 //! This could be useful for proxies that want to make sure they only forward valid messages. Since this does not
 //! try to unmarshal anything it should be more efficient than doing a whole unmarshalling just to check for correctness.
 
+use crate::alloc_prelude::*;
+use core::ops::Range;
+
 use crate::signature;
 use crate::wire::errors::UnmarshalError;
+use crate::wire::unmarshal::NestingDepth;
 use crate::ByteOrder;
 
 /// Either Ok(amount_of_bytes) or Err(position, ErrorCode)
@@ -15,10 +19,46 @@ pub fn validate_marshalled(
     offset: usize,
     raw: &[u8],
     sig: &signature::Type,
+) -> ValidationResult {
+    validate_marshalled_at_depth(byteorder, offset, raw, sig, NestingDepth::default())
+}
+
+/// Find the bytes of each of the values described by `sigs` in `raw` without unmarshalling them, e.g. to forward
+/// a single argument of a message body or to decode it later. The values are validated while walking the buffer, so
+/// this fails like `validate_marshalled` if `raw` is shorter than the signatures imply.
+///
+/// The ranges do not include the padding in front of the values. Bytes after the last value are not looked at.
+pub fn arg_ranges(
+    byteorder: ByteOrder,
+    raw: &[u8],
+    sigs: &[signature::Type],
+) -> Result<Vec<Range<usize>>, (usize, UnmarshalError)> {
+    let mut ranges = Vec::with_capacity(sigs.len());
+    let mut offset = 0;
+    for sig in sigs {
+        let used = validate_marshalled(byteorder, offset, raw, sig)?;
+        let alignment = sig.get_alignment();
+        let padding = (alignment - offset % alignment) % alignment;
+        ranges.push(offset + padding..offset + used);
+        offset += used;
+    }
+    Ok(ranges)
+}
+
+/// Validate a value that is nested `depth` deep into other containers
+pub fn validate_marshalled_at_depth(
+    byteorder: ByteOrder,
+    offset: usize,
+    raw: &[u8],
+    sig: &signature::Type,
+    depth: NestingDepth,
 ) -> ValidationResult {
     match sig {
         signature::Type::Base(b) => validate_marshalled_base(byteorder, offset, raw, *b),
-        signature::Type::Container(c) => validate_marshalled_container(byteorder, offset, raw, c),
+        signature::Type::Container(c) => {
+            let depth = depth.enter(c).map_err(|err| (offset, err))?;
+            validate_container_content(byteorder, offset, raw, c, depth)
+        }
     }
 }
 
@@ -129,6 +169,20 @@ pub fn validate_marshalled_container(
     offset: usize,
     buf: &[u8],
     sig: &signature::Container,
+) -> ValidationResult {
+    let depth = NestingDepth::default()
+        .enter(sig)
+        .map_err(|err| (offset, err))?;
+    validate_container_content(byteorder, offset, buf, sig, depth)
+}
+
+/// Validate the content of a container, `depth` already includes the container itself
+fn validate_container_content(
+    byteorder: ByteOrder,
+    offset: usize,
+    buf: &[u8],
+    sig: &signature::Container,
+    depth: NestingDepth,
 ) -> ValidationResult {
     match sig {
         signature::Container::Array(elem_sig) => {
@@ -153,7 +207,7 @@ pub fn validate_marshalled_container(
             if elem_sig.bytes_always_valid() {
                 // bytes_always_valid() only returns true for types whose
                 // length is equal to their alignment
-                if bytes_in_array as usize % elem_sig.get_alignment() != 0 {
+                if !(bytes_in_array as usize).is_multiple_of(elem_sig.get_alignment()) {
                     // there is not a whole number of elements in the array.
                     return Err((offset, UnmarshalError::NotEnoughBytes));
                 }
@@ -161,11 +215,12 @@ pub fn validate_marshalled_container(
                 let mut bytes_used_counter = 0;
                 let array_end = offset + bytes_in_array as usize;
                 while bytes_used_counter < bytes_in_array as usize {
-                    let bytes_used = validate_marshalled(
+                    let bytes_used = validate_marshalled_at_depth(
                         byteorder,
                         offset + bytes_used_counter,
                         &buf[..array_end],
                         elem_sig,
+                        depth,
                     )?;
                     bytes_used_counter += bytes_used;
                 }
@@ -208,11 +263,12 @@ pub fn validate_marshalled_container(
                     *key_sig,
                 )?;
                 bytes_used_counter += key_bytes;
-                let val_bytes = validate_marshalled(
+                let val_bytes = validate_marshalled_at_depth(
                     byteorder,
                     offset + bytes_used_counter,
                     buf_for_dict,
                     val_sig,
+                    depth,
                 )?;
                 bytes_used_counter += val_bytes;
             }
@@ -224,8 +280,13 @@ pub fn validate_marshalled_container(
 
             let mut bytes_used_counter = 0;
             for field_sig in sigs.as_ref() {
-                let bytes_used =
-                    validate_marshalled(byteorder, offset + bytes_used_counter, buf, field_sig)?;
+                let bytes_used = validate_marshalled_at_depth(
+                    byteorder,
+                    offset + bytes_used_counter,
+                    buf,
+                    field_sig,
+                    depth,
+                )?;
                 bytes_used_counter += bytes_used;
             }
             Ok(padding + bytes_used_counter)
@@ -237,17 +298,24 @@ pub fn validate_marshalled_container(
                 signature::Type::parse_description(sig_str).map_err(|e| (offset, e.into()))?;
             if sig.len() != 1 {
                 // There must be exactly one type in the signature!
-                return Err((offset, UnmarshalError::WrongSignature));
+                return Err((
+                    offset,
+                    UnmarshalError::VariantSignatureNotSingleType {
+                        signature: sig_str.to_owned(),
+                    },
+                ));
             }
             let sig = sig.remove(0);
             let offset = offset + sig_bytes_used;
 
-            let param_bytes_used = validate_marshalled(byteorder, offset, buf, &sig)?;
+            let param_bytes_used =
+                validate_marshalled_at_depth(byteorder, offset, buf, &sig, depth)?;
             Ok(sig_bytes_used + param_bytes_used)
         }
     }
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn test_raw_validation() {
     // make sure it catches errors
@@ -361,3 +429,36 @@ fn test_array_element_overflow() {
     let typ = &signature::Type::parse_description("as").unwrap();
     validate_marshalled(ByteOrder::LittleEndian, 0, &buf, &typ[0]).unwrap_err();
 }
+#[test]
+fn test_arg_ranges() {
+    use crate::wire::marshal::MarshalContext;
+    use crate::Marshal;
+
+    let mut buf = Vec::new();
+    let mut fds = Vec::new();
+    let mut ctx = MarshalContext {
+        buf: &mut buf,
+        fds: &mut fds,
+        byteorder: ByteOrder::LittleEndian,
+    };
+    7u8.marshal(&mut ctx).unwrap();
+    "abc".marshal(&mut ctx).unwrap();
+    [(1u8, 2u64)][..].marshal(&mut ctx).unwrap();
+    3u16.marshal(&mut ctx).unwrap();
+
+    let sigs = signature::Type::parse_description("ysa(yt)q").unwrap();
+    let ranges = arg_ranges(ByteOrder::LittleEndian, &buf, &sigs).unwrap();
+    // the array length is followed by padding to the 8 aligned struct, which counts into the array
+    assert_eq!(ranges, [0..1, 4..12, 12..32, 32..34]);
+    assert_eq!(&buf[ranges[1].clone()], b"\x03\0\0\0abc\0");
+    assert_eq!(ranges.last().unwrap().end, buf.len());
+
+    assert_eq!(
+        arg_ranges(ByteOrder::LittleEndian, &buf[..30], &sigs),
+        Err((16, UnmarshalError::NotEnoughBytesForCollection))
+    );
+    assert_eq!(
+        arg_ranges(ByteOrder::LittleEndian, &[], &[]),
+        Ok(Vec::new())
+    );
+}
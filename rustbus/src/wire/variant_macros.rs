@@ -88,6 +88,15 @@ macro_rules! dbus_variant_sig_marshal {
                 }
                 Ok(())
             }
+            fn marshalled_len_at(&self, offset: usize) -> usize {
+                match self {
+                    $(
+                        Self::$name(v) => $crate::Marshal::marshalled_len_as_variant(v, offset),
+                    )+
+                    // marshalling a Catchall panics, the length of a value that can not be marshalled is meaningless
+                    Self::Catchall(_) => 0,
+                }
+            }
         }
     };
 }
@@ -107,7 +116,9 @@ macro_rules! dbus_variant_sig_unmarshal {
                 let sig = if sig.len() == 1 {
                     sig.remove(0)
                 } else {
-                    return Err($crate::wire::errors::UnmarshalError::WrongSignature);
+                    return Err($crate::wire::errors::UnmarshalError::VariantSignatureNotSingleType {
+                        signature: sig_str.into(),
+                    });
                 };
                 ctx.offset += sig_bytes;
 
@@ -128,6 +139,7 @@ macro_rules! dbus_variant_sig_unmarshal {
     };
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn test_variant_sig_macro() {
     use crate::Marshal;
@@ -147,7 +159,7 @@ fn test_variant_sig_macro() {
 
     // so the macro is able to use rustbus, like it would have to when importet into other crates
 
-    dbus_variant_sig!(MyVariant, String => std::string::String; V2 => i32; Integer => u32);
+    dbus_variant_sig!(MyVariant, String => alloc::string::String; V2 => i32; Integer => u32);
     let v1 = MyVariant::String("ABCD".to_owned());
     let v2 = MyVariant::V2(0);
     let v3 = MyVariant::Integer(100);
@@ -162,14 +174,10 @@ fn test_variant_sig_macro() {
     )
     .unwrap();
 
-    let (bytes, (uv1, uv2, uv3)) =
-        <(MyVariant, MyVariant, MyVariant) as Unmarshal>::unmarshal(&mut UnmarshalContext {
-            buf: ctx.buf,
-            fds: ctx.fds,
-            byteorder: ctx.byteorder,
-            offset: 0,
-        })
-        .unwrap();
+    let (bytes, (uv1, uv2, uv3)) = <(MyVariant, MyVariant, MyVariant) as Unmarshal>::unmarshal(
+        &mut UnmarshalContext::new(ctx.fds, ctx.buf, ctx.byteorder, 0),
+    )
+    .unwrap();
     assert_eq!(uv1, v1);
     assert_ne!(uv1, v2);
     assert_ne!(uv1, v3);
@@ -179,17 +187,19 @@ fn test_variant_sig_macro() {
 
     assert_eq!(uv3, v3);
 
-    let (_bytes, uv4) = MyVariant::unmarshal(&mut UnmarshalContext {
-        buf: ctx.buf,
-        fds: ctx.fds,
-        byteorder: ctx.byteorder,
-        offset: bytes,
-    })
+    let (_bytes, uv4) = MyVariant::unmarshal(&mut UnmarshalContext::new(
+        ctx.fds,
+        ctx.buf,
+        ctx.byteorder,
+        bytes,
+    ))
     .unwrap();
     assert_eq!(
         uv4,
         MyVariant::Catchall(crate::signature::Type::Base(crate::signature::Base::Uint64))
     );
+    // a size query must not panic, even though marshalling a Catchall does
+    assert_eq!(uv4.marshalled_len_at(3), 0);
 
     type Map = std::collections::HashMap<String, (i32, u8, (u64, MyVariant))>;
     type Struct = (u32, u32, MyVariant);
@@ -211,12 +221,7 @@ fn test_variant_sig_macro() {
     (&v1, &v2, &v3, &v4).marshal(ctx).unwrap();
     let (_bytes, (uv1, uv2, uv3, uv4)) =
         <(MyVariant2, MyVariant2, MyVariant2, MyVariant2) as Unmarshal>::unmarshal(
-            &mut UnmarshalContext {
-                buf: ctx.buf,
-                fds: ctx.fds,
-                byteorder: ctx.byteorder,
-                offset: 0,
-            },
+            &mut UnmarshalContext::new(ctx.fds, ctx.buf, ctx.byteorder, 0),
         )
         .unwrap();
     assert_eq!(uv1, v1);
@@ -242,12 +247,12 @@ fn test_variant_sig_macro() {
         ctx.fds,
     )
     .unwrap();
-    let (_bytes, uv) = <MyVariant2 as Unmarshal>::unmarshal(&mut UnmarshalContext {
-        buf: ctx.buf,
-        fds: ctx.fds,
-        byteorder: ctx.byteorder,
-        offset: 0,
-    })
+    let (_bytes, uv) = <MyVariant2 as Unmarshal>::unmarshal(&mut UnmarshalContext::new(
+        ctx.fds,
+        ctx.buf,
+        ctx.byteorder,
+        0,
+    ))
     .unwrap();
     assert_eq!(
         uv,
@@ -348,6 +353,15 @@ macro_rules! dbus_variant_var_marshal {
                 }
                 Ok(())
             }
+            fn marshalled_len_at(&self, offset: usize) -> usize {
+                match self {
+                    $(
+                        Self::$name(v) => $crate::Marshal::marshalled_len_as_variant(v, offset),
+                    )+
+                    // marshalling a Catchall panics, the length of a value that can not be marshalled is meaningless
+                    Self::Catchall(_) => 0,
+                }
+            }
         }
     };
 }
@@ -382,6 +396,7 @@ macro_rules! dbus_variant_var_unmarshal {
     };
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn test_variant_var_macro() {
     use crate::Marshal;
@@ -420,12 +435,7 @@ fn test_variant_var_macro() {
 
     let (bytes, (uv1, uv2, uv3, uv4)) =
         <(MyVariant, MyVariant, MyVariant, MyVariant) as Unmarshal>::unmarshal(
-            &mut UnmarshalContext {
-                buf: ctx.buf,
-                fds: ctx.fds,
-                byteorder: ctx.byteorder,
-                offset: 0,
-            },
+            &mut UnmarshalContext::new(ctx.fds, ctx.buf, ctx.byteorder, 0),
         )
         .unwrap();
     assert!(match uv1 {
@@ -445,14 +455,15 @@ fn test_variant_var_macro() {
         _ => false,
     });
 
-    let (_bytes, uv4) = MyVariant::unmarshal(&mut UnmarshalContext {
-        buf: ctx.buf,
-        fds: ctx.fds,
-        byteorder: ctx.byteorder,
-        offset: bytes,
-    })
+    let (_bytes, uv4) = MyVariant::unmarshal(&mut UnmarshalContext::new(
+        ctx.fds,
+        ctx.buf,
+        ctx.byteorder,
+        bytes,
+    ))
     .unwrap();
 
+    assert_eq!(uv4.marshalled_len_at(3), 0);
     assert!(match uv4 {
         MyVariant::Catchall(var) => {
             var.get::<u64>().unwrap() == 0xFFFFu64
@@ -478,12 +489,7 @@ fn test_variant_var_macro() {
     (&v1, &v2, &v3, &v4).marshal(ctx).unwrap();
     let (_bytes, (uv1, uv2, uv3, uv4)) =
         <(MyVariant2, MyVariant2, MyVariant2, MyVariant2) as Unmarshal>::unmarshal(
-            &mut UnmarshalContext {
-                buf: ctx.buf,
-                fds: ctx.fds,
-                byteorder: ctx.byteorder,
-                offset: 0,
-            },
+            &mut UnmarshalContext::new(ctx.fds, ctx.buf, ctx.byteorder, 0),
         )
         .unwrap();
     assert!(match uv1 {
@@ -568,12 +574,12 @@ fn test_variant_var_macro() {
         ctx.fds,
     )
     .unwrap();
-    let (_bytes, uv) = <MyVariant2 as Unmarshal>::unmarshal(&mut UnmarshalContext {
-        buf: ctx.buf,
-        fds: ctx.fds,
-        byteorder: ctx.byteorder,
-        offset: 0,
-    })
+    let (_bytes, uv) = <MyVariant2 as Unmarshal>::unmarshal(&mut UnmarshalContext::new(
+        ctx.fds,
+        ctx.buf,
+        ctx.byteorder,
+        0,
+    ))
     .unwrap();
     assert!(match uv {
         MyVariant2::Catchall(var) => {
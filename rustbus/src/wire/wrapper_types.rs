@@ -1,7 +1,24 @@
-use std::convert::TryFrom;
+use crate::alloc_prelude::*;
+use core::convert::TryFrom;
 
+pub mod dict_entry;
+pub mod names;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod nonzero;
+pub mod time;
+#[cfg(feature = "std")]
 pub mod unixfd;
 
+/// Unix fds can only be passed along with messages over unix sockets, which need the `std` feature
+#[cfg(not(feature = "std"))]
+pub mod unixfd {
+    /// Without `std` no unix fds exist, so this can not be constructed. Messages can still be unmarshalled but any
+    /// unix fd in them fails with `UnmarshalError::BadFdIndex`.
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub enum UnixFd {}
+}
+
 #[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
 /// Wraps a String or a &str or whatever implements AsRef<str> and checks at creation, that it is a valid ObjectPath
 pub struct ObjectPath<S: AsRef<str>>(S);
@@ -19,6 +36,22 @@ impl<S: AsRef<str>> AsRef<str> for ObjectPath<S> {
         self.0.as_ref()
     }
 }
+impl<S: AsRef<str>> core::ops::Deref for ObjectPath<S> {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+impl<S: AsRef<str>> core::fmt::Display for ObjectPath<S> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+impl<S: AsRef<str>> From<ObjectPath<S>> for crate::params::Base<'_> {
+    fn from(path: ObjectPath<S>) -> Self {
+        crate::params::Base::ObjectPath(path.as_ref().to_owned())
+    }
+}
 
 impl<'a> TryFrom<&'a str> for ObjectPath<&'a str> {
     type Error = crate::params::validation::Error;
@@ -66,3 +99,35 @@ impl TryFrom<String> for SignatureWrapper<String> {
         SignatureWrapper::<String>::new(value)
     }
 }
+
+#[test]
+fn test_object_path() {
+    use crate::params::validation::{Error, ObjectPathError};
+    use crate::params::Base;
+
+    let path = ObjectPath::new("/io/killing/spark").unwrap();
+    assert!(path.starts_with("/io"));
+    assert_eq!(&*path, "/io/killing/spark");
+    assert_eq!(path.to_string(), "/io/killing/spark");
+    let base: Base = path.into();
+    assert_eq!(base, Base::ObjectPath("/io/killing/spark".to_owned()));
+    let mut sig = String::new();
+    base.sig().to_str(&mut sig);
+    assert_eq!(sig, "o");
+
+    assert_eq!(
+        ObjectPath::new("/io/"),
+        Err(Error::InvalidObjectPath(ObjectPathError::TrailingSlash))
+    );
+    assert_eq!(
+        ObjectPath::new("/io//spark"),
+        Err(Error::InvalidObjectPath(ObjectPathError::EmptyElement(4)))
+    );
+    assert_eq!(
+        ObjectPath::try_from("/io/killing.spark"),
+        Err(Error::InvalidObjectPath(ObjectPathError::InvalidChar {
+            character: '.',
+            offset: 11
+        }))
+    );
+}
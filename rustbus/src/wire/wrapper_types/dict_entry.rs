@@ -0,0 +1,160 @@
+//! Dict entries that keep their order and allow duplicate keys
+
+use crate::alloc_prelude::*;
+
+use crate::signature;
+use crate::wire::errors::{MarshalError, UnmarshalError};
+use crate::wire::marshal::traits::SignatureBuffer;
+use crate::wire::marshal::traits::{
+    dict_entries_marshalled_len, estimate_dict_size, marshal_dict_entries,
+};
+use crate::wire::marshal::MarshalContext;
+use crate::wire::unmarshal::traits::unmarshal_dict_entries;
+use crate::wire::unmarshal::{UnmarshalContext, UnmarshalResult};
+use crate::{Marshal, Signature, Unmarshal};
+
+/// One entry of a dict. A `Vec<DictEntry<K, V>>` is marshalled as `a{KV}` in the order of the vec and unlike a map it
+/// can hold the same key more than once. Unmarshalling keeps the order and the duplicates of the message.
+///
+/// The spec only allows base types as keys. Rust can not express that bound for all the types that marshal as base
+/// types, so marshalling and unmarshalling fail with `signature::Error::ShouldBeBaseType` if `K` is a container.
+///
+/// ```rust
+/// use rustbus::wire::DictEntry;
+/// use rustbus::MessageBuilder;
+///
+/// let mut msg = MessageBuilder::new().signal("io.killing.spark", "Changed", "/io/killing/spark").build();
+/// msg.body.push_param(vec![DictEntry::new("b", 2u32), DictEntry::new("a", 1), DictEntry::new("b", 3)]).unwrap();
+/// assert_eq!(msg.get_sig(), "a{su}");
+/// let entries: Vec<DictEntry<&str, u32>> = msg.body.parser().get().unwrap();
+/// assert_eq!(entries[2], DictEntry::new("b", 3));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct DictEntry<K, V> {
+    pub key: K,
+    pub value: V,
+}
+
+impl<K, V> DictEntry<K, V> {
+    pub fn new(key: K, value: V) -> Self {
+        Self { key, value }
+    }
+}
+
+impl<K, V> From<(K, V)> for DictEntry<K, V> {
+    fn from((key, value): (K, V)) -> Self {
+        Self::new(key, value)
+    }
+}
+
+impl<K, V> From<DictEntry<K, V>> for (K, V) {
+    fn from(entry: DictEntry<K, V>) -> Self {
+        (entry.key, entry.value)
+    }
+}
+
+fn check_key<K: Signature>() -> Result<(), signature::Error> {
+    match K::signature() {
+        signature::Type::Base(_) => Ok(()),
+        signature::Type::Container(_) => Err(signature::Error::ShouldBeBaseType),
+    }
+}
+
+impl<K: Signature, V: Signature> Signature for Vec<DictEntry<K, V>> {
+    #[inline]
+    fn signature() -> signature::Type {
+        <alloc::collections::BTreeMap<K, V>>::signature()
+    }
+    #[inline]
+    fn alignment() -> usize {
+        4
+    }
+    #[inline]
+    fn sig_str(s_buf: &mut SignatureBuffer) {
+        <alloc::collections::BTreeMap<K, V>>::sig_str(s_buf)
+    }
+    fn has_sig(sig: &str) -> bool {
+        <alloc::collections::BTreeMap<K, V>>::has_sig(sig)
+    }
+}
+
+impl<K: Marshal, V: Marshal> Marshal for Vec<DictEntry<K, V>> {
+    fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+        check_key::<K>()?;
+        marshal_dict_entries(self.iter().map(|entry| (&entry.key, &entry.value)), ctx)
+    }
+    fn estimate_size(&self) -> usize {
+        estimate_dict_size(self.iter().map(|entry| (&entry.key, &entry.value)))
+    }
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        dict_entries_marshalled_len(self.iter().map(|entry| (&entry.key, &entry.value)), offset)
+    }
+}
+
+impl<'buf, 'fds, K: Unmarshal<'buf, 'fds>, V: Unmarshal<'buf, 'fds>> Unmarshal<'buf, 'fds>
+    for Vec<DictEntry<K, V>>
+{
+    fn unmarshal(ctx: &mut UnmarshalContext<'fds, 'buf>) -> UnmarshalResult<Self> {
+        check_key::<K>().map_err(|e| {
+            UnmarshalError::Validation(crate::params::validation::Error::InvalidSignature(e))
+        })?;
+        let mut entries = Vec::new();
+        let bytes = unmarshal_dict_entries(ctx, |key, value| {
+            entries.push(DictEntry::new(key, value));
+            true
+        })?;
+        Ok((bytes, entries))
+    }
+}
+
+#[test]
+fn test_dict_entries() {
+    use crate::ByteOrder;
+    use alloc::collections::BTreeMap;
+
+    let entries = vec![
+        DictEntry::new("b", 2u32),
+        DictEntry::new("a", 1),
+        DictEntry::new("b", 3),
+    ];
+    let mut sig = SignatureBuffer::new();
+    <Vec<DictEntry<&str, u32>>>::sig_str(&mut sig);
+    assert_eq!(sig.as_str(), "a{su}");
+    assert_eq!(
+        <Vec<DictEntry<&str, u32>>>::signature(),
+        <BTreeMap<&str, u32>>::signature()
+    );
+
+    let mut fds = Vec::new();
+    let mut buf = Vec::new();
+    let mut ctx = MarshalContext {
+        buf: &mut buf,
+        fds: &mut fds,
+        byteorder: ByteOrder::LittleEndian,
+    };
+    entries.marshal(&mut ctx).unwrap();
+    assert!(entries.estimate_size() >= buf.len());
+
+    let mut ctx = UnmarshalContext::new(&fds, &buf, ByteOrder::LittleEndian, 0);
+    let (bytes, unmarshalled) = <Vec<DictEntry<&str, u32>>>::unmarshal(&mut ctx).unwrap();
+    assert_eq!(bytes, buf.len());
+    assert_eq!(unmarshalled, entries);
+
+    // a map with the same content rejects the duplicate key
+    ctx.offset = 0;
+    assert_eq!(
+        <BTreeMap<&str, u32>>::unmarshal(&mut ctx).unwrap_err(),
+        UnmarshalError::DuplicateDictKey
+    );
+
+    let bad_keys = vec![DictEntry::new((1u8,), 2u32)];
+    let mut ctx = MarshalContext {
+        buf: &mut buf,
+        fds: &mut fds,
+        byteorder: ByteOrder::LittleEndian,
+    };
+    assert_eq!(
+        bad_keys.marshal(&mut ctx),
+        Err(signature::Error::ShouldBeBaseType.into())
+    );
+}
@@ -0,0 +1,223 @@
+//! Wrappers for bus, interface and member names that are validated when they are created.
+//!
+//! They marshal as plain strings and are validated again when unmarshalling. They can be passed to the `MessageBuilder`
+//! wherever a `String` is expected.
+//!
+//! ```rust
+//! use rustbus::params::validation::{Error, NameError};
+//! use rustbus::wire::{BusName, InterfaceName, MemberName};
+//! use rustbus::MessageBuilder;
+//! use std::convert::TryFrom;
+//!
+//! let dest = BusName::try_from("io.killing.spark").unwrap();
+//! let interface = InterfaceName::try_from("io.killing.spark").unwrap();
+//! let member = MemberName::try_from("Echo").unwrap();
+//! let call = MessageBuilder::new()
+//!     .call(member)
+//!     .with_interface(interface)
+//!     .on("/io/killing/spark")
+//!     .at(dest)
+//!     .build();
+//!
+//! assert_eq!(
+//!     InterfaceName::try_from("io.3killing"),
+//!     Err(Error::InvalidInterface(NameError::ElementStartsWithDigit(3)))
+//! );
+//! ```
+
+use crate::alloc_prelude::*;
+use core::convert::TryFrom;
+use core::fmt;
+
+use crate::params::validation::{validate_busname, validate_interface, validate_membername, Error};
+use crate::wire::errors::MarshalError;
+use crate::wire::marshal::traits::SignatureBuffer;
+use crate::wire::marshal::MarshalContext;
+use crate::wire::unmarshal::UnmarshalContext;
+use crate::{Marshal, Signature, Unmarshal};
+
+macro_rules! name_wrapper {
+    ($(#[$meta:meta])* $name:ident, $validate:path) => {
+        $(#[$meta])*
+        #[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, PartialOrd, Ord)]
+        pub struct $name<S: AsRef<str>>(S);
+
+        impl<S: AsRef<str>> $name<S> {
+            pub fn new(name: S) -> Result<Self, Error> {
+                $validate(name.as_ref())?;
+                Ok(Self(name))
+            }
+            pub fn to_owned(&self) -> $name<String> {
+                $name(self.as_ref().to_owned())
+            }
+            pub fn into_inner(self) -> S {
+                self.0
+            }
+        }
+        impl<S: AsRef<str>> AsRef<str> for $name<S> {
+            fn as_ref(&self) -> &str {
+                self.0.as_ref()
+            }
+        }
+        /// Allows looking up names in maps by `&str`
+        impl<S: AsRef<str>> core::borrow::Borrow<str> for $name<S> {
+            fn borrow(&self) -> &str {
+                self.0.as_ref()
+            }
+        }
+        impl<S: AsRef<str>> core::ops::Deref for $name<S> {
+            type Target = str;
+            fn deref(&self) -> &str {
+                self.0.as_ref()
+            }
+        }
+        impl<S: AsRef<str>> fmt::Display for $name<S> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_ref())
+            }
+        }
+        impl<S: AsRef<str>> From<$name<S>> for String {
+            fn from(name: $name<S>) -> String {
+                name.as_ref().to_owned()
+            }
+        }
+        impl<'a> TryFrom<&'a str> for $name<&'a str> {
+            type Error = Error;
+            fn try_from(value: &'a str) -> Result<Self, Error> {
+                Self::new(value)
+            }
+        }
+        impl TryFrom<String> for $name<String> {
+            type Error = Error;
+            fn try_from(value: String) -> Result<Self, Error> {
+                Self::new(value)
+            }
+        }
+
+        impl<S: AsRef<str>> Signature for $name<S> {
+            #[inline]
+            fn signature() -> crate::signature::Type {
+                <&str>::signature()
+            }
+            #[inline]
+            fn alignment() -> usize {
+                <&str>::alignment()
+            }
+            #[inline]
+            fn sig_str(s_buf: &mut SignatureBuffer) {
+                <&str>::sig_str(s_buf)
+            }
+            #[inline]
+            fn has_sig(sig: &str) -> bool {
+                <&str>::has_sig(sig)
+            }
+        }
+        impl<S: AsRef<str>> Marshal for $name<S> {
+            #[inline]
+            fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+                self.as_ref().marshal(ctx)
+            }
+            #[inline]
+            fn estimate_size(&self) -> usize {
+                self.as_ref().estimate_size()
+            }
+            #[inline]
+            fn marshalled_len_at(&self, offset: usize) -> usize {
+                self.as_ref().marshalled_len_at(offset)
+            }
+        }
+        impl<'buf, 'fds, S: AsRef<str> + Unmarshal<'buf, 'fds>> Unmarshal<'buf, 'fds> for $name<S> {
+            fn unmarshal(
+                ctx: &mut UnmarshalContext<'fds, 'buf>,
+            ) -> crate::wire::unmarshal::UnmarshalResult<Self> {
+                let (bytes, val) = S::unmarshal(ctx)?;
+                Ok((bytes, Self::new(val)?))
+            }
+        }
+    };
+}
+
+name_wrapper!(
+    /// A unique (`:1.42`) or well-known (`io.killing.spark`) bus name
+    BusName,
+    validate_busname
+);
+name_wrapper!(
+    /// An interface name like `org.freedesktop.DBus.Properties`
+    InterfaceName,
+    validate_interface
+);
+name_wrapper!(
+    /// The name of a method or signal like `GetAll`
+    MemberName,
+    validate_membername
+);
+
+impl<S: AsRef<str>> BusName<S> {
+    /// Unique names are assigned to connections by the bus and start with ':'
+    pub fn is_unique(&self) -> bool {
+        self.as_ref().starts_with(':')
+    }
+}
+
+#[test]
+fn test_names() {
+    use crate::params::validation::NameError;
+    use crate::ByteOrder;
+
+    assert!(BusName::new(":1.42").unwrap().is_unique());
+    assert!(!BusName::new("io.killing-spark").unwrap().is_unique());
+    assert_eq!(
+        BusName::new("io.1killing"),
+        Err(Error::InvalidBusname(NameError::ElementStartsWithDigit(3)))
+    );
+    assert_eq!(
+        BusName::new("io..killing"),
+        Err(Error::InvalidBusname(NameError::EmptyElement(3)))
+    );
+    assert_eq!(
+        BusName::new(":1."),
+        Err(Error::InvalidBusname(NameError::EmptyElement(3)))
+    );
+    assert_eq!(
+        InterfaceName::new("io.killing-spark"),
+        Err(Error::InvalidInterface(NameError::InvalidChar {
+            character: '-',
+            offset: 10
+        }))
+    );
+    assert_eq!(
+        InterfaceName::new("a.".repeat(127) + "ab"),
+        Err(Error::InvalidInterface(NameError::TooLong))
+    );
+    assert!(InterfaceName::new("a.".repeat(126) + "ab").is_ok());
+    assert_eq!(
+        MemberName::new("1Member"),
+        Err(Error::InvalidMembername(NameError::ElementStartsWithDigit(
+            0
+        )))
+    );
+    assert_eq!(
+        MemberName::new(""),
+        Err(Error::InvalidMembername(NameError::Empty))
+    );
+
+    let member = MemberName::new("GetAll").unwrap();
+    assert_eq!(member.to_string(), "GetAll");
+    assert_eq!(&*member, "GetAll");
+
+    let mut fds = Vec::new();
+    let mut buf = Vec::new();
+    let mut ctx = MarshalContext {
+        buf: &mut buf,
+        fds: &mut fds,
+        byteorder: ByteOrder::LittleEndian,
+    };
+    member.marshal(&mut ctx).unwrap();
+    "Not.Member".marshal(&mut ctx).unwrap();
+
+    let mut ctx = UnmarshalContext::new(&fds, &buf, ByteOrder::LittleEndian, 0);
+    let (_, unmarshalled) = MemberName::<&str>::unmarshal(&mut ctx).unwrap();
+    assert_eq!(unmarshalled, member);
+    assert!(MemberName::<String>::unmarshal(&mut ctx).is_err());
+}
@@ -0,0 +1,157 @@
+//! `Marshal` and `Unmarshal` for the address types of `std::net` as strings (signature `s`), enabled with the `net` feature
+//!
+//! Addresses are marshalled with their `Display` impl and unmarshalled with their `FromStr` impl. A string that does not
+//! parse fails with `UnmarshalError::InvalidAddress`.
+//!
+//! * IPv4 addresses are in dotted decimal notation: `192.168.0.1`
+//! * IPv6 addresses are in the compressed lowercase form of RFC 5952: `fe80::1`. IPv4-mapped addresses keep the
+//!   dotted suffix: `::ffff:192.168.0.1`
+//! * Socket addresses append the port: `192.168.0.1:8080` and `[fe80::1]:8080`. A non zero scope id of an IPv6 socket
+//!   address is written as a decimal number after a `%` inside the brackets: `[fe80::1%2]:8080`. Scope ids given by
+//!   interface name (`%eth0`) are not accepted. The flow info of an IPv6 socket address is not transmitted and is
+//!   always 0 after unmarshalling.
+//!
+//! `IpAddr` does not carry a scope id, so `fe80::1%2` is not a valid `IpAddr` string.
+//!
+//! ```rust
+//! use rustbus::MessageBuilder;
+//! use std::net::{IpAddr, SocketAddr};
+//!
+//! let addr: SocketAddr = "[fe80::1%2]:8080".parse().unwrap();
+//! let mut msg = MessageBuilder::new()
+//!     .signal("io.killing.spark", "Connected", "/io/killing/spark")
+//!     .build();
+//! msg.body.push_param(addr).unwrap();
+//! msg.body.push_param("10.0.0.1").unwrap();
+//!
+//! let mut parser = msg.body.parser();
+//! assert_eq!(parser.get::<SocketAddr>().unwrap(), addr);
+//! assert_eq!(parser.get::<IpAddr>().unwrap(), IpAddr::from([10, 0, 0, 1]));
+//! ```
+
+use std::fmt::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use crate::wire::errors::{MarshalError, UnmarshalError};
+use crate::wire::marshal::traits::SignatureBuffer;
+use crate::wire::marshal::MarshalContext;
+use crate::wire::unmarshal::UnmarshalContext;
+use crate::{Marshal, Signature, Unmarshal};
+
+macro_rules! impl_address {
+    ($t:ty, $max_len:expr) => {
+        impl Signature for $t {
+            #[inline]
+            fn signature() -> crate::signature::Type {
+                <&str>::signature()
+            }
+            #[inline]
+            fn alignment() -> usize {
+                <&str>::alignment()
+            }
+            #[inline]
+            fn sig_str(s_buf: &mut SignatureBuffer) {
+                <&str>::sig_str(s_buf)
+            }
+            #[inline]
+            fn has_sig(sig: &str) -> bool {
+                <&str>::has_sig(sig)
+            }
+        }
+        impl Marshal for $t {
+            fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+                self.to_string().as_str().marshal(ctx)
+            }
+            /// Length prefix, the longest possible address and the terminating null byte
+            #[inline]
+            fn estimate_size(&self) -> usize {
+                4 + $max_len + 1
+            }
+            fn marshalled_len_at(&self, offset: usize) -> usize {
+                let mut len = DisplayLen(0);
+                let _ = write!(len, "{}", self);
+                crate::wire::util::padding_for(4, offset) + 4 + len.0 + 1
+            }
+        }
+        impl<'buf, 'fds> Unmarshal<'buf, 'fds> for $t {
+            fn unmarshal(
+                ctx: &mut UnmarshalContext<'fds, 'buf>,
+            ) -> crate::wire::unmarshal::UnmarshalResult<Self> {
+                let (bytes, val) = <&str>::unmarshal(ctx)?;
+                let addr = val
+                    .parse()
+                    .map_err(|_| UnmarshalError::InvalidAddress(val.to_owned()))?;
+                Ok((bytes, addr))
+            }
+        }
+    };
+}
+
+/// Counts the bytes of a `Display` output without allocating the string
+struct DisplayLen(usize);
+
+impl fmt::Write for DisplayLen {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+// `ffff:ffff:ffff:ffff:ffff:ffff:255.255.255.255`
+const MAX_IPV6_LEN: usize = 45;
+// `[` address `%` u32 scope id `]:` port
+const MAX_SOCKET_ADDR_LEN: usize = 1 + MAX_IPV6_LEN + 1 + 10 + 2 + 5;
+
+impl_address!(Ipv4Addr, 15);
+impl_address!(Ipv6Addr, MAX_IPV6_LEN);
+impl_address!(IpAddr, MAX_IPV6_LEN);
+impl_address!(SocketAddr, MAX_SOCKET_ADDR_LEN);
+
+#[test]
+fn test_addresses() {
+    use std::net::SocketAddrV6;
+
+    use crate::message_builder::MarshalledMessageBody;
+
+    let v4 = Ipv4Addr::new(192, 168, 0, 1);
+    let v6: Ipv6Addr = "fe80::1".parse().unwrap();
+    let scoped = SocketAddr::V6(SocketAddrV6::new(v6, 8080, 0, 2));
+    let mut body = MarshalledMessageBody::new();
+    body.push_param(v4).unwrap();
+    body.push_param(IpAddr::V6(v6)).unwrap();
+    body.push_param(scoped).unwrap();
+    body.push_param(SocketAddr::from((v4, 53))).unwrap();
+    body.push_param(v4.to_ipv6_mapped()).unwrap();
+    body.push_param("fe80::1%2").unwrap();
+    body.push_param("256.0.0.1").unwrap();
+
+    // length prefix, "[fe80::1%2]:8080" and the null byte
+    assert_eq!(scoped.marshalled_len_at(1), 3 + 4 + 16 + 1);
+
+    let mut parser = body.parser();
+    assert_eq!(parser.get::<&str>().unwrap(), "192.168.0.1");
+    assert_eq!(parser.get::<&str>().unwrap(), "fe80::1");
+    assert_eq!(parser.get::<&str>().unwrap(), "[fe80::1%2]:8080");
+    assert_eq!(parser.get::<&str>().unwrap(), "192.168.0.1:53");
+    assert_eq!(parser.get::<&str>().unwrap(), "::ffff:192.168.0.1");
+
+    let mut parser = body.parser();
+    assert_eq!(parser.get::<Ipv4Addr>().unwrap(), v4);
+    assert_eq!(parser.get::<IpAddr>().unwrap(), IpAddr::V6(v6));
+    assert_eq!(parser.get::<SocketAddr>().unwrap(), scoped);
+    assert_eq!(
+        parser.get::<SocketAddr>().unwrap(),
+        SocketAddr::from((v4, 53))
+    );
+    assert_eq!(parser.get::<Ipv6Addr>().unwrap(), v4.to_ipv6_mapped());
+    assert_eq!(
+        parser.get::<IpAddr>(),
+        Err(UnmarshalError::InvalidAddress("fe80::1%2".to_owned()))
+    );
+    // a failed get does not advance the parser
+    assert_eq!(parser.get::<&str>().unwrap(), "fe80::1%2");
+    assert_eq!(
+        parser.get::<Ipv4Addr>(),
+        Err(UnmarshalError::InvalidAddress("256.0.0.1".to_owned()))
+    );
+}
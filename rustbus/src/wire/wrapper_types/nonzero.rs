@@ -0,0 +1,166 @@
+//! `Marshal` and `Unmarshal` for the `NonZero` integer types of `core::num` as their underlying integer type
+//!
+//! `NonZeroU32` is marshalled exactly like a `u32` and has its signature `u`, same for the other types. Unmarshalling a
+//! 0 fails with `UnmarshalError::ZeroValue` naming the type, so the check happens while decoding the message.
+//!
+//! ```rust
+//! use rustbus::wire::errors::UnmarshalError;
+//! use rustbus::MessageBuilder;
+//! use std::num::NonZeroU32;
+//!
+//! let mut msg = MessageBuilder::new()
+//!     .signal("io.killing.spark", "Handles", "/io/killing/spark")
+//!     .build();
+//! msg.body.push_param(NonZeroU32::new(7).unwrap()).unwrap();
+//! msg.body.push_param(0u32).unwrap();
+//!
+//! let mut parser = msg.body.parser();
+//! assert_eq!(parser.get::<NonZeroU32>().unwrap().get(), 7);
+//! assert_eq!(
+//!     parser.get::<NonZeroU32>(),
+//!     Err(UnmarshalError::ZeroValue("NonZeroU32"))
+//! );
+//! ```
+
+use core::num::{
+    NonZeroI16, NonZeroI32, NonZeroI64, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8,
+};
+
+use crate::wire::errors::{MarshalError, UnmarshalError};
+use crate::wire::marshal::traits::SignatureBuffer;
+use crate::wire::marshal::MarshalContext;
+use crate::wire::unmarshal::UnmarshalContext;
+use crate::{Marshal, Signature, Unmarshal};
+
+macro_rules! impl_nonzero {
+    ($t:ident, $int:ty) => {
+        impl Signature for $t {
+            #[inline]
+            fn signature() -> crate::signature::Type {
+                <$int>::signature()
+            }
+            #[inline]
+            fn alignment() -> usize {
+                <$int>::alignment()
+            }
+            #[inline]
+            fn sig_str(s_buf: &mut SignatureBuffer) {
+                <$int>::sig_str(s_buf)
+            }
+            #[inline]
+            fn has_sig(sig: &str) -> bool {
+                <$int>::has_sig(sig)
+            }
+        }
+        impl Marshal for $t {
+            fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+                self.get().marshal(ctx)
+            }
+            #[inline]
+            fn estimate_size(&self) -> usize {
+                self.get().estimate_size()
+            }
+            #[inline]
+            fn marshalled_len_at(&self, offset: usize) -> usize {
+                self.get().marshalled_len_at(offset)
+            }
+        }
+        impl<'buf, 'fds> Unmarshal<'buf, 'fds> for $t {
+            fn unmarshal(
+                ctx: &mut UnmarshalContext<'fds, 'buf>,
+            ) -> crate::wire::unmarshal::UnmarshalResult<Self> {
+                let (bytes, val) = <$int>::unmarshal(ctx)?;
+                let val = $t::new(val).ok_or(UnmarshalError::ZeroValue(stringify!($t)))?;
+                Ok((bytes, val))
+            }
+        }
+    };
+}
+
+impl_nonzero!(NonZeroU8, u8);
+impl_nonzero!(NonZeroU16, u16);
+impl_nonzero!(NonZeroU32, u32);
+impl_nonzero!(NonZeroU64, u64);
+impl_nonzero!(NonZeroI16, i16);
+impl_nonzero!(NonZeroI32, i32);
+impl_nonzero!(NonZeroI64, i64);
+
+#[test]
+fn test_nonzero() {
+    use crate::message_builder::MarshalledMessageBody;
+    use crate::ByteOrder;
+
+    assert_eq!(NonZeroU8::signature(), u8::signature());
+    assert!(NonZeroI64::has_sig("x"));
+    assert!(!NonZeroU32::has_sig("i"));
+
+    for byteorder in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+        let mut body = MarshalledMessageBody::with_byteorder(byteorder);
+        body.push_param(NonZeroU8::new(u8::MAX).unwrap()).unwrap();
+        body.push_param(NonZeroU16::new(2).unwrap()).unwrap();
+        body.push_param(NonZeroU32::new(3).unwrap()).unwrap();
+        body.push_param(NonZeroU64::new(u64::MAX).unwrap()).unwrap();
+        body.push_param(NonZeroI16::new(-5).unwrap()).unwrap();
+        body.push_param(NonZeroI32::new(i32::MIN).unwrap()).unwrap();
+        body.push_param(NonZeroI64::new(-7).unwrap()).unwrap();
+        assert_eq!(body.parser().get_next_sig(), Some("y"));
+        assert_eq!(body.parser().sigs_left(), 7);
+
+        // the same bytes as the plain integers
+        let mut plain = MarshalledMessageBody::with_byteorder(byteorder);
+        plain
+            .push_param5(u8::MAX, 2u16, 3u32, u64::MAX, -5i16)
+            .unwrap();
+        plain.push_param2(i32::MIN, -7i64).unwrap();
+        assert_eq!(body.buf, plain.buf);
+
+        let mut parser = body.parser();
+        assert_eq!(parser.get::<NonZeroU8>().unwrap().get(), u8::MAX);
+        assert_eq!(parser.get::<NonZeroU16>().unwrap().get(), 2);
+        assert_eq!(parser.get::<NonZeroU32>().unwrap().get(), 3);
+        assert_eq!(parser.get::<NonZeroU64>().unwrap().get(), u64::MAX);
+        assert_eq!(parser.get::<NonZeroI16>().unwrap().get(), -5);
+        assert_eq!(parser.get::<NonZeroI32>().unwrap().get(), i32::MIN);
+        assert_eq!(parser.get::<NonZeroI64>().unwrap().get(), -7);
+    }
+
+    let mut body = MarshalledMessageBody::new();
+    body.push_param3(0u8, 0u16, 0u32).unwrap();
+    body.push_param4(0u64, 0i16, 0i32, 0i64).unwrap();
+    let mut parser = body.parser();
+    assert_eq!(
+        parser.get::<NonZeroU8>(),
+        Err(UnmarshalError::ZeroValue("NonZeroU8"))
+    );
+    // a failed get does not advance the parser
+    assert_eq!(parser.get::<u8>(), Ok(0));
+    assert_eq!(
+        parser.get::<NonZeroU16>(),
+        Err(UnmarshalError::ZeroValue("NonZeroU16"))
+    );
+    parser.get::<u16>().unwrap();
+    assert_eq!(
+        parser.get::<NonZeroU32>(),
+        Err(UnmarshalError::ZeroValue("NonZeroU32"))
+    );
+    parser.get::<u32>().unwrap();
+    assert_eq!(
+        parser.get::<NonZeroU64>(),
+        Err(UnmarshalError::ZeroValue("NonZeroU64"))
+    );
+    parser.get::<u64>().unwrap();
+    assert_eq!(
+        parser.get::<NonZeroI16>(),
+        Err(UnmarshalError::ZeroValue("NonZeroI16"))
+    );
+    parser.get::<i16>().unwrap();
+    assert_eq!(
+        parser.get::<NonZeroI32>(),
+        Err(UnmarshalError::ZeroValue("NonZeroI32"))
+    );
+    parser.get::<i32>().unwrap();
+    assert_eq!(
+        parser.get::<NonZeroI64>(),
+        Err(UnmarshalError::ZeroValue("NonZeroI64"))
+    );
+}
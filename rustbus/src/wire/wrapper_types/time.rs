@@ -0,0 +1,212 @@
+//! Wrappers that marshal `Duration` and `SystemTime` as a `u64` (signature `t`) of micro- or milliseconds.
+//!
+//! D-Bus has no native type for time values, so services usually send them as a `t` counting micro- or milliseconds.
+//! Which unit is used is up to the service so pick the wrapper that matches the interface you are talking to:
+//!
+//! * `Micros` / `Millis` wrap a `Duration`, e.g. a timeout or an interval
+//! * `UnixMicros` / `UnixMillis` wrap a `SystemTime` that is sent as the time since the `UNIX_EPOCH`
+//!
+//! Marshalling fails with `MarshalError::TimeOutOfRange` if the value does not fit into a `u64` (or if a
+//! `SystemTime` lies before the `UNIX_EPOCH`). Unmarshalling fails with `UnmarshalError::TimeOutOfRange` if the
+//! received `u64` can not be represented as a `SystemTime` on this platform. Sub-unit precision is truncated.
+//!
+//! `UnixMicros` and `UnixMillis` need the `std` feature.
+
+use crate::wire::errors::{MarshalError, UnmarshalError};
+use crate::wire::marshal::traits::SignatureBuffer;
+use crate::wire::marshal::MarshalContext;
+use crate::wire::unmarshal::UnmarshalContext;
+use crate::{Marshal, Signature, Unmarshal};
+
+use core::convert::TryFrom;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A `Duration` that is marshalled as a `u64` of microseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Micros(pub Duration);
+
+/// A `Duration` that is marshalled as a `u64` of milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Millis(pub Duration);
+
+/// A `SystemTime` that is marshalled as a `u64` of microseconds since the `UNIX_EPOCH`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnixMicros(pub SystemTime);
+
+/// A `SystemTime` that is marshalled as a `u64` of milliseconds since the `UNIX_EPOCH`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnixMillis(pub SystemTime);
+
+impl Micros {
+    fn to_u64(self) -> Result<u64, MarshalError> {
+        u64::try_from(self.0.as_micros()).map_err(|_| MarshalError::TimeOutOfRange)
+    }
+    fn from_u64(val: u64) -> Result<Self, UnmarshalError> {
+        Ok(Micros(Duration::from_micros(val)))
+    }
+}
+
+impl Millis {
+    fn to_u64(self) -> Result<u64, MarshalError> {
+        u64::try_from(self.0.as_millis()).map_err(|_| MarshalError::TimeOutOfRange)
+    }
+    fn from_u64(val: u64) -> Result<Self, UnmarshalError> {
+        Ok(Millis(Duration::from_millis(val)))
+    }
+}
+
+#[cfg(feature = "std")]
+impl UnixMicros {
+    fn to_u64(self) -> Result<u64, MarshalError> {
+        let since_epoch = self
+            .0
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| MarshalError::TimeOutOfRange)?;
+        Micros(since_epoch).to_u64()
+    }
+    fn from_u64(val: u64) -> Result<Self, UnmarshalError> {
+        UNIX_EPOCH
+            .checked_add(Duration::from_micros(val))
+            .map(UnixMicros)
+            .ok_or(UnmarshalError::TimeOutOfRange)
+    }
+}
+
+#[cfg(feature = "std")]
+impl UnixMillis {
+    fn to_u64(self) -> Result<u64, MarshalError> {
+        let since_epoch = self
+            .0
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| MarshalError::TimeOutOfRange)?;
+        Millis(since_epoch).to_u64()
+    }
+    fn from_u64(val: u64) -> Result<Self, UnmarshalError> {
+        UNIX_EPOCH
+            .checked_add(Duration::from_millis(val))
+            .map(UnixMillis)
+            .ok_or(UnmarshalError::TimeOutOfRange)
+    }
+}
+
+macro_rules! impl_time_wrapper {
+    ($t:ty) => {
+        impl Signature for $t {
+            #[inline]
+            fn signature() -> crate::signature::Type {
+                u64::signature()
+            }
+            #[inline]
+            fn alignment() -> usize {
+                u64::alignment()
+            }
+            #[inline]
+            fn sig_str(s_buf: &mut SignatureBuffer) {
+                u64::sig_str(s_buf)
+            }
+            #[inline]
+            fn has_sig(sig: &str) -> bool {
+                u64::has_sig(sig)
+            }
+        }
+        impl Marshal for $t {
+            fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+                self.to_u64()?.marshal(ctx)
+            }
+            #[inline]
+            fn estimate_size(&self) -> usize {
+                8
+            }
+            #[inline]
+            fn marshalled_len_at(&self, offset: usize) -> usize {
+                0u64.marshalled_len_at(offset)
+            }
+        }
+        impl<'buf, 'fds> Unmarshal<'buf, 'fds> for $t {
+            fn unmarshal(
+                ctx: &mut UnmarshalContext<'fds, 'buf>,
+            ) -> crate::wire::unmarshal::UnmarshalResult<Self> {
+                let (bytes, val) = u64::unmarshal(ctx)?;
+                Ok((bytes, Self::from_u64(val)?))
+            }
+        }
+    };
+}
+
+impl_time_wrapper!(Micros);
+impl_time_wrapper!(Millis);
+#[cfg(feature = "std")]
+impl_time_wrapper!(UnixMicros);
+#[cfg(feature = "std")]
+impl_time_wrapper!(UnixMillis);
+
+#[cfg(feature = "std")]
+#[test]
+fn test_time_wrappers() {
+    use crate::ByteOrder;
+
+    fn roundtrip<T>(val: T) -> (u64, T)
+    where
+        T: Marshal + for<'buf, 'fds> Unmarshal<'buf, 'fds>,
+    {
+        let mut fds = Vec::new();
+        let mut buf = Vec::new();
+        let mut ctx = MarshalContext {
+            buf: &mut buf,
+            fds: &mut fds,
+            byteorder: ByteOrder::LittleEndian,
+        };
+        val.marshal(&mut ctx).unwrap();
+
+        let mut ctx = UnmarshalContext::new(&fds, &buf, ByteOrder::LittleEndian, 0);
+        let raw = u64::unmarshal(&mut ctx).unwrap().1;
+        ctx.offset = 0;
+        let (bytes, val) = T::unmarshal(&mut ctx).unwrap();
+        assert_eq!(bytes, 8);
+        (raw, val)
+    }
+
+    assert_eq!(Micros::signature(), u64::signature());
+    assert!(UnixMillis::has_sig("t"));
+
+    let dur = Duration::new(3, 4_005_006);
+    assert_eq!(
+        roundtrip(Micros(dur)),
+        (3_004_005, Micros(Duration::from_micros(3_004_005)))
+    );
+    assert_eq!(
+        roundtrip(Millis(dur)),
+        (3_004, Millis(Duration::from_millis(3_004)))
+    );
+
+    let time = UNIX_EPOCH + Duration::from_micros(1_600_000_000_123_456);
+    assert_eq!(
+        roundtrip(UnixMicros(time)),
+        (1_600_000_000_123_456, UnixMicros(time))
+    );
+    assert_eq!(
+        roundtrip(UnixMillis(time)),
+        (
+            1_600_000_000_123,
+            UnixMillis(UNIX_EPOCH + Duration::from_millis(1_600_000_000_123))
+        )
+    );
+
+    // values that do not fit into a u64
+    assert_eq!(
+        Micros(Duration::from_secs(u64::MAX)).to_u64(),
+        Err(MarshalError::TimeOutOfRange)
+    );
+    assert_eq!(
+        Millis(Duration::from_secs(u64::MAX)).to_u64(),
+        Err(MarshalError::TimeOutOfRange)
+    );
+    assert_eq!(
+        UnixMillis(UNIX_EPOCH - Duration::from_secs(1)).to_u64(),
+        Err(MarshalError::TimeOutOfRange)
+    );
+}
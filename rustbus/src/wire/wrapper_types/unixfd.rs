@@ -4,17 +4,42 @@ use crate::wire::marshal::MarshalContext;
 use crate::wire::unmarshal::UnmarshalContext;
 use crate::{Marshal, Signature, Unmarshal};
 
+use alloc::sync::Arc;
+use core::sync::atomic::AtomicI32;
 use std::io;
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
 use std::os::unix::io::RawFd;
-use std::sync::atomic::AtomicI32;
-use std::sync::Arc;
 
+/// Why `UnixFd::dup` failed
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DupError {
+    /// The dup syscall failed
     Io(io::ErrorKind),
+    /// The fd has been taken out of the `UnixFd` with `take_raw_fd`
     AlreadyTaken,
 }
 
+impl core::fmt::Display for DupError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DupError::Io(kind) => write!(f, "Could not dup the fd: {}", io::Error::from(*kind)),
+            DupError::AlreadyTaken => f.write_str("The fd has already been taken"),
+        }
+    }
+}
+
+impl core::error::Error for DupError {}
+
+/// Allows using `?` on `UnixFd::dup` in functions returning `io::Result`
+impl From<DupError> for io::Error {
+    fn from(e: DupError) -> Self {
+        match e {
+            DupError::Io(kind) => io::Error::from(kind),
+            DupError::AlreadyTaken => io::Error::new(io::ErrorKind::NotFound, e),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct UnixFdInner {
     inner: AtomicI32,
@@ -35,7 +60,7 @@ impl UnixFdInner {
     /// This is kinda like Cell::take it takes the FD and resets the atomic int to FD_INVALID which represents the invalid / taken state here.
     fn take(&self) -> Option<RawFd> {
         // load fd and see if it is already been taken
-        let loaded_fd: RawFd = self.inner.load(std::sync::atomic::Ordering::SeqCst);
+        let loaded_fd: RawFd = self.inner.load(core::sync::atomic::Ordering::SeqCst);
         if loaded_fd == Self::FD_INVALID {
             None
         } else {
@@ -43,21 +68,17 @@ impl UnixFdInner {
             let swapped_fd = self.inner.compare_exchange(
                 loaded_fd,
                 Self::FD_INVALID,
-                std::sync::atomic::Ordering::SeqCst,
-                std::sync::atomic::Ordering::SeqCst,
+                core::sync::atomic::Ordering::SeqCst,
+                core::sync::atomic::Ordering::SeqCst,
             );
             //  If swapped_fd == fd then we did a sucessful swap and we actually took the value
-            if let Ok(taken_fd) = swapped_fd {
-                Some(taken_fd)
-            } else {
-                None
-            }
+            swapped_fd.ok()
         }
     }
 
     /// This is kinda like Cell::get it returns the FD, FD_INVALID represents the invalid / taken state here.
     fn get(&self) -> Option<RawFd> {
-        let loaded = self.inner.load(std::sync::atomic::Ordering::SeqCst);
+        let loaded = self.inner.load(core::sync::atomic::Ordering::SeqCst);
         if loaded == Self::FD_INVALID {
             None
         } else {
@@ -86,11 +107,22 @@ impl UnixFdInner {
 /// 1. You can call take_raw_fd(). At this point UnixFd releases ownership. You are now responsible of closing the FD.
 /// 1. You can call get_raw_fd(). This will not release ownership, UnixFd will still close it if no more references to it exist.
 ///
+/// Clones of a UnixFd refer to the same FD, they do not dup() it. Taking the FD out of one of them empties all of them, so the
+/// FD can only be taken once. The FD is closed when the last clone is dropped, but only if it has not been taken. This
+/// also holds for clones of messages: they share the FDs with the original message.
+///
+/// | operation | consumes the FD |
+/// |-----------|-----------------|
+/// | `take_raw_fd()` | yes, the caller has to close it |
+/// | `get_raw_fd()`, `clone()` | no |
+/// | `dup()` | no, the new UnixFd owns a new FD |
+/// | dropping the last clone | yes, it is closed if it was not taken |
+///
 /// ## UnixFds and messages
 /// 1. When a UnixFd is **marshalled** rustbus will dup() the FD so that the message and the original UnixFd do not depend on each others lifetime. You are free to use
-/// or close the original one.
+///    or close the original one.
 /// 1. When a UnixFd is **unmarshalled** rustbus will **NOT** dup() the FD. This means if you call take_raw_fd(), it is gone from the message too! If you do not want this,
-/// you have to call dup() and then get_raw_fd() or take_raw_fd()
+///    you have to call dup() and then get_raw_fd() or take_raw_fd()
 #[derive(Clone, Debug)]
 pub struct UnixFd(Arc<UnixFdInner>);
 impl UnixFd {
@@ -109,13 +141,15 @@ impl UnixFd {
     /// Gets a owning `RawFd` from the UnixFd.
     /// Subsequent attempt to get the `RawFd` from
     /// other `UnixFd` referencing the same file descriptor will
-    /// fail.
+    /// fail. Only the first call on any of the clones returns the FD, so it can not be closed twice.
     pub fn take_raw_fd(self) -> Option<RawFd> {
         self.0.take()
     }
 
     /// Duplicate the underlying FD so you can use it as you will. This is different from just calling
     /// clone(). Clone only makes a new ref to the same underlying FD.
+    ///
+    /// Errors with `DupError::AlreadyTaken` if the FD has been taken. `DupError` converts into `io::Error`.
     pub fn dup(&self) -> Result<Self, DupError> {
         self.0.dup().map(|new_inner| Self(Arc::new(new_inner)))
     }
@@ -128,15 +162,28 @@ impl PartialEq<UnixFd> for UnixFd {
     }
 }
 
-// These two impls are just there so that params::Base can derive Eq and Hash so they can be used as Keys
+// These impls are just there so that params::Base can derive Eq and Hash so they can be used as Keys
 // in dicts. This does not really make sense for unixfds (why would you use them as keys...) but the
-// contracts for Eq and Hash should be fulfilled by these impls.
+// contracts for Eq, Ord and Hash should be fulfilled by these impls.
 impl Eq for UnixFd {}
-impl std::hash::Hash for UnixFd {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+impl core::hash::Hash for UnixFd {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
         state.write_i32(self.get_raw_fd().unwrap_or(0));
     }
 }
+impl Ord for UnixFd {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        if Arc::ptr_eq(&self.0, &other.0) {
+            return core::cmp::Ordering::Equal;
+        }
+        self.get_raw_fd().cmp(&other.get_raw_fd())
+    }
+}
+impl PartialOrd for UnixFd {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
 impl Signature for UnixFd {
     fn signature() -> crate::signature::Type {
@@ -157,6 +204,14 @@ impl Marshal for UnixFd {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
         crate::wire::util::marshal_unixfd(self, ctx)
     }
+    #[inline]
+    fn estimate_size(&self) -> usize {
+        4
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        0u32.marshalled_len_at(offset)
+    }
 }
 impl Signature for &dyn std::os::unix::io::AsRawFd {
     fn signature() -> crate::signature::Type {
@@ -175,15 +230,73 @@ impl Signature for &dyn std::os::unix::io::AsRawFd {
 }
 impl Marshal for &dyn std::os::unix::io::AsRawFd {
     fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
-        let fd = self.as_raw_fd();
-        let new_fd = nix::unistd::dup(fd)
-            .map_err(|err| MarshalError::DupUnixFd(io::Error::from(err).kind()))?;
-        ctx.fds.push(UnixFd::new(new_fd));
+        crate::wire::util::marshal_raw_fd(self.as_raw_fd(), ctx)
+    }
+    #[inline]
+    fn marshalled_len_at(&self, offset: usize) -> usize {
+        0u32.marshalled_len_at(offset)
+    }
+}
+
+/// The io safety types marshal like UnixFd. Marshalling dups the fd, the original stays open and owned by you.
+macro_rules! fd_type_impls {
+    ($($typ:ty),*) => {
+        $(
+            impl Signature for $typ {
+                fn signature() -> crate::signature::Type {
+                    UnixFd::signature()
+                }
+                fn alignment() -> usize {
+                    UnixFd::alignment()
+                }
+                #[inline]
+                fn sig_str(s_buf: &mut SignatureBuffer) {
+                    UnixFd::sig_str(s_buf)
+                }
+                fn has_sig(sig: &str) -> bool {
+                    UnixFd::has_sig(sig)
+                }
+            }
+            impl Marshal for $typ {
+                fn marshal(&self, ctx: &mut MarshalContext) -> Result<(), MarshalError> {
+                    crate::wire::util::marshal_raw_fd(self.as_raw_fd(), ctx)
+                }
+                #[inline]
+                fn estimate_size(&self) -> usize {
+                    4
+                }
+                #[inline]
+                fn marshalled_len_at(&self, offset: usize) -> usize {
+                    0u32.marshalled_len_at(offset)
+                }
+            }
+        )*
+    };
+}
+fd_type_impls!(OwnedFd, BorrowedFd<'_>, std::fs::File);
 
-        let idx = ctx.fds.len() - 1;
-        ctx.align_to(Self::alignment());
-        crate::wire::util::write_u32(idx as u32, ctx.byteorder, ctx.buf);
-        Ok(())
+/// Takes the fd out of the message, the message will not close it anymore. Unmarshalling the same fd
+/// a second time, into an `OwnedFd` or by `UnixFd::take_raw_fd`, fails with `UnmarshalError::FdAlreadyTaken`.
+impl<'buf, 'fds> Unmarshal<'buf, 'fds> for OwnedFd {
+    fn unmarshal(
+        ctx: &mut UnmarshalContext<'fds, 'buf>,
+    ) -> crate::wire::unmarshal::UnmarshalResult<Self> {
+        let (bytes, idx) = u32::unmarshal(ctx)?;
+        let idx = idx as usize;
+        let fd = ctx.fds.get(idx).ok_or(UnmarshalError::BadFdIndex(idx))?;
+        match fd.clone().take_raw_fd() {
+            // Safety: the fd was owned by the UnixFd which gave up the ownership
+            Some(raw) => Ok((bytes, unsafe { OwnedFd::from_raw_fd(raw) })),
+            None => Err(UnmarshalError::FdAlreadyTaken(idx)),
+        }
+    }
+}
+impl<'buf, 'fds> Unmarshal<'buf, 'fds> for std::fs::File {
+    fn unmarshal(
+        ctx: &mut UnmarshalContext<'fds, 'buf>,
+    ) -> crate::wire::unmarshal::UnmarshalResult<Self> {
+        let (bytes, fd) = OwnedFd::unmarshal(ctx)?;
+        Ok((bytes, fd.into()))
     }
 }
 
@@ -234,9 +347,9 @@ fn test_races_in_unixfd() {
     const NUM_THREADS: usize = 20;
     const NUM_RUNS: usize = 100;
 
-    let barrier = std::sync::Arc::new(std::sync::Barrier::new(NUM_THREADS + 1));
+    let barrier = alloc::sync::Arc::new(std::sync::Barrier::new(NUM_THREADS + 1));
 
-    let result = std::sync::Arc::new(std::sync::Mutex::new(vec![false; NUM_THREADS]));
+    let result = alloc::sync::Arc::new(std::sync::Mutex::new(vec![false; NUM_THREADS]));
 
     for _ in 0..NUM_RUNS {
         for idx in 0..NUM_THREADS {
@@ -273,3 +386,115 @@ fn test_unixfd_dup() {
     let _raw = fd.clone().take_raw_fd();
     assert_eq!(fd.dup(), Err(DupError::AlreadyTaken));
 }
+
+#[test]
+fn test_too_many_fds() {
+    use crate::wire::errors::MarshalError;
+    use crate::wire::marshal::{marshal, MAX_UNIX_FDS};
+
+    let fd = UnixFd::new(nix::unistd::dup(1).unwrap());
+    let mut msg = crate::message_builder::MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    for _ in 0..MAX_UNIX_FDS {
+        msg.body.push_param(&fd).unwrap();
+    }
+    assert_eq!(msg.body.raw_fds.len(), MAX_UNIX_FDS);
+    marshal(&msg, 1, &mut Vec::new()).unwrap();
+
+    msg.body.push_param(&fd).unwrap();
+    assert_eq!(
+        marshal(&msg, 1, &mut Vec::new()),
+        Err(MarshalError::TooManyFds {
+            count: MAX_UNIX_FDS + 1,
+            limit: MAX_UNIX_FDS
+        })
+    );
+}
+
+#[test]
+fn test_unixfd_ownership() {
+    use std::io::{Read, Write};
+    use std::os::fd::FromRawFd;
+
+    // dropping the last clone closes the write end, the reader sees EOF
+    let (read, write) = nix::unistd::pipe().unwrap();
+    let mut read = std::fs::File::from(read);
+    let fd = UnixFd::new(std::os::fd::IntoRawFd::into_raw_fd(write));
+    let clone = fd.clone();
+    drop(fd);
+    assert!(clone.get_raw_fd().is_some());
+    drop(clone);
+    let mut buf = Vec::new();
+    read.read_to_end(&mut buf).unwrap();
+    assert!(buf.is_empty());
+
+    // a taken fd survives the UnixFd
+    let (read, write) = nix::unistd::pipe().unwrap();
+    let mut read = std::fs::File::from(read);
+    let fd = UnixFd::new(std::os::fd::IntoRawFd::into_raw_fd(write));
+    let clone = fd.clone();
+    let raw = fd.take_raw_fd().unwrap();
+    assert!(clone.get_raw_fd().is_none());
+    assert!(clone.clone().take_raw_fd().is_none());
+    drop(clone);
+    let mut write = unsafe { std::fs::File::from_raw_fd(raw) };
+    write.write_all(b"still open").unwrap();
+    drop(write);
+    read.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, b"still open");
+}
+
+#[test]
+fn test_dup_error_into_io_error() {
+    fn dup_taken() -> io::Result<UnixFd> {
+        let fd = UnixFd::new(nix::unistd::dup(1).unwrap());
+        let taken = fd.clone();
+        nix::unistd::close(taken.take_raw_fd().unwrap()).unwrap();
+        Ok(fd.dup()?)
+    }
+    assert_eq!(dup_taken().unwrap_err().kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_io_safety_types() {
+    use crate::message_builder::MessageBuilder;
+    use std::io::{Read, Write};
+    use std::os::fd::AsFd;
+
+    let (read, write) = nix::unistd::pipe().unwrap();
+    let mut msg = MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    msg.body.push_param(&write).unwrap();
+    msg.body.push_param(write.as_fd()).unwrap();
+    msg.body.push_param(std::fs::File::from(read)).unwrap();
+    assert_eq!(msg.get_sig(), "hhh");
+    assert_eq!(msg.body.raw_fds.len(), 3);
+    // the originals were dup'ed, the message has its own copies
+    drop(write);
+
+    let mut parser = msg.body.parser();
+    let mut owned_write: std::fs::File = parser.get().unwrap();
+    let _: UnixFd = parser.get().unwrap();
+    let owned_read: OwnedFd = parser.get().unwrap();
+    assert!(msg.body.raw_fds[0].get_raw_fd().is_none());
+    assert!(msg.body.raw_fds[1].get_raw_fd().is_some());
+    assert!(msg.body.raw_fds[2].get_raw_fd().is_none());
+
+    // the message does not close the taken fds
+    let mut parser = msg.body.parser();
+    assert_eq!(
+        parser.get::<OwnedFd>().unwrap_err(),
+        UnmarshalError::FdAlreadyTaken(0)
+    );
+    owned_write.write_all(b"owned").unwrap();
+    drop(owned_write);
+    // the second write end still lives in the message
+    nix::unistd::close(msg.body.raw_fds[1].clone().take_raw_fd().unwrap()).unwrap();
+    let mut buf = String::new();
+    std::fs::File::from(owned_read)
+        .read_to_string(&mut buf)
+        .unwrap();
+    assert_eq!(buf, "owned");
+}
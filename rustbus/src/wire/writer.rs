@@ -0,0 +1,233 @@
+//! Turn messages into the bytes that are written to a stream, without doing any io
+//!
+//! This is the sending counterpart of `MessageReassembler`. Together they contain everything a connection needs besides
+//! the socket itself, so a connection can be built over any kind of io, e.g. the `AsyncRead`/`AsyncWrite` of an async
+//! runtime. `SendConn` and `RecvConn` are the blocking connections built on top of them.
+//!
+//! ```rust
+//! use rustbus::message_builder::MessageBuilder;
+//! use rustbus::wire::{MessageReassembler, MessageWriter};
+//!
+//! let mut writer = MessageWriter::new();
+//! let mut reassembler = MessageReassembler::new();
+//! for arg in ["first", "second"] {
+//!     let mut msg = MessageBuilder::new()
+//!         .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+//!         .build();
+//!     msg.body.push_param(arg).unwrap();
+//!     writer.push(msg).unwrap();
+//! }
+//!
+//! // a stream that takes 10 bytes at a time
+//! let mut chunk = [0; 10];
+//! let mut fds = Vec::new();
+//! while !writer.is_empty() {
+//!     let len = writer.fill_outgoing(&mut chunk, &mut fds);
+//!     reassembler.push(&chunk[..len]);
+//! }
+//! let first = reassembler.next_message().unwrap().unwrap();
+//! assert_eq!(first.dynheader.serial, Some(1));
+//! let second = reassembler.next_message().unwrap().unwrap();
+//! assert_eq!(second.body.get_first::<&str>().unwrap(), "second");
+//! ```
+
+use crate::alloc_prelude::*;
+use alloc::collections::VecDeque;
+
+use crate::message_builder::MarshalledMessage;
+use crate::wire::errors::MarshalError;
+use crate::wire::marshal;
+use crate::wire::UnixFd;
+
+struct QueuedMessage {
+    header: Vec<u8>,
+    msg: MarshalledMessage,
+}
+
+impl QueuedMessage {
+    fn len(&self) -> usize {
+        self.header.len() + self.msg.get_buf().len()
+    }
+}
+
+/// Assigns serials to messages, marshals their headers and queues them until their bytes are taken with
+/// `fill_outgoing`.
+///
+/// Serials count up from 1 and wrap from `u32::MAX` back to 1, 0 is never handed out since it is not a valid serial.
+/// Messages that already have a serial in `dynheader.serial` keep it.
+pub struct MessageWriter {
+    pub(crate) serial_counter: u32,
+    queue: VecDeque<QueuedMessage>,
+    /// How many bytes of the first message in the queue were taken already
+    taken: usize,
+}
+
+impl Default for MessageWriter {
+    fn default() -> Self {
+        Self {
+            serial_counter: 1,
+            queue: VecDeque::new(),
+            taken: 0,
+        }
+    }
+}
+
+impl core::fmt::Debug for MessageWriter {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MessageWriter")
+            .field("serial_counter", &self.serial_counter)
+            .field("queued_messages", &self.queue.len())
+            .field("pending_bytes", &self.pending_bytes())
+            .finish()
+    }
+}
+
+impl MessageWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The serial the next message that has no serial yet will get. This does not allocate it.
+    pub fn peek_serial(&self) -> u32 {
+        self.serial_counter
+    }
+
+    /// Allocate a serial without queueing anything
+    pub fn next_serial(&mut self) -> u32 {
+        let serial = self.serial_counter;
+        self.serial_counter = serial.checked_add(1).unwrap_or(1);
+        serial
+    }
+
+    /// Append the header of `msg` to `buf` and return the serial of the message. A serial is only allocated if the
+    /// message has none and the header could be marshalled.
+    ///
+    /// This is for connections that write the body straight from the message instead of queueing it with `push`.
+    pub fn marshal_header(
+        &mut self,
+        msg: &MarshalledMessage,
+        buf: &mut Vec<u8>,
+    ) -> Result<u32, MarshalError> {
+        let serial = msg.dynheader.serial.unwrap_or(self.serial_counter);
+        marshal::marshal(msg, serial, buf)?;
+        if msg.dynheader.serial.is_none() {
+            self.next_serial();
+        }
+        Ok(serial)
+    }
+
+    /// Queue a message to be sent and return its serial
+    pub fn push(&mut self, msg: MarshalledMessage) -> Result<u32, MarshalError> {
+        let mut header = Vec::new();
+        let serial = self.marshal_header(&msg, &mut header)?;
+        self.queue.push_back(QueuedMessage { header, msg });
+        Ok(serial)
+    }
+
+    /// Whether all queued messages have been taken by `fill_outgoing`
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// How many bytes of the queued messages have not been taken yet
+    pub fn pending_bytes(&self) -> usize {
+        self.queue.iter().map(QueuedMessage::len).sum::<usize>() - self.taken
+    }
+
+    /// Copy as many pending bytes into `buf` as fit and return how many that were. Messages that are done are dropped
+    /// from the queue.
+    ///
+    /// The unix fds of every message that starts in this chunk are appended to `fds`. They have to be sent together
+    /// with the first byte of the chunk, so write the whole chunk (repeating short writes) before filling the next one.
+    pub fn fill_outgoing(&mut self, buf: &mut [u8], fds: &mut Vec<UnixFd>) -> usize {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let Some(queued) = self.queue.front() else {
+                break;
+            };
+            if self.taken == 0 {
+                fds.extend(queued.msg.body.raw_fds.iter().cloned());
+            }
+            let header_len = queued.header.len();
+            let src = if self.taken < header_len {
+                &queued.header[self.taken..]
+            } else {
+                &queued.msg.get_buf()[self.taken - header_len..]
+            };
+            let len = usize::min(src.len(), buf.len() - filled);
+            buf[filled..filled + len].copy_from_slice(&src[..len]);
+            filled += len;
+            self.taken += len;
+            if self.taken == queued.len() {
+                self.queue.pop_front();
+                self.taken = 0;
+            }
+        }
+        filled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::string_signal as signal;
+    use crate::wire::MessageReassembler;
+
+    #[test]
+    fn test_fill_outgoing() {
+        let args = ["first", "second with a longer body", "third"];
+        for chunk_size in [1, 7, 64, 1024] {
+            let mut writer = MessageWriter::new();
+            let mut serials = Vec::new();
+            for arg in args {
+                serials.push(writer.push(signal(arg)).unwrap());
+            }
+            let mut with_serial = signal("own serial");
+            with_serial.dynheader.serial = Some(100);
+            assert_eq!(writer.push(with_serial).unwrap(), 100);
+            assert_eq!(serials, [1, 2, 3]);
+            assert_eq!(writer.peek_serial(), 4);
+
+            let mut reassembler = MessageReassembler::new();
+            let mut chunk = vec![0; chunk_size];
+            let mut fds = Vec::new();
+            let total = writer.pending_bytes();
+            let mut received = 0;
+            while !writer.is_empty() {
+                let len = writer.fill_outgoing(&mut chunk, &mut fds);
+                assert!(len == chunk_size || writer.is_empty());
+                received += len;
+                reassembler.push(&chunk[..len]);
+            }
+            assert_eq!(received, total);
+            assert_eq!(writer.pending_bytes(), 0);
+            assert_eq!(writer.fill_outgoing(&mut chunk, &mut fds), 0);
+
+            for (serial, arg) in [
+                (1, args[0]),
+                (2, args[1]),
+                (3, args[2]),
+                (100, "own serial"),
+            ] {
+                let msg = reassembler.next_message().unwrap().unwrap();
+                assert_eq!(msg.dynheader.serial, Some(serial));
+                assert_eq!(msg.body.get_first::<&str>().unwrap(), arg);
+            }
+            assert!(reassembler.next_message().unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_failed_header_keeps_serial() {
+        let mut writer = MessageWriter::new();
+        let mut broken = signal("broken");
+        broken.dynheader.member = Some("Not.A.Member".into());
+        assert!(writer.push(broken).is_err());
+        assert!(writer.is_empty());
+        assert_eq!(writer.peek_serial(), 1);
+
+        writer.serial_counter = u32::MAX;
+        assert_eq!(writer.push(signal("last")).unwrap(), u32::MAX);
+        assert_eq!(writer.next_serial(), 1);
+    }
+}
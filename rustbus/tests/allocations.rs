@@ -0,0 +1,95 @@
+//! Checks that marshalling borrowed data does not allocate behind the users back.
+//! This is its own test binary so the counting allocator does not affect the other tests.
+
+use rustbus::message_builder::{MarshalledMessageBody, MessageBuilder};
+use rustbus::params::{Base, Param, Variant};
+use rustbus::wire::marshal::marshal_into_slice;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+/// Counts the allocations (including reallocations) of the current thread, so tests running in parallel
+/// do not disturb each other
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn count_allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn test_push_str_does_not_allocate() {
+    let arg = String::from("a string that is marshalled straight from the borrow");
+    let arg: &str = &arg;
+
+    let mut body = MarshalledMessageBody::with_capacity(128);
+    assert_eq!(count_allocations(|| body.push_param(arg).unwrap()), 0);
+    assert_eq!(body.parser().get::<&str>().unwrap(), arg);
+
+    let mut msg = MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    msg.body.reserve(128);
+    assert_eq!(count_allocations(|| msg.body.push_param(arg).unwrap()), 0);
+    assert_eq!(msg.get_sig(), "s");
+}
+
+#[test]
+fn test_variant_get_base_does_not_allocate() {
+    let var = Variant::new(Param::Base(Base::Uint32(42)));
+    let mut val = 0;
+    assert_eq!(count_allocations(|| val = var.get::<u32>().unwrap()), 0);
+    assert_eq!(val, 42);
+
+    let var = Variant::new(Param::Base(Base::String("no scratch buffer".to_owned())));
+    let mut s = "";
+    assert_eq!(count_allocations(|| s = var.get::<&str>().unwrap()), 0);
+    assert_eq!(s, "no scratch buffer");
+    // only the returned string itself is allocated
+    let mut owned = String::new();
+    assert_eq!(
+        count_allocations(|| owned = var.get::<String>().unwrap()),
+        1
+    );
+    assert_eq!(owned, "no scratch buffer");
+}
+
+#[test]
+fn test_marshal_into_slice_does_not_allocate() {
+    let mut msg = MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    msg.body
+        .push_param("written into a fixed size buffer")
+        .unwrap();
+
+    let mut out = [0u8; 256];
+    let mut len = 0;
+    assert_eq!(
+        count_allocations(|| len = marshal_into_slice(&msg, 1, &mut out).unwrap()),
+        0
+    );
+    assert!(len > msg.get_buf().len());
+}
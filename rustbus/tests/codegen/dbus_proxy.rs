@@ -0,0 +1,113 @@
+// Generated by rustbus::introspect::codegen, do not edit
+
+/// Calls the methods of the `org.freedesktop.DBus` interface
+pub struct DBusProxy<'a> {
+    conn: &'a mut ::rustbus::RpcConn,
+    destination: ::std::string::String,
+    path: ::std::string::String,
+}
+
+impl<'a> DBusProxy<'a> {
+    pub const INTERFACE: &'static str = "org.freedesktop.DBus";
+
+    /// Call the methods of the object at `path` of `destination`
+    pub fn new<D: ::std::convert::Into<::std::string::String>, P: ::std::convert::Into<::std::string::String>>(
+        conn: &'a mut ::rustbus::RpcConn,
+        destination: D,
+        path: P,
+    ) -> Self {
+        Self {
+            conn,
+            destination: destination.into(),
+            path: path.into(),
+        }
+    }
+
+    /// `Hello() -> (s)`
+    pub fn hello(&mut self) -> ::std::result::Result<::std::string::String, ::rustbus::connection::Error> {
+        self.conn.call_method(
+            self.destination.as_str(),
+            self.path.as_str(),
+            Self::INTERFACE,
+            "Hello",
+            (),
+        )
+    }
+
+    /// `NameHasOwner(s) -> (b)`
+    pub fn name_has_owner(&mut self, arg0: &str) -> ::std::result::Result<bool, ::rustbus::connection::Error> {
+        self.conn.call_method(
+            self.destination.as_str(),
+            self.path.as_str(),
+            Self::INTERFACE,
+            "NameHasOwner",
+            (arg0,),
+        )
+    }
+
+    /// `ListNames() -> (as)`
+    pub fn list_names(&mut self) -> ::std::result::Result<::std::vec::Vec<::std::string::String>, ::rustbus::connection::Error> {
+        self.conn.call_method(
+            self.destination.as_str(),
+            self.path.as_str(),
+            Self::INTERFACE,
+            "ListNames",
+            (),
+        )
+    }
+
+    /// `GetNameOwner(s) -> (s)`
+    pub fn get_name_owner(&mut self, arg0: &str) -> ::std::result::Result<::std::string::String, ::rustbus::connection::Error> {
+        self.conn.call_method(
+            self.destination.as_str(),
+            self.path.as_str(),
+            Self::INTERFACE,
+            "GetNameOwner",
+            (arg0,),
+        )
+    }
+
+    /// `GetConnectionUnixUser(s) -> (u)`
+    pub fn get_connection_unix_user(&mut self, arg0: &str) -> ::std::result::Result<u32, ::rustbus::connection::Error> {
+        self.conn.call_method(
+            self.destination.as_str(),
+            self.path.as_str(),
+            Self::INTERFACE,
+            "GetConnectionUnixUser",
+            (arg0,),
+        )
+    }
+
+    /// `GetConnectionCredentials(s) -> (a{sv})`
+    pub fn get_connection_credentials(&mut self, arg0: &str) -> ::std::result::Result<::std::collections::HashMap<::std::string::String, ::rustbus::params::Variant<'static, 'static>>, ::rustbus::connection::Error> {
+        self.conn.call_method(
+            self.destination.as_str(),
+            self.path.as_str(),
+            Self::INTERFACE,
+            "GetConnectionCredentials",
+            (arg0,),
+        )
+    }
+
+    /// `StartServiceByName(su) -> (u)`
+    pub fn start_service_by_name(&mut self, arg0: &str, arg1: u32) -> ::std::result::Result<u32, ::rustbus::connection::Error> {
+        self.conn.call_method(
+            self.destination.as_str(),
+            self.path.as_str(),
+            Self::INTERFACE,
+            "StartServiceByName",
+            (arg0, arg1),
+        )
+    }
+
+    /// `GetId() -> (s)`
+    pub fn get_id(&mut self) -> ::std::result::Result<::std::string::String, ::rustbus::connection::Error> {
+        self.conn.call_method(
+            self.destination.as_str(),
+            self.path.as_str(),
+            Self::INTERFACE,
+            "GetId",
+            (),
+        )
+    }
+}
@@ -0,0 +1,197 @@
+//! Property test for `testing::roundtrip` on randomly generated params of random signatures, and for the exact
+//! `marshalled_len` of these params
+
+use rustbus::params::{validation, Array, Base, Container, Dict, Param, Variant};
+use rustbus::signature;
+use rustbus::testing::{roundtrip, roundtrip_with_byteorder, RoundtripError};
+use rustbus::wire::errors::MarshalError;
+use rustbus::wire::marshal::container::{marshal_param, param_marshalled_len};
+use rustbus::wire::marshal::MarshalContext;
+use rustbus::ByteOrder;
+
+/// xorshift64, so failures can be reproduced from the seed
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+const BASE_TYPES: &[signature::Base] = &[
+    signature::Base::Byte,
+    signature::Base::Boolean,
+    signature::Base::Int16,
+    signature::Base::Uint16,
+    signature::Base::Int32,
+    signature::Base::Uint32,
+    signature::Base::Int64,
+    signature::Base::Uint64,
+    signature::Base::Double,
+    signature::Base::String,
+    signature::Base::ObjectPath,
+    signature::Base::Signature,
+];
+
+fn gen_base_type(rng: &mut Rng) -> signature::Base {
+    BASE_TYPES[rng.below(BASE_TYPES.len() as u64) as usize]
+}
+
+fn gen_type(rng: &mut Rng, depth: usize) -> signature::Type {
+    if depth == 0 || rng.below(3) == 0 {
+        return signature::Type::Base(gen_base_type(rng));
+    }
+    let container = match rng.below(4) {
+        0 => signature::Container::Array(Box::new(gen_type(rng, depth - 1))),
+        1 => signature::Container::Dict(gen_base_type(rng), Box::new(gen_type(rng, depth - 1))),
+        2 => {
+            let fields = (0..=rng.below(3))
+                .map(|_| gen_type(rng, depth - 1))
+                .collect();
+            signature::Container::Struct(signature::StructTypes::new(fields).unwrap())
+        }
+        _ => signature::Container::Variant,
+    };
+    signature::Type::Container(container)
+}
+
+fn gen_string(rng: &mut Rng) -> String {
+    const CHARS: &[char] = &['a', 'Z', '0', ' ', '/', '{', 'ä', '€', '\n'];
+    (0..rng.below(8))
+        .map(|_| CHARS[rng.below(CHARS.len() as u64) as usize])
+        .collect()
+}
+
+fn gen_base(rng: &mut Rng, typ: signature::Base) -> Base<'static> {
+    match typ {
+        signature::Base::Byte => Base::Byte(rng.next() as u8),
+        signature::Base::Boolean => Base::Boolean(rng.below(2) == 0),
+        signature::Base::Int16 => Base::Int16(rng.next() as i16),
+        signature::Base::Uint16 => Base::Uint16(rng.next() as u16),
+        signature::Base::Int32 => Base::Int32(rng.next() as i32),
+        signature::Base::Uint32 => Base::Uint32(rng.next() as u32),
+        signature::Base::Int64 => Base::Int64(rng.next() as i64),
+        signature::Base::Uint64 => Base::Uint64(rng.next()),
+        // any bit pattern, including NaNs, has to survive
+        signature::Base::Double => Base::Double(rng.next()),
+        signature::Base::String => Base::String(gen_string(rng)),
+        signature::Base::ObjectPath => {
+            let path = (0..rng.below(3))
+                .map(|idx| format!("/e{}_{}", idx, rng.below(100)))
+                .collect::<String>();
+            Base::ObjectPath(if path.is_empty() {
+                "/".to_owned()
+            } else {
+                path
+            })
+        }
+        signature::Base::Signature => Base::Signature(gen_type(rng, 2).to_string()),
+        signature::Base::UnixFd => unreachable!("Unix fds do not compare equal after a roundtrip"),
+    }
+}
+
+fn gen_param(rng: &mut Rng, typ: &signature::Type, depth: usize) -> Param<'static, 'static> {
+    match typ {
+        signature::Type::Base(base) => Param::Base(gen_base(rng, *base)),
+        signature::Type::Container(signature::Container::Array(elem)) => {
+            Param::Container(Container::Array(Array {
+                element_sig: elem.as_ref().clone(),
+                values: (0..rng.below(4))
+                    .map(|_| gen_param(rng, elem, depth))
+                    .collect(),
+            }))
+        }
+        signature::Type::Container(signature::Container::Dict(key, value)) => {
+            let map = (0..rng.below(4))
+                .map(|_| (gen_base(rng, *key), gen_param(rng, value, depth)))
+                .collect();
+            Param::Container(Container::Dict(Dict {
+                key_sig: *key,
+                value_sig: value.as_ref().clone(),
+                map,
+            }))
+        }
+        signature::Type::Container(signature::Container::Struct(fields)) => {
+            Param::Container(Container::Struct(
+                fields
+                    .as_ref()
+                    .iter()
+                    .map(|field| gen_param(rng, field, depth))
+                    .collect(),
+            ))
+        }
+        signature::Type::Container(signature::Container::Variant) => {
+            let inner = gen_type(rng, depth.saturating_sub(1));
+            let value = gen_param(rng, &inner, depth.saturating_sub(1));
+            Param::Container(Container::Variant(Box::new(Variant::new(value))))
+        }
+    }
+}
+
+#[test]
+fn roundtrip_random_params() {
+    for seed in 1..=2000u64 {
+        let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let typ = gen_type(&mut rng, 4);
+        let param = gen_param(&mut rng, &typ, 4);
+        assert_eq!(param.sig(), typ);
+        for byteorder in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let result = roundtrip_with_byteorder(&param, byteorder);
+            assert_eq!(
+                result.as_ref(),
+                Ok(&param),
+                "seed {} with signature {} in {:?}",
+                seed,
+                typ,
+                byteorder
+            );
+        }
+    }
+}
+
+#[test]
+fn marshalled_len_of_random_params() {
+    for seed in 1..=500u64 {
+        let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let typ = gen_type(&mut rng, 4);
+        let param = gen_param(&mut rng, &typ, 4);
+        for offset in 0..8 {
+            let mut buf = vec![0; offset];
+            let mut fds = Vec::new();
+            let mut ctx = MarshalContext {
+                buf: &mut buf,
+                fds: &mut fds,
+                byteorder: ByteOrder::LittleEndian,
+            };
+            marshal_param(&param, &mut ctx).unwrap();
+            assert_eq!(
+                param_marshalled_len(&param, offset),
+                buf.len() - offset,
+                "seed {} with signature {} at offset {}",
+                seed,
+                typ,
+                offset
+            );
+        }
+    }
+}
+
+#[test]
+fn roundtrip_reports_marshal_errors() {
+    let inconsistent = Param::Container(Container::Array(Array {
+        element_sig: signature::Type::Base(signature::Base::Uint32),
+        values: vec![Param::Base(Base::StringRef("not a u32"))],
+    }));
+    assert!(matches!(
+        roundtrip(&inconsistent),
+        Err(RoundtripError::Marshal(MarshalError::Validation(
+            validation::Error::ArrayElementTypesDiffer
+        )))
+    ));
+}
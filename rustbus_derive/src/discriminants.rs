@@ -0,0 +1,239 @@
+//! Derives for enums marked with `#[rustbus(discriminant)]`.
+//!
+//! These enums are still marshalled as a variant but the content is a u32 discriminant followed by the fields.
+//! Unit variants have the signature `u`, variants with fields the signature `(u...)`.
+
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote, ToTokens};
+use syn::{punctuated::Punctuated, token::Comma, Variant};
+
+/// Discriminants follow the same rules as in rust: explicit values are used as is, all others count up from the
+/// previous one, starting at 0.
+fn discriminants(variants: &Punctuated<Variant, Comma>) -> Vec<Literal> {
+    let mut next = 0u32;
+    variants
+        .iter()
+        .map(|variant| {
+            if let Some((_, expr)) = &variant.discriminant {
+                next = match expr {
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Int(lit),
+                        ..
+                    }) => lit
+                        .base10_parse()
+                        .expect("Enum discriminants must fit into a u32"),
+                    _ => panic!("Enum discriminants must be integer literals"),
+                };
+            }
+            let discriminant = next;
+            next = next.wrapping_add(1);
+            Literal::u32_suffixed(discriminant)
+        })
+        .collect()
+}
+
+fn field_names(variant: &Variant) -> Vec<TokenStream> {
+    variant
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| match &field.ident {
+            Some(ident) => ident.to_token_stream(),
+            None => syn::Ident::new(&format!("v{}", idx), proc_macro2::Span::call_site())
+                .to_token_stream(),
+        })
+        .collect()
+}
+
+pub fn make_discriminant_marshal_impl(
+    ident: &syn::Ident,
+    generics: &syn::Generics,
+    variants: &Punctuated<Variant, Comma>,
+) -> TokenStream {
+    let (impl_gen, typ_gen, clause_gen) = generics.split_for_impl();
+    let marshal = variants
+        .iter()
+        .zip(discriminants(variants))
+        .map(|(variant, discriminant)| variant_marshal(variant, discriminant));
+
+    quote! {
+        impl #impl_gen ::rustbus::Marshal for #ident #typ_gen #clause_gen {
+            #[inline]
+            fn marshal(&self, ctx: &mut ::rustbus::wire::marshal::MarshalContext<'_,'_>) -> Result<(), ::rustbus::wire::errors::MarshalError> {
+                match self {
+                    #( #marshal )*
+                }
+            }
+        }
+    }
+}
+
+fn variant_marshal(variant: &Variant, discriminant: Literal) -> TokenStream {
+    let name = &variant.ident;
+    let field_names = field_names(variant);
+    let field_types = variant
+        .fields
+        .iter()
+        .map(|field| field.ty.to_token_stream());
+
+    let pattern = match &variant.fields {
+        syn::Fields::Unit => quote! { Self::#name },
+        syn::Fields::Named(_) => quote! { Self::#name{ #( #field_names, )* } },
+        syn::Fields::Unnamed(_) => quote! { Self::#name( #( #field_names, )* ) },
+    };
+
+    if variant.fields.is_empty() {
+        quote! {
+            #pattern => {
+                ::rustbus::wire::util::write_signature("u", ctx.buf)?;
+                ::rustbus::Marshal::marshal(&#discriminant, ctx)
+            },
+        }
+    } else {
+        quote! {
+            #pattern => {
+                let mut sig = "(u".to_owned();
+                let mut sig_str = ::rustbus::wire::marshal::traits::SignatureBuffer::new();
+                #(
+                    sig_str.clear();
+                    <#field_types as ::rustbus::Signature>::sig_str(&mut sig_str);
+                    sig.push_str(sig_str.as_ref());
+                )*
+                sig.push(')');
+                ::rustbus::wire::util::write_signature(&sig, ctx.buf)?;
+
+                // align to 8 because we treat this as a struct
+                ctx.align_to(8);
+                ::rustbus::Marshal::marshal(&#discriminant, ctx)?;
+                #(
+                    ::rustbus::Marshal::marshal(#field_names, ctx)?;
+                )*
+                Ok(())
+            },
+        }
+    }
+}
+
+pub fn make_discriminant_unmarshal_impl(
+    ident: &syn::Ident,
+    generics: &syn::Generics,
+    variants: &Punctuated<Variant, Comma>,
+) -> TokenStream {
+    let discriminants = discriminants(variants);
+
+    let unit_variants = variants
+        .iter()
+        .zip(discriminants.iter())
+        .filter(|(variant, _)| variant.fields.is_empty())
+        .map(|(variant, discriminant)| {
+            let name = &variant.ident;
+            quote! {
+                if discriminant == #discriminant {
+                    return Ok((ctx.offset - start_offset, Self::#name));
+                }
+            }
+        });
+
+    let field_variants = variants
+        .iter()
+        .zip(discriminants.iter())
+        .filter(|(variant, _)| !variant.fields.is_empty())
+        .map(|(variant, discriminant)| variant_unmarshal(variant, discriminant));
+
+    let mut bufdef = syn::LifetimeParam {
+        attrs: Vec::new(),
+        lifetime: syn::Lifetime::new("'__internal_buf", proc_macro2::Span::call_site()),
+        colon_token: None,
+        bounds: syn::punctuated::Punctuated::new(),
+    };
+
+    let mut new_generics = generics.clone();
+    for lt in new_generics.lifetimes_mut() {
+        bufdef.bounds.push(lt.lifetime.clone());
+        lt.bounds.push(bufdef.lifetime.clone());
+    }
+
+    let typ_generics = new_generics.clone();
+    let (_, typ_gen, _) = typ_generics.split_for_impl();
+
+    new_generics
+        .params
+        .insert(0, syn::GenericParam::Lifetime(bufdef));
+
+    let (impl_gen, _, clause_gen) = new_generics.split_for_impl();
+
+    quote! {
+        impl #impl_gen ::rustbus::Unmarshal<'__internal_buf, '_> for #ident #typ_gen #clause_gen {
+            #[inline]
+            fn unmarshal(ctx: &mut ::rustbus::wire::unmarshal::UnmarshalContext<'_,'__internal_buf>) -> Result<(usize,Self), ::rustbus::wire::errors::UnmarshalError> {
+                let start_offset = ctx.offset;
+                let (sig_bytes, sig) = ::rustbus::wire::util::unmarshal_signature(&ctx.buf[ctx.offset..])?;
+                ctx.offset += sig_bytes;
+
+                if sig == "u" {
+                    let (_, discriminant) = <u32 as ::rustbus::Unmarshal>::unmarshal(ctx)?;
+                    #( #unit_variants )*
+                    return Err(::rustbus::wire::errors::UnmarshalError::UnknownDiscriminant(discriminant));
+                }
+                if !sig.starts_with("(u") {
+                    return Err(::rustbus::wire::errors::UnmarshalError::NoDiscriminant {
+                        signature: sig.into(),
+                    });
+                }
+
+                ctx.align_to(8)?;
+                let (_, discriminant) = <u32 as ::rustbus::Unmarshal>::unmarshal(ctx)?;
+                #( #field_variants )*
+                Err(::rustbus::wire::errors::UnmarshalError::UnknownDiscriminant(discriminant))
+            }
+        }
+    }
+}
+
+fn variant_unmarshal(variant: &Variant, discriminant: &Literal) -> TokenStream {
+    let name = &variant.ident;
+    let field_names = field_names(variant);
+    let field_types1 = variant
+        .fields
+        .iter()
+        .map(|field| field.ty.to_token_stream());
+    let field_types2 = field_types1.clone();
+
+    let construct = match &variant.fields {
+        syn::Fields::Named(_) => quote! {
+            Self::#name{
+                #(
+                    #field_names: <#field_types2 as ::rustbus::Unmarshal>::unmarshal(ctx)?.1,
+                )*
+            }
+        },
+        _ => quote! {
+            Self::#name(
+                #(
+                    <#field_types2 as ::rustbus::Unmarshal>::unmarshal(ctx)?.1,
+                )*
+            )
+        },
+    };
+
+    quote! {
+        if discriminant == #discriminant {
+            let mut expected_sig = "(u".to_owned();
+            let mut sig_str = ::rustbus::wire::marshal::traits::SignatureBuffer::new();
+            #(
+                sig_str.clear();
+                <#field_types1 as ::rustbus::Signature>::sig_str(&mut sig_str);
+                expected_sig.push_str(sig_str.as_ref());
+            )*
+            expected_sig.push(')');
+            if !sig.eq(&expected_sig) {
+                return Err(::rustbus::wire::errors::UnmarshalError::WrongSignature {
+                    expected: expected_sig,
+                    actual: sig.into(),
+                });
+            }
+            let this = #construct;
+            return Ok((ctx.offset - start_offset, this));
+        }
+    }
+}
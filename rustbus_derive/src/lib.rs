@@ -1,7 +1,39 @@
+mod discriminants;
+mod string_enums;
 mod structs;
 mod variants;
 
-#[proc_macro_derive(Marshal)]
+/// How an enum is marshalled, selected by an attribute on the enum
+#[derive(PartialEq, Eq)]
+enum EnumKind {
+    /// No attribute, each variant is marshalled as a variant with its fields
+    Variant,
+    /// `#[rustbus(discriminant)]`
+    Discriminant,
+    /// `#[rustbus(string_enum)]`
+    StringEnum,
+}
+
+fn enum_kind(attrs: &[syn::Attribute]) -> EnumKind {
+    let mut kind = EnumKind::Variant;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("rustbus")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("discriminant") {
+                kind = EnumKind::Discriminant;
+                Ok(())
+            } else if meta.path.is_ident("string_enum") {
+                kind = EnumKind::StringEnum;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported rustbus attribute"))
+            }
+        })
+        .unwrap();
+    }
+    kind
+}
+
+#[proc_macro_derive(Marshal, attributes(rustbus))]
 pub fn derive_marshal(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast: syn::DeriveInput = syn::parse(input).unwrap();
 
@@ -9,13 +41,21 @@ pub fn derive_marshal(input: proc_macro::TokenStream) -> proc_macro::TokenStream
         syn::Data::Struct(data) => {
             structs::make_struct_marshal_impl(&ast.ident, &ast.generics, &data.fields).into()
         }
+        syn::Data::Enum(data) if enum_kind(&ast.attrs) == EnumKind::StringEnum => {
+            string_enums::make_string_enum_marshal_impl(&ast.ident, &ast.generics, &data.variants)
+                .into()
+        }
+        syn::Data::Enum(data) if enum_kind(&ast.attrs) == EnumKind::Discriminant => {
+            discriminants::make_discriminant_marshal_impl(&ast.ident, &ast.generics, &data.variants)
+                .into()
+        }
         syn::Data::Enum(data) => {
             variants::make_variant_marshal_impl(&ast.ident, &ast.generics, &data.variants).into()
         }
         _ => unimplemented!("Nothing but structs can be derived on right now"),
     }
 }
-#[proc_macro_derive(Unmarshal)]
+#[proc_macro_derive(Unmarshal, attributes(rustbus))]
 pub fn derive_unmarshal(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast: syn::DeriveInput = syn::parse(input).unwrap();
 
@@ -23,13 +63,25 @@ pub fn derive_unmarshal(input: proc_macro::TokenStream) -> proc_macro::TokenStre
         syn::Data::Struct(data) => {
             structs::make_struct_unmarshal_impl(&ast.ident, &ast.generics, &data.fields).into()
         }
+        syn::Data::Enum(data) if enum_kind(&ast.attrs) == EnumKind::StringEnum => {
+            string_enums::make_string_enum_unmarshal_impl(&ast.ident, &ast.generics, &data.variants)
+                .into()
+        }
+        syn::Data::Enum(data) if enum_kind(&ast.attrs) == EnumKind::Discriminant => {
+            discriminants::make_discriminant_unmarshal_impl(
+                &ast.ident,
+                &ast.generics,
+                &data.variants,
+            )
+            .into()
+        }
         syn::Data::Enum(data) => {
             variants::make_variant_unmarshal_impl(&ast.ident, &ast.generics, &data.variants).into()
         }
         _ => unimplemented!("Nothing but structs can be derived on right now"),
     }
 }
-#[proc_macro_derive(Signature)]
+#[proc_macro_derive(Signature, attributes(rustbus))]
 pub fn derive_signature(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast: syn::DeriveInput = syn::parse(input).unwrap();
 
@@ -37,6 +89,9 @@ pub fn derive_signature(input: proc_macro::TokenStream) -> proc_macro::TokenStre
         syn::Data::Struct(data) => {
             structs::make_struct_signature_impl(&ast.ident, &ast.generics, &data.fields).into()
         }
+        syn::Data::Enum(_data) if enum_kind(&ast.attrs) == EnumKind::StringEnum => {
+            string_enums::make_string_enum_signature_impl(&ast.ident, &ast.generics).into()
+        }
         syn::Data::Enum(_data) => {
             variants::make_variant_signature_imp(&ast.ident, &ast.generics).into()
         }
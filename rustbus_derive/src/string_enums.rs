@@ -0,0 +1,176 @@
+//! Derives for enums marked with `#[rustbus(string_enum)]`.
+//!
+//! These enums are marshalled as a plain string (signature `s`) holding the name of the variant. The name can be
+//! changed with `#[rustbus(rename = "...")]` on the variant. One variant with a single field can be marked with
+//! `#[rustbus(other)]`, it is used for all strings that do not match any other variant and holds the string as is.
+//! The other variant is marshalled as its string without checking it, so if it holds the name of a named variant it
+//! is unmarshalled as that named variant and does not roundtrip.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{punctuated::Punctuated, token::Comma, Variant};
+
+enum VariantKind {
+    Named(String),
+    Other,
+}
+
+fn variant_kind(variant: &Variant) -> VariantKind {
+    let mut name = variant.ident.to_string();
+    let mut other = false;
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("rustbus"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                name = meta.value()?.parse::<syn::LitStr>()?.value();
+                Ok(())
+            } else if meta.path.is_ident("other") {
+                other = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported rustbus attribute"))
+            }
+        })
+        .unwrap();
+    }
+
+    if other {
+        match &variant.fields {
+            syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => VariantKind::Other,
+            _ => panic!("The other variant of a string enum must have exactly one unnamed field"),
+        }
+    } else {
+        if !variant.fields.is_empty() {
+            panic!("String enums can only have unit variants and one other variant");
+        }
+        VariantKind::Named(name)
+    }
+}
+
+fn variant_kinds(variants: &Punctuated<Variant, Comma>) -> Vec<(&syn::Ident, VariantKind)> {
+    let kinds: Vec<_> = variants
+        .iter()
+        .map(|variant| (&variant.ident, variant_kind(variant)))
+        .collect();
+    let others = kinds
+        .iter()
+        .filter(|(_, kind)| matches!(kind, VariantKind::Other))
+        .count();
+    if others > 1 {
+        panic!("String enums can have at most one other variant");
+    }
+    kinds
+}
+
+pub fn make_string_enum_signature_impl(
+    ident: &syn::Ident,
+    generics: &syn::Generics,
+) -> TokenStream {
+    let (impl_gen, typ_gen, clause_gen) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_gen ::rustbus::Signature for #ident #typ_gen #clause_gen {
+            #[inline]
+            fn signature() -> ::rustbus::signature::Type {
+                <&str as ::rustbus::Signature>::signature()
+            }
+            fn alignment() -> usize {
+                <&str as ::rustbus::Signature>::alignment()
+            }
+            fn has_sig(sig: &str) -> bool {
+                <&str as ::rustbus::Signature>::has_sig(sig)
+            }
+        }
+    }
+}
+
+pub fn make_string_enum_marshal_impl(
+    ident: &syn::Ident,
+    generics: &syn::Generics,
+    variants: &Punctuated<Variant, Comma>,
+) -> TokenStream {
+    let (impl_gen, typ_gen, clause_gen) = generics.split_for_impl();
+    let marshal = variant_kinds(variants)
+        .into_iter()
+        .map(|(name, kind)| match kind {
+            VariantKind::Named(value) => quote! {
+                Self::#name => ::rustbus::Marshal::marshal(&#value, ctx),
+            },
+            VariantKind::Other => quote! {
+                Self::#name(value) => {
+                    ::rustbus::Marshal::marshal(&::core::convert::AsRef::<str>::as_ref(value), ctx)
+                }
+            },
+        });
+
+    quote! {
+        impl #impl_gen ::rustbus::Marshal for #ident #typ_gen #clause_gen {
+            #[inline]
+            fn marshal(&self, ctx: &mut ::rustbus::wire::marshal::MarshalContext<'_,'_>) -> Result<(), ::rustbus::wire::errors::MarshalError> {
+                match self {
+                    #( #marshal )*
+                }
+            }
+        }
+    }
+}
+
+pub fn make_string_enum_unmarshal_impl(
+    ident: &syn::Ident,
+    generics: &syn::Generics,
+    variants: &Punctuated<Variant, Comma>,
+) -> TokenStream {
+    let kinds = variant_kinds(variants);
+    let named = kinds.iter().filter_map(|(name, kind)| match kind {
+        VariantKind::Named(value) => Some(quote! { #value => Self::#name, }),
+        VariantKind::Other => None,
+    });
+    let fallback = match kinds
+        .iter()
+        .find(|(_, kind)| matches!(kind, VariantKind::Other))
+    {
+        Some((name, _)) => quote! { _ => Self::#name(::core::convert::Into::into(value)), },
+        None => quote! {
+            _ => return Err(::rustbus::wire::errors::UnmarshalError::UnknownVariant(value.to_owned())),
+        },
+    };
+
+    let mut bufdef = syn::LifetimeParam {
+        attrs: Vec::new(),
+        lifetime: syn::Lifetime::new("'__internal_buf", proc_macro2::Span::call_site()),
+        colon_token: None,
+        bounds: syn::punctuated::Punctuated::new(),
+    };
+
+    let mut new_generics = generics.clone();
+    for lt in new_generics.lifetimes_mut() {
+        bufdef.bounds.push(lt.lifetime.clone());
+        lt.bounds.push(bufdef.lifetime.clone());
+    }
+
+    let typ_generics = new_generics.clone();
+    let (_, typ_gen, _) = typ_generics.split_for_impl();
+
+    new_generics
+        .params
+        .insert(0, syn::GenericParam::Lifetime(bufdef));
+
+    let (impl_gen, _, clause_gen) = new_generics.split_for_impl();
+
+    quote! {
+        impl #impl_gen ::rustbus::Unmarshal<'__internal_buf, '_> for #ident #typ_gen #clause_gen {
+            #[inline]
+            fn unmarshal(ctx: &mut ::rustbus::wire::unmarshal::UnmarshalContext<'_,'__internal_buf>) -> Result<(usize,Self), ::rustbus::wire::errors::UnmarshalError> {
+                let (bytes, value) = <&'__internal_buf str as ::rustbus::Unmarshal>::unmarshal(ctx)?;
+                let this = match value {
+                    #( #named )*
+                    #fallback
+                };
+                Ok((bytes, this))
+            }
+        }
+    }
+}
@@ -8,6 +8,8 @@ pub fn make_struct_marshal_impl(
 ) -> TokenStream {
     let (impl_gen, typ_gen, clause_gen) = generics.split_for_impl();
     let marshal = struct_field_marshal(fields);
+    let estimate_size = struct_field_estimate_size(fields);
+    let marshalled_len = struct_field_marshalled_len(fields);
 
     quote! {
         impl #impl_gen ::rustbus::Marshal for #ident #typ_gen #clause_gen {
@@ -15,6 +17,14 @@ pub fn make_struct_marshal_impl(
             fn marshal(&self, ctx: &mut ::rustbus::wire::marshal::MarshalContext<'_,'_>) -> Result<(), ::rustbus::wire::errors::MarshalError> {
                 #marshal
             }
+            #[inline]
+            fn estimate_size(&self) -> usize {
+                #estimate_size
+            }
+            #[inline]
+            fn marshalled_len_at(&self, offset: usize) -> usize {
+                #marshalled_len
+            }
         }
     }
 }
@@ -94,6 +104,30 @@ fn struct_field_marshal(fields: &syn::Fields) -> TokenStream {
             Ok(())
     }
 }
+fn struct_field_estimate_size(fields: &syn::Fields) -> TokenStream {
+    let field_names = fields
+        .iter()
+        .map(|field| field.ident.as_ref().unwrap().to_token_stream());
+
+    quote! {
+            0 #(
+                + ::rustbus::Marshal::estimate_size(&self.#field_names)
+            )*
+    }
+}
+fn struct_field_marshalled_len(fields: &syn::Fields) -> TokenStream {
+    let field_names = fields
+        .iter()
+        .map(|field| field.ident.as_ref().unwrap().to_token_stream());
+
+    quote! {
+            let mut pos = offset + ::rustbus::wire::util::padding_for(8, offset);
+            #(
+                pos += ::rustbus::Marshal::marshalled_len_at(&self.#field_names, pos);
+            )*
+            pos - offset
+    }
+}
 fn struct_field_unmarshal(fields: &syn::Fields) -> TokenStream {
     let field_names = fields
         .iter()
@@ -136,7 +136,7 @@ fn variant_marshal(enum_name: syn::Ident, variant: &syn::Variant) -> TokenStream
                 #enum_name::#name( val ) => {
                     let mut sig_str = ::rustbus::wire::marshal::traits::SignatureBuffer::new();
                     <#ty as ::rustbus::Signature>::sig_str(&mut sig_str);
-                    ::rustbus::wire::util::write_signature(sig_str.as_ref(), &mut ctx.buf);
+                    ::rustbus::wire::util::write_signature(sig_str.as_ref(), &mut ctx.buf)?;
 
                     val.marshal(ctx)?;
                     Ok(())
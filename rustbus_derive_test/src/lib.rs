@@ -57,6 +57,9 @@ fn test_derive() {
         .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
         .build();
 
+    // the derived estimate is the sum of the fields
+    assert_eq!(rustbus::Marshal::estimate_size(&a), 4 + 8 + 11 + 19 + 22);
+
     // add a parameter to the signal
     sig.body.push_param(&a).unwrap();
 
@@ -163,3 +166,154 @@ pub fn test_enum_derive() {
         err
     );
 }
+
+#[test]
+pub fn test_enum_discriminant_derive() {
+    use rustbus::wire::errors::UnmarshalError;
+    use rustbus::wire::unmarshal::traits::Variant;
+    use rustbus::MessageBuilder;
+    use rustbus_derive::{Marshal, Signature, Unmarshal};
+
+    #[derive(Marshal, Unmarshal, Signature, PartialEq, Eq, Debug)]
+    #[rustbus(discriminant)]
+    #[repr(u32)]
+    enum State<'a> {
+        Idle,
+        Running(u64),
+        Failed { code: i32, reason: &'a str },
+        Stopped = 10,
+        Paused,
+    }
+
+    #[derive(Marshal, Unmarshal, Signature, PartialEq, Eq, Debug)]
+    #[rustbus(discriminant)]
+    enum Other {
+        Zero,
+    }
+
+    let mut sig = MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    sig.body.push_param(State::Idle).unwrap();
+    sig.body.push_param(State::Running(100)).unwrap();
+    sig.body
+        .push_param(State::Failed {
+            code: -1,
+            reason: "ABCD",
+        })
+        .unwrap();
+    sig.body.push_param(State::Stopped).unwrap();
+    sig.body.push_param(State::Paused).unwrap();
+    assert_eq!(sig.get_sig(), "vvvvv");
+
+    // the variants contain the discriminant and the fields
+    let mut parser = sig.body.parser();
+    let v = parser.get::<Variant>().unwrap();
+    assert_eq!(v.get_value_sig().to_string(), "u");
+    assert_eq!(v.get::<u32>().unwrap(), 0);
+    let v = parser.get::<Variant>().unwrap();
+    assert_eq!(v.get_value_sig().to_string(), "(ut)");
+    assert_eq!(v.get::<(u32, u64)>().unwrap(), (1, 100));
+    let v = parser.get::<Variant>().unwrap();
+    assert_eq!(v.get::<(u32, i32, &str)>().unwrap(), (2, -1, "ABCD"));
+    assert_eq!(parser.get::<Variant>().unwrap().get::<u32>().unwrap(), 10);
+    assert_eq!(parser.get::<Variant>().unwrap().get::<u32>().unwrap(), 11);
+
+    let mut parser = sig.body.parser();
+    assert_eq!(parser.get::<State>().unwrap(), State::Idle);
+    assert_eq!(parser.get::<State>().unwrap(), State::Running(100));
+    assert_eq!(
+        parser.get::<State>().unwrap(),
+        State::Failed {
+            code: -1,
+            reason: "ABCD"
+        }
+    );
+    assert_eq!(parser.get::<State>().unwrap(), State::Stopped);
+    assert_eq!(parser.get::<State>().unwrap(), State::Paused);
+
+    // unknown discriminants are rejected
+    let mut parser = sig.body.parser();
+    assert_eq!(parser.get::<Other>().unwrap(), Other::Zero);
+    assert_eq!(
+        parser.get::<Other>(),
+        Err(UnmarshalError::UnknownDiscriminant(1))
+    );
+
+    // variants that hold no discriminant at all are rejected as well
+    let mut sig = MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    sig.body
+        .push_param(rustbus::wire::marshal::traits::Variant("ABCD"))
+        .unwrap();
+    assert_eq!(
+        sig.body.parser().get::<State>(),
+        Err(UnmarshalError::NoDiscriminant {
+            signature: "s".to_owned()
+        })
+    );
+}
+
+#[test]
+pub fn test_string_enum_derive() {
+    use rustbus::wire::errors::UnmarshalError;
+    use rustbus::MessageBuilder;
+    use rustbus_derive::{Marshal, Signature, Unmarshal};
+
+    #[derive(Marshal, Unmarshal, Signature, PartialEq, Eq, Debug)]
+    #[rustbus(string_enum)]
+    enum Mode {
+        Idle,
+        #[rustbus(rename = "power-saver")]
+        PowerSaver,
+    }
+
+    #[derive(Marshal, Unmarshal, Signature, PartialEq, Eq, Debug)]
+    #[rustbus(string_enum)]
+    enum Profile<'a> {
+        Performance,
+        #[rustbus(other)]
+        Other(&'a str),
+    }
+
+    let mut sig = MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    sig.body.push_param(Mode::Idle).unwrap();
+    sig.body.push_param(Mode::PowerSaver).unwrap();
+    sig.body.push_param(Profile::Other("balanced")).unwrap();
+    sig.body.push_param(Profile::Performance).unwrap();
+    assert_eq!(sig.get_sig(), "ssss");
+
+    let mut parser = sig.body.parser();
+    assert_eq!(parser.get::<&str>().unwrap(), "Idle");
+    assert_eq!(parser.get::<&str>().unwrap(), "power-saver");
+    assert_eq!(parser.get::<&str>().unwrap(), "balanced");
+    assert_eq!(parser.get::<&str>().unwrap(), "Performance");
+
+    let mut parser = sig.body.parser();
+    assert_eq!(parser.get::<Mode>().unwrap(), Mode::Idle);
+    assert_eq!(parser.get::<Mode>().unwrap(), Mode::PowerSaver);
+    assert_eq!(
+        parser.get::<Mode>(),
+        Err(UnmarshalError::UnknownVariant("balanced".to_owned()))
+    );
+
+    let mut parser = sig.body.parser();
+    assert_eq!(parser.get::<Profile>().unwrap(), Profile::Other("Idle"));
+    assert_eq!(
+        parser.get::<Profile>().unwrap(),
+        Profile::Other("power-saver")
+    );
+    assert_eq!(parser.get::<Profile>().unwrap(), Profile::Other("balanced"));
+    assert_eq!(parser.get::<Profile>().unwrap(), Profile::Performance);
+
+    // an other variant holding the name of a named variant is unmarshalled as the named variant
+    let mut sig = MessageBuilder::new()
+        .signal("io.killing.spark", "TestSignal", "/io/killing/spark")
+        .build();
+    sig.body.push_param(Profile::Other("Performance")).unwrap();
+    let mut parser = sig.body.parser();
+    assert_eq!(parser.get::<Profile>().unwrap(), Profile::Performance);
+}